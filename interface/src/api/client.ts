@@ -31,6 +31,7 @@ export interface InboundMessageEvent {
 	sender_name?: string | null;
 	sender_id: string;
 	text: string;
+	turn_id: string | null;
 }
 
 export interface OutboundMessageEvent {
@@ -38,6 +39,7 @@ export interface OutboundMessageEvent {
 	agent_id: string;
 	channel_id: string;
 	text: string;
+	turn_id: string | null;
 }
 
 export interface TypingStateEvent {
@@ -94,6 +96,7 @@ export interface ToolStartedEvent {
 	process_type: ProcessType;
 	process_id: string;
 	tool_name: string;
+	turn_id: string | null;
 }
 
 export interface ToolCompletedEvent {
@@ -103,6 +106,48 @@ export interface ToolCompletedEvent {
 	process_type: ProcessType;
 	process_id: string;
 	tool_name: string;
+	turn_id: string | null;
+}
+
+export type ToolLoopStep =
+	| {
+			kind: "response";
+			iteration: number;
+			text: string | null;
+			tool_calls: string[];
+			elapsed_ms: number;
+	  }
+	| {
+			kind: "tool_call";
+			iteration: number;
+			tool_name: string;
+			args: string;
+			elapsed_ms: number;
+	  }
+	| {
+			kind: "tool_result";
+			iteration: number;
+			tool_name: string;
+			result: string;
+			elapsed_ms: number;
+	  };
+
+export interface ToolLoopStepEvent {
+	type: "tool_loop_step";
+	agent_id: string;
+	channel_id: string | null;
+	process_type: ProcessType;
+	process_id: string;
+	step: ToolLoopStep;
+	turn_id: string | null;
+}
+
+export interface InjectionFlaggedEvent {
+	type: "injection_flagged";
+	agent_id: string;
+	channel_id: string;
+	sender_id: string;
+	reasons: string[];
 }
 
 export type ApiEvent =
@@ -115,7 +160,9 @@ export type ApiEvent =
 	| BranchStartedEvent
 	| BranchCompletedEvent
 	| ToolStartedEvent
-	| ToolCompletedEvent;
+	| ToolCompletedEvent
+	| ToolLoopStepEvent
+	| InjectionFlaggedEvent;
 
 async function fetchJson<T>(path: string): Promise<T> {
 	const response = await fetch(`${API_BASE}${path}`);
@@ -390,14 +437,15 @@ export type CortexEventType =
 	| "branch_killed"
 	| "circuit_breaker_tripped"
 	| "observation_created"
-	| "health_check";
+	| "health_check"
+	| "injection_flagged";
 
 export const CORTEX_EVENT_TYPES: CortexEventType[] = [
 	"bulletin_generated", "bulletin_failed",
 	"maintenance_run", "memory_merged", "memory_decayed", "memory_pruned",
 	"association_created", "contradiction_flagged",
 	"worker_killed", "branch_killed", "circuit_breaker_tripped",
-	"observation_created", "health_check",
+	"observation_created", "health_check", "injection_flagged",
 ];
 
 export interface CortexEvent {
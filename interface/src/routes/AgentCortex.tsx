@@ -29,13 +29,14 @@ const EVENT_CATEGORY_COLORS: Record<string, string> = {
 	circuit_breaker_tripped: "bg-amber-500/15 text-amber-400",
 	observation_created: "bg-cyan-500/15 text-cyan-400",
 	health_check: "bg-blue-500/15 text-blue-400",
+	injection_flagged: "bg-red-500/15 text-red-400",
 };
 
-/** Groups for the filter pills — reduces clutter vs showing all 13 types. */
+/** Groups for the filter pills — reduces clutter vs showing all 14 types. */
 const FILTER_GROUPS: { label: string; types: CortexEventType[] }[] = [
 	{ label: "Bulletin", types: ["bulletin_generated", "bulletin_failed"] },
 	{ label: "Maintenance", types: ["maintenance_run", "memory_merged", "memory_decayed", "memory_pruned"] },
-	{ label: "Health", types: ["worker_killed", "branch_killed", "circuit_breaker_tripped", "health_check"] },
+	{ label: "Health", types: ["worker_killed", "branch_killed", "circuit_breaker_tripped", "health_check", "injection_flagged"] },
 	{ label: "Consolidation", types: ["association_created", "contradiction_flagged", "observation_created"] },
 ];
 
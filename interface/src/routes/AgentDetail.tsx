@@ -717,6 +717,7 @@ const CORTEX_EVENT_COLORS: Record<string, string> = {
 	circuit_breaker_tripped: "bg-amber-500/20 text-amber-400",
 	observation_created: "bg-indigo-500/20 text-indigo-400",
 	health_check: "bg-gray-500/20 text-gray-400",
+	injection_flagged: "bg-red-500/20 text-red-400",
 };
 
 function CortexEventsSection({
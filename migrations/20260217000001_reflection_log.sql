@@ -0,0 +1,15 @@
+-- Reflection log: one row per self-review of a channel reply before sending.
+-- Lets operators see what the critique model flagged and what it changed.
+CREATE TABLE IF NOT EXISTS reflection_log (
+    id          TEXT PRIMARY KEY NOT NULL,
+    channel_id  TEXT NOT NULL,
+    draft       TEXT NOT NULL,   -- reply as written by the channel model
+    approved    INTEGER NOT NULL, -- 1 if the draft was sent unchanged
+    critique    TEXT NOT NULL,
+    revised     TEXT,            -- replacement text, NULL when approved
+    model       TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_reflection_log_channel ON reflection_log(channel_id, created_at);
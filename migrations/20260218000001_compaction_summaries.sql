@@ -0,0 +1,20 @@
+-- Compaction summaries: one row per compaction of a channel's history, with
+-- the threshold that triggered it and the model window it was measured against.
+-- The original table from 20260211000004 had a different shape and was never
+-- read, so any leftover copy is replaced.
+DROP TABLE IF EXISTS compaction_summaries;
+
+CREATE TABLE compaction_summaries (
+    id                 TEXT PRIMARY KEY NOT NULL,
+    channel_id         TEXT NOT NULL,
+    action             TEXT NOT NULL,    -- background, aggressive, or emergency
+    trigger_reason     TEXT NOT NULL,
+    model              TEXT NOT NULL,
+    context_tokens     INTEGER NOT NULL, -- assembled context when the check ran
+    context_window     INTEGER NOT NULL,
+    messages_compacted INTEGER NOT NULL,
+    summary            TEXT,             -- NULL for emergency truncation
+    created_at         TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_compaction_summaries_channel ON compaction_summaries(channel_id, created_at);
@@ -0,0 +1,14 @@
+-- Tool traces: the chain of model responses, tool calls, and tool results in
+-- one channel turn, with how the loop ended.
+CREATE TABLE IF NOT EXISTS tool_traces (
+    id          TEXT PRIMARY KEY NOT NULL,
+    channel_id  TEXT NOT NULL,
+    outcome     TEXT NOT NULL,    -- completed, max_iterations, timed_out, cancelled, failed
+    iterations  INTEGER NOT NULL, -- LLM calls made
+    tool_calls  INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    steps       TEXT NOT NULL,    -- JSON array of steps, arguments and results truncated
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_tool_traces_channel ON tool_traces(channel_id, created_at);
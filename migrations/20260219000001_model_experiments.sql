@@ -0,0 +1,16 @@
+-- Model experiments: one row per channel turn while an A/B experiment runs,
+-- tagged with the variant and model that generated it.
+CREATE TABLE IF NOT EXISTS model_experiments (
+    id             TEXT PRIMARY KEY NOT NULL,
+    channel_id     TEXT NOT NULL,
+    variant        TEXT NOT NULL,    -- control, alternate
+    model          TEXT NOT NULL,
+    outcome        TEXT NOT NULL,    -- tool loop outcome: completed, max_iterations, ...
+    duration_ms    INTEGER NOT NULL,
+    input_tokens   INTEGER NOT NULL,
+    output_tokens  INTEGER NOT NULL,
+    response_chars INTEGER NOT NULL,
+    created_at     TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_model_experiments_created ON model_experiments(created_at);
@@ -0,0 +1,14 @@
+-- Agent traces: the model's reasoning and tool-loop deliberation behind one
+-- bot message. Kept for post-incident analysis and never sent to users.
+CREATE TABLE IF NOT EXISTS agent_traces (
+    id           TEXT PRIMARY KEY NOT NULL,
+    message_id   TEXT NOT NULL,    -- conversation_messages.id of the bot message
+    channel_id   TEXT NOT NULL,
+    outcome      TEXT NOT NULL,    -- tool loop outcome: completed, max_iterations, ...
+    reasoning    TEXT NOT NULL,    -- JSON array of reasoning segments
+    deliberation TEXT NOT NULL,    -- JSON array of model responses in the tool loop
+    created_at   TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_traces_message ON agent_traces(message_id);
+CREATE INDEX IF NOT EXISTS idx_agent_traces_channel ON agent_traces(channel_id, created_at);
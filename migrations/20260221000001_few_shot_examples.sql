@@ -0,0 +1,10 @@
+-- Few-shot examples: curated exchanges the channel prompt shows the model as
+-- demonstrations of the style and answers the operator wants.
+CREATE TABLE IF NOT EXISTS few_shot_examples (
+    id           TEXT PRIMARY KEY NOT NULL,
+    user_message TEXT NOT NULL,
+    response     TEXT NOT NULL,
+    note         TEXT,             -- why this example exists, shown to the model
+    enabled      INTEGER NOT NULL DEFAULT 1,
+    created_at   TEXT NOT NULL DEFAULT (datetime('now'))
+);
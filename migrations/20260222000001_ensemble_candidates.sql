@@ -0,0 +1,21 @@
+-- Ensemble candidates: every model's answer to one LLM call in an ensemble
+-- channel, and which one was used. One round per call, one row per model.
+CREATE TABLE IF NOT EXISTS ensemble_candidates (
+    id            TEXT PRIMARY KEY NOT NULL,
+    round_id      TEXT NOT NULL,
+    channel_id    TEXT NOT NULL,
+    position      INTEGER NOT NULL,  -- 0 is the routed channel model
+    model         TEXT NOT NULL,
+    chosen        INTEGER NOT NULL,
+    judged_by     TEXT NOT NULL,     -- judge model, or "consensus"
+    reason        TEXT,              -- the judge's reason for its pick
+    content       TEXT,              -- the answer; NULL when the call failed
+    error         TEXT,
+    duration_ms   INTEGER NOT NULL,
+    input_tokens  INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    created_at    TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_ensemble_candidates_round ON ensemble_candidates(round_id);
+CREATE INDEX IF NOT EXISTS idx_ensemble_candidates_channel ON ensemble_candidates(channel_id, created_at);
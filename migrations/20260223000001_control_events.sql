@@ -0,0 +1,12 @@
+-- Control events: reactions users put on bot messages to act on the reply
+-- (regenerate, delete, expand). Kept as lightweight feedback on replies.
+CREATE TABLE IF NOT EXISTS control_events (
+    id                TEXT PRIMARY KEY NOT NULL,
+    channel_id        TEXT NOT NULL,
+    action            TEXT NOT NULL,     -- "regenerate", "delete", or "expand"
+    target_message_id TEXT NOT NULL,     -- the bot message reacted to
+    user_id           TEXT NOT NULL,
+    created_at        TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_control_events_channel ON control_events(channel_id, created_at);
@@ -0,0 +1,3 @@
+-- Private memories: saved in a DM with private memory on, and only recalled
+-- in the channel they were saved in (`channel_id`).
+ALTER TABLE memories ADD COLUMN private INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,15 @@
+-- Moderation actions: every attempt by an agent's moderate tool, including
+-- ones its moderation policy denied and ones the platform rejected.
+CREATE TABLE IF NOT EXISTS moderation_actions (
+    id          TEXT PRIMARY KEY NOT NULL,
+    agent_id    TEXT NOT NULL,
+    channel_id  TEXT NOT NULL,
+    action      TEXT NOT NULL,     -- "delete_message", "timeout_user", or "apply_role"
+    target      TEXT NOT NULL,     -- what the action was taken on, e.g. "user 123 for 600s"
+    reason      TEXT NOT NULL,
+    outcome     TEXT NOT NULL,     -- "done", "denied", or "failed"
+    error       TEXT,
+    created_at  TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_moderation_actions_channel ON moderation_actions(channel_id, created_at);
@@ -0,0 +1,6 @@
+-- Announcements and set start times for cron jobs. A job with a `message`
+-- posts it as-is instead of running its prompt; `start_at` is when it first
+-- fires, and `run_once` jobs disable themselves after firing.
+ALTER TABLE cron_jobs ADD COLUMN message TEXT;
+ALTER TABLE cron_jobs ADD COLUMN start_at TEXT;
+ALTER TABLE cron_jobs ADD COLUMN run_once INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Reaction sentiment: positive and negative emoji reactions on the bot's
+-- replies. A removed reaction deletes its row.
+CREATE TABLE IF NOT EXISTS reaction_sentiment (
+    id                 TEXT PRIMARY KEY NOT NULL,
+    channel_id         TEXT NOT NULL,
+    target_message_id  TEXT NOT NULL,  -- platform ID of the reply reacted to
+    user_id            TEXT NOT NULL,
+    emoji              TEXT NOT NULL,
+    score              INTEGER NOT NULL,  -- 1 for positive, -1 for negative
+    created_at         TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (target_message_id, user_id, emoji)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reaction_sentiment_channel ON reaction_sentiment(channel_id, created_at);
@@ -0,0 +1,2 @@
+-- Per-channel settings changed from chat with `/settings`, as JSON.
+ALTER TABLE channels ADD COLUMN settings TEXT;
@@ -0,0 +1,7 @@
+-- Turn ids: the id a message gets when it enters the system, shared by the
+-- rows written during the turn it starts.
+ALTER TABLE conversation_messages ADD COLUMN turn_id TEXT;
+ALTER TABLE tool_traces ADD COLUMN turn_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_messages_turn ON conversation_messages(turn_id);
+CREATE INDEX IF NOT EXISTS idx_tool_traces_turn ON tool_traces(turn_id);
@@ -0,0 +1,15 @@
+-- Turn stats: one row per channel turn, for the per-agent activity stats.
+CREATE TABLE IF NOT EXISTS turn_stats (
+    id            TEXT PRIMARY KEY NOT NULL,
+    channel_id    TEXT NOT NULL,
+    turn_id       TEXT NOT NULL,
+    model         TEXT NOT NULL,
+    outcome       TEXT NOT NULL,    -- tool loop outcome: completed, max_iterations, ...
+    duration_ms   INTEGER NOT NULL,
+    tool_calls    INTEGER NOT NULL,
+    input_tokens  INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    created_at    TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_turn_stats_created ON turn_stats(created_at);
@@ -0,0 +1,19 @@
+-- LLM calls: one row per completion call, for looking into failures and cost
+-- anomalies. Prompts and responses are truncated, and redacted unless
+-- `[llm.call_log] redact = false`.
+CREATE TABLE IF NOT EXISTS llm_calls (
+    id            TEXT PRIMARY KEY NOT NULL,
+    turn_id       TEXT,
+    model         TEXT NOT NULL,
+    outcome       TEXT NOT NULL,    -- ok or error
+    duration_ms   INTEGER NOT NULL,
+    input_tokens  INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    prompt        TEXT,
+    response      TEXT,
+    error         TEXT,
+    created_at    TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_calls_created ON llm_calls(created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_calls_turn ON llm_calls(turn_id);
@@ -0,0 +1,14 @@
+-- Channel activity rollups: one row per channel and UTC hour, rebuilt from
+-- conversation_messages by the analytics rollup loop.
+CREATE TABLE IF NOT EXISTS channel_activity_hourly (
+    channel_id          TEXT NOT NULL,
+    hour                TEXT NOT NULL,  -- 'YYYY-MM-DD HH:00:00', UTC
+    user_messages       INTEGER NOT NULL,
+    assistant_messages  INTEGER NOT NULL,
+    answered_messages   INTEGER NOT NULL,  -- user messages in this hour the agent replied to
+    replies             INTEGER NOT NULL,  -- replies sent in this hour
+    response_ms         INTEGER NOT NULL,  -- total time those replies took
+    PRIMARY KEY (channel_id, hour)
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_activity_hour ON channel_activity_hourly(hour);
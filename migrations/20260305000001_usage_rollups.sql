@@ -0,0 +1,12 @@
+-- Token usage rollups: one row per model and UTC hour, rebuilt from llm_calls
+-- by the usage rollup loop. Kept after the calls themselves are pruned.
+CREATE TABLE IF NOT EXISTS usage_rollups (
+    hour          TEXT NOT NULL,  -- 'YYYY-MM-DD HH:00:00', UTC
+    model         TEXT NOT NULL,
+    calls         INTEGER NOT NULL,
+    failed_calls  INTEGER NOT NULL,
+    input_tokens  INTEGER NOT NULL,
+    output_tokens INTEGER NOT NULL,
+    cost_usd      REAL NOT NULL,  -- priced when rolled up; 0 for models without a price
+    PRIMARY KEY (hour, model)
+);
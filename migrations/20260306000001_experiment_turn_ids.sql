@@ -0,0 +1,5 @@
+-- Link each experiment row to its turn, so the variant behind a reply can be
+-- found from the reply's turn_id in conversation_messages.
+ALTER TABLE model_experiments ADD COLUMN turn_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_model_experiments_turn ON model_experiments(turn_id);
@@ -0,0 +1,8 @@
+-- libSQL outbox: writes waiting to be copied to the agent's remote
+-- conversation store, oldest first. Rows are deleted once the server has
+-- them, so writes made while it's unreachable are retried, across restarts.
+CREATE TABLE IF NOT EXISTS libsql_outbox (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    statement  TEXT NOT NULL,    -- JSON: {"sql": ..., "args": [...]}
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
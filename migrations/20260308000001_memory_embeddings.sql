@@ -0,0 +1,29 @@
+-- Memory embeddings for the SQLite vector store, the default backend. Each
+-- embedding is EMBEDDING_DIM little-endian f32s.
+CREATE TABLE IF NOT EXISTS memory_embeddings (
+    memory_id   TEXT PRIMARY KEY NOT NULL,
+    content     TEXT NOT NULL,
+    embedding   BLOB NOT NULL
+);
+
+-- Keyword index over the content, kept in sync by the triggers below.
+CREATE VIRTUAL TABLE IF NOT EXISTS memory_embeddings_fts USING fts5(
+    content,
+    content = 'memory_embeddings',
+    content_rowid = 'rowid'
+);
+
+CREATE TRIGGER IF NOT EXISTS memory_embeddings_insert AFTER INSERT ON memory_embeddings BEGIN
+    INSERT INTO memory_embeddings_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS memory_embeddings_delete AFTER DELETE ON memory_embeddings BEGIN
+    INSERT INTO memory_embeddings_fts (memory_embeddings_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS memory_embeddings_update AFTER UPDATE ON memory_embeddings BEGIN
+    INSERT INTO memory_embeddings_fts (memory_embeddings_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+    INSERT INTO memory_embeddings_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
@@ -0,0 +1,14 @@
+-- Admin audit trail: one row per administrative action taken through the
+-- API, like creating an agent, editing a binding, or rewriting the config.
+-- Lives in the instance's admin.db rather than an agent's database.
+CREATE TABLE IF NOT EXISTS admin_audit (
+    id         TEXT PRIMARY KEY NOT NULL,
+    actor      TEXT NOT NULL,
+    action     TEXT NOT NULL,
+    agent_id   TEXT,
+    payload    TEXT NOT NULL,    -- JSON, with credentials redacted
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_created ON admin_audit(created_at);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_action ON admin_audit(action, created_at);
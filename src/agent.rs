@@ -1,10 +1,31 @@
 //! Agent processes: channels, branches, workers, compactor, cortex.
 
+pub mod analytics;
+pub mod backpressure;
 pub mod branch;
+pub mod capabilities;
+pub mod catch_up;
 pub mod channel;
 pub mod compactor;
+pub mod context;
+pub mod controls;
 pub mod cortex;
 pub mod cortex_chat;
+pub mod digest;
+pub mod experiment;
+pub mod forums;
+pub mod health;
 pub mod ingestion;
+pub mod injection;
+pub mod listening;
+pub mod reasoning;
+pub mod reflection;
+pub mod reload_gate;
+pub mod sentiment;
+pub mod stats;
 pub mod status;
+pub mod task_queue;
+pub mod token_stream;
+pub mod tool_loop;
+pub mod vision;
 pub mod worker;
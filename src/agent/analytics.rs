@@ -0,0 +1,403 @@
+//! Channel activity analytics: when channels are busy, how fast the agent
+//! replies, and how much of the conversation it takes part in.
+//!
+//! Messages are rolled up into `channel_activity_hourly`, one row per channel
+//! and UTC hour, by a loop that runs every [`ROLLUP_INTERVAL`]. Each pass
+//! rebuilds the last [`REROLL_WINDOW`] of hours, so replies that land after a
+//! pass still count toward the hour of the message they answered. Reports
+//! read only the rollups, so they lag behind by up to one interval.
+//!
+//! An assistant message answers every user message since the agent's
+//! previous message in the channel. Its response time is measured from the
+//! earliest of them.
+
+use crate::db::TimedQuery as _;
+
+use chrono::{DateTime, Duration as ChronoDuration, DurationRound as _, Utc};
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often the rollup loop runs.
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Hours rebuilt on every pass, counting back from the latest rollup.
+const REROLL_WINDOW: ChronoDuration = ChronoDuration::hours(24);
+
+/// How far back the first pass rolls up existing messages.
+const BACKFILL: ChronoDuration = ChronoDuration::days(90);
+
+/// One channel's activity in one hour.
+#[derive(Debug, Default)]
+struct HourlyActivity {
+    user_messages: i64,
+    assistant_messages: i64,
+    answered_messages: i64,
+    replies: i64,
+    response_ms: i64,
+}
+
+/// Spawn the rollup loop for one agent's database, scanning messages through
+/// `readers`. The first pass runs right away and backfills existing messages.
+pub fn spawn_rollup_loop(pool: SqlitePool, readers: SqlitePool) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match rollup(&pool, &readers).await {
+                Ok(hours) => tracing::debug!(hours, "channel activity rolled up"),
+                Err(error) => tracing::warn!(%error, "failed to roll up channel activity"),
+            }
+            tokio::time::sleep(ROLLUP_INTERVAL).await;
+        }
+    })
+}
+
+/// Rebuild the rollups from the start of the reroll window, or from the
+/// backfill horizon if there are none yet. Returns the number of
+/// channel-hours written. The message scan runs on `readers`, so it doesn't
+/// hold up writes.
+pub async fn rollup(pool: &SqlitePool, readers: &SqlitePool) -> crate::error::Result<usize> {
+    let latest: Option<String> =
+        sqlx::query_scalar("SELECT MAX(hour) FROM channel_activity_hourly")
+            .fetch_one(readers)
+            .timed("channel_activity_hourly.latest")
+            .await?;
+    let latest = latest
+        .and_then(|hour| chrono::NaiveDateTime::parse_from_str(&hour, HOUR_FORMAT).ok())
+        .map(|hour| hour.and_utc());
+    let start = match latest {
+        Some(latest) => latest - REROLL_WINDOW,
+        None => Utc::now() - BACKFILL,
+    };
+    let start = start
+        .duration_trunc(ChronoDuration::hours(1))
+        .unwrap_or(start);
+
+    // Messages from before `start` are only read to know which user messages
+    // were still waiting on a reply when the window opened.
+    let rows = sqlx::query(
+        "SELECT channel_id, role, created_at FROM conversation_messages \
+         WHERE created_at >= ? \
+         ORDER BY channel_id, created_at",
+    )
+    .bind(format_hour(start - REROLL_WINDOW))
+    .fetch_all(readers)
+    .timed_with("analytics.rollup_messages", || {
+        format!("since={}", format_hour(start - REROLL_WINDOW))
+    })
+    .await?;
+    let messages: Vec<(String, bool, DateTime<Utc>)> = rows
+        .iter()
+        .filter_map(|row| {
+            let created_at = row.try_get("created_at").ok()?;
+            let role: String = row.get("role");
+            Some((row.get("channel_id"), role == "user", created_at))
+        })
+        .collect();
+
+    let buckets = bucket(&messages, start);
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query("DELETE FROM channel_activity_hourly WHERE hour >= ?")
+        .bind(format_hour(start))
+        .execute(&mut *transaction)
+        .timed("channel_activity_hourly.delete")
+        .await?;
+    for ((channel_id, hour), activity) in &buckets {
+        sqlx::query(
+            "INSERT INTO channel_activity_hourly \
+             (channel_id, hour, user_messages, assistant_messages, answered_messages, replies, response_ms) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(channel_id)
+        .bind(hour)
+        .bind(activity.user_messages)
+        .bind(activity.assistant_messages)
+        .bind(activity.answered_messages)
+        .bind(activity.replies)
+        .bind(activity.response_ms)
+        .execute(&mut *transaction)
+        .timed_with("channel_activity_hourly.insert", || {
+            format!("channel_id={channel_id} hour={hour}")
+        })
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(buckets.len())
+}
+
+const HOUR_FORMAT: &str = "%Y-%m-%d %H:00:00";
+
+fn format_hour(time: DateTime<Utc>) -> String {
+    time.format(HOUR_FORMAT).to_string()
+}
+
+/// Tally messages, ordered by channel and time, into channel-hours from
+/// `start` on.
+fn bucket(
+    messages: &[(String, bool, DateTime<Utc>)],
+    start: DateTime<Utc>,
+) -> BTreeMap<(String, String), HourlyActivity> {
+    let mut buckets: BTreeMap<(String, String), HourlyActivity> = BTreeMap::new();
+    let mut pending: Vec<DateTime<Utc>> = Vec::new();
+    let mut current_channel: Option<&str> = None;
+
+    for (channel_id, from_user, created_at) in messages {
+        if current_channel != Some(channel_id.as_str()) {
+            current_channel = Some(channel_id.as_str());
+            pending.clear();
+        }
+        let mut tally = |at: DateTime<Utc>, update: &dyn Fn(&mut HourlyActivity)| {
+            if at >= start {
+                update(
+                    buckets
+                        .entry((channel_id.clone(), format_hour(at)))
+                        .or_default(),
+                );
+            }
+        };
+
+        if *from_user {
+            tally(*created_at, &|activity| activity.user_messages += 1);
+            pending.push(*created_at);
+            continue;
+        }
+
+        tally(*created_at, &|activity| activity.assistant_messages += 1);
+        let Some(first) = pending.first().copied() else {
+            continue;
+        };
+        for asked_at in pending.drain(..) {
+            tally(asked_at, &|activity| activity.answered_messages += 1);
+        }
+        let response_ms = (*created_at - first).num_milliseconds().max(0);
+        tally(*created_at, &|activity| {
+            activity.replies += 1;
+            activity.response_ms += response_ms;
+        });
+    }
+
+    buckets
+}
+
+/// Activity over the last `days` days, for one channel or all of them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelAnalytics {
+    pub days: u32,
+    /// Messages per UTC weekday and hour. Only hours with messages are listed.
+    pub heatmap: Vec<HeatmapCell>,
+    /// Average response time per day, oldest first.
+    pub response_times: Vec<DailyResponseTime>,
+    /// Share of user messages answered, per channel, busiest first.
+    pub participation: Vec<ChannelParticipation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeatmapCell {
+    /// 0 is Sunday.
+    pub weekday: u8,
+    pub hour: u8,
+    pub messages: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyResponseTime {
+    /// UTC date, `YYYY-MM-DD`.
+    pub day: String,
+    pub replies: i64,
+    pub avg_response_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelParticipation {
+    pub channel_id: String,
+    pub display_name: Option<String>,
+    pub user_messages: i64,
+    pub answered_messages: i64,
+    pub assistant_messages: i64,
+    pub participation_rate: Option<f64>,
+}
+
+/// Report on the rolled-up activity of the last `days` days. Limited to one
+/// channel if `channel_id` is given.
+pub async fn report(
+    pool: &SqlitePool,
+    channel_id: Option<&str>,
+    days: u32,
+) -> crate::error::Result<ChannelAnalytics> {
+    let window = format!("-{days} days");
+
+    let heatmap = sqlx::query(
+        "SELECT CAST(strftime('%w', hour) AS INTEGER) AS weekday, \
+         CAST(strftime('%H', hour) AS INTEGER) AS hour_of_day, \
+         SUM(user_messages + assistant_messages) AS messages \
+         FROM channel_activity_hourly \
+         WHERE hour >= datetime('now', ?) AND (? IS NULL OR channel_id = ?) \
+         GROUP BY weekday, hour_of_day \
+         ORDER BY weekday, hour_of_day",
+    )
+    .bind(&window)
+    .bind(channel_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .timed_with("analytics.heatmap", || {
+        format!("channel_id={channel_id:?} days={days}")
+    })
+    .await?
+    .into_iter()
+    .map(|row| HeatmapCell {
+        weekday: row.get::<i64, _>("weekday") as u8,
+        hour: row.get::<i64, _>("hour_of_day") as u8,
+        messages: row.get("messages"),
+    })
+    .collect();
+
+    let response_times = sqlx::query(
+        "SELECT date(hour) AS day, SUM(replies) AS replies, SUM(response_ms) AS response_ms \
+         FROM channel_activity_hourly \
+         WHERE hour >= datetime('now', ?) AND (? IS NULL OR channel_id = ?) \
+         GROUP BY day \
+         HAVING SUM(replies) > 0 \
+         ORDER BY day",
+    )
+    .bind(&window)
+    .bind(channel_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .timed_with("analytics.response_times", || {
+        format!("channel_id={channel_id:?} days={days}")
+    })
+    .await?
+    .into_iter()
+    .map(|row| {
+        let replies: i64 = row.get("replies");
+        DailyResponseTime {
+            day: row.get("day"),
+            replies,
+            avg_response_ms: row.get::<i64, _>("response_ms") as f64 / replies as f64,
+        }
+    })
+    .collect();
+
+    let participation = sqlx::query(
+        "SELECT a.channel_id, c.display_name, \
+         SUM(a.user_messages) AS user_messages, \
+         SUM(a.answered_messages) AS answered_messages, \
+         SUM(a.assistant_messages) AS assistant_messages \
+         FROM channel_activity_hourly a \
+         LEFT JOIN channels c ON c.id = a.channel_id \
+         WHERE a.hour >= datetime('now', ?) AND (? IS NULL OR a.channel_id = ?) \
+         GROUP BY a.channel_id \
+         ORDER BY user_messages DESC, a.channel_id",
+    )
+    .bind(&window)
+    .bind(channel_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .timed_with("analytics.participation", || {
+        format!("channel_id={channel_id:?} days={days}")
+    })
+    .await?
+    .into_iter()
+    .map(|row| {
+        let user_messages: i64 = row.get("user_messages");
+        let answered_messages: i64 = row.get("answered_messages");
+        ChannelParticipation {
+            channel_id: row.get("channel_id"),
+            display_name: row.get("display_name"),
+            user_messages,
+            answered_messages,
+            assistant_messages: row.get("assistant_messages"),
+            participation_rate: (user_messages > 0)
+                .then(|| answered_messages as f64 / user_messages as f64),
+        }
+    })
+    .collect();
+
+    Ok(ChannelAnalytics {
+        days,
+        heatmap,
+        response_times,
+        participation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike as _;
+
+    #[tokio::test]
+    async fn test_rollup_reports_heatmap_response_times_and_participation() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let hour = (Utc::now() - ChronoDuration::hours(3))
+            .duration_trunc(ChronoDuration::hours(1))
+            .unwrap();
+        let at = |seconds: i64| {
+            (hour + ChronoDuration::seconds(seconds))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        // Two questions answered together after 30s, then one left unanswered.
+        // The second channel never gets a reply.
+        for (index, (channel_id, role, created_at)) in [
+            ("discord:1:2", "user", at(0)),
+            ("discord:1:2", "user", at(10)),
+            ("discord:1:2", "assistant", at(30)),
+            ("discord:1:2", "user", at(60)),
+            ("slack:T1:C1", "user", at(5)),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sqlx::query(
+                "INSERT INTO conversation_messages (id, channel_id, role, content, created_at) \
+                 VALUES (?, ?, ?, '', ?)",
+            )
+            .bind(index.to_string())
+            .bind(channel_id)
+            .bind(role)
+            .bind(created_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(rollup(&pool, &pool).await.unwrap(), 2);
+        // Rolling up again rebuilds the same hours instead of adding to them.
+        assert_eq!(rollup(&pool, &pool).await.unwrap(), 2);
+
+        let analytics = report(&pool, None, 7).await.unwrap();
+        assert_eq!(analytics.heatmap.len(), 1);
+        assert_eq!(analytics.heatmap[0].messages, 5);
+        assert_eq!(u32::from(analytics.heatmap[0].hour), hour.hour());
+
+        assert_eq!(analytics.response_times.len(), 1);
+        assert_eq!(analytics.response_times[0].replies, 1);
+        assert_eq!(analytics.response_times[0].avg_response_ms, 30_000.0);
+
+        let discord = &analytics.participation[0];
+        assert_eq!(discord.channel_id, "discord:1:2");
+        assert_eq!((discord.user_messages, discord.answered_messages), (3, 2));
+        assert!((discord.participation_rate.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(analytics.participation[1].participation_rate, Some(0.0));
+
+        let slack = report(&pool, Some("slack:T1:C1"), 7).await.unwrap();
+        assert_eq!(slack.participation.len(), 1);
+        assert!(slack.response_times.is_empty());
+    }
+}
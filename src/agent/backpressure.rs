@@ -0,0 +1,264 @@
+//! Backpressure: what happens to a channel's messages once it falls behind.
+//!
+//! Each channel reads its messages from a queue of
+//! [`QUEUE_SIZE`](crate::agent::channel::QUEUE_SIZE). The main loop hands
+//! them to the channel's [`ChannelInbox`] with [`forward`], which never
+//! waits: a task of the channel's own moves them into the queue as room
+//! opens, so a channel that's behind only holds up its own messages. A
+//! channel is behind when `max_pending` messages are waiting for it, or when
+//! the database has `max_pending_writes` writes in flight. Then the
+//! conversation's [`BackpressurePolicy`] applies:
+//!
+//! - `queue` lets messages wait their turn;
+//! - `coalesce` lets them wait too, and the channel answers everything
+//!   waiting in one turn;
+//! - `drop` drops new messages, telling the author once per backlog.
+//!
+//! Edits are never dropped by the policy, since they only update a message
+//! the channel already has. An inbox holds at most [`OVERFLOW_SIZE`]
+//! messages on top of the queue; past that, new messages are dropped
+//! whatever the policy, so a stuck channel can't grow without limit.
+
+use crate::InboundMessage;
+use crate::agent::channel::MESSAGE_EDIT_KEY;
+use crate::agent::listening;
+use crate::config::{BackpressureConfig, BackpressurePolicy};
+
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+
+/// Messages an inbox holds for its channel once the channel's queue is full.
+pub const OVERFLOW_SIZE: usize = 256;
+
+/// What [`forward`] did with a message.
+#[derive(Debug)]
+pub enum Forwarded {
+    Queued,
+    /// Dropped by the `drop` policy, or because the inbox was full. `notice`
+    /// holds the message when it's the first of a backlog, whose author
+    /// should be told.
+    Dropped {
+        notice: Option<InboundMessage>,
+    },
+}
+
+/// Where the main loop hands a channel its messages, in order.
+#[derive(Debug)]
+pub struct ChannelInbox {
+    inbox: mpsc::Sender<InboundMessage>,
+    queue: mpsc::Sender<InboundMessage>,
+}
+
+impl ChannelInbox {
+    /// Start moving messages into a channel's queue. The task ends when the
+    /// inbox is dropped or the channel stops reading.
+    pub fn spawn(queue: mpsc::Sender<InboundMessage>) -> Self {
+        let (inbox, mut waiting) = mpsc::channel(OVERFLOW_SIZE);
+        let target = queue.clone();
+        tokio::spawn(async move {
+            while let Some(message) = waiting.recv().await {
+                if target.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self { inbox, queue }
+    }
+
+    /// Messages waiting for the channel, in its queue or held for it.
+    pub fn pending(&self) -> usize {
+        pending(&self.queue) + pending(&self.inbox)
+    }
+}
+
+/// Whether a channel with `pending` waiting messages is behind.
+pub fn is_behind(pending: usize, config: &BackpressureConfig) -> bool {
+    pending >= config.max_pending
+        || (config.max_pending_writes > 0
+            && crate::otel::db_writes_pending() >= config.max_pending_writes as u64)
+}
+
+/// Messages waiting in a channel's queue.
+pub fn pending(sender: &mpsc::Sender<InboundMessage>) -> usize {
+    sender.max_capacity() - sender.capacity()
+}
+
+/// Hand a message to a channel, applying its backpressure policy. Never
+/// waits. `dropping` is kept per channel by the caller, and tracks whether
+/// the author of a dropped message was already told during this backlog.
+/// Fails only if the channel has stopped.
+pub fn forward(
+    inbox: &ChannelInbox,
+    message: InboundMessage,
+    config: &BackpressureConfig,
+    dropping: &mut bool,
+    agent_id: &str,
+) -> Result<Forwarded, SendError<InboundMessage>> {
+    let edit = message.metadata.contains_key(MESSAGE_EDIT_KEY);
+    if !is_behind(inbox.pending(), config) {
+        *dropping = false;
+    } else if !edit && config.policy_for(&message.conversation_id) == BackpressurePolicy::Drop {
+        tracing::debug!(
+            agent_id,
+            conversation_id = %message.conversation_id,
+            "channel behind, dropped message"
+        );
+        return Ok(dropped(message, dropping, agent_id));
+    }
+
+    let conversation_id = message.conversation_id.clone();
+    match inbox.inbox.try_send(message) {
+        Ok(()) => {
+            record_depth(agent_id, &conversation_id, inbox.pending());
+            Ok(Forwarded::Queued)
+        }
+        Err(TrySendError::Full(message)) => {
+            tracing::warn!(agent_id, %conversation_id, "channel inbox full, dropped message");
+            Ok(dropped(message, dropping, agent_id))
+        }
+        Err(TrySendError::Closed(message)) => Err(SendError(message)),
+    }
+}
+
+fn dropped(message: InboundMessage, dropping: &mut bool, agent_id: &str) -> Forwarded {
+    record_shed(agent_id, "dropped", 1);
+    // Passive messages wouldn't have been answered anyway, and edits don't
+    // get an answer.
+    let notify = !*dropping
+        && !listening::is_passive(&message)
+        && !message.metadata.contains_key(MESSAGE_EDIT_KEY);
+    *dropping |= notify;
+    Forwarded::Dropped {
+        notice: notify.then_some(message),
+    }
+}
+
+/// Export the number of messages waiting for a channel.
+pub fn record_depth(agent_id: &str, channel_id: &str, depth: usize) {
+    crate::otel::Metrics::global().channel_queue_depth.record(
+        depth as u64,
+        &[
+            opentelemetry::KeyValue::new("agent_id", agent_id.to_string()),
+            opentelemetry::KeyValue::new("channel_id", channel_id.to_string()),
+        ],
+    );
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .channel_queue_depth
+        .with_label_values(&[agent_id, channel_id])
+        .set(depth as i64);
+}
+
+/// Count messages dropped or folded into a batch, by `action`.
+pub fn record_shed(agent_id: &str, action: &'static str, count: u64) {
+    crate::otel::Metrics::global().messages_shed.add(
+        count,
+        &[
+            opentelemetry::KeyValue::new("agent_id", agent_id.to_string()),
+            opentelemetry::KeyValue::new("action", action),
+        ],
+    );
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .messages_shed_total
+        .with_label_values(&[agent_id, action])
+        .inc_by(count);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageContent;
+
+    use std::collections::HashMap;
+
+    fn message(text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "42".into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    fn notified(forwarded: Forwarded) -> Option<bool> {
+        match forwarded {
+            Forwarded::Queued => None,
+            Forwarded::Dropped { notice } => Some(notice.is_some()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_policy_notifies_once_per_backlog() {
+        let config = BackpressureConfig {
+            policy: BackpressurePolicy::Drop,
+            max_pending: 1,
+            max_pending_writes: 0,
+            ..Default::default()
+        };
+        let (sender, mut receiver) = mpsc::channel(4);
+        let inbox = ChannelInbox::spawn(sender);
+        let mut dropping = false;
+        let mut send = |message: InboundMessage| {
+            let forwarded = forward(&inbox, message, &config, &mut dropping, "main");
+            notified(forwarded.expect("channel is open"))
+        };
+
+        assert_eq!(send(message("one")), None);
+        assert_eq!(send(message("two")), Some(true));
+        assert_eq!(send(message("three")), Some(false));
+
+        // Edits still reach a channel that's behind.
+        let mut edit = message("one, edited");
+        edit.metadata.insert(MESSAGE_EDIT_KEY.into(), "1".into());
+        assert_eq!(send(edit), None);
+
+        // Caught up, so the next backlog is announced again.
+        receiver.recv().await.expect("queued message");
+        receiver.recv().await.expect("queued edit");
+        assert_eq!(send(message("four")), None);
+        assert_eq!(send(message("five")), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_slow_channel_never_holds_up_the_caller() {
+        let mut config = BackpressureConfig {
+            max_pending: 1,
+            max_pending_writes: 0,
+            ..Default::default()
+        };
+        // A channel that never reads, with a queue of one.
+        let (sender, _receiver) = mpsc::channel(1);
+        let inbox = ChannelInbox::spawn(sender);
+        let mut dropping = false;
+
+        // The queue policy holds messages for the channel without waiting:
+        // one in its queue, one on its way there, and a full inbox. Past
+        // that, they're dropped.
+        let mut held = 0;
+        for index in 0..OVERFLOW_SIZE * 2 {
+            let message = message(&index.to_string());
+            let forwarded = forward(&inbox, message, &config, &mut dropping, "main");
+            if notified(forwarded.expect("channel is open")).is_none() {
+                held += 1;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(held, OVERFLOW_SIZE + 2);
+
+        config
+            .channels
+            .insert("discord:1:2".into(), BackpressurePolicy::Drop);
+        let mut passive = message("passive");
+        listening::mark_passive(&mut passive);
+        dropping = false;
+        let forwarded = forward(&inbox, passive, &config, &mut dropping, "main");
+        assert_eq!(notified(forwarded.expect("channel is open")), Some(false));
+        assert!(!dropping);
+    }
+}
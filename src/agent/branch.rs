@@ -1,6 +1,6 @@
 //! Branch: Fork context for thinking and delegation.
 
-use crate::agent::compactor::estimate_history_tokens;
+use crate::agent::context::count_history_tokens;
 use crate::error::Result;
 use crate::hooks::SpacebotHook;
 use crate::llm::SpacebotModel;
@@ -91,6 +91,7 @@ impl Branch {
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Branch, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
+            .with_agent(&self.deps.agent_id)
             .with_routing((**routing).clone());
 
         let agent = AgentBuilder::new(model)
@@ -168,7 +169,7 @@ impl Branch {
     /// Removes the oldest 50% of messages when usage exceeds 70%.
     fn maybe_compact_history(&mut self) {
         let context_window = **self.deps.runtime_config.context_window.load();
-        let estimated = estimate_history_tokens(&self.history);
+        let estimated = count_history_tokens(&self.history);
         let usage = estimated as f32 / context_window as f32;
 
         if usage < 0.70 {
@@ -0,0 +1,62 @@
+//! Capability registry: what each agent handles, so agents can point work at
+//! the right peer.
+
+use crate::config::{Config, DefaultsConfig, ResolvedAgentConfig};
+
+use serde::Serialize;
+
+/// One agent's declared specialties plus the tools its config gives it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AgentCapabilities {
+    pub agent_id: String,
+    pub description: Option<String>,
+    pub topics: Vec<String>,
+    pub languages: Vec<String>,
+    /// Tools the agent's workers can use.
+    pub tools: Vec<String>,
+}
+
+impl AgentCapabilities {
+    pub fn from_config(agent: &ResolvedAgentConfig, defaults: &DefaultsConfig) -> Self {
+        let mut tools: Vec<String> = ["shell", "file", "exec"].map(String::from).to_vec();
+        if agent.browser.enabled {
+            tools.push("browser".into());
+        }
+        if agent.brave_search_key.is_some() {
+            tools.push("web_search".into());
+        }
+        if defaults.opencode.enabled {
+            tools.push("opencode".into());
+        }
+
+        Self {
+            agent_id: agent.id.clone(),
+            description: agent.capabilities.description.clone(),
+            topics: agent.capabilities.topics.clone(),
+            languages: agent.capabilities.languages.clone(),
+            tools,
+        }
+    }
+}
+
+/// Capabilities of every configured agent, in config order.
+pub fn registry(config: &Config) -> Vec<AgentCapabilities> {
+    config
+        .agents
+        .iter()
+        .map(|agent| {
+            AgentCapabilities::from_config(
+                &agent.resolve(&config.instance_dir, &config.defaults),
+                &config.defaults,
+            )
+        })
+        .collect()
+}
+
+/// Capabilities of every agent other than `agent_id`.
+pub fn peers(config: &Config, agent_id: &str) -> Vec<AgentCapabilities> {
+    registry(config)
+        .into_iter()
+        .filter(|capabilities| capabilities.agent_id != agent_id)
+        .collect()
+}
@@ -0,0 +1,34 @@
+//! Catching up on what was said while the bot was down.
+//!
+//! On startup, the messaging adapter fetches the messages sent in each
+//! channel since the last one recorded there and forwards them as context,
+//! so the channel persists them without starting a turn. With summarizing
+//! on, the last missed message in a channel instead carries `CATCH_UP_KEY`
+//! and an instruction to post a brief summary of what was missed, which
+//! addresses it to the agent.
+
+/// Metadata key set on the last message missed in a channel while the bot was
+/// down, when the agent should summarize what it missed.
+pub const CATCH_UP_KEY: &str = "catch_up";
+
+/// The instruction put ahead of the last of `missed` messages.
+pub fn instruction(missed: usize) -> String {
+    let messages = if missed == 1 { "message" } else { "messages" };
+    format!(
+        "[You were offline and missed {missed} {messages} in this channel, ending with the one \
+         below. Post a brief \"here's what I missed\" summary of them, and answer anything \
+         still waiting on you.]"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_counts_missed_messages() {
+        assert!(instruction(1).contains("missed 1 message in"));
+        assert!(instruction(12).contains("missed 12 messages in"));
+        assert!(instruction(12).starts_with('[') && instruction(12).ends_with(']'));
+    }
+}
@@ -1,13 +1,32 @@
 //! Channel: User-facing conversation process.
 
+use crate::agent::backpressure;
 use crate::agent::branch::Branch;
 use crate::agent::compactor::Compactor;
+use crate::agent::context::{ContextManager, ContextPlan};
+use crate::agent::controls::{self, ReactionControl};
+use crate::agent::forums;
+use crate::agent::listening;
+use crate::agent::reasoning::ReasoningTrace;
+use crate::agent::reload_gate::TurnGuard;
 use crate::agent::status::StatusBlock;
+use crate::agent::task_queue::{self, TaskPermit, TaskPriority};
+use crate::agent::token_stream;
+use crate::agent::tool_loop::{ToolLoop, ToolLoopOutcome};
+use crate::agent::vision;
 use crate::agent::worker::Worker;
-use crate::conversation::{ChannelStore, ConversationLogger, ProcessRunLogger};
+use crate::config::{
+    BackpressurePolicy, EXPENSIVE_TOOLS_KEY, GUILD_PERSONA_KEY, PRIVATE_MEMORY_KEY,
+};
+use crate::conversation::{
+    ChannelStore, ConversationLogger, ExampleStore, ProcessRunLogger, Verbosity,
+};
 use crate::error::{AgentError, Result};
 use crate::hooks::SpacebotHook;
 use crate::llm::SpacebotModel;
+use crate::llm::ensemble::Ensemble;
+use crate::messaging::presence::PresenceGuard;
+use crate::tools::ModerationTarget;
 use crate::{
     AgentDeps, BranchId, ChannelId, InboundMessage, OutboundResponse, ProcessEvent, ProcessId,
     ProcessType, WorkerId,
@@ -19,11 +38,142 @@ use rig::one_or_many::OneOrMany;
 use rig::tool::server::ToolServer;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::broadcast;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, mpsc, oneshot};
 use tracing::Instrument as _;
 
+/// Messages a channel's queue holds before senders have to wait.
+pub const QUEUE_SIZE: usize = 64;
+
+/// Metadata key on inbound messages that ask the agent to stop the channel's
+/// in-flight turn instead of starting one, such as a 🛑 reaction on Discord.
+pub const CANCEL_TURN_KEY: &str = "cancel_turn";
+
+/// Metadata key on cancel requests from someone who may stop any turn, not
+/// just one answering them, such as a Discord member who can manage messages.
+pub const MODERATOR_KEY: &str = "moderator";
+
+/// Metadata key on inbound messages that ask what the agents in the
+/// conversation are doing, such as Discord's `/status`. Answered without
+/// starting a turn.
+pub const STATUS_REQUEST_KEY: &str = "status_request";
+
+/// Metadata key on inbound messages that ask for the agent settings that
+/// apply in the conversation, such as Discord's `/config`. Answered without
+/// starting a turn.
+pub const CONFIG_REQUEST_KEY: &str = "config_request";
+
+/// Metadata key on inbound messages that change the conversation's settings,
+/// such as Discord's `/settings`, holding the new values by setting name.
+/// Applied without starting a turn.
+pub const SETTINGS_REQUEST_KEY: &str = "settings_request";
+
+/// Metadata key carrying the reply length set for the conversation.
+pub const VERBOSITY_KEY: &str = "channel_verbosity";
+
+/// Metadata key on inbound messages that carry the new content of an edited
+/// message, under the edited message's ID. They update the stored message and
+/// the history instead of starting a turn.
+pub const MESSAGE_EDIT_KEY: &str = "message_edit";
+
+/// Stops a channel's in-flight turn from outside the channel task.
+///
+/// The channel arms it for each turn and races the LLM call and tool loop
+/// against it. Cancelling when no turn is running does nothing.
+#[derive(Clone, Default)]
+pub struct TurnCanceller {
+    slot: Arc<std::sync::Mutex<ArmedTurn>>,
+}
+
+#[derive(Default)]
+struct ArmedTurn {
+    cancel_tx: Option<oneshot::Sender<String>>,
+    /// Senders of the messages the turn answers.
+    authors: Vec<String>,
+}
+
+impl TurnCanceller {
+    /// Cancel the running turn. Returns false if there was none.
+    pub fn cancel(&self, reason: impl Into<String>) -> bool {
+        let sender = self.lock().cancel_tx.take();
+        sender.is_some_and(|sender| sender.send(reason.into()).is_ok())
+    }
+
+    /// Cancel the running turn on behalf of `sender_id`, who may only stop a
+    /// turn answering one of their messages unless they're a moderator.
+    /// Returns false if there was no turn they may stop.
+    pub fn cancel_for(&self, sender_id: &str, moderator: bool, reason: impl Into<String>) -> bool {
+        let mut turn = self.lock();
+        if !moderator && !turn.authors.iter().any(|author| author == sender_id) {
+            return false;
+        }
+        let sender = turn.cancel_tx.take();
+        sender.is_some_and(|sender| sender.send(reason.into()).is_ok())
+    }
+
+    /// Whether a turn is running right now.
+    pub fn is_running(&self) -> bool {
+        self.lock().cancel_tx.is_some()
+    }
+
+    /// Record whose messages the next turn answers.
+    fn answering(&self, authors: Vec<String>) {
+        self.lock().authors = authors;
+    }
+
+    fn arm(&self) -> oneshot::Receiver<String> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.lock().cancel_tx = Some(cancel_tx);
+        cancel_rx
+    }
+
+    fn disarm(&self) {
+        *self.lock() = ArmedTurn::default();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ArmedTurn> {
+        self.slot.lock().expect("turn canceller lock poisoned")
+    }
+}
+
+/// A channel's system prompt, gathered once per turn. The context plan is
+/// measured on it without the memory bulletin, and the bulletin is added
+/// when it fits, so both come from the same status, examples, and channels.
+struct SystemPrompt {
+    prompt_engine: Arc<crate::prompts::PromptEngine>,
+    identity_context: Option<String>,
+    skills_prompt: Option<String>,
+    worker_capabilities: String,
+    conversation_context: Option<String>,
+    status_text: Option<String>,
+    coalesce_hint: Option<String>,
+    available_channels: Option<String>,
+    peer_agents: Option<String>,
+    few_shot_examples: Option<String>,
+}
+
+impl SystemPrompt {
+    fn render(&self, memory_bulletin: Option<String>) -> String {
+        self.prompt_engine
+            .render_channel_prompt(
+                self.identity_context.clone(),
+                memory_bulletin,
+                self.skills_prompt.clone(),
+                self.worker_capabilities.clone(),
+                self.conversation_context.clone(),
+                self.status_text.clone(),
+                self.coalesce_hint.clone(),
+                self.available_channels.clone(),
+                self.peer_agents.clone(),
+                self.few_shot_examples.clone(),
+            )
+            .expect("failed to render channel prompt")
+    }
+}
+
 /// Shared state that channel tools need to act on the channel.
 ///
 /// Wrapped in Arc and passed to tools (branch, spawn_worker, route, cancel)
@@ -46,9 +196,23 @@ pub struct ChannelState {
     pub channel_store: ChannelStore,
     pub screenshot_dir: std::path::PathBuf,
     pub logs_dir: std::path::PathBuf,
+    /// Stops the turn in progress, for the cancel API and reaction triggers.
+    pub turn_canceller: TurnCanceller,
+    /// Keep memories private to this channel: set for DMs with private memory.
+    pub private_memory: Arc<AtomicBool>,
 }
 
 impl ChannelState {
+    /// Abort the channel's in-flight LLM call or tool loop. Returns false if
+    /// the channel isn't running a turn.
+    pub fn cancel_turn(&self, reason: &str) -> bool {
+        let cancelled = self.turn_canceller.cancel(reason);
+        if cancelled {
+            tracing::info!(channel_id = %self.channel_id, %reason, "channel turn cancel requested");
+        }
+        cancelled
+    }
+
     /// Cancel a running worker by aborting its tokio task and cleaning up state.
     /// Returns an error message if the worker is not found.
     pub async fn cancel_worker(&self, worker_id: WorkerId) -> std::result::Result<(), String> {
@@ -124,6 +288,33 @@ pub struct Channel {
     coalesce_buffer: Vec<InboundMessage>,
     /// Deadline for flushing the coalesce buffer.
     coalesce_deadline: Option<tokio::time::Instant>,
+    /// Messages the listening mode let through without a turn, shown to the
+    /// model with the next one.
+    passive_context: Vec<String>,
+    /// Persona note of the Discord guild this conversation is in, from
+    /// `[messaging.discord.guilds]`, refreshed with each message.
+    guild_persona: Option<String>,
+    /// Reply length set for the conversation, refreshed with each message.
+    verbosity: Option<Verbosity>,
+    /// The latest message, which moderation actions default to.
+    moderation_target: Option<ModerationTarget>,
+    /// Whether the latest senders may start workers, per the platform's
+    /// role permissions.
+    allow_workers: bool,
+    /// Tags the agent may apply to the forum post this conversation is, if
+    /// tagging is on for its forum.
+    forum_tags: Option<Vec<String>>,
+    /// The latest user messages, so edits to them can be applied.
+    recent_messages: VecDeque<RecentMessage>,
+}
+
+/// A user message as it was stored and shown to the model.
+struct RecentMessage {
+    id: String,
+    raw_text: String,
+    /// Attribution put in front of the text in history, e.g. "alice: ".
+    prefix: String,
+    history_text: String,
 }
 
 impl Channel {
@@ -152,11 +343,18 @@ impl Channel {
         let history = Arc::new(RwLock::new(Vec::new()));
         let active_branches = Arc::new(RwLock::new(HashMap::new()));
         let active_workers = Arc::new(RwLock::new(HashMap::new()));
-        let (message_tx, message_rx) = mpsc::channel(64);
-
-        let conversation_logger = ConversationLogger::new(deps.sqlite_pool.clone());
+        let (message_tx, message_rx) = mpsc::channel(QUEUE_SIZE);
+
+        let mut conversation_logger = ConversationLogger::new(deps.sqlite_pool.clone())
+            .with_readers(deps.sqlite_read.clone())
+            .with_redactor(deps.runtime_config.redactor.clone())
+            .with_cache(deps.runtime_config.recent_messages.clone());
+        if let Some(store) = deps.runtime_config.conversation_store.load().as_ref() {
+            conversation_logger = conversation_logger.with_remote_store(store.clone());
+        }
         let process_run_logger = ProcessRunLogger::new(deps.sqlite_pool.clone());
-        let channel_store = ChannelStore::new(deps.sqlite_pool.clone());
+        let channel_store =
+            ChannelStore::new(deps.sqlite_pool.clone()).with_readers(deps.sqlite_read.clone());
 
         let compactor = Compactor::new(id.clone(), deps.clone(), history.clone());
 
@@ -174,6 +372,8 @@ impl Channel {
             channel_store,
             screenshot_dir,
             logs_dir,
+            turn_canceller: TurnCanceller::default(),
+            private_memory: compactor.private_memory.clone(),
         };
 
         // Each channel gets its own isolated tool server to avoid races between
@@ -199,6 +399,13 @@ impl Channel {
             memory_persistence_branches: HashSet::new(),
             coalesce_buffer: Vec::new(),
             coalesce_deadline: None,
+            passive_context: Vec::new(),
+            guild_persona: None,
+            verbosity: None,
+            moderation_target: None,
+            allow_workers: true,
+            forum_tags: None,
+            recent_messages: VecDeque::new(),
         };
 
         (channel, message_tx)
@@ -224,6 +431,20 @@ impl Channel {
 
             tokio::select! {
                 Some(message) = self.message_rx.recv() => {
+                    let pending = self.message_rx.len();
+                    backpressure::record_depth(&self.deps.agent_id, &self.id, pending);
+                    if message.metadata.contains_key(MESSAGE_EDIT_KEY) {
+                        self.handle_edit(message).await;
+                        continue;
+                    }
+                    let backlog = self.deps.runtime_config.backpressure.load();
+                    if backlog.policy_for(&message.conversation_id) == BackpressurePolicy::Coalesce
+                        && Self::can_batch(&message)
+                        && backpressure::is_behind(pending + 1, &backlog)
+                    {
+                        self.coalesce_backlog(message).await;
+                        continue;
+                    }
                     let config = self.deps.runtime_config.coalesce.load();
                     if self.should_coalesce(&message, &config) {
                         self.coalesce_buffer.push(message);
@@ -270,6 +491,8 @@ impl Channel {
     ///
     /// Returns false for:
     /// - System re-trigger messages (always process immediately)
+    /// - Passive messages (they only add context)
+    /// - Reaction controls (they act on a reply that's already been sent)
     /// - Messages when coalescing is disabled
     /// - Messages in DMs when multi_user_only is true
     fn should_coalesce(
@@ -277,10 +500,7 @@ impl Channel {
         message: &InboundMessage,
         config: &crate::config::CoalesceConfig,
     ) -> bool {
-        if !config.enabled {
-            return false;
-        }
-        if message.source == "system" {
+        if !config.enabled || !Self::can_batch(message) {
             return false;
         }
         if config.multi_user_only && self.is_dm() {
@@ -289,6 +509,46 @@ impl Channel {
         true
     }
 
+    /// Whether a message can share a turn with others. System re-triggers,
+    /// passive messages, and reaction controls are handled on their own.
+    fn can_batch(message: &InboundMessage) -> bool {
+        message.source != "system"
+            && !listening::is_passive(message)
+            && controls::requested(message).is_none()
+    }
+
+    /// Answer everything waiting in the queue in one turn, for a channel
+    /// that's behind under the `coalesce` backpressure policy. Edits are
+    /// applied, and messages that can't be batched are handled on their own,
+    /// in order.
+    async fn coalesce_backlog(&mut self, message: InboundMessage) {
+        self.coalesce_buffer.push(message);
+        let mut coalesced = 0;
+        while let Ok(next) = self.message_rx.try_recv() {
+            if next.metadata.contains_key(MESSAGE_EDIT_KEY) {
+                self.handle_edit(next).await;
+            } else if Self::can_batch(&next) {
+                self.coalesce_buffer.push(next);
+                coalesced += 1;
+            } else {
+                if let Err(error) = self.flush_coalesce_buffer().await {
+                    tracing::error!(%error, channel_id = %self.id, "error flushing coalesce buffer");
+                }
+                if let Err(error) = self.handle_message(next).await {
+                    tracing::error!(%error, channel_id = %self.id, "error handling message");
+                }
+            }
+        }
+        if coalesced > 0 {
+            tracing::info!(channel_id = %self.id, coalesced, "channel behind, batching waiting messages");
+            backpressure::record_shed(&self.deps.agent_id, "coalesced", coalesced);
+        }
+        backpressure::record_depth(&self.deps.agent_id, &self.id, 0);
+        if let Err(error) = self.flush_coalesce_buffer().await {
+            tracing::error!(%error, channel_id = %self.id, "error flushing coalesce buffer");
+        }
+    }
+
     /// Check if this is a DM (direct message) conversation based on conversation_id.
     fn is_dm(&self) -> bool {
         // Check conversation_id pattern for DM indicators
@@ -365,9 +625,18 @@ impl Channel {
     /// Formats all messages with attribution and timestamps, persists each
     /// individually to conversation history, then presents them as one user turn
     /// with a coalesce hint telling the LLM this is a fast-moving conversation.
-    #[tracing::instrument(skip(self, messages), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_count = messages.len()))]
-    async fn handle_message_batch(&mut self, messages: Vec<InboundMessage>) -> Result<()> {
+    #[tracing::instrument(skip(self, messages), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_count = messages.len(), turn_id))]
+    async fn handle_message_batch(&mut self, mut messages: Vec<InboundMessage>) -> Result<()> {
         let message_count = messages.len();
+        // Each message keeps its own turn id in the log; the combined turn
+        // takes the latest one's.
+        let turn_id = messages
+            .iter_mut()
+            .map(InboundMessage::assign_turn_id)
+            .last()
+            .unwrap_or_default();
+        tracing::Span::current().record("turn_id", turn_id.as_str());
+        let stream_tokens = messages.iter().any(token_stream::requested);
         let first_timestamp = messages
             .first()
             .map(|m| m.timestamp)
@@ -397,6 +666,14 @@ impl Channel {
                 self.conversation_id = Some(first.conversation_id.clone());
             }
         }
+        for message in &messages {
+            self.track_guild_persona(message);
+            self.track_verbosity(message);
+            self.track_private_memory(message);
+            self.track_forum_tags(message);
+            self.moderation_target = ModerationTarget::from_message(message);
+        }
+        self.track_allow_workers(&messages);
 
         // Capture conversation context from the first message
         if self.conversation_context.is_none() {
@@ -424,7 +701,12 @@ impl Channel {
                     });
                 self.conversation_context = Some(
                     prompt_engine
-                        .render_conversation_context(&first.source, server_name, channel_name)
+                        .render_conversation_context(
+                            &first.source,
+                            server_name,
+                            channel_name,
+                            &custom_emoji_names(first),
+                        )
                         .expect("failed to render conversation context"),
                 );
             }
@@ -434,14 +716,8 @@ impl Channel {
         let mut user_contents: Vec<UserContent> = Vec::new();
         let mut conversation_id = String::new();
 
-        for message in &messages {
+        for message in &mut messages {
             if message.source != "system" {
-                let sender_name = message
-                    .metadata
-                    .get("sender_display_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(&message.sender_id);
-
                 let (raw_text, attachments) = match &message.content {
                     crate::MessageContent::Text(text) => (text.clone(), Vec::new()),
                     crate::MessageContent::Media { text, attachments } => {
@@ -453,6 +729,28 @@ impl Channel {
                     }
                 };
 
+                // Download attachments for this message. Images are described
+                // before the message is persisted, so the log keeps what was
+                // shown.
+                let mut attachment_content = if !attachments.is_empty() {
+                    download_attachments(&self.deps, &attachments).await
+                } else {
+                    Vec::new()
+                };
+                if let Some(description) =
+                    vision::describe_images(&self.deps, &attachment_content).await
+                {
+                    attachment_content.push(UserContent::text(vision::history_text(&description)));
+                    message
+                        .metadata
+                        .insert(vision::IMAGE_DESCRIPTION_KEY.into(), description.into());
+                }
+
+                let sender_name = message
+                    .metadata
+                    .get("sender_display_name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&message.sender_id);
                 self.state.conversation_logger.log_user_message(
                     &self.state.channel_id,
                     sender_name,
@@ -485,17 +783,13 @@ impl Channel {
                     .and_then(|v| v.as_str())
                     .unwrap_or(&message.sender_id);
 
-                let formatted_text =
-                    format!("[{}] ({}): {}", display_name, relative_text, raw_text);
-
-                // Download attachments for this message
-                if !attachments.is_empty() {
-                    let attachment_content = download_attachments(&self.deps, &attachments).await;
-                    for content in attachment_content {
-                        user_contents.push(content);
-                    }
-                }
+                let prefix = format!("[{}] ({}): ", display_name, relative_text);
+                let formatted_text = self
+                    .screen_inbound(message, &raw_text, format!("{prefix}{raw_text}"))
+                    .await;
+                self.remember_message(message, &raw_text, prefix, &formatted_text);
 
+                user_contents.extend(attachment_content);
                 user_contents.push(UserContent::text(formatted_text));
             }
         }
@@ -514,25 +808,62 @@ impl Channel {
                 .join("\n")
         );
 
+        let combined_text = self.with_passive_context(combined_text);
+
+        let priority = messages
+            .iter()
+            .map(TaskPriority::of)
+            .max()
+            .unwrap_or(TaskPriority::Normal);
+        let bypass_queue = messages.iter().any(task_queue::bypasses_queue);
+        let Some(turn) = self.acquire_turn(priority, bypass_queue).await else {
+            return Ok(());
+        };
+        let presence = messages.last().and_then(|last| self.report_presence(last));
+
         // Build system prompt with coalesce hint
-        let system_prompt = self
-            .build_system_prompt_with_coalesce(message_count, elapsed_secs, unique_sender_count)
+        let (system_prompt, context_plan) = self
+            .assemble_context(
+                &combined_text,
+                Some((message_count, elapsed_secs, unique_sender_count)),
+            )
             .await;
 
         // Run agent turn
-        let (result, skip_flag) = self
+        self.state.turn_canceller.answering(
+            messages
+                .iter()
+                .map(|message| message.sender_id.clone())
+                .collect(),
+        );
+        let (result, skip_flag, reasoning) = self
             .run_agent_turn(
                 &combined_text,
                 &system_prompt,
+                &context_plan,
                 &conversation_id,
+                &turn_id,
                 Vec::new(), // Attachments already formatted into text
+                stream_tokens,
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag).await;
+        let fallback_message_id = self.handle_agent_result(result, &skip_flag, &turn_id).await;
+        if let Some(reasoning) = reasoning {
+            reasoning.persist(
+                &self.deps.sqlite_pool,
+                self.id.as_ref(),
+                fallback_message_id,
+            );
+        }
+        drop((turn, presence));
 
         // Check compaction
-        if let Err(error) = self.compactor.check_and_compact().await {
+        if let Err(error) = self
+            .compactor
+            .check_and_compact(context_plan.report.system_tokens)
+            .await
+        {
             tracing::warn!(channel_id = %self.id, %error, "compaction check failed");
         }
 
@@ -543,64 +874,16 @@ impl Channel {
         Ok(())
     }
 
-    /// Build system prompt with coalesce hint for batched messages.
-    async fn build_system_prompt_with_coalesce(
-        &self,
-        message_count: usize,
-        elapsed_secs: f64,
-        unique_senders: usize,
-    ) -> String {
-        let rc = &self.deps.runtime_config;
-        let prompt_engine = rc.prompts.load();
-
-        let identity_context = rc.identity.load().render();
-        let memory_bulletin = rc.memory_bulletin.load();
-        let skills = rc.skills.load();
-        let skills_prompt = skills.render_channel_prompt(&prompt_engine);
-
-        let browser_enabled = rc.browser_config.load().enabled;
-        let web_search_enabled = rc.brave_search_key.load().is_some();
-        let opencode_enabled = rc.opencode.load().enabled;
-        let worker_capabilities = prompt_engine
-            .render_worker_capabilities(browser_enabled, web_search_enabled, opencode_enabled)
-            .expect("failed to render worker capabilities");
-
-        let status_text = {
-            let status = self.state.status_block.read().await;
-            status.render()
-        };
-
-        // Render coalesce hint
-        let elapsed_str = format!("{:.1}s", elapsed_secs);
-        let coalesce_hint = prompt_engine
-            .render_coalesce_hint(message_count, &elapsed_str, unique_senders)
-            .ok();
-
-        let available_channels = self.build_available_channels().await;
-
-        let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
-
-        prompt_engine
-            .render_channel_prompt(
-                empty_to_none(identity_context),
-                empty_to_none(memory_bulletin.to_string()),
-                empty_to_none(skills_prompt),
-                worker_capabilities,
-                self.conversation_context.clone(),
-                empty_to_none(status_text),
-                coalesce_hint,
-                available_channels,
-            )
-            .expect("failed to render channel prompt")
-    }
-
     /// Handle an incoming message by running the channel's LLM agent loop.
     ///
     /// The LLM decides which tools to call: reply (to respond), branch (to think),
     /// spawn_worker (to delegate), route (to follow up with a worker), cancel, or
     /// memory_save. The tools act on the channel's shared state directly.
-    #[tracing::instrument(skip(self, message), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_id = %message.id))]
-    async fn handle_message(&mut self, message: InboundMessage) -> Result<()> {
+    #[tracing::instrument(skip(self, message), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_id = %message.id, turn_id))]
+    async fn handle_message(&mut self, mut message: InboundMessage) -> Result<()> {
+        // Messages synthesized inside the process get their turn id here.
+        let turn_id = message.assign_turn_id();
+        tracing::Span::current().record("turn_id", turn_id.as_str());
         tracing::info!(
             channel_id = %self.id,
             message_id = %message.id,
@@ -611,6 +894,21 @@ impl Channel {
         if self.conversation_id.is_none() {
             self.conversation_id = Some(message.conversation_id.clone());
         }
+        self.track_guild_persona(&message);
+        self.track_verbosity(&message);
+        self.track_private_memory(&message);
+        self.track_forum_tags(&message);
+        self.moderation_target = ModerationTarget::from_message(&message);
+        self.track_allow_workers(std::slice::from_ref(&message));
+
+        // Reaction controls are recorded as feedback. A delete has already
+        // been carried out by the adapter, so it doesn't start a turn.
+        if let Some(control) = controls::requested(&message) {
+            controls::record(&self.deps.sqlite_pool, self.id.as_ref(), &message, control);
+            if control == ReactionControl::Delete {
+                return Ok(());
+            }
+        }
 
         let (raw_text, attachments) = match &message.content {
             crate::MessageContent::Text(text) => (text.clone(), Vec::new()),
@@ -621,13 +919,28 @@ impl Channel {
             crate::MessageContent::Interaction { .. } => (message.content.to_string(), Vec::new()),
         };
 
-        let user_text = format_user_message(&raw_text, &message);
+        let user_text = self
+            .screen_inbound(
+                &message,
+                &raw_text,
+                format_user_message(&raw_text, &message),
+            )
+            .await;
 
-        let attachment_content = if !attachments.is_empty() {
+        let passive = listening::is_passive(&message);
+        let mut attachment_content = if !attachments.is_empty() && !passive {
             download_attachments(&self.deps, &attachments).await
         } else {
             Vec::new()
         };
+        // Describe images before the message is persisted, so the log and
+        // later non-vision turns keep what was shown.
+        if let Some(description) = vision::describe_images(&self.deps, &attachment_content).await {
+            attachment_content.push(UserContent::text(vision::history_text(&description)));
+            message
+                .metadata
+                .insert(vision::IMAGE_DESCRIPTION_KEY.into(), description.into());
+        }
 
         // Persist user messages (skip system re-triggers)
         if message.source != "system" {
@@ -646,6 +959,13 @@ impl Channel {
             self.state
                 .channel_store
                 .upsert(&message.conversation_id, &message.metadata);
+
+            let formatted = format_user_message(&raw_text, &message);
+            let prefix = formatted
+                .strip_suffix(raw_text.as_str())
+                .unwrap_or_default()
+                .to_string();
+            self.remember_message(&message, &raw_text, prefix, &user_text);
         }
 
         // Capture conversation context from the first message (platform, channel, server)
@@ -673,26 +993,65 @@ impl Channel {
                 });
             self.conversation_context = Some(
                 prompt_engine
-                    .render_conversation_context(&message.source, server_name, channel_name)
+                    .render_conversation_context(
+                        &message.source,
+                        server_name,
+                        channel_name,
+                        &custom_emoji_names(&message),
+                    )
                     .expect("failed to render conversation context"),
             );
         }
 
-        let system_prompt = self.build_system_prompt().await;
+        if passive {
+            if self.passive_context.len() >= MAX_PASSIVE_CONTEXT {
+                self.passive_context.remove(0);
+            }
+            self.passive_context.push(user_text);
+            return Ok(());
+        }
+        let user_text = self.with_passive_context(user_text);
+
+        let priority = TaskPriority::of(&message);
+        let bypass_queue = task_queue::bypasses_queue(&message);
+        let Some(turn) = self.acquire_turn(priority, bypass_queue).await else {
+            return Ok(());
+        };
+        let presence = self.report_presence(&message);
 
-        let (result, skip_flag) = self
+        let (system_prompt, context_plan) = self.assemble_context(&user_text, None).await;
+
+        self.state
+            .turn_canceller
+            .answering(vec![message.sender_id.clone()]);
+        let (result, skip_flag, reasoning) = self
             .run_agent_turn(
                 &user_text,
                 &system_prompt,
+                &context_plan,
                 &message.conversation_id,
+                &turn_id,
                 attachment_content,
+                token_stream::requested(&message),
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag).await;
+        let fallback_message_id = self.handle_agent_result(result, &skip_flag, &turn_id).await;
+        if let Some(reasoning) = reasoning {
+            reasoning.persist(
+                &self.deps.sqlite_pool,
+                self.id.as_ref(),
+                fallback_message_id,
+            );
+        }
+        drop((turn, presence));
 
         // Check context size and trigger compaction if needed
-        if let Err(error) = self.compactor.check_and_compact().await {
+        if let Err(error) = self
+            .compactor
+            .check_and_compact(context_plan.report.system_tokens)
+            .await
+        {
             tracing::warn!(channel_id = %self.id, %error, "compaction check failed");
         }
 
@@ -705,6 +1064,194 @@ impl Channel {
         Ok(())
     }
 
+    /// Keep a user message so a later edit to it can be applied.
+    fn remember_message(
+        &mut self,
+        message: &InboundMessage,
+        raw_text: &str,
+        prefix: String,
+        history_text: &str,
+    ) {
+        if self.recent_messages.len() >= MAX_EDITABLE_MESSAGES {
+            self.recent_messages.pop_front();
+        }
+        self.recent_messages.push_back(RecentMessage {
+            id: message.id.clone(),
+            raw_text: raw_text.to_string(),
+            prefix,
+            history_text: history_text.to_string(),
+        });
+    }
+
+    /// Apply an edit to a message this channel has seen.
+    ///
+    /// A message still waiting in the coalesce buffer is answered as edited.
+    /// An earlier one is updated in the conversation log, in the history, and
+    /// in the passive context, without starting a turn.
+    async fn handle_edit(&mut self, message: InboundMessage) {
+        if let Some(buffered) = self
+            .coalesce_buffer
+            .iter_mut()
+            .find(|buffered| buffered.id == message.id)
+        {
+            buffered.content = message.content;
+            return;
+        }
+
+        let Some(index) = self
+            .recent_messages
+            .iter()
+            .rposition(|recent| recent.id == message.id)
+        else {
+            tracing::debug!(channel_id = %self.id, message_id = %message.id, "ignoring edit to unknown message");
+            return;
+        };
+
+        let new_raw = match &message.content {
+            crate::MessageContent::Text(text) => text.clone(),
+            crate::MessageContent::Media { text, .. } => text.clone().unwrap_or_default(),
+            crate::MessageContent::Interaction { .. } => return,
+        };
+        if new_raw == self.recent_messages[index].raw_text {
+            return;
+        }
+
+        let recent = &self.recent_messages[index];
+        self.state.conversation_logger.edit_user_message(
+            &self.state.channel_id,
+            &recent.id,
+            &new_raw,
+        );
+
+        let new_history = self
+            .screen_inbound(
+                &message,
+                &new_raw,
+                format!("{}{new_raw} (edited)", recent.prefix),
+            )
+            .await;
+        // The same sender can send the same text twice, so the edit goes to
+        // the occurrence that lines up with this message: the newer messages
+        // reading the same appear after it, newest last.
+        let old_history = &self.recent_messages[index].history_text;
+        let mut newer_duplicates = self
+            .recent_messages
+            .iter()
+            .skip(index + 1)
+            .filter(|recent| recent.history_text == *old_history)
+            .count();
+        // Messages heard since the last turn aren't in history yet and are
+        // the newest of all.
+        let mut applied = false;
+        for heard in self.passive_context.iter_mut().rev() {
+            if heard == old_history {
+                if newer_duplicates == 0 {
+                    heard.clone_from(&new_history);
+                    applied = true;
+                    break;
+                }
+                newer_duplicates -= 1;
+            }
+        }
+        if !applied {
+            let mut history = self.state.history.write().await;
+            replace_in_history(&mut history, old_history, &new_history, newer_duplicates);
+        }
+
+        tracing::info!(channel_id = %self.id, message_id = %message.id, "applied message edit");
+        let recent = &mut self.recent_messages[index];
+        recent.raw_text = new_raw;
+        recent.history_text = new_history;
+    }
+
+    /// Prepend the messages heard since the last turn, if any.
+    fn with_passive_context(&mut self, user_text: String) -> String {
+        if self.passive_context.is_empty() {
+            return user_text;
+        }
+        let heard = std::mem::take(&mut self.passive_context);
+        format!(
+            "[{} earlier messages in this channel, which didn't call for a reply]\n{}\n\n{}",
+            heard.len(),
+            heard.join("\n"),
+            user_text
+        )
+    }
+
+    /// Show the turn in the bot's presence, e.g. "replying in #general",
+    /// until the guard is dropped.
+    fn report_presence(&self, message: &InboundMessage) -> Option<PresenceGuard> {
+        let messaging_manager = self.deps.messaging_manager.as_ref()?;
+        let label = match message
+            .metadata
+            .get("discord_channel_name")
+            .and_then(|v| v.as_str())
+        {
+            Some(name) => format!("replying in #{name}"),
+            None if message.source == "cron" => "running a scheduled task".to_string(),
+            None => "replying".to_string(),
+        };
+        Some(messaging_manager.presence().begin(label))
+    }
+
+    /// Wait for the agent's task queue to admit a turn, unless the trigger
+    /// bypasses it, then for any config reload in progress to apply. None
+    /// when the queue dropped the trigger; the message is already in history,
+    /// so the next turn still sees it.
+    async fn acquire_turn(
+        &self,
+        priority: TaskPriority,
+        bypass_queue: bool,
+    ) -> Option<(TaskPermit, TurnGuard)> {
+        let permit = if bypass_queue {
+            TaskPermit::unqueued()
+        } else {
+            match self.deps.runtime_config.task_queue.acquire(priority).await {
+                Ok(permit) => permit,
+                Err(error) => {
+                    tracing::warn!(channel_id = %self.id, %error, "turn skipped");
+                    return None;
+                }
+            }
+        };
+        let reload_guard = self.deps.runtime_config.reload_gate.enter().await;
+        Some((permit, reload_guard))
+    }
+
+    /// Screen an inbound message for prompt injection. A flagged message is
+    /// reported to the cortex and its context text wrapped as untrusted; the
+    /// wrapper stays in history so later turns see the same warning.
+    async fn screen_inbound(
+        &self,
+        message: &InboundMessage,
+        raw_text: &str,
+        text: String,
+    ) -> String {
+        if message.source == "system" {
+            return text;
+        }
+        let reasons = crate::agent::injection::screen(&self.deps, raw_text).await;
+        if reasons.is_empty() {
+            return text;
+        }
+
+        crate::agent::injection::report(
+            &self.deps,
+            &self.state.channel_id,
+            &message.sender_id,
+            raw_text,
+            &reasons,
+        );
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        match prompt_engine.render_system_untrusted_message(&text, &reasons) {
+            Ok(wrapped) => wrapped,
+            Err(error) => {
+                tracing::warn!(%error, "failed to render untrusted message marker");
+                text
+            }
+        }
+    }
+
     /// Build the rendered available channels fragment for cross-channel awareness.
     async fn build_available_channels(&self) -> Option<String> {
         if self.deps.messaging_manager.is_none() {
@@ -742,13 +1289,106 @@ impl Channel {
         prompt_engine.render_available_channels(entries).ok()
     }
 
-    /// Assemble the full system prompt using the PromptEngine.
-    async fn build_system_prompt(&self) -> String {
-        let rc = &self.deps.runtime_config;
-        let prompt_engine = rc.prompts.load();
+    /// Build the rendered peer agents fragment from the capability registry.
+    fn build_peer_agents(&self) -> Option<String> {
+        let peers = self.deps.runtime_config.peers.load();
+        if peers.is_empty() {
+            return None;
+        }
 
-        let identity_context = rc.identity.load().render();
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        prompt_engine.render_peer_agents(&peers).ok()
+    }
+
+    /// Render the operator's curated example exchanges for the system prompt.
+    async fn build_few_shot_examples(&self) -> Option<String> {
+        let store = ExampleStore::new(self.deps.sqlite_pool.clone())
+            .with_readers(self.deps.sqlite_read.clone());
+        let examples = match store.load_for_prompt().await {
+            Ok(examples) => examples,
+            Err(error) => {
+                tracing::warn!(%error, "failed to load few-shot examples for system prompt");
+                return None;
+            }
+        };
+        if examples.is_empty() {
+            return None;
+        }
+
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        prompt_engine.render_few_shot_examples(&examples).ok()
+    }
+
+    /// Render the system prompt and plan which history and memories fit in
+    /// the channel model's context window.
+    ///
+    /// The prompt is rendered without the memory bulletin first so the plan
+    /// can weigh the bulletin against history; it's rendered again with the
+    /// bulletin only if the plan keeps it.
+    #[tracing::instrument(skip(self, user_text, coalesce), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
+    async fn assemble_context(
+        &self,
+        user_text: &str,
+        coalesce: Option<(usize, f64, usize)>,
+    ) -> (String, ContextPlan) {
+        let start = std::time::Instant::now();
+        let rc = &self.deps.runtime_config;
+        let model_name = rc
+            .routing
+            .load()
+            .resolve(ProcessType::Channel, None)
+            .to_string();
+        let context_window = self
+            .deps
+            .llm_manager
+            .context_window(&model_name, **rc.context_window.load());
         let memory_bulletin = rc.memory_bulletin.load();
+
+        let prompt = self.render_system_prompt(coalesce).await;
+        let base_prompt = prompt.render(None);
+        let history = self.state.history.read().await.clone();
+        let plan = ContextManager::new(context_window).plan(
+            &base_prompt,
+            user_text,
+            &memory_bulletin,
+            history,
+        );
+
+        if plan.report.is_trimmed() {
+            let report = &plan.report;
+            tracing::info!(
+                channel_id = %self.id,
+                model = %model_name,
+                context_window = report.context_window,
+                used_tokens = report.used_tokens,
+                dropped_messages = report.dropped_messages,
+                dropped_summaries = report.dropped_summaries,
+                dropped_memories = report.dropped_memories,
+                dropped_tokens = report.dropped_tokens,
+                "trimmed context to fit model window"
+            );
+        }
+
+        let system_prompt = if plan.include_memories && !memory_bulletin.is_empty() {
+            prompt.render(Some(memory_bulletin.to_string()))
+        } else {
+            base_prompt
+        };
+
+        let agent = opentelemetry::KeyValue::new("agent_id", self.deps.agent_id.to_string());
+        crate::otel::Metrics::global()
+            .context_assembly_duration
+            .record(start.elapsed().as_secs_f64(), &[agent]);
+
+        (system_prompt, plan)
+    }
+
+    /// Gather what goes into the system prompt. `coalesce` adds the hint for
+    /// batched messages: how many, over how long, and from how many senders.
+    async fn render_system_prompt(&self, coalesce: Option<(usize, f64, usize)>) -> SystemPrompt {
+        let rc = &self.deps.runtime_config;
+        let prompt_engine = rc.prompts.load_full();
+
         let skills = rc.skills.load();
         let skills_prompt = skills.render_channel_prompt(&prompt_engine);
 
@@ -759,44 +1399,162 @@ impl Channel {
             .render_worker_capabilities(browser_enabled, web_search_enabled, opencode_enabled)
             .expect("failed to render worker capabilities");
 
-        let status_text = {
-            let status = self.state.status_block.read().await;
-            status.render()
-        };
-
-        let available_channels = self.build_available_channels().await;
+        let coalesce_hint = coalesce.and_then(|(message_count, elapsed_secs, unique_senders)| {
+            let elapsed_str = format!("{:.1}s", elapsed_secs);
+            prompt_engine
+                .render_coalesce_hint(message_count, &elapsed_str, unique_senders)
+                .ok()
+        });
 
         let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
 
-        prompt_engine
-            .render_channel_prompt(
-                empty_to_none(identity_context),
-                empty_to_none(memory_bulletin.to_string()),
-                empty_to_none(skills_prompt),
-                worker_capabilities,
-                self.conversation_context.clone(),
-                empty_to_none(status_text),
-                None, // coalesce_hint - only set for batched messages
-                available_channels,
-            )
-            .expect("failed to render channel prompt")
+        SystemPrompt {
+            identity_context: empty_to_none(self.identity_context()),
+            skills_prompt: empty_to_none(skills_prompt),
+            worker_capabilities,
+            conversation_context: self.conversation_context.clone(),
+            status_text: empty_to_none(self.status_text().await),
+            coalesce_hint,
+            available_channels: self.build_available_channels().await,
+            peer_agents: self.build_peer_agents(),
+            few_shot_examples: self.build_few_shot_examples().await,
+            prompt_engine,
+        }
+    }
+
+    /// Keep the guild persona current. Only guild messages carry it, so
+    /// synthetic re-triggers leave it as it was.
+    fn track_guild_persona(&mut self, message: &InboundMessage) {
+        if message.metadata.contains_key("discord_guild_id") {
+            self.guild_persona = message
+                .metadata
+                .get(GUILD_PERSONA_KEY)
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+        }
+    }
+
+    /// Keep the reply length current. Synthetic re-triggers don't carry it,
+    /// so they leave it as it was.
+    fn track_verbosity(&mut self, message: &InboundMessage) {
+        if message.source != "system" {
+            self.verbosity = message
+                .metadata
+                .get(VERBOSITY_KEY)
+                .and_then(|value| serde_json::from_value(value.clone()).ok());
+        }
+    }
+
+    /// Follow whether the senders may start workers. A batch needs every
+    /// sender to be allowed. Synthetic re-triggers don't carry it, so they
+    /// leave it as it was.
+    fn track_allow_workers(&mut self, messages: &[InboundMessage]) {
+        let mut senders = messages
+            .iter()
+            .filter(|message| message.source != "system")
+            .peekable();
+        if senders.peek().is_some() {
+            self.allow_workers = senders.all(|message| {
+                message
+                    .metadata
+                    .get(EXPENSIVE_TOOLS_KEY)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true)
+            });
+        }
+    }
+
+    /// Follow the platform's private memory setting for DMs. Synthetic
+    /// re-triggers don't carry it, so they leave it as it was.
+    fn track_private_memory(&self, message: &InboundMessage) {
+        if message.source != "system" && message.conversation_id.contains(":dm:") {
+            let private = message
+                .metadata
+                .get(PRIVATE_MEMORY_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.state.private_memory.store(private, Ordering::Relaxed);
+        }
+    }
+
+    /// Follow the tags the agent may apply to this forum post. Synthetic
+    /// re-triggers and reaction controls don't carry them, so they leave them
+    /// as they were.
+    fn track_forum_tags(&mut self, message: &InboundMessage) {
+        if message.source != "system" && controls::requested(message).is_none() {
+            self.forum_tags = forums::available_tags(message);
+        }
+    }
+
+    /// The agent's identity files, plus the guild's persona note and the
+    /// reply length set for the conversation.
+    fn identity_context(&self) -> String {
+        let mut context = self.deps.runtime_config.identity.load().render();
+        if let Some(persona) = &self.guild_persona {
+            context.push_str(&format!("\n\n## In this server\n\n{persona}"));
+        }
+        if let Some(verbosity) = self.verbosity {
+            context.push_str(&format!("\n\n## Reply length\n\n{}", verbosity.note()));
+        }
+        context.trim_start().to_string()
+    }
+
+    /// The status block, plus a note when most recent reactions to replies
+    /// in this channel were negative.
+    async fn status_text(&self) -> String {
+        let status = self.state.status_block.read().await.render();
+        let config = **self.deps.runtime_config.sentiment.load();
+        if !config.feedback {
+            return status;
+        }
+        let tally = match crate::agent::sentiment::channel_tally(
+            &self.deps.sqlite_pool,
+            &self.id,
+            config.window_hours,
+        )
+        .await
+        {
+            Ok(tally) => tally,
+            Err(error) => {
+                tracing::warn!(%error, channel_id = %self.id, "failed to load reaction sentiment");
+                return status;
+            }
+        };
+        match crate::agent::sentiment::feedback_note(&config, tally) {
+            Some(note) => format!("{status}\n\n{note}").trim_start().to_string(),
+            None => status,
+        }
     }
 
     /// Register per-turn tools, run the LLM agentic loop, and clean up.
     ///
     /// Returns the prompt result and skip flag for the caller to dispatch.
-    #[tracing::instrument(skip(self, user_text, system_prompt, attachment_content), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
+    #[tracing::instrument(skip(self, user_text, system_prompt, context_plan, attachment_content), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
     async fn run_agent_turn(
         &self,
         user_text: &str,
         system_prompt: &str,
+        context_plan: &ContextPlan,
         conversation_id: &str,
+        turn_id: &str,
         attachment_content: Vec<UserContent>,
+        stream_tokens: bool,
     ) -> Result<(
         std::result::Result<String, rig::completion::PromptError>,
         crate::tools::SkipFlag,
+        Option<ReasoningTrace>,
     )> {
         let skip_flag = crate::tools::new_skip_flag();
+        let reply_message_id = crate::tools::new_reply_message_id();
+
+        let reflection_enabled = self.deps.runtime_config.reflection.load().enabled;
+        let reflector = reflection_enabled.then(|| {
+            crate::agent::reflection::Reflector::new(
+                self.deps.clone(),
+                self.id.clone(),
+                self.conversation_context.clone(),
+            )
+        });
 
         if let Err(error) = crate::tools::add_channel_tools(
             &self.tool_server,
@@ -804,7 +1562,13 @@ impl Channel {
             self.response_tx.clone(),
             conversation_id,
             skip_flag.clone(),
+            reply_message_id.clone(),
+            Some(turn_id.to_string()),
             self.deps.cron_tool.clone(),
+            reflector,
+            self.moderation_target.clone(),
+            self.allow_workers,
+            self.forum_tags.clone(),
         )
         .await
         {
@@ -815,9 +1579,40 @@ impl Channel {
         let rc = &self.deps.runtime_config;
         let routing = rc.routing.load();
         let max_turns = **rc.max_turns.load();
-        let model_name = routing.resolve(ProcessType::Channel, None);
-        let model = SpacebotModel::make(&self.deps.llm_manager, model_name)
+        let routed_model = routing.resolve(ProcessType::Channel, None);
+        let experiment = crate::agent::experiment::assign(&rc.experiment.load(), routed_model);
+        let model_name = experiment
+            .as_ref()
+            .map_or(routed_model, |assignment| assignment.model.as_str());
+        let mut model = SpacebotModel::make(&self.deps.llm_manager, model_name)
+            .with_agent(&self.deps.agent_id)
+            .with_turn_id(Some(turn_id.to_string()))
             .with_routing((**routing).clone());
+        let ensemble_config = rc.ensemble.load();
+        let ensemble = ensemble_config.applies_to(conversation_id).then(|| {
+            Arc::new(Ensemble::new(
+                &ensemble_config,
+                (**rc.prompts.load()).clone(),
+            ))
+        });
+        if let Some(ensemble) = &ensemble {
+            model = model.with_ensemble(ensemble.clone());
+        }
+        // A streamed draft can't be taken back, so there's none when
+        // reflection may revise the reply or the guardrails may block it.
+        let stream_tokens = stream_tokens
+            && !reflection_enabled
+            && self
+                .deps
+                .messaging_manager
+                .as_ref()
+                .is_none_or(|manager| manager.allows_token_streaming());
+        // The forwarder ends once the agent, and with it the sink, is dropped.
+        if stream_tokens {
+            let (sink, deltas) = mpsc::unbounded_channel();
+            model = model.with_token_sink(sink);
+            token_stream::spawn_forwarder(deltas, self.response_tx.clone());
+        }
 
         let agent = AgentBuilder::new(model)
             .preamble(system_prompt)
@@ -830,64 +1625,153 @@ impl Channel {
             .send(OutboundResponse::Status(crate::StatusUpdate::Thinking))
             .await;
 
+        // Work on a copy so the write lock is released before the agentic loop.
+        // The branch tool needs a read lock on history to clone it for the branch,
+        // and holding a write lock across the entire agentic loop would deadlock.
+        // Only the part of history that fits the context window is sent, taken
+        // from the same snapshot the plan measured.
+        let mut history = context_plan.history().to_vec();
+
         // Inject attachments as a user message before the text prompt
         if !attachment_content.is_empty() {
-            let mut history = self.state.history.write().await;
             let content = OneOrMany::many(attachment_content).unwrap_or_else(|_| {
                 OneOrMany::one(UserContent::text("[attachment processing failed]"))
             });
-            history.push(rig::message::Message::User { content });
-            drop(history);
+            let message = rig::message::Message::User { content };
+            self.state.history.write().await.push(message.clone());
+            history.push(message);
         }
+        let sent_len = history.len();
+
+        // One tool loop spans the whole turn, including the correction retry,
+        // so the wall-clock limit and the trace cover every LLM call.
+        let tool_loop_config = **rc.tool_loop.load();
+        let tool_loop = ToolLoop::new(&tool_loop_config);
+        let hook = self
+            .hook
+            .clone()
+            .with_tool_loop(tool_loop.clone())
+            .with_typing(self.response_tx.clone())
+            .with_turn_id(Some(turn_id.to_string()));
+
+        let cancel_rx = self.state.turn_canceller.arm();
+        let turn = async {
+            let mut result = agent
+                .prompt(user_text)
+                .with_history(&mut history)
+                .with_hook(hook.clone())
+                .await;
+
+            // If the LLM responded with text that looks like tool call syntax, it failed
+            // to use the tool calling API. Inject a correction and give it one more try.
+            if let Ok(ref response) = result {
+                if extract_reply_from_tool_syntax(response.trim()).is_some() {
+                    tracing::warn!(channel_id = %self.id, "LLM emitted tool syntax as text, retrying with correction");
+                    let prompt_engine = self.deps.runtime_config.prompts.load();
+                    let correction = prompt_engine.render_system_tool_syntax_correction()?;
+                    result = agent
+                        .prompt(&correction)
+                        .with_history(&mut history)
+                        .with_hook(hook.clone())
+                        .await;
+                }
+            }
 
-        // Clone history out so the write lock is released before the agentic loop.
-        // The branch tool needs a read lock on history to clone it for the branch,
-        // and holding a write lock across the entire agentic loop would deadlock.
-        let mut history = {
-            let guard = self.state.history.read().await;
-            guard.clone()
+            Ok::<_, crate::error::Error>(result)
         };
-
-        let mut result = agent
-            .prompt(user_text)
-            .with_history(&mut history)
-            .with_hook(self.hook.clone())
-            .await;
-
-        // If the LLM responded with text that looks like tool call syntax, it failed
-        // to use the tool calling API. Inject a correction and give it one more try.
-        if let Ok(ref response) = result {
-            if extract_reply_from_tool_syntax(response.trim()).is_some() {
-                tracing::warn!(channel_id = %self.id, "LLM emitted tool syntax as text, retrying with correction");
-                let prompt_engine = self.deps.runtime_config.prompts.load();
-                let correction = prompt_engine.render_system_tool_syntax_correction()?;
-                result = agent
-                    .prompt(&correction)
-                    .with_history(&mut history)
-                    .with_hook(self.hook.clone())
-                    .await;
+        let outcome = tokio::select! {
+            result = turn => Ok(result),
+            Ok(reason) = cancel_rx => Err(reason),
+        };
+        self.state.turn_canceller.disarm();
+
+        let result = match outcome {
+            Ok(result) => result?,
+            Err(reason) => {
+                let replied = reply_message_id
+                    .lock()
+                    .expect("reply message id lock poisoned")
+                    .is_some();
+                if replied {
+                    // Keep what the user saw, so the next turn knows it was said.
+                    drop_unanswered_tool_calls(&mut history, sent_len);
+                } else {
+                    // Nothing went out, so only the user's message is kept.
+                    history.truncate(sent_len);
+                    history.push(rig::message::Message::user(user_text));
+                }
+                // Take down any partially streamed reply.
+                let _ = self.response_tx.send(OutboundResponse::StreamAbort).await;
+                Err(rig::completion::PromptError::PromptCancelled {
+                    chat_history: Box::new(Vec::new()),
+                    reason,
+                })
             }
-        }
+        };
 
-        // Write history back after the agentic loop completes
-        {
-            let mut guard = self.state.history.write().await;
-            *guard = history;
+        let trace = tool_loop.finish(&result);
+        if trace.outcome == ToolLoopOutcome::TimedOut {
+            tracing::warn!(
+                channel_id = %self.id,
+                iterations = trace.iterations,
+                duration_ms = trace.duration_ms,
+                "tool loop hit its time limit"
+            );
+        }
+        crate::agent::stats::record(
+            &self.deps.sqlite_pool,
+            self.id.as_ref(),
+            turn_id,
+            model_name,
+            &trace,
+        );
+        if let Some(assignment) = &experiment {
+            crate::agent::experiment::record(
+                &self.deps.sqlite_pool,
+                self.id.as_ref(),
+                turn_id,
+                assignment,
+                &trace,
+                crate::agent::experiment::response_chars(&history[sent_len..]),
+            );
+        }
+        if let Some(ensemble) = &ensemble {
+            ensemble.persist(&self.deps.sqlite_pool, self.id.as_ref());
         }
+        // Persisted once handle_agent_result has logged any fallback reply.
+        let reasoning = tool_loop_config
+            .trace
+            .then(|| ReasoningTrace::from_tool_trace(&trace, reply_message_id))
+            .flatten();
+        if tool_loop_config.trace && trace.tool_calls > 0 {
+            trace.persist(&self.deps.sqlite_pool, self.id.as_ref(), Some(turn_id));
+        }
+
+        // Append the turn's new messages to the channel's current history
+        // rather than the trimmed copy, keeping any compaction that ran
+        // during the turn.
+        self.state
+            .history
+            .write()
+            .await
+            .extend(history.split_off(sent_len));
 
         if let Err(error) = crate::tools::remove_channel_tools(&self.tool_server).await {
             tracing::warn!(%error, "failed to remove channel tools");
         }
 
-        Ok((result, skip_flag))
+        Ok((result, skip_flag, reasoning))
     }
 
     /// Dispatch the LLM result: send fallback text, log errors, clean up typing.
+    /// Returns the id of the fallback message, if one was sent.
     async fn handle_agent_result(
         &self,
         result: std::result::Result<String, rig::completion::PromptError>,
         skip_flag: &crate::tools::SkipFlag,
-    ) {
+        turn_id: &str,
+    ) -> Option<String> {
+        let mut fallback_message_id = None;
         match result {
             Ok(response) => {
                 let skipped = skip_flag.load(std::sync::atomic::Ordering::Relaxed);
@@ -906,12 +1790,26 @@ impl Channel {
                         if extracted.is_some() {
                             tracing::warn!(channel_id = %self.id, "extracted reply from malformed tool syntax in LLM text output");
                         }
-                        self.state
-                            .conversation_logger
-                            .log_bot_message(&self.state.channel_id, final_text);
+                        // Plain-text replies skip the reply tool, so review them here.
+                        let final_text = if self.deps.runtime_config.reflection.load().enabled {
+                            crate::agent::reflection::Reflector::new(
+                                self.deps.clone(),
+                                self.id.clone(),
+                                self.conversation_context.clone(),
+                            )
+                            .review(final_text)
+                            .await
+                        } else {
+                            final_text.to_string()
+                        };
+                        fallback_message_id = Some(self.state.conversation_logger.log_bot_message(
+                            &self.state.channel_id,
+                            &final_text,
+                            Some(turn_id),
+                        ));
                         if let Err(error) = self
                             .response_tx
-                            .send(OutboundResponse::Text(final_text.to_string()))
+                            .send(OutboundResponse::Text(final_text))
                             .await
                         {
                             tracing::error!(%error, channel_id = %self.id, "failed to send fallback reply");
@@ -929,6 +1827,19 @@ impl Channel {
             }
             Err(error) => {
                 tracing::error!(channel_id = %self.id, %error, "channel LLM call failed");
+                if crate::llm::routing::is_rate_limit_error(&error.to_string())
+                    && let Some(messaging_manager) = &self.deps.messaging_manager
+                {
+                    let cooldown = self
+                        .deps
+                        .runtime_config
+                        .routing
+                        .load()
+                        .rate_limit_cooldown_secs;
+                    messaging_manager
+                        .presence()
+                        .rate_limited(std::time::Duration::from_secs(cooldown));
+                }
             }
         }
 
@@ -937,6 +1848,8 @@ impl Channel {
             .response_tx
             .send(OutboundResponse::Status(crate::StatusUpdate::StopTyping))
             .await;
+
+        fallback_message_id
     }
 
     /// Handle a process event (branch results, worker completions, status updates).
@@ -1181,10 +2094,15 @@ async fn spawn_branch(
         h.clone()
     };
 
+    let private_channel = state
+        .private_memory
+        .load(Ordering::Relaxed)
+        .then(|| state.channel_id.clone());
     let tool_server = crate::tools::create_branch_tool_server(
         state.deps.memory_search.clone(),
         state.conversation_logger.clone(),
         state.channel_store.clone(),
+        private_channel,
     );
     let branch_max_turns = **state.deps.runtime_config.branch_max_turns.load();
 
@@ -1611,6 +2529,80 @@ fn format_user_message(raw_text: &str, message: &InboundMessage) -> String {
     format!("{display_name}{bot_tag}{reply_context}: {raw_text}")
 }
 
+/// Names of the server's custom emojis the agent can write as `:name:`.
+fn custom_emoji_names(message: &InboundMessage) -> Vec<String> {
+    message
+        .metadata
+        .get("discord_custom_emojis")
+        .and_then(|v| v.as_array())
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Replace a user message's text in history, counting from the newest and
+/// passing over `skip` newer occurrences of the same text. Only whole lines
+/// match, so a message isn't found inside a longer one that quotes it.
+fn replace_in_history(
+    history: &mut [rig::message::Message],
+    old: &str,
+    new: &str,
+    mut skip: usize,
+) -> bool {
+    for message in history.iter_mut().rev() {
+        let rig::message::Message::User { content } = message else {
+            continue;
+        };
+        let parts: Vec<_> = content.iter_mut().collect();
+        for part in parts.into_iter().rev() {
+            let UserContent::Text(text) = part else {
+                continue;
+            };
+            let starts: Vec<usize> = text
+                .text
+                .match_indices(old)
+                .map(|(start, _)| start)
+                .filter(|&start| {
+                    let end = start + old.len();
+                    (start == 0 || text.text[..start].ends_with('\n'))
+                        && (end == text.text.len() || text.text[end..].starts_with('\n'))
+                })
+                .collect();
+            for start in starts.into_iter().rev() {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                text.text.replace_range(start..start + old.len(), new);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Drop tool calls at the end of history, past `from`, that never got their
+/// results. A cancelled loop can stop between a call and its result, and
+/// providers reject a call without one.
+fn drop_unanswered_tool_calls(history: &mut Vec<rig::message::Message>, from: usize) {
+    while history.len() > from {
+        let Some(rig::message::Message::Assistant { content, .. }) = history.last() else {
+            break;
+        };
+        if !content
+            .iter()
+            .any(|item| matches!(item, rig::message::AssistantContent::ToolCall(_)))
+        {
+            break;
+        }
+        history.pop();
+    }
+}
+
 /// Check if a ProcessEvent is targeted at a specific channel.
 ///
 /// Events from branches and workers carry a channel_id. We only process events
@@ -1636,6 +2628,14 @@ fn event_is_for_channel(event: &ProcessEvent, channel_id: &ChannelId) -> bool {
     }
 }
 
+/// Passive messages kept for the next turn. Older ones are dropped from the
+/// prompt; they're still in the conversation history.
+const MAX_PASSIVE_CONTEXT: usize = 50;
+
+/// User messages kept so edits to them can be applied. Edits to older ones
+/// are ignored.
+const MAX_EDITABLE_MESSAGES: usize = 100;
+
 /// Image MIME types we support for vision.
 const IMAGE_MIME_PREFIXES: &[&str] = &["image/jpeg", "image/png", "image/gif", "image/webp"];
 
@@ -1827,3 +2827,103 @@ async fn download_text_attachment(
         attachment.filename, attachment.mime_type, truncated
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_turn_canceller_only_fires_while_armed() {
+        let canceller = TurnCanceller::default();
+        assert!(!canceller.cancel("idle"));
+
+        let cancel_rx = canceller.arm();
+        assert!(canceller.cancel("stop"));
+        assert_eq!(cancel_rx.await.ok().as_deref(), Some("stop"));
+        // The first cancel consumed the turn's sender.
+        assert!(!canceller.cancel("again"));
+
+        let _cancel_rx = canceller.arm();
+        canceller.disarm();
+        assert!(!canceller.cancel("after turn"));
+    }
+
+    #[tokio::test]
+    async fn test_only_authors_and_moderators_cancel_a_turn() {
+        let canceller = TurnCanceller::default();
+        canceller.answering(vec!["alice".into()]);
+        let _cancel_rx = canceller.arm();
+        assert!(!canceller.cancel_for("bob", false, "stop"));
+        assert!(canceller.cancel_for("alice", false, "stop"));
+
+        canceller.answering(vec!["alice".into()]);
+        let _cancel_rx = canceller.arm();
+        assert!(canceller.cancel_for("bob", true, "stop"));
+    }
+
+    #[test]
+    fn test_edits_replace_the_matching_occurrence_of_user_text() {
+        let mut history = vec![
+            rig::message::Message::user("alice: lunch at noon"),
+            rig::message::Message::assistant("sounds good"),
+            rig::message::Message::user("bob: hi\nalice: lunch at noon"),
+            rig::message::Message::user("alice: lunch at noon?"),
+        ];
+
+        // The older of two identical messages: one newer occurrence is skipped.
+        assert!(replace_in_history(
+            &mut history,
+            "alice: lunch at noon",
+            "alice: lunch at one (edited)",
+            1,
+        ));
+        assert_eq!(
+            history[0],
+            rig::message::Message::user("alice: lunch at one (edited)")
+        );
+        assert_eq!(
+            history[2],
+            rig::message::Message::user("bob: hi\nalice: lunch at noon")
+        );
+        // Only whole lines match.
+        assert_eq!(
+            history[3],
+            rig::message::Message::user("alice: lunch at noon?")
+        );
+        assert!(!replace_in_history(
+            &mut history,
+            "carol: hello",
+            "carol: bye",
+            0,
+        ));
+    }
+
+    #[test]
+    fn test_cancelled_turn_drops_only_unanswered_tool_calls() {
+        let call = |id: &str| rig::message::Message::Assistant {
+            id: None,
+            content: OneOrMany::one(rig::message::AssistantContent::tool_call(
+                id,
+                "reply",
+                serde_json::json!({"content": "hi"}),
+            )),
+        };
+        let mut history = vec![
+            rig::message::Message::user("alice: hi"),
+            call("1"),
+            rig::message::Message::tool_result("1", "sent"),
+            call("2"),
+        ];
+
+        drop_unanswered_tool_calls(&mut history, 1);
+        assert_eq!(history.len(), 3);
+        // An answered call stays.
+        drop_unanswered_tool_calls(&mut history, 1);
+        assert_eq!(history.len(), 3);
+
+        // Nothing before `from` is touched.
+        let mut history = vec![call("1")];
+        drop_unanswered_tool_calls(&mut history, 1);
+        assert_eq!(history.len(), 1);
+    }
+}
@@ -4,7 +4,11 @@
 //! spawns compaction workers when thresholds are crossed. The LLM work (summarization
 //! + memory extraction) happens in the spawned worker, not here.
 
+use crate::agent::context::count_history_tokens;
+use crate::config::CompactionConfig;
+use crate::db::TimedQuery as _;
 use crate::error::Result;
+use crate::events::{self, BusEvent};
 use crate::llm::SpacebotModel;
 use crate::{AgentDeps, ChannelId, ProcessType};
 use rig::agent::AgentBuilder;
@@ -12,13 +16,20 @@ use rig::completion::{CompletionModel as _, Prompt as _};
 use rig::message::{AssistantContent, Message, UserContent};
 use rig::tool::server::{ToolServer, ToolServerHandle};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::RwLock;
 
+/// Prefix of the summary message the compactor puts at the head of history.
+pub const COMPACTION_SUMMARY_PREFIX: &str = "[Compaction Summary]";
+
 /// Programmatic monitor that watches channel context size and triggers compaction.
 pub struct Compactor {
     pub channel_id: ChannelId,
     pub deps: AgentDeps,
     pub history: Arc<RwLock<Vec<Message>>>,
+    /// Save extracted memories private to the channel. Shared with the
+    /// channel, which sets it for DMs with private memory.
+    pub private_memory: Arc<AtomicBool>,
     /// Is a compaction currently running.
     is_compacting: Arc<RwLock<bool>>,
 }
@@ -30,74 +41,82 @@ impl Compactor {
             channel_id,
             deps,
             history,
+            private_memory: Arc::new(AtomicBool::new(false)),
             is_compacting: Arc::new(RwLock::new(false)),
         }
     }
 
     /// Check context size and trigger compaction if needed.
     ///
-    /// Called by the channel after each turn. Returns the action taken, if any.
-    pub async fn check_and_compact(&self) -> Result<Option<CompactionAction>> {
+    /// Called by the channel after each turn with the token count of its last
+    /// system prompt, memory bulletin included. The assembled context (that
+    /// plus the full history) is measured against the channel model's window,
+    /// so models with small windows compact sooner. Returns the action taken,
+    /// if any.
+    pub async fn check_and_compact(
+        &self,
+        system_tokens: usize,
+    ) -> Result<Option<CompactionAction>> {
         let is_compacting = *self.is_compacting.read().await;
         if is_compacting {
             return Ok(None);
         }
 
         let rc = &self.deps.runtime_config;
-        let context_window = **rc.context_window.load();
+        let model = rc
+            .routing
+            .load()
+            .resolve(ProcessType::Channel, None)
+            .to_string();
+        let context_window = self
+            .deps
+            .llm_manager
+            .context_window(&model, **rc.context_window.load());
         let compaction_config = **rc.compaction.load();
 
-        let usage = {
+        let context_tokens = {
             let history = self.history.read().await;
-            let estimated_tokens = estimate_history_tokens(&history);
-            estimated_tokens as f32 / context_window as f32
+            system_tokens + count_history_tokens(&history)
         };
 
-        let action = if usage >= compaction_config.emergency_threshold {
-            Some(CompactionAction::EmergencyTruncate)
-        } else if usage >= compaction_config.aggressive_threshold {
-            Some(CompactionAction::Aggressive)
-        } else if usage >= compaction_config.background_threshold {
-            Some(CompactionAction::Background)
-        } else {
-            None
+        let Some(trigger) =
+            CompactionTrigger::evaluate(&compaction_config, model, context_tokens, context_window)
+        else {
+            return Ok(None);
         };
+        let action = trigger.action;
 
-        if let Some(action) = action {
-            tracing::info!(
-                channel_id = %self.channel_id,
-                usage = %format!("{:.1}%", usage * 100.0),
-                ?action,
-                "compaction triggered"
-            );
-
-            match action {
-                CompactionAction::EmergencyTruncate => {
-                    // Emergency is synchronous — fast, no LLM
-                    self.emergency_truncate().await?;
-                }
-                CompactionAction::Background | CompactionAction::Aggressive => {
-                    // Background/aggressive spawn a worker
-                    self.spawn_compaction_worker(action).await;
-                }
-            }
+        tracing::info!(
+            channel_id = %self.channel_id,
+            reason = %trigger.reason(),
+            ?action,
+            "compaction triggered"
+        );
 
-            Ok(Some(action))
-        } else {
-            Ok(None)
+        match action {
+            CompactionAction::EmergencyTruncate => {
+                // Emergency is synchronous — fast, no LLM
+                self.emergency_truncate(&trigger).await?;
+            }
+            CompactionAction::Background | CompactionAction::Aggressive => {
+                // Background/aggressive spawn a worker
+                self.spawn_compaction_worker(trigger).await;
+            }
         }
+
+        Ok(Some(action))
     }
 
     /// Spawn a compaction worker in the background.
     ///
     /// The worker reads old messages, runs an LLM to produce a summary + extract
     /// memories, then swaps the summary into the channel's history.
-    async fn spawn_compaction_worker(&self, action: CompactionAction) {
+    async fn spawn_compaction_worker(&self, trigger: CompactionTrigger) {
         let mut is_compacting = self.is_compacting.write().await;
         *is_compacting = true;
         drop(is_compacting);
 
-        let fraction = match action {
+        let fraction = match trigger.action {
             CompactionAction::Background => 0.3,
             CompactionAction::Aggressive => 0.5,
             CompactionAction::EmergencyTruncate => unreachable!(),
@@ -106,6 +125,10 @@ impl Compactor {
         let history = self.history.clone();
         let is_compacting = self.is_compacting.clone();
         let channel_id = self.channel_id.clone();
+        let private_channel = self
+            .private_memory
+            .load(Ordering::Relaxed)
+            .then(|| channel_id.clone());
         let deps = self.deps.clone();
         let prompt_engine = deps.runtime_config.prompts.load();
         let compactor_prompt = prompt_engine
@@ -113,15 +136,31 @@ impl Compactor {
             .expect("failed to render compactor prompt");
 
         tokio::spawn(async move {
-            let result = run_compaction(&deps, &compactor_prompt, &history, fraction).await;
+            let result = run_compaction(
+                &deps,
+                &compactor_prompt,
+                &history,
+                fraction,
+                private_channel,
+            )
+            .await;
 
             match result {
-                Ok(turns_compacted) => {
+                Ok((turns_compacted, summary)) => {
                     tracing::info!(
                         channel_id = %channel_id,
                         turns_compacted,
                         "compaction completed"
                     );
+                    if turns_compacted > 0 {
+                        record_compaction(
+                            &deps,
+                            &channel_id,
+                            &trigger,
+                            turns_compacted,
+                            Some(summary),
+                        );
+                    }
                 }
                 Err(error) => {
                     tracing::error!(
@@ -141,7 +180,7 @@ impl Compactor {
     ///
     /// Only fires at 95%+ context usage. Removes the oldest half of messages and
     /// inserts a marker. Fast and synchronous.
-    async fn emergency_truncate(&self) -> Result<()> {
+    async fn emergency_truncate(&self, trigger: &CompactionTrigger) -> Result<()> {
         let mut history = self.history.write().await;
         let total = history.len();
         if total <= 2 {
@@ -167,6 +206,8 @@ impl Compactor {
             "emergency truncation performed"
         );
 
+        record_compaction(&self.deps, &self.channel_id, trigger, remove_count, None);
+
         Ok(())
     }
 }
@@ -178,7 +219,8 @@ async fn run_compaction(
     compactor_prompt: &str,
     history: &Arc<RwLock<Vec<Message>>>,
     fraction: f32,
-) -> Result<usize> {
+    private_channel: Option<ChannelId>,
+) -> Result<(usize, String)> {
     // 1. Read and remove the oldest messages from history
     let (removed_messages, remove_count) = {
         let mut hist = history.write().await;
@@ -187,7 +229,7 @@ async fn run_compaction(
             .max(1)
             .min(total.saturating_sub(2));
         if remove_count == 0 {
-            return Ok(0);
+            return Ok((0, String::new()));
         }
         let removed: Vec<Message> = hist.drain(..remove_count).collect();
         (removed, remove_count)
@@ -199,14 +241,16 @@ async fn run_compaction(
     // 3. Run the compaction LLM to produce summary + extracted memories
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Worker, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
 
     // Give the compaction worker memory_save so it can directly persist memories
     let tool_server: ToolServerHandle = ToolServer::new()
-        .tool(crate::tools::MemorySaveTool::new(
-            deps.memory_search.clone(),
-        ))
+        .tool(
+            crate::tools::MemorySaveTool::new(deps.memory_search.clone())
+                .with_private_channel(private_channel),
+        )
         .run();
 
     let agent = AgentBuilder::new(model)
@@ -232,68 +276,60 @@ async fn run_compaction(
     // 4. Insert the summary at the beginning of the channel's history
     {
         let mut hist = history.write().await;
-        let summary_message = format!("[Compaction Summary]: {summary}");
+        let summary_message = format!("{COMPACTION_SUMMARY_PREFIX}: {summary}");
         hist.insert(0, Message::from(summary_message));
     }
 
-    Ok(remove_count)
-}
-
-/// Estimate token count for a history using chars/4 heuristic.
-///
-/// This is intentionally rough — it's only used for threshold checks, not billing.
-/// Overestimates slightly, which is the safe direction for compaction triggers.
-pub fn estimate_history_tokens(history: &[Message]) -> usize {
-    let mut chars = 0usize;
-
-    for message in history {
-        match message {
-            Message::User { content } => {
-                for item in content.iter() {
-                    chars += estimate_user_content_chars(item);
-                }
-            }
-            Message::Assistant { content, .. } => {
-                for item in content.iter() {
-                    chars += estimate_assistant_content_chars(item);
-                }
-            }
-        }
-    }
-
-    // ~4 chars per token for English text. Slightly conservative.
-    chars / 4
+    Ok((remove_count, summary))
 }
 
-fn estimate_user_content_chars(content: &UserContent) -> usize {
-    match content {
-        UserContent::Text(t) => t.text.len(),
-        UserContent::ToolResult(tr) => {
-            let mut size = 0;
-            for item in tr.content.iter() {
-                match item {
-                    rig::message::ToolResultContent::Text(t) => size += t.text.len(),
-                    rig::message::ToolResultContent::Image(_) => size += 100,
-                }
-            }
-            size
-        }
-        UserContent::Image(_) => 500,
-        UserContent::Audio(_) => 500,
-        UserContent::Video(_) => 500,
-        UserContent::Document(_) => 1000,
-    }
-}
-
-fn estimate_assistant_content_chars(content: &AssistantContent) -> usize {
-    match content {
-        AssistantContent::Text(t) => t.text.len(),
-        AssistantContent::ToolCall(tc) => {
-            tc.function.name.len() + tc.function.arguments.to_string().len()
+/// Publish a finished compaction, and persist it and what triggered it to
+/// `compaction_summaries`.
+fn record_compaction(
+    deps: &AgentDeps,
+    channel_id: &ChannelId,
+    trigger: &CompactionTrigger,
+    messages_compacted: usize,
+    summary: Option<String>,
+) {
+    events::publish(BusEvent::CompactionCompleted {
+        agent_id: deps.agent_id.clone(),
+        channel_id: channel_id.clone(),
+        action: trigger.action.as_str(),
+        messages_compacted,
+    });
+
+    let pool = deps.sqlite_pool.clone();
+    let id = uuid::Uuid::new_v4().to_string();
+    let channel_id = channel_id.to_string();
+    let action = trigger.action.as_str();
+    let reason = trigger.reason();
+    let model = trigger.model.clone();
+    let context_tokens = trigger.context_tokens as i64;
+    let context_window = trigger.context_window as i64;
+
+    tokio::spawn(async move {
+        if let Err(error) = sqlx::query(
+            "INSERT INTO compaction_summaries \
+             (id, channel_id, action, trigger_reason, model, context_tokens, context_window, messages_compacted, summary) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&channel_id)
+        .bind(action)
+        .bind(&reason)
+        .bind(&model)
+        .bind(context_tokens)
+        .bind(context_window)
+        .bind(messages_compacted as i64)
+        .bind(&summary)
+        .execute(&pool)
+        .timed("compaction_summaries.insert")
+        .await
+        {
+            tracing::warn!(%error, "failed to persist compaction summary");
         }
-        AssistantContent::Reasoning(r) => r.reasoning.iter().map(|s| s.len()).sum(),
-        AssistantContent::Image(_) => 500,
-    }
+    });
 }
 
 /// Render messages into a human-readable transcript for the compaction LLM.
@@ -370,3 +406,104 @@ pub enum CompactionAction {
     /// Emergency truncation (no LLM, drop oldest 50%).
     EmergencyTruncate,
 }
+
+impl CompactionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompactionAction::Background => "background",
+            CompactionAction::Aggressive => "aggressive",
+            CompactionAction::EmergencyTruncate => "emergency",
+        }
+    }
+}
+
+/// Why a compaction ran: which threshold the assembled context crossed, for
+/// which model and window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionTrigger {
+    pub action: CompactionAction,
+    pub model: String,
+    pub context_tokens: usize,
+    pub context_window: usize,
+    /// The threshold that was crossed, as a fraction of the window.
+    pub threshold: f32,
+}
+
+impl CompactionTrigger {
+    /// Pick the most severe threshold `context_tokens` has crossed, if any.
+    pub fn evaluate(
+        config: &CompactionConfig,
+        model: String,
+        context_tokens: usize,
+        context_window: usize,
+    ) -> Option<Self> {
+        let usage = context_tokens as f32 / context_window.max(1) as f32;
+        let (action, threshold) = [
+            (
+                CompactionAction::EmergencyTruncate,
+                config.emergency_threshold,
+            ),
+            (CompactionAction::Aggressive, config.aggressive_threshold),
+            (CompactionAction::Background, config.background_threshold),
+        ]
+        .into_iter()
+        .find(|(_, threshold)| usage >= *threshold)?;
+
+        Some(Self {
+            action,
+            model,
+            context_tokens,
+            context_window,
+            threshold,
+        })
+    }
+
+    pub fn usage(&self) -> f32 {
+        self.context_tokens as f32 / self.context_window.max(1) as f32
+    }
+
+    /// Human-readable trigger reason, as stored with the summary.
+    pub fn reason(&self) -> String {
+        format!(
+            "context at {:.1}% of {}'s {}-token window ({} tokens) crossed the {} threshold of {:.0}%",
+            self.usage() * 100.0,
+            self.model,
+            self.context_window,
+            self.context_tokens,
+            self.action.as_str(),
+            self.threshold * 100.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_scales_with_model_window() {
+        let tokens = 30_000;
+        assert!(
+            CompactionTrigger::evaluate(
+                &CompactionConfig::default(),
+                "large".into(),
+                tokens,
+                200_000
+            )
+            .is_none()
+        );
+
+        let trigger = CompactionTrigger::evaluate(
+            &CompactionConfig::default(),
+            "anthropic/small".into(),
+            tokens,
+            32_000,
+        )
+        .expect("small window should trigger");
+        assert_eq!(trigger.action, CompactionAction::Aggressive);
+        assert_eq!(
+            trigger.reason(),
+            "context at 93.8% of anthropic/small's 32000-token window (30000 tokens) crossed the aggressive threshold of 85%"
+        );
+    }
+}
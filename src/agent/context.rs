@@ -0,0 +1,349 @@
+//! Token-aware context assembly: fit prompt, summaries, memories, and history
+//! into a model's context window.
+
+use crate::agent::compactor::COMPACTION_SUMMARY_PREFIX;
+
+use rig::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use serde::Serialize;
+
+/// Tokens held back for the model's response.
+const RESPONSE_RESERVE_TOKENS: usize = 4096;
+
+/// Per-message framing (role markers, separators) added by chat templates.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Recent messages kept ahead of the memory bulletin. Past this floor, older
+/// history only fills whatever space the memories leave.
+const RECENT_MESSAGE_FLOOR: usize = 8;
+
+/// Flat costs for non-text content, which tokenizers don't see as text.
+const IMAGE_TOKENS: usize = 256;
+const DOCUMENT_TOKENS: usize = 512;
+
+/// Count tokens the way BPE tokenizers (cl100k/o200k style) split text.
+///
+/// Not a real tokenizer: it pre-splits like tiktoken (letter runs, digit
+/// groups, punctuation, whitespace) and prices each piece from typical
+/// vocabulary coverage. Short words are one token, long words several, digits
+/// go in groups of three, and non-ASCII characters cost one token each. It
+/// lands close to real counts for English prose and code and errs high for
+/// everything else, which is the safe direction for window budgeting.
+pub fn count_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        if character.is_ascii_alphabetic() {
+            let mut length = 1;
+            while chars.next_if(char::is_ascii_alphabetic).is_some() {
+                length += 1;
+            }
+            tokens += 1 + (length - 1) / 6;
+        } else if character.is_ascii_digit() {
+            let mut length = 1;
+            while chars.next_if(char::is_ascii_digit).is_some() {
+                length += 1;
+            }
+            tokens += length.div_ceil(3);
+        } else if character.is_whitespace() {
+            let mut length = 1;
+            while chars.next_if(|next| next.is_whitespace()).is_some() {
+                length += 1;
+            }
+            // A single space merges into the following word.
+            if length > 1 || character != ' ' {
+                tokens += 1;
+            }
+        } else {
+            tokens += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Tokens a message takes up in a prompt, including framing.
+pub fn count_message_tokens(message: &Message) -> usize {
+    let content_tokens: usize = match message {
+        Message::User { content } => content.iter().map(count_user_content_tokens).sum(),
+        Message::Assistant { content, .. } => {
+            content.iter().map(count_assistant_content_tokens).sum()
+        }
+    };
+    content_tokens + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Tokens a whole history takes up in a prompt.
+pub fn count_history_tokens(history: &[Message]) -> usize {
+    history.iter().map(count_message_tokens).sum()
+}
+
+fn count_user_content_tokens(content: &UserContent) -> usize {
+    match content {
+        UserContent::Text(text) => count_tokens(&text.text),
+        UserContent::ToolResult(result) => result
+            .content
+            .iter()
+            .map(|item| match item {
+                ToolResultContent::Text(text) => count_tokens(&text.text),
+                ToolResultContent::Image(_) => IMAGE_TOKENS,
+            })
+            .sum(),
+        UserContent::Image(_) | UserContent::Audio(_) | UserContent::Video(_) => IMAGE_TOKENS,
+        UserContent::Document(_) => DOCUMENT_TOKENS,
+    }
+}
+
+fn count_assistant_content_tokens(content: &AssistantContent) -> usize {
+    match content {
+        AssistantContent::Text(text) => count_tokens(&text.text),
+        AssistantContent::ToolCall(call) => {
+            count_tokens(&call.function.name) + count_tokens(&call.function.arguments.to_string())
+        }
+        AssistantContent::Reasoning(reasoning) => reasoning
+            .reasoning
+            .iter()
+            .map(|text| count_tokens(text))
+            .sum(),
+        AssistantContent::Image(_) => IMAGE_TOKENS,
+    }
+}
+
+/// Whether a history message is a summary inserted by the compactor.
+pub fn is_compaction_summary(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content.iter().any(|item| {
+            matches!(item, UserContent::Text(text) if text.text.starts_with(COMPACTION_SUMMARY_PREFIX))
+        }),
+        Message::Assistant { .. } => false,
+    }
+}
+
+fn is_tool_result(message: &Message) -> bool {
+    match message {
+        Message::User { content } => content
+            .iter()
+            .any(|item| matches!(item, UserContent::ToolResult(_))),
+        Message::Assistant { .. } => false,
+    }
+}
+
+/// What a context plan had to leave out, logged whenever anything is dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ContextReport {
+    pub context_window: usize,
+    /// Window minus the response reserve.
+    pub budget: usize,
+    /// System prompt plus the full memory bulletin, whether or not it fit.
+    pub system_tokens: usize,
+    pub used_tokens: usize,
+    pub dropped_messages: usize,
+    pub dropped_summaries: usize,
+    pub dropped_memories: bool,
+    pub dropped_tokens: usize,
+}
+
+impl ContextReport {
+    pub fn is_trimmed(&self) -> bool {
+        self.dropped_messages > 0 || self.dropped_summaries > 0 || self.dropped_memories
+    }
+}
+
+/// Which parts of the stored history and memory go into the next prompt.
+#[derive(Debug, Clone)]
+pub struct ContextPlan {
+    pub include_memories: bool,
+    /// The kept messages of the snapshot the plan was made from, so what's
+    /// sent is what was measured even if the channel's history has changed
+    /// since, such as by compaction.
+    history: Vec<Message>,
+    pub report: ContextReport,
+}
+
+impl ContextPlan {
+    /// The history to send.
+    pub fn history(&self) -> &[Message] {
+        &self.history
+    }
+}
+
+/// Fits a prompt into a model's context window by token count.
+///
+/// The system prompt and the incoming message are always sent. The rest is
+/// filled in priority order: compaction summaries (newest first), the most
+/// recent messages, the memory bulletin, then older messages until the
+/// window is full. History is only ever cut from the old end, so what's sent
+/// is a contiguous tail plus whatever summaries fit.
+#[derive(Debug, Clone, Copy)]
+pub struct ContextManager {
+    context_window: usize,
+}
+
+impl ContextManager {
+    pub fn new(context_window: usize) -> Self {
+        Self { context_window }
+    }
+
+    /// Tokens available for the prompt after reserving room for the response.
+    pub fn budget(&self) -> usize {
+        let reserve = RESPONSE_RESERVE_TOKENS.min(self.context_window / 4);
+        self.context_window - reserve
+    }
+
+    /// Decide what to send. `system_prompt` is the rendered system prompt
+    /// without the memory bulletin; `memories` is the bulletin itself;
+    /// `history` is a snapshot of the channel's history, trimmed into the plan.
+    pub fn plan(
+        &self,
+        system_prompt: &str,
+        prompt: &str,
+        memories: &str,
+        history: Vec<Message>,
+    ) -> ContextPlan {
+        let budget = self.budget();
+        let system_tokens = count_tokens(system_prompt);
+        let fixed_tokens = system_tokens + count_tokens(prompt) + 2 * MESSAGE_OVERHEAD_TOKENS;
+        let mut remaining = budget.saturating_sub(fixed_tokens);
+
+        let costs: Vec<usize> = history.iter().map(count_message_tokens).collect();
+        let summaries: Vec<bool> = history.iter().map(is_compaction_summary).collect();
+        let mut kept = vec![false; history.len()];
+
+        for index in (0..history.len()).rev().filter(|&index| summaries[index]) {
+            if costs[index] <= remaining {
+                kept[index] = true;
+                remaining -= costs[index];
+            }
+        }
+
+        let mut cursor = history.len();
+        let mut history_full = false;
+        let mut take_older = |limit: usize, remaining: &mut usize| {
+            let mut taken = 0;
+            while !history_full && cursor > 0 && taken < limit {
+                let index = cursor - 1;
+                if !summaries[index] {
+                    if costs[index] > *remaining {
+                        history_full = true;
+                        break;
+                    }
+                    kept[index] = true;
+                    *remaining -= costs[index];
+                    taken += 1;
+                }
+                cursor -= 1;
+            }
+        };
+
+        take_older(RECENT_MESSAGE_FLOOR, &mut remaining);
+
+        let memory_tokens = count_tokens(memories);
+        let include_memories = memory_tokens <= remaining;
+        if include_memories {
+            remaining -= memory_tokens;
+        }
+
+        take_older(usize::MAX, &mut remaining);
+
+        // A tool result whose call was cut off is rejected by providers, so
+        // the tail must start on something other than a tool result.
+        if let Some(first) = (0..history.len()).find(|&index| kept[index] && !summaries[index]) {
+            let mut index = first;
+            while index < history.len() && kept[index] && is_tool_result(&history[index]) {
+                kept[index] = false;
+                remaining += costs[index];
+                index += 1;
+            }
+        }
+
+        let dropped: Vec<usize> = (0..history.len()).filter(|&index| !kept[index]).collect();
+        let dropped_summaries = dropped.iter().filter(|&&index| summaries[index]).count();
+        let dropped_tokens = dropped.iter().map(|&index| costs[index]).sum::<usize>()
+            + if include_memories { 0 } else { memory_tokens };
+        let history = history
+            .into_iter()
+            .zip(kept)
+            .filter_map(|(message, kept)| kept.then_some(message))
+            .collect();
+
+        ContextPlan {
+            include_memories,
+            report: ContextReport {
+                context_window: self.context_window,
+                budget,
+                system_tokens: system_tokens + memory_tokens,
+                used_tokens: budget.saturating_sub(remaining),
+                dropped_messages: dropped.len() - dropped_summaries,
+                dropped_summaries,
+                dropped_memories: !include_memories,
+                dropped_tokens,
+            },
+            history,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(count: usize, words: usize) -> Vec<Message> {
+        (0..count)
+            .map(|index| Message::from(format!("message {index}: {}", "word ".repeat(words))))
+            .collect()
+    }
+
+    #[test]
+    fn test_count_tokens_tracks_bpe_granularity() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("hello world"), 2);
+        assert_eq!(count_tokens("internationalization"), 4);
+        assert_eq!(count_tokens("1234567"), 3);
+        assert_eq!(count_tokens("a, b."), 4);
+    }
+
+    #[test]
+    fn test_plan_keeps_everything_when_it_fits() {
+        let history = messages(5, 10);
+        let plan = ContextManager::new(128_000).plan("system", "hi", "memories", history);
+
+        assert!(plan.include_memories);
+        assert!(!plan.report.is_trimmed());
+        assert_eq!(plan.history().len(), 5);
+    }
+
+    #[test]
+    fn test_plan_drops_oldest_messages_and_keeps_summaries() {
+        let mut history = vec![Message::from(format!(
+            "{COMPACTION_SUMMARY_PREFIX}: earlier the user asked about deploys"
+        ))];
+        history.extend(messages(40, 50));
+
+        // Room for the summary and roughly ten of the forty messages.
+        let manager = ContextManager::new(1_000);
+        let plan = manager.plan("system", "hi", "", history.clone());
+        let sent = plan.history();
+
+        assert!(is_compaction_summary(&sent[0]));
+        assert_eq!(plan.report.dropped_summaries, 0);
+        assert!(plan.report.dropped_messages > 0);
+        assert_eq!(sent.len(), history.len() - plan.report.dropped_messages);
+        // The newest message always survives and the tail stays contiguous.
+        assert_eq!(
+            format!("{:?}", sent.last()),
+            format!("{:?}", history.last())
+        );
+        assert!(plan.report.used_tokens <= manager.budget());
+    }
+
+    #[test]
+    fn test_plan_drops_memories_before_recent_messages() {
+        let history = messages(4, 20);
+        let memories = "fact ".repeat(2_000);
+        let plan = ContextManager::new(1_000).plan("system", "hi", &memories, history);
+
+        assert!(!plan.include_memories);
+        assert!(plan.report.dropped_memories);
+        assert_eq!(plan.report.dropped_messages, 0);
+    }
+}
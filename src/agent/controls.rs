@@ -0,0 +1,163 @@
+//! Reaction controls: emoji reactions on a bot message that act on the reply.
+//!
+//! The messaging adapter turns a control reaction into an inbound message
+//! carrying `REACTION_CONTROL_KEY`. Delete is carried out by the adapter, so
+//! the channel only records it. Regenerate and expand arrive with an
+//! instruction as their text and start a turn like any addressed message.
+//! Every control is written to `control_events` as feedback on the reply.
+
+use crate::InboundMessage;
+use crate::config::ReactionControlsConfig;
+use crate::db::TimedQuery as _;
+
+/// Metadata key naming the control, on inbound messages from a control
+/// reaction.
+pub const REACTION_CONTROL_KEY: &str = "reaction_control";
+
+/// Metadata key holding the platform id of the bot message reacted to.
+pub const CONTROL_TARGET_KEY: &str = "reaction_control_target";
+
+/// What a control reaction asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionControl {
+    Regenerate,
+    Delete,
+    Expand,
+}
+
+impl ReactionControl {
+    /// The control an emoji stands for, if controls are enabled. Emoji
+    /// variation selectors are ignored, since clients differ on sending them.
+    pub fn from_emoji(config: &ReactionControlsConfig, emoji: &str) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let emoji = strip_variation(emoji);
+        [
+            (&config.regenerate, Self::Regenerate),
+            (&config.delete, Self::Delete),
+            (&config.expand, Self::Expand),
+        ]
+        .into_iter()
+        .find(|(configured, _)| strip_variation(configured) == emoji)
+        .map(|(_, control)| control)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Regenerate => "regenerate",
+            Self::Delete => "delete",
+            Self::Expand => "expand",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "regenerate" => Some(Self::Regenerate),
+            "delete" => Some(Self::Delete),
+            "expand" => Some(Self::Expand),
+            _ => None,
+        }
+    }
+
+    /// The text the channel sees for this control on a reply.
+    pub fn instruction(self, reply: &str) -> String {
+        match self {
+            Self::Regenerate => format!(
+                "[Reacted to your reply below, asking you to regenerate it. Answer the same \
+                 message again with a different reply.]\n\nYour reply:\n{reply}"
+            ),
+            Self::Delete => "[Deleted your reply.]".to_string(),
+            Self::Expand => format!(
+                "[Reacted to your reply below, asking you to expand on it. Go into more \
+                 detail.]\n\nYour reply:\n{reply}"
+            ),
+        }
+    }
+}
+
+fn strip_variation(emoji: &str) -> String {
+    emoji.replace('\u{FE0F}', "")
+}
+
+/// The control an inbound message carries, if any.
+pub fn requested(message: &InboundMessage) -> Option<ReactionControl> {
+    message
+        .metadata
+        .get(REACTION_CONTROL_KEY)
+        .and_then(|value| value.as_str())
+        .and_then(ReactionControl::parse)
+}
+
+/// Write a control event to `control_events` in the background.
+pub fn record(
+    pool: &sqlx::SqlitePool,
+    channel_id: &str,
+    message: &InboundMessage,
+    control: ReactionControl,
+) {
+    let target_message_id = message
+        .metadata
+        .get(CONTROL_TARGET_KEY)
+        .map(|value| match value {
+            serde_json::Value::String(id) => id.clone(),
+            other => other.to_string(),
+        })
+        .unwrap_or_default();
+    let pool = pool.clone();
+    let id = uuid::Uuid::new_v4().to_string();
+    let channel_id = channel_id.to_string();
+    let user_id = message.sender_id.clone();
+
+    tokio::spawn(async move {
+        if let Err(error) = sqlx::query(
+            "INSERT INTO control_events (id, channel_id, action, target_message_id, user_id) \
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&channel_id)
+        .bind(control.as_str())
+        .bind(&target_message_id)
+        .bind(&user_id)
+        .execute(&pool)
+        .timed("control_events.insert")
+        .await
+        {
+            tracing::warn!(%error, channel_id, "failed to persist control event");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emojis_map_to_controls_ignoring_variation_selectors() {
+        let config = ReactionControlsConfig::default();
+        assert_eq!(
+            ReactionControl::from_emoji(&config, "🔁"),
+            Some(ReactionControl::Regenerate)
+        );
+        // Discord may send the wastebasket with or without U+FE0F.
+        assert_eq!(
+            ReactionControl::from_emoji(&config, "🗑"),
+            Some(ReactionControl::Delete)
+        );
+        assert_eq!(
+            ReactionControl::from_emoji(&config, "➕"),
+            Some(ReactionControl::Expand)
+        );
+        assert_eq!(ReactionControl::from_emoji(&config, "👍"), None);
+
+        let disabled = ReactionControlsConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(ReactionControl::from_emoji(&disabled, "🔁"), None);
+        assert_eq!(
+            ReactionControl::parse(ReactionControl::Expand.as_str()),
+            Some(ReactionControl::Expand)
+        );
+    }
+}
@@ -9,6 +9,7 @@
 //! The cortex also observes system-wide activity via signals for future use in
 //! health monitoring and memory consolidation.
 
+use crate::db::TimedQuery as _;
 use crate::error::Result;
 use crate::hooks::CortexHook;
 use crate::llm::SpacebotModel;
@@ -58,6 +59,12 @@ pub enum Signal {
         channel_id: String,
         turns_compacted: i64,
     },
+    /// An inbound message was flagged as a possible prompt injection.
+    InjectionFlagged {
+        channel_id: String,
+        sender_id: String,
+        reasons: Vec<String>,
+    },
     /// Error occurred.
     Error {
         component: String,
@@ -82,11 +89,23 @@ pub struct CortexEvent {
 #[derive(Debug, Clone)]
 pub struct CortexLogger {
     pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
 }
 
 impl CortexLogger {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
     }
 
     /// Log a cortex action. Fire-and-forget.
@@ -106,6 +125,7 @@ impl CortexLogger {
             .bind(&summary)
             .bind(&details_json)
             .execute(&pool)
+            .timed("cortex_events.insert")
             .await
             {
                 tracing::warn!(%error, "failed to persist cortex event");
@@ -128,7 +148,10 @@ impl CortexLogger {
             .bind(event_type)
             .bind(limit)
             .bind(offset)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.readers)
+            .timed_with("cortex_events.load", || {
+                format!("event_type={event_type} limit={limit} offset={offset}")
+            })
             .await?
         } else {
             sqlx::query_as::<_, CortexEventRow>(
@@ -137,7 +160,10 @@ impl CortexLogger {
             )
             .bind(limit)
             .bind(offset)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.readers)
+            .timed_with("cortex_events.load", || {
+                format!("limit={limit} offset={offset}")
+            })
             .await?
         };
 
@@ -153,10 +179,12 @@ impl CortexLogger {
             sqlx::query_as("SELECT COUNT(*) FROM cortex_events WHERE event_type = ?")
                 .bind(event_type)
                 .fetch_one(&self.pool)
+                .timed_with("cortex_events.count", || format!("event_type={event_type}"))
                 .await?
         } else {
             sqlx::query_as("SELECT COUNT(*) FROM cortex_events")
                 .fetch_one(&self.pool)
+                .timed("cortex_events.count")
                 .await?
         };
 
@@ -219,6 +247,16 @@ impl Cortex {
                 channel_id: channel_id.to_string(),
                 turns_compacted: (*threshold_reached * 100.0) as i64,
             }),
+            ProcessEvent::InjectionFlagged {
+                channel_id,
+                sender_id,
+                reasons,
+                ..
+            } => Some(Signal::InjectionFlagged {
+                channel_id: channel_id.to_string(),
+                sender_id: sender_id.clone(),
+                reasons: reasons.clone(),
+            }),
             _ => None,
         };
 
@@ -462,8 +500,9 @@ pub async fn generate_bulletin(deps: &AgentDeps, logger: &CortexLogger) -> bool
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
 
     // No tools needed — the LLM just synthesizes the pre-gathered data
     let agent = AgentBuilder::new(model).preamble(&bulletin_prompt).build();
@@ -534,6 +573,7 @@ pub async fn load_profile(pool: &SqlitePool, agent_id: &str) -> Option<AgentProf
     )
     .bind(agent_id)
     .fetch_optional(pool)
+    .timed_with("agent_profile.load", || format!("agent_id={agent_id}"))
     .await
     .ok()
     .flatten()
@@ -621,8 +661,9 @@ async fn generate_profile(deps: &AgentDeps, logger: &CortexLogger) {
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
 
     let agent = AgentBuilder::new(model).preamble(&profile_prompt).build();
 
@@ -660,6 +701,7 @@ async fn generate_profile(deps: &AgentDeps, logger: &CortexLogger) {
                     .bind(&profile_data.bio)
                     .bind(&avatar_seed)
                     .execute(&deps.sqlite_pool)
+                    .timed("agent_profile.upsert")
                     .await
                     {
                         tracing::warn!(%error, "failed to persist agent profile");
@@ -784,7 +826,7 @@ async fn run_association_pass(
     let is_backfill = since.is_none();
 
     let store = deps.memory_search.store();
-    let embedding_table = deps.memory_search.embedding_table();
+    let vector_store = deps.memory_search.vector_store();
 
     // Get the memories to process
     let memories = match fetch_memories_for_association(&deps.sqlite_pool, since).await {
@@ -808,7 +850,7 @@ async fn run_association_pass(
         }
 
         // Find similar memories via embedding search
-        let similar = match embedding_table
+        let similar = match vector_store
             .find_similar(memory_id, similarity_threshold, 10)
             .await
         {
@@ -884,12 +926,14 @@ async fn fetch_memories_for_association(
         .bind(since)
         .bind(since)
         .fetch_all(pool)
+        .timed_with("cortex.memory_ids", || format!("since={since}"))
         .await?
     } else {
         sqlx::query(
             "SELECT id FROM memories WHERE forgotten = 0 ORDER BY importance DESC, created_at DESC",
         )
         .fetch_all(pool)
+        .timed("cortex.memory_ids")
         .await?
     };
 
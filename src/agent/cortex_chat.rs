@@ -6,6 +6,7 @@
 //! into the system prompt as context.
 
 use crate::conversation::history::ProcessRunLogger;
+use crate::db::TimedQuery as _;
 use crate::llm::SpacebotModel;
 use crate::{AgentDeps, ProcessType};
 
@@ -116,6 +117,9 @@ impl<M: CompletionModel> PromptHook<M> for CortexChatHook {
 #[derive(Debug, Clone)]
 pub struct CortexChatStore {
     pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
 }
 
 #[derive(sqlx::FromRow)]
@@ -143,7 +147,16 @@ impl ChatMessageRow {
 
 impl CortexChatStore {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
     }
 
     /// Load chat history for a thread, newest first, then reverse to chronological order.
@@ -158,7 +171,10 @@ impl CortexChatStore {
         )
         .bind(thread_id)
         .bind(limit)
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed_with("cortex_chat_messages.load", || {
+            format!("thread_id={thread_id}")
+        })
         .await?;
 
         let mut messages: Vec<CortexChatMessage> =
@@ -186,6 +202,9 @@ impl CortexChatStore {
         .bind(content)
         .bind(channel_context)
         .execute(&self.pool)
+        .timed_with("cortex_chat_messages.insert", || {
+            format!("thread_id={thread_id}")
+        })
         .await?;
         Ok(id)
     }
@@ -196,6 +215,7 @@ impl CortexChatStore {
             "SELECT thread_id FROM cortex_chat_messages ORDER BY created_at DESC LIMIT 1",
         )
         .fetch_optional(&self.pool)
+        .timed("cortex_chat_messages.latest_thread")
         .await?;
         Ok(row.map(|r| r.0))
     }
@@ -263,6 +283,7 @@ impl CortexChatSession {
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Branch, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
+            .with_agent(&self.deps.agent_id)
             .with_routing((**routing).clone());
 
         let agent = AgentBuilder::new(model)
@@ -0,0 +1,361 @@
+//! Daily digest: a summary of the day's conversations, posted once a day.
+//!
+//! At `[digest] time`, local time, the messages of the past day are grouped
+//! by conversation, or by guild with `scope = "guild"`. Each group with at
+//! least `min_messages` messages is summarized from its messages and the
+//! compaction summaries written in the same day, and the summaries are posted
+//! as one message to `destination`. Cron conversations aren't covered.
+
+use crate::config::{DigestConfig, DigestScope};
+use crate::cron::scheduler::DeliveryTarget;
+use crate::db::TimedQuery as _;
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, OutboundResponse, ProcessType};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Local, NaiveTime, TimeZone as _};
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How far back a digest looks.
+const DIGEST_WINDOW: &str = "-1 day";
+
+/// Most messages of one group sent to the model, keeping the latest.
+const MAX_MESSAGES: usize = 300;
+
+/// The messages and compaction summaries of one digest section.
+#[derive(Debug, Clone)]
+pub struct DigestGroup {
+    /// Conversation name, or guild name with the guild scope.
+    pub name: String,
+    /// Transcript lines, oldest first.
+    pub lines: Vec<String>,
+    pub summaries: Vec<String>,
+}
+
+/// Spawn the digest loop for one agent. It sleeps until the next digest
+/// time and reads `[digest]` again each time, so config changes apply from
+/// the next digest on.
+pub fn spawn_digest_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let time = deps.runtime_config.digest.load().time;
+            tokio::time::sleep(delay_until(time, Local::now())).await;
+
+            let config = deps.runtime_config.digest.load_full();
+            if !config.enabled {
+                continue;
+            }
+            match post_digest(&deps, &config).await {
+                Ok(0) => tracing::info!("no conversations for the daily digest"),
+                Ok(sections) => tracing::info!(sections, "daily digest posted"),
+                Err(error) => tracing::warn!(%error, "failed to post daily digest"),
+            }
+        }
+    })
+}
+
+/// How long from `now` until `time` next comes around.
+fn delay_until(time: NaiveTime, now: DateTime<Local>) -> Duration {
+    let today = now.date_naive().and_time(time);
+    let next = if today > now.naive_local() {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+    Local
+        .from_local_datetime(&next)
+        .earliest()
+        .map(|next| (next - now).to_std().unwrap_or_default())
+        // The time doesn't exist that day, skipped by a DST change.
+        .unwrap_or(Duration::from_secs(3600))
+}
+
+/// Summarize the past day and post it. Returns the number of sections
+/// posted; nothing is posted without any.
+pub async fn post_digest(deps: &AgentDeps, config: &DigestConfig) -> anyhow::Result<usize> {
+    let destination = config
+        .destination
+        .as_deref()
+        .and_then(DeliveryTarget::parse)
+        .context("digest.destination isn't in adapter:target form")?;
+    let messaging_manager = deps
+        .messaging_manager
+        .as_ref()
+        .context("messaging isn't running")?;
+
+    let groups = collect_groups(&deps.sqlite_read, config).await?;
+    let mut sections = Vec::new();
+    for group in &groups {
+        match summarize(deps, config, group).await {
+            Ok(summary) => sections.push(format!("**{}**\n{}", group.name, summary.trim())),
+            Err(error) => {
+                tracing::warn!(%error, group = %group.name, "failed to summarize for the digest");
+            }
+        }
+    }
+    if sections.is_empty() {
+        return Ok(0);
+    }
+
+    let text = format!(
+        "**Daily digest, {}**\n\n{}",
+        Local::now().format("%A %B %-d"),
+        sections.join("\n\n")
+    );
+    messaging_manager
+        .broadcast(
+            &destination.adapter,
+            &destination.target,
+            OutboundResponse::Text(text),
+        )
+        .await?;
+    Ok(sections.len())
+}
+
+/// The past day's messages and compaction summaries, grouped by the
+/// digest's scope, busiest group first. Groups with fewer than
+/// `min_messages` messages are left out.
+pub async fn collect_groups(
+    pool: &SqlitePool,
+    config: &DigestConfig,
+) -> crate::error::Result<Vec<DigestGroup>> {
+    let messages = sqlx::query(
+        "SELECT m.channel_id, m.role, m.sender_name, m.content, \
+         c.display_name, \
+         CAST(json_extract(c.platform_meta, '$.discord_guild_id') AS TEXT) AS guild_id, \
+         json_extract(c.platform_meta, '$.discord_guild_name') AS guild_name \
+         FROM conversation_messages m \
+         LEFT JOIN channels c ON c.id = m.channel_id \
+         WHERE m.created_at >= datetime('now', ?) AND m.channel_id NOT LIKE 'cron:%' \
+         ORDER BY m.created_at, m.rowid",
+    )
+    .bind(DIGEST_WINDOW)
+    .fetch_all(pool)
+    .timed("digest.messages")
+    .await?;
+
+    let summaries = sqlx::query(
+        "SELECT channel_id, summary FROM compaction_summaries \
+         WHERE created_at >= datetime('now', ?) AND summary IS NOT NULL \
+         ORDER BY created_at",
+    )
+    .bind(DIGEST_WINDOW)
+    .fetch_all(pool)
+    .timed("digest.summaries")
+    .await?;
+
+    // Which group each conversation belongs to, and the names to show.
+    let mut group_of: HashMap<String, String> = HashMap::new();
+    let mut groups: HashMap<String, DigestGroup> = HashMap::new();
+
+    for row in &messages {
+        let channel_id: String = row.get("channel_id");
+        if !config.channels.is_empty() && !config.channels.contains(&channel_id) {
+            continue;
+        }
+        let channel_name: String = row
+            .try_get::<Option<String>, _>("display_name")
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| channel_id.clone());
+        let guild_id: Option<String> = row.try_get("guild_id").ok().flatten();
+
+        let (key, name) = match (config.scope, guild_id) {
+            (DigestScope::Guild, Some(guild_id)) => {
+                let guild_name: Option<String> = row.try_get("guild_name").ok().flatten();
+                let name = guild_name.unwrap_or_else(|| format!("guild {guild_id}"));
+                (format!("guild:{guild_id}"), name)
+            }
+            _ => (channel_id.clone(), channel_name.clone()),
+        };
+        group_of.insert(channel_id, key.clone());
+
+        let role: String = row.get("role");
+        let sender = if role == "assistant" {
+            "agent".to_string()
+        } else {
+            row.try_get::<Option<String>, _>("sender_name")
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| "user".to_string())
+        };
+        let content: String = row.get("content");
+        let line = if config.scope == DigestScope::Guild && key.starts_with("guild:") {
+            format!("[{channel_name}] {sender}: {content}")
+        } else {
+            format!("{sender}: {content}")
+        };
+
+        groups
+            .entry(key)
+            .or_insert_with(|| DigestGroup {
+                name,
+                lines: Vec::new(),
+                summaries: Vec::new(),
+            })
+            .lines
+            .push(line);
+    }
+
+    for row in &summaries {
+        let channel_id: String = row.get("channel_id");
+        if let Some(group) = group_of
+            .get(&channel_id)
+            .and_then(|key| groups.get_mut(key))
+        {
+            group.summaries.push(row.get("summary"));
+        }
+    }
+
+    let mut groups: Vec<(usize, DigestGroup)> = groups
+        .into_values()
+        .filter_map(|mut group| {
+            let count = group.lines.len();
+            if count < config.min_messages.max(1) {
+                return None;
+            }
+            if group.lines.len() > MAX_MESSAGES {
+                group.lines.drain(..group.lines.len() - MAX_MESSAGES);
+            }
+            Some((count, group))
+        })
+        .collect();
+    groups.sort_by(|(a_count, a), (b_count, b)| b_count.cmp(a_count).then(a.name.cmp(&b.name)));
+
+    Ok(groups.into_iter().map(|(_, group)| group).collect())
+}
+
+/// Summarize one group with the digest model.
+async fn summarize(
+    deps: &AgentDeps,
+    config: &DigestConfig,
+    group: &DigestGroup,
+) -> anyhow::Result<String> {
+    let prompt_engine = deps.runtime_config.prompts.load();
+    let system_prompt = prompt_engine.render_static("digest")?;
+    let prompt = prompt_engine.render_system_digest_conversation(
+        &group.name,
+        &group.summaries,
+        &group.lines.join("\n"),
+    )?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = config
+        .model
+        .clone()
+        .unwrap_or_else(|| routing.resolve(ProcessType::Compactor, None).to_string());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&system_prompt).build();
+
+    Ok(agent.prompt(&prompt).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_messages_are_grouped_by_guild_and_quiet_groups_left_out() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let meta = r#"{"discord_guild_id":1,"discord_guild_name":"Rocketry"}"#;
+        for (id, name) in [("discord:1:2", "general"), ("discord:1:3", "launches")] {
+            sqlx::query(
+                "INSERT INTO channels (id, platform, display_name, platform_meta) \
+                 VALUES (?, 'discord', ?, ?)",
+            )
+            .bind(id)
+            .bind(name)
+            .bind(meta)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        for (index, (channel_id, role, content, age)) in [
+            (
+                "discord:1:2",
+                "user",
+                "when is the next launch?",
+                "-3 hours",
+            ),
+            ("discord:1:3", "assistant", "Friday at noon.", "-2 hours"),
+            ("discord:1:3", "user", "old news", "-3 days"),
+            ("discord:dm:9", "user", "hello", "-1 hour"),
+            ("cron:standup", "assistant", "standup time", "-1 hour"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sqlx::query(
+                "INSERT INTO conversation_messages \
+                 (id, channel_id, role, sender_name, content, created_at) \
+                 VALUES (?, ?, ?, 'ada', ?, datetime('now', ?))",
+            )
+            .bind(index.to_string())
+            .bind(channel_id)
+            .bind(role)
+            .bind(content)
+            .bind(age)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO compaction_summaries \
+             (id, channel_id, action, trigger_reason, model, context_tokens, context_window, \
+              messages_compacted, summary) \
+             VALUES ('s1', 'discord:1:2', 'background', 'threshold', 'm', 0, 0, 10, \
+             'Talked about fuel.')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let config = DigestConfig {
+            scope: DigestScope::Guild,
+            min_messages: 2,
+            ..Default::default()
+        };
+        let groups = collect_groups(&pool, &config).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "Rocketry");
+        assert_eq!(
+            groups[0].lines,
+            [
+                "[general] ada: when is the next launch?",
+                "[launches] agent: Friday at noon."
+            ]
+        );
+        assert_eq!(groups[0].summaries, ["Talked about fuel."]);
+
+        let config = DigestConfig {
+            min_messages: 1,
+            ..Default::default()
+        };
+        let names: Vec<String> = collect_groups(&pool, &config)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|group| group.name)
+            .collect();
+        assert_eq!(names, ["discord:dm:9", "general", "launches"]);
+    }
+}
@@ -0,0 +1,239 @@
+//! Model experiments: route a share of channel turns to an alternate model
+//! and record both variants so they can be compared.
+
+use crate::agent::tool_loop::ToolTrace;
+use crate::config::{ExperimentConfig, ModelPricing};
+use crate::db::TimedQuery as _;
+
+use rig::message::{AssistantContent, Message};
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+
+/// Which side of the experiment a turn ran on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// The model routing picked.
+    Control,
+    Alternate,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::Control => "control",
+            Variant::Alternate => "alternate",
+        }
+    }
+}
+
+/// The variant and model chosen for one turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub variant: Variant,
+    pub model: String,
+}
+
+/// Pick the variant for a turn. None when no experiment is running.
+pub fn assign(config: &ExperimentConfig, routed_model: &str) -> Option<Assignment> {
+    assign_with_roll(config, routed_model, rand::random::<f64>())
+}
+
+/// `assign` with the random draw, in `[0, 1)`, passed in.
+fn assign_with_roll(
+    config: &ExperimentConfig,
+    routed_model: &str,
+    roll: f64,
+) -> Option<Assignment> {
+    if !config.enabled {
+        return None;
+    }
+    let alternate = config.alternate_model.as_deref()?;
+
+    if roll < config.fraction {
+        Some(Assignment {
+            variant: Variant::Alternate,
+            model: alternate.to_string(),
+        })
+    } else {
+        Some(Assignment {
+            variant: Variant::Control,
+            model: routed_model.to_string(),
+        })
+    }
+}
+
+/// Characters the model wrote for the user in a turn: the content of its
+/// reply tool calls, or its plain text when it answered without the tool.
+pub fn response_chars(new_messages: &[Message]) -> usize {
+    let mut reply_chars = 0;
+    let mut text_chars = 0;
+    for message in new_messages {
+        let Message::Assistant { content, .. } = message else {
+            continue;
+        };
+        for item in content.iter() {
+            match item {
+                AssistantContent::ToolCall(call) if call.function.name == "reply" => {
+                    reply_chars += call.function.arguments["content"]
+                        .as_str()
+                        .map_or(0, |content| content.chars().count());
+                }
+                AssistantContent::Text(text) => text_chars += text.text.chars().count(),
+                _ => {}
+            }
+        }
+    }
+    if reply_chars > 0 {
+        reply_chars
+    } else {
+        text_chars
+    }
+}
+
+/// Write one turn's measurements to `model_experiments` in the background,
+/// under the turn's id so they can be joined with the messages it wrote.
+pub fn record(
+    pool: &SqlitePool,
+    channel_id: &str,
+    turn_id: &str,
+    assignment: &Assignment,
+    trace: &ToolTrace,
+    response_chars: usize,
+) {
+    let pool = pool.clone();
+    let id = uuid::Uuid::new_v4().to_string();
+    let channel_id = channel_id.to_string();
+    let turn_id = turn_id.to_string();
+    let variant = assignment.variant.as_str();
+    let model = assignment.model.clone();
+    let outcome = trace.outcome.as_str();
+    let duration_ms = trace.duration_ms as i64;
+    let input_tokens = trace.input_tokens as i64;
+    let output_tokens = trace.output_tokens as i64;
+
+    tokio::spawn(async move {
+        if let Err(error) = sqlx::query(
+            "INSERT INTO model_experiments \
+             (id, channel_id, turn_id, variant, model, outcome, duration_ms, input_tokens, output_tokens, response_chars) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&channel_id)
+        .bind(&turn_id)
+        .bind(variant)
+        .bind(&model)
+        .bind(outcome)
+        .bind(duration_ms)
+        .bind(input_tokens)
+        .bind(output_tokens)
+        .bind(response_chars as i64)
+        .execute(&pool)
+        .timed("model_experiments.insert")
+        .await
+        {
+            tracing::warn!(%error, "failed to persist model experiment run");
+        }
+    });
+}
+
+/// Aggregates for one variant and model.
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantSummary {
+    pub variant: String,
+    pub model: String,
+    pub turns: i64,
+    /// Turns that didn't complete normally.
+    pub failures: i64,
+    pub avg_duration_ms: f64,
+    pub avg_input_tokens: f64,
+    pub avg_output_tokens: f64,
+    pub avg_response_chars: f64,
+    /// Average USD per turn, if the model has a price in `pricing`.
+    pub avg_cost_usd: Option<f64>,
+}
+
+/// Summarize experiment runs from the last `since_hours` hours.
+pub async fn summarize(
+    pool: &SqlitePool,
+    pricing: &HashMap<String, ModelPricing>,
+    since_hours: u32,
+) -> crate::error::Result<Vec<VariantSummary>> {
+    let rows = sqlx::query(
+        "SELECT variant, model, COUNT(*) AS turns, \
+         SUM(CASE WHEN outcome = 'completed' THEN 0 ELSE 1 END) AS failures, \
+         AVG(duration_ms) AS avg_duration_ms, \
+         AVG(input_tokens) AS avg_input_tokens, \
+         AVG(output_tokens) AS avg_output_tokens, \
+         AVG(response_chars) AS avg_response_chars \
+         FROM model_experiments \
+         WHERE created_at >= datetime('now', ?) \
+         GROUP BY variant, model \
+         ORDER BY variant, model",
+    )
+    .bind(format!("-{since_hours} hours"))
+    .fetch_all(pool)
+    .timed_with("model_experiments.summarize", || {
+        format!("since_hours={since_hours}")
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let model: String = row.get("model");
+            let avg_input_tokens: f64 = row.get("avg_input_tokens");
+            let avg_output_tokens: f64 = row.get("avg_output_tokens");
+            let avg_cost_usd = pricing.get(&model).map(|price| {
+                (avg_input_tokens * price.input + avg_output_tokens * price.output) / 1_000_000.0
+            });
+            VariantSummary {
+                variant: row.get("variant"),
+                model,
+                turns: row.get("turns"),
+                failures: row.get("failures"),
+                avg_duration_ms: row.get("avg_duration_ms"),
+                avg_input_tokens,
+                avg_output_tokens,
+                avg_response_chars: row.get("avg_response_chars"),
+                avg_cost_usd,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_splits_by_fraction() {
+        let config = ExperimentConfig {
+            enabled: true,
+            alternate_model: Some("openai/gpt-4.1-mini".into()),
+            fraction: 0.25,
+            ..Default::default()
+        };
+
+        let alternate = assign_with_roll(&config, "anthropic/claude-sonnet-4", 0.1).unwrap();
+        assert_eq!(alternate.variant, Variant::Alternate);
+        assert_eq!(alternate.model, "openai/gpt-4.1-mini");
+
+        let control = assign_with_roll(&config, "anthropic/claude-sonnet-4", 0.25).unwrap();
+        assert_eq!(control.variant, Variant::Control);
+        assert_eq!(control.model, "anthropic/claude-sonnet-4");
+
+        let unset = ExperimentConfig {
+            alternate_model: None,
+            ..config.clone()
+        };
+        assert!(assign_with_roll(&unset, "anthropic/claude-sonnet-4", 0.1).is_none());
+        let disabled = ExperimentConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(assign_with_roll(&disabled, "anthropic/claude-sonnet-4", 0.1).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! Forum posts: triage of new posts, and the forum's tags for tagging them.
+//!
+//! Each forum post is a thread, so it's its own conversation. When triage is
+//! on, the messaging adapter puts the triage instruction ahead of a new
+//! post's opening message and marks it with `FORUM_TRIAGE_KEY`, which
+//! addresses it to the agent. When tagging is on, every message in a post
+//! carries the forum's tags under `FORUM_TAGS_KEY`, and the channel gets the
+//! `tag_post` tool for them.
+
+use crate::InboundMessage;
+
+/// Metadata key set on the opening message of a new forum post to triage.
+pub const FORUM_TRIAGE_KEY: &str = "forum_triage";
+
+/// Metadata key holding the names of the tags the agent may apply to the
+/// forum post the message is in.
+pub const FORUM_TAGS_KEY: &str = "forum_tags";
+
+/// A new forum post for the agent to triage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForumTriage {
+    /// Name of the forum channel.
+    pub forum: String,
+    /// Title of the post.
+    pub title: String,
+    /// Who handles what, from the forum config.
+    pub routing: Option<String>,
+}
+
+impl ForumTriage {
+    /// The instruction put ahead of the post's text. `tags` are the tags the
+    /// agent may apply, if tagging is on.
+    pub fn instruction(&self, tags: &[String]) -> String {
+        let mut steps = vec!["reply with a summary of the post in a sentence or two".to_string()];
+        if !tags.is_empty() {
+            steps.push(format!(
+                "apply the tags that fit with tag_post (available: {})",
+                tags.join(", ")
+            ));
+        }
+        steps.push(
+            "route it to whoever should handle it, by mentioning them or asking a peer agent, \
+             or answer it yourself if you can"
+                .to_string(),
+        );
+
+        let mut instruction = format!(
+            "[New post \"{}\" in the #{} forum. Triage it: {}.",
+            self.title,
+            self.forum,
+            steps.join("; ")
+        );
+        if let Some(routing) = &self.routing {
+            instruction.push_str(&format!(" Routing guide: {}", routing.trim()));
+        }
+        instruction.push(']');
+        instruction
+    }
+}
+
+/// The tags the agent may apply to the forum post a message is in. None
+/// outside forum posts or when tagging is off.
+pub fn available_tags(message: &InboundMessage) -> Option<Vec<String>> {
+    message
+        .metadata
+        .get(FORUM_TAGS_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triage_instruction_lists_tags_and_routing() {
+        let triage = ForumTriage {
+            forum: "help".into(),
+            title: "Login fails".into(),
+            routing: Some("Billing goes to <@&42>.".into()),
+        };
+
+        let tagged = triage.instruction(&["bug".into(), "billing".into()]);
+        assert!(tagged.starts_with("[New post \"Login fails\" in the #help forum."));
+        assert!(tagged.contains("tag_post (available: bug, billing)"));
+        assert!(tagged.ends_with("Routing guide: Billing goes to <@&42>.]"));
+
+        let untagged = ForumTriage {
+            routing: None,
+            ..triage
+        }
+        .instruction(&[]);
+        assert!(!untagged.contains("tag_post"));
+        assert!(untagged.ends_with("if you can.]"));
+    }
+}
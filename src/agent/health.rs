@@ -0,0 +1,239 @@
+//! Agent health: LLM liveness tracking and automatic restarts.
+
+use crate::config::HealthConfig;
+use crate::events::{self, BusEvent};
+
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Overall state of an agent, derived from its failure streak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// The last LLM call succeeded, or none have been made yet.
+    Healthy,
+    /// Recent calls failed, but fewer than the restart threshold.
+    Degraded,
+    /// The failure streak reached the restart threshold.
+    Failing,
+}
+
+/// Point-in-time health of one agent, as returned by `GET /agents/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentHealth {
+    pub agent_id: String,
+    pub status: HealthStatus,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    /// LLM calls currently queued or in flight.
+    pub pending_calls: usize,
+    /// Turns running and triggers waiting in the agent's task queue. The
+    /// registry doesn't see the queue; the health API fills these in.
+    pub running_tasks: usize,
+    pub queued_tasks: usize,
+    pub restarts: u32,
+    pub last_restart_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct AgentEntry {
+    last_success_at: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    pending_calls: usize,
+    restarts: u32,
+    last_restart_at: Option<DateTime<Utc>>,
+    last_restart: Option<Instant>,
+}
+
+/// Shared registry of per-agent liveness, fed by every LLM call.
+///
+/// When an agent's consecutive failures reach `restart_after_failures`, the
+/// registry broadcasts the agent ID on the restart channel. The main loop
+/// tears down that agent's active channels so they are rebuilt from scratch
+/// on the next message.
+#[derive(Debug)]
+pub struct HealthRegistry {
+    config: ArcSwap<HealthConfig>,
+    agents: Mutex<HashMap<String, AgentEntry>>,
+    restart_tx: broadcast::Sender<String>,
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new(HealthConfig::default())
+    }
+}
+
+impl HealthRegistry {
+    pub fn new(config: HealthConfig) -> Self {
+        let (restart_tx, _) = broadcast::channel(16);
+        Self {
+            config: ArcSwap::from_pointee(config),
+            agents: Mutex::new(HashMap::new()),
+            restart_tx,
+        }
+    }
+
+    /// Swap in new restart thresholds.
+    pub fn set_config(&self, config: HealthConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    /// Receive the IDs of agents that need their channels restarted.
+    pub fn subscribe_restarts(&self) -> broadcast::Receiver<String> {
+        self.restart_tx.subscribe()
+    }
+
+    /// Mark an LLM call as pending until the returned guard is dropped.
+    pub fn call_started(self: &Arc<Self>, agent_id: &str) -> PendingCall {
+        self.entry(agent_id, |entry| entry.pending_calls += 1);
+        PendingCall {
+            registry: self.clone(),
+            agent_id: agent_id.to_string(),
+        }
+    }
+
+    pub fn record_success(&self, agent_id: &str) {
+        self.entry(agent_id, |entry| {
+            entry.last_success_at = Some(Utc::now());
+            entry.consecutive_failures = 0;
+        });
+    }
+
+    /// Record a failed call and publish it, and request a restart if the
+    /// streak crossed the threshold and the agent wasn't restarted within the
+    /// cooldown.
+    pub fn record_failure(&self, agent_id: &str, error: &str) {
+        let config = **self.config.load();
+        let mut restart = false;
+        self.entry(agent_id, |entry| {
+            entry.last_failure_at = Some(Utc::now());
+            entry.last_error = Some(error.to_string());
+            entry.consecutive_failures += 1;
+
+            let cooled_down = entry
+                .last_restart
+                .is_none_or(|at| at.elapsed().as_secs() >= config.restart_cooldown_secs);
+            if config.restart_after_failures > 0
+                && entry.consecutive_failures >= config.restart_after_failures
+                && cooled_down
+            {
+                entry.restarts += 1;
+                entry.last_restart = Some(Instant::now());
+                entry.last_restart_at = Some(Utc::now());
+                restart = true;
+            }
+        });
+        events::publish(BusEvent::AgentError {
+            agent_id: agent_id.to_string(),
+            error: error.to_string(),
+        });
+
+        if restart {
+            tracing::warn!(
+                agent_id,
+                restart_after_failures = config.restart_after_failures,
+                %error,
+                "agent LLM calls keep failing, restarting its channels"
+            );
+            // No receivers just means nothing is running a main loop (tests, CLI).
+            self.restart_tx.send(agent_id.to_string()).ok();
+        }
+    }
+
+    /// Health for the given agents, including ones that haven't made a call yet.
+    pub fn snapshot(&self, agent_ids: &[String]) -> Vec<AgentHealth> {
+        let restart_after = self.config.load().restart_after_failures;
+        let agents = self.agents.lock().expect("health registry lock poisoned");
+        let default_entry = AgentEntry::default();
+
+        agent_ids
+            .iter()
+            .map(|agent_id| {
+                let entry = agents.get(agent_id).unwrap_or(&default_entry);
+                let status = match entry.consecutive_failures {
+                    0 => HealthStatus::Healthy,
+                    failures if restart_after > 0 && failures >= restart_after => {
+                        HealthStatus::Failing
+                    }
+                    _ => HealthStatus::Degraded,
+                };
+                AgentHealth {
+                    agent_id: agent_id.clone(),
+                    status,
+                    last_success_at: entry.last_success_at,
+                    last_failure_at: entry.last_failure_at,
+                    last_error: entry.last_error.clone(),
+                    consecutive_failures: entry.consecutive_failures,
+                    pending_calls: entry.pending_calls,
+                    running_tasks: 0,
+                    queued_tasks: 0,
+                    restarts: entry.restarts,
+                    last_restart_at: entry.last_restart_at,
+                }
+            })
+            .collect()
+    }
+
+    fn entry(&self, agent_id: &str, update: impl FnOnce(&mut AgentEntry)) {
+        let mut agents = self.agents.lock().expect("health registry lock poisoned");
+        update(agents.entry(agent_id.to_string()).or_default());
+    }
+}
+
+/// Decrements an agent's pending call count when dropped.
+#[derive(Debug)]
+pub struct PendingCall {
+    registry: Arc<HealthRegistry>,
+    agent_id: String,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.registry.entry(&self.agent_id, |entry| {
+            entry.pending_calls = entry.pending_calls.saturating_sub(1);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_streak_triggers_one_restart_per_cooldown() {
+        let registry = HealthRegistry::new(HealthConfig {
+            restart_after_failures: 2,
+            restart_cooldown_secs: 3600,
+        });
+        let mut restarts = registry.subscribe_restarts();
+        let ids = vec!["main".to_string()];
+
+        registry.record_failure("main", "503");
+        assert_eq!(registry.snapshot(&ids)[0].status, HealthStatus::Degraded);
+        assert!(restarts.try_recv().is_err());
+
+        registry.record_failure("main", "503");
+        assert_eq!(restarts.try_recv().unwrap(), "main");
+        assert_eq!(registry.snapshot(&ids)[0].status, HealthStatus::Failing);
+
+        // Still inside the cooldown.
+        registry.record_failure("main", "503");
+        assert!(restarts.try_recv().is_err());
+
+        registry.record_success("main");
+        let health = &registry.snapshot(&ids)[0];
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(health.restarts, 1);
+    }
+}
@@ -11,6 +11,7 @@
 use crate::AgentDeps;
 use crate::ProcessType;
 use crate::config::IngestionConfig;
+use crate::db::TimedQuery as _;
 use crate::llm::SpacebotModel;
 
 use anyhow::Context as _;
@@ -184,7 +185,7 @@ async fn process_file(
     let chunks = chunk_text(&content, config.chunk_size);
     let total_chunks = chunks.len();
 
-    let completed = load_completed_chunks(&deps.sqlite_pool, &hash).await?;
+    let completed = load_completed_chunks(&deps.sqlite_read, &hash).await?;
     let remaining = total_chunks - completed.len();
 
     // Record file-level tracking (idempotent — skips if already exists from a previous run)
@@ -321,6 +322,7 @@ async fn load_completed_chunks(pool: &SqlitePool, hash: &str) -> anyhow::Result<
     )
     .bind(hash)
     .fetch_all(pool)
+    .timed_with("ingestion_progress.load", || format!("content_hash={hash}"))
     .await
     .context("failed to load ingestion progress")?;
 
@@ -346,6 +348,9 @@ async fn record_chunk_completed(
     .bind(total_chunks)
     .bind(filename)
     .execute(pool)
+    .timed_with("ingestion_progress.insert", || {
+        format!("content_hash={hash}")
+    })
     .await
     .context("failed to record ingestion progress")?;
 
@@ -357,6 +362,9 @@ async fn delete_progress(pool: &SqlitePool, hash: &str) -> anyhow::Result<()> {
     sqlx::query("DELETE FROM ingestion_progress WHERE content_hash = ?")
         .bind(hash)
         .execute(pool)
+        .timed_with("ingestion_progress.delete", || {
+            format!("content_hash={hash}")
+        })
         .await
         .context("failed to clean up ingestion progress")?;
 
@@ -390,6 +398,7 @@ async fn upsert_ingestion_file(
     .bind(file_size)
     .bind(total_chunks)
     .execute(pool)
+    .timed_with("ingestion_files.upsert", || format!("content_hash={hash}"))
     .await
     .context("failed to upsert ingestion file record")?;
 
@@ -408,6 +417,7 @@ async fn complete_ingestion_file(
     .bind(status)
     .bind(hash)
     .execute(pool)
+    .timed_with("ingestion_files.set_status", || format!("content_hash={hash}"))
     .await
     .context("failed to update ingestion file status")?;
 
@@ -466,8 +476,9 @@ async fn process_chunk(
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
 
     let conversation_logger =
         crate::conversation::history::ConversationLogger::new(deps.sqlite_pool.clone());
@@ -476,6 +487,7 @@ async fn process_chunk(
         deps.memory_search.clone(),
         conversation_logger,
         channel_store,
+        None,
     );
 
     let agent = AgentBuilder::new(model)
@@ -0,0 +1,202 @@
+//! Listening modes: which inbound messages start a channel turn.
+
+use crate::agent::catch_up::CATCH_UP_KEY;
+use crate::agent::controls::REACTION_CONTROL_KEY;
+use crate::agent::forums::FORUM_TRIAGE_KEY;
+use crate::config::{ListenMode, ListenRule, ListeningConfig, ROLE_MENTION_KEY};
+use crate::{InboundMessage, MessageContent};
+
+/// Metadata key set on messages the channel should keep as context only.
+const PASSIVE_KEY: &str = "listening_passive";
+
+/// Whether the message is addressed to the agent: a DM, a mention or reply,
+/// a mention of a role mapped to it, a slash command, reaction control, or
+/// click on one of its buttons or menus, a message in a thread the agent is
+/// following, a new forum post to triage, the last message missed while the
+/// bot was down when it should summarize them, a webchat, webhook, stdio,
+/// email, WhatsApp, or SMS message, or a question from another agent.
+pub fn is_addressed(message: &InboundMessage) -> bool {
+    let flag = |key: &str| {
+        message
+            .metadata
+            .get(key)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    };
+
+    if matches!(
+        message.source.as_str(),
+        "webchat" | "webhook" | "stdio" | "email" | "whatsapp" | "sms"
+    ) || message.sender_id.starts_with("agent:")
+        || message.conversation_id.contains(":dm:")
+        || flag("discord_mentions_bot")
+        || flag("slack_mentions_bot")
+        || flag("matrix_mentions_bot")
+        || flag("irc_mentions_bot")
+        || flag("xmpp_mentions_bot")
+        || flag("reply_to_is_bot")
+        || flag("discord_followed_thread")
+        || flag(ROLE_MENTION_KEY)
+        || message.metadata.contains_key("slack_command")
+        || message.metadata.contains_key("discord_command")
+        || message.metadata.contains_key(REACTION_CONTROL_KEY)
+        || message.metadata.contains_key(FORUM_TRIAGE_KEY)
+        || message.metadata.contains_key(CATCH_UP_KEY)
+        || matches!(message.content, MessageContent::Interaction { .. })
+    {
+        return true;
+    }
+
+    // Telegram has no mention flag; look for the bot's handle in the text.
+    message
+        .metadata
+        .get("telegram_bot_username")
+        .and_then(|value| value.as_str())
+        .is_some_and(|username| {
+            message
+                .content
+                .to_string()
+                .to_lowercase()
+                .contains(&format!("@{}", username.to_lowercase()))
+        })
+}
+
+/// Whether the message should start a turn in its conversation. `guild_rule`
+/// is the listening rule of the message's guild, if it overrides one.
+pub fn should_respond(
+    config: &ListeningConfig,
+    guild_rule: Option<&ListenRule>,
+    message: &InboundMessage,
+) -> bool {
+    should_respond_with_roll(config, guild_rule, message, rand::random::<f64>())
+}
+
+/// `should_respond` with the random draw, in `[0, 1)`, passed in.
+fn should_respond_with_roll(
+    config: &ListeningConfig,
+    guild_rule: Option<&ListenRule>,
+    message: &InboundMessage,
+    roll: f64,
+) -> bool {
+    if is_addressed(message) {
+        return true;
+    }
+
+    let rule = config.rule_for(&message.conversation_id, guild_rule);
+    match rule.mode {
+        ListenMode::All => true,
+        ListenMode::MentionOnly => false,
+        ListenMode::Keyword => {
+            let text = message.content.to_string().to_lowercase();
+            rule.keywords
+                .iter()
+                .any(|keyword| text.contains(&keyword.to_lowercase()))
+        }
+        ListenMode::Probabilistic => roll < rule.chance,
+    }
+}
+
+/// Mark a message as context only: the channel persists it and shows it to
+/// the model with the next turn, but doesn't start a turn for it.
+pub fn mark_passive(message: &mut InboundMessage) {
+    message
+        .metadata
+        .insert(PASSIVE_KEY.into(), serde_json::Value::Bool(true));
+}
+
+pub fn is_passive(message: &InboundMessage) -> bool {
+    message
+        .metadata
+        .get(PASSIVE_KEY)
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    fn message(conversation_id: &str, text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: conversation_id.into(),
+            sender_id: "42".into(),
+            agent_id: None,
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn test_modes_filter_unaddressed_messages() {
+        let config = ListeningConfig {
+            rule: ListenRule {
+                mode: ListenMode::MentionOnly,
+                ..Default::default()
+            },
+            channels: HashMap::from([
+                (
+                    "discord:1:ops".to_string(),
+                    ListenRule {
+                        mode: ListenMode::Keyword,
+                        keywords: vec!["Deploy".into()],
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "discord:1:random".to_string(),
+                    ListenRule {
+                        mode: ListenMode::Probabilistic,
+                        chance: 0.2,
+                        ..Default::default()
+                    },
+                ),
+            ]),
+        };
+
+        let chatter = message("discord:1:general", "anyone around?");
+        assert!(!should_respond_with_roll(&config, None, &chatter, 0.0));
+        let mut mention = chatter.clone();
+        mention
+            .metadata
+            .insert("discord_mentions_bot".into(), true.into());
+        assert!(should_respond_with_roll(&config, None, &mention, 0.0));
+
+        assert!(should_respond_with_roll(
+            &config,
+            None,
+            &message("discord:1:ops", "deploy is stuck"),
+            0.9
+        ));
+        assert!(!should_respond_with_roll(
+            &config,
+            None,
+            &message("discord:1:ops", "lunch?"),
+            0.0
+        ));
+
+        let random = message("discord:1:random", "hello");
+        assert!(should_respond_with_roll(&config, None, &random, 0.1));
+        assert!(!should_respond_with_roll(&config, None, &random, 0.5));
+
+        // A guild's rule applies where the conversation has no override.
+        let guild_rule = ListenRule::default();
+        assert!(should_respond_with_roll(
+            &config,
+            Some(&guild_rule),
+            &chatter,
+            0.0
+        ));
+        assert!(!should_respond_with_roll(
+            &config,
+            Some(&guild_rule),
+            &random,
+            0.5
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+//! Reasoning traces: why the agent answered as it did.
+//!
+//! Reasoning (thinking) segments the model returns, and the text it wrote
+//! between tool calls, are recorded by the turn's `ToolLoop` but never
+//! streamed as tool loop steps, added to history, or sent to an adapter.
+//! After the turn they're written to `agent_traces`, keyed by the id of the
+//! bot message the turn produced, for post-incident analysis.
+
+use crate::agent::tool_loop::{ToolLoopOutcome, ToolLoopStep, ToolTrace};
+use crate::db::TimedQuery as _;
+use crate::tools::ReplyMessageId;
+
+use serde::{Deserialize, Serialize};
+
+/// Reasoning segments longer than this are truncated.
+const REASONING_SEGMENT_MAX_BYTES: usize = 16_000;
+
+/// One block of model reasoning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReasoningSegment {
+    /// The LLM call that produced it, counting from 1.
+    pub iteration: usize,
+    pub text: String,
+    pub elapsed_ms: u64,
+}
+
+impl ReasoningSegment {
+    pub fn new(iteration: usize, text: &str, elapsed_ms: u64) -> Self {
+        Self {
+            iteration,
+            text: crate::tools::truncate_output(text, REASONING_SEGMENT_MAX_BYTES),
+            elapsed_ms,
+        }
+    }
+}
+
+/// A turn's reasoning, waiting for the id of the message it produced.
+#[derive(Debug)]
+pub struct ReasoningTrace {
+    outcome: ToolLoopOutcome,
+    reasoning: Vec<ReasoningSegment>,
+    /// The model's responses in the loop: text written alongside tool calls
+    /// and which tools it picked.
+    deliberation: Vec<ToolLoopStep>,
+    reply_message_id: ReplyMessageId,
+}
+
+impl ReasoningTrace {
+    /// Take the reasoning out of a finished tool loop. Returns None when the
+    /// turn had neither reasoning nor tool calls, so there's nothing to
+    /// explain beyond the reply itself.
+    pub fn from_tool_trace(trace: &ToolTrace, reply_message_id: ReplyMessageId) -> Option<Self> {
+        if trace.reasoning.is_empty() && trace.tool_calls == 0 {
+            return None;
+        }
+        let deliberation = trace
+            .steps
+            .iter()
+            .filter(|step| matches!(step, ToolLoopStep::Response { .. }))
+            .cloned()
+            .collect();
+
+        Some(Self {
+            outcome: trace.outcome,
+            reasoning: trace.reasoning.clone(),
+            deliberation,
+            reply_message_id,
+        })
+    }
+
+    /// The message the turn produced: the last reply sent through the reply
+    /// tool, or else the fallback text reply.
+    fn message_id(&self, fallback_message_id: Option<String>) -> Option<String> {
+        self.reply_message_id
+            .lock()
+            .expect("reply message id lock poisoned")
+            .clone()
+            .or(fallback_message_id)
+    }
+
+    /// Write the trace to `agent_traces` in the background. Turns that sent
+    /// no message aren't recorded.
+    pub fn persist(
+        self,
+        pool: &sqlx::SqlitePool,
+        channel_id: &str,
+        fallback_message_id: Option<String>,
+    ) {
+        let Some(message_id) = self.message_id(fallback_message_id) else {
+            return;
+        };
+        let (reasoning, deliberation) = match (
+            serde_json::to_string(&self.reasoning),
+            serde_json::to_string(&self.deliberation),
+        ) {
+            (Ok(reasoning), Ok(deliberation)) => (reasoning, deliberation),
+            (Err(error), _) | (_, Err(error)) => {
+                tracing::warn!(%error, "failed to serialize agent trace");
+                return;
+            }
+        };
+        let pool = pool.clone();
+        let id = uuid::Uuid::new_v4().to_string();
+        let channel_id = channel_id.to_string();
+
+        tokio::spawn(async move {
+            if let Err(error) = sqlx::query(
+                "INSERT INTO agent_traces (id, message_id, channel_id, outcome, reasoning, deliberation) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&message_id)
+            .bind(&channel_id)
+            .bind(self.outcome.as_str())
+            .bind(&reasoning)
+            .bind(&deliberation)
+            .execute(&pool)
+            .timed("agent_traces.insert")
+            .await
+            {
+                tracing::warn!(%error, "failed to persist agent trace");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tool_loop::ToolLoop;
+    use crate::config::ToolLoopConfig;
+
+    #[test]
+    fn test_trace_keyed_by_reply_then_fallback() {
+        let tool_loop = ToolLoop::new(&ToolLoopConfig::default());
+        tool_loop.begin_iteration().unwrap();
+        let plain = tool_loop.finish(&Ok("hi".into()));
+        assert!(ReasoningTrace::from_tool_trace(&plain, Default::default()).is_none());
+
+        tool_loop.record_reasoning("The user wants the deploy status, check CI first.");
+        tool_loop.record(tool_loop.response_step(None, vec!["reply".into()]));
+        tool_loop.record(tool_loop.tool_call_step("reply", "{}"));
+        let trace = tool_loop.finish(&Ok(String::new()));
+
+        let reply_id = crate::tools::new_reply_message_id();
+        let reasoning = ReasoningTrace::from_tool_trace(&trace, reply_id.clone()).unwrap();
+        assert_eq!(reasoning.reasoning.len(), 1);
+        assert_eq!(reasoning.deliberation.len(), 1);
+        assert_eq!(
+            reasoning.message_id(Some("fallback".into())).as_deref(),
+            Some("fallback")
+        );
+
+        *reply_id.lock().unwrap() = Some("reply".into());
+        assert_eq!(
+            reasoning.message_id(Some("fallback".into())).as_deref(),
+            Some("reply")
+        );
+    }
+}
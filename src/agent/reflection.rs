@@ -0,0 +1,214 @@
+//! Reflection: critique and revise channel replies before they are sent.
+
+use crate::db::TimedQuery as _;
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, ChannelId, ProcessType};
+
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel, Prompt};
+use serde::Deserialize;
+
+use std::time::Instant;
+
+/// Reviews a channel's draft replies against the agent's persona and channel
+/// rules using a cheap model, and swaps in a revision when the critique asks
+/// for one.
+///
+/// Created per turn when reflection is enabled and handed to the reply tool.
+/// Every verdict is written to `reflection_log` so critiques can be inspected
+/// after the fact.
+#[derive(Clone)]
+pub struct Reflector {
+    deps: AgentDeps,
+    channel_id: ChannelId,
+    conversation_context: Option<String>,
+}
+
+impl std::fmt::Debug for Reflector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reflector")
+            .field("channel_id", &self.channel_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// LLM response shape for a reflection pass.
+#[derive(Debug, Deserialize)]
+struct ReflectionVerdict {
+    approved: bool,
+    #[serde(default)]
+    critique: String,
+    #[serde(default)]
+    revised: Option<String>,
+}
+
+impl Reflector {
+    pub fn new(
+        deps: AgentDeps,
+        channel_id: ChannelId,
+        conversation_context: Option<String>,
+    ) -> Self {
+        Self {
+            deps,
+            channel_id,
+            conversation_context,
+        }
+    }
+
+    /// Review a draft and return the text that should be sent.
+    ///
+    /// Any failure (render error, LLM error, unparseable verdict) falls back to
+    /// the original draft so reflection can never block a reply.
+    #[tracing::instrument(skip(self, draft), fields(channel_id = %self.channel_id, agent_id = %self.deps.agent_id))]
+    pub async fn review(&self, draft: &str) -> String {
+        let started = Instant::now();
+        let rc = &self.deps.runtime_config;
+        let prompt_engine = rc.prompts.load();
+
+        let identity_context = {
+            let rendered = rc.identity.load().render();
+            if rendered.is_empty() {
+                None
+            } else {
+                Some(rendered)
+            }
+        };
+
+        let (system_prompt, review_prompt) = match (
+            prompt_engine.render_static("reflection"),
+            prompt_engine.render_system_reflection_draft(
+                identity_context.as_deref(),
+                self.conversation_context.as_deref(),
+                draft,
+            ),
+        ) {
+            (Ok(system_prompt), Ok(review_prompt)) => (system_prompt, review_prompt),
+            (Err(error), _) | (_, Err(error)) => {
+                tracing::warn!(%error, "failed to render reflection prompt, sending draft");
+                return draft.to_string();
+            }
+        };
+
+        let routing = rc.routing.load();
+        let model_name = rc
+            .reflection
+            .load()
+            .model
+            .clone()
+            .unwrap_or_else(|| routing.resolve(ProcessType::Compactor, None).to_string());
+        let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
+            .with_agent(&self.deps.agent_id)
+            .with_routing((**routing).clone());
+        let agent = AgentBuilder::new(model).preamble(&system_prompt).build();
+
+        let response = match agent.prompt(&review_prompt).await {
+            Ok(response) => response,
+            Err(error) => {
+                tracing::warn!(%error, model = %model_name, "reflection LLM call failed, sending draft");
+                return draft.to_string();
+            }
+        };
+
+        let verdict = match parse_verdict(&response) {
+            Ok(verdict) => verdict,
+            Err(error) => {
+                tracing::warn!(%error, raw = %response, "failed to parse reflection verdict, sending draft");
+                return draft.to_string();
+            }
+        };
+
+        let revised = verdict
+            .revised
+            .as_deref()
+            .map(str::trim)
+            .filter(|text| !verdict.approved && !text.is_empty());
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        tracing::info!(
+            approved = verdict.approved,
+            revised = revised.is_some(),
+            critique = %verdict.critique,
+            model = %model_name,
+            duration_ms,
+            "reply reflection complete"
+        );
+        self.log(draft, &verdict, revised, &model_name, duration_ms);
+
+        revised.unwrap_or(draft).to_string()
+    }
+
+    /// Persist a reflection verdict. Fire-and-forget.
+    fn log(
+        &self,
+        draft: &str,
+        verdict: &ReflectionVerdict,
+        revised: Option<&str>,
+        model_name: &str,
+        duration_ms: u64,
+    ) {
+        let pool = self.deps.sqlite_pool.clone();
+        let id = uuid::Uuid::new_v4().to_string();
+        let channel_id = self.channel_id.to_string();
+        let draft = draft.to_string();
+        let approved = verdict.approved;
+        let critique = verdict.critique.clone();
+        let revised = revised.map(str::to_string);
+        let model_name = model_name.to_string();
+
+        tokio::spawn(async move {
+            if let Err(error) = sqlx::query(
+                "INSERT INTO reflection_log (id, channel_id, draft, approved, critique, revised, model, duration_ms) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&channel_id)
+            .bind(&draft)
+            .bind(approved)
+            .bind(&critique)
+            .bind(&revised)
+            .bind(&model_name)
+            .bind(duration_ms as i64)
+            .execute(&pool)
+            .timed("reflection_log.insert")
+            .await
+            {
+                tracing::warn!(%error, "failed to persist reflection verdict");
+            }
+        });
+    }
+}
+
+/// Parse the verdict JSON, tolerating markdown code fences around it.
+fn parse_verdict(response: &str) -> serde_json::Result<ReflectionVerdict> {
+    let cleaned = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_verdict_with_fences() {
+        let verdict = parse_verdict(
+            "```json\n{\"approved\": false, \"critique\": \"too long\", \"revised\": \"short\"}\n```",
+        )
+        .expect("fenced verdict should parse");
+        assert!(!verdict.approved);
+        assert_eq!(verdict.critique, "too long");
+        assert_eq!(verdict.revised.as_deref(), Some("short"));
+    }
+
+    #[test]
+    fn test_parse_verdict_approved_without_revision() {
+        let verdict = parse_verdict(r#"{"approved": true, "critique": "fine", "revised": null}"#)
+            .expect("approved verdict should parse");
+        assert!(verdict.approved);
+        assert!(verdict.revised.is_none());
+    }
+}
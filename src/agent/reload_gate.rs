@@ -0,0 +1,137 @@
+//! Keeps config reloads and channel turns apart.
+//!
+//! Every channel turn holds the agent's gate open for as long as it runs. A
+//! reload closes it: new turns wait, and the reload waits for the running
+//! ones to finish before it applies, so no turn sees a mix of old and new
+//! settings. A reload that can't drain within [`DRAIN_TIMEOUT`] applies
+//! anyway, rather than holding the agent's channels up behind a long turn.
+//!
+//! A reload that touches several agents, or instance-level settings like
+//! bindings and provider keys, closes every gate with [`close_all`] before
+//! it swaps anything, so the agents drain together within one timeout.
+
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a reload waits for running turns to finish.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Held by a turn while it runs. Dropping it lets a waiting reload apply.
+pub type TurnGuard = OwnedRwLockReadGuard<()>;
+
+/// Held by a reload while it applies. Dropping it lets new turns start.
+pub type ReloadGuard = OwnedRwLockWriteGuard<()>;
+
+/// One agent's gate between turns and reloads.
+#[derive(Debug, Default)]
+pub struct ReloadGate {
+    lock: Arc<RwLock<()>>,
+}
+
+impl ReloadGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait out a reload in progress, then keep reloads from applying until
+    /// the guard is dropped.
+    pub async fn enter(&self) -> TurnGuard {
+        self.lock.clone().read_owned().await
+    }
+
+    /// Stop new turns from starting and wait for the running ones to finish.
+    /// None if they're still running after [`DRAIN_TIMEOUT`]; the gate is
+    /// open again then, and the caller reloads anyway.
+    pub async fn close(&self, agent_id: &str) -> Option<ReloadGuard> {
+        self.close_within(agent_id, DRAIN_TIMEOUT).await
+    }
+
+    async fn close_within(&self, agent_id: &str, timeout: Duration) -> Option<ReloadGuard> {
+        match tokio::time::timeout(timeout, self.lock.clone().write_owned()).await {
+            Ok(guard) => Some(guard),
+            Err(_) => {
+                tracing::warn!(
+                    agent_id,
+                    "turns still running after drain timeout, reloading anyway"
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Close several agents' gates at once, waiting for their turns to finish
+/// together. The guards are held until the returned list is dropped.
+pub async fn close_all<'a>(
+    gates: impl IntoIterator<Item = (&'a str, &'a ReloadGate)>,
+) -> Vec<Option<ReloadGuard>> {
+    futures::future::join_all(
+        gates
+            .into_iter()
+            .map(|(agent_id, gate)| gate.close(agent_id)),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload_waits_for_turns_and_holds_new_ones() {
+        let gate = Arc::new(ReloadGate::new());
+        let running = gate.enter().await;
+
+        let reload = tokio::spawn({
+            let gate = gate.clone();
+            async move { gate.close("main").await.is_some() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!reload.is_finished());
+
+        // A turn that starts while the reload waits is held behind it.
+        let next_turn = tokio::spawn({
+            let gate = gate.clone();
+            async move { drop(gate.enter().await) }
+        });
+        tokio::task::yield_now().await;
+        assert!(!next_turn.is_finished());
+
+        drop(running);
+        assert!(reload.await.unwrap());
+        next_turn.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_close_all_holds_every_agent_at_once() {
+        let (first, second) = (ReloadGate::new(), ReloadGate::new());
+        let first_turn = first.enter().await;
+        let second_turn = second.enter().await;
+
+        let check = async {
+            tokio::task::yield_now().await;
+            // Both gates are closing while the first agent is still
+            // draining, rather than one after the other.
+            for gate in [&first, &second] {
+                let entered = tokio::time::timeout(Duration::from_millis(10), gate.enter()).await;
+                assert!(entered.is_err());
+            }
+            drop(first_turn);
+            drop(second_turn);
+        };
+        let (guards, ()) = tokio::join!(close_all([("first", &first), ("second", &second)]), check);
+        assert!(guards.iter().all(Option::is_some));
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_after_drain_timeout() {
+        let gate = ReloadGate::new();
+        let _running = gate.enter().await;
+        let closed = gate.close_within("main", Duration::from_millis(10)).await;
+        assert!(closed.is_none());
+        // The gate opened again when the reload gave up.
+        drop(gate.enter().await);
+    }
+}
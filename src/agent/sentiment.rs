@@ -0,0 +1,259 @@
+//! Reaction sentiment: emoji reactions on the bot's replies, read as a
+//! signal of how well each reply was received.
+//!
+//! The messaging adapter turns a reaction with a known sentiment into an
+//! inbound message carrying `REACTION_SENTIMENT_KEY`. It never starts a turn.
+//! Each agent in the conversation writes it to `reaction_sentiment`, which the
+//! sentiment API sums per channel and day. With `feedback` on, a channel whose
+//! recent replies were mostly reacted to negatively is told so in its prompt.
+
+use crate::InboundMessage;
+use crate::config::SentimentConfig;
+use crate::db::TimedQuery as _;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+/// Metadata key holding a serialized `SentimentReaction`, on inbound messages
+/// from a reaction to one of the bot's replies.
+pub const REACTION_SENTIMENT_KEY: &str = "reaction_sentiment";
+
+/// Reactions counted as approval of a reply.
+const POSITIVE: &[&str] = &[
+    "👍", "❤", "😂", "🎉", "🙏", "🔥", "💯", "✅", "😄", "👏", "😍", "🥰", "🙌", "⭐",
+];
+
+/// Reactions counted as disapproval of a reply.
+const NEGATIVE: &[&str] = &[
+    "👎", "😕", "😠", "😡", "❌", "🤦", "🙄", "💩", "😒", "😞", "🤮",
+];
+
+/// A reaction added to or removed from one of the bot's replies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentimentReaction {
+    /// Platform ID of the reply reacted to.
+    pub target_message_id: String,
+    pub emoji: String,
+    /// 1 for a positive reaction, -1 for a negative one.
+    pub score: i64,
+    /// Whether the reaction was taken back.
+    pub removed: bool,
+}
+
+/// The sentiment of an emoji: 1, -1, or None if it doesn't carry one.
+/// Variation selectors and skin tones are ignored.
+pub fn score(emoji: &str) -> Option<i64> {
+    let emoji: String = emoji
+        .chars()
+        .filter(|c| *c != '\u{FE0F}' && !('\u{1F3FB}'..='\u{1F3FF}').contains(c))
+        .collect();
+    if POSITIVE.contains(&emoji.as_str()) {
+        Some(1)
+    } else if NEGATIVE.contains(&emoji.as_str()) {
+        Some(-1)
+    } else {
+        None
+    }
+}
+
+/// The sentiment reaction an inbound message carries, if any.
+pub fn requested(message: &InboundMessage) -> Option<SentimentReaction> {
+    message
+        .metadata
+        .get(REACTION_SENTIMENT_KEY)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+/// Write a reaction to `reaction_sentiment`, or delete it if it was removed,
+/// in the background.
+pub fn record(pool: &SqlitePool, channel_id: &str, user_id: &str, reaction: SentimentReaction) {
+    let pool = pool.clone();
+    let id = uuid::Uuid::new_v4().to_string();
+    let channel_id = channel_id.to_string();
+    let user_id = user_id.to_string();
+
+    tokio::spawn(async move {
+        let result = if reaction.removed {
+            sqlx::query(
+                "DELETE FROM reaction_sentiment \
+                 WHERE target_message_id = ? AND user_id = ? AND emoji = ?",
+            )
+            .bind(&reaction.target_message_id)
+            .bind(&user_id)
+            .bind(&reaction.emoji)
+            .execute(&pool)
+            .timed("reaction_sentiment.delete")
+            .await
+        } else {
+            sqlx::query(
+                "INSERT OR IGNORE INTO reaction_sentiment \
+                 (id, channel_id, target_message_id, user_id, emoji, score) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&channel_id)
+            .bind(&reaction.target_message_id)
+            .bind(&user_id)
+            .bind(&reaction.emoji)
+            .bind(reaction.score)
+            .execute(&pool)
+            .timed("reaction_sentiment.insert")
+            .await
+        };
+        if let Err(error) = result {
+            tracing::warn!(%error, channel_id, "failed to persist reaction sentiment");
+        }
+    });
+}
+
+/// Positive and negative reactions on one channel's replies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SentimentTally {
+    pub positive: i64,
+    pub negative: i64,
+}
+
+impl SentimentTally {
+    pub fn total(&self) -> i64 {
+        self.positive + self.negative
+    }
+
+    /// Share of reactions that were positive. None without any reactions.
+    pub fn satisfaction(&self) -> Option<f64> {
+        (self.total() > 0).then(|| self.positive as f64 / self.total() as f64)
+    }
+}
+
+/// Reactions on a channel's replies over the last `since_hours` hours.
+pub async fn channel_tally(
+    pool: &SqlitePool,
+    channel_id: &str,
+    since_hours: u64,
+) -> crate::error::Result<SentimentTally> {
+    let row = sqlx::query(
+        "SELECT COALESCE(SUM(score > 0), 0) AS positive, \
+         COALESCE(SUM(score < 0), 0) AS negative \
+         FROM reaction_sentiment \
+         WHERE channel_id = ? AND created_at >= datetime('now', ?)",
+    )
+    .bind(channel_id)
+    .bind(format!("-{since_hours} hours"))
+    .fetch_one(pool)
+    .timed_with("reaction_sentiment.tally", || {
+        format!("channel_id={channel_id}")
+    })
+    .await?;
+
+    Ok(SentimentTally {
+        positive: row.get("positive"),
+        negative: row.get("negative"),
+    })
+}
+
+/// Reactions for one channel on one day.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySentiment {
+    pub channel_id: String,
+    /// UTC date, `YYYY-MM-DD`.
+    pub day: String,
+    #[serde(flatten)]
+    pub tally: SentimentTally,
+    pub satisfaction: Option<f64>,
+}
+
+/// Reactions per channel and day over the last `days` days, oldest first.
+/// Limited to one channel if `channel_id` is given.
+pub async fn daily(
+    pool: &SqlitePool,
+    channel_id: Option<&str>,
+    days: u32,
+) -> crate::error::Result<Vec<DailySentiment>> {
+    let rows = sqlx::query(
+        "SELECT channel_id, date(created_at) AS day, \
+         SUM(score > 0) AS positive, SUM(score < 0) AS negative \
+         FROM reaction_sentiment \
+         WHERE created_at >= datetime('now', ?) AND (? IS NULL OR channel_id = ?) \
+         GROUP BY channel_id, day \
+         ORDER BY day, channel_id",
+    )
+    .bind(format!("-{days} days"))
+    .bind(channel_id)
+    .bind(channel_id)
+    .fetch_all(pool)
+    .timed_with("reaction_sentiment.daily", || {
+        format!("channel_id={channel_id:?}")
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let tally = SentimentTally {
+                positive: row.get("positive"),
+                negative: row.get("negative"),
+            };
+            DailySentiment {
+                channel_id: row.get("channel_id"),
+                day: row.get("day"),
+                tally,
+                satisfaction: tally.satisfaction(),
+            }
+        })
+        .collect())
+}
+
+/// A note for the channel's prompt when most recent reactions to its replies
+/// were negative. None if feedback is off or there are too few reactions.
+pub fn feedback_note(config: &SentimentConfig, tally: SentimentTally) -> Option<String> {
+    if !config.feedback
+        || tally.total() < i64::from(config.min_reactions)
+        || tally.negative <= tally.positive
+    {
+        return None;
+    }
+    Some(format!(
+        "Recent replies in this channel were poorly received: {} of {} reactions in the last \
+         {} hours were negative. Consider whether your replies have been too long, off-topic, \
+         or unhelpful, and adjust.",
+        tally.negative,
+        tally.total(),
+        config.window_hours
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_emojis_and_notes_mostly_negative_channels() {
+        assert_eq!(score("👍"), Some(1));
+        assert_eq!(score("👍🏽"), Some(1));
+        assert_eq!(score("❤️"), Some(1));
+        assert_eq!(score("👎"), Some(-1));
+        assert_eq!(score("🦀"), None);
+
+        let config = SentimentConfig {
+            feedback: true,
+            ..Default::default()
+        };
+        let negative = SentimentTally {
+            positive: 1,
+            negative: 3,
+        };
+        assert!(
+            feedback_note(&config, negative)
+                .unwrap()
+                .contains("3 of 4 reactions")
+        );
+        assert_eq!(negative.satisfaction(), Some(0.25));
+
+        let too_few = SentimentTally {
+            positive: 0,
+            negative: 2,
+        };
+        assert!(feedback_note(&config, too_few).is_none());
+        let off = SentimentConfig::default();
+        assert!(feedback_note(&off, negative).is_none());
+    }
+}
@@ -0,0 +1,225 @@
+//! Agent activity stats: one row per channel turn, aggregated into the
+//! numbers a dashboard needs.
+
+use crate::agent::tool_loop::ToolTrace;
+use crate::config::ModelPricing;
+use crate::db::TimedQuery as _;
+use crate::otel::db_write;
+
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+
+/// Channels listed in [`AgentStats::busiest_channels`].
+const BUSIEST_CHANNELS: i64 = 5;
+
+/// Record a finished channel turn. Fire-and-forget.
+pub fn record(pool: &SqlitePool, channel_id: &str, turn_id: &str, model: &str, trace: &ToolTrace) {
+    let pool = pool.clone();
+    let id = uuid::Uuid::new_v4().to_string();
+    let channel_id = channel_id.to_string();
+    let turn_id = turn_id.to_string();
+    let model = model.to_string();
+    let outcome = trace.outcome.as_str();
+    let duration_ms = trace.duration_ms as i64;
+    let tool_calls = trace.tool_calls as i64;
+    let input_tokens = trace.input_tokens as i64;
+    let output_tokens = trace.output_tokens as i64;
+
+    let write = async move {
+        sqlx::query(
+            "INSERT INTO turn_stats \
+             (id, channel_id, turn_id, model, outcome, duration_ms, tool_calls, input_tokens, output_tokens) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&channel_id)
+        .bind(&turn_id)
+        .bind(&model)
+        .bind(outcome)
+        .bind(duration_ms)
+        .bind(tool_calls)
+        .bind(input_tokens)
+        .bind(output_tokens)
+        .execute(&pool)
+        .await
+        .inspect_err(|error| tracing::warn!(%error, "failed to persist turn stats"))
+    };
+    tokio::spawn(db_write("turn_stats", "insert", write));
+}
+
+/// An agent's channel turns over a recent window.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AgentStats {
+    pub since_hours: u32,
+    pub turns: i64,
+    /// Turns that failed, timed out, or hit `max_turns`.
+    pub errors: i64,
+    /// Turns per tool loop outcome.
+    pub outcomes: HashMap<String, i64>,
+    pub avg_duration_ms: Option<f64>,
+    pub tool_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Spend on the models that have a price. None if none of them do.
+    pub cost_usd: Option<f64>,
+    pub busiest_channels: Vec<ChannelActivity>,
+}
+
+/// Turns handled in one channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelActivity {
+    pub channel_id: String,
+    pub display_name: Option<String>,
+    pub turns: i64,
+}
+
+/// Aggregate the turns of the last `since_hours`.
+pub async fn summarize(
+    pool: &SqlitePool,
+    pricing: &HashMap<String, ModelPricing>,
+    since_hours: u32,
+) -> crate::error::Result<AgentStats> {
+    let window = format!("-{since_hours} hours");
+    let rows = sqlx::query(
+        "SELECT model, outcome, COUNT(*) AS turns, \
+         SUM(duration_ms) AS duration_ms, \
+         SUM(tool_calls) AS tool_calls, \
+         SUM(input_tokens) AS input_tokens, \
+         SUM(output_tokens) AS output_tokens \
+         FROM turn_stats \
+         WHERE created_at >= datetime('now', ?) \
+         GROUP BY model, outcome",
+    )
+    .bind(&window)
+    .fetch_all(pool)
+    .timed_with("turn_stats.summarize", || {
+        format!("since_hours={since_hours}")
+    })
+    .await?;
+
+    let mut stats = AgentStats {
+        since_hours,
+        ..Default::default()
+    };
+    let mut duration_ms = 0;
+    for row in rows {
+        let model: String = row.get("model");
+        let outcome: String = row.get("outcome");
+        let turns: i64 = row.get("turns");
+        let input_tokens: i64 = row.get("input_tokens");
+        let output_tokens: i64 = row.get("output_tokens");
+
+        stats.turns += turns;
+        if matches!(outcome.as_str(), "failed" | "timed_out" | "max_iterations") {
+            stats.errors += turns;
+        }
+        *stats.outcomes.entry(outcome).or_default() += turns;
+        duration_ms += row.get::<i64, _>("duration_ms");
+        stats.tool_calls += row.get::<i64, _>("tool_calls");
+        stats.input_tokens += input_tokens;
+        stats.output_tokens += output_tokens;
+        if let Some(price) = pricing.get(&model) {
+            *stats.cost_usd.get_or_insert(0.0) += price.cost_usd(input_tokens, output_tokens);
+        }
+    }
+    stats.avg_duration_ms = (stats.turns > 0).then(|| duration_ms as f64 / stats.turns as f64);
+
+    stats.busiest_channels = sqlx::query(
+        "SELECT t.channel_id, c.display_name, COUNT(*) AS turns \
+         FROM turn_stats t \
+         LEFT JOIN channels c ON c.id = t.channel_id \
+         WHERE t.created_at >= datetime('now', ?) \
+         GROUP BY t.channel_id \
+         ORDER BY turns DESC \
+         LIMIT ?",
+    )
+    .bind(&window)
+    .bind(BUSIEST_CHANNELS)
+    .fetch_all(pool)
+    .timed_with("turn_stats.busiest_channels", || {
+        format!("since_hours={since_hours}")
+    })
+    .await?
+    .into_iter()
+    .map(|row| ChannelActivity {
+        channel_id: row.get("channel_id"),
+        display_name: row.get("display_name"),
+        turns: row.get("turns"),
+    })
+    .collect();
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::tool_loop::ToolLoopOutcome;
+
+    #[tokio::test]
+    async fn test_summarize_totals_turns_errors_and_spend() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let turn = |outcome, duration_ms| ToolTrace {
+            outcome,
+            iterations: 1,
+            tool_calls: 2,
+            duration_ms,
+            input_tokens: 1_000,
+            output_tokens: 100,
+            steps: Vec::new(),
+            reasoning: Vec::new(),
+        };
+        let model = "anthropic/claude-sonnet-4";
+        for (channel_id, trace) in [
+            ("discord:1:2", turn(ToolLoopOutcome::Completed, 1_000)),
+            ("discord:1:2", turn(ToolLoopOutcome::TimedOut, 3_000)),
+            ("slack:T1:C1", turn(ToolLoopOutcome::Completed, 2_000)),
+        ] {
+            record(&pool, channel_id, "turn", model, &trace);
+        }
+        // Writes are fire-and-forget; wait until all three have landed.
+        while sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM turn_stats")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+            < 3
+        {
+            tokio::task::yield_now().await;
+        }
+
+        let pricing = HashMap::from([(
+            model.to_string(),
+            ModelPricing {
+                input: 3.0,
+                output: 15.0,
+            },
+        )]);
+        let stats = summarize(&pool, &pricing, 24).await.unwrap();
+        assert_eq!(stats.turns, 3);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.outcomes["completed"], 2);
+        assert_eq!(stats.avg_duration_ms, Some(2_000.0));
+        assert_eq!(stats.tool_calls, 6);
+        assert_eq!(stats.input_tokens, 3_000);
+        assert!((stats.cost_usd.unwrap() - 0.0135).abs() < 1e-9);
+        assert_eq!(stats.busiest_channels[0].channel_id, "discord:1:2");
+        assert_eq!(stats.busiest_channels[0].turns, 2);
+
+        let unpriced = summarize(&pool, &HashMap::new(), 24).await.unwrap();
+        assert_eq!(unpriced.cost_usd, None);
+    }
+}
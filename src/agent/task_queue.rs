@@ -0,0 +1,272 @@
+//! Per-agent priority queue for channel turns.
+
+use crate::InboundMessage;
+use crate::config::{QueueOverflow, TaskQueueConfig};
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// How urgently a trigger should get a turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPriority {
+    /// Scheduled jobs; nobody is waiting on them.
+    Low,
+    /// Ordinary channel traffic.
+    Normal,
+    /// Someone is waiting on this agent specifically (see
+    /// `listening::is_addressed`), or a follow-up to work already in progress.
+    High,
+}
+
+impl TaskPriority {
+    /// Classify an inbound trigger.
+    pub fn of(message: &InboundMessage) -> Self {
+        if message.source == "cron" {
+            return TaskPriority::Low;
+        }
+        if message.source == "system" || crate::agent::listening::is_addressed(message) {
+            TaskPriority::High
+        } else {
+            TaskPriority::Normal
+        }
+    }
+}
+
+/// Whether a trigger runs outside the queue. Questions from other agents do:
+/// the asking agent's turn holds a permit while it waits for the answer, so
+/// two agents asking each other would otherwise sit on each other's slots
+/// until the question times out.
+pub fn bypasses_queue(message: &InboundMessage) -> bool {
+    message.sender_id.starts_with("agent:")
+}
+
+/// Error returned when a trigger doesn't get a turn.
+#[derive(Debug, thiserror::Error)]
+#[error("task queue full, {priority:?} trigger dropped")]
+pub struct TaskDropped {
+    pub priority: TaskPriority,
+}
+
+/// Highest priority first, then oldest first.
+type QueueKey = (Reverse<TaskPriority>, u64);
+
+#[derive(Debug)]
+struct QueueState {
+    config: TaskQueueConfig,
+    running: usize,
+    next_seq: u64,
+    waiting: BTreeMap<QueueKey, oneshot::Sender<TaskPermit>>,
+}
+
+/// Admits an agent's channel turns in priority order.
+///
+/// Every channel acquires a permit before running a turn and holds it until
+/// the turn ends. Once `max_concurrent` permits are out, new triggers wait in
+/// the queue; when it holds `capacity` triggers, the overflow policy picks one
+/// to drop. A dropped trigger's `acquire` returns `TaskDropped`.
+#[derive(Debug)]
+pub struct TaskQueue {
+    state: Mutex<QueueState>,
+}
+
+/// Held for the duration of one turn. Dropping it starts the next queued one.
+#[derive(Debug)]
+pub struct TaskPermit {
+    queue: Option<Arc<TaskQueue>>,
+}
+
+impl TaskPermit {
+    /// A permit that doesn't count against any queue, for triggers that
+    /// [bypass it](bypasses_queue).
+    pub fn unqueued() -> Self {
+        Self { queue: None }
+    }
+}
+
+impl Drop for TaskPermit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release();
+        }
+    }
+}
+
+impl TaskQueue {
+    pub fn new(config: TaskQueueConfig) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                config,
+                running: 0,
+                next_seq: 0,
+                waiting: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> TaskQueueConfig {
+        self.lock().config
+    }
+
+    /// Swap in new limits. A higher `max_concurrent` starts waiting triggers
+    /// right away; a lower one takes effect as running turns finish.
+    pub fn set_config(self: &Arc<Self>, config: TaskQueueConfig) {
+        let mut state = self.lock();
+        state.config = config;
+        self.dispatch(&mut state);
+    }
+
+    /// Triggers waiting for a turn.
+    pub fn depth(&self) -> usize {
+        self.lock().waiting.len()
+    }
+
+    /// Turns currently holding a permit.
+    pub fn running(&self) -> usize {
+        self.lock().running
+    }
+
+    /// Wait for a turn slot.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        priority: TaskPriority,
+    ) -> Result<TaskPermit, TaskDropped> {
+        let permit_rx = {
+            let mut state = self.lock();
+            let max_concurrent = state.config.max_concurrent;
+            if max_concurrent == 0 || (state.running < max_concurrent && state.waiting.is_empty()) {
+                state.running += 1;
+                return Ok(self.permit());
+            }
+
+            let key = (Reverse(priority), state.next_seq);
+            state.next_seq += 1;
+
+            if state.waiting.len() >= state.config.capacity {
+                let evict = match state.config.overflow {
+                    QueueOverflow::Reject => None,
+                    QueueOverflow::DropOldest => {
+                        state.waiting.keys().min_by_key(|(_, seq)| *seq).copied()
+                    }
+                    // The last key is the newest of the lowest priority. If the
+                    // new trigger would sort after it, the new one is dropped.
+                    QueueOverflow::DropLowest => state
+                        .waiting
+                        .keys()
+                        .next_back()
+                        .copied()
+                        .filter(|last| *last > key),
+                };
+                let Some(evict) = evict else {
+                    tracing::warn!(
+                        ?priority,
+                        depth = state.waiting.len(),
+                        "task queue full, dropping new trigger"
+                    );
+                    return Err(TaskDropped { priority });
+                };
+                // Dropping the sender fails that waiter's `acquire`.
+                state.waiting.remove(&evict);
+                let dropped = evict.0.0;
+                tracing::warn!(
+                    ?dropped,
+                    ?priority,
+                    "task queue full, dropping queued trigger"
+                );
+            }
+
+            let (permit_tx, permit_rx) = oneshot::channel();
+            state.waiting.insert(key, permit_tx);
+            tracing::debug!(
+                ?priority,
+                depth = state.waiting.len(),
+                "trigger queued for a turn"
+            );
+            permit_rx
+        };
+
+        permit_rx.await.map_err(|_| TaskDropped { priority })
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut state = self.lock();
+        state.running = state.running.saturating_sub(1);
+        self.dispatch(&mut state);
+    }
+
+    /// Hand permits to waiters, best first, while there are free slots.
+    fn dispatch(self: &Arc<Self>, state: &mut QueueState) {
+        while state.config.max_concurrent == 0 || state.running < state.config.max_concurrent {
+            let Some((_, permit_tx)) = state.waiting.pop_first() else {
+                break;
+            };
+            state.running += 1;
+            if let Err(mut permit) = permit_tx.send(self.permit()) {
+                // The waiter gave up. Disarm the permit so dropping it here,
+                // under the lock, doesn't release again.
+                permit.queue = None;
+                state.running -= 1;
+            }
+        }
+    }
+
+    fn permit(self: &Arc<Self>) -> TaskPermit {
+        TaskPermit {
+            queue: Some(self.clone()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().expect("task queue lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_waiters_start_by_priority_and_overflow_drops_lowest() {
+        let queue = Arc::new(TaskQueue::new(TaskQueueConfig {
+            max_concurrent: 1,
+            capacity: 2,
+            overflow: QueueOverflow::DropLowest,
+        }));
+        let running = queue
+            .acquire(TaskPriority::Normal)
+            .await
+            .expect("free slot");
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let spawn_waiter = |priority: TaskPriority| {
+            let queue = queue.clone();
+            let order_tx = order_tx.clone();
+            tokio::spawn(async move {
+                let result = queue.acquire(priority).await;
+                order_tx.send((priority, result.is_ok())).ok();
+            })
+        };
+        let low = spawn_waiter(TaskPriority::Low);
+        tokio::task::yield_now().await;
+        let normal = spawn_waiter(TaskPriority::Normal);
+        tokio::task::yield_now().await;
+        assert_eq!(queue.depth(), 2);
+
+        // Full: the high-priority trigger takes the low one's place.
+        let high = spawn_waiter(TaskPriority::High);
+        low.await.expect("join");
+        assert_eq!(order_rx.recv().await, Some((TaskPriority::Low, false)));
+
+        drop(running);
+        high.await.expect("join");
+        normal.await.expect("join");
+        assert_eq!(order_rx.recv().await, Some((TaskPriority::High, true)));
+        assert_eq!(order_rx.recv().await, Some((TaskPriority::Normal, true)));
+        assert_eq!(queue.depth(), 0);
+        assert_eq!(queue.running(), 0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Token streaming for channel turns.
+//!
+//! When the inbound message asks for it, the turn's model streams its output
+//! (see `llm::streaming`) and the deltas are turned into a draft reply sent
+//! to the conversation as `StreamStart` / `StreamChunk`. Only text meant for
+//! the user is forwarded: plain-text output, and the `content` argument of
+//! the `reply` tool as the model writes it. The reply that's finally sent is
+//! authoritative and replaces the draft.
+
+use crate::llm::streaming::TokenDelta;
+use crate::tools::ReplyTool;
+use crate::{InboundMessage, OutboundResponse};
+
+use rig::tool::Tool as _;
+use tokio::sync::mpsc;
+
+/// Metadata key on inbound messages that want the reply's tokens streamed.
+pub const STREAM_TOKENS_KEY: &str = "stream_tokens";
+
+/// Whether a message asked for its reply's tokens to be streamed.
+pub fn requested(message: &InboundMessage) -> bool {
+    message
+        .metadata
+        .get(STREAM_TOKENS_KEY)
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Forward a turn's token deltas as draft updates until the model is dropped.
+pub fn spawn_forwarder(
+    mut deltas: mpsc::UnboundedReceiver<TokenDelta>,
+    response_tx: mpsc::Sender<OutboundResponse>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut draft = DraftStream::default();
+        while let Some(delta) = deltas.recv().await {
+            for response in draft.push(delta) {
+                if response_tx.send(response).await.is_err() {
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/// What the model is writing right now.
+#[derive(Debug, Default)]
+enum Source {
+    #[default]
+    Text,
+    Reply(PartialStringField),
+    /// A tool call whose arguments aren't shown to the user.
+    OtherTool,
+}
+
+#[derive(Debug, Default)]
+struct DraftStream {
+    source: Source,
+    started: bool,
+}
+
+impl DraftStream {
+    fn push(&mut self, delta: TokenDelta) -> Vec<OutboundResponse> {
+        let text = match delta {
+            TokenDelta::Start => {
+                self.source = Source::Text;
+                self.started = false;
+                None
+            }
+            TokenDelta::Text(text) => matches!(self.source, Source::Text).then_some(text),
+            TokenDelta::ToolCallStart { tool_name } => {
+                self.source = if tool_name == ReplyTool::NAME {
+                    // The reply starts a fresh draft, replacing any text the
+                    // model wrote before calling the tool.
+                    self.started = false;
+                    Source::Reply(PartialStringField::new("content"))
+                } else {
+                    Source::OtherTool
+                };
+                None
+            }
+            TokenDelta::ToolArgs(fragment) => match &mut self.source {
+                Source::Reply(field) => field.push(&fragment),
+                _ => None,
+            },
+        };
+
+        let Some(text) = text.filter(|text| !text.is_empty()) else {
+            return Vec::new();
+        };
+        let mut responses = Vec::with_capacity(2);
+        if !self.started {
+            self.started = true;
+            responses.push(OutboundResponse::StreamStart);
+        }
+        responses.push(OutboundResponse::StreamChunk(text));
+        responses
+    }
+}
+
+/// Decodes one string field of a JSON object that's still being written.
+#[derive(Debug)]
+struct PartialStringField {
+    key: &'static str,
+    buffer: String,
+    /// Bytes of the decoded value already returned.
+    emitted: usize,
+}
+
+impl PartialStringField {
+    fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            buffer: String::new(),
+            emitted: 0,
+        }
+    }
+
+    /// Add a fragment and return the newly decoded part of the value.
+    fn push(&mut self, fragment: &str) -> Option<String> {
+        self.buffer.push_str(fragment);
+        let value = decode_partial_string(&self.buffer, self.key)?;
+        if value.len() <= self.emitted {
+            return None;
+        }
+        let new = value[self.emitted..].to_string();
+        self.emitted = value.len();
+        Some(new)
+    }
+}
+
+/// Decode as much of `key`'s string value as the partial JSON contains,
+/// stopping before an escape sequence that isn't complete yet.
+fn decode_partial_string(json: &str, key: &str) -> Option<String> {
+    let pattern = format!("\"{key}\"");
+    let start = json.find(&pattern)? + pattern.len();
+    let rest = json[start..].trim_start().strip_prefix(':')?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+
+    let mut value = String::new();
+    let mut chars = rest.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('b') | Some('f') => {}
+                Some('u') => match decode_unicode_escape(&mut chars) {
+                    Some(decoded) => value.push(decoded),
+                    None => break,
+                },
+                Some(escaped) => value.push(escaped),
+                None => break,
+            },
+            char => value.push(char),
+        }
+    }
+    Some(value)
+}
+
+/// Decode the hex digits after `\u`, including a following low surrogate.
+/// None means the escape is incomplete.
+fn decode_unicode_escape(chars: &mut std::str::Chars<'_>) -> Option<char> {
+    let hex: String = chars.by_ref().take(4).collect();
+    let code = u32::from_str_radix(&hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 4)?;
+    if !(0xD800..0xDC00).contains(&code) {
+        return Some(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+    let low: String = chars.by_ref().take(6).collect();
+    let low = low
+        .strip_prefix("\\u")
+        .filter(|low| low.len() == 4)
+        .and_then(|low| u32::from_str_radix(low, 16).ok())?;
+    let combined = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+    Some(char::from_u32(combined).unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(responses: Vec<OutboundResponse>) -> Vec<String> {
+        responses
+            .into_iter()
+            .map(|response| match response {
+                OutboundResponse::StreamStart => "<start>".to_string(),
+                OutboundResponse::StreamChunk(text) => text,
+                other => panic!("unexpected response: {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_streams_reply_content_as_draft() {
+        let mut draft = DraftStream::default();
+        let mut seen = Vec::new();
+        for delta in [
+            TokenDelta::Start,
+            TokenDelta::Text("Let me check.".into()),
+            TokenDelta::ToolCallStart {
+                tool_name: "memory_recall".into(),
+            },
+            TokenDelta::ToolArgs(r#"{"query": "deploys"}"#.into()),
+            TokenDelta::Start,
+            TokenDelta::ToolCallStart {
+                tool_name: "reply".into(),
+            },
+            TokenDelta::ToolArgs(r#"{"content": "Deploy is \"gr"#.into()),
+            TokenDelta::ToolArgs(r#"een\" é\"#.into()),
+            TokenDelta::ToolArgs(r#"n🚀", "thread_name": null}"#.into()),
+        ] {
+            seen.extend(chunks(draft.push(delta)));
+        }
+
+        assert_eq!(
+            seen,
+            [
+                "<start>",
+                "Let me check.",
+                "<start>",
+                "Deploy is \"gr",
+                "een\" é",
+                "\n🚀"
+            ]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Tool loop: guards and tracing for the chain of tool calls in one turn.
+//!
+//! A channel turn may take several LLM calls, each asking for tools whose
+//! results feed the next call. Rig drives that loop and stops it at
+//! `max_turns`; `ToolLoop` adds a wall-clock deadline checked before every
+//! LLM call and records each step so the chain can be streamed to the API and
+//! persisted to `tool_traces`. Reasoning segments are kept apart from the
+//! steps; see `agent::reasoning`.
+
+use crate::agent::reasoning::ReasoningSegment;
+use crate::config::ToolLoopConfig;
+use crate::db::TimedQuery as _;
+
+use rig::completion::PromptError;
+use serde::{Deserialize, Serialize};
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Tool arguments and results longer than this are truncated in traces.
+const TRACE_STEP_MAX_BYTES: usize = 4_000;
+
+/// One step of a tool loop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolLoopStep {
+    /// The model answered. `tool_calls` names the tools it asked for; an
+    /// empty list ends the loop.
+    Response {
+        iteration: usize,
+        text: Option<String>,
+        tool_calls: Vec<String>,
+        elapsed_ms: u64,
+    },
+    ToolCall {
+        iteration: usize,
+        tool_name: String,
+        args: String,
+        elapsed_ms: u64,
+    },
+    ToolResult {
+        iteration: usize,
+        tool_name: String,
+        result: String,
+        elapsed_ms: u64,
+    },
+}
+
+/// How a tool loop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolLoopOutcome {
+    Completed,
+    /// Stopped by `max_turns`.
+    MaxIterations,
+    /// Stopped by the wall-clock limit.
+    TimedOut,
+    /// Stopped by a hook for another reason, e.g. a leaked secret.
+    Cancelled,
+    Failed,
+}
+
+impl ToolLoopOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolLoopOutcome::Completed => "completed",
+            ToolLoopOutcome::MaxIterations => "max_iterations",
+            ToolLoopOutcome::TimedOut => "timed_out",
+            ToolLoopOutcome::Cancelled => "cancelled",
+            ToolLoopOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// A finished tool loop.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolTrace {
+    pub outcome: ToolLoopOutcome,
+    pub iterations: usize,
+    pub tool_calls: usize,
+    pub duration_ms: u64,
+    /// Token usage summed over every LLM call in the loop.
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub steps: Vec<ToolLoopStep>,
+    /// Kept out of the serialized trace so it can't reach a user.
+    #[serde(skip)]
+    pub reasoning: Vec<ReasoningSegment>,
+}
+
+#[derive(Debug)]
+struct ToolLoopInner {
+    started: Instant,
+    max_duration: Option<Duration>,
+    iteration: AtomicUsize,
+    timed_out: AtomicBool,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+    steps: Mutex<Vec<ToolLoopStep>>,
+    reasoning: Mutex<Vec<ReasoningSegment>>,
+}
+
+/// Shared state of one turn's tool loop, held by the turn's hook.
+#[derive(Debug, Clone)]
+pub struct ToolLoop {
+    inner: Arc<ToolLoopInner>,
+}
+
+impl ToolLoop {
+    pub fn new(config: &ToolLoopConfig) -> Self {
+        Self {
+            inner: Arc::new(ToolLoopInner {
+                started: Instant::now(),
+                max_duration: (config.max_duration_secs > 0)
+                    .then(|| Duration::from_secs(config.max_duration_secs)),
+                iteration: AtomicUsize::new(0),
+                timed_out: AtomicBool::new(false),
+                input_tokens: AtomicU64::new(0),
+                output_tokens: AtomicU64::new(0),
+                steps: Mutex::new(Vec::new()),
+                reasoning: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Start the next LLM call, or return why the loop must stop.
+    pub fn begin_iteration(&self) -> std::result::Result<usize, String> {
+        if let Some(max_duration) = self.inner.max_duration
+            && self.inner.started.elapsed() >= max_duration
+        {
+            self.inner.timed_out.store(true, Ordering::Relaxed);
+            return Err(format!(
+                "Turn stopped: tool loop exceeded its {}s time limit.",
+                max_duration.as_secs()
+            ));
+        }
+        Ok(self.inner.iteration.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// The LLM call currently in progress, counting from 1.
+    pub fn iteration(&self) -> usize {
+        self.inner.iteration.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.inner.started.elapsed().as_millis() as u64
+    }
+
+    pub fn response_step(&self, text: Option<String>, tool_calls: Vec<String>) -> ToolLoopStep {
+        ToolLoopStep::Response {
+            iteration: self.iteration(),
+            text: text.map(|text| crate::tools::truncate_output(&text, TRACE_STEP_MAX_BYTES)),
+            tool_calls,
+            elapsed_ms: self.elapsed_ms(),
+        }
+    }
+
+    pub fn tool_call_step(&self, tool_name: &str, args: &str) -> ToolLoopStep {
+        ToolLoopStep::ToolCall {
+            iteration: self.iteration(),
+            tool_name: tool_name.to_string(),
+            args: crate::tools::truncate_output(args, TRACE_STEP_MAX_BYTES),
+            elapsed_ms: self.elapsed_ms(),
+        }
+    }
+
+    pub fn tool_result_step(&self, tool_name: &str, result: &str) -> ToolLoopStep {
+        ToolLoopStep::ToolResult {
+            iteration: self.iteration(),
+            tool_name: tool_name.to_string(),
+            result: crate::tools::truncate_output(result, TRACE_STEP_MAX_BYTES),
+            elapsed_ms: self.elapsed_ms(),
+        }
+    }
+
+    /// Add one LLM call's token usage.
+    pub fn add_usage(&self, input_tokens: u64, output_tokens: u64) {
+        self.inner
+            .input_tokens
+            .fetch_add(input_tokens, Ordering::Relaxed);
+        self.inner
+            .output_tokens
+            .fetch_add(output_tokens, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, step: ToolLoopStep) {
+        self.inner
+            .steps
+            .lock()
+            .expect("tool loop lock poisoned")
+            .push(step);
+    }
+
+    /// Record a reasoning segment from the current LLM call. Unlike steps,
+    /// these aren't emitted as events.
+    pub fn record_reasoning(&self, text: &str) {
+        let segment = ReasoningSegment::new(self.iteration(), text, self.elapsed_ms());
+        self.inner
+            .reasoning
+            .lock()
+            .expect("tool loop lock poisoned")
+            .push(segment);
+    }
+
+    /// Close the loop and classify how it ended.
+    pub fn finish(&self, result: &std::result::Result<String, PromptError>) -> ToolTrace {
+        let outcome = match result {
+            Ok(_) => ToolLoopOutcome::Completed,
+            Err(PromptError::MaxTurnsError { .. }) => ToolLoopOutcome::MaxIterations,
+            Err(PromptError::PromptCancelled { .. })
+                if self.inner.timed_out.load(Ordering::Relaxed) =>
+            {
+                ToolLoopOutcome::TimedOut
+            }
+            Err(PromptError::PromptCancelled { .. }) => ToolLoopOutcome::Cancelled,
+            Err(_) => ToolLoopOutcome::Failed,
+        };
+        let steps = self
+            .inner
+            .steps
+            .lock()
+            .expect("tool loop lock poisoned")
+            .clone();
+        let tool_calls = steps
+            .iter()
+            .filter(|step| matches!(step, ToolLoopStep::ToolCall { .. }))
+            .count();
+        let reasoning = self
+            .inner
+            .reasoning
+            .lock()
+            .expect("tool loop lock poisoned")
+            .clone();
+
+        ToolTrace {
+            outcome,
+            iterations: self.iteration(),
+            tool_calls,
+            duration_ms: self.elapsed_ms(),
+            input_tokens: self.inner.input_tokens.load(Ordering::Relaxed),
+            output_tokens: self.inner.output_tokens.load(Ordering::Relaxed),
+            steps,
+            reasoning,
+        }
+    }
+}
+
+impl ToolTrace {
+    /// Write the trace to `tool_traces` in the background.
+    pub fn persist(self, pool: &sqlx::SqlitePool, channel_id: &str, turn_id: Option<&str>) {
+        let pool = pool.clone();
+        let id = uuid::Uuid::new_v4().to_string();
+        let channel_id = channel_id.to_string();
+        let turn_id = turn_id.map(String::from);
+        let steps = match serde_json::to_string(&self.steps) {
+            Ok(steps) => steps,
+            Err(error) => {
+                tracing::warn!(%error, "failed to serialize tool trace");
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
+            if let Err(error) = sqlx::query(
+                "INSERT INTO tool_traces (id, channel_id, turn_id, outcome, iterations, tool_calls, duration_ms, steps) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&channel_id)
+            .bind(&turn_id)
+            .bind(self.outcome.as_str())
+            .bind(self.iterations as i64)
+            .bind(self.tool_calls as i64)
+            .bind(self.duration_ms as i64)
+            .bind(&steps)
+            .execute(&pool)
+            .timed("tool_traces.insert")
+            .await
+            {
+                tracing::warn!(%error, "failed to persist tool trace");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline_stops_next_iteration() {
+        let tool_loop = ToolLoop::new(&ToolLoopConfig {
+            max_duration_secs: 0,
+            trace: true,
+        });
+        assert_eq!(tool_loop.begin_iteration(), Ok(1));
+        tool_loop.record(tool_loop.tool_call_step("reply", "{}"));
+        assert_eq!(tool_loop.begin_iteration(), Ok(2));
+
+        let trace = tool_loop.finish(&Ok("done".into()));
+        assert_eq!(trace.outcome, ToolLoopOutcome::Completed);
+        assert_eq!(trace.iterations, 2);
+        assert_eq!(trace.tool_calls, 1);
+
+        let expired = ToolLoop {
+            inner: Arc::new(ToolLoopInner {
+                started: Instant::now() - Duration::from_secs(10),
+                max_duration: Some(Duration::from_secs(5)),
+                iteration: AtomicUsize::new(0),
+                timed_out: AtomicBool::new(false),
+                input_tokens: AtomicU64::new(0),
+                output_tokens: AtomicU64::new(0),
+                steps: Mutex::new(Vec::new()),
+                reasoning: Mutex::new(Vec::new()),
+            }),
+        };
+        assert!(expired.begin_iteration().is_err());
+        assert!(expired.inner.timed_out.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,113 @@
+//! Image understanding: describe attached images with a vision model.
+//!
+//! The channel still sends the images themselves to its model. The
+//! description is what outlives them: it's stored in the persisted message
+//! metadata and kept as text next to the images in the history, so turns on
+//! non-vision models (and anything reading the conversation log) know what
+//! was shown.
+
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, ProcessType};
+
+use rig::OneOrMany;
+use rig::agent::AgentBuilder;
+use rig::completion::Prompt;
+use rig::message::{Message, UserContent};
+
+use std::time::Instant;
+
+/// Metadata key holding the description of a message's attached images.
+pub const IMAGE_DESCRIPTION_KEY: &str = "image_description";
+
+/// Describe the images among a message's downloaded attachment parts.
+///
+/// Returns None when vision is disabled, there are no images, or the call
+/// fails. The images are still sent to the channel model either way.
+#[tracing::instrument(skip(deps, parts), fields(agent_id = %deps.agent_id))]
+pub async fn describe_images(deps: &AgentDeps, parts: &[UserContent]) -> Option<String> {
+    let rc = &deps.runtime_config;
+    let vision = rc.vision.load();
+    if !vision.enabled {
+        return None;
+    }
+    let mut content = images(parts);
+    if content.is_empty() {
+        return None;
+    }
+    let image_count = content.len();
+
+    let system_prompt = match rc.prompts.load().render_static("vision") {
+        Ok(prompt) => prompt,
+        Err(error) => {
+            tracing::warn!(%error, "failed to render vision prompt");
+            return None;
+        }
+    };
+
+    let started = Instant::now();
+    let routing = rc.routing.load();
+    let model_name = vision
+        .model
+        .clone()
+        .unwrap_or_else(|| routing.resolve(ProcessType::Channel, None).to_string());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_agent(&deps.agent_id)
+        .with_routing((**routing).clone());
+    let agent = AgentBuilder::new(model).preamble(&system_prompt).build();
+
+    content.push(UserContent::text(format!(
+        "Describe the {image_count} attached image(s)."
+    )));
+    let message = Message::User {
+        content: OneOrMany::many(content).ok()?,
+    };
+
+    match agent.prompt(message).await {
+        Ok(description) if !description.trim().is_empty() => {
+            tracing::info!(
+                model = %model_name,
+                image_count,
+                latency_ms = started.elapsed().as_millis() as u64,
+                "described attached images"
+            );
+            Some(description.trim().to_string())
+        }
+        Ok(_) => None,
+        Err(error) => {
+            tracing::warn!(%error, model = %model_name, "failed to describe attached images");
+            None
+        }
+    }
+}
+
+/// The description as it's kept in the history next to the images.
+pub fn history_text(description: &str) -> String {
+    format!("[Attached images, as described by a vision model]\n{description}")
+}
+
+fn images(parts: &[UserContent]) -> Vec<UserContent> {
+    parts
+        .iter()
+        .filter(|part| matches!(part, UserContent::Image(_)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rig::message::ImageMediaType;
+
+    #[test]
+    fn test_only_images_are_sent_for_description() {
+        let parts = [
+            UserContent::text("[Attachment: notes.pdf (application/pdf, 1.0 KB)]"),
+            UserContent::image_base64("aGVsbG8=", Some(ImageMediaType::PNG), None),
+        ];
+        let images = images(&parts);
+        assert_eq!(images.len(), 1);
+        assert!(matches!(images[0], UserContent::Image(_)));
+        assert!(history_text("A red button.").ends_with("\nA red button."));
+    }
+}
@@ -1,6 +1,6 @@
 //! Worker: Independent task execution process.
 
-use crate::agent::compactor::estimate_history_tokens;
+use crate::agent::context::count_history_tokens;
 use crate::config::BrowserConfig;
 use crate::error::Result;
 use crate::hooks::SpacebotHook;
@@ -199,6 +199,7 @@ impl Worker {
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Worker, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
+            .with_agent(&self.deps.agent_id)
             .with_routing((**routing).clone());
 
         let agent = AgentBuilder::new(model)
@@ -364,7 +365,7 @@ impl Worker {
     /// No LLM call, just programmatic truncation with a summary marker.
     async fn maybe_compact_history(&self, history: &mut Vec<rig::message::Message>) {
         let context_window = **self.deps.runtime_config.context_window.load();
-        let estimated = estimate_history_tokens(history);
+        let estimated = count_history_tokens(history);
         let usage = estimated as f32 / context_window as f32;
 
         if usage < 0.70 {
@@ -402,7 +403,7 @@ impl Worker {
         }
 
         let context_window = **self.deps.runtime_config.context_window.load();
-        let estimated = estimate_history_tokens(history);
+        let estimated = count_history_tokens(history);
         let usage = estimated as f32 / context_window as f32;
 
         let remove_count = ((total as f32 * fraction) as usize)
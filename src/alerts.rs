@@ -0,0 +1,411 @@
+//! Alert rules, checked in the background.
+//!
+//! Every `[alerts] check_interval_secs` each `[[alerts.rules]]` entry is
+//! evaluated, per agent for the conditions that are per agent. A tripped rule
+//! notifies each of its targets: an `adapter:target` destination like
+//! `discord:123456789` or `email:ops@example.com`, sent through that
+//! messaging adapter, or an `http(s)://` URL, which gets the alert as JSON.
+//! A rule that stays tripped notifies again only after `cooldown_secs`.
+
+use crate::OutboundResponse;
+use crate::agent::health::{AgentHealth, HealthRegistry, HealthStatus};
+use crate::api::ApiState;
+use crate::config::{AlertCondition, AlertRule, AlertsConfig, ModelPricing};
+use crate::cron::scheduler::DeliveryTarget;
+use crate::db::TimedQuery as _;
+use crate::events::{self, BusEvent};
+use crate::otel::{DbWriteCounts, db_write_counts};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Writes needed since the last check before their failure rate counts, so
+/// a single failed write in a quiet minute doesn't page anyone.
+const MIN_WRITES: u64 = 10;
+
+/// A tripped rule, as posted to webhook targets.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub condition: AlertCondition,
+    /// None for instance-wide conditions.
+    pub agent_id: Option<String>,
+    pub value: f64,
+    pub threshold: f64,
+    pub message: String,
+    pub fired_at: DateTime<Utc>,
+}
+
+/// What the rules are checked against, gathered once per check.
+#[derive(Debug, Default)]
+struct Observations {
+    /// Writes since the previous check.
+    db_writes: DbWriteCounts,
+    health: Vec<AgentHealth>,
+    /// USD per agent over the last 24 hours.
+    daily_spend: HashMap<String, f64>,
+}
+
+struct Checker {
+    config: AlertsConfig,
+    health: Arc<HealthRegistry>,
+    state: Arc<ApiState>,
+    http: reqwest::Client,
+    db_writes: DbWriteCounts,
+    /// When each rule last notified, per agent.
+    last_fired: HashMap<(usize, Option<String>), Instant>,
+}
+
+/// Spawn a background task that checks the alert rules until the process
+/// exits, and as soon as an agent's LLM call fails if a rule watches for
+/// that. Agents are read from the API state on every check, so agents added
+/// or removed later are covered.
+pub fn spawn_alert_loop(config: AlertsConfig, health: Arc<HealthRegistry>, state: Arc<ApiState>) {
+    let mut checker = Checker {
+        config,
+        health,
+        state,
+        http: reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default(),
+        db_writes: db_write_counts(),
+        last_fired: HashMap::new(),
+    };
+    let watches_errors = checker.config.rules.iter().any(|rule| {
+        matches!(
+            rule.condition,
+            AlertCondition::ConsecutiveLlmErrors | AlertCondition::AgentUnhealthy
+        )
+    });
+    let mut bus = events::subscribe();
+    tokio::spawn(async move {
+        let period = Duration::from_secs(checker.config.check_interval_secs);
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                event = bus.recv() => {
+                    // Check an agent error right away instead of on the next
+                    // tick; the cooldown keeps a burst from notifying twice.
+                    if !(watches_errors && matches!(event, Ok(BusEvent::AgentError { .. }))) {
+                        continue;
+                    }
+                }
+            }
+            checker.check().await;
+        }
+    });
+}
+
+impl Checker {
+    async fn check(&mut self) {
+        let observations = self.observe().await;
+        let cooldown = Duration::from_secs(self.config.cooldown_secs);
+
+        for (index, rule) in self.config.rules.iter().enumerate() {
+            for alert in evaluate(rule, &observations) {
+                let key = (index, alert.agent_id.clone());
+                if self
+                    .last_fired
+                    .get(&key)
+                    .is_some_and(|fired| fired.elapsed() < cooldown)
+                {
+                    continue;
+                }
+                self.last_fired.insert(key, Instant::now());
+
+                tracing::warn!(
+                    condition = alert.condition.as_str(),
+                    agent_id = alert.agent_id.as_deref(),
+                    "alert: {}",
+                    alert.message
+                );
+                for target in &rule.notify {
+                    if let Err(error) = self.notify(target, &alert).await {
+                        tracing::warn!(%error, target, "failed to send alert");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn observe(&mut self) -> Observations {
+        let counts = db_write_counts();
+        let db_writes = DbWriteCounts {
+            writes: counts.writes - self.db_writes.writes,
+            failures: counts.failures - self.db_writes.failures,
+        };
+        self.db_writes = counts;
+
+        let pools = self.state.agent_read_pools.load_full();
+        let mut agent_ids: Vec<String> = pools.keys().cloned().collect();
+        agent_ids.sort();
+
+        let mut daily_spend = HashMap::new();
+        let watches_spend = self
+            .config
+            .rules
+            .iter()
+            .any(|rule| rule.condition == AlertCondition::DailySpendUsd);
+        if watches_spend {
+            let runtime_configs = self.state.runtime_configs.load_full();
+            for (agent_id, pool) in pools.iter() {
+                let Some(runtime_config) = runtime_configs.get(agent_id) else {
+                    continue;
+                };
+                let pricing = runtime_config.experiment.load().pricing.clone();
+                match daily_spend_usd(pool, &pricing).await {
+                    Ok(spend) => {
+                        daily_spend.insert(agent_id.clone(), spend);
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, agent_id, "failed to total LLM spend for alerts");
+                    }
+                }
+            }
+        }
+
+        Observations {
+            db_writes,
+            health: self.health.snapshot(&agent_ids),
+            daily_spend,
+        }
+    }
+
+    async fn notify(&self, target: &str, alert: &Alert) -> anyhow::Result<()> {
+        let text = format!("Spacebot alert: {}", alert.message);
+        notify(&self.http, &self.state, target, &text, alert).await
+    }
+}
+
+/// Send a notification to one target: `text` through a messaging adapter, or
+/// `body` as JSON to a URL.
+pub async fn notify<T: Serialize>(
+    http: &reqwest::Client,
+    state: &ApiState,
+    target: &str,
+    text: &str,
+    body: &T,
+) -> anyhow::Result<()> {
+    if target.starts_with("http://") || target.starts_with("https://") {
+        http.post(target)
+            .json(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        return Ok(());
+    }
+
+    let delivery = DeliveryTarget::parse(target)
+        .ok_or_else(|| anyhow::anyhow!("expected adapter:target or a URL"))?;
+    let messaging = state
+        .messaging_manager
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("messaging isn't running"))?;
+    messaging
+        .broadcast(
+            &delivery.adapter,
+            &delivery.target,
+            OutboundResponse::Text(text.to_string()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// The alerts a rule raises against one round of observations.
+fn evaluate(rule: &AlertRule, observations: &Observations) -> Vec<Alert> {
+    let alert = |agent_id: Option<&str>, value: f64, message: String| Alert {
+        condition: rule.condition,
+        agent_id: agent_id.map(String::from),
+        value,
+        threshold: rule.threshold,
+        message,
+        fired_at: Utc::now(),
+    };
+    let last_error = |health: &AgentHealth| {
+        health
+            .last_error
+            .clone()
+            .unwrap_or_else(|| "unknown".into())
+    };
+
+    match rule.condition {
+        AlertCondition::DbWriteFailureRate => {
+            let DbWriteCounts { writes, failures } = observations.db_writes;
+            let rate = failures as f64 / writes as f64;
+            if writes < MIN_WRITES || rate < rule.threshold {
+                return Vec::new();
+            }
+            vec![alert(
+                None,
+                rate,
+                format!(
+                    "{failures} of {writes} database writes failed since the last check ({:.0}%)",
+                    rate * 100.0
+                ),
+            )]
+        }
+        AlertCondition::ConsecutiveLlmErrors => observations
+            .health
+            .iter()
+            .filter(|health| {
+                health.consecutive_failures > 0
+                    && f64::from(health.consecutive_failures) >= rule.threshold
+            })
+            .map(|health| {
+                alert(
+                    Some(health.agent_id.as_str()),
+                    f64::from(health.consecutive_failures),
+                    format!(
+                        "agent '{}': {} LLM calls in a row failed. Last error: {}",
+                        health.agent_id,
+                        health.consecutive_failures,
+                        last_error(health)
+                    ),
+                )
+            })
+            .collect(),
+        AlertCondition::DailySpendUsd => observations
+            .daily_spend
+            .iter()
+            .filter(|(_, spend)| **spend >= rule.threshold)
+            .map(|(agent_id, spend)| {
+                alert(
+                    Some(agent_id.as_str()),
+                    *spend,
+                    format!(
+                        "agent '{agent_id}' spent ${spend:.2} on LLM calls in the last 24 hours, \
+                         over its ${:.2} budget",
+                        rule.threshold
+                    ),
+                )
+            })
+            .collect(),
+        AlertCondition::AgentUnhealthy => observations
+            .health
+            .iter()
+            .filter(|health| health.status == HealthStatus::Failing)
+            .map(|health| {
+                alert(
+                    Some(health.agent_id.as_str()),
+                    f64::from(health.consecutive_failures),
+                    format!(
+                        "agent '{}' is unhealthy after {} failed LLM calls in a row. Last error: {}",
+                        health.agent_id,
+                        health.consecutive_failures,
+                        last_error(health)
+                    ),
+                )
+            })
+            .collect(),
+    }
+}
+
+/// An agent's LLM spend over the last 24 hours, from its `llm_calls`.
+/// Models without a price count as free.
+async fn daily_spend_usd(
+    pool: &SqlitePool,
+    pricing: &HashMap<String, ModelPricing>,
+) -> crate::error::Result<f64> {
+    let rows = sqlx::query(
+        "SELECT model, SUM(input_tokens) AS input_tokens, SUM(output_tokens) AS output_tokens \
+         FROM llm_calls \
+         WHERE created_at >= datetime('now', '-1 day') \
+         GROUP BY model",
+    )
+    .fetch_all(pool)
+    .timed("llm_calls.daily_spend")
+    .await?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let price = pricing.get(&row.get::<String, _>("model"))?;
+            Some(price.cost_usd(row.get("input_tokens"), row.get("output_tokens")))
+        })
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_trip_at_their_threshold() {
+        let rule = |condition, threshold| AlertRule {
+            condition,
+            threshold,
+            notify: vec!["discord:1".into()],
+        };
+        let health = |agent_id: &str, status, consecutive_failures| AgentHealth {
+            agent_id: agent_id.into(),
+            status,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: Some("503 overloaded".into()),
+            consecutive_failures,
+            pending_calls: 0,
+            running_tasks: 0,
+            queued_tasks: 0,
+            restarts: 0,
+            last_restart_at: None,
+        };
+        let observations = Observations {
+            db_writes: DbWriteCounts {
+                writes: 40,
+                failures: 4,
+            },
+            health: vec![
+                health("main", HealthStatus::Failing, 6),
+                health("support", HealthStatus::Degraded, 2),
+            ],
+            daily_spend: HashMap::from([("main".into(), 12.5), ("support".into(), 3.0)]),
+        };
+
+        let failure_rate = evaluate(
+            &rule(AlertCondition::DbWriteFailureRate, 0.1),
+            &observations,
+        );
+        assert_eq!(failure_rate.len(), 1);
+        assert_eq!(failure_rate[0].agent_id, None);
+        assert!(
+            evaluate(
+                &rule(AlertCondition::DbWriteFailureRate, 0.2),
+                &observations
+            )
+            .is_empty()
+        );
+
+        let errors = evaluate(
+            &rule(AlertCondition::ConsecutiveLlmErrors, 2.0),
+            &observations,
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].message.contains("503 overloaded"));
+
+        let spend = evaluate(&rule(AlertCondition::DailySpendUsd, 10.0), &observations);
+        assert_eq!(spend.len(), 1);
+        assert_eq!(spend[0].agent_id.as_deref(), Some("main"));
+
+        let unhealthy = evaluate(&rule(AlertCondition::AgentUnhealthy, 0.0), &observations);
+        assert_eq!(unhealthy.len(), 1);
+        assert_eq!(unhealthy[0].agent_id.as_deref(), Some("main"));
+
+        // Too few writes to judge a failure rate.
+        let quiet = Observations {
+            db_writes: DbWriteCounts {
+                writes: 2,
+                failures: 2,
+            },
+            ..Default::default()
+        };
+        assert!(evaluate(&rule(AlertCondition::DbWriteFailureRate, 0.1), &quiet).is_empty());
+    }
+}
@@ -5,12 +5,15 @@
 //! Includes an SSE endpoint for realtime event streaming.
 
 mod agents;
+mod audit;
 mod bindings;
 mod channels;
 mod config;
 mod cortex;
 mod cron;
+mod examples;
 mod ingest;
+mod json_stream;
 mod memories;
 mod messaging;
 mod models;
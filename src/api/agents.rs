@@ -1,7 +1,10 @@
+use super::audit::Actor;
 use super::state::{AgentInfo, ApiState};
 
+use crate::agent::capabilities::AgentCapabilities;
 use crate::agent::cortex::CortexLogger;
 use crate::conversation::channels::ChannelStore;
+use crate::db::TimedQuery as _;
 
 use axum::Json;
 use axum::extract::{Query, State};
@@ -16,6 +19,104 @@ pub(super) struct AgentsResponse {
     agents: Vec<AgentInfo>,
 }
 
+#[derive(Serialize)]
+pub(super) struct AgentsHealthResponse {
+    agents: Vec<crate::agent::health::AgentHealth>,
+}
+
+#[derive(Serialize)]
+pub(super) struct AgentsCapabilitiesResponse {
+    agents: Vec<AgentCapabilities>,
+}
+
+#[derive(Serialize)]
+pub(super) struct AgentPresetsResponse {
+    presets: &'static [crate::identity::presets::Preset],
+}
+
+#[derive(Deserialize)]
+pub(super) struct ExperimentQuery {
+    agent_id: String,
+    #[serde(default = "default_experiment_hours")]
+    since_hours: u32,
+}
+
+fn default_experiment_hours() -> u32 {
+    168
+}
+
+#[derive(Serialize)]
+pub(super) struct ExperimentResponse {
+    enabled: bool,
+    alternate_model: Option<String>,
+    fraction: f64,
+    variants: Vec<crate::agent::experiment::VariantSummary>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct StatsQuery {
+    #[serde(default = "default_stats_hours")]
+    since_hours: u32,
+}
+
+fn default_stats_hours() -> u32 {
+    24
+}
+
+#[derive(Deserialize)]
+pub(super) struct AnalyticsQuery {
+    channel_id: Option<String>,
+    #[serde(default = "default_analytics_days")]
+    days: u32,
+}
+
+fn default_analytics_days() -> u32 {
+    30
+}
+
+#[derive(Deserialize)]
+pub(super) struct UsageQuery {
+    #[serde(default = "default_analytics_days")]
+    days: u32,
+    #[serde(default)]
+    granularity: crate::llm::usage::Granularity,
+}
+
+#[derive(Deserialize)]
+pub(super) struct SentimentQuery {
+    agent_id: String,
+    channel_id: Option<String>,
+    #[serde(default = "default_sentiment_days")]
+    days: u32,
+}
+
+fn default_sentiment_days() -> u32 {
+    30
+}
+
+#[derive(Serialize)]
+pub(super) struct SentimentResponse {
+    enabled: bool,
+    days: Vec<crate::agent::sentiment::DailySentiment>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct StructuredPromptRequest {
+    agent_id: String,
+    prompt: String,
+    schema: serde_json::Value,
+    /// Total LLM calls allowed, including retries after invalid JSON.
+    max_attempts: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub(super) struct StructuredPromptResponse {
+    success: bool,
+    output: Option<serde_json::Value>,
+    attempts: usize,
+    errors: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub(super) struct AgentOverviewResponse {
     memory_counts: HashMap<String, i64>,
@@ -113,6 +214,10 @@ pub(super) struct AgentOverviewQuery {
 #[derive(Deserialize)]
 pub(super) struct CreateAgentRequest {
     agent_id: String,
+    /// Preset to instantiate the agent from.
+    preset: Option<String>,
+    #[serde(default)]
+    preset_params: HashMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -128,9 +233,249 @@ pub(super) async fn list_agents(State(state): State<Arc<ApiState>>) -> Json<Agen
     })
 }
 
+/// Liveness of every agent: last LLM success, failure streak, queued calls,
+/// task queue depth, restarts.
+pub(super) async fn agents_health(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<AgentsHealthResponse>, StatusCode> {
+    let agent_ids: Vec<String> = state
+        .agent_configs
+        .load()
+        .iter()
+        .map(|agent| agent.id.clone())
+        .collect();
+
+    let guard = state.llm_manager.read().await;
+    let llm_manager = guard.as_ref().ok_or_else(|| {
+        tracing::error!("LLM manager not available");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut agents = llm_manager.health().snapshot(&agent_ids);
+    let runtime_configs = state.runtime_configs.load();
+    for health in &mut agents {
+        if let Some(runtime_config) = runtime_configs.get(&health.agent_id) {
+            health.running_tasks = runtime_config.task_queue.running();
+            health.queued_tasks = runtime_config.task_queue.depth();
+        }
+    }
+
+    Ok(Json(AgentsHealthResponse { agents }))
+}
+
+/// Declared capabilities of every agent: description, topics, languages, and
+/// the tools its config enables.
+pub(super) async fn agents_capabilities(
+    State(state): State<Arc<ApiState>>,
+) -> Json<AgentsCapabilitiesResponse> {
+    let mut agents: Vec<AgentCapabilities> = state
+        .runtime_configs
+        .load()
+        .values()
+        .map(|runtime_config| (**runtime_config.capabilities.load()).clone())
+        .collect();
+    agents.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+
+    Json(AgentsCapabilitiesResponse { agents })
+}
+
+/// Presets new agents can be created from, with their parameters.
+pub(super) async fn agent_presets() -> Json<AgentPresetsResponse> {
+    Json(AgentPresetsResponse {
+        presets: crate::identity::presets::PRESETS,
+    })
+}
+
+/// Compare the control and alternate models of an agent's model experiment:
+/// latency, token usage, estimated cost, and reply length per variant.
+pub(super) async fn agent_experiment(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ExperimentQuery>,
+) -> Result<Json<ExperimentResponse>, StatusCode> {
+    let pools = state.agent_read_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs
+        .get(&query.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let config = runtime_config.experiment.load();
+
+    let variants =
+        crate::agent::experiment::summarize(pool, &config.pricing, query.since_hours)
+            .await
+            .map_err(|error| {
+                tracing::warn!(%error, agent_id = %query.agent_id, "failed to summarize model experiment");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+    Ok(Json(ExperimentResponse {
+        enabled: config.enabled,
+        alternate_model: config.alternate_model.clone(),
+        fraction: config.fraction,
+        variants,
+    }))
+}
+
+/// An agent's recent channel turns: count, latency, errors, token spend, and
+/// busiest channels.
+pub(super) async fn agent_stats(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Path(agent_id): axum::extract::Path<String>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<crate::agent::stats::AgentStats>, StatusCode> {
+    let pools = state.agent_read_pools.load();
+    let pool = pools.get(&agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs
+        .get(&agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let stats = crate::agent::stats::summarize(
+        pool,
+        &runtime_config.experiment.load().pricing,
+        query.since_hours,
+    )
+    .await
+    .map_err(|error| {
+        tracing::warn!(%error, %agent_id, "failed to summarize agent stats");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(stats))
+}
+
+/// Channel activity of an agent: an hour-of-week heatmap, daily response
+/// times, and the share of messages it replied to per channel.
+pub(super) async fn agent_analytics(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Path(agent_id): axum::extract::Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<crate::agent::analytics::ChannelAnalytics>, StatusCode> {
+    let pools = state.agent_read_pools.load();
+    let pool = pools.get(&agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let analytics = crate::agent::analytics::report(pool, query.channel_id.as_deref(), query.days)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, %agent_id, "failed to report channel analytics");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(analytics))
+}
+
+/// Tokens and cost of an agent's LLM calls per model, hour or day.
+pub(super) async fn agent_usage(
+    State(state): State<Arc<ApiState>>,
+    axum::extract::Path(agent_id): axum::extract::Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<crate::llm::usage::UsageReport>, StatusCode> {
+    let pools = state.agent_read_pools.load();
+    let pool = pools.get(&agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let usage = crate::llm::usage::report(pool, query.days, query.granularity)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, %agent_id, "failed to report token usage");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(usage))
+}
+
+/// Reactions on an agent's replies per channel and day, with the share that
+/// were positive.
+pub(super) async fn agent_sentiment(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<SentimentQuery>,
+) -> Result<Json<SentimentResponse>, StatusCode> {
+    let pools = state.agent_read_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs
+        .get(&query.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let days = crate::agent::sentiment::daily(pool, query.channel_id.as_deref(), query.days)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, agent_id = %query.agent_id, "failed to summarize reaction sentiment");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SentimentResponse {
+        enabled: runtime_config.sentiment.load().enabled,
+        days,
+    }))
+}
+
+/// Prompt an agent for JSON matching a caller-supplied schema.
+///
+/// Invalid responses are retried with the validation errors fed back to the
+/// model. A response that never validates returns `success: false` with the
+/// last round of errors.
+pub(super) async fn structured_prompt(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<StructuredPromptRequest>,
+) -> Result<Json<StructuredPromptResponse>, StatusCode> {
+    use crate::llm::structured::{self, StructuredOutputError};
+
+    let runtime_configs = state.runtime_configs.load();
+    let runtime_config = runtime_configs
+        .get(&request.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let llm_manager = state.llm_manager.read().await.clone().ok_or_else(|| {
+        tracing::error!("LLM manager not available");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let routing = runtime_config.routing.load();
+    let model_name = routing
+        .resolve(crate::ProcessType::Branch, None)
+        .to_string();
+    let model = crate::llm::SpacebotModel::make(&llm_manager, &model_name)
+        .with_agent(&request.agent_id)
+        .with_routing((**routing).clone());
+    let agent = rig::agent::AgentBuilder::new(model).build();
+    let prompt_engine = runtime_config.prompts.load();
+
+    let result = structured::prompt_structured(
+        &agent,
+        &request.prompt,
+        &request.schema,
+        &prompt_engine,
+        request
+            .max_attempts
+            .unwrap_or(structured::DEFAULT_MAX_ATTEMPTS),
+    )
+    .await;
+
+    match result {
+        Ok(output) => Ok(Json(StructuredPromptResponse {
+            success: true,
+            output: Some(output.value),
+            attempts: output.attempts,
+            errors: Vec::new(),
+        })),
+        Err(StructuredOutputError::Invalid {
+            attempts, errors, ..
+        }) => Ok(Json(StructuredPromptResponse {
+            success: false,
+            output: None,
+            attempts,
+            errors,
+        })),
+        Err(error) => {
+            tracing::warn!(%error, agent_id = %request.agent_id, "structured prompt failed");
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
 /// Create a new agent and initialize it live (directories, databases, memory, identity, cron, cortex).
 pub(super) async fn create_agent(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<CreateAgentRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let agent_id = request.agent_id.trim().to_string();
@@ -151,6 +496,17 @@ pub(super) async fn create_agent(
         }
     }
 
+    if let Some(preset) = &request.preset {
+        let rendered = crate::identity::presets::find(preset)
+            .and_then(|preset| preset.render(&request.preset_params));
+        if let Err(error) = rendered {
+            return Ok(Json(serde_json::json!({
+                "success": false,
+                "message": error.to_string()
+            })));
+        }
+    }
+
     let config_path = state.config_path.read().await.clone();
     let instance_dir = (**state.instance_dir.load()).clone();
 
@@ -178,6 +534,16 @@ pub(super) async fn create_agent(
 
     let mut new_table = toml_edit::Table::new();
     new_table["id"] = toml_edit::value(&agent_id);
+    if let Some(preset) = &request.preset {
+        new_table["preset"] = toml_edit::value(preset);
+        let mut params: Vec<_> = request.preset_params.iter().collect();
+        params.sort();
+        let mut params_table = toml_edit::Table::new();
+        for (name, value) in params {
+            params_table[name.as_str()] = toml_edit::value(value);
+        }
+        new_table["preset_params"] = toml_edit::Item::Table(params_table);
+    }
     agents_array.push(new_table);
 
     tokio::fs::write(&config_path, doc.to_string())
@@ -193,7 +559,21 @@ pub(super) async fn create_agent(
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let raw_config = crate::config::AgentConfig {
+    // A preset's config is expanded when the file is parsed, so read the new
+    // entry back rather than building it here.
+    let preset_config = match &request.preset {
+        Some(_) => crate::config::Config::load_from_path_async(&config_path)
+            .await
+            .map_err(|error| {
+                tracing::error!(%error, "failed to reload config.toml");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .agents
+            .into_iter()
+            .find(|agent| agent.id == agent_id),
+        None => None,
+    };
+    let raw_config = preset_config.unwrap_or_else(|| crate::config::AgentConfig {
         id: agent_id.clone(),
         default: false,
         workspace: None,
@@ -209,10 +589,32 @@ pub(super) async fn create_agent(
         ingestion: None,
         cortex: None,
         browser: None,
+        reflection: None,
+        vision: None,
+        moderation: None,
+        sentiment: None,
+        tool_loop: None,
+        redaction: None,
+        injection: None,
+        experiment: None,
+        ensemble: None,
+        task_queue: None,
+        listening: None,
+        digest: None,
+        backpressure: None,
+        max_concurrent_llm_calls: None,
         brave_search_key: None,
         cron: Vec::new(),
-    };
+        vector_store: None,
+        conversation_store: None,
+        capabilities: crate::config::CapabilitiesConfig::default(),
+        embed_color: None,
+        display_name: None,
+        avatar_url: None,
+        preset: None,
+    });
     let agent_config = raw_config.resolve(&instance_dir, defaults);
+    let embedding_config = defaults.embedding.clone();
     drop(defaults);
 
     for dir in [
@@ -254,27 +656,87 @@ pub(super) async fn create_agent(
             .clone()
     };
 
+    let llm_manager = {
+        let guard = state.llm_manager.read().await;
+        guard
+            .as_ref()
+            .ok_or_else(|| {
+                tracing::error!("LLM manager not available");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .clone()
+    };
+    llm_manager
+        .call_log()
+        .register(&agent_id, db.sqlite.clone());
+
     let memory_store = crate::memory::MemoryStore::new(db.sqlite.clone());
-    let embedding_table = crate::memory::EmbeddingTable::open_or_create(&db.lance)
-        .await
-        .map_err(|error| {
-            tracing::error!(%error, agent_id = %agent_id, "failed to init embeddings");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let vector_store = crate::memory::open_vector_store(
+        &agent_config.vector_store,
+        &agent_id,
+        &db,
+        llm_manager.http_client().clone(),
+    )
+    .await
+    .map_err(|error| {
+        tracing::error!(
+            %error,
+            agent_id = %agent_id,
+            backend = agent_config.vector_store.name(),
+            "failed to open vector store"
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    if let Err(error) = embedding_table.ensure_fts_index().await {
+    if let Err(error) = vector_store.ensure_fts_index().await {
         tracing::warn!(%error, agent_id = %agent_id, "failed to create FTS index");
     }
 
-    let memory_search = std::sync::Arc::new(crate::memory::MemorySearch::new(
-        memory_store,
-        embedding_table,
-        embedding_model,
-    ));
+    let conversation_store = crate::conversation::open_conversation_store(
+        &agent_config.conversation_store,
+        &agent_id,
+        &db.sqlite,
+        llm_manager.http_client().clone(),
+    )
+    .await
+    .map_err(|error| {
+        tracing::error!(
+            %error,
+            agent_id = %agent_id,
+            backend = agent_config.conversation_store.name(),
+            "failed to open conversation store"
+        );
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let embedding_pipeline = crate::memory::EmbeddingPipeline::spawn(
+        embedding_model.clone(),
+        vector_store.clone(),
+        embedding_config,
+    );
+    let memory_search = std::sync::Arc::new(
+        crate::memory::MemorySearch::new(memory_store, vector_store, embedding_model)
+            .with_embedding_pipeline(embedding_pipeline),
+    );
 
     let (event_tx, _) = tokio::sync::broadcast::channel(256);
     let arc_agent_id: crate::AgentId = std::sync::Arc::from(agent_id.as_str());
-
+    llm_manager
+        .concurrency()
+        .register_agent(&agent_id, agent_config.max_concurrent_llm_calls);
+
+    if let Some(preset) = &agent_config.preset {
+        crate::identity::scaffold_preset_files(
+            &agent_config.workspace,
+            &preset.name,
+            &preset.params,
+        )
+        .await
+        .map_err(|error| {
+            tracing::error!(%error, agent_id = %agent_id, "failed to write preset files");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
     crate::identity::scaffold_identity_files(&agent_config.workspace)
         .await
         .map_err(|error| {
@@ -318,17 +780,17 @@ pub(super) async fn create_agent(
         skills,
     ));
     runtime_config.set_settings(settings_store.clone());
-
-    let llm_manager = {
-        let guard = state.llm_manager.read().await;
-        guard
-            .as_ref()
-            .ok_or_else(|| {
-                tracing::error!("LLM manager not available");
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?
-            .clone()
-    };
+    if let Some(conversation_store) = conversation_store {
+        runtime_config.set_conversation_store(conversation_store);
+    }
+    runtime_config.set_peers(
+        state
+            .runtime_configs
+            .load()
+            .values()
+            .map(|peer| (**peer.capabilities.load()).clone())
+            .collect(),
+    );
 
     let deps = crate::AgentDeps {
         agent_id: arc_agent_id.clone(),
@@ -338,6 +800,7 @@ pub(super) async fn create_agent(
         runtime_config: runtime_config.clone(),
         event_tx: event_tx.clone(),
         sqlite_pool: db.sqlite.clone(),
+        sqlite_read: db.sqlite_read.clone(),
         messaging_manager: {
             let guard = state.messaging_manager.read().await;
             guard.as_ref().cloned()
@@ -347,7 +810,9 @@ pub(super) async fn create_agent(
     let event_rx = event_tx.subscribe();
     state.register_agent_events(agent_id.clone(), event_rx);
 
-    let cron_store = std::sync::Arc::new(crate::cron::CronStore::new(db.sqlite.clone()));
+    let cron_store = std::sync::Arc::new(
+        crate::cron::CronStore::new(db.sqlite.clone()).with_readers(db.sqlite_read.clone()),
+    );
     let cron_context = crate::cron::CronContext {
         deps: deps.clone(),
         screenshot_dir: agent_config.screenshot_dir(),
@@ -369,8 +834,10 @@ pub(super) async fn create_agent(
     let browser_config = (**runtime_config.browser_config.load()).clone();
     let brave_search_key = (**runtime_config.brave_search_key.load()).clone();
     let conversation_logger =
-        crate::conversation::history::ConversationLogger::new(db.sqlite.clone());
-    let channel_store = crate::conversation::ChannelStore::new(db.sqlite.clone());
+        crate::conversation::history::ConversationLogger::new(db.sqlite.clone())
+            .with_readers(db.sqlite_read.clone());
+    let channel_store = crate::conversation::ChannelStore::new(db.sqlite.clone())
+        .with_readers(db.sqlite_read.clone());
     let cortex_tool_server = crate::tools::create_cortex_chat_tool_server(
         memory_search.clone(),
         conversation_logger,
@@ -381,14 +848,16 @@ pub(super) async fn create_agent(
         runtime_config.workspace_dir.clone(),
         runtime_config.instance_dir.clone(),
     );
-    let cortex_store = crate::agent::cortex_chat::CortexChatStore::new(db.sqlite.clone());
+    let cortex_store = crate::agent::cortex_chat::CortexChatStore::new(db.sqlite.clone())
+        .with_readers(db.sqlite_read.clone());
     let cortex_session = crate::agent::cortex_chat::CortexChatSession::new(
         deps.clone(),
         cortex_tool_server,
         cortex_store,
     );
 
-    let cortex_logger = crate::agent::cortex::CortexLogger::new(db.sqlite.clone());
+    let cortex_logger = crate::agent::cortex::CortexLogger::new(db.sqlite.clone())
+        .with_readers(db.sqlite_read.clone());
     tokio::spawn({
         let deps = deps.clone();
         let logger = cortex_logger.clone();
@@ -403,12 +872,21 @@ pub(super) async fn create_agent(
         }
     });
 
+    crate::agent::analytics::spawn_rollup_loop(db.sqlite.clone(), db.sqlite_read.clone());
+    crate::llm::usage::spawn_rollup_loop(
+        db.sqlite.clone(),
+        db.sqlite_read.clone(),
+        runtime_config.clone(),
+    );
+    crate::agent::digest::spawn_digest_loop(deps.clone());
+
     let ingestion_config = **runtime_config.ingestion.load();
     if ingestion_config.enabled {
         crate::agent::ingestion::spawn_ingestion_loop(agent_config.ingest_dir(), deps.clone());
     }
 
     let sqlite_pool = db.sqlite.clone();
+    let sqlite_read = db.sqlite_read.clone();
     let mut deps_with_cron = deps.clone();
     deps_with_cron.cron_tool = Some(cron_tool);
     let agent = crate::Agent {
@@ -426,6 +904,12 @@ pub(super) async fn create_agent(
         pools.insert(agent_id.clone(), sqlite_pool);
         state.agent_pools.store(std::sync::Arc::new(pools));
 
+        let mut read_pools = (**state.agent_read_pools.load()).clone();
+        read_pools.insert(agent_id.clone(), sqlite_read);
+        state
+            .agent_read_pools
+            .store(std::sync::Arc::new(read_pools));
+
         let mut searches = (**state.memory_searches.load()).clone();
         searches.insert(agent_id.clone(), memory_search);
         state.memory_searches.store(std::sync::Arc::new(searches));
@@ -469,6 +953,15 @@ pub(super) async fn create_agent(
     }
 
     tracing::info!(agent_id = %agent_id, "agent created and initialized via API");
+    state.audit(
+        &actor,
+        "agent.create",
+        Some(agent_id.as_str()),
+        serde_json::json!({
+            "preset": request.preset,
+            "preset_params": request.preset_params,
+        }),
+    );
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -480,6 +973,7 @@ pub(super) async fn create_agent(
 /// Delete an agent: remove from config.toml, clean up API state, signal main loop.
 pub(super) async fn delete_agent(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Query(query): Query<DeleteAgentQuery>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let agent_id = query.agent_id.trim().to_string();
@@ -542,10 +1036,12 @@ pub(super) async fn delete_agent(
             })?;
     }
 
-    // Close the SQLite pool before removing state
+    // Close the SQLite pools before removing state
     {
-        let pools = state.agent_pools.load();
-        if let Some(pool) = pools.get(&agent_id) {
+        if let Some(pool) = state.agent_read_pools.load().get(&agent_id) {
+            pool.close().await;
+        }
+        if let Some(pool) = state.agent_pools.load().get(&agent_id) {
             pool.close().await;
         }
     }
@@ -556,6 +1052,12 @@ pub(super) async fn delete_agent(
         pools.remove(&agent_id);
         state.agent_pools.store(std::sync::Arc::new(pools));
 
+        let mut read_pools = (**state.agent_read_pools.load()).clone();
+        read_pools.remove(&agent_id);
+        state
+            .agent_read_pools
+            .store(std::sync::Arc::new(read_pools));
+
         let mut searches = (**state.memory_searches.load()).clone();
         searches.remove(&agent_id);
         state.memory_searches.store(std::sync::Arc::new(searches));
@@ -597,6 +1099,12 @@ pub(super) async fn delete_agent(
     }
 
     tracing::info!(agent_id = %agent_id, "agent deleted via API");
+    state.audit(
+        &actor,
+        "agent.delete",
+        Some(agent_id.as_str()),
+        serde_json::json!({}),
+    );
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -609,13 +1117,14 @@ pub(super) async fn agent_overview(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<AgentOverviewQuery>,
 ) -> Result<Json<AgentOverviewResponse>, StatusCode> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
     let memory_rows = sqlx::query(
         "SELECT memory_type, COUNT(*) as count FROM memories WHERE forgotten = 0 GROUP BY memory_type",
     )
     .fetch_all(pool)
+    .timed_with("overview.memory_counts", || format!("agent_id={}", query.agent_id))
     .await
     .map_err(|error| {
         tracing::warn!(%error, agent_id = %query.agent_id, "failed to count memories");
@@ -639,6 +1148,7 @@ pub(super) async fn agent_overview(
         "SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled FROM cron_jobs ORDER BY created_at ASC",
     )
     .fetch_all(pool)
+    .timed_with("overview.cron_jobs", || format!("agent_id={}", query.agent_id))
     .await
     .unwrap_or_default();
 
@@ -684,6 +1194,7 @@ pub(super) async fn agent_overview(
         "SELECT date(created_at) as date, COUNT(*) as count FROM memories WHERE forgotten = 0 AND created_at > date('now', '-30 days') GROUP BY date ORDER BY date",
     )
     .fetch_all(pool)
+    .timed_with("overview.memory_daily", || format!("agent_id={}", query.agent_id))
     .await
     .unwrap_or_default();
 
@@ -702,6 +1213,7 @@ pub(super) async fn agent_overview(
     )
     .bind(activity_window.to_rfc3339())
     .fetch_all(pool)
+    .timed_with("overview.branch_activity", || format!("agent_id={}", query.agent_id))
     .await
     .unwrap_or_default();
 
@@ -710,6 +1222,7 @@ pub(super) async fn agent_overview(
     )
     .bind(activity_window.to_rfc3339())
     .fetch_all(pool)
+    .timed_with("overview.worker_activity", || format!("agent_id={}", query.agent_id))
     .await
     .unwrap_or_default();
 
@@ -746,6 +1259,7 @@ pub(super) async fn agent_overview(
         "SELECT CAST(strftime('%w', created_at) AS INTEGER) as day, CAST(strftime('%H', created_at) AS INTEGER) as hour, COUNT(*) as count FROM conversation_messages WHERE created_at > date('now', '-90 days') GROUP BY day, hour",
     )
     .fetch_all(pool)
+    .timed_with("overview.heatmap", || format!("agent_id={}", query.agent_id))
     .await
     .unwrap_or_default();
 
@@ -777,7 +1291,7 @@ pub(super) async fn instance_overview(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<InstanceOverviewResponse>, StatusCode> {
     let uptime = state.started_at.elapsed();
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let configs = state.agent_configs.load();
 
     let mut agents: Vec<AgentSummary> = Vec::new();
@@ -802,11 +1316,17 @@ pub(super) async fn instance_overview(
         let memory_total: i64 =
             sqlx::query_scalar("SELECT COUNT(*) FROM memories WHERE forgotten = 0")
                 .fetch_one(pool)
+                .timed_with("agents_overview.memory_count", || {
+                    format!("agent_id={agent_id}")
+                })
                 .await
                 .unwrap_or(0);
 
         let cron_job_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cron_jobs")
             .fetch_one(pool)
+            .timed_with("agents_overview.cron_count", || {
+                format!("agent_id={agent_id}")
+            })
             .await
             .unwrap_or(0);
 
@@ -816,6 +1336,7 @@ pub(super) async fn instance_overview(
         )
         .bind(activity_window.to_rfc3339())
         .fetch_all(pool)
+        .timed_with("agents_overview.activity", || format!("agent_id={agent_id}"))
         .await
         .unwrap_or_default();
 
@@ -868,7 +1389,7 @@ pub(super) async fn get_agent_profile(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<AgentOverviewQuery>,
 ) -> Result<Json<AgentProfileResponse>, StatusCode> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
     let profile = crate::agent::cortex::load_profile(pool, &query.agent_id).await;
@@ -899,6 +1420,7 @@ pub(super) async fn get_identity(
 /// The file watcher will pick up changes and hot-reload identity into RuntimeConfig.
 pub(super) async fn update_identity(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::Json(request): axum::Json<IdentityUpdateRequest>,
 ) -> Result<Json<IdentityResponse>, StatusCode> {
     let workspaces = state.agent_workspaces.load();
@@ -933,6 +1455,17 @@ pub(super) async fn update_identity(
             })?;
     }
 
+    state.audit(
+        &actor,
+        "agent.update_identity",
+        Some(request.agent_id.as_str()),
+        serde_json::json!({
+            "soul": request.soul.is_some(),
+            "identity": request.identity.is_some(),
+            "user": request.user.is_some(),
+        }),
+    );
+
     let updated = crate::identity::Identity::load(workspace).await;
 
     Ok(Json(IdentityResponse {
@@ -0,0 +1,96 @@
+//! Admin audit trail: who changed what through the API.
+
+use super::state::ApiState;
+
+use crate::audit::{AuditEntry, AuditFilter};
+
+use axum::Json;
+use axum::extract::{FromRequestParts, Query, State};
+use axum::http::StatusCode;
+use axum::http::request::Parts;
+use serde::{Deserialize, Serialize};
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Header naming who's making an admin request.
+const ACTOR_HEADER: &str = "x-spacebot-actor";
+
+/// Recorded when a request doesn't name its actor.
+const DEFAULT_ACTOR: &str = "api";
+
+/// Longest actor name kept from the header.
+const MAX_ACTOR_LEN: usize = 64;
+
+/// Who's making a request, from the `X-Spacebot-Actor` header.
+pub(super) struct Actor(pub String);
+
+impl<S: Send + Sync> FromRequestParts<S> for Actor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let actor = parts
+            .headers
+            .get(ACTOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|actor| !actor.is_empty())
+            .map(|actor| actor.chars().take(MAX_ACTOR_LEN).collect())
+            .unwrap_or_else(|| DEFAULT_ACTOR.to_string());
+        Ok(Self(actor))
+    }
+}
+
+impl std::ops::Deref for Actor {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn default_limit() -> i64 {
+    100
+}
+
+#[derive(Deserialize)]
+pub(super) struct AuditQuery {
+    #[serde(default)]
+    actor: Option<String>,
+    #[serde(default)]
+    action: Option<String>,
+    #[serde(default)]
+    agent_id: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+#[derive(Serialize)]
+pub(super) struct AuditResponse {
+    entries: Vec<AuditEntry>,
+}
+
+/// List recorded admin actions, newest first.
+pub(super) async fn list_audit(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditResponse>, StatusCode> {
+    let audit_log = state.audit_log.load();
+    let audit_log = audit_log
+        .as_ref()
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let filter = AuditFilter {
+        actor: query.actor,
+        action: query.action,
+        agent_id: query.agent_id,
+        limit: query.limit,
+    };
+    let entries = audit_log.list(&filter).await.map_err(|error| {
+        tracing::warn!(%error, "failed to list admin actions");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(AuditResponse { entries }))
+}
@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::ApiState;
 
 use axum::Json;
@@ -155,6 +156,7 @@ pub(super) async fn list_bindings(
 /// Create a new binding (and optionally configure platform credentials).
 pub(super) async fn create_binding(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::Json(request): axum::Json<CreateBindingRequest>,
 ) -> Result<Json<CreateBindingResponse>, StatusCode> {
     let config_path = state.config_path.read().await.clone();
@@ -313,8 +315,22 @@ pub(super) async fn create_binding(
         channel = %request.channel,
         "binding created via API"
     );
+    state.audit(
+        &actor,
+        "binding.create",
+        Some(request.agent_id.as_str()),
+        serde_json::json!({
+            "channel": request.channel,
+            "guild_id": request.guild_id,
+            "workspace_id": request.workspace_id,
+            "chat_id": request.chat_id,
+            "channel_ids": request.channel_ids,
+            "dm_allowed_users": request.dm_allowed_users,
+            "credentials_changed": request.platform_credentials.is_some(),
+        }),
+    );
 
-    if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
+    if let Ok(new_config) = crate::config::Config::load_from_path_async(&config_path).await {
         let bindings_guard = state.bindings.read().await;
         if let Some(bindings_swap) = bindings_guard.as_ref() {
             bindings_swap.store(std::sync::Arc::new(new_config.bindings.clone()));
@@ -471,6 +487,7 @@ pub(super) async fn create_binding(
 
 pub(super) async fn update_binding(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::Json(request): axum::Json<UpdateBindingRequest>,
 ) -> Result<Json<UpdateBindingResponse>, StatusCode> {
     let config_path = state.config_path.read().await.clone();
@@ -598,8 +615,28 @@ pub(super) async fn update_binding(
         channel = %request.channel,
         "binding updated via API"
     );
+    state.audit(
+        &actor,
+        "binding.update",
+        Some(request.agent_id.as_str()),
+        serde_json::json!({
+            "original": {
+                "agent_id": request.original_agent_id,
+                "channel": request.original_channel,
+                "guild_id": request.original_guild_id,
+                "workspace_id": request.original_workspace_id,
+                "chat_id": request.original_chat_id,
+            },
+            "channel": request.channel,
+            "guild_id": request.guild_id,
+            "workspace_id": request.workspace_id,
+            "chat_id": request.chat_id,
+            "channel_ids": request.channel_ids,
+            "dm_allowed_users": request.dm_allowed_users,
+        }),
+    );
 
-    if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
+    if let Ok(new_config) = crate::config::Config::load_from_path_async(&config_path).await {
         let bindings_guard = state.bindings.read().await;
         if let Some(bindings_swap) = bindings_guard.as_ref() {
             bindings_swap.store(std::sync::Arc::new(new_config.bindings.clone()));
@@ -636,6 +673,7 @@ pub(super) async fn update_binding(
 /// Delete a binding by matching agent_id + channel + platform-specific identifiers.
 pub(super) async fn delete_binding(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::Json(request): axum::Json<DeleteBindingRequest>,
 ) -> Result<Json<DeleteBindingResponse>, StatusCode> {
     let config_path = state.config_path.read().await.clone();
@@ -718,8 +756,19 @@ pub(super) async fn delete_binding(
         channel = %request.channel,
         "binding deleted via API"
     );
+    state.audit(
+        &actor,
+        "binding.delete",
+        Some(request.agent_id.as_str()),
+        serde_json::json!({
+            "channel": request.channel,
+            "guild_id": request.guild_id,
+            "workspace_id": request.workspace_id,
+            "chat_id": request.chat_id,
+        }),
+    );
 
-    if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
+    if let Ok(new_config) = crate::config::Config::load_from_path_async(&config_path).await {
         let bindings_guard = state.bindings.read().await;
         if let Some(bindings_swap) = bindings_guard.as_ref() {
             bindings_swap.store(std::sync::Arc::new(new_config.bindings.clone()));
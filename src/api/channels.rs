@@ -1,5 +1,9 @@
+use super::audit::Actor;
+use super::json_stream::StreamedJson;
 use super::state::ApiState;
 
+use crate::agent::channel::ChannelState;
+use crate::agent::status::StatusBlock;
 use crate::conversation::channels::ChannelStore;
 use crate::conversation::history::ProcessRunLogger;
 
@@ -26,12 +30,6 @@ pub(super) struct ChannelsResponse {
     channels: Vec<ChannelResponse>,
 }
 
-#[derive(Serialize)]
-pub(super) struct MessagesResponse {
-    items: Vec<crate::conversation::history::TimelineItem>,
-    has_more: bool,
-}
-
 #[derive(Deserialize)]
 pub(super) struct MessagesQuery {
     channel_id: String,
@@ -46,6 +44,8 @@ fn default_message_limit() -> i64 {
 
 #[derive(Deserialize)]
 pub(super) struct CancelProcessRequest {
+    /// Agent whose process to cancel. None searches every agent in the channel.
+    agent_id: Option<String>,
     channel_id: String,
     process_type: String,
     process_id: String,
@@ -57,9 +57,23 @@ pub(super) struct CancelProcessResponse {
     message: String,
 }
 
+#[derive(Deserialize)]
+pub(super) struct CancelTurnRequest {
+    /// Channel to stop. None stops every channel of the agent.
+    channel_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct CancelTurnResponse {
+    success: bool,
+    message: String,
+    /// Channels whose turn was cancelled.
+    cancelled: Vec<String>,
+}
+
 /// List active channels across all agents.
 pub(super) async fn list_channels(State(state): State<Arc<ApiState>>) -> Json<ChannelsResponse> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let mut all_channels = Vec::new();
 
     for (agent_id, pool) in pools.iter() {
@@ -90,12 +104,13 @@ pub(super) async fn list_channels(State(state): State<Arc<ApiState>>) -> Json<Ch
 }
 
 /// Get the unified timeline for a channel: messages, branch runs, and worker runs
-/// interleaved chronologically.
+/// interleaved chronologically. Streamed as `{items, has_more}`, since worker
+/// results can make it large.
 pub(super) async fn channel_messages(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<MessagesQuery>,
-) -> Json<MessagesResponse> {
-    let pools = state.agent_pools.load();
+) -> StreamedJson {
+    let pools = state.agent_read_pools.load();
     let limit = query.limit.min(100);
     let fetch_limit = limit + 1;
 
@@ -105,14 +120,12 @@ pub(super) async fn channel_messages(
             .load_channel_timeline(&query.channel_id, fetch_limit, query.before.as_deref())
             .await
         {
-            Ok(items) if !items.is_empty() => {
+            Ok(mut items) if !items.is_empty() => {
                 let has_more = items.len() as i64 > limit;
-                let items = if has_more {
-                    items[items.len() - limit as usize..].to_vec()
-                } else {
-                    items
-                };
-                return Json(MessagesResponse { items, has_more });
+                if has_more {
+                    items = items.split_off(items.len() - limit as usize);
+                }
+                return timeline_response(items, has_more);
             }
             Ok(_) => continue,
             Err(error) => {
@@ -122,27 +135,44 @@ pub(super) async fn channel_messages(
         }
     }
 
-    Json(MessagesResponse {
-        items: vec![],
-        has_more: false,
-    })
+    timeline_response(Vec::new(), false)
+}
+
+fn timeline_response(
+    items: Vec<crate::conversation::history::TimelineItem>,
+    has_more: bool,
+) -> StreamedJson {
+    StreamedJson::new()
+        .array("items", items)
+        .field("has_more", &has_more)
 }
 
 /// Get live status (active workers, branches, completed items) for all channels.
+/// Channels shared by several agents report the work of all of them.
 pub(super) async fn channel_status(
     State(state): State<Arc<ApiState>>,
-) -> Json<HashMap<String, serde_json::Value>> {
+) -> Json<HashMap<String, StatusBlock>> {
     let snapshot: Vec<_> = {
         let blocks = state.channel_status_blocks.read().await;
-        blocks.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        blocks
+            .iter()
+            .map(|((_, channel_id), block)| (channel_id.clone(), block.clone()))
+            .collect()
     };
 
-    let mut result = HashMap::new();
+    let mut result: HashMap<String, StatusBlock> = HashMap::new();
     for (channel_id, status_block) in snapshot {
         let block = status_block.read().await;
-        if let Ok(value) = serde_json::to_value(&*block) {
-            result.insert(channel_id, value);
-        }
+        let merged = result.entry(channel_id).or_default();
+        merged
+            .active_branches
+            .extend(block.active_branches.iter().cloned());
+        merged
+            .active_workers
+            .extend(block.active_workers.iter().cloned());
+        merged
+            .completed_items
+            .extend(block.completed_items.iter().cloned());
     }
 
     Json(result)
@@ -151,42 +181,122 @@ pub(super) async fn channel_status(
 /// Cancel a running worker or branch via the API.
 pub(super) async fn cancel_process(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<CancelProcessRequest>,
 ) -> Result<Json<CancelProcessResponse>, StatusCode> {
     let states = state.channel_states.read().await;
-    let channel_state = states
-        .get(&request.channel_id)
-        .ok_or(StatusCode::NOT_FOUND)?;
+    let candidates: Vec<&ChannelState> = states
+        .iter()
+        .filter(|((agent_id, channel_id), _)| {
+            *channel_id == request.channel_id
+                && request
+                    .agent_id
+                    .as_ref()
+                    .is_none_or(|requested| requested == agent_id)
+        })
+        .map(|(_, channel_state)| channel_state)
+        .collect();
 
-    match request.process_type.as_str() {
+    // Process ids are unique, so at most one of the channel's agents owns it.
+    let mut cancelled_by = None;
+    let message = match request.process_type.as_str() {
         "worker" => {
             let worker_id: crate::WorkerId = request
                 .process_id
                 .parse()
                 .map_err(|_| StatusCode::BAD_REQUEST)?;
-            channel_state
-                .cancel_worker(worker_id)
-                .await
-                .map_err(|_| StatusCode::NOT_FOUND)?;
-            Ok(Json(CancelProcessResponse {
-                success: true,
-                message: format!("Worker {} cancelled", request.process_id),
-            }))
+            for channel_state in candidates {
+                if channel_state.cancel_worker(worker_id).await.is_ok() {
+                    cancelled_by = Some(channel_state);
+                    break;
+                }
+            }
+            format!("Worker {} cancelled", request.process_id)
         }
         "branch" => {
             let branch_id: crate::BranchId = request
                 .process_id
                 .parse()
                 .map_err(|_| StatusCode::BAD_REQUEST)?;
-            channel_state
-                .cancel_branch(branch_id)
-                .await
-                .map_err(|_| StatusCode::NOT_FOUND)?;
-            Ok(Json(CancelProcessResponse {
-                success: true,
-                message: format!("Branch {} cancelled", request.process_id),
-            }))
+            for channel_state in candidates {
+                if channel_state.cancel_branch(branch_id).await.is_ok() {
+                    cancelled_by = Some(channel_state);
+                    break;
+                }
+            }
+            format!("Branch {} cancelled", request.process_id)
         }
-        _ => Err(StatusCode::BAD_REQUEST),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let channel_state = cancelled_by.ok_or(StatusCode::NOT_FOUND)?;
+
+    state.audit(
+        &actor,
+        "process.cancel",
+        Some(&channel_state.deps.agent_id[..]),
+        serde_json::json!({
+            "channel_id": request.channel_id,
+            "process_type": request.process_type,
+            "process_id": request.process_id,
+        }),
+    );
+    Ok(Json(CancelProcessResponse {
+        success: true,
+        message,
+    }))
+}
+
+/// Abort an agent's in-flight LLM call or tool loop, in one channel or in all
+/// of its channels. Any partially streamed reply is removed.
+pub(super) async fn cancel_turn(
+    State(state): State<Arc<ApiState>>,
+    actor: Actor,
+    axum::extract::Path(agent_id): axum::extract::Path<String>,
+    Json(request): Json<CancelTurnRequest>,
+) -> Result<Json<CancelTurnResponse>, StatusCode> {
+    if !state.runtime_configs.load().contains_key(&agent_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let states = state.channel_states.read().await;
+    let channels: Vec<_> = states
+        .iter()
+        .filter(|((channel_agent_id, channel_id), _)| {
+            *channel_agent_id == agent_id
+                && request
+                    .channel_id
+                    .as_ref()
+                    .is_none_or(|requested| requested == *channel_id)
+        })
+        .collect();
+    if request.channel_id.is_some() && channels.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
     }
+
+    let cancelled: Vec<String> = channels
+        .into_iter()
+        .filter(|(_, channel_state)| channel_state.cancel_turn("cancelled via API"))
+        .map(|((_, channel_id), _)| channel_id.clone())
+        .collect();
+
+    state.audit(
+        &actor,
+        "turn.cancel",
+        Some(agent_id.as_str()),
+        serde_json::json!({
+            "channel_id": request.channel_id,
+            "cancelled": cancelled,
+        }),
+    );
+
+    let message = match cancelled.len() {
+        0 => "No turn in progress".to_string(),
+        1 => "Cancelled 1 turn".to_string(),
+        count => format!("Cancelled {count} turns"),
+    };
+    Ok(Json(CancelTurnResponse {
+        success: !cancelled.is_empty(),
+        message,
+        cancelled,
+    }))
 }
@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::ApiState;
 
 use axum::Json;
@@ -89,7 +90,7 @@ pub(super) struct AgentConfigQuery {
     agent_id: String,
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub(super) struct AgentConfigUpdateRequest {
     agent_id: String,
     #[serde(default)]
@@ -110,7 +111,7 @@ pub(super) struct AgentConfigUpdateRequest {
     discord: Option<DiscordUpdate>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct RoutingUpdate {
     channel: Option<String>,
     branch: Option<String>,
@@ -120,7 +121,7 @@ pub(super) struct RoutingUpdate {
     rate_limit_cooldown_secs: Option<u64>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct TuningUpdate {
     max_concurrent_branches: Option<usize>,
     max_concurrent_workers: Option<usize>,
@@ -130,14 +131,14 @@ pub(super) struct TuningUpdate {
     history_backfill_count: Option<usize>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct CompactionUpdate {
     background_threshold: Option<f32>,
     aggressive_threshold: Option<f32>,
     emergency_threshold: Option<f32>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct CortexUpdate {
     tick_interval_secs: Option<u64>,
     worker_timeout_secs: Option<u64>,
@@ -148,7 +149,7 @@ pub(super) struct CortexUpdate {
     bulletin_max_turns: Option<usize>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct CoalesceUpdate {
     enabled: Option<bool>,
     debounce_ms: Option<u64>,
@@ -157,24 +158,45 @@ pub(super) struct CoalesceUpdate {
     multi_user_only: Option<bool>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct MemoryPersistenceUpdate {
     enabled: Option<bool>,
     message_interval: Option<usize>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct BrowserUpdate {
     enabled: Option<bool>,
     headless: Option<bool>,
     evaluate_enabled: Option<bool>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub(super) struct DiscordUpdate {
     allow_bot_messages: Option<bool>,
 }
 
+#[derive(Serialize, Debug)]
+pub(super) struct LastReloadResponse {
+    last_reload: Option<crate::config::ConfigReload>,
+}
+
+/// What the agent's last effective hot reload changed, field by field.
+/// `last_reload` is null until a reload changes something.
+pub(super) async fn get_last_reload(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<AgentConfigQuery>,
+) -> Result<Json<LastReloadResponse>, StatusCode> {
+    let runtime_configs = state.runtime_configs.load();
+    let rc = runtime_configs
+        .get(&query.agent_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(LastReloadResponse {
+        last_reload: (**rc.last_reload.load()).clone(),
+    }))
+}
+
 /// Get the resolved configuration for an agent.
 /// Reads live values from the agent's RuntimeConfig (hot-reloaded via ArcSwap).
 pub(super) async fn get_agent_config(
@@ -265,6 +287,7 @@ pub(super) async fn get_agent_config(
 /// This preserves formatting and comments while writing the new values.
 pub(super) async fn update_agent_config(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::Json(request): axum::Json<AgentConfigUpdateRequest>,
 ) -> Result<Json<AgentConfigResponse>, StatusCode> {
     let config_path = state.config_path.read().await.clone();
@@ -322,12 +345,23 @@ pub(super) async fn update_agent_config(
         })?;
 
     tracing::info!(agent_id = %request.agent_id, "config.toml updated via API");
-
-    match crate::config::Config::load_from_path(&config_path) {
+    state.audit(
+        &actor,
+        "config.update_agent",
+        Some(request.agent_id.as_str()),
+        serde_json::to_value(&request).unwrap_or_default(),
+    );
+
+    match crate::config::Config::load_from_path_async(&config_path).await {
         Ok(new_config) => {
-            let runtime_configs = state.runtime_configs.load();
-            if let Some(rc) = runtime_configs.get(&request.agent_id) {
-                rc.reload_config(&new_config, &request.agent_id);
+            let runtime_config = state.runtime_configs.load().get(&request.agent_id).cloned();
+            let _reloading = match &runtime_config {
+                Some(rc) => rc.reload_gate.close(&request.agent_id).await,
+                None => None,
+            };
+            if let Some(rc) = &runtime_config {
+                let changes = rc.reload_config(&new_config, &request.agent_id);
+                rc.record_reload("api", changes);
             }
             if request.discord.is_some() {
                 if let Some(discord_config) = &new_config.messaging.discord {
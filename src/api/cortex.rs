@@ -67,7 +67,7 @@ pub(super) async fn cortex_chat_messages(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<CortexChatMessagesQuery>,
 ) -> Result<Json<CortexChatMessagesResponse>, StatusCode> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
     let store = CortexChatStore::new(pool.clone());
 
@@ -155,7 +155,7 @@ pub(super) async fn cortex_events(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<CortexEventsQuery>,
 ) -> Result<Json<CortexEventsResponse>, StatusCode> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
     let logger = CortexLogger::new(pool.clone());
 
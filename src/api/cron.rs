@@ -28,6 +28,7 @@ fn default_cron_executions_limit() -> i64 {
 pub(super) struct CreateCronRequest {
     agent_id: String,
     id: String,
+    #[serde(default)]
     prompt: String,
     #[serde(default = "default_interval")]
     interval_secs: u64,
@@ -38,6 +39,13 @@ pub(super) struct CreateCronRequest {
     active_end_hour: Option<u8>,
     #[serde(default = "default_enabled")]
     enabled: bool,
+    /// Announcement posted as-is instead of running the prompt.
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    start_at: Option<String>,
+    #[serde(default)]
+    run_once: bool,
 }
 
 fn default_interval() -> u64 {
@@ -75,6 +83,9 @@ struct CronJobWithStats {
     delivery_target: String,
     enabled: bool,
     active_hours: Option<(u8, u8)>,
+    message: Option<String>,
+    start_at: Option<String>,
+    run_once: bool,
     success_count: u64,
     failure_count: u64,
     last_executed_at: Option<String>,
@@ -123,6 +134,9 @@ pub(super) async fn list_cron_jobs(
             delivery_target: config.delivery_target,
             enabled: config.enabled,
             active_hours: config.active_hours,
+            message: config.message,
+            start_at: config.start_at,
+            run_once: config.run_once,
             success_count: stats.success_count,
             failure_count: stats.failure_count,
             last_executed_at: stats.last_executed_at,
@@ -174,6 +188,18 @@ pub(super) async fn create_or_update_cron(
         .get(&request.agent_id)
         .ok_or(StatusCode::NOT_FOUND)?;
 
+    if request.prompt.is_empty() && request.message.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if request.run_once && request.start_at.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(start_at) = &request.start_at
+        && crate::cron::scheduler::parse_start_at(start_at).is_none()
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
     let active_hours = match (request.active_start_hour, request.active_end_hour) {
         (Some(start), Some(end)) => Some((start, end)),
         _ => None,
@@ -186,6 +212,9 @@ pub(super) async fn create_or_update_cron(
         delivery_target: request.delivery_target,
         active_hours,
         enabled: request.enabled,
+        message: request.message,
+        start_at: request.start_at,
+        run_once: request.run_once,
     };
 
     store.save(&config).await.map_err(|error| {
@@ -0,0 +1,144 @@
+use super::state::ApiState;
+
+use crate::conversation::{ExampleStore, FewShotExample};
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize)]
+pub(super) struct ExamplesQuery {
+    agent_id: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CreateExampleRequest {
+    agent_id: String,
+    user_message: String,
+    response: String,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct DeleteExampleQuery {
+    agent_id: String,
+    id: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct ToggleExampleRequest {
+    agent_id: String,
+    id: String,
+    enabled: bool,
+}
+
+#[derive(Serialize)]
+pub(super) struct ExamplesResponse {
+    examples: Vec<FewShotExample>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ExampleActionResponse {
+    success: bool,
+    message: String,
+}
+
+fn example_store(state: &ApiState, agent_id: &str) -> Result<ExampleStore, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let mut store = ExampleStore::new(pool.clone());
+    if let Some(readers) = state.agent_read_pools.load().get(agent_id) {
+        store = store.with_readers(readers.clone());
+    }
+    Ok(store)
+}
+
+/// List an agent's few-shot examples.
+pub(super) async fn list_examples(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<ExamplesQuery>,
+) -> Result<Json<ExamplesResponse>, StatusCode> {
+    let store = example_store(&state, &query.agent_id)?;
+    let examples = store.list().await.map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, "failed to list few-shot examples");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(ExamplesResponse { examples }))
+}
+
+/// Add a few-shot example. It's used from the next turn on.
+pub(super) async fn create_example(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CreateExampleRequest>,
+) -> Result<Json<FewShotExample>, StatusCode> {
+    if request.user_message.trim().is_empty() || request.response.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let store = example_store(&state, &request.agent_id)?;
+    let note = request
+        .note
+        .as_deref()
+        .filter(|note| !note.trim().is_empty());
+    let example = store
+        .add(&request.user_message, &request.response, note)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, agent_id = %request.agent_id, "failed to save few-shot example");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(example))
+}
+
+/// Delete a few-shot example.
+pub(super) async fn delete_example(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<DeleteExampleQuery>,
+) -> Result<Json<ExampleActionResponse>, StatusCode> {
+    let store = example_store(&state, &query.agent_id)?;
+    let deleted = store.delete(&query.id).await.map_err(|error| {
+        tracing::warn!(%error, agent_id = %query.agent_id, example_id = %query.id, "failed to delete few-shot example");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(ExampleActionResponse {
+        success: true,
+        message: format!("Example '{}' deleted", query.id),
+    }))
+}
+
+/// Enable or disable a few-shot example without deleting it.
+pub(super) async fn toggle_example(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<ToggleExampleRequest>,
+) -> Result<Json<ExampleActionResponse>, StatusCode> {
+    let store = example_store(&state, &request.agent_id)?;
+    let updated = store
+        .set_enabled(&request.id, request.enabled)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, agent_id = %request.agent_id, example_id = %request.id, "failed to update few-shot example");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !updated {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let status = if request.enabled {
+        "enabled"
+    } else {
+        "disabled"
+    };
+    Ok(Json(ExampleActionResponse {
+        success: true,
+        message: format!("Example '{}' {}", request.id, status),
+    }))
+}
@@ -1,5 +1,7 @@
 use super::state::ApiState;
 
+use crate::db::TimedQuery as _;
+
 use axum::Json;
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
@@ -52,7 +54,7 @@ pub(super) async fn list_ingest_files(
 ) -> Result<Json<IngestFilesResponse>, StatusCode> {
     use sqlx::Row as _;
 
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
     let rows = sqlx::query(
@@ -71,6 +73,7 @@ pub(super) async fn list_ingest_files(
         "#,
     )
     .fetch_all(pool)
+    .timed("ingestion_files.list")
     .await
     .map_err(|error| {
         tracing::warn!(%error, "failed to list ingest files");
@@ -177,6 +180,7 @@ pub(super) async fn upload_ingest_file(
                 .bind(safe_name)
                 .bind(file_size)
                 .execute(pool)
+                .timed_with("ingestion_files.queue", || format!("content_hash={hash}"))
                 .await;
             }
         }
@@ -205,6 +209,9 @@ pub(super) async fn delete_ingest_file(
     sqlx::query("DELETE FROM ingestion_files WHERE content_hash = ?")
         .bind(&query.content_hash)
         .execute(pool)
+        .timed_with("ingestion_files.delete", || {
+            format!("content_hash={}", query.content_hash)
+        })
         .await
         .map_err(|error| {
             tracing::warn!(%error, "failed to delete ingest file record");
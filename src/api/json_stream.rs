@@ -0,0 +1,120 @@
+//! JSON responses serialized while they're sent.
+//!
+//! `Json` serializes the whole response into one buffer before the first
+//! byte goes out, so a large timeline or memory graph is held twice: once as
+//! values and once as JSON. [`StreamedJson`] writes an object field by field,
+//! and its arrays an element at a time as the client reads them, so only one
+//! element's JSON is buffered at once.
+
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use std::iter;
+
+type Chunks = Box<dyn Iterator<Item = serde_json::Result<Bytes>> + Send>;
+
+/// A JSON object response whose array fields are serialized lazily.
+#[derive(Default)]
+pub(super) struct StreamedJson {
+    parts: Vec<Chunks>,
+    fields: usize,
+}
+
+impl StreamedJson {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field, serialized now. For small values.
+    pub(super) fn field(mut self, name: &str, value: &impl Serialize) -> Self {
+        let mut chunk = self.key(name);
+        let chunk = serde_json::to_writer(&mut chunk, value).map(|()| Bytes::from(chunk));
+        self.parts.push(Box::new(iter::once(chunk)));
+        self
+    }
+
+    /// Add an array field, serialized an element at a time as the response
+    /// is sent.
+    pub(super) fn array<T>(mut self, name: &str, items: Vec<T>) -> Self
+    where
+        T: Serialize + Send + 'static,
+    {
+        let mut open = self.key(name);
+        open.push(b'[');
+        let elements = items.into_iter().enumerate().map(|(index, item)| {
+            let mut chunk = if index == 0 { Vec::new() } else { vec![b','] };
+            serde_json::to_writer(&mut chunk, &item)?;
+            Ok(Bytes::from(chunk))
+        });
+        self.parts.push(Box::new(
+            iter::once(Ok(Bytes::from(open)))
+                .chain(elements)
+                .chain(iter::once(Ok(Bytes::from_static(b"]")))),
+        ));
+        self
+    }
+
+    /// The object's opening brace or the separator before this field, and
+    /// the field's name.
+    fn key(&mut self, name: &str) -> Vec<u8> {
+        let mut key = vec![if self.fields == 0 { b'{' } else { b',' }];
+        self.fields += 1;
+        serde_json::to_writer(&mut key, name).expect("strings always serialize");
+        key.push(b':');
+        key
+    }
+}
+
+impl IntoResponse for StreamedJson {
+    fn into_response(self) -> Response {
+        let close: &'static [u8] = if self.fields == 0 { b"{}" } else { b"}" };
+        let chunks = self
+            .parts
+            .into_iter()
+            .flatten()
+            .chain(iter::once(Ok(Bytes::from_static(close))))
+            .inspect(|chunk| {
+                // Headers are already sent, so the client sees a cut-off body.
+                if let Err(error) = chunk {
+                    tracing::warn!(%error, "failed to serialize streamed response");
+                }
+            });
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            Body::from_stream(futures::stream::iter(chunks)),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: StreamedJson) -> serde_json::Value {
+        let body = response.into_response().into_body();
+        let bytes = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .expect("body is readable");
+        serde_json::from_slice(&bytes).expect("body is valid JSON")
+    }
+
+    #[tokio::test]
+    async fn test_streamed_object_matches_buffered_json() {
+        let response = StreamedJson::new()
+            .array(
+                "items",
+                vec![serde_json::json!({"id": 1}), serde_json::json!("two")],
+            )
+            .array::<u8>("empty", Vec::new())
+            .field("has_more", &true);
+        assert_eq!(
+            body_json(response).await,
+            serde_json::json!({"items": [{"id": 1}, "two"], "empty": [], "has_more": true})
+        );
+
+        assert_eq!(body_json(StreamedJson::new()).await, serde_json::json!({}));
+    }
+}
@@ -1,3 +1,4 @@
+use super::json_stream::StreamedJson;
 use super::state::ApiState;
 
 use crate::memory::search::{SearchConfig, SearchMode};
@@ -20,13 +21,6 @@ pub(super) struct MemoriesSearchResponse {
     results: Vec<MemorySearchResult>,
 }
 
-#[derive(Serialize)]
-pub(super) struct MemoryGraphResponse {
-    nodes: Vec<Memory>,
-    edges: Vec<Association>,
-    total: usize,
-}
-
 #[derive(Serialize)]
 pub(super) struct MemoryGraphNeighborsResponse {
     nodes: Vec<Memory>,
@@ -179,7 +173,7 @@ pub(super) async fn search_memories(
 pub(super) async fn memory_graph(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<MemoryGraphQuery>,
-) -> Result<Json<MemoryGraphResponse>, StatusCode> {
+) -> Result<StreamedJson, StatusCode> {
     let searches = state.memory_searches.load();
     let memory_search = searches.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
     let store = memory_search.store();
@@ -209,11 +203,10 @@ pub(super) async fn memory_graph(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    Ok(Json(MemoryGraphResponse {
-        nodes,
-        edges,
-        total,
-    }))
+    Ok(StreamedJson::new()
+        .array("nodes", nodes)
+        .array("edges", edges)
+        .field("total", &total))
 }
 
 /// Get the neighbors of a specific memory node. Returns new nodes
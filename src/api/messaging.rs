@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::ApiState;
 
 use axum::Json;
@@ -183,6 +184,7 @@ pub(super) async fn messaging_status(
 /// bindings for that platform, and shut down the adapter.
 pub(super) async fn disconnect_platform(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<DisconnectPlatformRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let platform = &request.platform;
@@ -229,7 +231,7 @@ pub(super) async fn disconnect_platform(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
+    if let Ok(new_config) = crate::config::Config::load_from_path_async(&config_path).await {
         let bindings_guard = state.bindings.read().await;
         if let Some(bindings_swap) = bindings_guard.as_ref() {
             bindings_swap.store(std::sync::Arc::new(new_config.bindings.clone()));
@@ -244,6 +246,12 @@ pub(super) async fn disconnect_platform(
     }
 
     tracing::info!(platform = %platform, "platform disconnected via API");
+    state.audit(
+        &actor,
+        "platform.disconnect",
+        None,
+        serde_json::json!({ "platform": platform }),
+    );
 
     Ok(Json(serde_json::json!({
         "success": true,
@@ -255,6 +263,7 @@ pub(super) async fn disconnect_platform(
 /// adapter. When enabling, reads credentials from config and hot-starts it.
 pub(super) async fn toggle_platform(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<TogglePlatformRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let platform = &request.platform;
@@ -297,7 +306,7 @@ pub(super) async fn toggle_platform(
     let manager = manager_guard.as_ref();
 
     if request.enabled {
-        if let Ok(new_config) = crate::config::Config::load_from_path(&config_path) {
+        if let Ok(new_config) = crate::config::Config::load_from_path_async(&config_path).await {
             if let Some(manager) = manager {
                 match platform.as_str() {
                     "discord" => {
@@ -385,10 +394,8 @@ pub(super) async fn toggle_platform(
                     }
                     "webhook" => {
                         if let Some(webhook_config) = &new_config.messaging.webhook {
-                            let adapter = crate::messaging::webhook::WebhookAdapter::new(
-                                webhook_config.port,
-                                &webhook_config.bind,
-                            );
+                            let adapter =
+                                crate::messaging::webhook::WebhookAdapter::new(webhook_config);
                             if let Err(error) = manager.register_and_start(adapter).await {
                                 tracing::error!(%error, "failed to start webhook adapter on toggle");
                             }
@@ -432,6 +439,12 @@ pub(super) async fn toggle_platform(
         "disabled"
     };
     tracing::info!(platform = %platform, action, "platform toggled via API");
+    state.audit(
+        &actor,
+        "platform.toggle",
+        None,
+        serde_json::json!({ "platform": platform, "enabled": request.enabled }),
+    );
 
     Ok(Json(serde_json::json!({
         "success": true,
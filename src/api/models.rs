@@ -402,6 +402,11 @@ pub(super) async fn get_models(
     Ok(Json(ModelsResponse { models }))
 }
 
+/// Latency percentiles of recent successful calls, per provider and model.
+pub(super) async fn model_latency() -> Json<Vec<crate::llm::latency::ModelLatency>> {
+    Json(crate::llm::latency::LatencyTracker::global().summary())
+}
+
 pub(super) async fn refresh_models(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<ModelsResponse>, StatusCode> {
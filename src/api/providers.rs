@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::ApiState;
 
 use axum::Json;
@@ -211,6 +212,11 @@ fn build_test_llm_config(provider: &str, credential: &str) -> crate::config::Llm
         moonshot_key: (provider == "moonshot").then(|| credential.to_string()),
         zai_coding_plan_key: (provider == "zai-coding-plan").then(|| credential.to_string()),
         providers,
+        cache: crate::config::LlmCacheConfig::default(),
+        concurrency: crate::config::LlmConcurrencyConfig::default(),
+        throttle: crate::config::LlmThrottleConfig::default(),
+        call_log: crate::config::LlmCallLogConfig::default(),
+        context_windows: HashMap::new(),
     }
 }
 
@@ -338,6 +344,7 @@ pub(super) async fn get_providers(
 
 pub(super) async fn update_provider(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<ProviderUpdateRequest>,
 ) -> Result<Json<ProviderUpdateResponse>, StatusCode> {
     let Some(key_name) = provider_toml_key(&request.provider) else {
@@ -441,6 +448,12 @@ pub(super) async fn update_provider(
         .provider_setup_tx
         .try_send(crate::ProviderSetupEvent::ProvidersConfigured)
         .ok();
+    state.audit(
+        &actor,
+        "provider.update",
+        None,
+        serde_json::json!({ "provider": request.provider, "model": request.model }),
+    );
 
     Ok(Json(ProviderUpdateResponse {
         success: true,
@@ -536,6 +549,7 @@ pub(super) async fn test_provider_model(
 
 pub(super) async fn delete_provider(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::extract::Path(provider): axum::extract::Path<String>,
 ) -> Result<Json<ProviderUpdateResponse>, StatusCode> {
     let Some(key_name) = provider_toml_key(&provider) else {
@@ -570,6 +584,12 @@ pub(super) async fn delete_provider(
     tokio::fs::write(&config_path, doc.to_string())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.audit(
+        &actor,
+        "provider.delete",
+        None,
+        serde_json::json!({ "provider": provider }),
+    );
 
     Ok(Json(ProviderUpdateResponse {
         success: true,
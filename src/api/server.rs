@@ -2,8 +2,8 @@
 
 use super::state::ApiState;
 use super::{
-    agents, bindings, channels, config, cortex, cron, ingest, memories, messaging, models,
-    providers, settings, skills, system, webchat,
+    agents, audit, bindings, channels, config, cortex, cron, examples, ingest, memories, messaging,
+    models, providers, settings, skills, system, webchat,
 };
 
 use axum::Router;
@@ -49,6 +49,15 @@ pub async fn start_http_server(
                 .delete(agents::delete_agent),
         )
         .route("/agents/overview", get(agents::agent_overview))
+        .route("/agents/health", get(agents::agents_health))
+        .route("/agents/capabilities", get(agents::agents_capabilities))
+        .route("/agents/presets", get(agents::agent_presets))
+        .route("/agents/experiment", get(agents::agent_experiment))
+        .route("/agents/sentiment", get(agents::agent_sentiment))
+        .route("/agents/{agent_id}/stats", get(agents::agent_stats))
+        .route("/agents/{agent_id}/analytics", get(agents::agent_analytics))
+        .route("/agents/{agent_id}/usage", get(agents::agent_usage))
+        .route("/agents/structured", post(agents::structured_prompt))
         .route("/channels", get(channels::list_channels))
         .route("/channels/messages", get(channels::channel_messages))
         .route("/channels/status", get(channels::channel_status))
@@ -71,6 +80,7 @@ pub async fn start_http_server(
             "/agents/config",
             get(config::get_agent_config).put(config::update_agent_config),
         )
+        .route("/agents/config/reload", get(config::get_last_reload))
         .route(
             "/agents/cron",
             get(cron::list_cron_jobs)
@@ -80,7 +90,15 @@ pub async fn start_http_server(
         .route("/agents/cron/executions", get(cron::cron_executions))
         .route("/agents/cron/trigger", post(cron::trigger_cron))
         .route("/agents/cron/toggle", put(cron::toggle_cron))
+        .route(
+            "/agents/examples",
+            get(examples::list_examples)
+                .post(examples::create_example)
+                .delete(examples::delete_example),
+        )
+        .route("/agents/examples/toggle", put(examples::toggle_example))
         .route("/channels/cancel", post(channels::cancel_process))
+        .route("/agents/{agent_id}/cancel", post(channels::cancel_turn))
         .route(
             "/agents/ingest/files",
             get(ingest::list_ingest_files).delete(ingest::delete_ingest_file),
@@ -99,6 +117,7 @@ pub async fn start_http_server(
         .route("/providers/{provider}", delete(providers::delete_provider))
         .route("/models", get(models::get_models))
         .route("/models/refresh", post(models::refresh_models))
+        .route("/models/latency", get(models::model_latency))
         .route("/messaging/status", get(messaging::messaging_status))
         .route(
             "/messaging/disconnect",
@@ -125,6 +144,12 @@ pub async fn start_http_server(
             get(settings::update_check).post(settings::update_check_now),
         )
         .route("/update/apply", post(settings::update_apply))
+        .route("/audit", get(audit::list_audit))
+        .route(
+            "/admin/log-level",
+            get(system::log_level).put(system::update_log_level),
+        )
+        .route("/admin/last-recovery", get(system::last_recovery))
         .route("/webchat/send", post(webchat::webchat_send))
         .route("/webchat/history", get(webchat::webchat_history));
 
@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::ApiState;
 
 use axum::Json;
@@ -33,7 +34,7 @@ pub(super) struct OpenCodePermissionsResponse {
     webfetch: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub(super) struct GlobalSettingsUpdate {
     brave_search_key: Option<String>,
     api_enabled: Option<bool>,
@@ -43,7 +44,7 @@ pub(super) struct GlobalSettingsUpdate {
     opencode: Option<OpenCodeSettingsUpdate>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub(super) struct OpenCodeSettingsUpdate {
     enabled: Option<bool>,
     path: Option<String>,
@@ -53,7 +54,7 @@ pub(super) struct OpenCodeSettingsUpdate {
     permissions: Option<OpenCodePermissionsUpdate>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub(super) struct OpenCodePermissionsUpdate {
     edit: Option<String>,
     bash: Option<String>,
@@ -225,8 +226,10 @@ pub(super) async fn get_global_settings(
 
 pub(super) async fn update_global_settings(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<GlobalSettingsUpdate>,
 ) -> Result<Json<GlobalSettingsUpdateResponse>, StatusCode> {
+    let payload = serde_json::to_value(&request).unwrap_or_default();
     let config_path = state.config_path.read().await.clone();
 
     let content = if config_path.exists() {
@@ -333,6 +336,7 @@ pub(super) async fn update_global_settings(
     tokio::fs::write(&config_path, doc.to_string())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.audit(&actor, "settings.update", None, payload);
 
     let message = if requires_restart {
         "Settings updated. API server changes require a restart to take effect.".to_string()
@@ -367,9 +371,19 @@ pub(super) async fn update_check_now(
 /// Pull the new Docker image and recreate this container.
 pub(super) async fn update_apply(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     match crate::update::apply_docker_update(&state.update_status).await {
-        Ok(()) => Ok(Json(serde_json::json!({ "status": "updating" }))),
+        Ok(()) => {
+            let latest = state.update_status.load().latest_version.clone();
+            state.audit(
+                &actor,
+                "update.apply",
+                None,
+                serde_json::json!({ "version": latest }),
+            );
+            Ok(Json(serde_json::json!({ "status": "updating" })))
+        }
         Err(error) => {
             tracing::error!(%error, "update apply failed");
             Ok(Json(serde_json::json!({
@@ -405,6 +419,7 @@ pub(super) async fn get_raw_config(
 
 pub(super) async fn update_raw_config(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     Json(request): Json<RawConfigUpdateRequest>,
 ) -> Result<Json<RawConfigUpdateResponse>, StatusCode> {
     let config_path = state.config_path.read().await.clone();
@@ -428,12 +443,23 @@ pub(super) async fn update_raw_config(
         })?;
 
     tracing::info!("config.toml updated via raw editor");
-
-    match crate::config::Config::load_from_path(&config_path) {
+    state.audit(
+        &actor,
+        "config.update_raw",
+        None,
+        toml::from_str(&request.content).unwrap_or_default(),
+    );
+
+    match crate::config::Config::load_from_path_async(&config_path).await {
         Ok(new_config) => {
-            let runtime_configs = state.runtime_configs.load();
+            let runtime_configs = state.runtime_configs.load_full();
+            let gates = runtime_configs
+                .iter()
+                .map(|(agent_id, rc)| (agent_id.as_str(), &rc.reload_gate));
+            let _reloading = crate::agent::reload_gate::close_all(gates).await;
             for (agent_id, rc) in runtime_configs.iter() {
-                rc.reload_config(&new_config, agent_id);
+                let changes = rc.reload_config(&new_config, agent_id);
+                rc.record_reload("api", changes);
             }
         }
         Err(error) => {
@@ -1,3 +1,4 @@
+use super::audit::Actor;
 use super::state::{ApiEvent, ApiState};
 
 use axum::Json;
@@ -137,6 +138,7 @@ pub(super) async fn list_skills(
 /// Install a skill from GitHub.
 pub(super) async fn install_skill(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::extract::Json(req): axum::extract::Json<InstallSkillRequest>,
 ) -> Result<Json<InstallSkillResponse>, StatusCode> {
     let configs = state.agent_configs.load();
@@ -159,6 +161,16 @@ pub(super) async fn install_skill(
         })?;
 
     state.send_event(ApiEvent::ConfigReloaded);
+    state.audit(
+        &actor,
+        "skill.install",
+        Some(req.agent_id.as_str()),
+        serde_json::json!({
+            "spec": req.spec,
+            "instance": req.instance,
+            "installed": installed,
+        }),
+    );
 
     Ok(Json(InstallSkillResponse { installed }))
 }
@@ -166,6 +178,7 @@ pub(super) async fn install_skill(
 /// Remove an installed skill.
 pub(super) async fn remove_skill(
     State(state): State<Arc<ApiState>>,
+    actor: Actor,
     axum::extract::Json(req): axum::extract::Json<RemoveSkillRequest>,
 ) -> Result<Json<RemoveSkillResponse>, StatusCode> {
     let configs = state.agent_configs.load();
@@ -193,6 +206,14 @@ pub(super) async fn remove_skill(
         skill = %req.name,
         "skill removed"
     );
+    if removed_path.is_some() {
+        state.audit(
+            &actor,
+            "skill.remove",
+            Some(req.agent_id.as_str()),
+            serde_json::json!({ "name": req.name }),
+        );
+    }
 
     Ok(Json(RemoveSkillResponse {
         success: removed_path.is_some(),
@@ -3,13 +3,16 @@
 use crate::agent::channel::ChannelState;
 use crate::agent::cortex_chat::CortexChatSession;
 use crate::agent::status::StatusBlock;
+use crate::audit::AuditLog;
 use crate::config::{Binding, DefaultsConfig, DiscordPermissions, RuntimeConfig, SlackPermissions};
 use crate::cron::{CronStore, Scheduler};
+use crate::events::{self, BusEvent};
 use crate::llm::LlmManager;
 use crate::memory::{EmbeddingModel, MemorySearch};
 use crate::messaging::MessagingManager;
 use crate::messaging::webchat::WebChatAdapter;
 use crate::prompts::PromptEngine;
+use crate::recovery::RecoveryReport;
 use crate::update::SharedUpdateStatus;
 use crate::{ProcessEvent, ProcessId};
 
@@ -40,15 +43,19 @@ pub struct ApiState {
     pub event_tx: broadcast::Sender<ApiEvent>,
     /// Per-agent SQLite pools for querying channel/conversation data.
     pub agent_pools: arc_swap::ArcSwap<HashMap<String, sqlx::SqlitePool>>,
+    /// Per-agent read-only SQLite pools, for endpoints that only read.
+    pub agent_read_pools: arc_swap::ArcSwap<HashMap<String, sqlx::SqlitePool>>,
     /// Per-agent config summaries for the agents list endpoint.
     pub agent_configs: arc_swap::ArcSwap<Vec<AgentInfo>>,
     /// Per-agent memory search instances for the memories API.
     pub memory_searches: arc_swap::ArcSwap<HashMap<String, Arc<MemorySearch>>>,
-    /// Live status blocks for active channels, keyed by channel_id.
-    pub channel_status_blocks: RwLock<HashMap<String, Arc<tokio::sync::RwLock<StatusBlock>>>>,
-    /// Live channel states for active channels, keyed by channel_id.
+    /// Live status blocks for active channels, keyed by (agent_id, channel_id).
+    pub channel_status_blocks:
+        RwLock<HashMap<(String, String), Arc<tokio::sync::RwLock<StatusBlock>>>>,
+    /// Live channel states for active channels, keyed by (agent_id, channel_id),
+    /// since several agents can share a channel.
     /// Used by the cancel API to abort workers and branches.
-    pub channel_states: RwLock<HashMap<String, ChannelState>>,
+    pub channel_states: RwLock<HashMap<(String, String), ChannelState>>,
     /// Per-agent cortex chat sessions.
     pub cortex_chat_sessions: arc_swap::ArcSwap<HashMap<String, Arc<CortexChatSession>>>,
     /// Per-agent workspace paths for identity file access.
@@ -89,6 +96,11 @@ pub struct ApiState {
     pub agent_remove_tx: mpsc::Sender<String>,
     /// Shared webchat adapter for session management from API handlers.
     pub webchat_adapter: ArcSwap<Option<Arc<WebChatAdapter>>>,
+    /// Where admin actions are recorded. None if the admin database couldn't
+    /// be opened.
+    pub audit_log: ArcSwap<Option<Arc<AuditLog>>>,
+    /// What startup found the previous run left unfinished.
+    pub last_recovery: ArcSwap<Option<RecoveryReport>>,
 }
 
 /// Events sent to SSE clients. Wraps ProcessEvents with agent context.
@@ -102,12 +114,16 @@ pub enum ApiEvent {
         sender_name: Option<String>,
         sender_id: String,
         text: String,
+        /// Turn the message starts; see [`crate::TURN_ID_KEY`].
+        turn_id: Option<String>,
     },
     /// An outbound message sent by the bot.
     OutboundMessage {
         agent_id: String,
         channel_id: String,
         text: String,
+        /// Turn of the message being replied to.
+        turn_id: Option<String>,
     },
     /// Typing indicator state change.
     TypingState {
@@ -157,6 +173,7 @@ pub enum ApiEvent {
         process_type: String,
         process_id: String,
         tool_name: String,
+        turn_id: Option<String>,
     },
     /// A tool call completed on a process.
     ToolCompleted {
@@ -165,7 +182,36 @@ pub enum ApiEvent {
         process_type: String,
         process_id: String,
         tool_name: String,
+        turn_id: Option<String>,
     },
+    /// One step of a multi-step tool loop: a model response, tool call, or
+    /// tool result.
+    ToolLoopStep {
+        agent_id: String,
+        channel_id: Option<String>,
+        process_type: String,
+        process_id: String,
+        step: crate::agent::tool_loop::ToolLoopStep,
+        turn_id: Option<String>,
+    },
+    /// An inbound message was flagged as a possible prompt injection.
+    InjectionFlagged {
+        agent_id: String,
+        channel_id: String,
+        sender_id: String,
+        reasons: Vec<String>,
+    },
+    /// A channel's history was compacted or truncated.
+    CompactionCompleted {
+        agent_id: String,
+        channel_id: String,
+        action: String,
+        messages_compacted: usize,
+    },
+    /// A messaging adapter connected or disconnected.
+    LinkChanged { adapter: String, linked: bool },
+    /// One of an agent's LLM calls failed.
+    AgentError { agent_id: String, error: String },
     /// Configuration was reloaded (skills, identity, etc.).
     ConfigReloaded,
 }
@@ -181,6 +227,7 @@ impl ApiState {
             started_at: Instant::now(),
             event_tx,
             agent_pools: arc_swap::ArcSwap::from_pointee(HashMap::new()),
+            agent_read_pools: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             agent_configs: arc_swap::ArcSwap::from_pointee(Vec::new()),
             memory_searches: arc_swap::ArcSwap::from_pointee(HashMap::new()),
             channel_status_blocks: RwLock::new(HashMap::new()),
@@ -205,34 +252,51 @@ impl ApiState {
             agent_tx,
             agent_remove_tx,
             webchat_adapter: ArcSwap::from_pointee(None),
+            audit_log: ArcSwap::from_pointee(None),
+            last_recovery: ArcSwap::from_pointee(None),
         }
     }
 
     /// Register a channel's status block so the API can read snapshots.
     pub async fn register_channel_status(
         &self,
+        agent_id: String,
         channel_id: String,
         status_block: Arc<tokio::sync::RwLock<StatusBlock>>,
     ) {
         self.channel_status_blocks
             .write()
             .await
-            .insert(channel_id, status_block);
+            .insert((agent_id, channel_id), status_block);
     }
 
     /// Remove a channel's status block when it's dropped.
-    pub async fn unregister_channel_status(&self, channel_id: &str) {
-        self.channel_status_blocks.write().await.remove(channel_id);
+    pub async fn unregister_channel_status(&self, agent_id: &str, channel_id: &str) {
+        self.channel_status_blocks
+            .write()
+            .await
+            .remove(&(agent_id.to_string(), channel_id.to_string()));
     }
 
     /// Register a channel's state for API-driven cancellation.
-    pub async fn register_channel_state(&self, channel_id: String, state: ChannelState) {
-        self.channel_states.write().await.insert(channel_id, state);
+    pub async fn register_channel_state(
+        &self,
+        agent_id: String,
+        channel_id: String,
+        state: ChannelState,
+    ) {
+        self.channel_states
+            .write()
+            .await
+            .insert((agent_id, channel_id), state);
     }
 
     /// Remove a channel's state when it's dropped.
-    pub async fn unregister_channel_state(&self, channel_id: &str) {
-        self.channel_states.write().await.remove(channel_id);
+    pub async fn unregister_channel_state(&self, agent_id: &str, channel_id: &str) {
+        self.channel_states
+            .write()
+            .await
+            .remove(&(agent_id.to_string(), channel_id.to_string()));
     }
 
     /// Register an agent's event stream. Spawns a task that forwards
@@ -328,6 +392,7 @@ impl ApiState {
                                 process_id,
                                 channel_id,
                                 tool_name,
+                                turn_id,
                                 ..
                             } => {
                                 let (process_type, id_str) = process_id_info(process_id);
@@ -338,6 +403,7 @@ impl ApiState {
                                         process_type,
                                         process_id: id_str,
                                         tool_name: tool_name.clone(),
+                                        turn_id: turn_id.clone(),
                                     })
                                     .ok();
                             }
@@ -345,6 +411,7 @@ impl ApiState {
                                 process_id,
                                 channel_id,
                                 tool_name,
+                                turn_id,
                                 ..
                             } => {
                                 let (process_type, id_str) = process_id_info(process_id);
@@ -355,6 +422,41 @@ impl ApiState {
                                         process_type,
                                         process_id: id_str,
                                         tool_name: tool_name.clone(),
+                                        turn_id: turn_id.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::ToolLoopStep {
+                                process_id,
+                                channel_id,
+                                step,
+                                turn_id,
+                                ..
+                            } => {
+                                let (process_type, id_str) = process_id_info(process_id);
+                                api_tx
+                                    .send(ApiEvent::ToolLoopStep {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.as_deref().map(|s| s.to_string()),
+                                        process_type,
+                                        process_id: id_str,
+                                        step: step.clone(),
+                                        turn_id: turn_id.clone(),
+                                    })
+                                    .ok();
+                            }
+                            ProcessEvent::InjectionFlagged {
+                                channel_id,
+                                sender_id,
+                                reasons,
+                                ..
+                            } => {
+                                api_tx
+                                    .send(ApiEvent::InjectionFlagged {
+                                        agent_id: agent_id.clone(),
+                                        channel_id: channel_id.to_string(),
+                                        sender_id: sender_id.clone(),
+                                        reasons: reasons.clone(),
                                     })
                                     .ok();
                             }
@@ -370,11 +472,53 @@ impl ApiState {
         });
     }
 
+    /// Spawn a task that forwards the event bus events SSE clients care about
+    /// into the aggregated API event stream.
+    pub fn forward_bus_events(&self) {
+        let api_tx = self.event_tx.clone();
+        let mut bus_rx = events::subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match bus_rx.recv().await {
+                    Ok(BusEvent::CompactionCompleted {
+                        agent_id,
+                        channel_id,
+                        action,
+                        messages_compacted,
+                    }) => ApiEvent::CompactionCompleted {
+                        agent_id: agent_id.to_string(),
+                        channel_id: channel_id.to_string(),
+                        action: action.to_string(),
+                        messages_compacted,
+                    },
+                    Ok(BusEvent::LinkChanged { adapter, linked }) => {
+                        ApiEvent::LinkChanged { adapter, linked }
+                    }
+                    Ok(BusEvent::AgentError { agent_id, error }) => {
+                        ApiEvent::AgentError { agent_id, error }
+                    }
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        tracing::debug!(count, "API bus forwarder lagged, skipped events");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                api_tx.send(event).ok();
+            }
+        });
+    }
+
     /// Set the SQLite pools for all agents.
     pub fn set_agent_pools(&self, pools: HashMap<String, sqlx::SqlitePool>) {
         self.agent_pools.store(Arc::new(pools));
     }
 
+    /// Set the read-only SQLite pools for all agents.
+    pub fn set_agent_read_pools(&self, pools: HashMap<String, sqlx::SqlitePool>) {
+        self.agent_read_pools.store(Arc::new(pools));
+    }
+
     /// Set the agent config summaries for the agents list endpoint.
     pub fn set_agent_configs(&self, configs: Vec<AgentInfo>) {
         self.agent_configs.store(Arc::new(configs));
@@ -466,6 +610,29 @@ impl ApiState {
         self.webchat_adapter.store(Arc::new(Some(adapter)));
     }
 
+    /// Set the admin audit log.
+    pub fn set_audit_log(&self, audit_log: Arc<AuditLog>) {
+        self.audit_log.store(Arc::new(Some(audit_log)));
+    }
+
+    /// Set the recovery report produced on startup.
+    pub fn set_last_recovery(&self, report: RecoveryReport) {
+        self.last_recovery.store(Arc::new(Some(report)));
+    }
+
+    /// Record an admin action taken through the API.
+    pub fn audit(
+        &self,
+        actor: &str,
+        action: &str,
+        agent_id: Option<&str>,
+        payload: serde_json::Value,
+    ) {
+        if let Some(audit_log) = self.audit_log.load().as_ref() {
+            audit_log.record(actor, action, agent_id, payload);
+        }
+    }
+
     /// Send an event to all SSE subscribers.
     pub fn send_event(&self, event: ApiEvent) {
         let _ = self.event_tx.send(event);
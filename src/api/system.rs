@@ -1,10 +1,12 @@
+use super::audit::Actor;
 use super::state::{ApiEvent, ApiState};
 
 use axum::Json;
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::response::Sse;
 use futures::stream::Stream;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::sync::Arc;
 
@@ -28,6 +30,25 @@ pub(super) struct StatusResponse {
     uptime_seconds: u64,
 }
 
+#[derive(Serialize)]
+pub(super) struct LogLevelResponse {
+    /// Directives of the running log filter.
+    filter: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct LogLevelUpdate {
+    /// New filter, in `RUST_LOG` syntax.
+    filter: String,
+}
+
+#[derive(Serialize)]
+pub(super) struct LogLevelUpdateResponse {
+    success: bool,
+    message: String,
+    filter: Option<String>,
+}
+
 pub(super) async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
@@ -62,6 +83,53 @@ pub(super) async fn status(State(state): State<Arc<ApiState>>) -> Json<StatusRes
     })
 }
 
+/// The running log filter.
+pub(super) async fn log_level() -> Result<Json<LogLevelResponse>, StatusCode> {
+    let filter = crate::daemon::log_filter().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(LogLevelResponse { filter }))
+}
+
+/// Replace the log filter without restarting, like turning on debug logs for
+/// one module.
+pub(super) async fn update_log_level(
+    State(state): State<Arc<ApiState>>,
+    actor: Actor,
+    Json(request): Json<LogLevelUpdate>,
+) -> Json<LogLevelUpdateResponse> {
+    let previous = crate::daemon::log_filter();
+    if let Err(error) = crate::daemon::set_log_filter(request.filter.trim()) {
+        return Json(LogLevelUpdateResponse {
+            success: false,
+            message: format!("{error:#}"),
+            filter: previous,
+        });
+    }
+
+    let filter = crate::daemon::log_filter();
+    tracing::info!(filter = ?filter, previous = ?previous, "log filter updated via API");
+    state.audit(
+        &actor,
+        "log_level.update",
+        None,
+        serde_json::json!({ "filter": filter, "previous": previous }),
+    );
+
+    Json(LogLevelUpdateResponse {
+        success: true,
+        message: "Log filter updated. It applies until the next restart.".to_string(),
+        filter,
+    })
+}
+
+/// What startup found the previous run left unfinished.
+pub(super) async fn last_recovery(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<crate::recovery::RecoveryReport>, StatusCode> {
+    let report = state.last_recovery.load();
+    let report = report.as_ref().clone().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(report))
+}
+
 /// SSE endpoint streaming all agent events to connected clients.
 pub(super) async fn events_sse(
     State(state): State<Arc<ApiState>>,
@@ -84,6 +152,11 @@ pub(super) async fn events_sse(
                             ApiEvent::BranchCompleted { .. } => "branch_completed",
                             ApiEvent::ToolStarted { .. } => "tool_started",
                             ApiEvent::ToolCompleted { .. } => "tool_completed",
+                            ApiEvent::ToolLoopStep { .. } => "tool_loop_step",
+                            ApiEvent::InjectionFlagged { .. } => "injection_flagged",
+                            ApiEvent::CompactionCompleted { .. } => "compaction_completed",
+                            ApiEvent::LinkChanged { .. } => "link_changed",
+                            ApiEvent::AgentError { .. } => "agent_error",
                             ApiEvent::ConfigReloaded => "config_reloaded",
                         };
                         yield Ok(axum::response::sse::Event::default()
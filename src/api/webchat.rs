@@ -20,6 +20,10 @@ pub(super) struct WebChatSendRequest {
     #[serde(default = "default_sender_name")]
     sender_name: String,
     message: String,
+    /// Stream the reply's tokens as `stream_start` / `stream_chunk` events
+    /// before the final `text` event.
+    #[serde(default)]
+    stream: bool,
 }
 
 fn default_sender_name() -> String {
@@ -55,6 +59,12 @@ pub(super) async fn webchat_send(
         "display_name".into(),
         serde_json::Value::String(request.sender_name.clone()),
     );
+    if request.stream {
+        metadata.insert(
+            crate::agent::token_stream::STREAM_TOKENS_KEY.into(),
+            serde_json::Value::Bool(true),
+        );
+    }
 
     let inbound = InboundMessage {
         id: uuid::Uuid::new_v4().to_string(),
@@ -84,6 +94,7 @@ pub(super) async fn webchat_send(
                 WebChatEvent::StreamStart => "stream_start",
                 WebChatEvent::StreamChunk(_) => "stream_chunk",
                 WebChatEvent::StreamEnd => "stream_end",
+                WebChatEvent::StreamAbort => "stream_abort",
                 WebChatEvent::ToolStarted { .. } => "tool_started",
                 WebChatEvent::ToolCompleted { .. } => "tool_completed",
                 WebChatEvent::StopTyping => "stop_typing",
@@ -132,9 +143,12 @@ pub(super) async fn webchat_history(
     State(state): State<Arc<ApiState>>,
     Query(query): Query<WebChatHistoryQuery>,
 ) -> Result<Json<Vec<WebChatHistoryMessage>>, StatusCode> {
-    let pools = state.agent_pools.load();
+    let pools = state.agent_read_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
-    let logger = ConversationLogger::new(pool.clone());
+    let mut logger = ConversationLogger::new(pool.clone());
+    if let Some(runtime_config) = state.runtime_configs.load().get(&query.agent_id) {
+        logger = logger.with_cache(runtime_config.recent_messages.clone());
+    }
 
     let channel_id: crate::ChannelId = Arc::from(query.session_id.as_str());
 
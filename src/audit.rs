@@ -0,0 +1,210 @@
+//! Admin audit trail: administrative actions taken through the API.
+//!
+//! Actions that change how the instance runs, like creating or deleting an
+//! agent, editing a binding, or rewriting the config, are written to the
+//! `admin_audit` table with who took them and what they asked for. Most of
+//! them aren't scoped to one agent, so the table lives in the instance's own
+//! `admin.db` rather than an agent's database. Credentials in a payload are
+//! redacted before it's stored.
+
+use crate::config::redact_secrets;
+use crate::db::TimedQuery as _;
+use crate::error::Result;
+use crate::otel::db_write;
+
+use anyhow::Context as _;
+use serde::Serialize;
+use sqlx::{Row as _, SqlitePool};
+
+use std::path::Path;
+
+/// Most entries returned by one [`AuditLog::list`].
+pub const MAX_LIST_LIMIT: i64 = 500;
+
+/// One recorded action.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    /// Who took the action, from the `X-Spacebot-Actor` header.
+    pub actor: String,
+    /// What was done, like `agent.create` or `binding.delete`.
+    pub action: String,
+    /// The agent acted on, if the action is scoped to one.
+    pub agent_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Which entries [`AuditLog::list`] returns. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub agent_id: Option<String>,
+    pub limit: i64,
+}
+
+/// The instance's audit table.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    pool: SqlitePool,
+}
+
+impl AuditLog {
+    /// Open `admin.db` in the instance directory and bring its schema up to
+    /// date.
+    pub async fn connect(instance_dir: &Path) -> Result<Self> {
+        let url = format!(
+            "sqlite:{}?mode=rwc",
+            instance_dir.join("admin.db").display()
+        );
+        let pool = SqlitePool::connect(&url)
+            .await
+            .with_context(|| "failed to connect to the admin database")?;
+        Self::migrate(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    async fn migrate(pool: &SqlitePool) -> Result<()> {
+        sqlx::migrate!("./migrations/admin")
+            .run(pool)
+            .await
+            .with_context(|| "failed to run admin database migrations")?;
+        Ok(())
+    }
+
+    /// Record an action. Fire-and-forget.
+    pub fn record(
+        &self,
+        actor: &str,
+        action: &str,
+        agent_id: Option<&str>,
+        mut payload: serde_json::Value,
+    ) {
+        redact_secrets(&mut payload);
+        tracing::info!(actor, action, agent_id, "admin action");
+
+        let pool = self.pool.clone();
+        let id = uuid::Uuid::new_v4().to_string();
+        let actor = actor.to_string();
+        let action = action.to_string();
+        let agent_id = agent_id.map(String::from);
+        let payload = payload.to_string();
+
+        let write = async move {
+            sqlx::query(
+                "INSERT INTO admin_audit (id, actor, action, agent_id, payload) \
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&actor)
+            .bind(&action)
+            .bind(&agent_id)
+            .bind(&payload)
+            .execute(&pool)
+            .await
+            .inspect_err(|error| tracing::warn!(%error, action, "failed to record admin action"))
+        };
+        tokio::spawn(db_write("admin_audit", "insert", write));
+    }
+
+    /// Recorded actions matching `filter`, newest first.
+    pub async fn list(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, actor, action, agent_id, payload, created_at FROM admin_audit \
+             WHERE (? IS NULL OR actor = ?) \
+             AND (? IS NULL OR action = ?) \
+             AND (? IS NULL OR agent_id = ?) \
+             ORDER BY created_at DESC, rowid DESC \
+             LIMIT ?",
+        )
+        .bind(&filter.actor)
+        .bind(&filter.actor)
+        .bind(&filter.action)
+        .bind(&filter.action)
+        .bind(&filter.agent_id)
+        .bind(&filter.agent_id)
+        .bind(filter.limit.clamp(1, MAX_LIST_LIMIT))
+        .fetch_all(&self.pool)
+        .timed_with("admin_audit.list", || format!("{filter:?}"))
+        .await
+        .with_context(|| "failed to list admin actions")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AuditEntry {
+                id: row.get("id"),
+                actor: row.get("actor"),
+                action: row.get("action"),
+                agent_id: row.get("agent_id"),
+                payload: serde_json::from_str(row.get("payload")).unwrap_or_default(),
+                created_at: row
+                    .try_get("created_at")
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_actions_are_recorded_redacted_and_filtered() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        AuditLog::migrate(&pool).await.expect("migrations");
+        let audit_log = AuditLog { pool };
+
+        audit_log.record(
+            "ops",
+            "provider.update",
+            None,
+            serde_json::json!({ "provider": "anthropic", "api_key": "sk-ant-123" }),
+        );
+        audit_log.record(
+            "jane",
+            "agent.delete",
+            Some("support"),
+            serde_json::json!({}),
+        );
+
+        // Writes are fire-and-forget; wait until both have landed.
+        let all = AuditFilter {
+            limit: 10,
+            ..Default::default()
+        };
+        while audit_log.list(&all).await.unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let entries = audit_log
+            .list(&AuditFilter {
+                action: Some("provider.update".into()),
+                ..all.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "ops");
+        assert_eq!(entries[0].payload["provider"], "anthropic");
+        assert_eq!(entries[0].payload["api_key"], "[redacted]");
+
+        let entries = audit_log
+            .list(&AuditFilter {
+                agent_id: Some("support".into()),
+                ..all
+            })
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "agent.delete");
+    }
+}
@@ -0,0 +1,368 @@
+//! Configuration checks for `spacebot check`.
+//!
+//! Loading a config already validates its syntax and each value on its own.
+//! These checks cover what only shows up across the whole config: agents
+//! that other entries point at, models whose provider has no credentials,
+//! and agent databases that can't be opened. Every problem is collected, so
+//! one run lists everything to fix.
+
+use crate::config::{Config, ResolvedAgentConfig};
+use crate::cron::scheduler::DeliveryTarget;
+use crate::llm::routing::provider_from_model;
+
+use std::collections::HashSet;
+
+/// Run every check, returning one line per problem found.
+pub async fn run(config: &Config) -> Vec<String> {
+    let mut problems = check_config(config);
+    for agent in config.resolve_agents() {
+        if let Err(problem) = check_database(&agent).await {
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+/// Checks that only need the config itself.
+pub fn check_config(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_agents(config, &mut problems);
+    check_references(config, &mut problems);
+    for agent in config.resolve_agents() {
+        check_models(config, &agent, &mut problems);
+        for job in &agent.cron {
+            check_delivery_target(
+                config,
+                &format!("cron job '{}' of agent '{}'", job.id, agent.id),
+                &job.delivery_target,
+                &mut problems,
+            );
+        }
+        if agent.digest.enabled
+            && let Some(destination) = &agent.digest.destination
+        {
+            check_delivery_target(
+                config,
+                &format!("digest of agent '{}'", agent.id),
+                destination,
+                &mut problems,
+            );
+        }
+    }
+    problems
+}
+
+fn check_agents(config: &Config, problems: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    for agent in &config.agents {
+        if agent.id.is_empty()
+            || !agent
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            problems.push(format!(
+                "agent id '{}' must be letters, digits, '-' and '_' only",
+                agent.id
+            ));
+        }
+        if !seen.insert(agent.id.as_str()) {
+            problems.push(format!("agent '{}' is defined more than once", agent.id));
+        }
+    }
+
+    let defaults: Vec<&str> = config
+        .agents
+        .iter()
+        .filter(|agent| agent.default)
+        .map(|agent| agent.id.as_str())
+        .collect();
+    if defaults.len() > 1 {
+        problems.push(format!(
+            "only one agent can be the default, but {} are",
+            defaults.join(", ")
+        ));
+    }
+    let default_agent_id = config.default_agent_id();
+    if !config
+        .agents
+        .iter()
+        .any(|agent| agent.id == default_agent_id)
+    {
+        problems.push(format!(
+            "no agent is marked default and there is no agent '{default_agent_id}' to fall back to"
+        ));
+    }
+}
+
+/// Entries that name an agent or a messaging adapter must name one that
+/// exists.
+fn check_references(config: &Config, problems: &mut Vec<String>) {
+    let agent_ids: HashSet<&str> = config
+        .agents
+        .iter()
+        .map(|agent| agent.id.as_str())
+        .collect();
+    let mut check_agent = |what: String, agent_id: &str| {
+        if !agent_ids.contains(agent_id) {
+            problems.push(format!("{what} routes to unknown agent '{agent_id}'"));
+        }
+    };
+
+    for binding in &config.bindings {
+        check_agent(
+            format!("binding for {}", binding.channel),
+            &binding.agent_id,
+        );
+    }
+    for (index, rule) in config.channel_routing.rules.iter().enumerate() {
+        for agent_id in &rule.agents {
+            check_agent(format!("channel rule {}", index + 1), agent_id);
+        }
+    }
+    if let Some(slack) = &config.messaging.slack {
+        for command in &slack.commands {
+            check_agent(
+                format!("slack command {}", command.command),
+                &command.agent_id,
+            );
+        }
+    }
+    if let Some(webhook) = &config.messaging.webhook {
+        for hook in &webhook.hooks {
+            if let Some(agent_id) = &hook.agent {
+                check_agent(format!("webhook hook '{}'", hook.name), agent_id);
+            }
+        }
+    }
+
+    for binding in &config.bindings {
+        if adapter_enabled(config, &binding.channel) == Some(false) {
+            problems.push(format!(
+                "binding for {} routes to agent '{}', but {} isn't enabled",
+                binding.channel, binding.agent_id, binding.channel
+            ));
+        }
+    }
+    if let Some(webhook) = &config.messaging.webhook {
+        for hook in &webhook.hooks {
+            if let Some(deliver_to) = &hook.deliver_to {
+                check_delivery_target(
+                    config,
+                    &format!("webhook hook '{}'", hook.name),
+                    deliver_to,
+                    problems,
+                );
+            }
+        }
+    }
+    for (index, rule) in config.alerts.rules.iter().enumerate() {
+        for target in &rule.notify {
+            if !target.starts_with("http://") && !target.starts_with("https://") {
+                check_delivery_target(
+                    config,
+                    &format!("alert rule {} ({})", index + 1, rule.condition.as_str()),
+                    target,
+                    problems,
+                );
+            }
+        }
+    }
+    for target in &config.alerts.recovery_notify {
+        if !target.starts_with("http://") && !target.starts_with("https://") {
+            check_delivery_target(config, "alerts.recovery_notify", target, problems);
+        }
+    }
+}
+
+fn check_delivery_target(config: &Config, what: &str, raw: &str, problems: &mut Vec<String>) {
+    let Some(target) = DeliveryTarget::parse(raw) else {
+        problems.push(format!(
+            "{what} delivers to '{raw}', which isn't in adapter:target form"
+        ));
+        return;
+    };
+    match adapter_enabled(config, &target.adapter) {
+        Some(true) => {}
+        Some(false) => problems.push(format!(
+            "{what} delivers to {}, which isn't enabled",
+            target.adapter
+        )),
+        None => problems.push(format!(
+            "{what} delivers to unknown adapter '{}'",
+            target.adapter
+        )),
+    }
+}
+
+/// Whether the adapter is configured and enabled, or None for a name that
+/// isn't an adapter. Stdio is only known at startup, so it counts as enabled.
+fn adapter_enabled(config: &Config, adapter: &str) -> Option<bool> {
+    let messaging = &config.messaging;
+    Some(match adapter {
+        "discord" => messaging.discord.as_ref().is_some_and(|c| c.enabled),
+        "slack" => messaging.slack.as_ref().is_some_and(|c| c.enabled),
+        "telegram" => messaging.telegram.as_ref().is_some_and(|c| c.enabled),
+        "webhook" => messaging.webhook.as_ref().is_some_and(|c| c.enabled),
+        "twitch" => messaging.twitch.as_ref().is_some_and(|c| c.enabled),
+        "matrix" => messaging.matrix.as_ref().is_some_and(|c| c.enabled),
+        "irc" => messaging.irc.as_ref().is_some_and(|c| c.enabled),
+        "email" => messaging.email.as_ref().is_some_and(|c| c.enabled),
+        "whatsapp" => messaging.whatsapp.as_ref().is_some_and(|c| c.enabled),
+        "sms" => messaging.sms.as_ref().is_some_and(|c| c.enabled),
+        "xmpp" => messaging.xmpp.as_ref().is_some_and(|c| c.enabled),
+        "webchat" | "stdio" => true,
+        _ => return None,
+    })
+}
+
+/// Every model the agent can call must belong to a provider with
+/// credentials.
+fn check_models(config: &Config, agent: &ResolvedAgentConfig, problems: &mut Vec<String>) {
+    let routing = &agent.routing;
+    let mut models: Vec<(String, &str)> = vec![
+        ("routing.channel".into(), routing.channel.as_str()),
+        ("routing.branch".into(), routing.branch.as_str()),
+        ("routing.worker".into(), routing.worker.as_str()),
+        ("routing.compactor".into(), routing.compactor.as_str()),
+        ("routing.cortex".into(), routing.cortex.as_str()),
+    ];
+    for (task, model) in &routing.task_overrides {
+        models.push((format!("routing.task_overrides.{task}"), model));
+    }
+    for (model, chain) in &routing.fallbacks {
+        for fallback in chain {
+            models.push((format!("routing.fallbacks.\"{model}\""), fallback));
+        }
+    }
+    if let Some(model) = &agent.reflection.model {
+        models.push(("reflection.model".into(), model));
+    }
+    if let Some(model) = &agent.vision.model {
+        models.push(("vision.model".into(), model));
+    }
+    if let Some(model) = &agent.experiment.alternate_model {
+        models.push(("experiment.alternate_model".into(), model));
+    }
+    for model in &agent.ensemble.models {
+        models.push(("ensemble.models".into(), model));
+    }
+    if let Some(model) = &agent.ensemble.judge_model {
+        models.push(("ensemble.judge_model".into(), model));
+    }
+    if let Some(model) = &agent.digest.model {
+        models.push(("digest.model".into(), model));
+    }
+
+    let mut reported = HashSet::new();
+    for (field, model) in models {
+        let provider = provider_from_model(model).to_lowercase();
+        let problem = match config.llm.providers.get(&provider) {
+            None => format!(
+                "agent '{}' {field} uses {model}, but provider '{provider}' isn't configured",
+                agent.id
+            ),
+            Some(provider_config) if provider_config.api_key.is_empty() && provider != "ollama" => {
+                format!(
+                    "agent '{}' {field} uses {model}, but provider '{provider}' has no API key",
+                    agent.id
+                )
+            }
+            Some(_) => continue,
+        };
+        if reported.insert(problem.clone()) {
+            problems.push(problem);
+        }
+    }
+}
+
+/// Open the agent's SQLite database read-only, if it exists yet. Read-only
+/// works alongside a running daemon and never creates or migrates anything.
+async fn check_database(agent: &ResolvedAgentConfig) -> Result<(), String> {
+    let path = agent.data_dir.join("spacebot.db");
+    if !path.exists() {
+        return Ok(());
+    }
+    let url = format!("sqlite:{}?mode=ro", path.display());
+    let result = async {
+        let pool = sqlx::SqlitePool::connect(&url).await?;
+        let result = sqlx::query("SELECT 1").execute(&pool).await;
+        pool.close().await;
+        result.map(|_| ())
+    }
+    .await;
+    result.map_err(|error| {
+        format!(
+            "agent '{}' database {} can't be opened: {error}",
+            agent.id,
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_reports_every_broken_reference_and_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+[llm]
+anthropic_key = "test-key"
+
+[[agents]]
+id = "main"
+default = true
+
+[agents.routing]
+channel = "anthropic/claude-sonnet-4"
+worker = "openai/o3"
+
+[[agents]]
+id = "main"
+
+[[bindings]]
+agent_id = "support"
+channel = "discord"
+
+[[channel_rules]]
+agents = ["main", "sales"]
+
+[messaging.webhook]
+enabled = true
+
+[[messaging.webhook.hooks]]
+name = "ci"
+token = "secret"
+deliver_to = "slack:C123"
+"#,
+        )
+        .unwrap();
+        let config = Config::load_from_path(&path).unwrap();
+
+        let problems = check_config(&config);
+        let expected = [
+            "agent 'main' is defined more than once",
+            "binding for discord routes to unknown agent 'support'",
+            "channel rule 1 routes to unknown agent 'sales'",
+            "binding for discord routes to agent 'support', but discord isn't enabled",
+            "webhook hook 'ci' delivers to slack, which isn't enabled",
+            "agent 'main' routing.worker uses openai/o3, but provider 'openai' isn't configured",
+        ];
+        for problem in expected {
+            assert!(
+                problems.iter().any(|found| found == problem),
+                "missing {problem:?} in {problems:#?}"
+            );
+        }
+        assert!(
+            !problems
+                .iter()
+                .any(|problem| problem.contains("routing.channel"))
+        );
+    }
+}
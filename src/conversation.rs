@@ -2,7 +2,14 @@
 
 pub mod channels;
 pub mod context;
+pub mod examples;
 pub mod history;
+pub mod libsql;
+pub mod recent;
+pub mod redaction;
 
-pub use channels::ChannelStore;
+pub use channels::{ChannelSettings, ChannelStore, Verbosity};
+pub use examples::{ExampleStore, FewShotExample};
 pub use history::{ConversationLogger, ProcessRunLogger, TimelineItem};
+pub use libsql::{ConversationStoreConfig, LibsqlStore, open_conversation_store};
+pub use recent::RecentMessages;
@@ -1,5 +1,9 @@
 //! Channel tracking and metadata (SQLite).
 
+use crate::config::{ListenMode, ListenRule, ListeningConfig};
+use crate::db::TimedQuery as _;
+
+use serde::{Deserialize, Serialize};
 use sqlx::{Row as _, SqlitePool};
 use std::collections::HashMap;
 
@@ -11,6 +15,9 @@ use std::collections::HashMap;
 #[derive(Debug, Clone)]
 pub struct ChannelStore {
     pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
 }
 
 /// A tracked channel with its metadata.
@@ -25,9 +32,137 @@ pub struct ChannelInfo {
     pub last_activity_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// How long the agent's replies in a channel should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Brief,
+    Normal,
+    Detailed,
+}
+
+impl Verbosity {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "brief" => Some(Self::Brief),
+            "normal" => Some(Self::Normal),
+            "detailed" => Some(Self::Detailed),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Brief => "brief",
+            Self::Normal => "normal",
+            Self::Detailed => "detailed",
+        }
+    }
+
+    /// The instruction added to the agent's system prompt.
+    pub fn note(self) -> &'static str {
+        match self {
+            Self::Brief => {
+                "Keep replies in this channel brief: a sentence or two, with no preamble \
+                 or recap. Expand only when asked."
+            }
+            Self::Normal => "Keep replies in this channel to a moderate length.",
+            Self::Detailed => {
+                "Replies in this channel can be detailed: explain your reasoning, give \
+                 examples, and cover edge cases."
+            }
+        }
+    }
+}
+
+/// Settings changed for one channel from chat, such as with Discord's
+/// `/settings`. Each one left unset falls back to the config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelSettings {
+    pub listen_mode: Option<ListenMode>,
+    pub verbosity: Option<Verbosity>,
+    /// Agent that handles the channel, in place of the one routing picks.
+    pub agent: Option<String>,
+}
+
+impl ChannelSettings {
+    /// Change `listening`, `verbosity`, or `agent` to `value`, or back to
+    /// the config's with "default".
+    pub fn update(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let reset = value.eq_ignore_ascii_case("default");
+        match name {
+            "listening" if reset => self.listen_mode = None,
+            "listening" => {
+                let mode = serde_json::from_value(value.into())
+                    .map_err(|_| format!("unknown listening mode: {value}"))?;
+                self.listen_mode = Some(mode);
+            }
+            "verbosity" if reset => self.verbosity = None,
+            "verbosity" => {
+                let verbosity =
+                    Verbosity::parse(value).ok_or_else(|| format!("unknown verbosity: {value}"))?;
+                self.verbosity = Some(verbosity);
+            }
+            "agent" if reset => self.agent = None,
+            "agent" => self.agent = Some(value.to_string()),
+            _ => return Err(format!("unknown setting: {name}")),
+        }
+        Ok(())
+    }
+
+    /// The listening config with the channel's mode applied over the rule
+    /// the conversation would otherwise get. None without a mode set.
+    pub fn listening(
+        &self,
+        config: &ListeningConfig,
+        conversation_id: &str,
+        guild_rule: Option<&ListenRule>,
+    ) -> Option<ListeningConfig> {
+        let mode = self.listen_mode?;
+        let rule = ListenRule {
+            mode,
+            ..config.rule_for(conversation_id, guild_rule).clone()
+        };
+        let mut config = config.clone();
+        config.channels.insert(conversation_id.to_string(), rule);
+        Some(config)
+    }
+
+    /// The settings in a line, like "listening: mention_only, verbosity:
+    /// brief".
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(mode) = self.listen_mode {
+            let mode = serde_json::to_value(mode).unwrap_or_default();
+            parts.push(format!("listening: {}", mode.as_str().unwrap_or_default()));
+        }
+        if let Some(verbosity) = self.verbosity {
+            parts.push(format!("verbosity: {}", verbosity.name()));
+        }
+        if let Some(agent) = &self.agent {
+            parts.push(format!("agent: {agent}"));
+        }
+        if parts.is_empty() {
+            "all from the config".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
 impl ChannelStore {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
     }
 
     /// Upsert a channel when it's first seen or when metadata changes.
@@ -56,6 +191,7 @@ impl ChannelStore {
             .bind(&display_name)
             .bind(&platform_meta)
             .execute(&pool)
+            .timed_with("channels.upsert", || format!("id={channel_id}"))
             .await
             {
                 tracing::warn!(%error, %channel_id, "failed to upsert channel");
@@ -73,6 +209,7 @@ impl ChannelStore {
                 sqlx::query("UPDATE channels SET last_activity_at = CURRENT_TIMESTAMP WHERE id = ?")
                     .bind(&channel_id)
                     .execute(&pool)
+                    .timed_with("channels.touch", || format!("id={channel_id}"))
                     .await
             {
                 tracing::warn!(%error, %channel_id, "failed to touch channel");
@@ -89,6 +226,7 @@ impl ChannelStore {
              ORDER BY last_activity_at DESC"
         )
         .fetch_all(&self.pool)
+        .timed("channels.list_active")
         .await
         .map_err(|e| anyhow::anyhow!(e))?;
 
@@ -146,12 +284,58 @@ impl ChannelStore {
         )
         .bind(channel_id)
         .fetch_optional(&self.pool)
+        .timed_with("channels.get", || format!("id={channel_id}"))
         .await
         .map_err(|e| anyhow::anyhow!(e))?;
 
         Ok(row.map(row_to_channel_info))
     }
 
+    /// Keep a channel's settings, adding the channel if it hasn't been seen
+    /// yet. Fire-and-forget.
+    pub fn set_settings(&self, channel_id: &str, settings: &ChannelSettings) {
+        let pool = self.pool.clone();
+        let channel_id = channel_id.to_string();
+        let platform = extract_platform(&channel_id);
+        let settings = (*settings != ChannelSettings::default())
+            .then(|| serde_json::to_string(settings).ok())
+            .flatten();
+
+        tokio::spawn(async move {
+            if let Err(error) = sqlx::query(
+                "INSERT INTO channels (id, platform, settings) VALUES (?, ?, ?) \
+                 ON CONFLICT(id) DO UPDATE SET settings = excluded.settings",
+            )
+            .bind(&channel_id)
+            .bind(&platform)
+            .bind(&settings)
+            .execute(&pool)
+            .timed_with("channels.set_settings", || format!("id={channel_id}"))
+            .await
+            {
+                tracing::warn!(%error, %channel_id, "failed to save channel settings");
+            }
+        });
+    }
+
+    /// The settings of every channel that has any, by channel ID.
+    pub async fn load_settings(&self) -> crate::error::Result<HashMap<String, ChannelSettings>> {
+        let rows = sqlx::query("SELECT id, settings FROM channels WHERE settings IS NOT NULL")
+            .fetch_all(&self.readers)
+            .timed("channels.load_settings")
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let id: String = row.try_get("id").ok()?;
+                let settings: String = row.try_get("settings").ok()?;
+                Some((id, serde_json::from_str(&settings).ok()?))
+            })
+            .collect())
+    }
+
     /// Resolve a channel's display name by ID.
     pub async fn resolve_name(&self, channel_id: &str) -> Option<String> {
         self.get(channel_id)
@@ -265,3 +449,41 @@ fn extract_platform_meta(
         serde_json::to_string(&serde_json::Value::Object(meta)).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_update_and_override_listening() {
+        let mut settings = ChannelSettings::default();
+        settings.update("listening", "mention_only").unwrap();
+        settings.update("verbosity", "brief").unwrap();
+        settings.update("agent", "dev").unwrap();
+        assert_eq!(
+            settings.describe(),
+            "listening: mention_only, verbosity: brief, agent: dev"
+        );
+        assert!(settings.update("listening", "sometimes").is_err());
+        assert!(settings.update("tone", "formal").is_err());
+
+        let config = ListeningConfig::default();
+        let listening = settings
+            .listening(&config, "discord:1:2", None)
+            .expect("mode is set");
+        assert_eq!(
+            listening.rule_for("discord:1:2", None).mode,
+            ListenMode::MentionOnly
+        );
+        assert_eq!(
+            listening.rule_for("discord:1:3", None).mode,
+            ListenMode::All
+        );
+
+        settings.update("listening", "default").unwrap();
+        settings.update("verbosity", "default").unwrap();
+        settings.update("agent", "Default").unwrap();
+        assert_eq!(settings, ChannelSettings::default());
+        assert!(settings.listening(&config, "discord:1:2", None).is_none());
+    }
+}
@@ -35,9 +35,12 @@ pub async fn build_channel_context(
     }
 
     // Add identity memories (always included)
-    let identity_memories = memory_store
+    let identity_memories: Vec<_> = memory_store
         .get_by_type(crate::memory::types::MemoryType::Identity, 10)
-        .await?;
+        .await?
+        .into_iter()
+        .filter(|m| !m.private)
+        .collect();
 
     if !identity_memories.is_empty() {
         context.push_str("## Identity\n\n");
@@ -0,0 +1,206 @@
+//! Few-shot example store (SQLite).
+//!
+//! Operators curate example exchanges through the API. The enabled ones are
+//! rendered into the channel prompt as demonstrations, which steers style and
+//! correctness without editing the persona prose.
+
+use crate::db::TimedQuery as _;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+/// Most examples injected into one channel prompt, newest first.
+pub const MAX_PROMPT_EXAMPLES: i64 = 8;
+
+/// A curated exchange: what a user said and how the agent should answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FewShotExample {
+    pub id: String,
+    pub user_message: String,
+    pub response: String,
+    /// What the example demonstrates, shown to the model alongside it.
+    pub note: Option<String>,
+    pub enabled: bool,
+    pub created_at: String,
+}
+
+/// Stores an agent's few-shot examples.
+#[derive(Debug, Clone)]
+pub struct ExampleStore {
+    pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
+}
+
+impl ExampleStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
+    }
+
+    /// Add an example and return it.
+    pub async fn add(
+        &self,
+        user_message: &str,
+        response: &str,
+        note: Option<&str>,
+    ) -> crate::error::Result<FewShotExample> {
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO few_shot_examples (id, user_message, response, note) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(user_message)
+        .bind(response)
+        .bind(note)
+        .execute(&self.pool)
+        .timed("few_shot_examples.insert")
+        .await
+        .context("failed to save few-shot example")?;
+
+        Ok(self
+            .get(&id)
+            .await?
+            .context("few-shot example missing after insert")?)
+    }
+
+    /// Get one example by ID.
+    pub async fn get(&self, id: &str) -> crate::error::Result<Option<FewShotExample>> {
+        let row = sqlx::query(
+            "SELECT id, user_message, response, note, enabled, created_at \
+             FROM few_shot_examples WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .timed_with("few_shot_examples.get", || format!("id={id}"))
+        .await
+        .context("failed to load few-shot example")?;
+
+        Ok(row.as_ref().map(row_to_example))
+    }
+
+    /// List all examples, oldest first.
+    pub async fn list(&self) -> crate::error::Result<Vec<FewShotExample>> {
+        let rows = sqlx::query(
+            "SELECT id, user_message, response, note, enabled, created_at \
+             FROM few_shot_examples ORDER BY created_at ASC, rowid ASC",
+        )
+        .fetch_all(&self.pool)
+        .timed("few_shot_examples.list")
+        .await
+        .context("failed to list few-shot examples")?;
+
+        Ok(rows.iter().map(row_to_example).collect())
+    }
+
+    /// The examples to show in the channel prompt: the newest enabled ones,
+    /// returned in the order they were added.
+    pub async fn load_for_prompt(&self) -> crate::error::Result<Vec<FewShotExample>> {
+        let rows = sqlx::query(
+            "SELECT id, user_message, response, note, enabled, created_at \
+             FROM few_shot_examples WHERE enabled = 1 \
+             ORDER BY created_at DESC, rowid DESC LIMIT ?",
+        )
+        .bind(MAX_PROMPT_EXAMPLES)
+        .fetch_all(&self.readers)
+        .timed("few_shot_examples.load_for_prompt")
+        .await
+        .context("failed to load few-shot examples")?;
+
+        let mut examples: Vec<_> = rows.iter().map(row_to_example).collect();
+        examples.reverse();
+        Ok(examples)
+    }
+
+    /// Enable or disable an example. Returns false if it doesn't exist.
+    pub async fn set_enabled(&self, id: &str, enabled: bool) -> crate::error::Result<bool> {
+        let result = sqlx::query("UPDATE few_shot_examples SET enabled = ? WHERE id = ?")
+            .bind(enabled as i64)
+            .bind(id)
+            .execute(&self.pool)
+            .timed_with("few_shot_examples.set_enabled", || format!("id={id}"))
+            .await
+            .context("failed to update few-shot example")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete an example. Returns false if it doesn't exist.
+    pub async fn delete(&self, id: &str) -> crate::error::Result<bool> {
+        let result = sqlx::query("DELETE FROM few_shot_examples WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .timed_with("few_shot_examples.delete", || format!("id={id}"))
+            .await
+            .context("failed to delete few-shot example")?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn row_to_example(row: &sqlx::sqlite::SqliteRow) -> FewShotExample {
+    FewShotExample {
+        id: row.try_get("id").unwrap_or_default(),
+        user_message: row.try_get("user_message").unwrap_or_default(),
+        response: row.try_get("response").unwrap_or_default(),
+        note: row.try_get("note").ok().flatten(),
+        enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
+        created_at: row.try_get("created_at").unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn store() -> ExampleStore {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+        ExampleStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_prompt_examples_skip_disabled_in_insertion_order() {
+        let store = store().await;
+        let first = store.add("hi", "Hey!", None).await.unwrap();
+        let second = store
+            .add("deploy?", "Checking CI now.", Some("be brief"))
+            .await
+            .unwrap();
+        let third = store.add("thanks", "Anytime.", None).await.unwrap();
+        assert!(store.set_enabled(&second.id, false).await.unwrap());
+
+        let ids: Vec<_> = store
+            .load_for_prompt()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|example| example.id)
+            .collect();
+        assert_eq!(ids, [first.id.clone(), third.id]);
+
+        assert!(store.delete(&first.id).await.unwrap());
+        assert!(!store.delete(&first.id).await.unwrap());
+        assert_eq!(store.list().await.unwrap().len(), 2);
+    }
+}
@@ -1,10 +1,17 @@
 //! Conversation message persistence (SQLite).
 
+use crate::conversation::libsql::LibsqlStore;
+use crate::conversation::recent::{self, RecentMessages};
+use crate::conversation::redaction::Redactor;
+use crate::db::TimedQuery as _;
+use crate::events::{self, BusEvent};
+use crate::otel::db_write;
 use crate::{BranchId, ChannelId, WorkerId};
 
 use serde::Serialize;
 use sqlx::{Row as _, SqlitePool};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Persists conversation messages (user and assistant) to SQLite.
 ///
@@ -13,6 +20,15 @@ use std::collections::HashMap;
 #[derive(Debug, Clone)]
 pub struct ConversationLogger {
     pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
+    /// Applied to message content before it's written, when enabled.
+    redactor: Option<Arc<Redactor>>,
+    /// Recent messages of the agent's channels, kept current by the writes.
+    cache: Option<Arc<RecentMessages>>,
+    /// Remote copy of the messages, written after each local write.
+    remote: Option<Arc<LibsqlStore>>,
 }
 
 /// A persisted conversation message.
@@ -25,15 +41,56 @@ pub struct ConversationMessage {
     pub sender_id: Option<String>,
     pub content: String,
     pub metadata: Option<String>,
+    /// See [`crate::TURN_ID_KEY`]. None for messages logged before turn ids.
+    pub turn_id: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl ConversationLogger {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+            redactor: None,
+            cache: None,
+            remote: None,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
+    }
+
+    /// Redact PII from content before persisting it. Loaded history keeps
+    /// the placeholders.
+    pub fn with_redactor(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// Serve [`load_recent`](Self::load_recent) from `cache` where it can,
+    /// and keep it current with this logger's writes.
+    pub fn with_cache(mut self, cache: Arc<RecentMessages>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Copy every write to a remote libSQL table once it's in SQLite.
+    pub fn with_remote_store(mut self, remote: Arc<LibsqlStore>) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    fn redact(&self, channel_id: &str, content: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(channel_id, content),
+            None => content.to_string(),
+        }
     }
 
-    /// Log a user message. Fire-and-forget.
+    /// Log a user message, with the turn id from its metadata. Fire-and-forget.
     pub fn log_user_message(
         &self,
         channel_id: &ChannelId,
@@ -47,13 +104,19 @@ impl ConversationLogger {
         let channel_id = channel_id.to_string();
         let sender_name = sender_name.to_string();
         let sender_id = sender_id.to_string();
-        let content = content.to_string();
+        let content = self.redact(&channel_id, content);
+        let turn_id = metadata
+            .get(crate::TURN_ID_KEY)
+            .and_then(|id| id.as_str())
+            .map(String::from);
         let metadata_json = serde_json::to_string(metadata).ok();
+        let cache = self.cache.clone();
+        let remote = self.remote.clone();
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, sender_name, sender_id, content, metadata) \
-                 VALUES (?, ?, 'user', ?, ?, ?, ?)"
+        let write = async move {
+            sqlx::query(
+                "INSERT INTO conversation_messages (id, channel_id, role, sender_name, sender_id, content, metadata, turn_id) \
+                 VALUES (?, ?, 'user', ?, ?, ?, ?, ?)"
             )
             .bind(&id)
             .bind(&channel_id)
@@ -61,53 +124,168 @@ impl ConversationLogger {
             .bind(&sender_id)
             .bind(&content)
             .bind(&metadata_json)
+            .bind(&turn_id)
             .execute(&pool)
             .await
-            {
-                tracing::warn!(%error, "failed to persist user message");
+            .inspect_err(|error| tracing::warn!(%error, "failed to persist user message"))?;
+            persisted(
+                cache,
+                remote,
+                ConversationMessage {
+                    id: id.clone(),
+                    channel_id: channel_id.clone(),
+                    role: "user".to_string(),
+                    sender_name: Some(sender_name),
+                    sender_id: Some(sender_id),
+                    content,
+                    metadata: metadata_json,
+                    turn_id: turn_id.clone(),
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .await;
+            events::publish(BusEvent::MessagePersisted {
+                channel_id,
+                message_id: id,
+                role: "user",
+                turn_id,
+            });
+            Ok::<_, sqlx::Error>(())
+        };
+        tokio::spawn(db_write("conversation_messages", "insert", write));
+    }
+
+    /// Replace the content of the user message with Discord message id
+    /// `message_id`, and mark it edited. Fire-and-forget.
+    pub fn edit_user_message(&self, channel_id: &ChannelId, message_id: &str, new_content: &str) {
+        let pool = self.pool.clone();
+        let channel_id = channel_id.to_string();
+        let message_id = message_id.to_string();
+        let new_content = self.redact(&channel_id, new_content);
+        let cache = self.cache.clone();
+        let remote = self.remote.clone();
+
+        let write = async move {
+            let result = sqlx::query(
+                "UPDATE conversation_messages \
+                 SET content = ?, metadata = json_set(COALESCE(metadata, '{}'), '$.edited', json('true')) \
+                 WHERE id = (SELECT id FROM conversation_messages \
+                 WHERE channel_id = ? AND role = 'user' \
+                 AND CAST(json_extract(metadata, '$.discord_message_id') AS TEXT) = ? \
+                 ORDER BY created_at DESC LIMIT 1)",
+            )
+            .bind(&new_content)
+            .bind(&channel_id)
+            .bind(&message_id)
+            .execute(&pool)
+            .await
+            .inspect_err(|error| tracing::warn!(%error, "failed to persist user message edit"))?;
+            if let Some(cache) = cache {
+                cache.invalidate(&channel_id);
+            }
+            if let Some(remote) = remote {
+                remote
+                    .edit_user_message(&channel_id, &message_id, &new_content)
+                    .await;
             }
-        });
+            Ok::<_, sqlx::Error>(result)
+        };
+        tokio::spawn(db_write("conversation_messages", "update", write));
     }
 
-    /// Log a bot (assistant) message. Fire-and-forget.
-    pub fn log_bot_message(&self, channel_id: &ChannelId, content: &str) {
+    /// Log a bot (assistant) message. Fire-and-forget; returns the row id.
+    pub fn log_bot_message(
+        &self,
+        channel_id: &ChannelId,
+        content: &str,
+        turn_id: Option<&str>,
+    ) -> String {
         let pool = self.pool.clone();
         let id = uuid::Uuid::new_v4().to_string();
         let channel_id = channel_id.to_string();
-        let content = content.to_string();
-
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, content) \
-                 VALUES (?, ?, 'assistant', ?)",
+        let content = self.redact(&channel_id, content);
+        let turn_id = turn_id.map(String::from);
+        let row_id = id.clone();
+        let cache = self.cache.clone();
+        let remote = self.remote.clone();
+
+        let write = async move {
+            sqlx::query(
+                "INSERT INTO conversation_messages (id, channel_id, role, content, turn_id) \
+                 VALUES (?, ?, 'assistant', ?, ?)",
             )
-            .bind(&id)
+            .bind(&row_id)
             .bind(&channel_id)
             .bind(&content)
+            .bind(&turn_id)
             .execute(&pool)
             .await
-            {
-                tracing::warn!(%error, "failed to persist bot message");
-            }
-        });
+            .inspect_err(|error| tracing::warn!(%error, "failed to persist bot message"))?;
+            persisted(
+                cache,
+                remote,
+                ConversationMessage {
+                    id: row_id.clone(),
+                    channel_id: channel_id.clone(),
+                    role: "assistant".to_string(),
+                    sender_name: None,
+                    sender_id: None,
+                    content,
+                    metadata: None,
+                    turn_id: turn_id.clone(),
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .await;
+            events::publish(BusEvent::MessagePersisted {
+                channel_id,
+                message_id: row_id,
+                role: "assistant",
+                turn_id,
+            });
+            Ok::<_, sqlx::Error>(())
+        };
+        tokio::spawn(db_write("conversation_messages", "insert", write));
+
+        id
     }
 
-    /// Load recent messages for a channel (oldest first).
+    /// Load recent messages for a channel (oldest first). Served from the
+    /// cache when it holds them; otherwise read from SQLite, reading at least
+    /// [`recent::MESSAGES_PER_CHANNEL`] messages to fill it.
     pub async fn load_recent(
         &self,
         channel_id: &ChannelId,
         limit: i64,
     ) -> crate::error::Result<Vec<ConversationMessage>> {
+        let wanted = usize::try_from(limit).unwrap_or(0);
+        if let Some(cache) = &self.cache
+            && let Some(messages) = cache.get(channel_id, wanted)
+        {
+            return Ok(messages);
+        }
+        let generation = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.generation(channel_id));
+        let fetch_limit = match generation {
+            Some(_) => limit.max(recent::MESSAGES_PER_CHANNEL as i64),
+            None => limit,
+        };
+
         let rows = sqlx::query(
-            "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
+            "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, turn_id, created_at \
              FROM conversation_messages \
              WHERE channel_id = ? \
              ORDER BY created_at DESC \
              LIMIT ?",
         )
         .bind(channel_id.as_ref())
-        .bind(limit)
-        .fetch_all(&self.pool)
+        .bind(fetch_limit)
+        .fetch_all(&self.readers)
+        .timed_with("conversation_messages.load_recent", || {
+            format!("channel_id={channel_id} limit={fetch_limit}")
+        })
         .await
         .map_err(|e| anyhow::anyhow!(e))?;
 
@@ -121,6 +299,7 @@ impl ConversationLogger {
                 sender_id: row.try_get("sender_id").ok(),
                 content: row.try_get("content").unwrap_or_default(),
                 metadata: row.try_get("metadata").ok(),
+                turn_id: row.try_get("turn_id").ok(),
                 created_at: row
                     .try_get("created_at")
                     .unwrap_or_else(|_| chrono::Utc::now()),
@@ -129,6 +308,13 @@ impl ConversationLogger {
 
         // Reverse to chronological order
         messages.reverse();
+        if let (Some(cache), Some(generation)) = (&self.cache, generation) {
+            let complete = (messages.len() as i64) < fetch_limit;
+            cache.fill(channel_id, generation, &messages, complete);
+        }
+        if messages.len() > wanted {
+            messages.drain(..messages.len() - wanted);
+        }
 
         Ok(messages)
     }
@@ -140,7 +326,7 @@ impl ConversationLogger {
         limit: i64,
     ) -> crate::error::Result<Vec<ConversationMessage>> {
         let rows = sqlx::query(
-            "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
+            "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, turn_id, created_at \
              FROM conversation_messages \
              WHERE channel_id = ? \
              ORDER BY created_at DESC \
@@ -148,7 +334,10 @@ impl ConversationLogger {
         )
         .bind(channel_id)
         .bind(limit)
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed_with("conversation_messages.load_transcript", || {
+            format!("channel_id={channel_id} limit={limit}")
+        })
         .await
         .map_err(|e| anyhow::anyhow!(e))?;
 
@@ -162,6 +351,7 @@ impl ConversationLogger {
                 sender_id: row.try_get("sender_id").ok(),
                 content: row.try_get("content").unwrap_or_default(),
                 metadata: row.try_get("metadata").ok(),
+                turn_id: row.try_get("turn_id").ok(),
                 created_at: row
                     .try_get("created_at")
                     .unwrap_or_else(|_| chrono::Utc::now()),
@@ -171,6 +361,68 @@ impl ConversationLogger {
         messages.reverse();
         Ok(messages)
     }
+
+    /// The latest user message in each active channel on a platform, most
+    /// recently active channel first. Tells where to catch up from after a
+    /// restart.
+    pub async fn latest_per_channel(
+        &self,
+        platform: &str,
+        limit: i64,
+    ) -> crate::error::Result<Vec<ConversationMessage>> {
+        let rows = sqlx::query(
+            "SELECT m.id, m.channel_id, m.role, m.sender_name, m.sender_id, m.content, m.metadata, m.turn_id, m.created_at \
+             FROM conversation_messages m \
+             JOIN channels c ON c.id = m.channel_id \
+             WHERE c.platform = ? AND c.is_active = 1 AND m.role = 'user' \
+             AND m.created_at = (SELECT MAX(created_at) FROM conversation_messages \
+                 WHERE channel_id = m.channel_id AND role = 'user') \
+             ORDER BY m.created_at DESC \
+             LIMIT ?",
+        )
+        .bind(platform)
+        .bind(limit)
+        .fetch_all(&self.readers)
+        .timed_with("conversation_messages.latest_per_channel", || {
+            format!("platform={platform} limit={limit}")
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+        let messages: Vec<ConversationMessage> = rows
+            .into_iter()
+            .map(|row| ConversationMessage {
+                id: row.try_get("id").unwrap_or_default(),
+                channel_id: row.try_get("channel_id").unwrap_or_default(),
+                role: row.try_get("role").unwrap_or_default(),
+                sender_name: row.try_get("sender_name").ok(),
+                sender_id: row.try_get("sender_id").ok(),
+                content: row.try_get("content").unwrap_or_default(),
+                metadata: row.try_get("metadata").ok(),
+                turn_id: row.try_get("turn_id").ok(),
+                created_at: row
+                    .try_get("created_at")
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+            })
+            .collect();
+
+        Ok(messages)
+    }
+}
+
+/// Pass a message that was just written to SQLite on to the recent message
+/// cache and the remote store.
+async fn persisted(
+    cache: Option<Arc<RecentMessages>>,
+    remote: Option<Arc<LibsqlStore>>,
+    message: ConversationMessage,
+) {
+    if let Some(remote) = remote {
+        remote.insert(&message).await;
+    }
+    if let Some(cache) = cache {
+        cache.push(message);
+    }
 }
 
 /// A unified timeline item combining messages, branch runs, and worker runs.
@@ -208,11 +460,23 @@ pub enum TimelineItem {
 #[derive(Debug, Clone)]
 pub struct ProcessRunLogger {
     pool: SqlitePool,
+    /// Pool for the timeline query. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
 }
 
 impl ProcessRunLogger {
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Load the timeline from the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
     }
 
     /// Record a branch starting. Fire-and-forget.
@@ -227,8 +491,8 @@ impl ProcessRunLogger {
         let channel_id = channel_id.to_string();
         let description = description.to_string();
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
+        let write = async move {
+            sqlx::query(
                 "INSERT INTO branch_runs (id, channel_id, description) VALUES (?, ?, ?)",
             )
             .bind(&id)
@@ -236,10 +500,11 @@ impl ProcessRunLogger {
             .bind(&description)
             .execute(&pool)
             .await
-            {
-                tracing::warn!(%error, branch_id = %id, "failed to persist branch start");
-            }
-        });
+            .inspect_err(|error| {
+                tracing::warn!(%error, branch_id = %id, "failed to persist branch start")
+            })
+        };
+        tokio::spawn(db_write("branch_runs", "insert", write));
     }
 
     /// Record a branch completing with its conclusion. Fire-and-forget.
@@ -248,18 +513,19 @@ impl ProcessRunLogger {
         let id = branch_id.to_string();
         let conclusion = conclusion.to_string();
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
+        let write = async move {
+            sqlx::query(
                 "UPDATE branch_runs SET conclusion = ?, completed_at = CURRENT_TIMESTAMP WHERE id = ?"
             )
             .bind(&conclusion)
             .bind(&id)
             .execute(&pool)
             .await
-            {
-                tracing::warn!(%error, branch_id = %id, "failed to persist branch completion");
-            }
-        });
+            .inspect_err(|error| {
+                tracing::warn!(%error, branch_id = %id, "failed to persist branch completion")
+            })
+        };
+        tokio::spawn(db_write("branch_runs", "update", write));
     }
 
     /// Record a worker starting. Fire-and-forget.
@@ -274,18 +540,18 @@ impl ProcessRunLogger {
         let channel_id = channel_id.map(|c| c.to_string());
         let task = task.to_string();
 
-        tokio::spawn(async move {
-            if let Err(error) =
-                sqlx::query("INSERT INTO worker_runs (id, channel_id, task) VALUES (?, ?, ?)")
-                    .bind(&id)
-                    .bind(&channel_id)
-                    .bind(&task)
-                    .execute(&pool)
-                    .await
-            {
-                tracing::warn!(%error, worker_id = %id, "failed to persist worker start");
-            }
-        });
+        let write = async move {
+            sqlx::query("INSERT INTO worker_runs (id, channel_id, task) VALUES (?, ?, ?)")
+                .bind(&id)
+                .bind(&channel_id)
+                .bind(&task)
+                .execute(&pool)
+                .await
+                .inspect_err(|error| {
+                    tracing::warn!(%error, worker_id = %id, "failed to persist worker start")
+                })
+        };
+        tokio::spawn(db_write("worker_runs", "insert", write));
     }
 
     /// Update a worker's status. Fire-and-forget.
@@ -294,16 +560,17 @@ impl ProcessRunLogger {
         let id = worker_id.to_string();
         let status = status.to_string();
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query("UPDATE worker_runs SET status = ? WHERE id = ?")
+        let write = async move {
+            sqlx::query("UPDATE worker_runs SET status = ? WHERE id = ?")
                 .bind(&status)
                 .bind(&id)
                 .execute(&pool)
                 .await
-            {
-                tracing::warn!(%error, worker_id = %id, "failed to persist worker status");
-            }
-        });
+                .inspect_err(|error| {
+                    tracing::warn!(%error, worker_id = %id, "failed to persist worker status")
+                })
+        };
+        tokio::spawn(db_write("worker_runs", "update", write));
     }
 
     /// Record a worker completing with its result. Fire-and-forget.
@@ -312,18 +579,19 @@ impl ProcessRunLogger {
         let id = worker_id.to_string();
         let result = result.to_string();
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
+        let write = async move {
+            sqlx::query(
                 "UPDATE worker_runs SET result = ?, status = 'done', completed_at = CURRENT_TIMESTAMP WHERE id = ?"
             )
             .bind(&result)
             .bind(&id)
             .execute(&pool)
             .await
-            {
-                tracing::warn!(%error, worker_id = %id, "failed to persist worker completion");
-            }
-        });
+            .inspect_err(|error| {
+                tracing::warn!(%error, worker_id = %id, "failed to persist worker completion")
+            })
+        };
+        tokio::spawn(db_write("worker_runs", "update", write));
     }
 
     /// Load a unified timeline for a channel: messages, branch runs, and worker runs
@@ -369,7 +637,10 @@ impl ProcessRunLogger {
         }
 
         let rows = query
-            .fetch_all(&self.pool)
+            .fetch_all(&self.readers)
+            .timed_with("timeline.load", || {
+                format!("channel_id={channel_id} limit={limit} before={before:?}")
+            })
             .await
             .map_err(|e| anyhow::anyhow!(e))?;
 
@@ -0,0 +1,774 @@
+//! Conversation messages replicated to a remote libSQL server (Turso or
+//! sqld).
+//!
+//! With the `libsql` conversation store, every message the conversation
+//! logger writes is also written to a per-agent table on the server, over
+//! libSQL's HTTP protocol. This is a best-effort replica, not a backend: the
+//! agent's SQLite database stays the working copy every query reads, and the
+//! remote table is the copy that outlives the host. When an agent starts with
+//! no messages in SQLite, they're restored from the remote table, so a fresh
+//! host picks up where the last one stopped. When the remote table is
+//! created, it's seeded with the messages already in SQLite.
+//!
+//! Remote writes are queued in the agent's `libsql_outbox` table and sent in
+//! order by a background writer, batched into one request when several are
+//! waiting. Rows leave the outbox only once the server has them, so while
+//! it's unreachable writes wait on disk, retried with backoff up to
+//! [`MAX_RETRY_DELAY`], rather than in memory. Only a statement the server
+//! rejects is dropped, since sending it again can't succeed.
+
+use crate::conversation::history::ConversationMessage;
+use crate::db::TimedQuery as _;
+use crate::error::{DbError, Result};
+
+use serde::Deserialize;
+use sqlx::{Row as _, SqlitePool};
+use tokio::sync::mpsc;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Messages copied per request when seeding or restoring.
+const PAGE_SIZE: i64 = 500;
+
+/// Queued writes sent in one request.
+const MAX_BATCH: i64 = 100;
+
+/// Wait before retrying after the server couldn't be reached. Doubles with
+/// each failure in a row, up to [`MAX_RETRY_DELAY`].
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest wait between retries while the server is unreachable.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Where an agent's conversation messages are kept.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum ConversationStoreConfig {
+    /// Only the agent's SQLite database.
+    #[default]
+    Sqlite,
+    /// The agent's SQLite database, replicated to a libSQL server.
+    Libsql {
+        /// `libsql://`, `https://` or `http://` URL of the database.
+        url: String,
+        #[serde(skip_serializing)]
+        auth_token: Option<String>,
+        /// Table name prefix. The agent ID is appended so agents never share
+        /// a table.
+        table_prefix: String,
+    },
+}
+
+impl ConversationStoreConfig {
+    /// Backend name as written in config, for logs and error context.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+            Self::Libsql { .. } => "libsql",
+        }
+    }
+}
+
+/// Open the configured conversation store for an agent. Returns None for
+/// plain SQLite.
+///
+/// Creates the remote table and seeds it from SQLite the first time, or
+/// restores SQLite from it when SQLite has no messages yet.
+pub async fn open_conversation_store(
+    config: &ConversationStoreConfig,
+    agent_id: &str,
+    pool: &SqlitePool,
+    http_client: reqwest::Client,
+) -> Result<Option<Arc<LibsqlStore>>> {
+    let ConversationStoreConfig::Libsql {
+        url,
+        auth_token,
+        table_prefix,
+    } = config
+    else {
+        return Ok(None);
+    };
+
+    let client = LibsqlClient::new(http_client, url, auth_token.clone());
+    let table_name = format!("{table_prefix}_{agent_id}");
+    let table = format!("\"{}\"", table_name.replace('"', "\"\""));
+
+    if ensure_table(&client, &table_name, &table).await? {
+        seed_remote(&client, &table, pool).await?;
+    } else {
+        let local: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM conversation_messages")
+            .fetch_one(pool)
+            .timed("libsql.local_count")
+            .await?;
+        if local == 0 {
+            restore_local(&client, &table, pool).await?;
+        }
+    }
+
+    // One pending wake-up is enough: the writer sends everything in the
+    // outbox each time it wakes.
+    let (wake, receiver) = mpsc::channel(1);
+    tokio::spawn(run_writer(client, pool.clone(), receiver));
+    Ok(Some(Arc::new(LibsqlStore {
+        table,
+        pool: pool.clone(),
+        wake,
+    })))
+}
+
+/// Remote copy of an agent's conversation messages. Writes are queued in the
+/// outbox and sent in the background.
+#[derive(Debug)]
+pub struct LibsqlStore {
+    /// Quoted table identifier, safe to interpolate into SQL.
+    table: String,
+    pool: SqlitePool,
+    wake: mpsc::Sender<()>,
+}
+
+impl LibsqlStore {
+    /// Queue a copy of a message that was just written to SQLite.
+    pub async fn insert(&self, message: &ConversationMessage) {
+        self.enqueue(insert_statement(
+            &self.table,
+            [
+                Value::Text(message.id.clone()),
+                Value::Text(message.channel_id.clone()),
+                Value::Text(message.role.clone()),
+                message.sender_name.clone().into(),
+                message.sender_id.clone().into(),
+                Value::Text(message.content.clone()),
+                message.metadata.clone().into(),
+                message.turn_id.clone().into(),
+                Value::Text(message.created_at.format("%Y-%m-%d %H:%M:%S").to_string()),
+            ],
+        ))
+        .await;
+    }
+
+    /// Queue the same edit [`ConversationLogger::edit_user_message`] made in
+    /// SQLite. Content is passed as stored, after redaction.
+    ///
+    /// [`ConversationLogger::edit_user_message`]: crate::conversation::ConversationLogger::edit_user_message
+    pub async fn edit_user_message(&self, channel_id: &str, message_id: &str, new_content: &str) {
+        let table = &self.table;
+        self.enqueue(Statement {
+            sql: format!(
+                "UPDATE {table} \
+                 SET content = ?, metadata = json_set(COALESCE(metadata, '{{}}'), '$.edited', json('true')) \
+                 WHERE id = (SELECT id FROM {table} \
+                 WHERE channel_id = ? AND role = 'user' \
+                 AND CAST(json_extract(metadata, '$.discord_message_id') AS TEXT) = ? \
+                 ORDER BY created_at DESC LIMIT 1)"
+            ),
+            args: vec![
+                Value::Text(new_content.to_string()),
+                Value::Text(channel_id.to_string()),
+                Value::Text(message_id.to_string()),
+            ],
+        })
+        .await;
+    }
+
+    /// Add a write to the outbox and wake the writer.
+    async fn enqueue(&self, statement: Statement) {
+        let queued = sqlx::query("INSERT INTO libsql_outbox (statement) VALUES (?)")
+            .bind(statement.to_json().to_string())
+            .execute(&self.pool)
+            .timed("libsql.outbox_insert")
+            .await;
+        if let Err(error) = queued {
+            tracing::warn!(%error, table = %self.table, "failed to queue libsql write");
+            return;
+        }
+        // Full means a wake-up is already pending.
+        let _ = self.wake.try_send(());
+    }
+}
+
+/// Send the outbox in order, whenever it's woken, until every store handle
+/// is dropped. Writes still in the outbox then are sent on the next start.
+async fn run_writer(client: LibsqlClient, pool: SqlitePool, mut wake: mpsc::Receiver<()>) {
+    let mut retry_delay = RETRY_DELAY;
+    loop {
+        match send_outbox_batch(&client, &pool).await {
+            Ok(0) => {
+                if wake.recv().await.is_none() {
+                    break;
+                }
+            }
+            Ok(_) => retry_delay = RETRY_DELAY,
+            Err(error) => {
+                if wake.is_closed() {
+                    break;
+                }
+                tracing::warn!(
+                    %error,
+                    retry_in_secs = retry_delay.as_secs(),
+                    "libsql write failed, keeping it queued"
+                );
+                tokio::time::sleep(retry_delay).await;
+                retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Send the oldest writes in the outbox in one request, and delete them once
+/// the server has them. Returns how many were sent. A transport error leaves
+/// them queued; statements the server rejects are logged and deleted.
+async fn send_outbox_batch(client: &LibsqlClient, pool: &SqlitePool) -> Result<usize> {
+    let rows = sqlx::query("SELECT id, statement FROM libsql_outbox ORDER BY id LIMIT ?")
+        .bind(MAX_BATCH)
+        .fetch_all(pool)
+        .timed("libsql.outbox_batch")
+        .await?;
+    let Some(last) = rows.last() else {
+        return Ok(0);
+    };
+    let last_id: i64 = last.get("id");
+
+    let statements: Vec<Statement> = rows
+        .iter()
+        .filter_map(|row| {
+            let json: String = row.get("statement");
+            let statement = serde_json::from_str(&json)
+                .ok()
+                .and_then(|json| Statement::from_json(&json));
+            if statement.is_none() {
+                tracing::warn!(statement = %json, "dropping unreadable libsql outbox entry");
+            }
+            statement
+        })
+        .collect();
+    let results = client.execute_each(&statements).await?;
+    for (statement, result) in statements.iter().zip(results) {
+        if let Err(error) = result {
+            tracing::warn!(%error, sql = %statement.sql, "libsql rejected a write, dropping it");
+        }
+    }
+
+    sqlx::query("DELETE FROM libsql_outbox WHERE id <= ?")
+        .bind(last_id)
+        .execute(pool)
+        .timed("libsql.outbox_delete")
+        .await?;
+    Ok(rows.len())
+}
+
+/// Create the table if it doesn't exist yet. Returns `true` when it was
+/// created by this call.
+async fn ensure_table(client: &LibsqlClient, table_name: &str, table: &str) -> Result<bool> {
+    let existing = client
+        .execute(&[Statement {
+            sql: "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?".into(),
+            args: vec![Value::Text(table_name.to_string())],
+        }])
+        .await?;
+    if existing
+        .first()
+        .is_some_and(|result| !result.rows.is_empty())
+    {
+        return Ok(false);
+    }
+
+    let index = format!("\"{}_channel_time\"", table_name.replace('"', ""));
+    client
+        .execute(&[
+            Statement {
+                sql: format!(
+                    "CREATE TABLE IF NOT EXISTS {table} (\
+                     id TEXT PRIMARY KEY, \
+                     channel_id TEXT NOT NULL, \
+                     role TEXT NOT NULL, \
+                     sender_name TEXT, \
+                     sender_id TEXT, \
+                     content TEXT NOT NULL, \
+                     metadata TEXT, \
+                     turn_id TEXT, \
+                     created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP)"
+                ),
+                args: Vec::new(),
+            },
+            Statement {
+                sql: format!(
+                    "CREATE INDEX IF NOT EXISTS {index} ON {table}(channel_id, created_at)"
+                ),
+                args: Vec::new(),
+            },
+        ])
+        .await?;
+    tracing::info!(table = %table_name, "created libsql conversation table");
+    Ok(true)
+}
+
+/// Copy every message in SQLite to the remote table.
+async fn seed_remote(client: &LibsqlClient, table: &str, pool: &SqlitePool) -> Result<()> {
+    let mut after = 0_i64;
+    let mut copied = 0;
+    loop {
+        let rows = sqlx::query(
+            "SELECT rowid, id, channel_id, role, sender_name, sender_id, content, metadata, \
+             turn_id, CAST(created_at AS TEXT) AS created_at \
+             FROM conversation_messages WHERE rowid > ? ORDER BY rowid LIMIT ?",
+        )
+        .bind(after)
+        .bind(PAGE_SIZE)
+        .fetch_all(pool)
+        .timed_with("libsql.seed_page", || format!("after={after}"))
+        .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.get("rowid");
+
+        let statements: Vec<Statement> = rows
+            .iter()
+            .map(|row| {
+                let text = |column: &str| -> Value {
+                    row.try_get::<Option<String>, _>(column)
+                        .ok()
+                        .flatten()
+                        .into()
+                };
+                insert_statement(
+                    table,
+                    [
+                        text("id"),
+                        text("channel_id"),
+                        text("role"),
+                        text("sender_name"),
+                        text("sender_id"),
+                        text("content"),
+                        text("metadata"),
+                        text("turn_id"),
+                        text("created_at"),
+                    ],
+                )
+            })
+            .collect();
+        client.execute(&statements).await?;
+        copied += statements.len();
+    }
+
+    if copied > 0 {
+        tracing::info!(
+            count = copied,
+            "seeded libsql conversation table from sqlite"
+        );
+    }
+    Ok(())
+}
+
+/// Copy every message in the remote table to SQLite.
+async fn restore_local(client: &LibsqlClient, table: &str, pool: &SqlitePool) -> Result<()> {
+    let mut after = 0_i64;
+    let mut restored = 0;
+    loop {
+        let result = client
+            .execute(&[Statement {
+                sql: format!(
+                    "SELECT rowid, id, channel_id, role, sender_name, sender_id, content, \
+                     metadata, turn_id, created_at \
+                     FROM {table} WHERE rowid > ? ORDER BY rowid LIMIT ?"
+                ),
+                args: vec![Value::Integer(after), Value::Integer(PAGE_SIZE)],
+            }])
+            .await?
+            .pop()
+            .unwrap_or_default();
+        let Some(last) = result.rows.last() else {
+            break;
+        };
+        after = match last.first() {
+            Some(Value::Integer(rowid)) => *rowid,
+            other => {
+                return Err(DbError::Query(format!("invalid libsql rowid: {other:?}")).into());
+            }
+        };
+
+        let mut transaction = pool.begin().await?;
+        for row in &result.rows {
+            let mut query = sqlx::query(
+                "INSERT OR IGNORE INTO conversation_messages \
+                 (id, channel_id, role, sender_name, sender_id, content, metadata, turn_id, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            );
+            for value in row.iter().skip(1) {
+                query = query.bind(value.as_text());
+            }
+            query.execute(&mut *transaction).await?;
+        }
+        transaction.commit().await?;
+        restored += result.rows.len();
+    }
+
+    if restored > 0 {
+        tracing::info!(
+            count = restored,
+            "restored conversation messages from libsql"
+        );
+    }
+    Ok(())
+}
+
+fn insert_statement(table: &str, args: [Value; 9]) -> Statement {
+    Statement {
+        sql: format!(
+            "INSERT OR IGNORE INTO {table} \
+             (id, channel_id, role, sender_name, sender_id, content, metadata, turn_id, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        ),
+        args: args.into(),
+    }
+}
+
+/// A SQL statement with positional arguments.
+#[derive(Debug, Clone, PartialEq)]
+struct Statement {
+    sql: String,
+    args: Vec<Value>,
+}
+
+impl Statement {
+    /// The protocol's JSON form, also how the outbox stores it.
+    fn to_json(&self) -> serde_json::Value {
+        let args: Vec<serde_json::Value> = self.args.iter().map(Value::to_json).collect();
+        serde_json::json!({ "sql": self.sql, "args": args })
+    }
+
+    fn from_json(value: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            sql: value.get("sql")?.as_str()?.to_string(),
+            args: value
+                .get("args")?
+                .as_array()?
+                .iter()
+                .map(Value::from_json)
+                .collect(),
+        })
+    }
+}
+
+/// A libSQL value. Blobs aren't used by the conversation table.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<Option<String>> for Value {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(Value::Null, Value::Text)
+    }
+}
+
+impl Value {
+    fn as_text(&self) -> Option<String> {
+        match self {
+            Value::Null => None,
+            Value::Integer(value) => Some(value.to_string()),
+            Value::Float(value) => Some(value.to_string()),
+            Value::Text(value) => Some(value.clone()),
+        }
+    }
+
+    /// The protocol's JSON form. Integers travel as strings so they keep
+    /// their 64 bits.
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::json!({ "type": "null" }),
+            Value::Integer(value) => {
+                serde_json::json!({ "type": "integer", "value": value.to_string() })
+            }
+            Value::Float(value) => serde_json::json!({ "type": "float", "value": value }),
+            Value::Text(value) => serde_json::json!({ "type": "text", "value": value }),
+        }
+    }
+
+    fn from_json(value: &serde_json::Value) -> Self {
+        match value.get("type").and_then(|kind| kind.as_str()) {
+            Some("integer") => value
+                .get("value")
+                .and_then(|value| value.as_str())
+                .and_then(|value| value.parse().ok())
+                .map_or(Value::Null, Value::Integer),
+            Some("float") => value
+                .get("value")
+                .and_then(|value| value.as_f64())
+                .map_or(Value::Null, Value::Float),
+            Some("text") => value
+                .get("value")
+                .and_then(|value| value.as_str())
+                .map_or(Value::Null, |value| Value::Text(value.to_string())),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Rows returned by one statement.
+#[derive(Debug, Default)]
+struct QueryResult {
+    rows: Vec<Vec<Value>>,
+}
+
+/// Client for libSQL's HTTP protocol (Hrana over HTTP, v2 pipelines).
+#[derive(Debug, Clone)]
+struct LibsqlClient {
+    http_client: reqwest::Client,
+    pipeline_url: String,
+    auth_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PipelineResponse {
+    results: Vec<PipelineResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum PipelineResult {
+    Ok { response: PipelineStreamResponse },
+    Error { error: PipelineError },
+}
+
+#[derive(Deserialize)]
+struct PipelineStreamResponse {
+    result: Option<PipelineRows>,
+}
+
+#[derive(Deserialize)]
+struct PipelineRows {
+    rows: Vec<Vec<serde_json::Value>>,
+}
+
+#[derive(Deserialize)]
+struct PipelineError {
+    message: String,
+}
+
+impl LibsqlClient {
+    fn new(http_client: reqwest::Client, url: &str, auth_token: Option<String>) -> Self {
+        Self {
+            http_client,
+            pipeline_url: pipeline_url(url),
+            auth_token,
+        }
+    }
+
+    /// Run statements in one request, each on its own, and return their
+    /// rows. Fails if the request or any statement does.
+    async fn execute(&self, statements: &[Statement]) -> Result<Vec<QueryResult>> {
+        self.execute_each(statements)
+            .await?
+            .into_iter()
+            .map(|result| {
+                result.map_err(|error| {
+                    DbError::Query(format!("libsql statement failed: {error}")).into()
+                })
+            })
+            .collect()
+    }
+
+    /// Run statements in one request, each on its own. Fails if the request
+    /// does; each statement's own error is returned in its place.
+    async fn execute_each(
+        &self,
+        statements: &[Statement],
+    ) -> Result<Vec<std::result::Result<QueryResult, String>>> {
+        let mut requests: Vec<serde_json::Value> = statements
+            .iter()
+            .map(|statement| serde_json::json!({ "type": "execute", "stmt": statement.to_json() }))
+            .collect();
+        requests.push(serde_json::json!({ "type": "close" }));
+
+        let mut builder = self
+            .http_client
+            .post(&self.pipeline_url)
+            .json(&serde_json::json!({ "requests": requests }));
+        if let Some(auth_token) = &self.auth_token {
+            builder = builder.bearer_auth(auth_token);
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|error| DbError::Query(format!("libsql request failed: {error}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(DbError::Query(format!("libsql returned {status}: {body}")).into());
+        }
+        let parsed: PipelineResponse = response
+            .json()
+            .await
+            .map_err(|error| DbError::Query(format!("invalid libsql response: {error}")))?;
+
+        Ok(parsed
+            .results
+            .into_iter()
+            .take(statements.len())
+            .map(|result| match result {
+                PipelineResult::Ok { response } => Ok(QueryResult {
+                    rows: response
+                        .result
+                        .map(|result| {
+                            result
+                                .rows
+                                .iter()
+                                .map(|row| row.iter().map(Value::from_json).collect())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                }),
+                PipelineResult::Error { error } => Err(error.message),
+            })
+            .collect())
+    }
+}
+
+/// The pipeline endpoint of a database URL. `libsql://` URLs are served
+/// over HTTPS.
+fn pipeline_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let base = match url.strip_prefix("libsql://") {
+        Some(host) => format!("https://{host}"),
+        None => url.to_string(),
+    };
+    format!("{base}/v2/pipeline")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls_and_values_follow_the_protocol() {
+        assert_eq!(
+            pipeline_url("libsql://spacebot-acme.turso.io/"),
+            "https://spacebot-acme.turso.io/v2/pipeline"
+        );
+        assert_eq!(
+            pipeline_url("http://127.0.0.1:8080"),
+            "http://127.0.0.1:8080/v2/pipeline"
+        );
+
+        let integer = Value::Integer(i64::MAX);
+        assert_eq!(
+            integer.to_json(),
+            serde_json::json!({ "type": "integer", "value": "9223372036854775807" })
+        );
+        assert_eq!(Value::from_json(&integer.to_json()), integer);
+        assert_eq!(
+            Value::from_json(&serde_json::json!({ "type": "text", "value": "hi" })),
+            Value::Text("hi".into())
+        );
+        assert_eq!(
+            Value::from_json(&serde_json::json!({ "type": "blob", "base64": "AA==" })),
+            Value::Null
+        );
+        assert_eq!(Value::from(None::<String>).as_text(), None);
+
+        let response: PipelineResponse = serde_json::from_value(serde_json::json!({
+            "baton": null,
+            "base_url": null,
+            "results": [
+                {
+                    "type": "ok",
+                    "response": {
+                        "type": "execute",
+                        "result": {
+                            "cols": [{ "name": "rowid" }, { "name": "id" }],
+                            "rows": [[
+                                { "type": "integer", "value": "7" },
+                                { "type": "text", "value": "m1" }
+                            ]],
+                            "affected_row_count": 0
+                        }
+                    }
+                },
+                { "type": "error", "error": { "message": "no such table", "code": "SQLITE_ERROR" } },
+                { "type": "ok", "response": { "type": "close" } }
+            ]
+        }))
+        .expect("pipeline response should parse");
+        assert_eq!(response.results.len(), 3);
+        let PipelineResult::Ok { response: first } = &response.results[0] else {
+            panic!("first statement should succeed");
+        };
+        assert_eq!(
+            first.result.as_ref().map(|result| result.rows.len()),
+            Some(1)
+        );
+        let PipelineResult::Error { error } = &response.results[1] else {
+            panic!("second statement should fail");
+        };
+        assert_eq!(error.message, "no such table");
+    }
+
+    #[tokio::test]
+    async fn test_writes_wait_in_the_outbox_until_sent() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let (wake, mut woken) = mpsc::channel(1);
+        let store = LibsqlStore {
+            table: "\"spacebot_main\"".into(),
+            pool: pool.clone(),
+            wake,
+        };
+        store
+            .insert(&ConversationMessage {
+                id: "m1".into(),
+                channel_id: "discord:1:2".into(),
+                role: "user".into(),
+                sender_name: Some("Ada".into()),
+                sender_id: Some("42".into()),
+                content: "hello".into(),
+                metadata: None,
+                turn_id: None,
+                created_at: chrono::Utc::now(),
+            })
+            .await;
+        store
+            .edit_user_message("discord:1:2", "99", "hello again")
+            .await;
+        assert!(woken.try_recv().is_ok());
+
+        let queued = || async {
+            sqlx::query_scalar::<_, String>("SELECT statement FROM libsql_outbox ORDER BY id")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .iter()
+                .map(|json| Statement::from_json(&serde_json::from_str(json).unwrap()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let statements = queued().await;
+        assert_eq!(statements.len(), 2);
+        assert!(
+            statements[0]
+                .sql
+                .starts_with("INSERT OR IGNORE INTO \"spacebot_main\"")
+        );
+        assert_eq!(statements[0].args[0], Value::Text("m1".into()));
+        assert!(statements[1].sql.starts_with("UPDATE"));
+
+        // A server that can't be reached leaves every write queued.
+        let client = LibsqlClient::new(reqwest::Client::new(), "http://127.0.0.1:9", None);
+        assert!(send_outbox_batch(&client, &pool).await.is_err());
+        assert_eq!(queued().await, statements);
+    }
+}
@@ -0,0 +1,230 @@
+//! In-memory cache of the latest messages of each active channel.
+//!
+//! The reply tool reads a channel's recent messages on every reply, and
+//! webchat on every history load. [`RecentMessages`] keeps the last
+//! [`MESSAGES_PER_CHANNEL`] messages of the most recently used channels, so
+//! hot channels are read from memory. A channel is filled from SQLite on its
+//! first read; after that, every message the [`ConversationLogger`] writes is
+//! appended once it's persisted. An edit drops the channel's messages, so its
+//! next read goes back to SQLite.
+//!
+//! [`ConversationLogger`]: crate::conversation::ConversationLogger
+
+use crate::conversation::history::ConversationMessage;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Messages kept per channel.
+pub const MESSAGES_PER_CHANNEL: usize = 100;
+
+/// Channels kept. Past this, the least recently used channel is dropped.
+const MAX_CHANNELS: usize = 200;
+
+/// Recent messages of an agent's channels, shared by all of its channels'
+/// loggers. Messages are kept as stored, with redacted values left as
+/// placeholders.
+#[derive(Debug, Default)]
+pub struct RecentMessages {
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    channels: HashMap<String, CachedChannel>,
+    /// Source of channel generations, so no two changes share one.
+    changes: u64,
+}
+
+#[derive(Debug)]
+struct CachedChannel {
+    /// Oldest first. None until filled from SQLite.
+    messages: Option<VecDeque<ConversationMessage>>,
+    /// Whether `messages` holds every message of the channel, so reads of
+    /// more messages than are cached can still be answered.
+    complete: bool,
+    /// Changed on every write, so a fill that raced one is dropped.
+    generation: u64,
+    last_used: Instant,
+}
+
+impl CachedChannel {
+    fn new() -> Self {
+        Self {
+            messages: None,
+            complete: false,
+            generation: 0,
+            last_used: Instant::now(),
+        }
+    }
+}
+
+impl RecentMessages {
+    /// The latest `limit` messages of a channel, oldest first, if they're all
+    /// cached.
+    pub fn get(&self, channel_id: &str, limit: usize) -> Option<Vec<ConversationMessage>> {
+        let mut state = self.state.lock().expect("recent messages lock poisoned");
+        let channel = state.channels.get_mut(channel_id)?;
+        let messages = channel.messages.as_ref()?;
+        if limit > messages.len() && !channel.complete {
+            return None;
+        }
+        channel.last_used = Instant::now();
+        let skip = messages.len().saturating_sub(limit);
+        Some(messages.iter().skip(skip).cloned().collect())
+    }
+
+    /// The channel's generation. Read it before querying SQLite and pass it
+    /// to [`fill`](Self::fill).
+    pub fn generation(&self, channel_id: &str) -> u64 {
+        let state = self.state.lock().expect("recent messages lock poisoned");
+        state
+            .channels
+            .get(channel_id)
+            .map_or(0, |channel| channel.generation)
+    }
+
+    /// Cache a channel's latest messages, oldest first, as read from SQLite.
+    /// `complete` says the read returned every message of the channel. Dropped
+    /// if the channel was written to since `generation` was read, since the
+    /// read may have missed the write.
+    pub fn fill(
+        &self,
+        channel_id: &str,
+        generation: u64,
+        messages: &[ConversationMessage],
+        complete: bool,
+    ) {
+        let mut state = self.state.lock().expect("recent messages lock poisoned");
+        let channel = state
+            .channels
+            .entry(channel_id.to_string())
+            .or_insert_with(CachedChannel::new);
+        if channel.generation != generation {
+            return;
+        }
+        let skip = messages.len().saturating_sub(MESSAGES_PER_CHANNEL);
+        channel.messages = Some(messages.iter().skip(skip).cloned().collect());
+        channel.complete = complete && skip == 0;
+        channel.last_used = Instant::now();
+        state.evict();
+    }
+
+    /// Append a message once it's persisted. Channels that haven't been
+    /// filled yet only record the write.
+    pub fn push(&self, message: ConversationMessage) {
+        let mut state = self.state.lock().expect("recent messages lock poisoned");
+        state.changes += 1;
+        let generation = state.changes;
+        let channel = state
+            .channels
+            .entry(message.channel_id.clone())
+            .or_insert_with(CachedChannel::new);
+        channel.generation = generation;
+        channel.last_used = Instant::now();
+        if let Some(messages) = &mut channel.messages
+            && !messages.iter().any(|cached| cached.id == message.id)
+        {
+            messages.push_back(message);
+            if messages.len() > MESSAGES_PER_CHANNEL {
+                messages.pop_front();
+                channel.complete = false;
+            }
+        }
+        state.evict();
+    }
+
+    /// Drop a channel's messages after one of them was edited or deleted.
+    pub fn invalidate(&self, channel_id: &str) {
+        let mut state = self.state.lock().expect("recent messages lock poisoned");
+        state.changes += 1;
+        let generation = state.changes;
+        if let Some(channel) = state.channels.get_mut(channel_id) {
+            channel.messages = None;
+            channel.complete = false;
+            channel.generation = generation;
+        }
+    }
+}
+
+impl CacheState {
+    fn evict(&mut self) {
+        while self.channels.len() > MAX_CHANNELS {
+            let Some(oldest) = self
+                .channels
+                .iter()
+                .min_by_key(|(_, channel)| channel.last_used)
+                .map(|(channel_id, _)| channel_id.clone())
+            else {
+                break;
+            };
+            self.channels.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str) -> ConversationMessage {
+        ConversationMessage {
+            id: id.to_string(),
+            channel_id: "discord:1:2".to_string(),
+            role: "user".to_string(),
+            sender_name: Some("ada".to_string()),
+            sender_id: Some("7".to_string()),
+            content: format!("message {id}"),
+            metadata: None,
+            turn_id: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    fn ids(messages: Option<Vec<ConversationMessage>>) -> Option<Vec<String>> {
+        messages.map(|messages| messages.into_iter().map(|message| message.id).collect())
+    }
+
+    #[test]
+    fn test_filled_channels_are_served_appended_and_invalidated() {
+        let cache = RecentMessages::default();
+        assert!(cache.get("discord:1:2", 10).is_none());
+
+        let generation = cache.generation("discord:1:2");
+        cache.fill(
+            "discord:1:2",
+            generation,
+            &[message("1"), message("2")],
+            true,
+        );
+        cache.push(message("3"));
+        // Already filled from SQLite, so not appended twice.
+        cache.push(message("3"));
+        assert_eq!(
+            ids(cache.get("discord:1:2", 2)),
+            Some(vec!["2".into(), "3".into()])
+        );
+        assert_eq!(
+            ids(cache.get("discord:1:2", 50)).map(|ids| ids.len()),
+            Some(3)
+        );
+
+        // A fill that raced a write is dropped.
+        let stale = cache.generation("discord:1:2");
+        cache.invalidate("discord:1:2");
+        assert!(cache.get("discord:1:2", 1).is_none());
+        cache.push(message("4"));
+        cache.fill("discord:1:2", stale, &[message("1")], true);
+        assert!(cache.get("discord:1:2", 1).is_none());
+
+        // Past the per-channel limit, reads of more than is kept miss.
+        let generation = cache.generation("discord:1:2");
+        let many: Vec<_> = (0..MESSAGES_PER_CHANNEL + 5)
+            .map(|index| message(&index.to_string()))
+            .collect();
+        cache.fill("discord:1:2", generation, &many, true);
+        assert!(cache.get("discord:1:2", MESSAGES_PER_CHANNEL).is_some());
+        assert!(cache.get("discord:1:2", MESSAGES_PER_CHANNEL + 1).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+//! PII redaction for persisted conversation content.
+
+use crate::config::RedactionConfig;
+
+use arc_swap::ArcSwap;
+use regex::{Captures, Regex};
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Values remembered, across all scopes, so repeats get the same placeholder.
+/// Past this, new values still get redacted but with an unnumbered
+/// placeholder.
+const MAX_MAP_ENTRIES: usize = 10_000;
+
+static EMAIL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b").expect("hardcoded regex")
+});
+
+static CARD_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").expect("hardcoded regex"));
+
+static PHONE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{1,4}\)[\s.-]?|\b\d{2,4}[\s.-])\d{3,4}[\s.-]\d{3,4}\b|\+\d{8,15}\b",
+    )
+    .expect("hardcoded regex")
+});
+
+static TOKEN_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    let mut patterns = crate::secrets::scan::secret_patterns().to_vec();
+    patterns.extend([
+        // Bearer tokens in pasted headers
+        Regex::new(r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]{16,}=*").expect("hardcoded regex"),
+        // JWTs
+        Regex::new(r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+")
+            .expect("hardcoded regex"),
+    ]);
+    patterns
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PiiKind {
+    Email,
+    Phone,
+    Token,
+    Card,
+}
+
+impl PiiKind {
+    fn label(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "EMAIL",
+            PiiKind::Phone => "PHONE",
+            PiiKind::Token => "TOKEN",
+            PiiKind::Card => "CARD",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RedactionMaps {
+    scopes: HashMap<String, ScopeMap>,
+    /// Values remembered across all scopes.
+    entries: usize,
+}
+
+/// Placeholders handed out in one scope, by a keyed hash of the value, so
+/// the values themselves aren't kept.
+#[derive(Debug, Default)]
+struct ScopeMap {
+    placeholders: HashMap<u64, String>,
+    counts: HashMap<PiiKind, usize>,
+}
+
+/// Replaces emails, phone numbers, tokens, and card numbers with numbered
+/// placeholders before content is written to the database.
+///
+/// Placeholders are numbered per scope, such as a channel, and a value gets
+/// the same one every time it appears in that scope. They're never turned
+/// back into values: stored content, and history loaded from it, keep the
+/// placeholders.
+#[derive(Debug)]
+pub struct Redactor {
+    config: ArcSwap<RedactionConfig>,
+    maps: Mutex<RedactionMaps>,
+    hasher: RandomState,
+}
+
+impl Redactor {
+    pub fn new(config: RedactionConfig) -> Self {
+        Self {
+            config: ArcSwap::from_pointee(config),
+            maps: Mutex::new(RedactionMaps::default()),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn set_config(&self, config: RedactionConfig) {
+        self.config.store(Arc::new(config));
+    }
+
+    pub fn config(&self) -> RedactionConfig {
+        **self.config.load()
+    }
+
+    /// Redact every enabled category, numbering placeholders within
+    /// `scope`. Returns the text unchanged when redaction is off.
+    pub fn redact(&self, scope: &str, text: &str) -> String {
+        let config = **self.config.load();
+        if !config.enabled {
+            return text.to_string();
+        }
+        let mut maps = self.maps.lock().expect("redaction map lock poisoned");
+        let mut map = ScopedMap {
+            maps: &mut maps,
+            scope,
+            hasher: &self.hasher,
+        };
+        let mut text = text.to_string();
+
+        // Tokens first: keys contain digit runs the other patterns would
+        // split. Cards before phones, since spaced card numbers look like
+        // phone numbers.
+        if config.tokens {
+            for pattern in TOKEN_PATTERNS.iter() {
+                text = replace(pattern, &text, |value| {
+                    Some(map.placeholder(PiiKind::Token, value))
+                });
+            }
+        }
+        if config.emails {
+            text = replace(&EMAIL_PATTERN, &text, |value| {
+                Some(map.placeholder(PiiKind::Email, value))
+            });
+        }
+        if config.credit_cards {
+            text = replace(&CARD_PATTERN, &text, |value| {
+                passes_luhn(value).then(|| map.placeholder(PiiKind::Card, value))
+            });
+        }
+        if config.phone_numbers {
+            text = replace(&PHONE_PATTERN, &text, |value| {
+                Some(map.placeholder(PiiKind::Phone, value))
+            });
+        }
+
+        text
+    }
+}
+
+struct ScopedMap<'a> {
+    maps: &'a mut RedactionMaps,
+    scope: &'a str,
+    hasher: &'a RandomState,
+}
+
+impl ScopedMap<'_> {
+    /// The placeholder for a value, reusing the one it got before in this
+    /// scope so a transcript still shows which mentions were the same.
+    fn placeholder(&mut self, kind: PiiKind, value: &str) -> String {
+        let key = self.hasher.hash_one((kind, value));
+        if let Some(placeholder) = self
+            .maps
+            .scopes
+            .get(self.scope)
+            .and_then(|scope| scope.placeholders.get(&key))
+        {
+            return placeholder.clone();
+        }
+        if self.maps.entries >= MAX_MAP_ENTRIES {
+            return format!("[{}]", kind.label());
+        }
+
+        self.maps.entries += 1;
+        let scope = self.maps.scopes.entry(self.scope.to_string()).or_default();
+        let count = scope.counts.entry(kind).or_default();
+        *count += 1;
+        let placeholder = format!("[{}_{count}]", kind.label());
+        scope.placeholders.insert(key, placeholder.clone());
+        placeholder
+    }
+}
+
+/// Replace each match with what `placeholder` returns, or leave it if None.
+fn replace(
+    pattern: &Regex,
+    text: &str,
+    mut placeholder: impl FnMut(&str) -> Option<String>,
+) -> String {
+    pattern
+        .replace_all(text, |captures: &Captures| {
+            let value = &captures[0];
+            placeholder(value).unwrap_or_else(|| value.to_string())
+        })
+        .into_owned()
+}
+
+/// Luhn checksum over the digits in `value`, ignoring separators.
+fn passes_luhn(value: &str) -> bool {
+    let digits: Vec<u32> = value.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, &digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> RedactionConfig {
+        RedactionConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_redacts_each_category_with_stable_placeholders() {
+        let redactor = Redactor::new(enabled());
+        let redacted = redactor.redact(
+            "discord:1:2",
+            "Mail jane@example.com or call +1 555-123-4567. Card 4111 1111 1111 1111, \
+             key sk-abcdefghijklmnopqrstuvwx. Again: jane@example.com",
+        );
+        assert_eq!(
+            redacted,
+            "Mail [EMAIL_1] or call [PHONE_1]. Card [CARD_1], \
+             key [TOKEN_1]. Again: [EMAIL_1]"
+        );
+
+        // Dates, versions, and IDs that aren't cards or phone numbers.
+        let invoice = "Invoice 20240115-0042 paid on 2024-01-15, client v1.2.3";
+        assert_eq!(redactor.redact("discord:1:2", invoice), invoice);
+    }
+
+    #[test]
+    fn test_scopes_and_disabled_categories() {
+        let redactor = Redactor::new(RedactionConfig {
+            phone_numbers: false,
+            ..enabled()
+        });
+        let redacted = redactor.redact("discord:1:2", "bob@example.org, 555-123-4567");
+        assert_eq!(redacted, "[EMAIL_1], 555-123-4567");
+
+        // Numbering starts over in another channel, so placeholders don't
+        // link values across channels.
+        assert_eq!(
+            redactor.redact("discord:1:3", "amy@example.org, bob@example.org"),
+            "[EMAIL_1], [EMAIL_2]"
+        );
+        assert_eq!(
+            redactor.redact("discord:1:2", "bob@example.org"),
+            "[EMAIL_1]"
+        );
+
+        redactor.set_config(RedactionConfig::default());
+        assert_eq!(
+            redactor.redact("discord:1:2", "bob@example.org"),
+            "bob@example.org"
+        );
+    }
+}
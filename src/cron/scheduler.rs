@@ -3,18 +3,19 @@
 //! Each cron job gets its own tokio task that fires on an interval.
 //! When a job fires, it creates a fresh short-lived channel,
 //! runs the job's prompt through the LLM, and delivers the result
-//! to the delivery target via the messaging system.
+//! to the delivery target via the messaging system. Announcement jobs skip
+//! the LLM and post their message as-is.
 
 use crate::agent::channel::Channel;
 use crate::cron::store::CronStore;
 use crate::error::Result;
 use crate::messaging::MessagingManager;
 use crate::{AgentDeps, InboundMessage, MessageContent, OutboundResponse};
-use chrono::Timelike;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone as _, Timelike, Utc};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::time::{Duration, interval};
+use tokio::time::{Duration, interval, interval_at};
 
 /// A cron job definition loaded from the database.
 #[derive(Debug, Clone)]
@@ -26,6 +27,12 @@ pub struct CronJob {
     pub active_hours: Option<(u8, u8)>,
     pub enabled: bool,
     pub consecutive_failures: u32,
+    /// Posted as-is instead of running the prompt.
+    pub message: Option<String>,
+    /// When the job first fires. Later firings keep to its time of day.
+    pub start_at: Option<DateTime<Utc>>,
+    /// Whether the job disables itself after firing once.
+    pub run_once: bool,
 }
 
 /// Where to send cron job results.
@@ -33,7 +40,8 @@ pub struct CronJob {
 pub struct DeliveryTarget {
     /// Messaging adapter name (e.g. "discord").
     pub adapter: String,
-    /// Platform-specific target (e.g. a Discord channel ID).
+    /// Platform-specific target (e.g. a Discord channel ID, or "#standup"
+    /// for a channel by name).
     pub target: String,
 }
 
@@ -69,6 +77,18 @@ pub struct CronConfig {
     pub active_hours: Option<(u8, u8)>,
     #[serde(default = "default_true")]
     pub enabled: bool,
+    /// Text posted as-is on each firing instead of running `prompt`, for
+    /// announcements like reminders and standups.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// When the job first fires: an RFC 3339 timestamp or a local
+    /// "YYYY-MM-DD HH:MM". Without it, the first firing is one interval after
+    /// the job is registered.
+    #[serde(default)]
+    pub start_at: Option<String>,
+    /// Fire once at `start_at`, then disable.
+    #[serde(default)]
+    pub run_once: bool,
 }
 
 fn default_interval() -> u64 {
@@ -79,6 +99,46 @@ fn default_true() -> bool {
     true
 }
 
+/// Parse a job's `start_at`: an RFC 3339 timestamp, or a local date and time
+/// like "2026-03-02 09:30".
+pub fn parse_start_at(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(raw) {
+        return Some(time.with_timezone(&Utc));
+    }
+    [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+    .and_then(|time| Local.from_local_datetime(&time).earliest())
+    .map(|time| time.with_timezone(&Utc))
+}
+
+/// How long until a job with a start time next fires. A one-off job whose
+/// time passed while it wasn't running fires right away; a recurring one
+/// waits for its next slot.
+fn delay_until_next(
+    start_at: DateTime<Utc>,
+    interval_secs: u64,
+    run_once: bool,
+    now: DateTime<Utc>,
+) -> Duration {
+    if start_at > now {
+        return (start_at - now).to_std().unwrap_or_default();
+    }
+    if run_once {
+        return Duration::ZERO;
+    }
+    let interval_secs = interval_secs.max(1);
+    let elapsed = (now - start_at).num_seconds().max(0) as u64;
+    let next_slot = elapsed.div_ceil(interval_secs) * interval_secs;
+    Duration::from_secs(next_slot - elapsed)
+}
+
 /// Context needed to execute a cron job (agent resources + messaging).
 ///
 /// Prompts, identity, browser config, and skills are read from
@@ -119,6 +179,15 @@ impl Scheduler {
 
     /// Register and start a cron job from config.
     pub async fn register(&self, config: CronConfig) -> Result<()> {
+        let start_at = match config.start_at.as_deref() {
+            Some(raw) => Some(parse_start_at(raw).ok_or_else(|| {
+                crate::error::Error::Other(anyhow::anyhow!(
+                    "invalid start_at '{raw}', expected an RFC 3339 timestamp or 'YYYY-MM-DD HH:MM'"
+                ))
+            })?),
+            None => None,
+        };
+
         let delivery_target = DeliveryTarget::parse(&config.delivery_target).unwrap_or_else(|| {
             tracing::warn!(
                 cron_id = %config.id,
@@ -139,6 +208,9 @@ impl Scheduler {
             active_hours: config.active_hours,
             enabled: config.enabled,
             consecutive_failures: 0,
+            message: config.message,
+            start_at,
+            run_once: config.run_once,
         };
 
         {
@@ -162,15 +234,29 @@ impl Scheduler {
         let context = self.context.clone();
 
         let handle = tokio::spawn(async move {
-            // Look up interval before entering the loop
-            let interval_secs = {
+            // Look up the schedule before entering the loop
+            let (interval_secs, first_delay) = {
                 let j = jobs.read().await;
-                j.get(&job_id).map(|j| j.interval_secs).unwrap_or(3600)
+                j.get(&job_id)
+                    .map(|j| {
+                        let first_delay = j.start_at.map(|start_at| {
+                            delay_until_next(start_at, j.interval_secs, j.run_once, Utc::now())
+                        });
+                        (j.interval_secs, first_delay)
+                    })
+                    .unwrap_or((3600, None))
             };
 
-            let mut ticker = interval(Duration::from_secs(interval_secs));
-            // Skip the immediate first tick — jobs should wait for the first interval
-            ticker.tick().await;
+            let period = Duration::from_secs(interval_secs.max(1));
+            let mut ticker = match first_delay {
+                Some(delay) => interval_at(tokio::time::Instant::now() + delay, period),
+                None => {
+                    let mut ticker = interval(period);
+                    // Skip the immediate first tick — jobs should wait for the first interval
+                    ticker.tick().await;
+                    ticker
+                }
+            };
 
             loop {
                 ticker.tick().await;
@@ -190,8 +276,8 @@ impl Scheduler {
                     }
                 };
 
-                // Check active hours window
-                if let Some((start, end)) = job.active_hours {
+                // Check active hours window. One-off jobs fire at their set time.
+                if let Some((start, end)) = job.active_hours.filter(|_| !job.run_once) {
                     let current_hour = chrono::Local::now().hour() as u8;
                     let in_window = if start <= end {
                         current_hour >= start && current_hour < end
@@ -260,6 +346,20 @@ impl Scheduler {
                         }
                     }
                 }
+
+                if job.run_once {
+                    {
+                        let mut j = jobs.write().await;
+                        if let Some(j) = j.get_mut(&job_id) {
+                            j.enabled = false;
+                        }
+                    }
+                    if let Err(error) = context.store.update_enabled(&job_id, false).await {
+                        tracing::error!(%error, "failed to persist one-off cron job disabled state");
+                    }
+                    tracing::info!(cron_id = %job_id, "one-off cron job fired, disabling");
+                    break;
+                }
             }
         });
 
@@ -369,6 +469,17 @@ impl Scheduler {
 /// Execute a single cron job: create a fresh channel, run the prompt, deliver the result.
 #[tracing::instrument(skip(context), fields(cron_id = %job.id, agent_id = %context.deps.agent_id))]
 async fn run_cron_job(job: &CronJob, context: &CronContext) -> Result<()> {
+    if let Some(message) = &job.message {
+        if let Err(error) = context
+            .store
+            .log_execution(&job.id, true, Some(message))
+            .await
+        {
+            tracing::warn!(%error, "failed to log cron execution");
+        }
+        return deliver_result(job, context, message.clone()).await;
+    }
+
     let channel_id: crate::ChannelId = Arc::from(format!("cron:{}", job.id).as_str());
 
     // Create the outbound response channel to collect whatever the channel produces
@@ -462,37 +573,72 @@ async fn run_cron_job(job: &CronJob, context: &CronContext) -> Result<()> {
 
     // Deliver result to target (only if there's something to say)
     if has_result {
-        if let Err(error) = context
-            .messaging_manager
-            .broadcast(
-                &job.delivery_target.adapter,
-                &job.delivery_target.target,
-                OutboundResponse::Text(result_text),
-            )
-            .await
-        {
-            tracing::error!(
-                cron_id = %job.id,
-                target = %job.delivery_target,
-                %error,
-                "failed to deliver cron result"
-            );
-            // Log the delivery failure
-            let _ = context
-                .store
-                .log_execution(&job.id, false, Some(&error.to_string()))
-                .await;
-            return Err(error);
-        }
+        deliver_result(job, context, result_text).await
+    } else {
+        tracing::debug!(cron_id = %job.id, "cron job produced no output, skipping delivery");
+        Ok(())
+    }
+}
 
-        tracing::info!(
+/// Send a job's result or announcement to its delivery target.
+async fn deliver_result(job: &CronJob, context: &CronContext, text: String) -> Result<()> {
+    if let Err(error) = context
+        .messaging_manager
+        .broadcast(
+            &job.delivery_target.adapter,
+            &job.delivery_target.target,
+            OutboundResponse::Text(text),
+        )
+        .await
+    {
+        tracing::error!(
             cron_id = %job.id,
             target = %job.delivery_target,
-            "cron result delivered"
+            %error,
+            "failed to deliver cron result"
         );
-    } else {
-        tracing::debug!(cron_id = %job.id, "cron job produced no output, skipping delivery");
+        // Log the delivery failure
+        let _ = context
+            .store
+            .log_execution(&job.id, false, Some(&error.to_string()))
+            .await;
+        return Err(error);
     }
 
+    tracing::info!(
+        cron_id = %job.id,
+        target = %job.delivery_target,
+        "cron result delivered"
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_at_sets_first_firing_and_keeps_time_of_day() {
+        let start_at = parse_start_at("2026-03-02T09:30:00Z").unwrap();
+        let before = parse_start_at("2026-03-02T09:00:00Z").unwrap();
+        let after = parse_start_at("2026-03-04T10:00:00Z").unwrap();
+
+        assert_eq!(
+            delay_until_next(start_at, 86400, false, before),
+            Duration::from_secs(30 * 60)
+        );
+        // A daily job started before a restart keeps its 09:30 slot.
+        assert_eq!(
+            delay_until_next(start_at, 86400, false, after),
+            Duration::from_secs(23 * 3600 + 30 * 60)
+        );
+        // A missed one-off fires right away.
+        assert_eq!(
+            delay_until_next(start_at, 86400, true, after),
+            Duration::ZERO
+        );
+
+        assert!(parse_start_at("2026-03-02 09:30").is_some());
+        assert!(parse_start_at("tomorrow morning").is_none());
+    }
+}
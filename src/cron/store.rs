@@ -1,6 +1,7 @@
 //! Cron job CRUD storage (SQLite).
 
 use crate::cron::scheduler::CronConfig;
+use crate::db::TimedQuery as _;
 use crate::error::Result;
 use anyhow::Context as _;
 use sqlx::SqlitePool;
@@ -9,12 +10,24 @@ use sqlx::SqlitePool;
 #[derive(Debug)]
 pub struct CronStore {
     pool: SqlitePool,
+    /// Pool for `load_*` queries. The write pool unless set with
+    /// [`with_readers`](Self::with_readers).
+    readers: SqlitePool,
 }
 
 impl CronStore {
     /// Create a new cron store.
     pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run `load_*` queries on the database's read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
     }
 
     /// Save a cron job configuration.
@@ -24,15 +37,18 @@ impl CronStore {
 
         sqlx::query(
             r#"
-            INSERT INTO cron_jobs (id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO cron_jobs (id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, message, start_at, run_once)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(id) DO UPDATE SET
                 prompt = excluded.prompt,
                 interval_secs = excluded.interval_secs,
                 delivery_target = excluded.delivery_target,
                 active_start_hour = excluded.active_start_hour,
                 active_end_hour = excluded.active_end_hour,
-                enabled = excluded.enabled
+                enabled = excluded.enabled,
+                message = excluded.message,
+                start_at = excluded.start_at,
+                run_once = excluded.run_once
             "#
         )
         .bind(&config.id)
@@ -42,7 +58,11 @@ impl CronStore {
         .bind(active_start)
         .bind(active_end)
         .bind(config.enabled as i64)
+        .bind(&config.message)
+        .bind(&config.start_at)
+        .bind(config.run_once as i64)
         .execute(&self.pool)
+        .timed_with("cron_jobs.save", || format!("id={}", config.id))
         .await
         .context("failed to save cron job")?;
 
@@ -53,13 +73,14 @@ impl CronStore {
     pub async fn load_all(&self) -> Result<Vec<CronConfig>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled
+            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, message, start_at, run_once
             FROM cron_jobs
             WHERE enabled = 1
             ORDER BY created_at ASC
             "#
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed("cron_jobs.load_enabled")
         .await
         .context("failed to load cron jobs")?;
 
@@ -79,6 +100,9 @@ impl CronStore {
                     }
                 },
                 enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
+                message: row.try_get("message").ok().flatten(),
+                start_at: row.try_get("start_at").ok().flatten(),
+                run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
             })
             .collect();
 
@@ -90,6 +114,7 @@ impl CronStore {
         sqlx::query("DELETE FROM cron_jobs WHERE id = ?")
             .bind(id)
             .execute(&self.pool)
+            .timed_with("cron_jobs.delete", || format!("id={id}"))
             .await
             .context("failed to delete cron job")?;
 
@@ -102,6 +127,9 @@ impl CronStore {
             .bind(enabled as i64)
             .bind(id)
             .execute(&self.pool)
+            .timed_with("cron_jobs.set_enabled", || {
+                format!("id={id} enabled={enabled}")
+            })
             .await
             .context("failed to update cron job enabled state")?;
 
@@ -128,6 +156,7 @@ impl CronStore {
         .bind(success as i64)
         .bind(result_summary)
         .execute(&self.pool)
+        .timed_with("cron_executions.insert", || format!("cron_id={cron_id}"))
         .await
         .context("failed to log cron execution")?;
 
@@ -138,12 +167,13 @@ impl CronStore {
     pub async fn load_all_unfiltered(&self) -> Result<Vec<CronConfig>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled
+            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, message, start_at, run_once
             FROM cron_jobs
             ORDER BY created_at ASC
             "#,
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed("cron_jobs.load_all")
         .await
         .context("failed to load cron jobs")?;
 
@@ -163,6 +193,9 @@ impl CronStore {
                     }
                 },
                 enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
+                message: row.try_get("message").ok().flatten(),
+                start_at: row.try_get("start_at").ok().flatten(),
+                run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
             })
             .collect();
 
@@ -186,7 +219,10 @@ impl CronStore {
         )
         .bind(cron_id)
         .bind(limit)
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed_with("cron_executions.load", || {
+            format!("cron_id={cron_id} limit={limit}")
+        })
         .await
         .context("failed to load cron executions")?;
 
@@ -214,7 +250,8 @@ impl CronStore {
             "#,
         )
         .bind(limit)
-        .fetch_all(&self.pool)
+        .fetch_all(&self.readers)
+        .timed_with("cron_executions.load_all", || format!("limit={limit}"))
         .await
         .context("failed to load cron executions")?;
 
@@ -245,6 +282,7 @@ impl CronStore {
         )
         .bind(cron_id)
         .fetch_optional(&self.pool)
+        .timed_with("cron_executions.stats", || format!("cron_id={cron_id}"))
         .await
         .context("failed to load cron execution stats")?;
 
@@ -5,6 +5,7 @@ use crate::config::{Config, TelemetryConfig};
 use anyhow::{Context as _, anyhow};
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry_otlp::WithHttpConfig;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
 use opentelemetry_sdk::trace::SdkTracerProvider;
 use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
@@ -12,10 +13,16 @@ use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::watch;
 use tracing_subscriber::layer::SubscriberExt as _;
 use tracing_subscriber::util::SubscriberInitExt as _;
+use tracing_subscriber::{EnvFilter, Registry, reload};
 
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use std::time::Instant;
 
+/// Swaps the log filter of the subscriber installed by the `init_*_tracing`
+/// functions.
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
 /// Commands sent from CLI client to the running daemon.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "command", rename_all = "snake_case")]
@@ -116,16 +123,38 @@ pub fn daemonize(paths: &DaemonPaths) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The OTLP trace and metric providers.
+///
+/// The caller must hold onto them for the process lifetime and call
+/// `.shutdown()` before exit so buffered spans and metrics are flushed.
+pub struct OtelProviders {
+    pub tracer: SdkTracerProvider,
+    /// None when `telemetry.metrics_interval_secs` is 0.
+    pub meter: Option<SdkMeterProvider>,
+}
+
+impl OtelProviders {
+    /// Flush and stop both exporters.
+    pub fn shutdown(&self) {
+        if let Err(error) = self.tracer.shutdown() {
+            tracing::warn!(%error, "failed to flush OTel spans on shutdown");
+        }
+        if let Some(meter) = &self.meter
+            && let Err(error) = meter.shutdown()
+        {
+            tracing::warn!(%error, "failed to flush OTel metrics on shutdown");
+        }
+    }
+}
+
 /// Initialize tracing for background (daemon) mode.
 ///
-/// Returns an `SdkTracerProvider` if OTLP export is configured. The caller must
-/// hold onto it for the process lifetime and call `.shutdown()` before exit so
-/// the batch exporter flushes buffered spans.
+/// Returns the OTLP providers if OTLP export is configured.
 pub fn init_background_tracing(
     paths: &DaemonPaths,
     debug: bool,
     telemetry: &TelemetryConfig,
-) -> Option<SdkTracerProvider> {
+) -> Option<OtelProviders> {
     let file_appender = tracing_appender::rolling::daily(&paths.log_dir, "spacebot.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -133,25 +162,27 @@ pub fn init_background_tracing(
     // The process owns this — it's cleaned up on exit.
     std::mem::forget(_guard);
 
-    let filter = build_env_filter(debug);
+    let filter = build_env_filter(debug, telemetry.log_level.as_deref());
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_writer(non_blocking)
         .with_ansi(false);
 
-    match build_otlp_provider(telemetry) {
-        Some(provider) => {
-            let tracer = provider.tracer("spacebot");
+    match build_otlp_providers(telemetry) {
+        Some(providers) => {
+            let tracer = providers.tracer.tracer("spacebot");
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::error_reporting::layer())
                 .with(tracing_opentelemetry::layer().with_tracer(tracer))
                 .init();
-            Some(provider)
+            Some(providers)
         }
         None => {
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::error_reporting::layer())
                 .init();
             None
         }
@@ -160,62 +191,88 @@ pub fn init_background_tracing(
 
 /// Initialize tracing for foreground (terminal) mode.
 ///
-/// Returns an `SdkTracerProvider` if OTLP export is configured.
-pub fn init_foreground_tracing(
-    debug: bool,
-    telemetry: &TelemetryConfig,
-) -> Option<SdkTracerProvider> {
-    let filter = build_env_filter(debug);
-    let fmt_layer = tracing_subscriber::fmt::layer();
-
-    match build_otlp_provider(telemetry) {
-        Some(provider) => {
-            let tracer = provider.tracer("spacebot");
+/// Logs go to stderr, leaving stdout to the stdio adapter.
+/// Returns the OTLP providers if OTLP export is configured.
+pub fn init_foreground_tracing(debug: bool, telemetry: &TelemetryConfig) -> Option<OtelProviders> {
+    let filter = build_env_filter(debug, telemetry.log_level.as_deref());
+    let fmt_layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+
+    match build_otlp_providers(telemetry) {
+        Some(providers) => {
+            let tracer = providers.tracer.tracer("spacebot");
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::error_reporting::layer())
                 .with(tracing_opentelemetry::layer().with_tracer(tracer))
                 .init();
-            Some(provider)
+            Some(providers)
         }
         None => {
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::error_reporting::layer())
                 .init();
             None
         }
     }
 }
 
-fn build_env_filter(debug: bool) -> tracing_subscriber::EnvFilter {
-    if debug {
-        tracing_subscriber::EnvFilter::new("debug")
+/// The log filter, wrapped so [`set_log_filter`] can replace it later.
+fn build_env_filter(debug: bool, log_level: Option<&str>) -> reload::Layer<EnvFilter, Registry> {
+    let filter = if debug {
+        EnvFilter::new("debug")
     } else {
-        tracing_subscriber::EnvFilter::new("info")
-    }
+        EnvFilter::new(log_level.unwrap_or("info"))
+    };
+    let (layer, handle) = reload::Layer::new(filter);
+    let _ = LOG_FILTER.set(handle);
+    layer
+}
+
+/// The directives of the running log filter. None if tracing wasn't
+/// initialized here.
+pub fn log_filter() -> Option<String> {
+    LOG_FILTER
+        .get()?
+        .with_current(|filter| filter.to_string())
+        .ok()
 }
 
-/// Build an OTLP `SdkTracerProvider` when an endpoint is configured.
+/// Replace the running log filter, in `RUST_LOG` syntax like
+/// `info,spacebot::conversation::history=debug`. Applies to the next event
+/// logged; a restart goes back to `[telemetry] log_level`.
+pub fn set_log_filter(directives: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(directives)
+        .with_context(|| format!("invalid log filter '{directives}'"))?;
+    LOG_FILTER
+        .get()
+        .context("tracing wasn't initialized with a reloadable filter")?
+        .reload(filter)
+        .context("failed to replace the log filter")
+}
+
+/// Build the OTLP providers when an endpoint is configured, installing the
+/// meter provider globally for `crate::otel::Metrics`.
 ///
 /// Returns `None` if neither the config field nor the `OTEL_EXPORTER_OTLP_ENDPOINT`
 /// environment variable is set, allowing the OTel layer to be omitted entirely.
-fn build_otlp_provider(telemetry: &TelemetryConfig) -> Option<SdkTracerProvider> {
+fn build_otlp_providers(telemetry: &TelemetryConfig) -> Option<OtelProviders> {
     use opentelemetry_otlp::WithExportConfig as _;
 
     let endpoint = telemetry.otlp_endpoint.as_deref()?;
 
-    // The HTTP/protobuf endpoint path is /v1/traces by default. Append it only
-    // when the caller provided a bare host:port so both forms work.
-    let endpoint = if endpoint.ends_with("/v1/traces") {
-        endpoint.to_owned()
-    } else {
-        format!("{}/v1/traces", endpoint.trim_end_matches('/'))
-    };
+    // The HTTP/protobuf endpoint paths are /v1/traces and /v1/metrics. Accept
+    // either a bare host:port or the traces URL so both forms work.
+    let base = endpoint
+        .strip_suffix("/v1/traces")
+        .unwrap_or(endpoint)
+        .trim_end_matches('/');
 
     let mut exporter_builder = opentelemetry_otlp::SpanExporter::builder()
         .with_http()
-        .with_endpoint(endpoint);
+        .with_endpoint(format!("{base}/v1/traces"));
     if !telemetry.otlp_headers.is_empty() {
         exporter_builder = exporter_builder.with_headers(telemetry.otlp_headers.clone());
     }
@@ -249,13 +306,60 @@ fn build_otlp_provider(telemetry: &TelemetryConfig) -> Option<SdkTracerProvider>
         )
         .build();
 
-    let provider = SdkTracerProvider::builder()
+    let tracer = SdkTracerProvider::builder()
         .with_span_processor(batch_processor)
-        .with_resource(resource)
+        .with_resource(resource.clone())
         .with_sampler(sampler)
         .build();
 
-    Some(provider)
+    let meter = build_otlp_meter_provider(telemetry, base, resource);
+    if let Some(meter) = &meter {
+        opentelemetry::global::set_meter_provider(meter.clone());
+    }
+
+    Some(OtelProviders { tracer, meter })
+}
+
+/// Build the OTLP metrics provider, exporting every
+/// `telemetry.metrics_interval_secs`. Like the span processor, the reader
+/// runs on the Tokio runtime.
+fn build_otlp_meter_provider(
+    telemetry: &TelemetryConfig,
+    base: &str,
+    resource: opentelemetry_sdk::Resource,
+) -> Option<SdkMeterProvider> {
+    use opentelemetry_otlp::WithExportConfig as _;
+
+    if telemetry.metrics_interval_secs == 0 {
+        return None;
+    }
+    let mut exporter_builder = opentelemetry_otlp::MetricExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{base}/v1/metrics"));
+    if !telemetry.otlp_headers.is_empty() {
+        exporter_builder = exporter_builder.with_headers(telemetry.otlp_headers.clone());
+    }
+    let exporter = exporter_builder
+        .build()
+        .map_err(|error| eprintln!("failed to build OTLP metric exporter: {error}"))
+        .ok()?;
+
+    let reader =
+        opentelemetry_sdk::metrics::periodic_reader_with_async_runtime::PeriodicReader::builder(
+            exporter,
+            opentelemetry_sdk::runtime::Tokio,
+        )
+        .with_interval(std::time::Duration::from_secs(
+            telemetry.metrics_interval_secs,
+        ))
+        .build();
+
+    Some(
+        SdkMeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(resource)
+            .build(),
+    )
 }
 
 /// Start the IPC server. Returns a shutdown receiver that the main event
@@ -1,15 +1,50 @@
-//! Database connection management and migrations.
+//! Database connection management and migrations, and slow query logging.
+//!
+//! Each agent's SQLite database has two pools: a single-connection write
+//! pool, which every write goes through, and a pool of read-only
+//! connections. With the database in WAL mode, readers don't block the
+//! writer or each other. Stores take the read pool through their
+//! `with_readers` builders, and the agent's deps and the API carry it as
+//! `sqlite_read`, so `load_*` queries, which read whole transcripts and
+//! timelines, and the analytics scans never hold up a write.
+//!
+//! Queries are awaited through [`TimedQuery::timed`], with a label naming
+//! the query. One that takes longer than `[telemetry] slow_query_ms` is
+//! logged with its label and a summary of its bound values, and counted in
+//! the `slow_queries` metrics.
 
 use crate::error::{DbError, Result};
 use anyhow::Context as _;
 use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use std::future::Future;
 use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+
+/// Slow query threshold in milliseconds. 0 turns slow query logging off.
+static SLOW_QUERY_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_QUERY_MS);
+
+/// Default for `[telemetry] slow_query_ms`.
+pub const DEFAULT_SLOW_QUERY_MS: u64 = 250;
+
+/// Read-only connections per database.
+const READ_POOL_SIZE: u32 = 4;
+
+/// How long a connection waits for a lock before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Database connections bundle.
 pub struct Db {
-    /// SQLite pool for relational data.
+    /// SQLite write pool for relational data. Plain reads can use it too;
+    /// reads that can take a while should use [`Db::sqlite_read`].
     pub sqlite: SqlitePool,
 
+    /// Read-only SQLite pool on the same database.
+    pub sqlite_read: SqlitePool,
+
     /// LanceDB connection for vector storage.
     pub lance: lancedb::Connection,
 
@@ -22,9 +57,17 @@ use std::sync::Arc;
 impl Db {
     /// Connect to all databases and run migrations.
     pub async fn connect(data_dir: &Path) -> Result<Self> {
-        // SQLite
-        let sqlite_url = format!("sqlite:{}?mode=rwc", data_dir.join("spacebot.db").display());
-        let sqlite = SqlitePool::connect(&sqlite_url)
+        // SQLite: the write pool first, so the file exists, is migrated, and
+        // is in WAL mode before any reader opens it.
+        let sqlite_path = data_dir.join("spacebot.db");
+        let options = SqliteConnectOptions::new()
+            .filename(&sqlite_path)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT);
+        let sqlite = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone().create_if_missing(true))
             .await
             .with_context(|| "failed to connect to SQLite")?;
 
@@ -34,6 +77,12 @@ impl Db {
             .await
             .with_context(|| "failed to run database migrations")?;
 
+        let sqlite_read = SqlitePoolOptions::new()
+            .max_connections(READ_POOL_SIZE)
+            .connect_with(options.read_only(true))
+            .await
+            .with_context(|| "failed to open SQLite read pool")?;
+
         // LanceDB
         let lance_path = data_dir.join("lancedb");
         std::fs::create_dir_all(&lance_path).with_context(|| {
@@ -55,6 +104,7 @@ impl Db {
 
         Ok(Self {
             sqlite,
+            sqlite_read,
             lance,
             redb: Arc::new(redb),
         })
@@ -62,7 +112,176 @@ impl Db {
 
     /// Close all database connections gracefully.
     pub async fn close(self) {
+        self.sqlite_read.close().await;
         self.sqlite.close().await;
         // LanceDB and redb close automatically when dropped
     }
 }
+
+/// Set the slow query threshold, from `[telemetry] slow_query_ms`.
+pub fn set_slow_query_threshold(milliseconds: u64) {
+    SLOW_QUERY_MS.store(milliseconds, Ordering::Relaxed);
+}
+
+/// The slow query threshold in milliseconds, from `[telemetry]
+/// slow_query_ms`.
+pub fn slow_query_threshold() -> u64 {
+    SLOW_QUERY_MS.load(Ordering::Relaxed)
+}
+
+/// Log and count a query that took longer than `threshold` milliseconds. 0
+/// turns logging off. `binds` is only called for a slow query.
+pub fn record_if_slow(
+    query: impl std::fmt::Display,
+    binds: impl FnOnce() -> String,
+    elapsed: Duration,
+    threshold: u64,
+) {
+    if threshold == 0 || elapsed < Duration::from_millis(threshold) {
+        return;
+    }
+
+    let query = query.to_string();
+    tracing::warn!(
+        query = %query,
+        binds = %binds(),
+        elapsed_ms = elapsed.as_millis() as u64,
+        threshold_ms = threshold,
+        "slow query"
+    );
+    crate::otel::Metrics::global()
+        .db_slow_queries
+        .add(1, &[opentelemetry::KeyValue::new("query", query.clone())]);
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .db_slow_queries_total
+        .with_label_values(&[query.as_str()])
+        .inc();
+}
+
+/// Time a query future, for slow query logging.
+pub trait TimedQuery: Future + Sized {
+    /// Await this query under `label`, like `channels.list_active`.
+    fn timed(self, label: &'static str) -> Timed<Self, fn() -> String> {
+        self.timed_with(label, String::new as fn() -> String)
+    }
+
+    /// Like [`timed`](Self::timed), with a summary of the bound values to log
+    /// if the query is slow, like `channel_id=discord:1:2 limit=50`.
+    fn timed_with<B>(self, label: &'static str, binds: B) -> Timed<Self, B>
+    where
+        B: FnOnce() -> String,
+    {
+        Timed {
+            query: self,
+            label,
+            binds: Some(binds),
+            threshold: slow_query_threshold(),
+            started: None,
+        }
+    }
+}
+
+impl<F: Future> TimedQuery for F {}
+
+/// A query future being timed. See [`TimedQuery`].
+#[pin_project::pin_project]
+pub struct Timed<F, B> {
+    #[pin]
+    query: F,
+    label: &'static str,
+    binds: Option<B>,
+    threshold: u64,
+    /// Set on first poll, so time spent before the query is awaited doesn't
+    /// count.
+    started: Option<Instant>,
+}
+
+impl<F, B> Timed<F, B> {
+    /// Use `milliseconds` as the slow query threshold instead of
+    /// `[telemetry] slow_query_ms`.
+    pub fn threshold(mut self, milliseconds: u64) -> Self {
+        self.threshold = milliseconds;
+        self
+    }
+}
+
+impl<F, B> Future for Timed<F, B>
+where
+    F: Future,
+    B: FnOnce() -> String,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        let started = *this.started.get_or_insert_with(Instant::now);
+        let output = ready!(this.query.poll(context));
+        if let Some(binds) = this.binds.take() {
+            record_if_slow(*this.label, binds, started.elapsed(), *this.threshold);
+        }
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn test_reads_go_through_the_read_pool_and_see_committed_writes() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let db = Db::connect(data_dir.path()).await.unwrap();
+        let read_pool = db.sqlite_read.clone();
+        assert_eq!(read_pool.options().get_max_connections(), READ_POOL_SIZE);
+
+        sqlx::query("INSERT INTO channels (id, platform) VALUES ('discord:1:2', 'discord')")
+            .execute(&db.sqlite)
+            .await
+            .unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM channels")
+            .fetch_one(&read_pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!(
+            sqlx::query("DELETE FROM channels")
+                .execute(&read_pool)
+                .await
+                .is_err()
+        );
+
+        let pool = db.sqlite.clone();
+        db.close().await;
+        assert!(read_pool.is_closed());
+        assert!(pool.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_binds_are_summarized_only_for_slow_queries() {
+        let summarized = Cell::new(0);
+        let binds = || {
+            summarized.set(summarized.get() + 1);
+            "id=1".to_string()
+        };
+
+        let fast = async { 1 }
+            .timed_with("test.fast", binds)
+            .threshold(20)
+            .await;
+        assert_eq!(fast, 1);
+        assert_eq!(summarized.get(), 0);
+
+        let slow = async {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            2
+        }
+        .timed_with("test.slow", binds)
+        .threshold(20)
+        .await;
+        assert_eq!(slow, 2);
+        assert_eq!(summarized.get(), 1);
+    }
+}
@@ -176,6 +176,9 @@ pub enum SecretsError {
     #[error("secret not found: {key}")]
     NotFound { key: String },
 
+    #[error("failed to fetch secret {reference}: {details}")]
+    FetchFailed { reference: String, details: String },
+
     #[error("invalid key format")]
     InvalidKey,
 
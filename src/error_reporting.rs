@@ -0,0 +1,273 @@
+//! Error reporting to Sentry, or a Sentry-compatible service like GlitchTip.
+//!
+//! Built with the `sentry` feature and `[error_reporting] dsn` set, panics
+//! and `error`-level log events are reported. Each report is tagged with the
+//! `agent_id`, `channel_id`, and `turn_id` of the spans it happened in, so it
+//! can be matched to the turn's logs and rows. Without the feature a DSN is
+//! accepted but nothing is sent.
+
+use crate::config::ErrorReportingConfig;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use std::collections::BTreeMap;
+#[cfg(feature = "sentry")]
+use std::time::Duration;
+
+/// Span and event fields that become tags on a report. Everything else on
+/// the event goes into its extra data.
+const TAG_FIELDS: &[&str] = &[
+    "agent_id",
+    "channel_id",
+    "turn_id",
+    "worker_id",
+    "branch_id",
+    "tool_name",
+    "model",
+];
+
+/// How long to wait for queued reports to be sent on shutdown.
+#[cfg(feature = "sentry")]
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Keeps the reporting client alive. Hold it for the life of the process.
+pub struct ErrorReporting {
+    #[cfg(feature = "sentry")]
+    _guard: sentry::ClientInitGuard,
+}
+
+impl ErrorReporting {
+    /// Send reports still queued. Shutdown ends in `std::process::exit`,
+    /// which skips the flush the client would do when dropped.
+    #[cfg(feature = "sentry")]
+    pub fn flush(&self) {
+        if let Some(client) = sentry::Hub::current().client() {
+            client.flush(Some(FLUSH_TIMEOUT));
+        }
+    }
+
+    #[cfg(not(feature = "sentry"))]
+    pub fn flush(&self) {}
+}
+
+/// Start the reporting client and report panics, if a DSN is configured.
+/// Call before tracing is initialized, so [`layer`] finds the client.
+#[cfg(feature = "sentry")]
+pub fn init(config: &ErrorReportingConfig) -> Option<ErrorReporting> {
+    let dsn = match config.dsn.as_deref()?.parse::<sentry::types::Dsn>() {
+        Ok(dsn) => dsn,
+        Err(error) => {
+            eprintln!("invalid error_reporting.dsn, errors won't be reported: {error}");
+            return None;
+        }
+    };
+    let guard = sentry::init(sentry::ClientOptions {
+        dsn: Some(dsn),
+        release: sentry::release_name!(),
+        environment: config.environment.clone().map(Into::into),
+        sample_rate: config.sample_rate as f32,
+        attach_stacktrace: true,
+        ..Default::default()
+    });
+
+    // Panics are logged as errors rather than captured directly, so they're
+    // reported through the layer with the context of the span they hit.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!(target: "panic", "{info}");
+        default_hook(info);
+    }));
+
+    Some(ErrorReporting { _guard: guard })
+}
+
+#[cfg(not(feature = "sentry"))]
+pub fn init(config: &ErrorReportingConfig) -> Option<ErrorReporting> {
+    if config.dsn.is_some() {
+        eprintln!(
+            "error_reporting.dsn is set, but this build doesn't include the `sentry` feature; \
+             errors won't be reported"
+        );
+    }
+    None
+}
+
+/// The tracing layer that turns error events into reports. None if
+/// reporting isn't running.
+pub fn layer() -> Option<ReportLayer> {
+    #[cfg(feature = "sentry")]
+    if sentry::Hub::current()
+        .client()
+        .is_some_and(|client| client.is_enabled())
+    {
+        return Some(ReportLayer { send: capture });
+    }
+    None
+}
+
+/// An error event with the context it was logged in.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub message: String,
+    /// Module path the event was logged from.
+    pub target: String,
+    pub tags: BTreeMap<String, String>,
+    pub extra: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "sentry")]
+fn capture(report: Report) {
+    sentry::capture_event(sentry::protocol::Event {
+        level: sentry::Level::Error,
+        message: Some(report.message),
+        logger: Some(report.target),
+        tags: report.tags.into_iter().collect(),
+        extra: report
+            .extra
+            .into_iter()
+            .map(|(name, value)| (name, value.into()))
+            .collect(),
+        ..Default::default()
+    });
+}
+
+/// Reports `error` events. Tag fields are collected from every span as it's
+/// created or recorded, since ids like `turn_id` are often filled in after
+/// the span starts.
+pub struct ReportLayer {
+    send: fn(Report),
+}
+
+impl<S> Layer<S> for ReportLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        if let Some(span) = context.span(id)
+            && !fields.tags.is_empty()
+        {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, context: Context<'_, S>) {
+        let Some(span) = context.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        match extensions.get_mut::<Fields>() {
+            Some(fields) => values.record(fields),
+            None => {
+                let mut fields = Fields::default();
+                values.record(&mut fields);
+                if !fields.tags.is_empty() {
+                    extensions.insert(fields);
+                }
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+
+        let mut tags = BTreeMap::new();
+        if let Some(scope) = context.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<Fields>() {
+                    tags.extend(fields.tags.clone());
+                }
+            }
+        }
+        let mut fields = Fields {
+            tags,
+            ..Default::default()
+        };
+        event.record(&mut fields);
+
+        (self.send)(Report {
+            message: fields.message.unwrap_or_default(),
+            target: event.metadata().target().to_string(),
+            tags: fields.tags,
+            extra: fields.extra,
+        });
+    }
+}
+
+/// Field values of a span or event, sorted into a report's parts.
+#[derive(Debug, Default)]
+struct Fields {
+    message: Option<String>,
+    tags: BTreeMap<String, String>,
+    extra: BTreeMap<String, String>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{value:?}"));
+    }
+}
+
+impl Fields {
+    fn record(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = Some(value),
+            name if TAG_FIELDS.contains(&name) => {
+                self.tags.insert(name.to_string(), value);
+            }
+            name => {
+                self.extra.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use std::sync::Mutex;
+
+    static SENT: Mutex<Vec<Report>> = Mutex::new(Vec::new());
+
+    #[test]
+    fn test_error_events_are_reported_with_their_turn_context() {
+        let subscriber = tracing_subscriber::registry().with(ReportLayer {
+            send: |report| SENT.lock().unwrap().push(report),
+        });
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "handle_message",
+                agent_id = "main",
+                channel_id = "discord:1:2",
+                turn_id = tracing::field::Empty
+            );
+            let _entered = span.enter();
+            span.record("turn_id", "turn-1");
+
+            tracing::warn!("not reported");
+            tracing::error!(error = %"connection reset", "failed to persist message");
+        });
+
+        let sent = SENT.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        let report = &sent[0];
+        assert_eq!(report.message, "failed to persist message");
+        assert_eq!(report.tags["agent_id"], "main");
+        assert_eq!(report.tags["channel_id"], "discord:1:2");
+        assert_eq!(report.tags["turn_id"], "turn-1");
+        assert_eq!(report.extra["error"], "connection reset");
+    }
+}
@@ -0,0 +1,93 @@
+//! Process-wide event bus.
+//!
+//! Subsystems [`publish`] what happened without knowing who's listening, and
+//! the ones that react, like the API's event stream, metrics, and alerting,
+//! [`subscribe`] and pick out the events they care about. Publishing never
+//! blocks. A subscriber that falls behind skips the oldest events and is
+//! told how many it missed.
+
+use crate::{AgentId, ChannelId};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use std::sync::LazyLock;
+
+/// Events held for each subscriber before the slowest starts skipping.
+const CAPACITY: usize = 1024;
+
+static BUS: LazyLock<broadcast::Sender<BusEvent>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// Something that happened, as published on the bus.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BusEvent {
+    /// A conversation message was written to `conversation_messages`.
+    MessagePersisted {
+        channel_id: String,
+        message_id: String,
+        /// `user` or `assistant`.
+        role: &'static str,
+        turn_id: Option<String>,
+    },
+    /// A channel's history was compacted or truncated.
+    CompactionCompleted {
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        /// `background`, `aggressive`, or `emergency`.
+        action: &'static str,
+        messages_compacted: usize,
+    },
+    /// A messaging adapter connected or disconnected.
+    LinkChanged { adapter: String, linked: bool },
+    /// One of an agent's LLM calls failed.
+    AgentError { agent_id: String, error: String },
+    /// A tool call finished.
+    ToolExecuted {
+        agent_id: AgentId,
+        channel_id: Option<ChannelId>,
+        turn_id: Option<String>,
+        tool_name: String,
+        /// None if the start of the call wasn't seen.
+        duration_ms: Option<u64>,
+    },
+}
+
+/// Publish an event to everything subscribed.
+pub fn publish(event: BusEvent) {
+    // An error only means nothing is subscribed.
+    let _ = BUS.send(event);
+}
+
+/// Receive the events published from now on.
+pub fn subscribe() -> broadcast::Receiver<BusEvent> {
+    BUS.subscribe()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_events_published_after_subscribing() {
+        publish(BusEvent::LinkChanged {
+            adapter: "before".into(),
+            linked: true,
+        });
+        let mut events = subscribe();
+        publish(BusEvent::LinkChanged {
+            adapter: "after".into(),
+            linked: false,
+        });
+
+        // Other tests publish on the same bus.
+        loop {
+            if let BusEvent::LinkChanged { adapter, linked } = events.recv().await.unwrap() {
+                assert_eq!(adapter, "after");
+                assert!(!linked);
+                break;
+            }
+        }
+    }
+}
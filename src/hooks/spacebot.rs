@@ -1,9 +1,17 @@
 //! SpacebotHook: Prompt hook for channels, branches, and workers.
 
-use crate::{AgentId, ChannelId, ProcessEvent, ProcessId, ProcessType};
+use crate::agent::tool_loop::{ToolLoop, ToolLoopStep};
+use crate::events::{self, BusEvent};
+use crate::{
+    AgentId, ChannelId, OutboundResponse, ProcessEvent, ProcessId, ProcessType, StatusUpdate,
+};
 use rig::agent::{HookAction, PromptHook, ToolCallHookAction};
 use rig::completion::{CompletionModel, CompletionResponse, Message};
-use tokio::sync::broadcast;
+use rig::message::AssistantContent;
+use tokio::sync::{broadcast, mpsc};
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Hook for observing agent behavior and sending events.
 #[derive(Clone)]
@@ -13,6 +21,41 @@ pub struct SpacebotHook {
     process_type: ProcessType,
     channel_id: Option<ChannelId>,
     event_tx: broadcast::Sender<ProcessEvent>,
+    /// Set for a single turn to guard and trace its tool loop.
+    tool_loop: Option<ToolLoop>,
+    /// Set for a single channel turn to keep the typing indicator up.
+    typing: Option<TurnTyping>,
+    /// Set for a single turn to tag its tool calls; see [`crate::TURN_ID_KEY`].
+    turn_id: Option<String>,
+}
+
+/// Keeps the typing indicator up across a channel turn's LLM calls.
+///
+/// Sending a reply clears the indicator, and the tool loop can keep going
+/// afterwards, so it's refreshed before every LLM call. It stops once the
+/// turn has replied or skipped, so the call that wraps up the turn doesn't
+/// show the agent as typing again.
+#[derive(Clone)]
+struct TurnTyping {
+    response_tx: mpsc::Sender<OutboundResponse>,
+    finished: Arc<AtomicBool>,
+}
+
+impl TurnTyping {
+    fn refresh(&self) {
+        if !self.finished.load(Ordering::Relaxed) {
+            // A full outbound queue already has the turn's output in flight.
+            let _ = self
+                .response_tx
+                .try_send(OutboundResponse::Status(StatusUpdate::Thinking));
+        }
+    }
+
+    fn tool_called(&self, tool_name: &str) {
+        if matches!(tool_name, "reply" | "skip") {
+            self.finished.store(true, Ordering::Relaxed);
+        }
+    }
 }
 
 impl SpacebotHook {
@@ -30,9 +73,47 @@ impl SpacebotHook {
             process_type,
             channel_id,
             event_tx,
+            tool_loop: None,
+            typing: None,
+            turn_id: None,
         }
     }
 
+    /// Attach a turn's tool loop, enforcing its deadline and recording each
+    /// step.
+    pub fn with_tool_loop(mut self, tool_loop: ToolLoop) -> Self {
+        self.tool_loop = Some(tool_loop);
+        self
+    }
+
+    /// Refresh a channel turn's typing indicator before each of its LLM calls.
+    pub fn with_typing(mut self, response_tx: mpsc::Sender<OutboundResponse>) -> Self {
+        self.typing = Some(TurnTyping {
+            response_tx,
+            finished: Arc::new(AtomicBool::new(false)),
+        });
+        self
+    }
+
+    /// Tag the tool calls and events of a turn with its id.
+    pub fn with_turn_id(mut self, turn_id: Option<String>) -> Self {
+        self.turn_id = turn_id;
+        self
+    }
+
+    /// Record a tool loop step and stream it to event subscribers.
+    fn record_step(&self, tool_loop: &ToolLoop, step: ToolLoopStep) {
+        tool_loop.record(step.clone());
+        let event = ProcessEvent::ToolLoopStep {
+            agent_id: self.agent_id.clone(),
+            process_id: self.process_id.clone(),
+            channel_id: self.channel_id.clone(),
+            turn_id: self.turn_id.clone(),
+            step,
+        };
+        let _ = self.event_tx.send(event);
+    }
+
     /// Send a status update event.
     pub fn send_status(&self, status: impl Into<String>) {
         let event = ProcessEvent::StatusUpdate {
@@ -45,54 +126,17 @@ impl SpacebotHook {
 
     /// Scan content for potential secret leaks.
     fn scan_for_leaks(&self, content: &str) -> Option<String> {
-        use regex::Regex;
-        use std::sync::LazyLock;
-
-        static LEAK_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
-            vec![
-                // OpenAI keys
-                Regex::new(r"sk-[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
-                // Anthropic keys
-                Regex::new(r"sk-ant-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
-                // OpenRouter keys
-                Regex::new(r"sk-or-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
-                // PEM private keys
-                Regex::new(r"-----BEGIN.*PRIVATE KEY-----").expect("hardcoded regex"),
-                // GitHub personal access tokens
-                Regex::new(r"ghp_[a-zA-Z0-9]{36}").expect("hardcoded regex"),
-                // Google API keys
-                Regex::new(r"AIza[0-9A-Za-z_-]{35}").expect("hardcoded regex"),
-                // Discord bot tokens (base64 user ID . timestamp . HMAC)
-                Regex::new(r"[MN][A-Za-z0-9]{23,}\.[A-Za-z0-9_-]{6}\.[A-Za-z0-9_-]{27,}")
-                    .expect("hardcoded regex"),
-                // Slack bot tokens
-                Regex::new(r"xoxb-[0-9]{10,}-[0-9A-Za-z-]+").expect("hardcoded regex"),
-                // Slack app tokens
-                Regex::new(r"xapp-[0-9]-[A-Z0-9]+-[0-9]+-[a-f0-9]+").expect("hardcoded regex"),
-                // Telegram bot tokens
-                Regex::new(r"\d{8,}:[A-Za-z0-9_-]{35}").expect("hardcoded regex"),
-                // Brave Search API keys
-                Regex::new(r"BSA[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
-            ]
-        });
-
-        for pattern in LEAK_PATTERNS.iter() {
-            if let Some(matched) = pattern.find(content) {
-                return Some(matched.as_str().to_string());
-            }
-        }
-
-        None
+        crate::secrets::scan::find_secret(content).map(str::to_string)
     }
 }
 
-// Timer map for tool call duration measurement. Entries are inserted in
-// on_tool_call and removed in on_tool_result. If the agent terminates between
-// the two hooks (e.g. leak detection), orphaned entries stay in the map.
-// Bounded by concurrent tool calls so not a practical leak.
-#[cfg(feature = "metrics")]
+// Start time and `tool.call` span of each running tool call, for duration
+// measurement. Entries are inserted in on_tool_call and removed in
+// on_tool_result, which closes the span. If the agent terminates between the
+// two hooks (e.g. leak detection), orphaned entries stay in the map. Bounded
+// by concurrent tool calls so not a practical leak.
 static TOOL_CALL_TIMERS: std::sync::LazyLock<
-    std::sync::Mutex<std::collections::HashMap<String, std::time::Instant>>,
+    std::sync::Mutex<std::collections::HashMap<String, (std::time::Instant, tracing::Span)>>,
 > = std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
 
 impl<M> PromptHook<M> for SpacebotHook
@@ -100,6 +144,21 @@ where
     M: CompletionModel,
 {
     async fn on_completion_call(&self, _prompt: &Message, _history: &[Message]) -> HookAction {
+        if let Some(tool_loop) = &self.tool_loop
+            && let Err(reason) = tool_loop.begin_iteration()
+        {
+            tracing::warn!(
+                process_id = %self.process_id,
+                iteration = tool_loop.iteration(),
+                "tool loop deadline reached, stopping turn"
+            );
+            return HookAction::Terminate { reason };
+        }
+
+        if let Some(typing) = &self.typing {
+            typing.refresh();
+        }
+
         // Log the completion call but don't block it
         tracing::debug!(
             process_id = %self.process_id,
@@ -124,6 +183,26 @@ where
             "completion response received"
         );
 
+        if let Some(tool_loop) = &self.tool_loop {
+            tool_loop.add_usage(response.usage.input_tokens, response.usage.output_tokens);
+            let mut text = String::new();
+            let mut tool_calls = Vec::new();
+            for content in response.choice.iter() {
+                match content {
+                    AssistantContent::Text(part) => text.push_str(&part.text),
+                    AssistantContent::ToolCall(call) => tool_calls.push(call.function.name.clone()),
+                    AssistantContent::Reasoning(reasoning) => {
+                        for segment in reasoning.reasoning.iter().filter(|s| !s.trim().is_empty()) {
+                            tool_loop.record_reasoning(segment);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let text = (!text.trim().is_empty()).then_some(text);
+            self.record_step(tool_loop, tool_loop.response_step(text, tool_calls));
+        }
+
         HookAction::Continue
     }
 
@@ -131,7 +210,7 @@ where
         &self,
         tool_name: &str,
         _tool_call_id: Option<String>,
-        _internal_call_id: &str,
+        internal_call_id: &str,
         args: &str,
     ) -> ToolCallHookAction {
         // Scan tool arguments for secrets before execution
@@ -147,24 +226,42 @@ where
             };
         }
 
+        if let Some(typing) = &self.typing {
+            typing.tool_called(tool_name);
+        }
+
         // Send event without blocking
         let event = ProcessEvent::ToolStarted {
             agent_id: self.agent_id.clone(),
             process_id: self.process_id.clone(),
             channel_id: self.channel_id.clone(),
+            turn_id: self.turn_id.clone(),
             tool_name: tool_name.to_string(),
         };
         let _ = self.event_tx.send(event);
 
+        if let Some(tool_loop) = &self.tool_loop {
+            self.record_step(tool_loop, tool_loop.tool_call_step(tool_name, args));
+        }
+
         tracing::debug!(
             process_id = %self.process_id,
             tool_name = %tool_name,
             "tool call started"
         );
 
-        #[cfg(feature = "metrics")]
+        let span = tracing::info_span!(
+            "tool.call",
+            tool_name = %tool_name,
+            agent_id = %self.agent_id,
+            process_id = %self.process_id,
+            turn_id = self.turn_id.as_deref(),
+        );
         if let Ok(mut timers) = TOOL_CALL_TIMERS.lock() {
-            timers.insert(_internal_call_id.to_string(), std::time::Instant::now());
+            timers.insert(
+                internal_call_id.to_string(),
+                (std::time::Instant::now(), span),
+            );
         }
 
         ToolCallHookAction::Continue
@@ -174,7 +271,7 @@ where
         &self,
         tool_name: &str,
         _tool_call_id: Option<String>,
-        _internal_call_id: &str,
+        internal_call_id: &str,
         _args: &str,
         result: &str,
     ) -> HookAction {
@@ -203,11 +300,16 @@ where
             agent_id: self.agent_id.clone(),
             process_id: self.process_id.clone(),
             channel_id: self.channel_id.clone(),
+            turn_id: self.turn_id.clone(),
             tool_name: tool_name.to_string(),
             result: capped_result,
         };
         let _ = self.event_tx.send(event);
 
+        if let Some(tool_loop) = &self.tool_loop {
+            self.record_step(tool_loop, tool_loop.tool_result_step(tool_name, result));
+        }
+
         tracing::debug!(
             process_id = %self.process_id,
             tool_name = %tool_name,
@@ -215,24 +317,47 @@ where
             "tool call completed"
         );
 
-        #[cfg(feature = "metrics")]
-        {
-            let metrics = crate::telemetry::Metrics::global();
-            metrics
-                .tool_calls_total
-                .with_label_values(&[&*self.agent_id, tool_name])
-                .inc();
-            if let Some(start) = TOOL_CALL_TIMERS
-                .lock()
-                .ok()
-                .and_then(|mut timers| timers.remove(_internal_call_id))
-            {
-                metrics
-                    .tool_call_duration_seconds
-                    .observe(start.elapsed().as_secs_f64());
-            }
-        }
+        let elapsed = TOOL_CALL_TIMERS
+            .lock()
+            .ok()
+            .and_then(|mut timers| timers.remove(internal_call_id))
+            .map(|(start, _span)| start.elapsed());
+        events::publish(BusEvent::ToolExecuted {
+            agent_id: self.agent_id.clone(),
+            channel_id: self.channel_id.clone(),
+            turn_id: self.turn_id.clone(),
+            tool_name: tool_name.to_string(),
+            duration_ms: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+        });
 
         HookAction::Continue
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typing_refreshes_until_the_turn_replies() {
+        let (response_tx, mut response_rx) = mpsc::channel(8);
+        let typing = TurnTyping {
+            response_tx,
+            finished: Arc::new(AtomicBool::new(false)),
+        };
+
+        typing.refresh();
+        typing.tool_called("memory_save");
+        typing.refresh();
+        typing.tool_called("reply");
+        typing.refresh();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                response_rx.try_recv(),
+                Ok(OutboundResponse::Status(StatusUpdate::Thinking))
+            ));
+        }
+        assert!(response_rx.try_recv().is_err());
+    }
+}
@@ -1,5 +1,7 @@
-//! Identity file loading (SOUL.md, IDENTITY.md, USER.md).
+//! Identity file loading (SOUL.md, IDENTITY.md, USER.md) and agent presets.
 
 pub mod files;
+pub mod presets;
 
 pub use files::{Identity, scaffold_identity_files};
+pub use presets::scaffold_preset_files;
@@ -0,0 +1,253 @@
+//! Agent presets: ready-made personas with parameter slots.
+//!
+//! A preset bundles SOUL.md and IDENTITY.md templates with a fragment of
+//! `[[agents]]` config (capabilities, listening mode, cron jobs). Templates
+//! are rendered with the parameters given in `[agents.preset_params]` or the
+//! create-agent API request.
+
+use anyhow::Context as _;
+use minijinja::{Environment, UndefinedBehavior};
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A slot the caller fills in when instantiating a preset.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetParameter {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Value used when the parameter isn't given. None means it's required.
+    pub default: Option<&'static str>,
+}
+
+/// A reusable agent template.
+#[derive(Debug, Clone, Serialize)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: &'static [PresetParameter],
+    #[serde(skip)]
+    soul: &'static str,
+    #[serde(skip)]
+    identity: &'static str,
+    /// TOML fragment merged under the agent's own `[[agents]]` settings.
+    #[serde(skip)]
+    config: &'static str,
+}
+
+/// All shipped presets.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "moderator",
+        description: "Keeps community channels on track and answers questions about the rules.",
+        parameters: &[
+            PresetParameter {
+                name: "community_name",
+                description: "Name of the community the agent moderates",
+                default: None,
+            },
+            PresetParameter {
+                name: "rules_channel",
+                description: "Where the community rules are posted",
+                default: Some("the rules channel"),
+            },
+        ],
+        soul: include_str!("../../presets/moderator/SOUL.md.j2"),
+        identity: include_str!("../../presets/moderator/IDENTITY.md.j2"),
+        config: include_str!("../../presets/moderator/agent.toml.j2"),
+    },
+    Preset {
+        name: "support_triager",
+        description: "Answers support questions and routes the rest with a severity and summary.",
+        parameters: &[
+            PresetParameter {
+                name: "product_name",
+                description: "Product the agent supports",
+                default: None,
+            },
+            PresetParameter {
+                name: "escalation_contact",
+                description: "Who unresolved issues are handed to",
+                default: Some("the support team"),
+            },
+        ],
+        soul: include_str!("../../presets/support_triager/SOUL.md.j2"),
+        identity: include_str!("../../presets/support_triager/IDENTITY.md.j2"),
+        config: include_str!("../../presets/support_triager/agent.toml.j2"),
+    },
+    Preset {
+        name: "standup_coordinator",
+        description: "Opens a daily standup, collects updates, and posts a summary.",
+        parameters: &[
+            PresetParameter {
+                name: "team_name",
+                description: "Name of the team",
+                default: None,
+            },
+            PresetParameter {
+                name: "standup_channel",
+                description: "Delivery target for the standup, in `adapter:target` format",
+                default: None,
+            },
+            PresetParameter {
+                name: "standup_hour",
+                description: "Hour of day (0-23) to open the standup",
+                default: Some("9"),
+            },
+        ],
+        soul: include_str!("../../presets/standup_coordinator/SOUL.md.j2"),
+        identity: include_str!("../../presets/standup_coordinator/IDENTITY.md.j2"),
+        config: include_str!("../../presets/standup_coordinator/agent.toml.j2"),
+    },
+];
+
+/// Errors from instantiating a preset.
+#[derive(Debug, thiserror::Error)]
+pub enum PresetError {
+    #[error("unknown preset '{0}'")]
+    Unknown(String),
+
+    #[error("preset '{preset}' requires parameter '{parameter}'")]
+    MissingParameter { preset: String, parameter: String },
+
+    #[error("preset '{preset}' has no parameter '{parameter}'")]
+    UnknownParameter { preset: String, parameter: String },
+
+    #[error("failed to render preset '{preset}': {message}")]
+    Render { preset: String, message: String },
+}
+
+/// A preset rendered with concrete parameters.
+#[derive(Debug, Clone)]
+pub struct RenderedPreset {
+    /// Identity files, by file name.
+    pub files: Vec<(&'static str, String)>,
+    /// Agent config fragment.
+    pub config: toml::Table,
+}
+
+/// Look up a preset by name.
+pub fn find(name: &str) -> Result<&'static Preset, PresetError> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| PresetError::Unknown(name.to_string()))
+}
+
+impl Preset {
+    /// Fill the parameter slots. Missing required parameters and parameters
+    /// the preset doesn't declare are errors.
+    pub fn render(&self, params: &HashMap<String, String>) -> Result<RenderedPreset, PresetError> {
+        if let Some(unknown) = params
+            .keys()
+            .find(|key| !self.parameters.iter().any(|p| p.name == key.as_str()))
+        {
+            return Err(PresetError::UnknownParameter {
+                preset: self.name.to_string(),
+                parameter: unknown.clone(),
+            });
+        }
+
+        let mut values = HashMap::new();
+        for parameter in self.parameters {
+            let value = params
+                .get(parameter.name)
+                .map(String::as_str)
+                .or(parameter.default)
+                .ok_or_else(|| PresetError::MissingParameter {
+                    preset: self.name.to_string(),
+                    parameter: parameter.name.to_string(),
+                })?;
+            values.insert(parameter.name, value.to_string());
+        }
+
+        let render_error = |error: &dyn std::fmt::Display| PresetError::Render {
+            preset: self.name.to_string(),
+            message: error.to_string(),
+        };
+
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        // Quotes and escapes a value so it can be dropped into the TOML fragment.
+        env.add_filter("toml", |value: String| {
+            toml::Value::String(value).to_string()
+        });
+        let render = |source: &str| {
+            env.render_str(source, &values)
+                .map_err(|error| render_error(&error))
+        };
+
+        let files = vec![
+            ("SOUL.md", render(self.soul)?),
+            ("IDENTITY.md", render(self.identity)?),
+        ];
+        let config = render(self.config)?
+            .parse::<toml::Table>()
+            .map_err(|error| render_error(&error))?;
+
+        Ok(RenderedPreset { files, config })
+    }
+}
+
+/// Write a preset's identity files into an agent's workspace. Like
+/// `scaffold_identity_files`, files that already exist are left untouched,
+/// so edits made after instantiation survive restarts.
+pub async fn scaffold_preset_files(
+    workspace: &Path,
+    name: &str,
+    params: &HashMap<String, String>,
+) -> crate::error::Result<()> {
+    let rendered = find(name)
+        .and_then(|preset| preset.render(params))
+        .map_err(|error| crate::error::ConfigError::Invalid(error.to_string()))?;
+
+    for (filename, content) in rendered.files {
+        let target = workspace.join(filename);
+        if !target.exists() {
+            tokio::fs::write(&target, content)
+                .await
+                .with_context(|| format!("failed to write preset file: {}", target.display()))?;
+            tracing::info!(path = %target.display(), preset = name, "wrote preset identity file");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_slots_and_checks_parameters() {
+        let preset = find("standup_coordinator").expect("shipped preset");
+        let params = HashMap::from([
+            ("team_name".to_string(), "Platform \"Core\"".to_string()),
+            ("standup_channel".to_string(), "discord:123".to_string()),
+        ]);
+
+        let rendered = preset.render(&params).expect("renders");
+        assert!(rendered.files[1].1.contains("Platform \"Core\""));
+        let cron = &rendered.config["cron"].as_array().expect("cron jobs")[0];
+        assert_eq!(cron["delivery_target"].as_str(), Some("discord:123"));
+        assert_eq!(cron["active_start_hour"].as_integer(), Some(9));
+        assert_eq!(
+            rendered.config["capabilities"]["description"].as_str(),
+            Some("Standup coordinator for Platform \"Core\"")
+        );
+
+        let missing = HashMap::from([("team_name".to_string(), "Core".to_string())]);
+        assert!(matches!(
+            preset.render(&missing),
+            Err(PresetError::MissingParameter { parameter, .. }) if parameter == "standup_channel"
+        ));
+        let mut typo = params.clone();
+        typo.insert("team".into(), "Core".into());
+        assert!(matches!(
+            preset.render(&typo),
+            Err(PresetError::UnknownParameter { .. })
+        ));
+        assert!(matches!(find("bouncer"), Err(PresetError::Unknown(_))));
+    }
+}
@@ -1,20 +1,28 @@
 //! Spacebot: A Rust agentic system where every LLM process has a dedicated role.
 
 pub mod agent;
+pub mod alerts;
 pub mod api;
+pub mod audit;
+pub mod check;
 pub mod config;
 pub mod conversation;
 pub mod cron;
 pub mod daemon;
 pub mod db;
 pub mod error;
+pub mod error_reporting;
+pub mod events;
 pub mod hooks;
 pub mod identity;
 pub mod llm;
 pub mod memory;
 pub mod messaging;
 pub mod opencode;
+pub mod otel;
 pub mod prompts;
+pub mod recovery;
+pub mod repl;
 pub mod secrets;
 pub mod settings;
 pub mod skills;
@@ -128,15 +136,25 @@ pub enum ProcessEvent {
         agent_id: AgentId,
         process_id: ProcessId,
         channel_id: Option<ChannelId>,
+        turn_id: Option<String>,
         tool_name: String,
     },
     ToolCompleted {
         agent_id: AgentId,
         process_id: ProcessId,
         channel_id: Option<ChannelId>,
+        turn_id: Option<String>,
         tool_name: String,
         result: String,
     },
+    /// One step of a multi-step tool loop within a turn.
+    ToolLoopStep {
+        agent_id: AgentId,
+        process_id: ProcessId,
+        channel_id: Option<ChannelId>,
+        turn_id: Option<String>,
+        step: agent::tool_loop::ToolLoopStep,
+    },
     MemorySaved {
         agent_id: AgentId,
         memory_id: String,
@@ -147,6 +165,13 @@ pub enum ProcessEvent {
         channel_id: ChannelId,
         threshold_reached: f32,
     },
+    /// An inbound message was flagged as a possible prompt injection.
+    InjectionFlagged {
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        sender_id: String,
+        reasons: Vec<String>,
+    },
     StatusUpdate {
         agent_id: AgentId,
         process_id: ProcessId,
@@ -179,6 +204,8 @@ pub struct AgentDeps {
     pub runtime_config: Arc<config::RuntimeConfig>,
     pub event_tx: tokio::sync::broadcast::Sender<ProcessEvent>,
     pub sqlite_pool: sqlx::SqlitePool,
+    /// Read-only pool on the same database, for long reads.
+    pub sqlite_read: sqlx::SqlitePool,
     pub messaging_manager: Option<Arc<messaging::MessagingManager>>,
 }
 
@@ -221,6 +248,30 @@ pub struct InboundMessage {
     pub formatted_author: Option<String>,
 }
 
+/// Metadata key of the id a message is given when it enters the system. The
+/// logs, LLM calls, tool calls, persisted rows, and API events of the turn it
+/// starts all carry the id, so they can be joined when debugging.
+pub const TURN_ID_KEY: &str = "turn_id";
+
+impl InboundMessage {
+    /// The id of the turn this message starts, if it's been given one.
+    pub fn turn_id(&self) -> Option<&str> {
+        self.metadata.get(TURN_ID_KEY).and_then(|id| id.as_str())
+    }
+
+    /// The id of the turn this message starts, giving it a new one first if
+    /// it has none.
+    pub fn assign_turn_id(&mut self) -> String {
+        if let Some(turn_id) = self.turn_id() {
+            return turn_id.to_string();
+        }
+        let turn_id = uuid::Uuid::new_v4().to_string();
+        self.metadata
+            .insert(TURN_ID_KEY.into(), turn_id.clone().into());
+        turn_id
+    }
+}
+
 /// Message content variants.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -351,6 +402,9 @@ pub enum OutboundResponse {
     StreamStart,
     StreamChunk(String),
     StreamEnd,
+    /// The turn was cancelled mid-stream. Removes the partial message where
+    /// the platform allows it. No-op when nothing is being streamed.
+    StreamAbort,
     Status(StatusUpdate),
 }
 
@@ -478,3 +532,37 @@ pub enum StatusUpdate {
         result: String,
     },
 }
+
+/// A moderation action taken on a platform through the `moderate` tool.
+///
+/// IDs are the platform's own, as strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationAction {
+    DeleteMessage { message_id: String },
+    TimeoutUser { user_id: String, duration_secs: u64 },
+    ApplyRole { user_id: String, role_id: String },
+}
+
+impl ModerationAction {
+    /// The action's name, as used in the moderation policy and audit log.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DeleteMessage { .. } => "delete_message",
+            Self::TimeoutUser { .. } => "timeout_user",
+            Self::ApplyRole { .. } => "apply_role",
+        }
+    }
+
+    /// What the action is taken on, for the audit log.
+    pub fn target(&self) -> String {
+        match self {
+            Self::DeleteMessage { message_id } => format!("message {message_id}"),
+            Self::TimeoutUser {
+                user_id,
+                duration_secs,
+            } => format!("user {user_id} for {duration_secs}s"),
+            Self::ApplyRole { user_id, role_id } => format!("user {user_id}, role {role_id}"),
+        }
+    }
+}
@@ -1,10 +1,21 @@
 //! LLM provider management and routing.
 
+pub mod cache;
+pub mod call_log;
+pub mod concurrency;
+pub mod ensemble;
+pub mod latency;
 pub mod manager;
+pub mod mock;
 pub mod model;
 pub mod providers;
 pub mod routing;
+pub mod streaming;
+pub mod structured;
+pub mod throttle;
+pub mod usage;
 
 pub use manager::LlmManager;
+pub use mock::{MockProvider, MockResponse};
 pub use model::SpacebotModel;
 pub use routing::RoutingConfig;
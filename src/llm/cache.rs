@@ -0,0 +1,210 @@
+//! Prompt/response cache in front of LLM providers.
+
+use crate::config::LlmCacheConfig;
+use crate::llm::model::RawResponse;
+
+use rig::completion::{AssistantContent, CompletionRequest, CompletionResponse, Usage};
+use rig::one_or_many::OneOrMany;
+use sha2::{Digest as _, Sha256};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// In-memory cache of completion responses, keyed on the model and the
+/// exact request.
+///
+/// Scheduled jobs and test runs often send byte-identical prompts; with the
+/// cache enabled those are answered without hitting the provider. Entries
+/// expire after `ttl_secs`, and the oldest entries are evicted once
+/// `max_entries` is reached. Limits are read from the live config on every
+/// call, so they hot-reload with the rest of `[llm]`.
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    choice: OneOrMany<AssistantContent>,
+    raw_response: RawResponse,
+    stored_at: Instant,
+}
+
+impl ResponseCache {
+    /// Cache key for a request: SHA-256 over the model name and the request
+    /// as sent. Prompts that differ only in whitespace are different prompts,
+    /// since code blocks and tables depend on it.
+    pub fn key(model: &str, request: &CompletionRequest) -> String {
+        let value = serde_json::json!({
+            "model": model,
+            "preamble": request.preamble,
+            "chat_history": request.chat_history,
+            "documents": request.documents,
+            "tools": request.tools,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "tool_choice": request.tool_choice,
+            "additional_params": request.additional_params,
+        });
+        let digest = Sha256::digest(value.to_string().as_bytes());
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Look up a fresh entry. Hits report zero token usage since no provider
+    /// call was made.
+    pub fn get(
+        &self,
+        key: &str,
+        config: &LlmCacheConfig,
+    ) -> Option<CompletionResponse<RawResponse>> {
+        let mut entries = self.entries.lock().expect("response cache lock poisoned");
+        let ttl = Duration::from_secs(config.ttl_secs);
+        match entries.get(key) {
+            Some(entry) if entry.stored_at.elapsed() < ttl => Some(CompletionResponse {
+                choice: entry.choice.clone(),
+                usage: Usage::new(),
+                raw_response: entry.raw_response.clone(),
+            }),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Store a response, evicting expired entries and then the oldest ones
+    /// to stay within `max_entries`.
+    pub fn insert(
+        &self,
+        key: String,
+        response: &CompletionResponse<RawResponse>,
+        config: &LlmCacheConfig,
+    ) {
+        if config.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("response cache lock poisoned");
+        if entries.len() >= config.max_entries && !entries.contains_key(&key) {
+            let ttl = Duration::from_secs(config.ttl_secs);
+            entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+
+            while entries.len() >= config.max_entries {
+                let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| key.clone())
+                else {
+                    break;
+                };
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(
+            key,
+            CachedResponse {
+                choice: response.choice.clone(),
+                raw_response: response.raw_response.clone(),
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Drop every entry.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .expect("response cache lock poisoned")
+            .clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .expect("response cache lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::message::Message;
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest {
+            preamble: Some("You are a test.".into()),
+            chat_history: OneOrMany::one(Message::user(prompt)),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: None,
+        }
+    }
+
+    fn response(text: &str) -> CompletionResponse<RawResponse> {
+        CompletionResponse {
+            choice: OneOrMany::one(AssistantContent::text(text)),
+            usage: Usage::new(),
+            raw_response: RawResponse {
+                body: serde_json::Value::Null,
+            },
+        }
+    }
+
+    #[test]
+    fn test_key_covers_exact_prompt_and_model() {
+        let key = ResponseCache::key("anthropic/a", &request("hello world"));
+        assert_eq!(
+            key,
+            ResponseCache::key("anthropic/a", &request("hello world"))
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key("anthropic/a", &request("hello\nworld"))
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key("anthropic/b", &request("hello world"))
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key("anthropic/a", &request("hello there"))
+        );
+    }
+
+    #[test]
+    fn test_insert_evicts_oldest_and_respects_ttl() {
+        let cache = ResponseCache::default();
+        let config = LlmCacheConfig {
+            enabled: true,
+            ttl_secs: 60,
+            max_entries: 2,
+        };
+
+        cache.insert("a".into(), &response("a"), &config);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("b".into(), &response("b"), &config);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("c".into(), &response("c"), &config);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a", &config).is_none());
+        assert!(cache.get("c", &config).is_some());
+
+        let expired = LlmCacheConfig {
+            ttl_secs: 0,
+            ..config
+        };
+        assert!(cache.get("c", &expired).is_none());
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+//! Persisted record of every LLM call, for looking into failures and cost
+//! anomalies after the fact.
+//!
+//! Calls are written to the `llm_calls` table of the agent that made them.
+//! Each agent registers its database when it's initialized; calls made
+//! without an agent aren't recorded. Prompts and responses are truncated to
+//! `[llm.call_log] max_chars` and redacted before they're stored.
+
+use crate::config::{LlmCallLogConfig, RedactionConfig};
+use crate::conversation::redaction::Redactor;
+use crate::otel::db_write;
+
+use rig::completion::CompletionRequest;
+use sqlx::SqlitePool;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often each agent's table is pruned of calls past retention.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// One finished completion call.
+#[derive(Debug, Clone, Default)]
+pub struct LlmCall {
+    pub model: String,
+    pub turn_id: Option<String>,
+    pub duration_ms: i64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// The chat history sent, as JSON. See [`render_prompt`].
+    pub prompt: Option<String>,
+    /// The response content, as JSON. None if the call failed.
+    pub response: Option<String>,
+    pub error: Option<String>,
+    /// Answered from the response cache, without reaching a provider.
+    pub cached: bool,
+}
+
+struct AgentLog {
+    pool: SqlitePool,
+    last_pruned: Option<Instant>,
+}
+
+/// Per-agent databases calls are written to. Shared through the
+/// [`LlmManager`](super::LlmManager).
+pub struct CallLog {
+    agents: Mutex<HashMap<String, AgentLog>>,
+    redactor: Redactor,
+}
+
+impl Default for CallLog {
+    fn default() -> Self {
+        Self {
+            agents: Mutex::default(),
+            redactor: Redactor::new(RedactionConfig {
+                enabled: true,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl CallLog {
+    /// Record calls made for `agent_id` in this database.
+    pub fn register(&self, agent_id: &str, pool: SqlitePool) {
+        self.agents.lock().expect("call log lock poisoned").insert(
+            agent_id.to_string(),
+            AgentLog {
+                pool,
+                last_pruned: None,
+            },
+        );
+    }
+
+    /// Write a call to its agent's table in the background, first pruning
+    /// calls past retention if that's due.
+    pub fn record(&self, config: &LlmCallLogConfig, agent_id: &str, call: LlmCall) {
+        if !config.enabled {
+            return;
+        }
+        let (pool, prune) = {
+            let mut agents = self.agents.lock().expect("call log lock poisoned");
+            let Some(agent) = agents.get_mut(agent_id) else {
+                return;
+            };
+            let prune = agent
+                .last_pruned
+                .is_none_or(|pruned| pruned.elapsed() >= PRUNE_INTERVAL);
+            if prune {
+                agent.last_pruned = Some(Instant::now());
+            }
+            (agent.pool.clone(), prune)
+        };
+
+        let prompt = call
+            .prompt
+            .map(|prompt| self.prepare(config, agent_id, &prompt, Keep::End));
+        let response = call
+            .response
+            .map(|response| self.prepare(config, agent_id, &response, Keep::Start));
+        let error = call
+            .error
+            .map(|error| self.prepare(config, agent_id, &error, Keep::Start));
+        let outcome = if error.is_some() {
+            "error"
+        } else if call.cached {
+            "cached"
+        } else {
+            "ok"
+        };
+        let retention = format!("-{} days", config.retention_days);
+        let id = uuid::Uuid::new_v4().to_string();
+
+        let write = async move {
+            if prune
+                && let Err(error) =
+                    sqlx::query("DELETE FROM llm_calls WHERE created_at < datetime('now', ?)")
+                        .bind(&retention)
+                        .execute(&pool)
+                        .await
+            {
+                tracing::warn!(%error, "failed to prune LLM calls");
+            }
+
+            sqlx::query(
+                "INSERT INTO llm_calls \
+                 (id, turn_id, model, outcome, duration_ms, input_tokens, output_tokens, prompt, response, error) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&call.turn_id)
+            .bind(&call.model)
+            .bind(outcome)
+            .bind(call.duration_ms)
+            .bind(call.input_tokens as i64)
+            .bind(call.output_tokens as i64)
+            .bind(&prompt)
+            .bind(&response)
+            .bind(&error)
+            .execute(&pool)
+            .await
+            .inspect_err(|error| tracing::warn!(%error, "failed to persist LLM call"))
+        };
+        tokio::spawn(db_write("llm_calls", "insert", write));
+    }
+
+    /// Redact text, then cut it down to `max_chars`.
+    fn prepare(&self, config: &LlmCallLogConfig, agent_id: &str, text: &str, keep: Keep) -> String {
+        let text = if config.redact {
+            self.redactor.redact(agent_id, text)
+        } else {
+            text.to_string()
+        };
+        clip(&text, config.max_chars, keep)
+    }
+}
+
+/// The part of a prompt worth storing: its chat history, whose end is the
+/// message being answered. The preamble is left out, since it's rebuilt
+/// from config and the same across a turn's calls.
+pub fn render_prompt(request: &CompletionRequest) -> String {
+    serde_json::to_string(&request.chat_history).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Keep {
+    Start,
+    End,
+}
+
+/// At most `max_chars` characters of `text`, with an ellipsis where the rest
+/// was cut.
+fn clip(text: &str, max_chars: usize, keep: Keep) -> String {
+    if max_chars == 0 {
+        return String::new();
+    }
+    match keep {
+        Keep::Start => match text.char_indices().nth(max_chars) {
+            Some((index, _)) => format!("{}…", &text[..index]),
+            None => text.to_string(),
+        },
+        Keep::End => match text.char_indices().rev().nth(max_chars - 1) {
+            Some((index, _)) if index > 0 => format!("…{}", &text[index..]),
+            _ => text.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_are_recorded_clipped_and_redacted() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let call_log = CallLog::default();
+        call_log.register("main", pool.clone());
+        let config = LlmCallLogConfig {
+            max_chars: 40,
+            ..Default::default()
+        };
+        call_log.record(
+            &config,
+            "main",
+            LlmCall {
+                model: "anthropic/claude-sonnet-4".into(),
+                turn_id: Some("turn".into()),
+                prompt: Some(format!("{} mail me at jane@example.com", "x".repeat(100))),
+                error: Some("rate limited".into()),
+                ..Default::default()
+            },
+        );
+        call_log.record(&config, "unregistered", LlmCall::default());
+
+        let row = loop {
+            let row: Option<(String, String, Option<String>)> = sqlx::query_as(
+                "SELECT outcome, prompt, response FROM llm_calls WHERE turn_id = 'turn'",
+            )
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+            if let Some(row) = row {
+                break row;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(row.0, "error");
+        assert_eq!(row.1.chars().count(), 41);
+        assert!(row.1.starts_with('…'));
+        assert!(row.1.ends_with("mail me at [EMAIL_1]"));
+        assert_eq!(row.2, None);
+
+        call_log.record(
+            &config,
+            "main",
+            LlmCall {
+                model: "anthropic/claude-sonnet-4".into(),
+                turn_id: Some("cached turn".into()),
+                response: Some("[]".into()),
+                cached: true,
+                ..Default::default()
+            },
+        );
+        let cached = loop {
+            let row: Option<(String, i64, i64)> = sqlx::query_as(
+                "SELECT outcome, input_tokens, output_tokens FROM llm_calls \
+                 WHERE turn_id = 'cached turn'",
+            )
+            .fetch_optional(&pool)
+            .await
+            .unwrap();
+            if let Some(row) = row {
+                break row;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(cached, ("cached".to_string(), 0, 0));
+
+        assert_eq!(clip("abcdef", 3, Keep::Start), "abc…");
+        assert_eq!(clip("abcdef", 3, Keep::End), "…def");
+        assert_eq!(clip("abc", 3, Keep::End), "abc");
+    }
+}
@@ -0,0 +1,201 @@
+//! Concurrency limits on LLM calls, instance-wide and per agent.
+
+use crate::config::{LlmConcurrencyConfig, OverflowPolicy};
+
+use rig::completion::CompletionError;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Gates completions behind a global semaphore and one semaphore per agent.
+///
+/// A burst of mentions across channels would otherwise open one completion per
+/// channel, branch and worker at once and trip provider rate limits. Calls that
+/// can't get a slot wait in a bounded queue; once `max_queued` callers are
+/// waiting, the overflow policy decides whether new calls fail fast or keep
+/// queueing. Limits are fixed when the manager and agents start.
+#[derive(Debug)]
+pub struct ConcurrencyLimiter {
+    config: LlmConcurrencyConfig,
+    global: Option<Arc<Semaphore>>,
+    agents: RwLock<HashMap<String, Arc<Semaphore>>>,
+    waiting: AtomicUsize,
+}
+
+/// Held for the duration of a completion, including retries and fallbacks.
+#[derive(Debug)]
+pub struct LlmPermit {
+    _agent: Option<OwnedSemaphorePermit>,
+    _global: Option<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(config: LlmConcurrencyConfig) -> Self {
+        Self {
+            global: (config.max_concurrent > 0)
+                .then(|| Arc::new(Semaphore::new(config.max_concurrent))),
+            config,
+            agents: RwLock::new(HashMap::new()),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Set an agent's limit. 0 removes it. Calls already holding a permit keep it.
+    pub fn register_agent(&self, agent_id: &str, max_concurrent: usize) {
+        let mut agents = self.agents.write().expect("concurrency lock poisoned");
+        if max_concurrent == 0 {
+            agents.remove(agent_id);
+        } else {
+            agents.insert(
+                agent_id.to_string(),
+                Arc::new(Semaphore::new(max_concurrent)),
+            );
+        }
+    }
+
+    /// Number of calls currently waiting for a slot.
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+
+    /// Wait for an agent slot (if the agent is registered), then a global slot.
+    pub async fn acquire(&self, agent_id: Option<&str>) -> Result<LlmPermit, CompletionError> {
+        let agent_semaphore = agent_id.and_then(|agent_id| {
+            self.agents
+                .read()
+                .expect("concurrency lock poisoned")
+                .get(agent_id)
+                .cloned()
+        });
+
+        // Always agent first, then global, so two callers can't hold each
+        // other's second permit.
+        let agent = match agent_semaphore {
+            Some(semaphore) => Some(self.acquire_one(semaphore, "agent", agent_id).await?),
+            None => None,
+        };
+        let global = match &self.global {
+            Some(semaphore) => Some(
+                self.acquire_one(semaphore.clone(), "global", agent_id)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        Ok(LlmPermit {
+            _agent: agent,
+            _global: global,
+        })
+    }
+
+    async fn acquire_one(
+        &self,
+        semaphore: Arc<Semaphore>,
+        scope: &'static str,
+        agent_id: Option<&str>,
+    ) -> Result<OwnedSemaphorePermit, CompletionError> {
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => return Ok(permit),
+            Err(TryAcquireError::Closed) => {
+                return Err(CompletionError::ProviderError(
+                    "LLM concurrency limiter closed".into(),
+                ));
+            }
+            Err(TryAcquireError::NoPermits) => {}
+        }
+
+        let waiting = self.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = WaitingGuard(&self.waiting);
+        if waiting >= self.config.max_queued {
+            if self.config.overflow == OverflowPolicy::Reject {
+                tracing::warn!(scope, agent_id, waiting, "LLM call queue full, rejecting");
+                return Err(CompletionError::ProviderError(format!(
+                    "too many concurrent LLM calls ({scope} limit reached, {waiting} already queued)"
+                )));
+            }
+            tracing::warn!(
+                scope,
+                agent_id,
+                waiting,
+                "LLM call queue over capacity, waiting anyway"
+            );
+        } else {
+            tracing::debug!(scope, agent_id, waiting, "waiting for LLM call slot");
+        }
+
+        let queue_timeout = Duration::from_secs(self.config.queue_timeout_secs);
+        match tokio::time::timeout(queue_timeout, semaphore.acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err(CompletionError::ProviderError(
+                "LLM concurrency limiter closed".into(),
+            )),
+            Err(_) => Err(CompletionError::ProviderError(format!(
+                "gave up waiting for an LLM call slot after {}s ({scope} limit)",
+                self.config.queue_timeout_secs
+            ))),
+        }
+    }
+}
+
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(
+        max_concurrent: usize,
+        max_queued: usize,
+        overflow: OverflowPolicy,
+    ) -> ConcurrencyLimiter {
+        ConcurrencyLimiter::new(LlmConcurrencyConfig {
+            max_concurrent,
+            max_queued,
+            queue_timeout_secs: 1,
+            overflow,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_reject_when_queue_full() {
+        let limiter = limiter(1, 0, OverflowPolicy::Reject);
+        let _held = limiter.acquire(None).await.expect("first call gets a slot");
+        assert!(limiter.acquire(None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_agent_limit_queues_until_released() {
+        let limiter = Arc::new(limiter(0, 8, OverflowPolicy::Reject));
+        limiter.register_agent("main", 1);
+
+        let held = limiter.acquire(Some("main")).await.expect("slot");
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(Some("main")).await.map(|_| ()) }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.waiting(), 1);
+
+        // Other agents aren't affected by this agent's limit.
+        limiter
+            .acquire(Some("other"))
+            .await
+            .expect("unregistered agent");
+
+        drop(held);
+        waiter
+            .await
+            .expect("join")
+            .expect("queued call gets the slot");
+        assert_eq!(limiter.waiting(), 0);
+    }
+}
@@ -0,0 +1,408 @@
+//! Ensemble replies: several models answer, one answer is kept.
+//!
+//! For channels where quality matters more than cost, each LLM call goes to
+//! the routed model and up to two others in parallel. A judge model picks the
+//! best candidate; without one, the candidate that agrees most with the rest
+//! wins. Rounds are kept until the turn ends, then every candidate is written
+//! to `ensemble_candidates`.
+
+use crate::config::EnsembleConfig;
+use crate::db::TimedQuery as _;
+use crate::llm::model::{RawResponse, SpacebotModel};
+use crate::llm::structured;
+use crate::prompts::PromptEngine;
+
+use futures::future::BoxFuture;
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use rig::message::{AssistantContent, Message, UserContent};
+use rig::one_or_many::OneOrMany;
+use sqlx::SqlitePool;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// `judged_by` for rounds decided without a judge model.
+const CONSENSUS: &str = "consensus";
+
+/// Attempts the judge gets to return a valid verdict.
+const JUDGE_MAX_ATTEMPTS: usize = 2;
+
+/// One turn's ensemble. Shared by every LLM call in the turn.
+pub struct Ensemble {
+    /// Models that answer alongside the primary.
+    models: Vec<String>,
+    judge_model: Option<String>,
+    prompt_engine: PromptEngine,
+    rounds: Mutex<Vec<EnsembleRound>>,
+}
+
+/// One model's answer in a round.
+#[derive(Debug, Clone)]
+pub struct EnsembleCandidate {
+    pub model: String,
+    /// The answer as shown to the judge. None when the call failed.
+    pub content: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// The candidates for one LLM call and which one was used.
+#[derive(Debug, Clone)]
+pub struct EnsembleRound {
+    pub candidates: Vec<EnsembleCandidate>,
+    pub chosen: usize,
+    /// The judge model, or "consensus".
+    pub judged_by: String,
+    pub reason: Option<String>,
+}
+
+impl Ensemble {
+    pub fn new(config: &EnsembleConfig, prompt_engine: PromptEngine) -> Self {
+        Self {
+            models: config.models.clone(),
+            judge_model: config.judge_model.clone(),
+            prompt_engine,
+            rounds: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Send the request to every model at once and return the best answer.
+    /// Usage covers all candidates, so cost accounting sees the real spend.
+    /// Fails only when every candidate fails.
+    ///
+    /// Boxed because the candidates and the judge call back into
+    /// `SpacebotModel::completion`, which calls this.
+    pub fn complete<'a>(
+        &'a self,
+        primary: &'a SpacebotModel,
+        request: CompletionRequest,
+    ) -> BoxFuture<'a, Result<CompletionResponse<RawResponse>, CompletionError>> {
+        Box::pin(self.run(primary, request))
+    }
+
+    async fn run(
+        &self,
+        primary: &SpacebotModel,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse<RawResponse>, CompletionError> {
+        let models: Vec<SpacebotModel> = std::iter::once(primary.clone())
+            .chain(self.models.iter().map(|name| primary.sibling(name)))
+            .collect();
+        let results = futures::future::join_all(models.iter().map(|model| {
+            let request = request.clone();
+            async move {
+                let started = Instant::now();
+                let result = model.completion(request).await;
+                (result, started.elapsed().as_millis() as u64)
+            }
+        }))
+        .await;
+
+        let mut candidates = Vec::with_capacity(models.len());
+        let mut responses = Vec::with_capacity(models.len());
+        let mut usage = rig::completion::Usage::new();
+        let mut first_error = None;
+        for (model, (result, duration_ms)) in models.iter().zip(results) {
+            let model = model.full_model_name().to_string();
+            match result {
+                Ok(response) => {
+                    usage += response.usage;
+                    candidates.push(EnsembleCandidate {
+                        model,
+                        content: Some(describe(&response.choice)),
+                        error: None,
+                        duration_ms,
+                        input_tokens: response.usage.input_tokens,
+                        output_tokens: response.usage.output_tokens,
+                    });
+                    responses.push(Some(response));
+                }
+                Err(error) => {
+                    tracing::warn!(%model, %error, "ensemble candidate failed");
+                    candidates.push(EnsembleCandidate {
+                        model,
+                        content: None,
+                        error: Some(error.to_string()),
+                        duration_ms,
+                        input_tokens: 0,
+                        output_tokens: 0,
+                    });
+                    first_error.get_or_insert(error);
+                    responses.push(None);
+                }
+            }
+        }
+
+        let answered: Vec<usize> = (0..candidates.len())
+            .filter(|&index| candidates[index].content.is_some())
+            .collect();
+        let Some(&only) = answered.first() else {
+            return Err(first_error.expect("a failed candidate recorded its error"));
+        };
+        let (chosen, judged_by, reason) = if answered.len() == 1 {
+            (only, CONSENSUS.to_string(), None)
+        } else {
+            self.pick(primary, &request, &candidates, &answered).await
+        };
+
+        tracing::info!(
+            model = %candidates[chosen].model,
+            candidates = candidates.len(),
+            answered = answered.len(),
+            %judged_by,
+            "ensemble picked a candidate"
+        );
+        self.rounds
+            .lock()
+            .expect("ensemble rounds lock poisoned")
+            .push(EnsembleRound {
+                candidates,
+                chosen,
+                judged_by,
+                reason,
+            });
+
+        let mut response = responses
+            .swap_remove(chosen)
+            .expect("chosen candidate answered");
+        response.usage = usage;
+        Ok(response)
+    }
+
+    /// Ask the judge, falling back to consensus if it fails.
+    async fn pick(
+        &self,
+        primary: &SpacebotModel,
+        request: &CompletionRequest,
+        candidates: &[EnsembleCandidate],
+        answered: &[usize],
+    ) -> (usize, String, Option<String>) {
+        if let Some(judge_model) = &self.judge_model {
+            let judge = primary.sibling(judge_model);
+            match self.judge(judge, request, candidates, answered).await {
+                Ok((chosen, reason)) => return (chosen, judge_model.clone(), Some(reason)),
+                Err(error) => {
+                    tracing::warn!(%error, %judge_model, "ensemble judge failed, picking by consensus");
+                }
+            }
+        }
+        (consensus(candidates, answered), CONSENSUS.to_string(), None)
+    }
+
+    async fn judge(
+        &self,
+        judge: SpacebotModel,
+        request: &CompletionRequest,
+        candidates: &[EnsembleCandidate],
+        answered: &[usize],
+    ) -> Result<(usize, String), String> {
+        let shown: Vec<&str> = answered
+            .iter()
+            .map(|&index| candidates[index].content.as_deref().unwrap_or_default())
+            .collect();
+        let system_prompt = self
+            .prompt_engine
+            .render_static("ensemble_judge")
+            .map_err(|error| error.to_string())?;
+        let prompt = self
+            .prompt_engine
+            .render_system_ensemble_candidates(latest_user_text(request).as_deref(), &shown)
+            .map_err(|error| error.to_string())?;
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["choice", "reason"],
+            "properties": {
+                "choice": { "type": "integer", "enum": (1..=shown.len()).collect::<Vec<_>>() },
+                "reason": { "type": "string" },
+            },
+        });
+
+        let agent = AgentBuilder::new(judge).preamble(&system_prompt).build();
+        let verdict = structured::prompt_structured(
+            &agent,
+            &prompt,
+            &schema,
+            &self.prompt_engine,
+            JUDGE_MAX_ATTEMPTS,
+        )
+        .await
+        .map_err(|error| error.to_string())?
+        .value;
+
+        let choice = verdict["choice"]
+            .as_u64()
+            .ok_or("judge verdict has no choice")? as usize;
+        let reason = verdict["reason"].as_str().unwrap_or_default().to_string();
+        Ok((answered[choice - 1], reason))
+    }
+
+    /// Write the turn's rounds to `ensemble_candidates` in the background.
+    pub fn persist(&self, pool: &SqlitePool, channel_id: &str) {
+        let rounds =
+            std::mem::take(&mut *self.rounds.lock().expect("ensemble rounds lock poisoned"));
+        if rounds.is_empty() {
+            return;
+        }
+        let pool = pool.clone();
+        let channel_id = channel_id.to_string();
+
+        tokio::spawn(async move {
+            for round in rounds {
+                let round_id = uuid::Uuid::new_v4().to_string();
+                for (position, candidate) in round.candidates.iter().enumerate() {
+                    if let Err(error) = sqlx::query(
+                        "INSERT INTO ensemble_candidates \
+                         (id, round_id, channel_id, position, model, chosen, judged_by, reason, \
+                          content, error, duration_ms, input_tokens, output_tokens) \
+                         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    )
+                    .bind(uuid::Uuid::new_v4().to_string())
+                    .bind(&round_id)
+                    .bind(&channel_id)
+                    .bind(position as i64)
+                    .bind(&candidate.model)
+                    .bind(position == round.chosen)
+                    .bind(&round.judged_by)
+                    .bind(&round.reason)
+                    .bind(&candidate.content)
+                    .bind(&candidate.error)
+                    .bind(candidate.duration_ms as i64)
+                    .bind(candidate.input_tokens as i64)
+                    .bind(candidate.output_tokens as i64)
+                    .execute(&pool)
+                    .timed("ensemble_candidates.insert")
+                    .await
+                    {
+                        tracing::warn!(%error, "failed to persist ensemble candidate");
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Render an answer for the judge and the log: text and reply content as
+/// written, other tool calls by name and arguments.
+fn describe(choice: &OneOrMany<AssistantContent>) -> String {
+    let mut parts = Vec::new();
+    for item in choice.iter() {
+        match item {
+            AssistantContent::Text(text) => parts.push(text.text.clone()),
+            AssistantContent::ToolCall(call) if call.function.name == "reply" => {
+                if let Some(content) = call.function.arguments["content"].as_str() {
+                    parts.push(content.to_string());
+                }
+            }
+            AssistantContent::ToolCall(call) => parts.push(format!(
+                "[calls `{}` with {}]",
+                call.function.name, call.function.arguments
+            )),
+            _ => {}
+        }
+    }
+    parts.join("\n\n")
+}
+
+/// The text of the most recent user message in the request.
+fn latest_user_text(request: &CompletionRequest) -> Option<String> {
+    request.chat_history.iter().rev().find_map(|message| {
+        let Message::User { content } = message else {
+            return None;
+        };
+        let text: Vec<&str> = content
+            .iter()
+            .filter_map(|item| match item {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        (!text.is_empty()).then(|| text.join("\n"))
+    })
+}
+
+/// The answered candidate whose words overlap most with the others'. Ties
+/// go to the earlier candidate, so the routed model wins them.
+fn consensus(candidates: &[EnsembleCandidate], answered: &[usize]) -> usize {
+    let words: Vec<HashSet<String>> = answered
+        .iter()
+        .map(|&index| {
+            candidates[index]
+                .content
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .collect();
+
+    let mut best = (answered[0], f64::MIN);
+    for (position, &index) in answered.iter().enumerate() {
+        let agreement: f64 = words
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != position)
+            .map(|(_, other_words)| {
+                let union = words[position].union(other_words).count();
+                if union == 0 {
+                    return 0.0;
+                }
+                words[position].intersection(other_words).count() as f64 / union as f64
+            })
+            .sum();
+        if agreement > best.1 {
+            best = (index, agreement);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(model: &str, content: Option<&str>) -> EnsembleCandidate {
+        EnsembleCandidate {
+            model: model.into(),
+            content: content.map(str::to_string),
+            error: content.is_none().then(|| "timed out".to_string()),
+            duration_ms: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn test_consensus_picks_the_answer_others_agree_with() {
+        let candidates = [
+            candidate(
+                "anthropic/a",
+                Some("The deploy failed on the migration step"),
+            ),
+            candidate("openai/b", None),
+            candidate("openai/c", Some("Deploy is green, nothing to do")),
+            candidate(
+                "openai/d",
+                Some("the deploy failed on the migration step, retrying"),
+            ),
+        ];
+        assert_eq!(consensus(&candidates, &[0, 2, 3]), 0);
+        // Agreement between two answers is symmetric, so the routed model wins.
+        assert_eq!(consensus(&candidates, &[0, 2]), 0);
+
+        let choice = OneOrMany::many(vec![
+            AssistantContent::text("Checking."),
+            AssistantContent::tool_call("1", "reply", serde_json::json!({"content": "All good."})),
+            AssistantContent::tool_call("2", "memory_recall", serde_json::json!({"q": "x"})),
+        ])
+        .unwrap();
+        assert_eq!(
+            describe(&choice),
+            "Checking.\n\nAll good.\n\n[calls `memory_recall` with {\"q\":\"x\"}]"
+        );
+    }
+}
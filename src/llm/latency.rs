@@ -0,0 +1,123 @@
+//! Generation latency per provider and model.
+//!
+//! Every successful provider call is timed, without retry backoff or time
+//! spent waiting for a concurrency slot. The last [`WINDOW`] samples of each
+//! model are kept in memory to answer `GET /api/models/latency` with
+//! percentiles. With the `metrics` feature the same samples also go to the
+//! `spacebot_llm_generation_duration_seconds` histogram.
+
+use serde::Serialize;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+static LATENCY: LazyLock<LatencyTracker> = LazyLock::new(LatencyTracker::default);
+
+/// Samples kept per model.
+const WINDOW: usize = 1_000;
+
+/// Recent generation latencies, keyed by full model name.
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    models: Mutex<HashMap<String, VecDeque<Duration>>>,
+}
+
+/// Latency percentiles of one model's recent calls.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelLatency {
+    pub provider: String,
+    /// Full model name, `provider/model`.
+    pub model: String,
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyTracker {
+    pub fn global() -> &'static Self {
+        &LATENCY
+    }
+
+    /// Record one successful call to `model`, a full `provider/model` name.
+    pub fn record(&self, model: &str, elapsed: Duration) {
+        #[cfg(feature = "metrics")]
+        crate::telemetry::Metrics::global()
+            .llm_generation_duration_seconds
+            .with_label_values(&[provider(model), model])
+            .observe(elapsed.as_secs_f64());
+
+        let mut models = self.models.lock().expect("latency lock poisoned");
+        let samples = models.entry(model.to_string()).or_default();
+        if samples.len() == WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+    }
+
+    /// Percentiles for every model called so far, slowest p95 first.
+    pub fn summary(&self) -> Vec<ModelLatency> {
+        let models = self.models.lock().expect("latency lock poisoned");
+        let mut summary: Vec<ModelLatency> = models
+            .iter()
+            .filter(|(_, samples)| !samples.is_empty())
+            .map(|(model, samples)| {
+                let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+                sorted.sort_unstable();
+                let percentile = |p: f64| {
+                    // Nearest rank
+                    let rank = (p * sorted.len() as f64).ceil() as usize;
+                    sorted[rank.clamp(1, sorted.len()) - 1].as_millis() as u64
+                };
+                ModelLatency {
+                    provider: provider(model).to_string(),
+                    model: model.clone(),
+                    samples: sorted.len(),
+                    p50_ms: percentile(0.50),
+                    p95_ms: percentile(0.95),
+                    p99_ms: percentile(0.99),
+                    max_ms: percentile(1.0),
+                }
+            })
+            .collect();
+        summary.sort_by(|a, b| b.p95_ms.cmp(&a.p95_ms).then_with(|| a.model.cmp(&b.model)));
+        summary
+    }
+}
+
+fn provider(model: &str) -> &str {
+    model
+        .split_once('/')
+        .map_or(model, |(provider, _)| provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_reports_percentiles_over_the_window() {
+        let tracker = LatencyTracker::default();
+        for ms in 1..=100 {
+            tracker.record("openai/gpt-4.1", Duration::from_millis(ms));
+        }
+        for _ in 0..WINDOW + 5 {
+            tracker.record("anthropic/claude-sonnet-4", Duration::from_secs(2));
+        }
+
+        let summary = tracker.summary();
+        assert_eq!(summary[0].model, "anthropic/claude-sonnet-4");
+        assert_eq!(summary[0].samples, WINDOW);
+        assert_eq!(summary[0].p99_ms, 2_000);
+
+        let openai = &summary[1];
+        assert_eq!(openai.provider, "openai");
+        assert_eq!(openai.samples, 100);
+        assert_eq!(
+            (openai.p50_ms, openai.p95_ms, openai.p99_ms, openai.max_ms),
+            (50, 95, 99, 100)
+        );
+    }
+}
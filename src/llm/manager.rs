@@ -8,8 +8,14 @@
 //! `reload_config()` when config.toml changes, and all subsequent
 //! `get_api_key()` calls read the new values lock-free.
 
-use crate::config::{LlmConfig, ProviderConfig};
+use crate::agent::health::HealthRegistry;
+use crate::config::{LlmCacheConfig, LlmCallLogConfig, LlmConfig, ProviderConfig};
 use crate::error::{LlmError, Result};
+use crate::llm::cache::ResponseCache;
+use crate::llm::call_log::CallLog;
+use crate::llm::concurrency::ConcurrencyLimiter;
+use crate::llm::mock::MockProvider;
+use crate::llm::throttle::AdaptiveThrottle;
 use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use std::collections::HashMap;
@@ -23,6 +29,18 @@ pub struct LlmManager {
     http_client: reqwest::Client,
     /// Models currently in rate limit cooldown, with the time they were limited.
     rate_limited: Arc<RwLock<HashMap<String, Instant>>>,
+    /// Cached completions, used when `[llm.cache]` is enabled.
+    response_cache: ResponseCache,
+    /// Global and per-agent limits on in-flight completions.
+    concurrency: ConcurrencyLimiter,
+    /// Per-agent slowdown after provider rate limits and overload errors.
+    throttle: AdaptiveThrottle,
+    /// Per-agent liveness, fed by every completion.
+    health: Arc<HealthRegistry>,
+    /// Agent databases completions are recorded in.
+    call_log: CallLog,
+    /// Offline provider answering `mock/*` models. Only set by tests.
+    mock: Option<Arc<MockProvider>>,
 }
 
 impl LlmManager {
@@ -34,14 +52,41 @@ impl LlmManager {
             .with_context(|| "failed to build HTTP client")?;
 
         Ok(Self {
+            concurrency: ConcurrencyLimiter::new(config.concurrency),
+            throttle: AdaptiveThrottle::new(config.throttle),
             config: ArcSwap::from_pointee(config),
             http_client,
             rate_limited: Arc::new(RwLock::new(HashMap::new())),
+            response_cache: ResponseCache::default(),
+            health: Arc::default(),
+            call_log: CallLog::default(),
+            mock: None,
         })
     }
 
+    /// Report call outcomes to a shared health registry. The main loop keeps
+    /// one registry across manager rebuilds so restart subscriptions survive.
+    pub fn with_health_registry(mut self, health: Arc<HealthRegistry>) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Answer `mock/*` models from a [`MockProvider`] instead of a real API.
+    pub fn with_mock_provider(mut self, mock: Arc<MockProvider>) -> Self {
+        self.mock = Some(mock);
+        self
+    }
+
+    /// The attached mock provider, if any.
+    pub fn mock_provider(&self) -> Option<&Arc<MockProvider>> {
+        self.mock.as_ref()
+    }
+
     /// Atomically swap in new provider credentials.
     pub fn reload_config(&self, config: LlmConfig) {
+        if !config.cache.enabled {
+            self.response_cache.clear();
+        }
         self.config.store(Arc::new(config));
         tracing::info!("LLM provider keys reloaded");
     }
@@ -73,6 +118,50 @@ impl LlmManager {
         self.config.load().ollama_base_url.clone()
     }
 
+    /// Current response cache settings.
+    pub fn cache_config(&self) -> LlmCacheConfig {
+        self.config.load().cache
+    }
+
+    /// Effective context window for a model: the agent's configured window,
+    /// capped by the model's limit from `[llm.context_windows]` if one is set.
+    pub fn context_window(&self, model_name: &str, agent_window: usize) -> usize {
+        match self.config.load().context_windows.get(model_name) {
+            Some(&model_window) => model_window.min(agent_window),
+            None => agent_window,
+        }
+    }
+
+    /// Shared response cache. Check `cache_config().enabled` before using it.
+    pub fn response_cache(&self) -> &ResponseCache {
+        &self.response_cache
+    }
+
+    /// Shared limiter for in-flight completions.
+    pub fn concurrency(&self) -> &ConcurrencyLimiter {
+        &self.concurrency
+    }
+
+    /// Shared per-agent throttle for provider pushback.
+    pub fn throttle(&self) -> &AdaptiveThrottle {
+        &self.throttle
+    }
+
+    /// Current call log settings.
+    pub fn call_log_config(&self) -> LlmCallLogConfig {
+        self.config.load().call_log
+    }
+
+    /// Shared record of completion calls.
+    pub fn call_log(&self) -> &CallLog {
+        &self.call_log
+    }
+
+    /// Shared per-agent health registry.
+    pub fn health(&self) -> &Arc<HealthRegistry> {
+        &self.health
+    }
+
     /// Get the HTTP client.
     pub fn http_client(&self) -> &reqwest::Client {
         &self.http_client
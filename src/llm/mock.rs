@@ -0,0 +1,244 @@
+//! Deterministic mock provider for offline tests.
+
+use rig::completion::CompletionRequest;
+use rig::message::{Message, UserContent};
+use sha2::{Digest as _, Sha256};
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// A canned reply from the mock provider.
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    /// Plain assistant text.
+    Text(String),
+    /// A single tool call. Rig runs the tool and calls the model again with
+    /// the result, so follow-up responses are usually scripted after it.
+    ToolCall {
+        name: String,
+        arguments: serde_json::Value,
+    },
+    /// A provider error. Use a retriable message (e.g. "503") to exercise
+    /// retry and fallback paths.
+    Error(String),
+}
+
+impl MockResponse {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn tool_call(name: impl Into<String>, arguments: serde_json::Value) -> Self {
+        Self::ToolCall {
+            name: name.into(),
+            arguments,
+        }
+    }
+}
+
+/// A request the mock provider received, kept for assertions.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// Model name without the `mock/` prefix.
+    pub model: String,
+    pub preamble: Option<String>,
+    /// Text of the last message in the chat history.
+    pub prompt: String,
+    /// [`MockProvider::prompt_hash`] of `prompt`.
+    pub prompt_hash: String,
+    /// Names of the tools offered to the model.
+    pub tools: Vec<String>,
+}
+
+/// LLM provider that replays canned responses instead of calling an API.
+///
+/// Attach it with [`LlmManager::with_mock_provider`](crate::llm::LlmManager::with_mock_provider)
+/// and route any process to a `mock/<name>` model. Each completion is answered by,
+/// in order:
+///
+/// 1. the next response registered for the prompt's hash (`on_prompt` / `on_hash`),
+/// 2. the next response in the script (`then`),
+/// 3. the fallback response, if one is set.
+///
+/// If none of these match, the provider returns an error naming the prompt hash,
+/// so a failing test shows which key to register.
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    keyed: Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    script: Mutex<VecDeque<MockResponse>>,
+    fallback: Option<MockResponse>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stable key for a prompt: the first 16 hex chars of the SHA-256 of the
+    /// trimmed text.
+    pub fn prompt_hash(prompt: &str) -> String {
+        let digest = Sha256::digest(prompt.trim().as_bytes());
+        digest
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Answer `prompt` with `response`. Registering the same prompt several
+    /// times replays the responses in order.
+    pub fn on_prompt(self, prompt: &str, response: MockResponse) -> Self {
+        self.on_hash(&Self::prompt_hash(prompt), response)
+    }
+
+    /// Answer the prompt with this hash with `response`.
+    pub fn on_hash(self, hash: &str, response: MockResponse) -> Self {
+        self.keyed
+            .lock()
+            .expect("mock provider lock poisoned")
+            .entry(hash.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Append a response to the script used for prompts with no keyed match.
+    pub fn then(self, response: MockResponse) -> Self {
+        self.script
+            .lock()
+            .expect("mock provider lock poisoned")
+            .push_back(response);
+        self
+    }
+
+    /// Response used once keyed and scripted responses are exhausted.
+    pub fn with_fallback(mut self, response: MockResponse) -> Self {
+        self.fallback = Some(response);
+        self
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests
+            .lock()
+            .expect("mock provider lock poisoned")
+            .clone()
+    }
+
+    /// Answer a completion request with an Anthropic-shaped response body, so
+    /// it goes through the same parsing as a real provider call.
+    pub(crate) fn respond(
+        &self,
+        model: &str,
+        request: &CompletionRequest,
+    ) -> std::result::Result<serde_json::Value, String> {
+        let prompt = last_message_text(request.chat_history.last());
+        let prompt_hash = Self::prompt_hash(&prompt);
+
+        self.requests
+            .lock()
+            .expect("mock provider lock poisoned")
+            .push(MockRequest {
+                model: model.to_string(),
+                preamble: request.preamble.clone(),
+                prompt: prompt.clone(),
+                prompt_hash: prompt_hash.clone(),
+                tools: request.tools.iter().map(|tool| tool.name.clone()).collect(),
+            });
+
+        let keyed = self
+            .keyed
+            .lock()
+            .expect("mock provider lock poisoned")
+            .get_mut(&prompt_hash)
+            .and_then(VecDeque::pop_front);
+        let response = keyed
+            .or_else(|| {
+                self.script
+                    .lock()
+                    .expect("mock provider lock poisoned")
+                    .pop_front()
+            })
+            .or_else(|| self.fallback.clone())
+            .ok_or_else(|| {
+                format!("mock provider has no response for prompt hash {prompt_hash}: {prompt:?}")
+            })?;
+
+        let call_index = self
+            .requests
+            .lock()
+            .expect("mock provider lock poisoned")
+            .len();
+        let content = match response {
+            MockResponse::Text(text) => serde_json::json!([{ "type": "text", "text": text }]),
+            MockResponse::ToolCall { name, arguments } => serde_json::json!([{
+                "type": "tool_use",
+                "id": format!("mock_call_{call_index}"),
+                "name": name,
+                "input": arguments,
+            }]),
+            MockResponse::Error(message) => return Err(message),
+        };
+
+        Ok(serde_json::json!({
+            "content": content,
+            "usage": {
+                "input_tokens": prompt.len() / 4,
+                "output_tokens": 0,
+            },
+        }))
+    }
+}
+
+/// Flatten the text parts of a message. Tool results are included so a
+/// multi-step loop can be keyed on what the tool returned.
+fn last_message_text(message: Message) -> String {
+    match message {
+        Message::User { content } => content
+            .into_iter()
+            .filter_map(|part| match part {
+                UserContent::Text(text) => Some(text.text),
+                UserContent::ToolResult(result) => Some(
+                    result
+                        .content
+                        .into_iter()
+                        .filter_map(|part| match part {
+                            rig::message::ToolResultContent::Text(text) => Some(text.text),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Message::Assistant { content, .. } => content
+            .into_iter()
+            .filter_map(|part| match part {
+                rig::message::AssistantContent::Text(text) => Some(text.text),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_hash_is_stable_and_trimmed() {
+        assert_eq!(
+            MockProvider::prompt_hash("hello"),
+            MockProvider::prompt_hash("  hello\n")
+        );
+        assert_eq!(MockProvider::prompt_hash("hello").len(), 16);
+        assert_ne!(
+            MockProvider::prompt_hash("hello"),
+            MockProvider::prompt_hash("goodbye")
+        );
+    }
+}
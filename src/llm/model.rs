@@ -1,11 +1,18 @@
 //! SpacebotModel: Custom CompletionModel implementation that routes through LlmManager.
 
 use crate::config::{ApiType, ProviderConfig};
+use crate::llm::cache::ResponseCache;
+use crate::llm::call_log::{self, LlmCall};
+use crate::llm::ensemble::Ensemble;
+use crate::llm::latency::LatencyTracker;
 use crate::llm::manager::LlmManager;
 use crate::llm::routing::{
     self, MAX_FALLBACK_ATTEMPTS, MAX_RETRIES_PER_MODEL, RETRY_BASE_DELAY_MS, RoutingConfig,
 };
+use crate::llm::streaming::{self, StreamFormat, TokenSink};
+use crate::llm::throttle;
 
+use opentelemetry::KeyValue;
 use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest, GetTokenUsage};
 use rig::message::{
     AssistantContent, DocumentSourceKind, Image, Message, MimeType, Text, ToolCall, ToolFunction,
@@ -22,8 +29,8 @@ pub struct RawResponse {
     pub body: serde_json::Value,
 }
 
-/// Streaming response placeholder. Streaming will be implemented per-provider
-/// when we wire up SSE parsing.
+/// Streaming response placeholder. Rig's streaming API isn't used; token
+/// streaming goes through a token sink instead (see `llm::streaming`).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawStreamingResponse {
     pub body: serde_json::Value,
@@ -46,6 +53,14 @@ pub struct SpacebotModel {
     provider: String,
     full_model_name: String,
     routing: Option<RoutingConfig>,
+    /// Agent whose concurrency limit and health this model's calls count against.
+    agent_id: Option<String>,
+    /// Turn the calls are made for, recorded on their spans.
+    turn_id: Option<String>,
+    /// Receives output as it's generated. When set, provider calls stream.
+    token_sink: Option<TokenSink>,
+    /// When set, each call is answered by the ensemble instead.
+    ensemble: Option<Arc<Ensemble>>,
 }
 
 impl SpacebotModel {
@@ -59,16 +74,68 @@ impl SpacebotModel {
         &self.full_model_name
     }
 
+    /// Count calls against an agent's concurrency limit and health.
+    pub fn with_agent(mut self, agent_id: &str) -> Self {
+        self.agent_id = Some(agent_id.to_string());
+        self
+    }
+
+    /// Tag calls with the turn they're made for; see [`crate::TURN_ID_KEY`].
+    pub fn with_turn_id(mut self, turn_id: Option<String>) -> Self {
+        self.turn_id = turn_id;
+        self
+    }
+
     /// Attach routing config for fallback behavior.
     pub fn with_routing(mut self, routing: RoutingConfig) -> Self {
         self.routing = Some(routing);
         self
     }
 
-    /// Direct call to the provider (no fallback logic).
+    /// Stream provider responses, forwarding tokens to the sink as they arrive.
+    pub fn with_token_sink(mut self, sink: TokenSink) -> Self {
+        self.token_sink = Some(sink);
+        self
+    }
+
+    /// Answer every call with an ensemble of models, this one first.
+    pub fn with_ensemble(mut self, ensemble: Arc<Ensemble>) -> Self {
+        self.ensemble = Some(ensemble);
+        self
+    }
+
+    /// This model's agent and routing, pointed at another model.
+    pub(crate) fn sibling(&self, model_name: &str) -> Self {
+        Self {
+            routing: self.routing.clone(),
+            agent_id: self.agent_id.clone(),
+            turn_id: self.turn_id.clone(),
+            ..Self::make(&self.llm_manager, model_name)
+        }
+    }
+
+    /// Turn on streaming in a request body when there's a token sink.
+    fn enable_streaming(&self, body: &mut serde_json::Value, format: StreamFormat) {
+        if self.token_sink.is_some() {
+            format.enable(body);
+        }
+    }
+
+    /// Direct call to the provider (no fallback logic). Successful calls
+    /// count toward the model's latency percentiles.
     async fn attempt_completion(
         &self,
         request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let start = std::time::Instant::now();
+        let response = self.call_provider(request).await?;
+        LatencyTracker::global().record(&self.full_model_name, start.elapsed());
+        Ok(response)
+    }
+
+    async fn call_provider(
+        &self,
+        request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
         let provider_id = self
             .full_model_name
@@ -76,6 +143,15 @@ impl SpacebotModel {
             .map(|(provider, _)| provider)
             .unwrap_or("anthropic");
 
+        if provider_id == "mock"
+            && let Some(mock) = self.llm_manager.mock_provider()
+        {
+            let body = mock
+                .respond(&self.model_name, &request)
+                .map_err(CompletionError::ProviderError)?;
+            return parse_anthropic_response(body);
+        }
+
         let provider_config = self
             .llm_manager
             .get_provider(provider_id)
@@ -113,26 +189,44 @@ impl SpacebotModel {
         let model = if model_name == self.full_model_name {
             self.clone()
         } else {
-            SpacebotModel::make(&self.llm_manager, model_name)
+            SpacebotModel {
+                token_sink: self.token_sink.clone(),
+                ..SpacebotModel::make(&self.llm_manager, model_name)
+            }
         };
 
         let mut last_error = None;
         for attempt in 0..MAX_RETRIES_PER_MODEL {
             if attempt > 0 {
-                let delay_ms = RETRY_BASE_DELAY_MS * 2u64.pow((attempt - 1) as u32);
+                // Never retry faster than the throttle lets new calls start.
+                let delay_ms = (RETRY_BASE_DELAY_MS * 2u64.pow((attempt - 1) as u32)).max(
+                    self.agent_id
+                        .as_deref()
+                        .map_or(0, |agent_id| self.llm_manager.throttle().delay_ms(agent_id)),
+                );
                 tracing::debug!(
                     model = %model_name,
                     attempt = attempt + 1,
                     delay_ms,
                     "retrying after backoff"
                 );
-                tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+                tokio::time::sleep(throttle::jitter(delay_ms)).await;
             }
 
             match model.attempt_completion(request.clone()).await {
-                Ok(response) => return Ok(response),
+                Ok(response) => {
+                    if let Some(agent_id) = &self.agent_id {
+                        self.llm_manager.throttle().record_success(agent_id);
+                    }
+                    return Ok(response);
+                }
                 Err(error) => {
                     let error_str = error.to_string();
+                    if let Some(agent_id) = &self.agent_id
+                        && throttle::is_pushback_error(&error_str)
+                    {
+                        self.llm_manager.throttle().record_pushback(agent_id);
+                    }
                     if !routing::is_retriable_error(&error_str) {
                         // Non-retriable (auth error, bad request, etc) — bail immediately
                         return Err((error, false));
@@ -185,14 +279,72 @@ impl CompletionModel for SpacebotModel {
             provider,
             full_model_name,
             routing: None,
+            agent_id: None,
+            turn_id: None,
+            token_sink: None,
+            ensemble: None,
         }
     }
 
+    #[tracing::instrument(
+        name = "llm.completion",
+        skip_all,
+        fields(
+            model = %self.full_model_name,
+            agent_id = self.agent_id.as_deref(),
+            turn_id = self.turn_id.as_deref(),
+            input_tokens,
+            output_tokens,
+        )
+    )]
     async fn completion(
         &self,
         request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
-        #[cfg(feature = "metrics")]
+        if let Some(ensemble) = &self.ensemble {
+            // Candidates don't stream: only the chosen one reaches the user.
+            let primary = Self {
+                token_sink: None,
+                ensemble: None,
+                ..self.clone()
+            };
+            return ensemble.complete(&primary, request).await;
+        }
+
+        let cache_config = self.llm_manager.cache_config();
+        let cache_key = cache_config
+            .enabled
+            .then(|| ResponseCache::key(&self.full_model_name, &request));
+        if let Some(key) = &cache_key
+            && let Some(response) = self.llm_manager.response_cache().get(key, &cache_config)
+        {
+            tracing::debug!(model = %self.full_model_name, "LLM response cache hit");
+            self.record_cache_hit(&request, &response);
+            return Ok(response);
+        }
+
+        let _pending = self
+            .agent_id
+            .as_deref()
+            .map(|agent_id| self.llm_manager.health().call_started(agent_id));
+
+        // Throttled agents wait here, before taking a concurrency slot.
+        let _throttle = match self.agent_id.as_deref() {
+            Some(agent_id) => self.llm_manager.throttle().admit(agent_id).await,
+            None => None,
+        };
+
+        let _permit = self
+            .llm_manager
+            .concurrency()
+            .acquire(self.agent_id.as_deref())
+            .await?;
+
+        // Rendered up front, since the request moves into the call.
+        let call_log_config = self.llm_manager.call_log_config();
+        let prompt = (self.agent_id.is_some() && call_log_config.enabled)
+            .then(|| call_log::render_prompt(&request));
+
         let start = std::time::Instant::now();
 
         let result = async move {
@@ -287,9 +439,20 @@ impl CompletionModel for SpacebotModel {
         }
         .await;
 
+        let elapsed = start.elapsed().as_secs_f64();
+        self.record_otel_metrics(&result, elapsed);
+        if let Some(agent_id) = &self.agent_id
+            && call_log_config.enabled
+        {
+            self.llm_manager.call_log().record(
+                &call_log_config,
+                agent_id,
+                self.call_record(&result, start.elapsed(), prompt),
+            );
+        }
+
         #[cfg(feature = "metrics")]
         {
-            let elapsed = start.elapsed().as_secs_f64();
             let metrics = crate::telemetry::Metrics::global();
             // TODO: agent_id and tier are "unknown" because SpacebotModel doesn't
             // carry process context. Thread agent_id/ProcessType through to get
@@ -304,6 +467,22 @@ impl CompletionModel for SpacebotModel {
                 .observe(elapsed);
         }
 
+        if let Some(agent_id) = &self.agent_id {
+            match &result {
+                Ok(_) => self.llm_manager.health().record_success(agent_id),
+                Err(error) => self
+                    .llm_manager
+                    .health()
+                    .record_failure(agent_id, &error.to_string()),
+            }
+        }
+
+        if let (Some(key), Ok(response)) = (cache_key, &result) {
+            self.llm_manager
+                .response_cache()
+                .insert(key, response, &cache_config);
+        }
+
         result
     }
 
@@ -318,6 +497,97 @@ impl CompletionModel for SpacebotModel {
 }
 
 impl SpacebotModel {
+    /// Record a completion call on the OTel instruments and its token usage
+    /// on the current `llm.completion` span.
+    fn record_otel_metrics(
+        &self,
+        result: &Result<completion::CompletionResponse<RawResponse>, CompletionError>,
+        elapsed: f64,
+    ) {
+        let metrics = crate::otel::Metrics::global();
+        let model = KeyValue::new("model", self.full_model_name.clone());
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        let attributes = [model.clone(), KeyValue::new("outcome", outcome)];
+        metrics.llm_requests.add(1, &attributes);
+        metrics.llm_request_duration.record(elapsed, &attributes);
+
+        if let Ok(response) = result {
+            let usage = response.usage;
+            let span = tracing::Span::current();
+            span.record("input_tokens", usage.input_tokens);
+            span.record("output_tokens", usage.output_tokens);
+            for (direction, tokens) in [
+                ("input", usage.input_tokens),
+                ("output", usage.output_tokens),
+            ] {
+                metrics.llm_tokens.add(
+                    tokens,
+                    &[model.clone(), KeyValue::new("direction", direction)],
+                );
+            }
+        }
+    }
+
+    /// Count a response served from the cache as a request with outcome
+    /// `cached`, and log it as a call with zero tokens, so usage shows how
+    /// often the cache answered without the hits adding to cost.
+    fn record_cache_hit(
+        &self,
+        request: &CompletionRequest,
+        response: &completion::CompletionResponse<RawResponse>,
+    ) {
+        let attributes = [
+            KeyValue::new("model", self.full_model_name.clone()),
+            KeyValue::new("outcome", "cached"),
+        ];
+        crate::otel::Metrics::global()
+            .llm_requests
+            .add(1, &attributes);
+
+        let Some(agent_id) = self.agent_id.as_deref() else {
+            return;
+        };
+        let call_log_config = self.llm_manager.call_log_config();
+        let call = LlmCall {
+            model: self.full_model_name.clone(),
+            turn_id: self.turn_id.clone(),
+            prompt: call_log_config
+                .enabled
+                .then(|| call_log::render_prompt(request)),
+            response: serde_json::to_string(&response.choice).ok(),
+            cached: true,
+            ..Default::default()
+        };
+        self.llm_manager
+            .call_log()
+            .record(&call_log_config, agent_id, call);
+    }
+
+    /// A finished call, as it goes into the call log.
+    fn call_record(
+        &self,
+        result: &Result<completion::CompletionResponse<RawResponse>, CompletionError>,
+        elapsed: std::time::Duration,
+        prompt: Option<String>,
+    ) -> LlmCall {
+        let mut call = LlmCall {
+            model: self.full_model_name.clone(),
+            turn_id: self.turn_id.clone(),
+            duration_ms: elapsed.as_millis() as i64,
+            prompt,
+            ..Default::default()
+        };
+        match result {
+            Ok(response) => {
+                call.input_tokens = response.usage.input_tokens;
+                call.output_tokens = response.usage.output_tokens;
+                call.response = serde_json::to_string(&response.choice).ok();
+            }
+            Err(error) => call.error = Some(error.to_string()),
+        }
+        call
+    }
+
     async fn call_anthropic(
         &self,
         request: CompletionRequest,
@@ -357,6 +627,7 @@ impl SpacebotModel {
                 .collect();
             body["tools"] = serde_json::json!(tools);
         }
+        self.enable_streaming(&mut body, StreamFormat::Anthropic);
 
         let response = self
             .llm_manager
@@ -371,6 +642,12 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(sink) = &self.token_sink
+        {
+            let response_body = streaming::collect(response, StreamFormat::Anthropic, sink).await?;
+            return parse_anthropic_response(response_body);
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -443,6 +720,7 @@ impl SpacebotModel {
                 .collect();
             body["tools"] = serde_json::json!(tools);
         }
+        self.enable_streaming(&mut body, StreamFormat::OpenAiChat);
 
         let chat_completions_url = format!(
             "{}/v1/chat/completions",
@@ -469,6 +747,13 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(sink) = &self.token_sink
+        {
+            let response_body =
+                streaming::collect(response, StreamFormat::OpenAiChat, sink).await?;
+            return parse_openai_response(response_body, "OpenAI");
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -536,6 +821,7 @@ impl SpacebotModel {
                 .collect();
             body["tools"] = serde_json::json!(tools);
         }
+        self.enable_streaming(&mut body, StreamFormat::OpenAiResponses);
 
         let response = self
             .llm_manager
@@ -549,6 +835,13 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(sink) = &self.token_sink
+        {
+            let response_body =
+                streaming::collect(response, StreamFormat::OpenAiResponses, sink).await?;
+            return parse_openai_responses_response(response_body);
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -634,6 +927,7 @@ impl SpacebotModel {
                 .collect();
             body["tools"] = serde_json::json!(tools);
         }
+        self.enable_streaming(&mut body, StreamFormat::OpenAiChat);
 
         let response = self
             .llm_manager
@@ -647,6 +941,13 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(sink) = &self.token_sink
+        {
+            let response_body =
+                streaming::collect(response, StreamFormat::OpenAiChat, sink).await?;
+            return parse_openai_response(response_body, provider_display_name);
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
@@ -720,6 +1021,7 @@ impl SpacebotModel {
                 .collect();
             body["tools"] = serde_json::json!(tools);
         }
+        self.enable_streaming(&mut body, StreamFormat::OpenAiChat);
 
         let response = self.llm_manager.http_client().post(endpoint);
 
@@ -737,6 +1039,13 @@ impl SpacebotModel {
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
         let status = response.status();
+        if status.is_success()
+            && let Some(sink) = &self.token_sink
+        {
+            let response_body =
+                streaming::collect(response, StreamFormat::OpenAiChat, sink).await?;
+            return parse_openai_response(response_body, provider_display_name);
+        }
         let response_text = response.text().await.map_err(|e| {
             CompletionError::ProviderError(format!("failed to read response body: {e}"))
         })?;
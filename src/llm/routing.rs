@@ -6,7 +6,7 @@ use std::collections::HashMap;
 /// Model routing configuration. Lives on the agent config (via defaults).
 /// Determines which LLM model each process type uses, with task-type
 /// overrides for workers/branches and fallback chains for resilience.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
 pub struct RoutingConfig {
     /// Model per process type.
     pub channel: String,
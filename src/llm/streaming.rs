@@ -0,0 +1,374 @@
+//! Token streaming: forward model output to a sink as it's generated.
+//!
+//! Rig's prompt loop still sees one complete response per LLM call. When a
+//! `SpacebotModel` has a token sink, the provider request is sent with
+//! streaming on, each text or tool-argument delta is forwarded to the sink as
+//! it arrives, and the events are folded back into the body the provider
+//! would have returned without streaming, so the usual response parsers apply.
+
+use rig::completion::CompletionError;
+
+use futures::StreamExt as _;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+
+/// A piece of model output, in generation order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenDelta {
+    /// A new LLM response began. Output streamed before it belongs to an
+    /// earlier call or to an attempt that was retried.
+    Start,
+    Text(String),
+    /// The model started a tool call. `ToolArgs` that follow belong to it.
+    ToolCallStart {
+        tool_name: String,
+    },
+    /// A fragment of the current tool call's JSON arguments.
+    ToolArgs(String),
+}
+
+pub type TokenSink = mpsc::UnboundedSender<TokenDelta>;
+
+/// Wire format of a provider's event stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    Anthropic,
+    OpenAiChat,
+    OpenAiResponses,
+}
+
+impl StreamFormat {
+    /// Turn on streaming in a request body.
+    pub fn enable(self, body: &mut serde_json::Value) {
+        body["stream"] = serde_json::json!(true);
+        if self == StreamFormat::OpenAiChat {
+            // Usage is only reported in the final chunk when asked for.
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
+    }
+}
+
+/// Read a successful streaming response to the end, forwarding deltas to the
+/// sink, and return the equivalent non-streaming response body.
+pub async fn collect(
+    response: reqwest::Response,
+    format: StreamFormat,
+    sink: &TokenSink,
+) -> Result<serde_json::Value, CompletionError> {
+    let _ = sink.send(TokenDelta::Start);
+    let mut accumulator = Accumulator::new(format);
+    let mut bytes = response.bytes_stream();
+    let mut buffer = Vec::new();
+
+    while let Some(chunk) = bytes.next().await {
+        let chunk = chunk.map_err(|error| {
+            CompletionError::ProviderError(format!("stream interrupted: {error}"))
+        })?;
+        buffer.extend_from_slice(&chunk);
+        while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            accumulator.push_line(&String::from_utf8_lossy(&line), sink)?;
+        }
+    }
+    if !buffer.is_empty() {
+        accumulator.push_line(&String::from_utf8_lossy(&buffer), sink)?;
+    }
+
+    accumulator.finish()
+}
+
+/// Folds stream events into a response body.
+#[derive(Debug)]
+enum Accumulator {
+    Anthropic {
+        message: serde_json::Value,
+        /// Content blocks by index, with the raw JSON of tool_use inputs.
+        blocks: BTreeMap<u64, (serde_json::Value, String)>,
+    },
+    OpenAiChat {
+        content: String,
+        reasoning_content: String,
+        /// Tool calls by index: id, name, arguments.
+        tool_calls: BTreeMap<u64, (String, String, String)>,
+        usage: serde_json::Value,
+    },
+    OpenAiResponses {
+        response: serde_json::Value,
+    },
+}
+
+impl Accumulator {
+    fn new(format: StreamFormat) -> Self {
+        match format {
+            StreamFormat::Anthropic => Accumulator::Anthropic {
+                message: serde_json::json!({}),
+                blocks: BTreeMap::new(),
+            },
+            StreamFormat::OpenAiChat => Accumulator::OpenAiChat {
+                content: String::new(),
+                reasoning_content: String::new(),
+                tool_calls: BTreeMap::new(),
+                usage: serde_json::Value::Null,
+            },
+            StreamFormat::OpenAiResponses => Accumulator::OpenAiResponses {
+                response: serde_json::Value::Null,
+            },
+        }
+    }
+
+    /// Handle one line of the event stream. Only `data:` lines carry
+    /// anything; every format repeats the event name in the payload.
+    fn push_line(&mut self, line: &str, sink: &TokenSink) -> Result<(), CompletionError> {
+        let Some(data) = line.trim_end().strip_prefix("data:") else {
+            return Ok(());
+        };
+        let data = data.trim_start();
+        if data.is_empty() || data == "[DONE]" {
+            return Ok(());
+        }
+        let event: serde_json::Value = serde_json::from_str(data).map_err(|error| {
+            CompletionError::ProviderError(format!("invalid stream event: {error}"))
+        })?;
+        self.push(&event, sink)
+    }
+
+    fn push(&mut self, event: &serde_json::Value, sink: &TokenSink) -> Result<(), CompletionError> {
+        if let Some(message) = event["error"]["message"].as_str() {
+            return Err(stream_error(event["error"]["type"].as_str(), message));
+        }
+
+        match self {
+            Accumulator::Anthropic { message, blocks } => match event["type"].as_str() {
+                Some("message_start") => *message = event["message"].clone(),
+                Some("content_block_start") => {
+                    let block = event["content_block"].clone();
+                    if block["type"].as_str() == Some("tool_use") {
+                        let tool_name = block["name"].as_str().unwrap_or("").to_string();
+                        let _ = sink.send(TokenDelta::ToolCallStart { tool_name });
+                    }
+                    let index = event["index"].as_u64().unwrap_or(0);
+                    blocks.insert(index, (block, String::new()));
+                }
+                Some("content_block_delta") => {
+                    let index = event["index"].as_u64().unwrap_or(0);
+                    let Some((block, input)) = blocks.get_mut(&index) else {
+                        return Ok(());
+                    };
+                    let delta = &event["delta"];
+                    match delta["type"].as_str() {
+                        Some("text_delta") => {
+                            let text = delta["text"].as_str().unwrap_or("");
+                            let joined = format!("{}{text}", block["text"].as_str().unwrap_or(""));
+                            block["text"] = serde_json::json!(joined);
+                            let _ = sink.send(TokenDelta::Text(text.to_string()));
+                        }
+                        Some("input_json_delta") => {
+                            let partial = delta["partial_json"].as_str().unwrap_or("");
+                            input.push_str(partial);
+                            let _ = sink.send(TokenDelta::ToolArgs(partial.to_string()));
+                        }
+                        _ => {}
+                    }
+                }
+                Some("message_delta") => {
+                    message["stop_reason"] = event["delta"]["stop_reason"].clone();
+                    if let Some(output_tokens) = event["usage"]["output_tokens"].as_u64() {
+                        message["usage"]["output_tokens"] = serde_json::json!(output_tokens);
+                    }
+                }
+                _ => {}
+            },
+            Accumulator::OpenAiChat {
+                content,
+                reasoning_content,
+                tool_calls,
+                usage,
+            } => {
+                if event["usage"].is_object() {
+                    *usage = event["usage"].clone();
+                }
+                let delta = &event["choices"][0]["delta"];
+                if let Some(text) = delta["content"].as_str()
+                    && !text.is_empty()
+                {
+                    content.push_str(text);
+                    let _ = sink.send(TokenDelta::Text(text.to_string()));
+                }
+                if let Some(text) = delta["reasoning_content"].as_str() {
+                    reasoning_content.push_str(text);
+                }
+                for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                    let index = call["index"].as_u64().unwrap_or(0);
+                    let (id, name, arguments) = tool_calls.entry(index).or_default();
+                    if let Some(call_id) = call["id"].as_str() {
+                        *id = call_id.to_string();
+                    }
+                    if let Some(tool_name) = call["function"]["name"].as_str()
+                        && name.is_empty()
+                    {
+                        *name = tool_name.to_string();
+                        let _ = sink.send(TokenDelta::ToolCallStart {
+                            tool_name: tool_name.to_string(),
+                        });
+                    }
+                    if let Some(fragment) = call["function"]["arguments"].as_str() {
+                        arguments.push_str(fragment);
+                        let _ = sink.send(TokenDelta::ToolArgs(fragment.to_string()));
+                    }
+                }
+            }
+            Accumulator::OpenAiResponses { response } => match event["type"].as_str() {
+                Some("response.output_text.delta") => {
+                    let text = event["delta"].as_str().unwrap_or("");
+                    let _ = sink.send(TokenDelta::Text(text.to_string()));
+                }
+                Some("response.output_item.added")
+                    if event["item"]["type"].as_str() == Some("function_call") =>
+                {
+                    let tool_name = event["item"]["name"].as_str().unwrap_or("").to_string();
+                    let _ = sink.send(TokenDelta::ToolCallStart { tool_name });
+                }
+                Some("response.function_call_arguments.delta") => {
+                    let fragment = event["delta"].as_str().unwrap_or("");
+                    let _ = sink.send(TokenDelta::ToolArgs(fragment.to_string()));
+                }
+                Some("response.completed") => *response = event["response"].clone(),
+                Some("response.failed") | Some("response.incomplete") => {
+                    let reason = event["response"]["error"]["message"]
+                        .as_str()
+                        .or_else(|| event["response"]["incomplete_details"]["reason"].as_str())
+                        .unwrap_or("unknown error");
+                    return Err(stream_error(
+                        event["response"]["error"]["code"].as_str(),
+                        reason,
+                    ));
+                }
+                _ => {}
+            },
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<serde_json::Value, CompletionError> {
+        match self {
+            Accumulator::Anthropic {
+                mut message,
+                blocks,
+            } => {
+                let content: Vec<serde_json::Value> = blocks
+                    .into_values()
+                    .map(|(mut block, input)| {
+                        if block["type"].as_str() == Some("tool_use") {
+                            block["input"] = serde_json::from_str(&input)
+                                .unwrap_or_else(|_| serde_json::json!({}));
+                        }
+                        block
+                    })
+                    .collect();
+                message["content"] = serde_json::json!(content);
+                Ok(message)
+            }
+            Accumulator::OpenAiChat {
+                content,
+                reasoning_content,
+                tool_calls,
+                usage,
+            } => {
+                let mut message = serde_json::json!({
+                    "role": "assistant",
+                    "content": content,
+                });
+                if !reasoning_content.is_empty() {
+                    message["reasoning_content"] = serde_json::json!(reasoning_content);
+                }
+                if !tool_calls.is_empty() {
+                    let calls: Vec<serde_json::Value> = tool_calls
+                        .into_values()
+                        .map(|(id, name, arguments)| {
+                            serde_json::json!({
+                                "id": id,
+                                "type": "function",
+                                "function": { "name": name, "arguments": arguments },
+                            })
+                        })
+                        .collect();
+                    message["tool_calls"] = serde_json::json!(calls);
+                }
+                Ok(serde_json::json!({
+                    "choices": [{ "message": message }],
+                    "usage": usage,
+                }))
+            }
+            Accumulator::OpenAiResponses { response } => {
+                if response.is_null() {
+                    return Err(CompletionError::ProviderError(
+                        "stream ended before the response completed".into(),
+                    ));
+                }
+                Ok(response)
+            }
+        }
+    }
+}
+
+/// An error event from the stream, keeping the provider's error type so
+/// callers can tell a rate limit or overload from other failures.
+fn stream_error(kind: Option<&str>, message: &str) -> CompletionError {
+    CompletionError::ProviderError(match kind {
+        Some(kind) => format!("stream error ({kind}): {message}"),
+        None => format!("stream error: {message}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anthropic_events_fold_into_response_body() {
+        let (sink, mut rx) = mpsc::unbounded_channel();
+        let mut accumulator = Accumulator::new(StreamFormat::Anthropic);
+        let lines = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"content":[],"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Checking"}}"#,
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"t1","name":"reply","input":{}}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"content\": \"Hi"}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":" there\"}"}}"#,
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":9}}"#,
+        ];
+        for line in lines {
+            accumulator.push_line(line, &sink).unwrap();
+        }
+
+        let body = accumulator.finish().unwrap();
+        assert_eq!(body["content"][0]["text"], "Checking");
+        assert_eq!(body["content"][1]["input"]["content"], "Hi there");
+        assert_eq!(body["usage"]["output_tokens"], 9);
+
+        let mut deltas = Vec::new();
+        while let Ok(delta) = rx.try_recv() {
+            deltas.push(delta);
+        }
+        assert_eq!(deltas[0], TokenDelta::Text("Checking".into()));
+        assert_eq!(
+            deltas[1],
+            TokenDelta::ToolCallStart {
+                tool_name: "reply".into()
+            }
+        );
+        assert_eq!(deltas.len(), 4);
+
+        let error =
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let error = Accumulator::new(StreamFormat::Anthropic)
+            .push_line(error, &sink)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ProviderError: stream error (overloaded_error): Overloaded"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Structured output: prompt for JSON matching a schema, validate, and retry.
+
+use crate::prompts::PromptEngine;
+
+use rig::agent::Agent;
+use rig::completion::{CompletionModel, Prompt};
+use serde_json::Value;
+
+/// Attempts used when the caller doesn't ask for a specific number.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+/// A validated JSON answer.
+#[derive(Debug, Clone)]
+pub struct StructuredOutput {
+    pub value: Value,
+    /// LLM calls it took to get a valid answer, including the first.
+    pub attempts: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StructuredOutputError {
+    #[error("failed to render structured output prompt: {0}")]
+    Render(String),
+
+    #[error("structured output completion failed: {0}")]
+    Completion(String),
+
+    #[error("no valid JSON after {attempts} attempts: {}", errors.join("; "))]
+    Invalid {
+        attempts: usize,
+        errors: Vec<String>,
+        raw: String,
+    },
+}
+
+/// Ask `agent` for a JSON value that conforms to `schema`.
+///
+/// The prompt is wrapped with the schema and an instruction to answer with
+/// JSON only. Each response is parsed and validated; on failure the errors
+/// are fed back as a correction in the same conversation and the model tries
+/// again, up to `max_attempts` calls in total.
+pub async fn prompt_structured<M>(
+    agent: &Agent<M>,
+    prompt: &str,
+    schema: &Value,
+    prompt_engine: &PromptEngine,
+    max_attempts: usize,
+) -> Result<StructuredOutput, StructuredOutputError>
+where
+    M: CompletionModel,
+{
+    let schema_text = serde_json::to_string_pretty(schema)
+        .map_err(|error| StructuredOutputError::Render(error.to_string()))?;
+    let mut message = prompt_engine
+        .render_system_structured_output(prompt, &schema_text)
+        .map_err(|error| StructuredOutputError::Render(error.to_string()))?;
+    let mut history = Vec::new();
+    let max_attempts = max_attempts.max(1);
+
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let response = agent
+            .prompt(&message)
+            .with_history(&mut history)
+            .await
+            .map_err(|error| StructuredOutputError::Completion(error.to_string()))?;
+
+        let errors = match parse_json_response(&response) {
+            Ok(value) => {
+                let errors = validate(schema, &value);
+                if errors.is_empty() {
+                    return Ok(StructuredOutput {
+                        value,
+                        attempts: attempt,
+                    });
+                }
+                errors
+            }
+            Err(error) => vec![format!("response is not valid JSON: {error}")],
+        };
+
+        if attempt >= max_attempts {
+            return Err(StructuredOutputError::Invalid {
+                attempts: attempt,
+                errors,
+                raw: response,
+            });
+        }
+
+        tracing::debug!(attempt, ?errors, "structured output rejected, retrying");
+        message = prompt_engine
+            .render_system_structured_output_correction(&errors)
+            .map_err(|error| StructuredOutputError::Render(error.to_string()))?;
+    }
+}
+
+/// Parse a JSON response, tolerating markdown code fences around it.
+pub fn parse_json_response(response: &str) -> serde_json::Result<Value> {
+    let cleaned = response
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    serde_json::from_str(cleaned)
+}
+
+/// Check `value` against a JSON Schema and return every violation found.
+///
+/// Covers the keywords models are asked to follow in practice: `type`,
+/// `enum`, `const`, `properties`, `required`, `additionalProperties`,
+/// `items`, string and array length bounds, and numeric `minimum` /
+/// `maximum`. Unknown keywords are ignored rather than rejected.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "$", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true` accepts anything; `false` accepts nothing.
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{path}: no value is allowed here"));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| matches_type(value, name)) {
+            errors.push(format!(
+                "{path}: expected {}, got {}",
+                allowed.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        errors.push(format!(
+            "{path}: must be one of {}",
+            Value::Array(options.clone())
+        ));
+    }
+    if let Some(constant) = schema.get("const")
+        && constant != value
+    {
+        errors.push(format!("{path}: must equal {constant}"));
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(key) {
+                        errors.push(format!("{path}: missing required property \"{key}\""));
+                    }
+                }
+            }
+            for (key, field) in object {
+                let field_path = format!("{path}.{key}");
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(field_schema) => validate_at(field_schema, field, &field_path, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{path}: unexpected property \"{key}\""));
+                        }
+                        Some(extra_schema @ Value::Object(_)) => {
+                            validate_at(extra_schema, field, &field_path, errors);
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                && (items.len() as u64) < min
+            {
+                errors.push(format!("{path}: expected at least {min} items"));
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+                && (items.len() as u64) > max
+            {
+                errors.push(format!("{path}: expected at most {max} items"));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}[{index}]"), errors);
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+                && length < min
+            {
+                errors.push(format!("{path}: expected at least {min} characters"));
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+                && length > max
+            {
+                errors.push(format!("{path}: expected at most {max} characters"));
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64)
+                && number < min
+            {
+                errors.push(format!("{path}: must be at least {min}"));
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64)
+                && number > max
+            {
+                errors.push(format!("{path}: must be at most {max}"));
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn matches_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value.as_f64().is_some_and(|number| number.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::String(_) => "string",
+        Value::Number(_) => "number",
+        Value::Bool(_) => "boolean",
+        Value::Null => "null",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "sentiment": { "enum": ["positive", "neutral", "negative"] },
+                "score": { "type": "number", "minimum": 0, "maximum": 1 },
+                "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 2 }
+            },
+            "required": ["sentiment", "score"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_validate_accepts_conforming_value() {
+        let value = json!({ "sentiment": "positive", "score": 0.9, "tags": ["launch"] });
+        assert!(validate(&schema(), &value).is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_each_violation_with_path() {
+        let value = json!({ "sentiment": "ecstatic", "tags": ["a", 2, "c"], "extra": true });
+        let errors = validate(&schema(), &value);
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.contains("missing required property \"score\""))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("$.sentiment: must be one of"))
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e == "$.tags: expected at most 2 items")
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e == "$.tags[1]: expected string, got number")
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|e| e == "$: unexpected property \"extra\"")
+        );
+    }
+
+    #[test]
+    fn test_parse_json_response_strips_fences() {
+        let value = parse_json_response("```json\n{\"ok\": true}\n```").unwrap();
+        assert_eq!(value, json!({ "ok": true }));
+    }
+}
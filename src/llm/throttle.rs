@@ -0,0 +1,251 @@
+//! Adaptive throttling when providers push back.
+//!
+//! A 429 or "overloaded" response means the provider wants fewer requests.
+//! Retrying at the same rate makes the storm worse, so each one halves how
+//! many calls the agent may have in flight and doubles a jittered delay
+//! added before its calls. Every `recovery_successes` successful calls in a
+//! row win back one slot and halve the delay, until the agent is back to
+//! normal.
+
+use crate::config::LlmThrottleConfig;
+
+use tokio::sync::Notify;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Per-agent throttle state, shared by every model the manager hands out.
+#[derive(Debug)]
+pub struct AdaptiveThrottle {
+    config: LlmThrottleConfig,
+    agents: Mutex<HashMap<String, AgentThrottle>>,
+    released: Notify,
+}
+
+#[derive(Debug, Default)]
+struct AgentThrottle {
+    in_flight: usize,
+    /// Calls allowed in flight while throttled. None when not throttled.
+    limit: Option<usize>,
+    /// Delay before each call, before jitter.
+    delay_ms: u64,
+    /// Successful calls since the last pushback or recovery step.
+    successes: usize,
+}
+
+/// Counts a call against its agent's throttled limit until dropped.
+#[derive(Debug)]
+pub struct ThrottlePermit<'a> {
+    throttle: &'a AdaptiveThrottle,
+    agent_id: String,
+}
+
+impl AdaptiveThrottle {
+    pub fn new(config: LlmThrottleConfig) -> Self {
+        Self {
+            config,
+            agents: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+        }
+    }
+
+    /// Wait until the agent is under its throttled limit, then sit out the
+    /// current delay. Returns immediately when the agent isn't throttled.
+    pub async fn admit(&self, agent_id: &str) -> Option<ThrottlePermit<'_>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let delay_ms = loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            {
+                let mut agents = self.agents.lock().expect("throttle lock poisoned");
+                let agent = agents.entry(agent_id.to_string()).or_default();
+                if agent.limit.is_none_or(|limit| agent.in_flight < limit) {
+                    agent.in_flight += 1;
+                    break agent.delay_ms;
+                }
+            }
+            released.await;
+        };
+
+        let permit = ThrottlePermit {
+            throttle: self,
+            agent_id: agent_id.to_string(),
+        };
+        if delay_ms > 0 {
+            tracing::debug!(agent_id, delay_ms, "throttled, delaying LLM call");
+            tokio::time::sleep(jitter(delay_ms)).await;
+        }
+        Some(permit)
+    }
+
+    /// The provider pushed back on one of the agent's calls.
+    pub fn record_pushback(&self, agent_id: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut agents = self.agents.lock().expect("throttle lock poisoned");
+        let agent = agents.entry(agent_id.to_string()).or_default();
+        let limit = (agent.limit.unwrap_or(agent.in_flight) / 2).max(1);
+        agent.limit = Some(limit);
+        agent.delay_ms = (agent.delay_ms * 2)
+            .max(self.config.base_delay_ms)
+            .min(self.config.max_delay_ms);
+        agent.successes = 0;
+        tracing::warn!(
+            agent_id,
+            limit,
+            delay_ms = agent.delay_ms,
+            "provider pushed back, throttling agent"
+        );
+    }
+
+    /// One of the agent's calls succeeded.
+    pub fn record_success(&self, agent_id: &str) {
+        let mut agents = self.agents.lock().expect("throttle lock poisoned");
+        let Some(agent) = agents.get_mut(agent_id) else {
+            return;
+        };
+        let Some(limit) = agent.limit else {
+            return;
+        };
+        agent.successes += 1;
+        if agent.successes < self.config.recovery_successes {
+            return;
+        }
+
+        agent.successes = 0;
+        agent.delay_ms /= 2;
+        if agent.delay_ms < self.config.base_delay_ms {
+            agent.delay_ms = 0;
+            agent.limit = None;
+            tracing::info!(agent_id, "provider errors recovered, throttle lifted");
+        } else {
+            agent.limit = Some(limit + 1);
+            tracing::debug!(
+                agent_id,
+                limit = limit + 1,
+                delay_ms = agent.delay_ms,
+                "easing throttle"
+            );
+        }
+        self.released.notify_waiters();
+    }
+
+    /// The agent's current delay, so retries don't undercut it.
+    pub fn delay_ms(&self, agent_id: &str) -> u64 {
+        self.agents
+            .lock()
+            .expect("throttle lock poisoned")
+            .get(agent_id)
+            .map_or(0, |agent| agent.delay_ms)
+    }
+}
+
+impl Drop for ThrottlePermit<'_> {
+    fn drop(&mut self) {
+        let mut agents = self.throttle.agents.lock().expect("throttle lock poisoned");
+        if let Some(agent) = agents.get_mut(&self.agent_id) {
+            agent.in_flight = agent.in_flight.saturating_sub(1);
+        }
+        drop(agents);
+        self.throttle.released.notify_waiters();
+    }
+}
+
+/// Provider error types that mean the same as a 429 or 529.
+const PUSHBACK_ERROR_TYPES: &[&str] = &[
+    "rate_limit_error",
+    "overloaded_error",
+    "rate_limit_exceeded",
+];
+
+/// Whether an error means the provider wants fewer requests: a 429 or 529
+/// response, or a stream error of a rate limit or overloaded type. Only the
+/// status or type the provider calls put in their errors count, not numbers
+/// that happen to appear in the provider's message.
+pub fn is_pushback_error(error_message: &str) -> bool {
+    matches!(http_status(error_message), Some(429 | 529))
+        || error_type(error_message).is_some_and(|kind| PUSHBACK_ERROR_TYPES.contains(&kind))
+}
+
+/// The HTTP status in "... API error (529 ...)" or "... response (529 ...)".
+fn http_status(error_message: &str) -> Option<u16> {
+    ["API error (", "response ("].iter().find_map(|prefix| {
+        let (_, rest) = error_message.split_once(prefix)?;
+        let digits = rest.get(..3)?;
+        digits.parse().ok()
+    })
+}
+
+/// The provider's error type in "stream error (overloaded_error): ...".
+fn error_type(error_message: &str) -> Option<&str> {
+    let (_, rest) = error_message.split_once("stream error (")?;
+    let (kind, _) = rest.split_once("):")?;
+    Some(kind)
+}
+
+/// Spread a delay over 50-150% so throttled callers don't retry in lockstep.
+pub fn jitter(delay_ms: u64) -> Duration {
+    let factor = 0.5 + rand::random::<f64>();
+    Duration::from_millis((delay_ms as f64 * factor) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pushback_throttles_until_successes_recover() {
+        let throttle = AdaptiveThrottle::new(LlmThrottleConfig {
+            enabled: true,
+            base_delay_ms: 1,
+            max_delay_ms: 4,
+            recovery_successes: 2,
+        });
+
+        let first = throttle.admit("main").await.expect("enabled");
+        let second = throttle.admit("main").await.expect("enabled");
+        throttle.record_pushback("main");
+        throttle.record_pushback("main");
+        assert_eq!(throttle.delay_ms("main"), 2);
+
+        // Limit is down to one call, so a third waits for a release.
+        let waiter = throttle.admit("main");
+        tokio::pin!(waiter);
+        assert!(futures::poll!(waiter.as_mut()).is_pending());
+        drop(first);
+        drop(second);
+        let third = waiter.await.expect("admitted after release");
+        drop(third);
+
+        for _ in 0..4 {
+            throttle.record_success("main");
+        }
+        assert_eq!(throttle.delay_ms("main"), 0);
+    }
+
+    #[test]
+    fn test_pushback_errors_go_by_status_and_type() {
+        assert!(is_pushback_error(
+            "ProviderError: Anthropic API error (529 <unknown status code>): Overloaded"
+        ));
+        assert!(is_pushback_error(
+            "OpenAI API error (429 Too Many Requests): Rate limit reached"
+        ));
+        assert!(is_pushback_error(
+            "ProviderError: stream error (overloaded_error): Overloaded"
+        ));
+        // A 529 in the message, such as a token count, isn't a 529 response.
+        assert!(!is_pushback_error(
+            "OpenAI API error (400 Bad Request): prompt is 1529 tokens too long"
+        ));
+        assert!(!is_pushback_error(
+            "stream error (invalid_request_error): bad"
+        ));
+    }
+}
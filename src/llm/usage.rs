@@ -0,0 +1,314 @@
+//! Token usage over time: tokens and cost per model, for usage graphs.
+//!
+//! Calls in `llm_calls` are rolled up into `usage_rollups`, one row per model
+//! and UTC hour, by a loop that runs every [`ROLLUP_INTERVAL`]. Each pass
+//! rebuilds the last [`REROLL_WINDOW`] of hours, so calls still in flight at
+//! the previous pass are counted. Rollups outlive the calls they summarize,
+//! which are pruned after `[llm.call_log] retention_days`.
+//!
+//! Cost is priced with the agent's `[defaults.experiment.pricing]` when an
+//! hour is rolled up. Changing a price doesn't reprice hours already rolled
+//! up, and models without a price cost nothing.
+
+use crate::config::{ModelPricing, RuntimeConfig};
+use crate::db::TimedQuery as _;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the rollup loop runs.
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Hours rebuilt on every pass, counting back from the latest rollup.
+const REROLL_WINDOW: &str = "-2 hours";
+
+/// Spawn the rollup loop for one agent's database, scanning calls through
+/// `readers`. The first pass runs right away and rolls up every call still in
+/// `llm_calls`.
+pub fn spawn_rollup_loop(
+    pool: SqlitePool,
+    readers: SqlitePool,
+    runtime_config: Arc<RuntimeConfig>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let pricing = runtime_config.experiment.load().pricing.clone();
+            match rollup(&pool, &readers, &pricing).await {
+                Ok(rows) => tracing::debug!(rows, "token usage rolled up"),
+                Err(error) => tracing::warn!(%error, "failed to roll up token usage"),
+            }
+            tokio::time::sleep(ROLLUP_INTERVAL).await;
+        }
+    })
+}
+
+/// Rebuild the rollups from the start of the reroll window, or from the
+/// oldest call if there are none yet. Returns the number of model-hours
+/// written. The call scan runs on `readers`, so it doesn't hold up writes.
+pub async fn rollup(
+    pool: &SqlitePool,
+    readers: &SqlitePool,
+    pricing: &HashMap<String, ModelPricing>,
+) -> crate::error::Result<usize> {
+    let start: Option<String> =
+        sqlx::query_scalar("SELECT datetime(MAX(hour), ?) FROM usage_rollups")
+            .bind(REROLL_WINDOW)
+            .fetch_one(readers)
+            .timed("usage_rollups.latest")
+            .await?;
+    let start = start.unwrap_or_default();
+
+    let rows = sqlx::query(
+        "SELECT strftime('%Y-%m-%d %H:00:00', created_at) AS hour, model, \
+         COUNT(*) AS calls, \
+         SUM(outcome = 'error') AS failed_calls, \
+         SUM(input_tokens) AS input_tokens, \
+         SUM(output_tokens) AS output_tokens \
+         FROM llm_calls \
+         WHERE created_at >= ? \
+         GROUP BY hour, model",
+    )
+    .bind(&start)
+    .fetch_all(readers)
+    .timed_with("usage.rollup_calls", || format!("since={start:?}"))
+    .await?;
+
+    let mut transaction = pool.begin().await?;
+    sqlx::query("DELETE FROM usage_rollups WHERE hour >= ?")
+        .bind(&start)
+        .execute(&mut *transaction)
+        .timed("usage_rollups.delete")
+        .await?;
+    for row in &rows {
+        let hour: String = row.get("hour");
+        let model: String = row.get("model");
+        let input_tokens: i64 = row.get("input_tokens");
+        let output_tokens: i64 = row.get("output_tokens");
+        let cost_usd = pricing
+            .get(&model)
+            .map_or(0.0, |price| price.cost_usd(input_tokens, output_tokens));
+        sqlx::query(
+            "INSERT INTO usage_rollups \
+             (hour, model, calls, failed_calls, input_tokens, output_tokens, cost_usd) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&hour)
+        .bind(&model)
+        .bind(row.get::<i64, _>("calls"))
+        .bind(row.get::<i64, _>("failed_calls"))
+        .bind(input_tokens)
+        .bind(output_tokens)
+        .bind(cost_usd)
+        .execute(&mut *transaction)
+        .timed_with("usage_rollups.insert", || {
+            format!("hour={hour} model={model}")
+        })
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(rows.len())
+}
+
+/// Width of the buckets in a [`UsageReport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Hour,
+    #[default]
+    Day,
+}
+
+/// Token usage over the last `days` days.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub days: u32,
+    pub granularity: Granularity,
+    /// Usage per bucket and model, oldest first. Buckets without calls are
+    /// left out.
+    pub series: Vec<UsagePoint>,
+    /// Usage per model over the whole window, most expensive first.
+    pub models: Vec<ModelUsage>,
+    pub total_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsagePoint {
+    /// Start of the bucket, UTC: `YYYY-MM-DD HH:00:00` for hours,
+    /// `YYYY-MM-DD` for days.
+    pub period: String,
+    pub model: String,
+    pub calls: i64,
+    pub failed_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub calls: i64,
+    pub failed_calls: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// Report on the rolled-up usage of the last `days` days.
+pub async fn report(
+    pool: &SqlitePool,
+    days: u32,
+    granularity: Granularity,
+) -> crate::error::Result<UsageReport> {
+    let window = format!("-{days} days");
+    let period = match granularity {
+        Granularity::Hour => "hour",
+        Granularity::Day => "date(hour)",
+    };
+
+    let series = sqlx::query(&format!(
+        "SELECT {period} AS period, model, \
+         SUM(calls) AS calls, SUM(failed_calls) AS failed_calls, \
+         SUM(input_tokens) AS input_tokens, SUM(output_tokens) AS output_tokens, \
+         SUM(cost_usd) AS cost_usd \
+         FROM usage_rollups \
+         WHERE hour >= datetime('now', ?) \
+         GROUP BY period, model \
+         ORDER BY period, model"
+    ))
+    .bind(&window)
+    .fetch_all(pool)
+    .timed_with("usage.series", || {
+        format!("days={days} granularity={granularity:?}")
+    })
+    .await?
+    .into_iter()
+    .map(|row| UsagePoint {
+        period: row.get("period"),
+        model: row.get("model"),
+        calls: row.get("calls"),
+        failed_calls: row.get("failed_calls"),
+        input_tokens: row.get("input_tokens"),
+        output_tokens: row.get("output_tokens"),
+        cost_usd: row.get("cost_usd"),
+    })
+    .collect();
+
+    let models: Vec<ModelUsage> = sqlx::query(
+        "SELECT model, \
+         SUM(calls) AS calls, SUM(failed_calls) AS failed_calls, \
+         SUM(input_tokens) AS input_tokens, SUM(output_tokens) AS output_tokens, \
+         SUM(cost_usd) AS cost_usd \
+         FROM usage_rollups \
+         WHERE hour >= datetime('now', ?) \
+         GROUP BY model \
+         ORDER BY cost_usd DESC, input_tokens + output_tokens DESC, model",
+    )
+    .bind(&window)
+    .fetch_all(pool)
+    .timed_with("usage.models", || format!("days={days}"))
+    .await?
+    .into_iter()
+    .map(|row| ModelUsage {
+        model: row.get("model"),
+        calls: row.get("calls"),
+        failed_calls: row.get("failed_calls"),
+        input_tokens: row.get("input_tokens"),
+        output_tokens: row.get("output_tokens"),
+        cost_usd: row.get("cost_usd"),
+    })
+    .collect();
+
+    let total_cost_usd = models.iter().map(|model| model.cost_usd).sum();
+
+    Ok(UsageReport {
+        days,
+        granularity,
+        series,
+        models,
+        total_cost_usd,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rollup_totals_tokens_and_cost_per_model_and_hour() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        let pricing = HashMap::from([(
+            "anthropic/claude-sonnet".to_string(),
+            ModelPricing {
+                input: 3.0,
+                output: 15.0,
+            },
+        )]);
+
+        // Two hours of calls to a priced model, one of them failed, and a
+        // call to a model without a price.
+        for (index, (model, outcome, input_tokens, output_tokens, hours_ago)) in [
+            ("anthropic/claude-sonnet", "ok", 1_000_000, 100_000, 3),
+            ("anthropic/claude-sonnet", "error", 500_000, 0, 3),
+            ("anthropic/claude-sonnet", "ok", 1_000_000, 0, 2),
+            ("openai/gpt-mini", "ok", 2_000, 1_000, 2),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sqlx::query(
+                "INSERT INTO llm_calls \
+                 (id, model, outcome, duration_ms, input_tokens, output_tokens, created_at) \
+                 VALUES (?, ?, ?, 0, ?, ?, datetime('now', ?))",
+            )
+            .bind(index.to_string())
+            .bind(model)
+            .bind(outcome)
+            .bind(input_tokens)
+            .bind(output_tokens)
+            .bind(format!("-{hours_ago} hours"))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(rollup(&pool, &pool, &pricing).await.unwrap(), 3);
+        // Rolling up again rebuilds the same hours instead of adding to them.
+        assert_eq!(rollup(&pool, &pool, &pricing).await.unwrap(), 3);
+
+        let hourly = report(&pool, 1, Granularity::Hour).await.unwrap();
+        assert_eq!(hourly.series.len(), 3);
+        let first = &hourly.series[0];
+        assert_eq!(first.model, "anthropic/claude-sonnet");
+        assert_eq!((first.calls, first.failed_calls), (2, 1));
+        assert_eq!(first.input_tokens, 1_500_000);
+        assert!((first.cost_usd - 6.0).abs() < 1e-9);
+
+        let sonnet = &hourly.models[0];
+        assert_eq!(sonnet.model, "anthropic/claude-sonnet");
+        assert_eq!(sonnet.calls, 3);
+        assert!((sonnet.cost_usd - 9.0).abs() < 1e-9);
+        assert_eq!(hourly.models[1].cost_usd, 0.0);
+        assert!((hourly.total_cost_usd - 9.0).abs() < 1e-9);
+
+        let daily = report(&pool, 1, Granularity::Day).await.unwrap();
+        assert!(daily.series.iter().all(|point| point.period.len() == 10));
+    }
+}
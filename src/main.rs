@@ -23,6 +23,14 @@ struct Cli {
     /// Enable debug logging
     #[arg(short, long, global = true)]
     debug: bool,
+
+    /// Config profile to apply, like dev or prod (defaults to SPACEBOT_PROFILE)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Override a config value, like agents.main.max_turns=10 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
 }
 
 #[derive(Subcommand)]
@@ -32,6 +40,10 @@ enum Command {
         /// Run in the foreground instead of daemonizing
         #[arg(short, long)]
         foreground: bool,
+        /// Speak JSON-RPC over stdin and stdout, for running as a subprocess
+        /// (implies --foreground)
+        #[arg(long)]
+        stdio: bool,
     },
     /// Stop the running daemon
     Stop,
@@ -43,9 +55,51 @@ enum Command {
     },
     /// Show status of the running daemon
     Status,
+    /// Chat with an agent of the running daemon in the terminal
+    Chat {
+        /// Agent ID (defaults to the default agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Session name. Reusing one continues its conversation
+        #[arg(short, long, default_value = "default")]
+        session: String,
+    },
+    /// Validate the configuration, listing every problem found
+    Check,
+    /// Print the JSON Schema of the config file
+    ConfigSchema,
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
     /// Manage skills
     #[command(subcommand)]
     Skill(SkillCommand),
+    /// Encrypt values for the config
+    #[command(subcommand)]
+    Secret(SecretCommand),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the configuration the daemon would run with: includes, profile,
+    /// overrides, and env vars applied, and every default filled in
+    Print {
+        /// Mask API keys, tokens, and passwords
+        #[arg(long)]
+        redacted: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretCommand {
+    /// Generate a key for encrypting config values
+    Keygen,
+    /// Encrypt a value with SPACEBOT_CONFIG_KEY, printing it as enc:...
+    Encrypt {
+        /// Value to encrypt (read from stdin if not given, keeping it out of
+        /// shell history)
+        value: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -98,11 +152,22 @@ enum SkillCommand {
 
 /// Tracks an active conversation channel and its message sender.
 struct ActiveChannel {
-    message_tx: mpsc::Sender<spacebot::InboundMessage>,
+    /// Moves messages into the channel's queue from a task of its own, so a
+    /// channel that's behind never holds up the main loop.
+    inbox: spacebot::agent::backpressure::ChannelInbox,
     /// Latest inbound message for this conversation, shared with the outbound
     /// routing task so status updates (e.g. typing indicators) target the
     /// most recent message rather than the first one the channel ever received.
     latest_message: Arc<tokio::sync::RwLock<spacebot::InboundMessage>>,
+    /// Aborted when the agent's health registry requests a restart.
+    channel_handle: tokio::task::JoinHandle<()>,
+    /// Stops the channel's in-flight turn on a cancel request.
+    turn_canceller: spacebot::agent::channel::TurnCanceller,
+    /// Read to answer status requests without going through the channel.
+    status_block: Arc<tokio::sync::RwLock<spacebot::agent::status::StatusBlock>>,
+    /// Whether the author of a message dropped by backpressure was told
+    /// during the current backlog.
+    dropping: bool,
     /// Retained so the outbound routing task stays alive.
     _outbound_handle: tokio::task::JoinHandle<()>,
 }
@@ -113,17 +178,31 @@ fn main() -> anyhow::Result<()> {
         .expect("failed to install rustls crypto provider");
 
     let cli = Cli::parse();
-    let command = cli.command.unwrap_or(Command::Start { foreground: false });
+    if let Some(profile) = cli.profile {
+        spacebot::config::set_profile(profile);
+    }
+    spacebot::config::set_overrides(cli.overrides);
+    let command = cli.command.unwrap_or(Command::Start {
+        foreground: false,
+        stdio: false,
+    });
 
     match command {
-        Command::Start { foreground } => cmd_start(cli.config, cli.debug, foreground),
+        Command::Start { foreground, stdio } => cmd_start(cli.config, cli.debug, foreground, stdio),
         Command::Stop => cmd_stop(),
         Command::Restart { foreground } => {
             cmd_stop_if_running();
-            cmd_start(cli.config, cli.debug, foreground)
+            cmd_start(cli.config, cli.debug, foreground, false)
         }
         Command::Status => cmd_status(),
+        Command::Chat { agent, session } => cmd_chat(cli.config, agent, session),
+        Command::Check => cmd_check(cli.config),
+        Command::ConfigSchema => cmd_config_schema(),
+        Command::Config(ConfigCommand::Print { redacted }) => {
+            cmd_config_print(cli.config, redacted)
+        }
         Command::Skill(skill_cmd) => cmd_skill(cli.config, skill_cmd),
+        Command::Secret(secret_cmd) => cmd_secret(secret_cmd),
     }
 }
 
@@ -131,7 +210,9 @@ fn cmd_start(
     config_path: Option<std::path::PathBuf>,
     debug: bool,
     foreground: bool,
+    stdio: bool,
 ) -> anyhow::Result<()> {
+    let foreground = foreground || stdio;
     let paths = spacebot::daemon::DaemonPaths::from_default();
 
     // Bail if already running
@@ -175,14 +256,17 @@ fn cmd_start(
         .context("failed to build Tokio runtime")?;
 
     runtime.block_on(async {
-        let otel_provider = if foreground {
+        // Before tracing, so the tracing layer that reports errors finds the
+        // client.
+        let error_reporting = spacebot::error_reporting::init(&config.error_reporting);
+        let otel_providers = if foreground {
             spacebot::daemon::init_foreground_tracing(debug, &config.telemetry)
         } else {
             let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
             spacebot::daemon::init_background_tracing(&paths, debug, &config.telemetry)
         };
 
-        run(config, foreground, otel_provider).await
+        run(config, foreground, stdio, otel_providers, error_reporting).await
     })
 }
 
@@ -292,6 +376,99 @@ fn cmd_status() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn cmd_chat(
+    config_path: Option<std::path::PathBuf>,
+    agent: Option<String>,
+    session: String,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+
+    let paths = spacebot::daemon::DaemonPaths::from_default();
+    if spacebot::daemon::is_running(&paths).is_none() {
+        eprintln!("spacebot is not running, start it with `spacebot start`");
+        std::process::exit(1);
+    }
+
+    let agent_id = agent.unwrap_or_else(|| config.default_agent_id().to_string());
+    if !config.agents.iter().any(|agent| agent.id == agent_id) {
+        eprintln!("Agent not found: {agent_id}");
+        std::process::exit(1);
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(spacebot::repl::run(&config, &agent_id, &session))
+}
+
+fn cmd_check(config_path: Option<std::path::PathBuf>) -> anyhow::Result<()> {
+    let config = match load_config(&config_path) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("error: {error:#}");
+            std::process::exit(1);
+        }
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+    let problems = runtime.block_on(spacebot::check::run(&config));
+
+    if problems.is_empty() {
+        eprintln!("config OK ({} agents)", config.agents.len());
+        return Ok(());
+    }
+    for problem in &problems {
+        eprintln!("error: {problem}");
+    }
+    match problems.len() {
+        1 => eprintln!("1 problem found"),
+        count => eprintln!("{count} problems found"),
+    }
+    std::process::exit(1);
+}
+
+fn cmd_config_schema() -> anyhow::Result<()> {
+    let schema = serde_json::to_string_pretty(&spacebot::config::config_schema())
+        .context("failed to serialize config schema")?;
+    println!("{schema}");
+    Ok(())
+}
+
+fn cmd_config_print(config_path: Option<std::path::PathBuf>, redacted: bool) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    print!("{}", spacebot::config::effective_config(&config, redacted)?);
+    Ok(())
+}
+
+fn cmd_secret(secret_cmd: SecretCommand) -> anyhow::Result<()> {
+    use spacebot::secrets::encrypted;
+
+    match secret_cmd {
+        SecretCommand::Keygen => {
+            println!("{}", encrypted::generate_key());
+        }
+        SecretCommand::Encrypt { value } => {
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    let mut value = String::new();
+                    std::io::Read::read_to_string(&mut std::io::stdin(), &mut value)
+                        .context("failed to read value from stdin")?;
+                    value.trim_end_matches(['\r', '\n']).to_string()
+                }
+            };
+            let cipher = encrypted::load_key()?;
+            println!("{}", encrypted::encrypt(&cipher, &value)?);
+        }
+    }
+    Ok(())
+}
+
 fn cmd_skill(
     config_path: Option<std::path::PathBuf>,
     skill_cmd: SkillCommand,
@@ -489,18 +666,31 @@ fn load_config(
 async fn run(
     config: spacebot::config::Config,
     foreground: bool,
-    otel_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+    stdio: bool,
+    otel_providers: Option<spacebot::daemon::OtelProviders>,
+    error_reporting: Option<spacebot::error_reporting::ErrorReporting>,
 ) -> anyhow::Result<()> {
     let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
 
     tracing::info!("starting spacebot");
     tracing::info!(instance_dir = %config.instance_dir.display(), "configuration loaded");
+    spacebot::db::set_slow_query_threshold(config.telemetry.slow_query_ms);
+
+    // Removed on a graceful shutdown, so the next boot can tell whether this
+    // run crashed
+    let booted_at = chrono::Utc::now();
+    let previous_run = spacebot::recovery::mark_running(&config.instance_dir);
 
     // Start the IPC server for stop/status commands
     let (mut shutdown_rx, _ipc_handle) = spacebot::daemon::start_ipc_server(&paths)
         .await
         .context("failed to start IPC server")?;
 
+    // Set by the stdio adapter when stdin closes, so an embedded spacebot
+    // stops with the program that started it
+    let (stdio_closed_tx, mut stdio_closed_rx) = tokio::sync::watch::channel(false);
+    let stdio_closed_tx = stdio.then_some(stdio_closed_tx);
+
     // Create the provider setup channel so API handlers can signal the main loop
     let (provider_tx, mut provider_rx) = mpsc::channel::<spacebot::ProviderSetupEvent>(1);
     // Channel for newly created agents to be registered in the main event loop
@@ -515,6 +705,18 @@ async fn run(
         agent_remove_tx,
     ));
 
+    // Subscribe metrics and the API event stream to the event bus before
+    // anything publishes on it
+    spacebot::otel::spawn_event_metrics();
+    api_state.forward_bus_events();
+
+    match spacebot::audit::AuditLog::connect(&config.instance_dir).await {
+        Ok(audit_log) => api_state.set_audit_log(Arc::new(audit_log)),
+        Err(error) => {
+            tracing::warn!(%error, "failed to open admin database, actions won't be audited")
+        }
+    }
+
     // Start background update checker
     spacebot::update::spawn_update_checker(api_state.update_status.clone());
 
@@ -557,21 +759,45 @@ async fn run(
 
     // Shared LLM manager (same API keys for all agents)
     // This works even without keys; it will fail later at call time if no keys exist
+    // Shared across LLM manager rebuilds so restart requests keep reaching the main loop
+    let health_registry = Arc::new(spacebot::agent::health::HealthRegistry::new(
+        config.defaults.health,
+    ));
+    let mut health_restart_rx = health_registry.subscribe_restarts();
     let llm_manager = Arc::new(
         spacebot::llm::LlmManager::new(config.llm.clone())
             .await
-            .with_context(|| "failed to initialize LLM manager")?,
+            .with_context(|| "failed to initialize LLM manager")?
+            .with_health_registry(health_registry.clone()),
     );
 
     // Shared embedding model (stateless, agent-agnostic)
     let embedding_cache_dir = config.instance_dir.join("embedding_cache");
     let embedding_model = Arc::new(
-        spacebot::memory::EmbeddingModel::new(&embedding_cache_dir)
-            .context("failed to initialize embedding model")?,
+        spacebot::memory::EmbeddingModel::with_model(
+            &embedding_cache_dir,
+            &config.defaults.embedding.model,
+        )
+        .with_context(|| {
+            format!(
+                "failed to initialize embedding model '{}'",
+                config.defaults.embedding.model
+            )
+        })?,
     );
 
     tracing::info!("shared resources initialized");
 
+    // Alert rules are read at startup; agents are looked up on every check
+    if !config.alerts.rules.is_empty() {
+        spacebot::alerts::spawn_alert_loop(
+            config.alerts.clone(),
+            health_registry.clone(),
+            api_state.clone(),
+        );
+        tracing::info!(rules = config.alerts.rules.len(), "alert checks started");
+    }
+
     // Initialize the language for all text lookups (must happen before PromptEngine/tools)
     spacebot::prompts::text::init("en").with_context(|| "failed to initialize language")?;
 
@@ -593,10 +819,15 @@ async fn run(
     let bindings: Arc<ArcSwap<Vec<spacebot::config::Binding>>> =
         Arc::new(ArcSwap::from_pointee(config.bindings.clone()));
     api_state.set_bindings(bindings.clone()).await;
+    let channel_routing: Arc<ArcSwap<spacebot::config::ChannelRouting>> =
+        Arc::new(ArcSwap::from_pointee(config.channel_routing.clone()));
     let default_agent_id = config.default_agent_id().to_string();
+    // Settings changed from chat, by conversation ID. Loaded once agents are up.
+    let mut channel_settings: HashMap<String, spacebot::conversation::ChannelSettings> =
+        HashMap::new();
 
     // Set the config path on the API state for config.toml writes
-    let config_path = config.instance_dir.join("config.toml");
+    let config_path = spacebot::config::Config::default_config_path(&config.instance_dir);
     api_state.set_config_path(config_path.clone()).await;
     api_state.set_llm_manager(llm_manager.clone()).await;
     api_state.set_embedding_model(embedding_model.clone()).await;
@@ -616,6 +847,12 @@ async fn run(
         let mut slack_permissions = None;
         let mut telegram_permissions = None;
         let mut twitch_permissions = None;
+        let mut matrix_permissions = None;
+        let mut irc_permissions = None;
+        let mut email_permissions = None;
+        let mut whatsapp_permissions = None;
+        let mut sms_permissions = None;
+        let mut xmpp_permissions = None;
         initialize_agents(
             &config,
             &llm_manager,
@@ -633,9 +870,17 @@ async fn run(
             &mut slack_permissions,
             &mut telegram_permissions,
             &mut twitch_permissions,
+            &mut matrix_permissions,
+            &mut irc_permissions,
+            &mut email_permissions,
+            &mut whatsapp_permissions,
+            &mut sms_permissions,
+            &mut xmpp_permissions,
+            stdio_closed_tx.as_ref(),
         )
         .await?;
         agents_initialized = true;
+        channel_settings = load_channel_settings(&agents).await;
 
         // Start file watcher with populated agent data
         _file_watcher = spacebot::config::spawn_file_watcher(
@@ -646,9 +891,18 @@ async fn run(
             slack_permissions,
             telegram_permissions,
             twitch_permissions,
+            matrix_permissions,
+            irc_permissions,
+            email_permissions,
+            whatsapp_permissions,
+            sms_permissions,
+            xmpp_permissions,
             bindings.clone(),
+            channel_routing.clone(),
             Some(messaging_manager.clone()),
             llm_manager.clone(),
+            config.secrets.refresh_interval_secs,
+            config.remote.refresh_interval_secs,
         );
     } else {
         // Start file watcher in setup mode (no agents to watch yet)
@@ -660,12 +914,30 @@ async fn run(
             None,
             None,
             None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
             bindings.clone(),
+            channel_routing.clone(),
             None,
             llm_manager.clone(),
+            config.secrets.refresh_interval_secs,
+            config.remote.refresh_interval_secs,
         );
     }
 
+    report_recovery(
+        &agents,
+        previous_run,
+        booted_at,
+        &api_state,
+        &config.alerts.recovery_notify,
+    )
+    .await;
+
     if foreground {
         eprintln!(
             "spacebot running in foreground (pid {})",
@@ -675,8 +947,8 @@ async fn run(
         tracing::info!(pid = std::process::id(), "spacebot daemon started");
     }
 
-    // Active conversation channels: conversation_id -> ActiveChannel
-    let mut active_channels: HashMap<String, ActiveChannel> = HashMap::new();
+    // Active conversation channels: (agent_id, conversation_id) -> ActiveChannel
+    let mut active_channels: HashMap<(spacebot::AgentId, String), ActiveChannel> = HashMap::new();
 
     // Main event loop: route inbound messages to agent channels
     loop {
@@ -689,220 +961,512 @@ async fn run(
         };
         tokio::select! {
             Some(mut message) = inbound_next, if agents_initialized => {
-                let agent_id = if let Some(existing) = message.agent_id.as_ref() {
-                    existing.clone()
+                message.assign_turn_id();
+                spacebot::otel::Metrics::global().messages_received.add(
+                    1,
+                    &[opentelemetry::KeyValue::new("adapter", message.source.clone())],
+                );
+
+                // Cancel requests stop every agent's turn in the conversation
+                // that answers the sender, or any turn for a moderator, and
+                // never start a channel.
+                if message.metadata.contains_key(spacebot::agent::channel::CANCEL_TURN_KEY) {
+                    let moderator = message.metadata.contains_key(spacebot::agent::channel::MODERATOR_KEY);
+                    for ((agent_id, conversation_id), active) in &active_channels {
+                        if *conversation_id == message.conversation_id
+                            && active.turn_canceller.cancel_for(
+                                &message.sender_id,
+                                moderator,
+                                format!("cancelled by {}", message.sender_id),
+                            )
+                        {
+                            tracing::info!(
+                                agent_id = %agent_id,
+                                conversation_id = %conversation_id,
+                                sender_id = %message.sender_id,
+                                "channel turn cancelled by user"
+                            );
+                        }
+                    }
+                    continue;
+                }
+
+                // Status requests are answered from the active channels'
+                // status blocks, without starting a turn.
+                if message.metadata.contains_key(spacebot::agent::channel::STATUS_REQUEST_KEY) {
+                    let mut sections = Vec::new();
+                    for ((agent_id, conversation_id), active) in &active_channels {
+                        if *conversation_id != message.conversation_id {
+                            continue;
+                        }
+                        let state = if active.turn_canceller.is_running() { "replying" } else { "idle" };
+                        let status = active.status_block.read().await.render();
+                        sections.push(format!("**{agent_id}** ({state})\n{}", status.trim_end()).trim_end().to_string());
+                    }
+                    let text = if sections.is_empty() {
+                        "No agent is active in this channel.".to_string()
+                    } else {
+                        sections.join("\n\n")
+                    };
+                    if let Err(error) = messaging_manager.respond(&message, spacebot::OutboundResponse::Text(text)).await {
+                        tracing::warn!(%error, "failed to answer status request");
+                    }
+                    continue;
+                }
+
+                // Edits only update channels that already have the message,
+                // and never start one.
+                if message.metadata.contains_key(spacebot::agent::channel::MESSAGE_EDIT_KEY) {
+                    for ((agent_id, conversation_id), active) in &mut active_channels {
+                        if *conversation_id != message.conversation_id {
+                            continue;
+                        }
+                        let backpressure = agents
+                            .get(agent_id)
+                            .map(|agent| agent.deps.runtime_config.backpressure.load_full())
+                            .unwrap_or_default();
+                        if let Err(error) = spacebot::agent::backpressure::forward(
+                            &active.inbox,
+                            message.clone(),
+                            &backpressure,
+                            &mut active.dropping,
+                            agent_id,
+                        ) {
+                            tracing::warn!(%error, %conversation_id, "failed to forward message edit");
+                        }
+                    }
+                    continue;
+                }
+
+                // Settings changes apply to the conversation right away and are
+                // kept in every agent's channel registry, without starting a turn.
+                if let Some(request) = message
+                    .metadata
+                    .get(spacebot::agent::channel::SETTINGS_REQUEST_KEY)
+                    .and_then(|value| value.as_object())
+                {
+                    let mut settings = channel_settings
+                        .get(&message.conversation_id)
+                        .cloned()
+                        .unwrap_or_default();
+                    let result = request
+                        .iter()
+                        .try_for_each(|(name, value)| {
+                            settings.update(name, value.as_str().unwrap_or_default())
+                        })
+                        .and_then(|()| match &settings.agent {
+                            Some(agent) if !agents.keys().any(|id| id.as_ref() == agent) => {
+                                Err(format!("unknown agent: {agent}"))
+                            }
+                            _ => Ok(()),
+                        });
+                    let text = match result {
+                        Ok(()) => {
+                            for agent in agents.values() {
+                                spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone())
+                                    .set_settings(&message.conversation_id, &settings);
+                            }
+                            let text = format!("Channel settings: {}", settings.describe());
+                            channel_settings.insert(message.conversation_id.clone(), settings);
+                            text
+                        }
+                        Err(error) => format!("Settings not changed: {error}"),
+                    };
+                    if let Err(error) = messaging_manager.respond(&message, spacebot::OutboundResponse::Text(text)).await {
+                        tracing::warn!(%error, "failed to answer settings request");
+                    }
+                    continue;
+                }
+                let settings = channel_settings
+                    .get(&message.conversation_id)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(verbosity) = settings.verbosity {
+                    message.metadata.insert(
+                        spacebot::agent::channel::VERBOSITY_KEY.into(),
+                        serde_json::to_value(verbosity).unwrap_or_default(),
+                    );
+                }
+
+                let routing = channel_routing.load_full();
+                let guild = routing.guild_for(&message);
+                if let Some(persona) = guild.and_then(|guild| guild.persona.as_ref()) {
+                    message
+                        .metadata
+                        .insert(spacebot::config::GUILD_PERSONA_KEY.into(), persona.clone().into());
+                }
+                let role_mentioned = !routing.role_mention_agents(&message).is_empty();
+                if role_mentioned {
+                    message
+                        .metadata
+                        .insert(spacebot::config::ROLE_MENTION_KEY.into(), true.into());
+                }
+
+                // An agent set with `/settings` replaces routing, but mentioned
+                // roles still reach their agents.
+                let agent_ids = if let Some(existing) = message.agent_id.as_ref() {
+                    vec![existing.clone()]
+                } else if let Some(agent_id) = settings.agent.as_ref().filter(|_| !role_mentioned) {
+                    vec![Arc::from(agent_id.as_str())]
                 } else {
-                    let current_bindings = bindings.load();
-                    let resolved = spacebot::config::resolve_agent_for_message(
-                        &current_bindings,
+                    spacebot::config::resolve_agents_for_message(
+                        &routing,
+                        &bindings.load(),
                         &message,
                         &default_agent_id,
-                    );
-                    message.agent_id = Some(resolved.clone());
-                    resolved
+                    )
                 };
 
                 let conversation_id = message.conversation_id.clone();
 
-                // Find or create a channel for this conversation
-                if !active_channels.contains_key(&conversation_id) {
-                    let Some(agent) = agents.get(&agent_id) else {
-                        tracing::warn!(
-                            agent_id = %agent_id,
-                            conversation_id = %conversation_id,
-                            "message routed to unknown agent, dropping"
-                        );
-                        continue;
+                // Config requests are answered from the settings of the agents
+                // routed here, without starting a turn.
+                if message.metadata.contains_key(spacebot::agent::channel::CONFIG_REQUEST_KEY) {
+                    let guild_rule = guild.and_then(|guild| guild.listening.as_ref());
+                    let sections: Vec<String> = agent_ids
+                        .iter()
+                        .filter_map(|agent_id| {
+                            let agent = agents.get(agent_id)?;
+                            let summary = agent.deps.runtime_config.summary(&conversation_id, guild_rule);
+                            Some(format!(
+                                "**{agent_id}**\n{summary}\nChannel settings: {}",
+                                settings.describe()
+                            ))
+                        })
+                        .collect();
+                    let text = if sections.is_empty() {
+                        "No agent handles this channel.".to_string()
+                    } else {
+                        sections.join("\n\n")
                     };
+                    if let Err(error) = messaging_manager.respond(&message, spacebot::OutboundResponse::Text(text)).await {
+                        tracing::warn!(%error, "failed to answer config request");
+                    }
+                    continue;
+                }
 
-                    // Create outbound response channel
-                    let (response_tx, mut response_rx) = mpsc::channel::<spacebot::OutboundResponse>(32);
+                // Reactions on replies are recorded by each agent in the
+                // conversation and never start a channel.
+                if let Some(reaction) = spacebot::agent::sentiment::requested(&message) {
+                    for agent_id in &agent_ids {
+                        if let Some(agent) = agents.get(agent_id)
+                            && agent.deps.runtime_config.sentiment.load().enabled
+                        {
+                            spacebot::agent::sentiment::record(
+                                &agent.deps.sqlite_pool,
+                                &conversation_id,
+                                &message.sender_id,
+                                reaction.clone(),
+                            );
+                        }
+                    }
+                    continue;
+                }
 
-                    // Subscribe to the agent's event bus
-                    let event_rx = agent.deps.event_tx.subscribe();
+                // Each assigned agent runs its own channel for the conversation.
+                for agent_id in agent_ids {
+                    let mut message = message.clone();
+                    message.agent_id = Some(agent_id.clone());
+                    let channel_key = (agent_id.clone(), conversation_id.clone());
+
+                    // Messages the agent's listening mode doesn't respond to still
+                    // go to the channel, as context for its next turn.
+                    if let Some(agent) = agents.get(&agent_id) {
+                        let configured = agent.deps.runtime_config.listening.load();
+                        let guild_rule = guild.and_then(|guild| guild.listening.as_ref());
+                        let listening = settings.listening(&configured, &conversation_id, guild_rule);
+                        let listening = listening.as_ref().unwrap_or(&**configured);
+                        if !spacebot::agent::listening::should_respond(listening, guild_rule, &message) {
+                            spacebot::agent::listening::mark_passive(&mut message);
+                        }
 
-                    let channel_id: spacebot::ChannelId = Arc::from(conversation_id.as_str());
+                        // The name and avatar for adapters that reply through webhooks.
+                        let display_name = agent
+                            .deps
+                            .runtime_config
+                            .display_name
+                            .load()
+                            .as_ref()
+                            .clone()
+                            .unwrap_or_else(|| agent_id.to_string());
+                        message.metadata.insert(
+                            spacebot::config::AGENT_DISPLAY_NAME_KEY.into(),
+                            display_name.into(),
+                        );
+                        let avatar_url = agent.deps.runtime_config.avatar_url.load();
+                        if let Some(avatar_url) = avatar_url.as_ref() {
+                            message.metadata.insert(
+                                spacebot::config::AGENT_AVATAR_URL_KEY.into(),
+                                avatar_url.clone().into(),
+                            );
+                        }
+                    }
 
-                    let (channel, channel_tx) = spacebot::agent::channel::Channel::new(
-                        channel_id,
-                        agent.deps.clone(),
-                        response_tx,
-                        event_rx,
-                        agent.config.screenshot_dir(),
-                        agent.config.logs_dir(),
-                    );
+                    // Find or create a channel for this conversation
+                    if !active_channels.contains_key(&channel_key) {
+                        let Some(agent) = agents.get(&agent_id) else {
+                            tracing::warn!(
+                                agent_id = %agent_id,
+                                conversation_id = %conversation_id,
+                                "message routed to unknown agent, dropping"
+                            );
+                            continue;
+                        };
+
+                        // Create outbound response channel
+                        let (response_tx, mut response_rx) = mpsc::channel::<spacebot::OutboundResponse>(32);
+
+                        // Subscribe to the agent's event bus
+                        let event_rx = agent.deps.event_tx.subscribe();
+
+                        let channel_id: spacebot::ChannelId = Arc::from(conversation_id.as_str());
+
+                        let (channel, channel_tx) = spacebot::agent::channel::Channel::new(
+                            channel_id,
+                            agent.deps.clone(),
+                            response_tx,
+                            event_rx,
+                            agent.config.screenshot_dir(),
+                            agent.config.logs_dir(),
+                        );
 
-                    // Register the channel's status block with the API for snapshot queries
-                    api_state.register_channel_status(
-                        conversation_id.clone(),
-                        channel.state.status_block.clone(),
-                    ).await;
-
-                    // Register the channel state for API-driven cancellation
-                    api_state.register_channel_state(
-                        conversation_id.clone(),
-                        channel.state.clone(),
-                    ).await;
-
-                    // Backfill recent message history from the platform
-                    let backfill_count = agent.config.history_backfill_count();
-                    if backfill_count > 0 {
-                        match messaging_manager.fetch_history(&message, backfill_count).await {
-                            Ok(history_messages) if !history_messages.is_empty() => {
-                                let mut transcript = String::new();
-                                for entry in &history_messages {
-                                    let label = if entry.is_bot { "(you)" } else { &entry.author };
-                                    transcript.push_str(&format!("{}: {}\n", label, entry.content));
-                                }
+                        // Register the channel's status block with the API for snapshot queries
+                        api_state.register_channel_status(
+                            agent_id.to_string(),
+                            conversation_id.clone(),
+                            channel.state.status_block.clone(),
+                        ).await;
+
+                        // Register the channel state for API-driven cancellation
+                        api_state.register_channel_state(
+                            agent_id.to_string(),
+                            conversation_id.clone(),
+                            channel.state.clone(),
+                        ).await;
+
+                        // Backfill recent message history from the platform
+                        let backfill_count = agent.config.history_backfill_count();
+                        if backfill_count > 0 {
+                            match messaging_manager.fetch_history(&message, backfill_count).await {
+                                Ok(history_messages) if !history_messages.is_empty() => {
+                                    let mut transcript = String::new();
+                                    for entry in &history_messages {
+                                        let label = if entry.is_bot { "(you)" } else { &entry.author };
+                                        transcript.push_str(&format!("{}: {}\n", label, entry.content));
+                                    }
 
-                                let prompt_engine = agent.deps.runtime_config.prompts.load();
-                                let backfill_text = prompt_engine
-                                    .render_system_history_backfill(transcript.trim_end())
-                                    .unwrap_or(transcript);
+                                    let prompt_engine = agent.deps.runtime_config.prompts.load();
+                                    let backfill_text = prompt_engine
+                                        .render_system_history_backfill(transcript.trim_end())
+                                        .unwrap_or(transcript);
 
-                                let mut history = channel.state.history.write().await;
-                                history.push(rig::message::Message::from(backfill_text));
-                                drop(history);
+                                    let mut history = channel.state.history.write().await;
+                                    history.push(rig::message::Message::from(backfill_text));
+                                    drop(history);
 
-                                tracing::info!(
-                                    conversation_id = %conversation_id,
-                                    message_count = history_messages.len(),
-                                    "backfilled channel history"
-                                );
-                            }
-                            Err(error) => {
-                                tracing::warn!(%error, "failed to backfill channel history");
-                            }
-                            _ => {}
-                        }
-                    }
-
-                    // Spawn the channel's event loop
-                    tokio::spawn(async move {
-                        if let Err(error) = channel.run().await {
-                            tracing::error!(%error, "channel event loop failed");
-                        }
-                    });
-
-                    // Spawn outbound response routing: reads from response_rx,
-                    // sends to the messaging adapter and forwards to SSE
-                    let messaging_for_outbound = messaging_manager.clone();
-                    let latest_message = Arc::new(tokio::sync::RwLock::new(message.clone()));
-                    let outbound_message = latest_message.clone();
-                    let outbound_conversation_id = conversation_id.clone();
-                    let api_event_tx = api_state.event_tx.clone();
-                    let sse_agent_id = agent_id.to_string();
-                    let sse_channel_id = conversation_id.clone();
-                    let outbound_handle = tokio::spawn(async move {
-                        while let Some(response) = response_rx.recv().await {
-                            // Forward relevant events to SSE clients
-                            match &response {
-                                spacebot::OutboundResponse::Text(text) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::RichMessage { text, .. } => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::ThreadReply { text, .. } => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        text: text.clone(),
-                                    }).ok();
-                                }
-                                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::Thinking) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::TypingState {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        is_typing: true,
-                                    }).ok();
+                                    tracing::info!(
+                                        conversation_id = %conversation_id,
+                                        message_count = history_messages.len(),
+                                        "backfilled channel history"
+                                    );
                                 }
-                                spacebot::OutboundResponse::Status(spacebot::StatusUpdate::StopTyping) => {
-                                    api_event_tx.send(spacebot::api::ApiEvent::TypingState {
-                                        agent_id: sse_agent_id.clone(),
-                                        channel_id: sse_channel_id.clone(),
-                                        is_typing: false,
-                                    }).ok();
+                                Err(error) => {
+                                    tracing::warn!(%error, "failed to backfill channel history");
                                 }
                                 _ => {}
                             }
+                        }
 
-                            let current_message = outbound_message.read().await.clone();
-                            match response {
-                                spacebot::OutboundResponse::Status(status) => {
-                                    if let Err(error) = messaging_for_outbound
-                                        .send_status(&current_message, status)
-                                        .await
-                                    {
-                                        tracing::warn!(%error, "failed to send status update");
+                        // Spawn the channel's event loop
+                        let turn_canceller = channel.state.turn_canceller.clone();
+                        let status_block = channel.state.status_block.clone();
+                        let channel_handle = tokio::spawn(async move {
+                            if let Err(error) = channel.run().await {
+                                tracing::error!(%error, "channel event loop failed");
+                            }
+                        });
+
+                        // Spawn outbound response routing: reads from response_rx,
+                        // sends to the messaging adapter and forwards to SSE
+                        let messaging_for_outbound = messaging_manager.clone();
+                        let latest_message = Arc::new(tokio::sync::RwLock::new(message.clone()));
+                        let outbound_message = latest_message.clone();
+                        let outbound_conversation_id = conversation_id.clone();
+                        let api_event_tx = api_state.event_tx.clone();
+                        let sse_agent_id = agent_id.to_string();
+                        let sse_channel_id = conversation_id.clone();
+                        let outbound_handle = tokio::spawn(async move {
+                            while let Some(response) = response_rx.recv().await {
+                                let response = messaging_for_outbound.filter_outbound(response).await;
+                                let current_message = outbound_message.read().await.clone();
+                                let turn_id = current_message.turn_id().map(String::from);
+
+                                // Forward relevant events to SSE clients
+                                match &response {
+                                    spacebot::OutboundResponse::Text(text) => {
+                                        api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
+                                            agent_id: sse_agent_id.clone(),
+                                            channel_id: sse_channel_id.clone(),
+                                            text: text.clone(),
+                                            turn_id: turn_id.clone(),
+                                        }).ok();
+                                    }
+                                    spacebot::OutboundResponse::RichMessage { text, .. } => {
+                                        api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
+                                            agent_id: sse_agent_id.clone(),
+                                            channel_id: sse_channel_id.clone(),
+                                            text: text.clone(),
+                                            turn_id: turn_id.clone(),
+                                        }).ok();
+                                    }
+                                    spacebot::OutboundResponse::ThreadReply { text, .. } => {
+                                        api_event_tx.send(spacebot::api::ApiEvent::OutboundMessage {
+                                            agent_id: sse_agent_id.clone(),
+                                            channel_id: sse_channel_id.clone(),
+                                            text: text.clone(),
+                                            turn_id: turn_id.clone(),
+                                        }).ok();
+                                    }
+                                    spacebot::OutboundResponse::Status(spacebot::StatusUpdate::Thinking) => {
+                                        api_event_tx.send(spacebot::api::ApiEvent::TypingState {
+                                            agent_id: sse_agent_id.clone(),
+                                            channel_id: sse_channel_id.clone(),
+                                            is_typing: true,
+                                        }).ok();
+                                    }
+                                    spacebot::OutboundResponse::Status(spacebot::StatusUpdate::StopTyping) => {
+                                        api_event_tx.send(spacebot::api::ApiEvent::TypingState {
+                                            agent_id: sse_agent_id.clone(),
+                                            channel_id: sse_channel_id.clone(),
+                                            is_typing: false,
+                                        }).ok();
                                     }
+                                    _ => {}
                                 }
-                                response => {
-                                    tracing::info!(
-                                        conversation_id = %outbound_conversation_id,
-                                        "routing outbound response to messaging adapter"
-                                    );
-                                    if let Err(error) = messaging_for_outbound
-                                        .respond(&current_message, response)
-                                        .await
-                                    {
-                                        tracing::error!(%error, "failed to send outbound response");
+
+                                match response {
+                                    spacebot::OutboundResponse::Status(status) => {
+                                        if let Err(error) = messaging_for_outbound
+                                            .send_status(&current_message, status)
+                                            .await
+                                        {
+                                            tracing::warn!(%error, "failed to send status update");
+                                        }
+                                    }
+                                    response => {
+                                        // Replies to webhook hooks linked to a channel are
+                                        // posted there too.
+                                        if let Some((target, delivery)) =
+                                            spacebot::messaging::webhook::hook_delivery(
+                                                &current_message,
+                                                &response,
+                                            )
+                                            && let Err(error) = messaging_for_outbound
+                                                .broadcast(
+                                                    &target.adapter,
+                                                    &target.target,
+                                                    delivery,
+                                                )
+                                                .await
+                                        {
+                                            tracing::error!(
+                                                %error,
+                                                %target,
+                                                "failed to deliver webhook hook reply"
+                                            );
+                                        }
+                                        tracing::info!(
+                                            conversation_id = %outbound_conversation_id,
+                                            "routing outbound response to messaging adapter"
+                                        );
+                                        if let Err(error) = messaging_for_outbound
+                                            .respond(&current_message, response)
+                                            .await
+                                        {
+                                            tracing::error!(%error, "failed to send outbound response");
+                                        }
                                     }
                                 }
                             }
-                        }
-                    });
-
-                    active_channels.insert(conversation_id.clone(), ActiveChannel {
-                        message_tx: channel_tx,
-                        latest_message,
-                        _outbound_handle: outbound_handle,
-                    });
-
-                    tracing::info!(
-                        conversation_id = %conversation_id,
-                        agent_id = %agent_id,
-                        "new channel created"
-                    );
-                }
-
-                // Forward the message to the channel
-                if let Some(active) = active_channels.get(&conversation_id) {
-                    // Update the shared message reference so outbound routing
-                    // (typing indicators, reactions) targets this message
-                    *active.latest_message.write().await = message.clone();
-
-                    // Emit inbound message to SSE clients
-                    let sender_name = message.formatted_author.clone().or_else(|| {
-                        message
-                            .metadata
-                            .get("sender_display_name")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                    });
-                    api_state.event_tx.send(spacebot::api::ApiEvent::InboundMessage {
-                        agent_id: agent_id.to_string(),
-                        channel_id: conversation_id.clone(),
-                        sender_name,
-                        sender_id: message.sender_id.clone(),
-                        text: message.content.to_string(),
-                    }).ok();
-
-                    if let Err(error) = active.message_tx.send(message).await {
-                        tracing::error!(
+                        });
+
+                        active_channels.insert(channel_key.clone(), ActiveChannel {
+                            inbox: spacebot::agent::backpressure::ChannelInbox::spawn(channel_tx),
+                            latest_message,
+                            channel_handle,
+                            turn_canceller,
+                            status_block,
+                            dropping: false,
+                            _outbound_handle: outbound_handle,
+                        });
+
+                        tracing::info!(
                             conversation_id = %conversation_id,
-                            %error,
-                            "failed to forward message to channel"
+                            agent_id = %agent_id,
+                            "new channel created"
                         );
-                        active_channels.remove(&conversation_id);
+                    }
+
+                    // Forward the message to the channel
+                    if let Some(active) = active_channels.get_mut(&channel_key) {
+                        // Update the shared message reference so outbound routing
+                        // (typing indicators, reactions) targets this message.
+                        // Passive messages don't get a reply, so they don't move it.
+                        if !spacebot::agent::listening::is_passive(&message) {
+                            *active.latest_message.write().await = message.clone();
+                        }
+
+                        // Emit inbound message to SSE clients
+                        let sender_name = message.formatted_author.clone().or_else(|| {
+                            message
+                                .metadata
+                                .get("sender_display_name")
+                                .and_then(|v| v.as_str())
+                                .map(|s| s.to_string())
+                        });
+                        api_state.event_tx.send(spacebot::api::ApiEvent::InboundMessage {
+                            agent_id: agent_id.to_string(),
+                            channel_id: conversation_id.clone(),
+                            sender_name,
+                            sender_id: message.sender_id.clone(),
+                            text: message.content.to_string(),
+                            turn_id: message.turn_id().map(String::from),
+                        }).ok();
+
+                        let backpressure = agents
+                            .get(&agent_id)
+                            .map(|agent| agent.deps.runtime_config.backpressure.load_full())
+                            .unwrap_or_default();
+                        match spacebot::agent::backpressure::forward(
+                            &active.inbox,
+                            message,
+                            &backpressure,
+                            &mut active.dropping,
+                            &agent_id,
+                        ) {
+                            Ok(spacebot::agent::backpressure::Forwarded::Queued) => {}
+                            Ok(spacebot::agent::backpressure::Forwarded::Dropped { notice }) => {
+                                tracing::warn!(
+                                    conversation_id = %conversation_id,
+                                    agent_id = %agent_id,
+                                    "channel behind, dropped message"
+                                );
+                                if let Some(notice) = notice {
+                                    let text = backpressure.drop_notice.clone();
+                                    if let Err(error) = messaging_manager.respond(&notice, spacebot::OutboundResponse::Text(text)).await {
+                                        tracing::warn!(%error, "failed to send backpressure notice");
+                                    }
+                                }
+                            }
+                            Err(error) => {
+                                tracing::error!(
+                                    conversation_id = %conversation_id,
+                                    %error,
+                                    "failed to forward message to channel"
+                                );
+                                active_channels.remove(&channel_key);
+                            }
+                        }
                     }
                 }
             }
@@ -918,12 +1482,33 @@ async fn run(
                     tracing::warn!(agent_id = %agent_id, "agent not found in main loop for removal");
                 }
             }
+            Ok(agent_id) = health_restart_rx.recv() => {
+                // Drop the agent's channels; the next message rebuilds them
+                // with fresh history and state.
+                let channel_keys: Vec<(spacebot::AgentId, String)> = active_channels
+                    .keys()
+                    .filter(|(channel_agent_id, _)| **channel_agent_id == *agent_id)
+                    .cloned()
+                    .collect();
+                for channel_key in &channel_keys {
+                    if let Some(active) = active_channels.remove(channel_key) {
+                        active.channel_handle.abort();
+                    }
+                    api_state.unregister_channel_status(&channel_key.0, &channel_key.1).await;
+                    api_state.unregister_channel_state(&channel_key.0, &channel_key.1).await;
+                }
+                tracing::warn!(
+                    agent_id = %agent_id,
+                    channel_count = channel_keys.len(),
+                    "restarted agent channels after repeated LLM failures"
+                );
+            }
             Some(_event) = provider_rx.recv(), if !agents_initialized => {
                 tracing::info!("providers configured, initializing agents");
 
                 // Reload config from disk to pick up new keys
                 let new_config = if config_path.exists() {
-                    spacebot::config::Config::load_from_path(&config_path)
+                    spacebot::config::Config::load_from_path_async(&config_path).await
                 } else {
                     let instance_dir = config_path.parent()
                         .map(|p| p.to_path_buf())
@@ -936,12 +1521,20 @@ async fn run(
                         // Rebuild LlmManager with the new keys
                         match spacebot::llm::LlmManager::new(new_config.llm.clone()).await {
                             Ok(new_llm) => {
-                                let new_llm_manager = Arc::new(new_llm);
+                                health_registry.set_config(new_config.defaults.health);
+                                let new_llm_manager =
+                                    Arc::new(new_llm.with_health_registry(health_registry.clone()));
                                 let mut new_watcher_agents = Vec::new();
                                 let mut new_discord_permissions = None;
                                 let mut new_slack_permissions = None;
                                 let mut new_telegram_permissions = None;
                                 let mut new_twitch_permissions = None;
+                                let mut new_matrix_permissions = None;
+                                let mut new_irc_permissions = None;
+                                let mut new_email_permissions = None;
+                                let mut new_whatsapp_permissions = None;
+                                let mut new_sms_permissions = None;
+                                let mut new_xmpp_permissions = None;
                                 match initialize_agents(
                                     &new_config,
                                     &new_llm_manager,
@@ -959,9 +1552,17 @@ async fn run(
                                     &mut new_slack_permissions,
                                     &mut new_telegram_permissions,
                                     &mut new_twitch_permissions,
+                                    &mut new_matrix_permissions,
+                                    &mut new_irc_permissions,
+                                    &mut new_email_permissions,
+                                    &mut new_whatsapp_permissions,
+                                    &mut new_sms_permissions,
+                                    &mut new_xmpp_permissions,
+                                    stdio_closed_tx.as_ref(),
                                 ).await {
                                     Ok(()) => {
                                         agents_initialized = true;
+                                        channel_settings = load_channel_settings(&agents).await;
                                         // Restart file watcher with the new agent data
                                         _file_watcher = spacebot::config::spawn_file_watcher(
                                             config_path.clone(),
@@ -971,9 +1572,18 @@ async fn run(
                                             new_slack_permissions,
                                             new_telegram_permissions,
                                             new_twitch_permissions,
+                                            new_matrix_permissions,
+                                            new_irc_permissions,
+                                            new_email_permissions,
+                                            new_whatsapp_permissions,
+                                            new_sms_permissions,
+                                            new_xmpp_permissions,
                                             bindings.clone(),
+                                            channel_routing.clone(),
                                             Some(messaging_manager.clone()),
                                             new_llm_manager.clone(),
+                                            new_config.secrets.refresh_interval_secs,
+                                            new_config.remote.refresh_interval_secs,
                                         );
                                         tracing::info!("agents initialized after provider setup");
                                     }
@@ -999,6 +1609,10 @@ async fn run(
                 tracing::info!("shutdown signal received via IPC");
                 break;
             }
+            _ = stdio_closed_rx.wait_for(|closed| *closed), if stdio => {
+                tracing::info!("stdin closed, shutting down");
+                break;
+            }
             _ = tokio::signal::ctrl_c() => {
                 tracing::info!("shutdown signal received");
                 break;
@@ -1023,21 +1637,88 @@ async fn run(
 
     tracing::info!("spacebot stopped");
 
-    // Flush buffered OTLP spans before the process exits. Without this the
-    // batch exporter drops any spans recorded in the last export interval.
-    if let Some(provider) = otel_provider {
-        if let Err(error) = provider.shutdown() {
-            tracing::warn!(%error, "failed to flush OTel spans on shutdown");
-        }
+    // Flush buffered OTLP spans and metrics before the process exits. Without
+    // this the exporters drop anything recorded in the last export interval.
+    if let Some(providers) = otel_providers {
+        providers.shutdown();
+    }
+    if let Some(error_reporting) = error_reporting {
+        error_reporting.flush();
     }
 
     spacebot::daemon::cleanup(&paths);
+    spacebot::recovery::mark_stopped(&config.instance_dir);
 
     // Force exit — detached tasks (e.g. the serenity gateway client) may keep
     // the tokio runtime alive after all owned resources have been cleaned up.
     std::process::exit(0);
 }
 
+/// Settings changed from chat, from every agent's channel registry.
+async fn load_channel_settings(
+    agents: &HashMap<spacebot::AgentId, spacebot::Agent>,
+) -> HashMap<String, spacebot::conversation::ChannelSettings> {
+    let mut settings = HashMap::new();
+    for (agent_id, agent) in agents {
+        match spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone())
+            .with_readers(agent.db.sqlite_read.clone())
+            .load_settings()
+            .await
+        {
+            Ok(loaded) => settings.extend(loaded),
+            Err(error) => {
+                tracing::warn!(agent_id = %agent_id, %error, "failed to load channel settings");
+            }
+        }
+    }
+    settings
+}
+
+/// Check every agent for work the previous run left unfinished, log what was
+/// found, keep the report for the API, and tell `recovery_notify` if the
+/// previous run didn't shut down cleanly.
+async fn report_recovery(
+    agents: &HashMap<spacebot::AgentId, spacebot::Agent>,
+    previous_run: Option<spacebot::recovery::RunMarker>,
+    booted_at: chrono::DateTime<chrono::Utc>,
+    api_state: &Arc<spacebot::api::ApiState>,
+    notify: &[String],
+) {
+    let mut recoveries = Vec::new();
+    for (agent_id, agent) in agents {
+        match spacebot::recovery::inspect_agent(agent_id, &agent.db.sqlite, booted_at).await {
+            Ok(recovery) => recoveries.push(recovery),
+            Err(error) => {
+                tracing::warn!(agent_id = %agent_id, %error, "failed to check agent for recovery");
+            }
+        }
+    }
+    recoveries.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+    let report = spacebot::recovery::RecoveryReport::new(previous_run, recoveries);
+
+    if report.clean_shutdown && report.agents.is_empty() {
+        tracing::info!("previous run shut down cleanly");
+    } else {
+        tracing::warn!(
+            clean_shutdown = report.clean_shutdown,
+            "recovery report:\n{}",
+            report.summary()
+        );
+    }
+    if !report.clean_shutdown && !notify.is_empty() {
+        let http = reqwest::Client::new();
+        let text = format!("Spacebot restarted after a crash.\n{}", report.summary());
+        for target in notify {
+            if let Err(error) =
+                spacebot::alerts::notify(&http, api_state, target, &text, &report).await
+            {
+                tracing::warn!(%error, target, "failed to send recovery report");
+            }
+        }
+    }
+    api_state.set_last_recovery(report);
+}
+
 /// Initialize agents, messaging adapters, cron, cortex, and ingestion.
 /// Extracted so it can be called either at startup or after providers are configured.
 #[allow(clippy::too_many_arguments)]
@@ -1064,6 +1745,13 @@ async fn initialize_agents(
     slack_permissions: &mut Option<Arc<ArcSwap<spacebot::config::SlackPermissions>>>,
     telegram_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TelegramPermissions>>>,
     twitch_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TwitchPermissions>>>,
+    matrix_permissions: &mut Option<Arc<ArcSwap<spacebot::config::MatrixPermissions>>>,
+    irc_permissions: &mut Option<Arc<ArcSwap<spacebot::config::IrcPermissions>>>,
+    email_permissions: &mut Option<Arc<ArcSwap<spacebot::config::EmailPermissions>>>,
+    whatsapp_permissions: &mut Option<Arc<ArcSwap<spacebot::config::WhatsAppPermissions>>>,
+    sms_permissions: &mut Option<Arc<ArcSwap<spacebot::config::SmsPermissions>>>,
+    xmpp_permissions: &mut Option<Arc<ArcSwap<spacebot::config::XmppPermissions>>>,
+    stdio_closed: Option<&tokio::sync::watch::Sender<bool>>,
 ) -> anyhow::Result<()> {
     let resolved_agents = config.resolve_agents();
 
@@ -1111,6 +1799,9 @@ async fn initialize_agents(
                     agent_config.id
                 )
             })?;
+        llm_manager
+            .call_log()
+            .register(&agent_config.id, db.sqlite.clone());
 
         // Per-agent settings store (redb-backed)
         let settings_path = agent_config.data_dir.join("settings.redb");
@@ -1125,29 +1816,79 @@ async fn initialize_agents(
 
         // Per-agent memory system
         let memory_store = spacebot::memory::MemoryStore::new(db.sqlite.clone());
-        let embedding_table = spacebot::memory::EmbeddingTable::open_or_create(&db.lance)
-            .await
-            .with_context(|| {
-                format!("failed to init embeddings for agent '{}'", agent_config.id)
-            })?;
+        let vector_store = spacebot::memory::open_vector_store(
+            &agent_config.vector_store,
+            &agent_config.id,
+            &db,
+            llm_manager.http_client().clone(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "failed to open {} vector store for agent '{}'",
+                agent_config.vector_store.name(),
+                agent_config.id
+            )
+        })?;
 
         // Ensure FTS index exists for full-text search queries
-        if let Err(error) = embedding_table.ensure_fts_index().await {
+        if let Err(error) = vector_store.ensure_fts_index().await {
             tracing::warn!(%error, agent = %agent_config.id, "failed to create FTS index");
         }
 
-        let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
-            memory_store,
-            embedding_table,
+        // Remote conversation store, restored into SQLite on a fresh host
+        let conversation_store = spacebot::conversation::open_conversation_store(
+            &agent_config.conversation_store,
+            &agent_config.id,
+            &db.sqlite,
+            llm_manager.http_client().clone(),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "failed to open {} conversation store for agent '{}'",
+                agent_config.conversation_store.name(),
+                agent_config.id
+            )
+        })?;
+
+        let embedding_pipeline = spacebot::memory::EmbeddingPipeline::spawn(
             embedding_model.clone(),
-        ));
+            vector_store.clone(),
+            config.defaults.embedding.clone(),
+        );
+        let memory_search = Arc::new(
+            spacebot::memory::MemorySearch::new(
+                memory_store,
+                vector_store,
+                embedding_model.clone(),
+            )
+            .with_embedding_pipeline(embedding_pipeline),
+        );
 
         // Per-agent event bus (broadcast for fan-out to multiple channels)
         let (event_tx, _event_rx) = tokio::sync::broadcast::channel(256);
 
         let agent_id: spacebot::AgentId = Arc::from(agent_config.id.as_str());
-
-        // Scaffold identity templates if missing, then load
+        llm_manager
+            .concurrency()
+            .register_agent(&agent_config.id, agent_config.max_concurrent_llm_calls);
+
+        // Scaffold identity templates if missing, preset files first, then load
+        if let Some(preset) = &agent_config.preset {
+            spacebot::identity::scaffold_preset_files(
+                &agent_config.workspace,
+                &preset.name,
+                &preset.params,
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to write preset files for agent '{}'",
+                    agent_config.id
+                )
+            })?;
+        }
         spacebot::identity::scaffold_identity_files(&agent_config.workspace)
             .await
             .with_context(|| {
@@ -1173,8 +1914,16 @@ async fn initialize_agents(
             skills,
         ));
 
+        runtime_config.set_peers(spacebot::agent::capabilities::peers(
+            config,
+            &agent_config.id,
+        ));
+
         // Set the settings store in RuntimeConfig and apply config-driven defaults
         runtime_config.set_settings(settings_store.clone());
+        if let Some(conversation_store) = conversation_store {
+            runtime_config.set_conversation_store(conversation_store);
+        }
         if let Err(error) = settings_store.set_worker_log_mode(config.defaults.worker_log_mode) {
             tracing::warn!(%error, agent = %agent_config.id, "failed to set worker_log_mode from config");
         }
@@ -1193,6 +1942,7 @@ async fn initialize_agents(
             runtime_config,
             event_tx,
             sqlite_pool: db.sqlite.clone(),
+            sqlite_read: db.sqlite_read.clone(),
             messaging_manager: None,
         };
 
@@ -1212,6 +1962,7 @@ async fn initialize_agents(
     // Wire agent event streams, DB pools, and config summaries into the API server
     {
         let mut agent_pools = std::collections::HashMap::new();
+        let mut agent_read_pools = std::collections::HashMap::new();
         let mut agent_configs = Vec::new();
         let mut memory_searches = std::collections::HashMap::new();
         let mut agent_workspaces = std::collections::HashMap::new();
@@ -1220,6 +1971,7 @@ async fn initialize_agents(
             let event_rx = agent.deps.event_tx.subscribe();
             api_state.register_agent_events(agent_id.to_string(), event_rx);
             agent_pools.insert(agent_id.to_string(), agent.db.sqlite.clone());
+            agent_read_pools.insert(agent_id.to_string(), agent.db.sqlite_read.clone());
             memory_searches.insert(agent_id.to_string(), agent.deps.memory_search.clone());
             agent_workspaces.insert(agent_id.to_string(), agent.config.workspace.clone());
             runtime_configs.insert(agent_id.to_string(), agent.deps.runtime_config.clone());
@@ -1233,6 +1985,7 @@ async fn initialize_agents(
             });
         }
         api_state.set_agent_pools(agent_pools);
+        api_state.set_agent_read_pools(agent_read_pools);
         api_state.set_agent_configs(agent_configs);
         api_state.set_memory_searches(memory_searches);
         api_state.set_runtime_configs(runtime_configs);
@@ -1242,6 +1995,13 @@ async fn initialize_agents(
 
     // Initialize messaging adapters
     let new_messaging_manager = spacebot::messaging::MessagingManager::new();
+    match spacebot::messaging::guardrails::OutputFilter::from_config(
+        &config.messaging.guardrails,
+        llm_manager.clone(),
+    ) {
+        Ok(filter) => new_messaging_manager.set_output_filter(filter),
+        Err(error) => tracing::error!(%error, "invalid guardrails pattern, guardrails disabled"),
+    }
 
     // Shared Discord permissions (hot-reloadable via file watcher)
     *discord_permissions = config.messaging.discord.as_ref().map(|discord_config| {
@@ -1261,6 +2021,35 @@ async fn initialize_agents(
                     .clone()
                     .expect("discord permissions initialized when discord is enabled"),
             );
+
+            // Where each channel left off before the restart, to fetch what
+            // was said while down.
+            let mut recorded = Vec::new();
+            if discord_config.catch_up.enabled {
+                for (agent_id, agent) in agents.iter() {
+                    let logger = spacebot::conversation::history::ConversationLogger::new(
+                        agent.db.sqlite.clone(),
+                    )
+                    .with_readers(agent.db.sqlite_read.clone());
+                    match logger
+                        .latest_per_channel(
+                            "discord",
+                            spacebot::messaging::discord::BACKFILL_MAX_CHANNELS as i64,
+                        )
+                        .await
+                    {
+                        Ok(messages) => recorded.extend(messages),
+                        Err(error) => {
+                            tracing::warn!(
+                                agent_id = %agent_id,
+                                %error,
+                                "failed to load discord channels to catch up on"
+                            );
+                        }
+                    }
+                }
+            }
+            let adapter = adapter.with_catch_up(&recorded);
             new_messaging_manager.register(adapter).await;
         }
     }
@@ -1315,10 +2104,7 @@ async fn initialize_agents(
 
     if let Some(webhook_config) = &config.messaging.webhook {
         if webhook_config.enabled {
-            let adapter = spacebot::messaging::webhook::WebhookAdapter::new(
-                webhook_config.port,
-                &webhook_config.bind,
-            );
+            let adapter = spacebot::messaging::webhook::WebhookAdapter::new(webhook_config);
             new_messaging_manager.register(adapter).await;
         }
     }
@@ -1345,9 +2131,127 @@ async fn initialize_agents(
         }
     }
 
+    // Shared Matrix permissions (hot-reloadable via file watcher)
+    *matrix_permissions = config.messaging.matrix.as_ref().map(|matrix_config| {
+        let perms =
+            spacebot::config::MatrixPermissions::from_config(matrix_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(matrix_config) = &config.messaging.matrix {
+        if matrix_config.enabled {
+            let adapter = spacebot::messaging::matrix::MatrixAdapter::new(
+                &matrix_config.homeserver_url,
+                &matrix_config.access_token,
+                matrix_config.rooms.clone(),
+                matrix_config.auto_join,
+                matrix_permissions
+                    .clone()
+                    .expect("matrix permissions initialized when matrix is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    // Shared IRC permissions (hot-reloadable via file watcher)
+    *irc_permissions = config.messaging.irc.as_ref().map(|irc_config| {
+        let perms = spacebot::config::IrcPermissions::from_config(irc_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(irc_config) = &config.messaging.irc {
+        if irc_config.enabled {
+            let adapter = spacebot::messaging::irc::IrcAdapter::new(
+                irc_config,
+                irc_permissions
+                    .clone()
+                    .expect("irc permissions initialized when irc is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    // Shared email permissions (hot-reloadable via file watcher)
+    *email_permissions = config.messaging.email.as_ref().map(|email_config| {
+        let perms = spacebot::config::EmailPermissions::from_config(email_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(email_config) = &config.messaging.email {
+        if email_config.enabled {
+            let adapter = spacebot::messaging::email::EmailAdapter::new(
+                email_config,
+                email_permissions
+                    .clone()
+                    .expect("email permissions initialized when email is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    // Shared WhatsApp permissions (hot-reloadable via file watcher)
+    *whatsapp_permissions = config.messaging.whatsapp.as_ref().map(|whatsapp_config| {
+        let perms =
+            spacebot::config::WhatsAppPermissions::from_config(whatsapp_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(whatsapp_config) = &config.messaging.whatsapp {
+        if whatsapp_config.enabled {
+            let adapter = spacebot::messaging::whatsapp::WhatsAppAdapter::new(
+                whatsapp_config,
+                whatsapp_permissions
+                    .clone()
+                    .expect("whatsapp permissions initialized when whatsapp is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    // Shared SMS permissions (hot-reloadable via file watcher)
+    *sms_permissions = config.messaging.sms.as_ref().map(|sms_config| {
+        let perms = spacebot::config::SmsPermissions::from_config(sms_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(sms_config) = &config.messaging.sms {
+        if sms_config.enabled {
+            let adapter = spacebot::messaging::sms::SmsAdapter::new(
+                sms_config,
+                sms_permissions
+                    .clone()
+                    .expect("sms permissions initialized when sms is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    // Shared XMPP permissions (hot-reloadable via file watcher)
+    *xmpp_permissions = config.messaging.xmpp.as_ref().map(|xmpp_config| {
+        let perms = spacebot::config::XmppPermissions::from_config(xmpp_config, &config.bindings);
+        Arc::new(ArcSwap::from_pointee(perms))
+    });
+
+    if let Some(xmpp_config) = &config.messaging.xmpp {
+        if xmpp_config.enabled {
+            let adapter = spacebot::messaging::xmpp::XmppAdapter::new(
+                xmpp_config,
+                xmpp_permissions
+                    .clone()
+                    .expect("xmpp permissions initialized when xmpp is enabled"),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    if let Some(stdio_closed) = stdio_closed {
+        let adapter = spacebot::messaging::stdio::StdioAdapter::new(stdio_closed.clone());
+        new_messaging_manager.register(adapter).await;
+    }
+
     let webchat_adapter = Arc::new(spacebot::messaging::webchat::WebChatAdapter::new());
     new_messaging_manager
-        .register_shared(webchat_adapter.clone())
+        .register_webchat(webchat_adapter.clone())
         .await;
     api_state.set_webchat_adapter(webchat_adapter);
 
@@ -1362,6 +2266,7 @@ async fn initialize_agents(
         .await
         .context("failed to start messaging adapters")?;
     *inbound_stream = Some(new_inbound);
+    messaging_manager.spawn_presence_updates();
 
     tracing::info!("messaging adapters started");
 
@@ -1370,10 +2275,24 @@ async fn initialize_agents(
     let mut cron_schedulers_map = std::collections::HashMap::new();
 
     for (agent_id, agent) in agents.iter_mut() {
-        let store = Arc::new(spacebot::cron::CronStore::new(agent.db.sqlite.clone()));
+        let store = Arc::new(
+            spacebot::cron::CronStore::new(agent.db.sqlite.clone())
+                .with_readers(agent.db.sqlite_read.clone()),
+        );
 
         // Seed cron jobs from config into the database
         for cron_def in &agent.config.cron {
+            // A one-off whose time has passed is left as stored, so a
+            // restart doesn't re-enable it and post it again.
+            if cron_def.run_once
+                && cron_def
+                    .start_at
+                    .as_deref()
+                    .and_then(spacebot::cron::scheduler::parse_start_at)
+                    .is_some_and(|start_at| start_at <= chrono::Utc::now())
+            {
+                continue;
+            }
             let cron_config = spacebot::cron::CronConfig {
                 id: cron_def.id.clone(),
                 prompt: cron_def.prompt.clone(),
@@ -1381,6 +2300,9 @@ async fn initialize_agents(
                 delivery_target: cron_def.delivery_target.clone(),
                 active_hours: cron_def.active_hours,
                 enabled: cron_def.enabled,
+                message: cron_def.message.clone(),
+                start_at: cron_def.start_at.clone(),
+                run_once: cron_def.run_once,
             };
             if let Err(error) = store.save(&cron_config).await {
                 tracing::warn!(
@@ -1453,7 +2375,8 @@ async fn initialize_agents(
 
     // Start cortex bulletin loops and association loops for each agent
     for (agent_id, agent) in agents.iter() {
-        let cortex_logger = spacebot::agent::cortex::CortexLogger::new(agent.db.sqlite.clone());
+        let cortex_logger = spacebot::agent::cortex::CortexLogger::new(agent.db.sqlite.clone())
+            .with_readers(agent.db.sqlite_read.clone());
         let bulletin_handle =
             spacebot::agent::cortex::spawn_bulletin_loop(agent.deps.clone(), cortex_logger.clone());
         cortex_handles.push(bulletin_handle);
@@ -1465,6 +2388,28 @@ async fn initialize_agents(
         tracing::info!(agent_id = %agent_id, "cortex association loop started");
     }
 
+    // Start rollups and the daily digest for each agent
+    for (agent_id, agent) in agents.iter() {
+        let handle = spacebot::agent::analytics::spawn_rollup_loop(
+            agent.db.sqlite.clone(),
+            agent.db.sqlite_read.clone(),
+        );
+        cortex_handles.push(handle);
+        tracing::info!(agent_id = %agent_id, "channel analytics rollup loop started");
+
+        let handle = spacebot::llm::usage::spawn_rollup_loop(
+            agent.db.sqlite.clone(),
+            agent.db.sqlite_read.clone(),
+            agent.deps.runtime_config.clone(),
+        );
+        cortex_handles.push(handle);
+        tracing::info!(agent_id = %agent_id, "token usage rollup loop started");
+
+        let handle = spacebot::agent::digest::spawn_digest_loop(agent.deps.clone());
+        cortex_handles.push(handle);
+        tracing::info!(agent_id = %agent_id, "daily digest loop started");
+    }
+
     // Create cortex chat sessions for each agent
     {
         let mut sessions = std::collections::HashMap::new();
@@ -1472,8 +2417,10 @@ async fn initialize_agents(
             let browser_config = (**agent.deps.runtime_config.browser_config.load()).clone();
             let brave_search_key = (**agent.deps.runtime_config.brave_search_key.load()).clone();
             let conversation_logger =
-                spacebot::conversation::history::ConversationLogger::new(agent.db.sqlite.clone());
-            let channel_store = spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone());
+                spacebot::conversation::history::ConversationLogger::new(agent.db.sqlite.clone())
+                    .with_readers(agent.db.sqlite_read.clone());
+            let channel_store = spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone())
+                .with_readers(agent.db.sqlite_read.clone());
             let tool_server = spacebot::tools::create_cortex_chat_tool_server(
                 agent.deps.memory_search.clone(),
                 conversation_logger,
@@ -1484,7 +2431,8 @@ async fn initialize_agents(
                 agent.deps.runtime_config.workspace_dir.clone(),
                 agent.deps.runtime_config.instance_dir.clone(),
             );
-            let store = spacebot::agent::cortex_chat::CortexChatStore::new(agent.db.sqlite.clone());
+            let store = spacebot::agent::cortex_chat::CortexChatStore::new(agent.db.sqlite.clone())
+                .with_readers(agent.db.sqlite_read.clone());
             let session = spacebot::agent::cortex_chat::CortexChatSession::new(
                 agent.deps.clone(),
                 tool_server,
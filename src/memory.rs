@@ -3,12 +3,20 @@
 pub mod embedding;
 pub mod lance;
 pub mod maintenance;
+#[cfg(feature = "pgvector")]
+pub mod pgvector;
+pub mod pipeline;
 pub mod search;
+pub mod sqlite_vector;
 pub mod store;
 pub mod types;
+pub mod vector;
 
 pub use embedding::EmbeddingModel;
 pub use lance::EmbeddingTable;
+pub use pipeline::EmbeddingPipeline;
 pub use search::{MemorySearch, SearchConfig, SearchMode, SearchSort, curate_results};
+pub use sqlite_vector::SqliteVectorStore;
 pub use store::MemoryStore;
 pub use types::{Association, Memory, MemoryType, RelationType};
+pub use vector::{VectorBackendConfig, VectorStore, VectorStoreDyn, open_vector_store};
@@ -13,9 +13,31 @@ pub struct EmbeddingModel {
 }
 
 impl EmbeddingModel {
-    /// Create a new embedding model, storing downloaded model files in `cache_dir`.
+    /// Create the default embedding model, storing downloaded model files in `cache_dir`.
     pub fn new(cache_dir: &Path) -> Result<Self> {
-        let options = fastembed::InitOptions::default()
+        Self::with_model(cache_dir, &crate::config::EmbeddingConfig::default().model)
+    }
+
+    /// Create an embedding model from a fastembed model code such as
+    /// `"Xenova/bge-small-en-v1.5"`. The vector stores are sized for
+    /// [`EMBEDDING_DIM`](crate::memory::vector::EMBEDDING_DIM), so models with
+    /// any other dimension are rejected.
+    pub fn with_model(cache_dir: &Path, model_code: &str) -> Result<Self> {
+        let model_kind: fastembed::EmbeddingModel = model_code
+            .parse()
+            .map_err(|e: String| LlmError::EmbeddingFailed(e))?;
+        let dim = fastembed::TextEmbedding::get_model_info(&model_kind)
+            .map_err(|e| LlmError::EmbeddingFailed(e.to_string()))?
+            .dim;
+        if dim != crate::memory::vector::EMBEDDING_DIM {
+            return Err(LlmError::EmbeddingFailed(format!(
+                "embedding model '{model_code}' produces {dim}-dimensional vectors, expected {}",
+                crate::memory::vector::EMBEDDING_DIM
+            ))
+            .into());
+        }
+
+        let options = fastembed::InitOptions::new(model_kind)
             .with_cache_dir(cache_dir.to_path_buf())
             .with_show_download_progress(true);
 
@@ -54,6 +76,18 @@ impl EmbeddingModel {
 
         Ok(result.into_iter().next().unwrap_or_default())
     }
+
+    /// Generate embeddings for a batch of texts (async, spawns blocking task).
+    pub async fn embed_batch(self: &Arc<Self>, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || {
+            model.embed(texts, None).map_err(|e| {
+                crate::Error::Llm(crate::error::LlmError::EmbeddingFailed(e.to_string()))
+            })
+        })
+        .await
+        .map_err(|e| crate::Error::Other(anyhow::anyhow!("embedding task failed: {}", e)))?
+    }
 }
 
 /// Async function to embed text using a shared model.
@@ -9,7 +9,7 @@ use std::sync::Arc;
 
 /// Schema constants for the embeddings table.
 const TABLE_NAME: &str = "memory_embeddings";
-const EMBEDDING_DIM: i32 = 384; // all-MiniLM-L6-v2 dimension
+const EMBEDDING_DIM: i32 = crate::memory::vector::EMBEDDING_DIM as i32;
 
 /// LanceDB table for memory embeddings with HNSW index and FTS.
 pub struct EmbeddingTable {
@@ -61,6 +61,74 @@ impl EmbeddingTable {
         Ok(Self { table })
     }
 
+    /// Open the table only if it already exists, without creating it.
+    pub async fn open_existing(connection: &lancedb::Connection) -> Result<Option<Self>> {
+        let names = connection
+            .table_names()
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+        if !names.iter().any(|name| name == TABLE_NAME) {
+            return Ok(None);
+        }
+
+        let table = connection
+            .open_table(TABLE_NAME)
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+        Ok(Some(Self { table }))
+    }
+
+    /// Read every stored embedding as (memory_id, content, embedding) rows.
+    /// Used to seed another vector backend when an agent switches to it.
+    pub async fn export_all(&self) -> Result<Vec<(String, String, Vec<f32>)>> {
+        use lancedb::query::ExecutableQuery;
+
+        let results: Vec<arrow_array::RecordBatch> = self
+            .table
+            .query()
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+
+        let mut rows = Vec::new();
+        for batch in results {
+            let (Some(id_col), Some(content_col), Some(embedding_col)) = (
+                batch.column_by_name("id"),
+                batch.column_by_name("content"),
+                batch.column_by_name("embedding"),
+            ) else {
+                continue;
+            };
+            let ids: &arrow_array::StringArray = id_col.as_string::<i32>();
+            let contents: &arrow_array::StringArray = content_col.as_string::<i32>();
+            let Some(embeddings) = embedding_col
+                .as_any()
+                .downcast_ref::<arrow_array::FixedSizeListArray>()
+            else {
+                continue;
+            };
+
+            for i in 0..ids.len() {
+                if ids.is_valid(i) && contents.is_valid(i) && embeddings.is_valid(i) {
+                    let values = embeddings.value(i);
+                    let embedding = values.as_primitive::<Float32Type>().values().to_vec();
+                    rows.push((
+                        ids.value(i).to_string(),
+                        contents.value(i).to_string(),
+                        embedding,
+                    ));
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
     /// Create an empty embeddings table.
     async fn create_empty_table(connection: &lancedb::Connection) -> Result<lancedb::Table> {
         let schema = Self::schema();
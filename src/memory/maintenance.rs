@@ -1,5 +1,6 @@
 //! Memory maintenance: decay, prune, merge, reindex.
 
+use crate::db::TimedQuery as _;
 use crate::error::Result;
 use crate::memory::MemoryStore;
 use crate::memory::types::{Memory, MemoryType, RelationType};
@@ -109,6 +110,9 @@ async fn prune_memories(memory_store: &MemoryStore, config: &MaintenanceConfig)
     .bind(config.prune_threshold)
     .bind(cutoff_date)
     .fetch_all(memory_store.pool())
+    .timed_with("maintenance.prune_candidates", || {
+        format!("threshold={}", config.prune_threshold)
+    })
     .await?;
 
     let mut pruned_count = 0;
@@ -0,0 +1,204 @@
+//! Postgres + pgvector vector store backend (behind the `pgvector` feature).
+
+use crate::db::TimedQuery as _;
+use crate::error::{DbError, Result};
+use crate::memory::vector::{EMBEDDING_DIM, VectorStore};
+
+use sqlx::PgPool;
+
+/// Embeddings stored in a per-agent Postgres table with an HNSW cosine index
+/// and a generated `tsvector` column, so hybrid search keeps its keyword leg.
+#[derive(Debug, Clone)]
+pub struct PgVectorStore {
+    pool: PgPool,
+    /// Quoted table identifier, safe to interpolate into SQL.
+    table: String,
+}
+
+impl PgVectorStore {
+    /// Connect and make sure the extension, table and indexes exist.
+    ///
+    /// Returns the store and whether the table was created by this call.
+    pub async fn connect(url: &str, table_prefix: &str, agent_id: &str) -> Result<(Self, bool)> {
+        let pool = PgPool::connect(url)
+            .await
+            .map_err(|error| DbError::Query(format!("can't connect to postgres: {error}")))?;
+
+        let table_name = format!("{table_prefix}_{agent_id}");
+        let store = Self {
+            pool,
+            table: format!("\"{}\"", table_name.replace('"', "\"\"")),
+        };
+        let created = store.ensure_table(&table_name).await?;
+        Ok((store, created))
+    }
+
+    async fn ensure_table(&self, table_name: &str) -> Result<bool> {
+        sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&self.pool)
+            .await
+            .map_err(query_error)?;
+
+        // pgvector stores the dimension as the column's type modifier.
+        let existing_dim: Option<i32> = sqlx::query_scalar(
+            "SELECT a.atttypmod FROM pg_attribute a \
+             WHERE a.attrelid = to_regclass($1) AND a.attname = 'embedding'",
+        )
+        .bind(&self.table)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(query_error)?;
+
+        if let Some(dim) = existing_dim {
+            if dim != EMBEDDING_DIM as i32 {
+                return Err(DbError::Query(format!(
+                    "can't use pgvector table '{table_name}': expected {EMBEDDING_DIM} dimensions, found {dim}"
+                ))
+                .into());
+            }
+            return Ok(false);
+        }
+
+        let table = &self.table;
+        let statements = [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {table} (\
+                 id TEXT PRIMARY KEY, \
+                 content TEXT NOT NULL, \
+                 embedding vector({EMBEDDING_DIM}) NOT NULL, \
+                 content_tsv tsvector GENERATED ALWAYS AS (to_tsvector('english', content)) STORED)"
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS \"{}_embedding_idx\" ON {table} \
+                 USING hnsw (embedding vector_cosine_ops)",
+                table_name.replace('"', "")
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS \"{}_content_tsv_idx\" ON {table} USING gin (content_tsv)",
+                table_name.replace('"', "")
+            ),
+        ];
+        for statement in statements {
+            sqlx::query(&statement)
+                .execute(&self.pool)
+                .await
+                .map_err(query_error)?;
+        }
+
+        tracing::info!(table = %table_name, "created pgvector table");
+        Ok(true)
+    }
+}
+
+fn query_error(error: sqlx::Error) -> crate::Error {
+    DbError::Query(format!("pgvector query failed: {error}")).into()
+}
+
+/// pgvector's text input format: `[0.1,0.2,...]`.
+fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(f32::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+impl VectorStore for PgVectorStore {
+    fn name(&self) -> &'static str {
+        "pgvector"
+    }
+
+    async fn store(&self, memory_id: &str, content: &str, embedding: &[f32]) -> Result<()> {
+        if embedding.len() != EMBEDDING_DIM {
+            return Err(DbError::Query(format!(
+                "embedding dimension mismatch: expected {EMBEDDING_DIM}, got {}",
+                embedding.len()
+            ))
+            .into());
+        }
+        let sql = format!(
+            "INSERT INTO {} (id, content, embedding) VALUES ($1, $2, $3::vector) \
+             ON CONFLICT (id) DO UPDATE SET content = EXCLUDED.content, embedding = EXCLUDED.embedding",
+            self.table
+        );
+        sqlx::query(&sql)
+            .bind(memory_id)
+            .bind(content)
+            .bind(vector_literal(embedding))
+            .execute(&self.pool)
+            .timed_with("pgvector.store", || format!("id={memory_id}"))
+            .await
+            .map_err(query_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, memory_id: &str) -> Result<()> {
+        let sql = format!("DELETE FROM {} WHERE id = $1", self.table);
+        sqlx::query(&sql)
+            .bind(memory_id)
+            .execute(&self.pool)
+            .timed_with("pgvector.delete", || format!("id={memory_id}"))
+            .await
+            .map_err(query_error)?;
+        Ok(())
+    }
+
+    async fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        let sql = format!(
+            "SELECT id, (embedding <=> $1::vector)::real AS distance FROM {} \
+             ORDER BY embedding <=> $1::vector LIMIT $2",
+            self.table
+        );
+        sqlx::query_as(&sql)
+            .bind(vector_literal(query_embedding))
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .timed_with("pgvector.vector_search", || format!("limit={limit}"))
+            .await
+            .map_err(query_error)
+    }
+
+    async fn find_similar(
+        &self,
+        memory_id: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        // The self-join yields no rows when the source memory has no embedding.
+        let sql = format!(
+            "SELECT other.id, (1 - (other.embedding <=> source.embedding))::real AS similarity \
+             FROM {table} other, {table} source \
+             WHERE source.id = $1 AND other.id <> $1 \
+             AND 1 - (other.embedding <=> source.embedding) >= $2 \
+             ORDER BY other.embedding <=> source.embedding LIMIT $3",
+            table = self.table
+        );
+        sqlx::query_as(&sql)
+            .bind(memory_id)
+            .bind(threshold as f64)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .timed_with("pgvector.find_similar", || {
+                format!("id={memory_id} limit={limit}")
+            })
+            .await
+            .map_err(query_error)
+    }
+
+    async fn text_search(&self, query: &str, limit: usize) -> Result<Vec<(String, f32)>> {
+        let sql = format!(
+            "SELECT id, ts_rank(content_tsv, query)::real AS score \
+             FROM {}, plainto_tsquery('english', $1) query \
+             WHERE content_tsv @@ query ORDER BY score DESC LIMIT $2",
+            self.table
+        );
+        sqlx::query_as(&sql)
+            .bind(query)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .timed_with("pgvector.text_search", || format!("limit={limit}"))
+            .await
+            .map_err(query_error)
+    }
+}
@@ -0,0 +1,161 @@
+//! Background embedding pipeline: batches memory embeddings off the save path.
+
+use crate::config::EmbeddingConfig;
+use crate::error::Result;
+use crate::memory::EmbeddingModel;
+use crate::memory::vector::VectorStoreDyn;
+
+use tokio::sync::mpsc;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A memory waiting to be embedded and indexed.
+#[derive(Debug, Clone)]
+struct EmbeddingJob {
+    memory_id: String,
+    content: String,
+}
+
+/// Handle to a per-agent embedding worker.
+///
+/// `memory_save` enqueues here and returns as soon as the SQLite row is
+/// written. The worker collects jobs into batches (up to `batch_size`, or
+/// whatever arrived within `flush_interval_ms`), embeds each batch in one
+/// model call, and writes the vectors with retries.
+#[derive(Clone)]
+pub struct EmbeddingPipeline {
+    job_tx: mpsc::Sender<EmbeddingJob>,
+}
+
+impl std::fmt::Debug for EmbeddingPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddingPipeline")
+            .field(
+                "queued",
+                &(self.job_tx.max_capacity() - self.job_tx.capacity()),
+            )
+            .finish()
+    }
+}
+
+impl EmbeddingPipeline {
+    /// Spawn the worker task and return a handle to it. The worker exits once
+    /// every handle is dropped and the queue drains.
+    pub fn spawn(
+        embedding_model: Arc<EmbeddingModel>,
+        vector_store: Arc<dyn VectorStoreDyn>,
+        config: EmbeddingConfig,
+    ) -> Self {
+        let (job_tx, job_rx) = mpsc::channel(config.queue_capacity.max(1));
+        tokio::spawn(run_worker(job_rx, embedding_model, vector_store, config));
+        Self { job_tx }
+    }
+
+    /// Queue a memory for embedding. Waits only if the queue is full.
+    pub async fn enqueue(&self, memory_id: &str, content: &str) -> Result<()> {
+        self.job_tx
+            .send(EmbeddingJob {
+                memory_id: memory_id.to_string(),
+                content: content.to_string(),
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("embedding pipeline has shut down").into())
+    }
+}
+
+async fn run_worker(
+    mut job_rx: mpsc::Receiver<EmbeddingJob>,
+    embedding_model: Arc<EmbeddingModel>,
+    vector_store: Arc<dyn VectorStoreDyn>,
+    config: EmbeddingConfig,
+) {
+    let batch_size = config.batch_size.max(1);
+    let flush_interval = Duration::from_millis(config.flush_interval_ms);
+
+    while let Some(first) = job_rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + flush_interval;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, job_rx.recv()).await {
+                Ok(Some(job)) => batch.push(job),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        process_batch(&batch, &embedding_model, vector_store.as_ref(), &config).await;
+    }
+
+    tracing::debug!("embedding pipeline stopped");
+}
+
+async fn process_batch(
+    batch: &[EmbeddingJob],
+    embedding_model: &Arc<EmbeddingModel>,
+    vector_store: &dyn VectorStoreDyn,
+    config: &EmbeddingConfig,
+) {
+    let texts: Vec<String> = batch.iter().map(|job| job.content.clone()).collect();
+
+    let mut attempt = 0;
+    let embeddings = loop {
+        match embedding_model.embed_batch(texts.clone()).await {
+            Ok(embeddings) => break embeddings,
+            Err(error) if attempt < config.max_retries => {
+                attempt += 1;
+                tracing::warn!(%error, attempt, size = batch.len(), "embedding batch failed, retrying");
+                tokio::time::sleep(retry_delay(attempt)).await;
+            }
+            Err(error) => {
+                tracing::error!(%error, size = batch.len(), "embedding batch failed, dropping");
+                return;
+            }
+        }
+    };
+
+    for (job, embedding) in batch.iter().zip(embeddings) {
+        let mut attempt = 0;
+        loop {
+            match vector_store
+                .store(&job.memory_id, &job.content, &embedding)
+                .await
+            {
+                Ok(()) => break,
+                Err(error) if attempt < config.max_retries => {
+                    attempt += 1;
+                    tracing::warn!(%error, memory_id = %job.memory_id, attempt, "failed to store embedding, retrying");
+                    tokio::time::sleep(retry_delay(attempt)).await;
+                }
+                Err(error) => {
+                    tracing::error!(%error, memory_id = %job.memory_id, "failed to store embedding");
+                    break;
+                }
+            }
+        }
+    }
+
+    // Safe to call repeatedly — no-ops if the index already exists.
+    if let Err(error) = vector_store.ensure_fts_index().await {
+        tracing::warn!(%error, "failed to ensure FTS index after embedding batch");
+    }
+
+    tracing::debug!(size = batch.len(), "embedding batch indexed");
+}
+
+/// Exponential backoff starting at 250ms, capped at 8s.
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_millis(250u64.saturating_mul(1 << attempt.min(5)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_and_caps() {
+        assert_eq!(retry_delay(1), Duration::from_millis(500));
+        assert_eq!(retry_delay(2), Duration::from_millis(1000));
+        assert_eq!(retry_delay(5), Duration::from_millis(8000));
+        assert_eq!(retry_delay(12), Duration::from_millis(8000));
+    }
+}
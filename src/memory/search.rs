@@ -1,8 +1,10 @@
 //! Memory search: hybrid (vector + FTS + RRF + graph), temporal, importance, and typed queries.
 
 use crate::error::Result;
+use crate::memory::pipeline::EmbeddingPipeline;
 use crate::memory::types::{Memory, MemorySearchResult, MemoryType, RelationType};
-use crate::memory::{EmbeddingModel, EmbeddingTable, MemoryStore};
+use crate::memory::vector::VectorStoreDyn;
+use crate::memory::{EmbeddingModel, MemoryStore};
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -36,16 +38,19 @@ pub enum SearchSort {
 /// Bundles all memory search dependencies.
 pub struct MemorySearch {
     store: Arc<MemoryStore>,
-    embedding_table: EmbeddingTable,
+    vector_store: Arc<dyn VectorStoreDyn>,
     embedding_model: Arc<EmbeddingModel>,
+    /// Background batching worker. When unset, memories are embedded inline.
+    embedding_pipeline: Option<EmbeddingPipeline>,
 }
 
 impl Clone for MemorySearch {
     fn clone(&self) -> Self {
         Self {
             store: Arc::clone(&self.store),
-            embedding_table: self.embedding_table.clone(),
+            vector_store: Arc::clone(&self.vector_store),
             embedding_model: Arc::clone(&self.embedding_model),
+            embedding_pipeline: self.embedding_pipeline.clone(),
         }
     }
 }
@@ -54,6 +59,8 @@ impl std::fmt::Debug for MemorySearch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MemorySearch")
             .field("store", &self.store)
+            .field("vector_store", &self.vector_store.name())
+            .field("embedding_pipeline", &self.embedding_pipeline)
             .finish_non_exhaustive()
     }
 }
@@ -62,24 +69,54 @@ impl MemorySearch {
     /// Create a new MemorySearch instance.
     pub fn new(
         store: Arc<MemoryStore>,
-        embedding_table: EmbeddingTable,
+        vector_store: Arc<dyn VectorStoreDyn>,
         embedding_model: Arc<EmbeddingModel>,
     ) -> Self {
         Self {
             store,
-            embedding_table,
+            vector_store,
             embedding_model,
+            embedding_pipeline: None,
         }
     }
 
+    /// Route new memory embeddings through a background batching pipeline.
+    pub fn with_embedding_pipeline(mut self, pipeline: EmbeddingPipeline) -> Self {
+        self.embedding_pipeline = Some(pipeline);
+        self
+    }
+
+    /// Index a freshly saved memory in the vector store.
+    ///
+    /// With a pipeline attached this only queues the memory and returns; the
+    /// vector becomes searchable once its batch is flushed. Without one the
+    /// embedding is generated and written before returning.
+    pub async fn index_memory(&self, memory_id: &str, content: &str) -> Result<()> {
+        if let Some(pipeline) = &self.embedding_pipeline {
+            return pipeline.enqueue(memory_id, content).await;
+        }
+
+        let embedding = self.embedding_model.embed_one(content).await?;
+        self.vector_store
+            .store(memory_id, content, &embedding)
+            .await?;
+
+        // Safe to call repeatedly — no-ops if the index already exists.
+        if let Err(error) = self.vector_store.ensure_fts_index().await {
+            tracing::warn!(%error, "failed to ensure FTS index after memory save");
+        }
+
+        Ok(())
+    }
+
     /// Get a reference to the memory store.
     pub fn store(&self) -> &MemoryStore {
         &self.store
     }
 
-    /// Get a reference to the embedding table.
-    pub fn embedding_table(&self) -> &EmbeddingTable {
-        &self.embedding_table
+    /// Get a reference to the vector store backend.
+    pub fn vector_store(&self) -> &dyn VectorStoreDyn {
+        self.vector_store.as_ref()
     }
 
     /// Get a reference to the embedding model.
@@ -116,7 +153,12 @@ impl MemorySearch {
     ) -> Result<Vec<MemorySearchResult>> {
         let memories = self
             .store
-            .get_sorted(sort, config.max_results as i64, config.memory_type)
+            .get_sorted_visible(
+                sort,
+                config.max_results as i64,
+                config.memory_type,
+                config.channel_id.as_deref(),
+            )
             .await?;
 
         let total = memories.len();
@@ -153,11 +195,11 @@ impl MemorySearch {
         let mut fts_results = Vec::new();
         let mut graph_results = Vec::new();
 
-        // 1. Full-text search via LanceDB
+        // 1. Full-text search via the vector store's keyword index
         // FTS requires an inverted index. If the index doesn't exist yet (empty
         // table, first run) this will fail — fall back to vector + graph search.
         match self
-            .embedding_table
+            .vector_store
             .text_search(query, config.max_results_per_source)
             .await
         {
@@ -178,10 +220,10 @@ impl MemorySearch {
             }
         }
 
-        // 2. Vector similarity search via LanceDB
+        // 2. Vector similarity search
         let query_embedding = self.embedding_model.embed_one(query).await?;
         match self
-            .embedding_table
+            .vector_store
             .vector_search(&query_embedding, config.max_results_per_source)
             .await
         {
@@ -229,13 +271,15 @@ impl MemorySearch {
         let fused_results =
             reciprocal_rank_fusion(&vector_results, &fts_results, &graph_results, config.rrf_k);
 
-        // Convert to MemorySearchResult with ranks, applying optional type filter
+        // Convert to MemorySearchResult with ranks, applying optional type
+        // filter and leaving out other channels' private memories
         let results: Vec<MemorySearchResult> = fused_results
             .into_iter()
             .filter(|scored| {
                 config
                     .memory_type
                     .is_none_or(|t| scored.memory.memory_type == t)
+                    && scored.memory.visible_in(config.channel_id.as_ref())
             })
             .enumerate()
             .map(|(rank, scored)| MemorySearchResult {
@@ -340,6 +384,9 @@ pub struct SearchConfig {
     pub min_score: f32,
     /// Maximum graph traversal depth. Only used in hybrid mode.
     pub max_graph_depth: usize,
+    /// Channel the search is for. Private memories are only returned to the
+    /// channel they were saved in.
+    pub channel_id: Option<crate::ChannelId>,
 }
 
 impl Default for SearchConfig {
@@ -355,6 +402,7 @@ impl Default for SearchConfig {
             // score is ~0.016. Set threshold low enough to not discard everything.
             min_score: 0.0,
             max_graph_depth: 2,
+            channel_id: None,
         }
     }
 }
@@ -426,6 +474,7 @@ pub fn curate_results(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::memory::EmbeddingTable;
     use crate::memory::types::MemoryType;
     use chrono::{Duration, Utc};
 
@@ -565,7 +614,7 @@ mod tests {
             .unwrap();
         let embedding_table = EmbeddingTable::open_or_create(&lance_conn).await.unwrap();
         let embedding_model = Arc::new(EmbeddingModel::new(lance_dir.path()).unwrap());
-        let search = MemorySearch::new(store, embedding_table, embedding_model);
+        let search = MemorySearch::new(store, Arc::new(embedding_table), embedding_model);
 
         let config = SearchConfig {
             mode: SearchMode::Recent,
@@ -593,7 +642,7 @@ mod tests {
             .unwrap();
         let embedding_table = EmbeddingTable::open_or_create(&lance_conn).await.unwrap();
         let embedding_model = Arc::new(EmbeddingModel::new(lance_dir.path()).unwrap());
-        let search = MemorySearch::new(store, embedding_table, embedding_model);
+        let search = MemorySearch::new(store, Arc::new(embedding_table), embedding_model);
 
         let config = SearchConfig {
             mode: SearchMode::Important,
@@ -618,7 +667,7 @@ mod tests {
             .unwrap();
         let embedding_table = EmbeddingTable::open_or_create(&lance_conn).await.unwrap();
         let embedding_model = Arc::new(EmbeddingModel::new(lance_dir.path()).unwrap());
-        let search = MemorySearch::new(store, embedding_table, embedding_model);
+        let search = MemorySearch::new(store, Arc::new(embedding_table), embedding_model);
 
         let config = SearchConfig {
             mode: SearchMode::Typed,
@@ -643,7 +692,7 @@ mod tests {
             .unwrap();
         let embedding_table = EmbeddingTable::open_or_create(&lance_conn).await.unwrap();
         let embedding_model = Arc::new(EmbeddingModel::new(lance_dir.path()).unwrap());
-        let search = MemorySearch::new(store, embedding_table, embedding_model);
+        let search = MemorySearch::new(store, Arc::new(embedding_table), embedding_model);
 
         let config = SearchConfig {
             mode: SearchMode::Typed,
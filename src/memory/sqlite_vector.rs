@@ -0,0 +1,292 @@
+//! SQLite vector store backend, the default: embeddings live in the agent's
+//! own database, next to the memories they index.
+
+use crate::db::TimedQuery as _;
+use crate::error::{DbError, Result};
+use crate::memory::vector::{EMBEDDING_DIM, VectorStore};
+
+use sqlx::SqlitePool;
+
+/// Embeddings in the `memory_embeddings` table, with an FTS5 index over their
+/// content so hybrid search keeps its keyword leg.
+///
+/// Searches compare the query with every stored embedding, which is quick
+/// for an agent's memories into the tens of thousands. Deployments past that
+/// should move the index to Qdrant or pgvector.
+#[derive(Debug, Clone)]
+pub struct SqliteVectorStore {
+    pool: SqlitePool,
+    readers: SqlitePool,
+}
+
+impl SqliteVectorStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            readers: pool.clone(),
+            pool,
+        }
+    }
+
+    /// Run searches on a read-only pool.
+    pub fn with_readers(mut self, readers: SqlitePool) -> Self {
+        self.readers = readers;
+        self
+    }
+
+    /// Whether no embeddings are stored yet.
+    pub async fn is_empty(&self) -> Result<bool> {
+        let any: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM memory_embeddings)")
+            .fetch_one(&self.readers)
+            .timed("memory_embeddings.any")
+            .await
+            .map_err(query_error)?;
+        Ok(!any)
+    }
+
+    /// Every stored embedding's memory ID and cosine distance to `query`,
+    /// nearest first.
+    async fn ranked(&self, query: &[f32]) -> Result<Vec<(String, f32)>> {
+        let rows: Vec<(String, Vec<u8>)> =
+            sqlx::query_as("SELECT memory_id, embedding FROM memory_embeddings")
+                .fetch_all(&self.readers)
+                .timed("memory_embeddings.scan")
+                .await
+                .map_err(query_error)?;
+        let mut ranked: Vec<(String, f32)> = rows
+            .into_iter()
+            .filter_map(|(memory_id, blob)| {
+                let embedding = decode(&blob)?;
+                Some((memory_id, cosine_distance(query, &embedding)))
+            })
+            .collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        Ok(ranked)
+    }
+}
+
+fn query_error(error: sqlx::Error) -> crate::Error {
+    DbError::Query(format!("sqlite vector query failed: {error}")).into()
+}
+
+fn check_dim(embedding: &[f32]) -> Result<()> {
+    if embedding.len() != EMBEDDING_DIM {
+        return Err(DbError::Query(format!(
+            "embedding dimension mismatch: expected {EMBEDDING_DIM}, got {}",
+            embedding.len()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// None for a blob that isn't an embedding of the expected dimension.
+fn decode(blob: &[u8]) -> Option<Vec<f32>> {
+    if blob.len() != EMBEDDING_DIM * 4 {
+        return None;
+    }
+    Some(
+        blob.chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect(),
+    )
+}
+
+/// 1 - cosine similarity. A zero vector is as far as an orthogonal one.
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 1.0;
+    }
+    1.0 - dot / (norm_a * norm_b)
+}
+
+/// An FTS5 query matching any word of `text`. Each word is quoted, so
+/// punctuation and FTS5 operators in the text are taken literally.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{term}\""))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+impl VectorStore for SqliteVectorStore {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn store(&self, memory_id: &str, content: &str, embedding: &[f32]) -> Result<()> {
+        check_dim(embedding)?;
+        sqlx::query(
+            "INSERT INTO memory_embeddings (memory_id, content, embedding) VALUES (?, ?, ?) \
+             ON CONFLICT (memory_id) DO UPDATE SET \
+             content = excluded.content, embedding = excluded.embedding",
+        )
+        .bind(memory_id)
+        .bind(content)
+        .bind(encode(embedding))
+        .execute(&self.pool)
+        .timed_with("memory_embeddings.store", || format!("id={memory_id}"))
+        .await
+        .map_err(query_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, memory_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM memory_embeddings WHERE memory_id = ?")
+            .bind(memory_id)
+            .execute(&self.pool)
+            .timed_with("memory_embeddings.delete", || format!("id={memory_id}"))
+            .await
+            .map_err(query_error)?;
+        Ok(())
+    }
+
+    async fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        check_dim(query_embedding)?;
+        let mut ranked = self.ranked(query_embedding).await?;
+        ranked.truncate(limit);
+        Ok(ranked)
+    }
+
+    async fn find_similar(
+        &self,
+        memory_id: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        let blob: Option<Vec<u8>> =
+            sqlx::query_scalar("SELECT embedding FROM memory_embeddings WHERE memory_id = ?")
+                .bind(memory_id)
+                .fetch_optional(&self.readers)
+                .timed_with("memory_embeddings.load", || format!("id={memory_id}"))
+                .await
+                .map_err(query_error)?;
+        let Some(embedding) = blob.as_deref().and_then(decode) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self
+            .ranked(&embedding)
+            .await?
+            .into_iter()
+            .filter(|(id, _)| id != memory_id)
+            .map(|(id, distance)| (id, 1.0 - distance))
+            .take_while(|(_, similarity)| *similarity >= threshold)
+            .take(limit)
+            .collect())
+    }
+
+    async fn text_search(&self, query: &str, limit: usize) -> Result<Vec<(String, f32)>> {
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        // bm25() is lower for better matches.
+        let rows: Vec<(String, f64)> = sqlx::query_as(
+            "SELECT memory_embeddings.memory_id, -bm25(memory_embeddings_fts) AS score \
+             FROM memory_embeddings_fts \
+             JOIN memory_embeddings ON memory_embeddings.rowid = memory_embeddings_fts.rowid \
+             WHERE memory_embeddings_fts MATCH ? ORDER BY score DESC LIMIT ?",
+        )
+        .bind(&query)
+        .bind(limit as i64)
+        .fetch_all(&self.readers)
+        .timed_with("memory_embeddings.text_search", || format!("limit={limit}"))
+        .await
+        .map_err(query_error)?;
+        Ok(rows
+            .into_iter()
+            .map(|(memory_id, score)| (memory_id, score as f32))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sqlx::pool::PoolOptions;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    /// An embedding pointing mostly along `axis`, leaning towards `lean`.
+    fn embedding(axis: usize, lean: usize, weight: f32) -> Vec<f32> {
+        let mut embedding = vec![0.0; EMBEDDING_DIM];
+        embedding[axis] = 1.0;
+        embedding[lean] += weight;
+        embedding
+    }
+
+    #[tokio::test]
+    async fn test_searches_rank_by_cosine_distance_and_keywords() {
+        let options = SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        let store = SqliteVectorStore::new(pool);
+        assert!(store.is_empty().await.unwrap());
+
+        store
+            .store("a", "deploys happen on fridays", &embedding(0, 0, 0.0))
+            .await
+            .unwrap();
+        store
+            .store("b", "the cat is called Miso", &embedding(0, 1, 0.5))
+            .await
+            .unwrap();
+        store
+            .store("c", "prefers tea over coffee", &embedding(1, 1, 0.0))
+            .await
+            .unwrap();
+        assert!(!store.is_empty().await.unwrap());
+        assert!(store.store("d", "short", &[1.0]).await.is_err());
+
+        let results = store.vector_search(&embedding(0, 0, 0.0), 2).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert!(results[0].1.abs() < 1e-6);
+
+        let similar = store.find_similar("a", 0.5, 10).await.unwrap();
+        let ids: Vec<&str> = similar.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["b"]);
+        assert!(
+            store
+                .find_similar("missing", 0.0, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let matches = store.text_search("cat?", 10).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, "b");
+
+        // Overwriting and deleting keep the keyword index in step.
+        store
+            .store("b", "the dog is called Miso", &embedding(0, 1, 0.5))
+            .await
+            .unwrap();
+        assert!(store.text_search("cat", 10).await.unwrap().is_empty());
+        store.delete("b").await.unwrap();
+        assert!(store.text_search("Miso", 10).await.unwrap().is_empty());
+        assert!(store.text_search("\"", 10).await.unwrap().is_empty());
+    }
+}
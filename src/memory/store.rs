@@ -1,8 +1,10 @@
 //! Memory graph storage (SQLite).
 
+use crate::db::TimedQuery as _;
 use crate::error::{MemoryError, Result};
 use crate::memory::search::SearchSort;
 use crate::memory::types::{Association, Memory, MemoryType, RelationType};
+use crate::otel::db_write_with;
 
 use anyhow::Context as _;
 use sqlx::{Row, SqlitePool};
@@ -35,11 +37,11 @@ impl MemoryStore {
 
     /// Save a new memory to the store.
     pub async fn save(&self, memory: &Memory) -> Result<()> {
-        sqlx::query(
+        let query = sqlx::query(
             r#"
             INSERT INTO memories (id, content, memory_type, importance, created_at, updated_at, 
-                                 last_accessed_at, access_count, source, channel_id, forgotten)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                                 last_accessed_at, access_count, source, channel_id, forgotten, private)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&memory.id)
@@ -53,7 +55,13 @@ impl MemoryStore {
         .bind(&memory.source)
         .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
         .bind(memory.forgotten)
-        .execute(&self.pool)
+        .bind(memory.private);
+        db_write_with(
+            "memories",
+            "insert",
+            || format!("id={}", memory.id),
+            query.execute(&self.pool),
+        )
         .await
         .with_context(|| format!("failed to save memory {}", memory.id))?;
 
@@ -65,13 +73,14 @@ impl MemoryStore {
         let row = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, private
             FROM memories
             WHERE id = ?
             "#,
         )
         .bind(id)
         .fetch_optional(&self.pool)
+        .timed_with("memories.load", || format!("id={id}"))
         .await
         .with_context(|| format!("failed to load memory {}", id))?;
 
@@ -80,12 +89,12 @@ impl MemoryStore {
 
     /// Update an existing memory.
     pub async fn update(&self, memory: &Memory) -> Result<()> {
-        sqlx::query(
+        let query = sqlx::query(
             r#"
             UPDATE memories 
             SET content = ?, memory_type = ?, importance = ?, updated_at = ?, 
                 last_accessed_at = ?, access_count = ?, source = ?, channel_id = ?,
-                forgotten = ?
+                forgotten = ?, private = ?
             WHERE id = ?
             "#,
         )
@@ -98,8 +107,14 @@ impl MemoryStore {
         .bind(&memory.source)
         .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
         .bind(memory.forgotten)
-        .bind(&memory.id)
-        .execute(&self.pool)
+        .bind(memory.private)
+        .bind(&memory.id);
+        db_write_with(
+            "memories",
+            "update",
+            || format!("id={}", memory.id),
+            query.execute(&self.pool),
+        )
         .await
         .with_context(|| format!("failed to update memory {}", memory.id))?;
 
@@ -108,11 +123,15 @@ impl MemoryStore {
 
     /// Delete a memory by ID.
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM memories WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .with_context(|| format!("failed to delete memory {}", id))?;
+        let query = sqlx::query("DELETE FROM memories WHERE id = ?").bind(id);
+        db_write_with(
+            "memories",
+            "delete",
+            || format!("id={id}"),
+            query.execute(&self.pool),
+        )
+        .await
+        .with_context(|| format!("failed to delete memory {}", id))?;
 
         Ok(())
     }
@@ -121,7 +140,7 @@ impl MemoryStore {
     pub async fn record_access(&self, id: &str) -> Result<()> {
         let now = chrono::Utc::now();
 
-        sqlx::query(
+        let query = sqlx::query(
             r#"
             UPDATE memories 
             SET last_accessed_at = ?, access_count = access_count + 1
@@ -129,8 +148,13 @@ impl MemoryStore {
             "#,
         )
         .bind(now)
-        .bind(id)
-        .execute(&self.pool)
+        .bind(id);
+        db_write_with(
+            "memories",
+            "update",
+            || format!("id={id}"),
+            query.execute(&self.pool),
+        )
         .await
         .with_context(|| format!("failed to record access for memory {}", id))?;
 
@@ -140,12 +164,17 @@ impl MemoryStore {
     /// Mark a memory as forgotten. The memory stays in the database but is
     /// excluded from search results and recall.
     pub async fn forget(&self, id: &str) -> Result<bool> {
-        let result = sqlx::query(
+        let query = sqlx::query(
             "UPDATE memories SET forgotten = 1, updated_at = ? WHERE id = ? AND forgotten = 0",
         )
         .bind(chrono::Utc::now())
-        .bind(id)
-        .execute(&self.pool)
+        .bind(id);
+        let result = db_write_with(
+            "memories",
+            "update",
+            || format!("id={id}"),
+            query.execute(&self.pool),
+        )
         .await
         .with_context(|| format!("failed to forget memory {}", id))?;
 
@@ -154,7 +183,7 @@ impl MemoryStore {
 
     /// Create an association between two memories.
     pub async fn create_association(&self, association: &Association) -> Result<()> {
-        sqlx::query(
+        let query = sqlx::query(
             r#"
             INSERT INTO associations (id, source_id, target_id, relation_type, weight, created_at)
             VALUES (?, ?, ?, ?, ?, ?)
@@ -167,8 +196,18 @@ impl MemoryStore {
         .bind(&association.target_id)
         .bind(association.relation_type.to_string())
         .bind(association.weight)
-        .bind(association.created_at)
-        .execute(&self.pool)
+        .bind(association.created_at);
+        db_write_with(
+            "associations",
+            "upsert",
+            || {
+                format!(
+                    "source_id={} target_id={}",
+                    association.source_id, association.target_id
+                )
+            },
+            query.execute(&self.pool),
+        )
         .await
         .with_context(|| {
             format!(
@@ -192,6 +231,7 @@ impl MemoryStore {
         .bind(memory_id)
         .bind(memory_id)
         .fetch_all(&self.pool)
+        .timed_with("associations.get", || format!("memory_id={memory_id}"))
         .await
         .with_context(|| format!("failed to get associations for memory {}", memory_id))?;
 
@@ -233,6 +273,9 @@ impl MemoryStore {
 
         let rows = query
             .fetch_all(&self.pool)
+            .timed_with("associations.between", || {
+                format!("memory_ids={}", memory_ids.len())
+            })
             .await
             .context("failed to get associations between memory set")?;
 
@@ -310,7 +353,7 @@ impl MemoryStore {
         let rows = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, private
             FROM memories
             WHERE memory_type = ? AND forgotten = 0
             ORDER BY importance DESC, updated_at DESC
@@ -320,20 +363,24 @@ impl MemoryStore {
         .bind(&type_str)
         .bind(limit)
         .fetch_all(&self.pool)
+        .timed_with("memories.by_type", || {
+            format!("memory_type={type_str} limit={limit}")
+        })
         .await
         .with_context(|| format!("failed to get memories by type {:?}", memory_type))?;
 
         Ok(rows.into_iter().map(|row| row_to_memory(&row)).collect())
     }
 
-    /// Get high-importance memories for injection into context.
+    /// Get high-importance memories for injection into context. Private
+    /// memories are left out.
     pub async fn get_high_importance(&self, threshold: f32, limit: i64) -> Result<Vec<Memory>> {
         let rows = sqlx::query(
             r#"
             SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
+                   last_accessed_at, access_count, source, channel_id, forgotten, private
             FROM memories
-            WHERE importance >= ? AND forgotten = 0
+            WHERE importance >= ? AND forgotten = 0 AND private = 0
             ORDER BY importance DESC, updated_at DESC
             LIMIT ?
             "#,
@@ -341,6 +388,9 @@ impl MemoryStore {
         .bind(threshold)
         .bind(limit)
         .fetch_all(&self.pool)
+        .timed_with("memories.high_importance", || {
+            format!("threshold={threshold} limit={limit}")
+        })
         .await
         .with_context(|| "failed to get high importance memories")?;
 
@@ -356,46 +406,66 @@ impl MemoryStore {
         sort: SearchSort,
         limit: i64,
         memory_type: Option<MemoryType>,
+    ) -> Result<Vec<Memory>> {
+        self.query_sorted(sort, limit, memory_type, None).await
+    }
+
+    /// `get_sorted`, leaving out private memories of channels other than
+    /// `channel_id`.
+    pub async fn get_sorted_visible(
+        &self,
+        sort: SearchSort,
+        limit: i64,
+        memory_type: Option<MemoryType>,
+        channel_id: Option<&str>,
+    ) -> Result<Vec<Memory>> {
+        self.query_sorted(sort, limit, memory_type, Some(channel_id))
+            .await
+    }
+
+    async fn query_sorted(
+        &self,
+        sort: SearchSort,
+        limit: i64,
+        memory_type: Option<MemoryType>,
+        visible_in: Option<Option<&str>>,
     ) -> Result<Vec<Memory>> {
         let order_clause = match sort {
             SearchSort::Recent => "ORDER BY created_at DESC",
             SearchSort::Importance => "ORDER BY importance DESC, created_at DESC",
             SearchSort::MostAccessed => "ORDER BY access_count DESC, created_at DESC",
         };
-
-        let (query_str, type_filter) = if let Some(ref memory_type) = memory_type {
-            (
-                format!(
-                    "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
-                     FROM memories WHERE memory_type = ? AND forgotten = 0 {order_clause} LIMIT ?"
-                ),
-                Some(memory_type.to_string()),
-            )
+        let type_clause = if memory_type.is_some() {
+            "AND memory_type = ?"
         } else {
-            (
-                format!(
-                    "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
-                     FROM memories WHERE forgotten = 0 {order_clause} LIMIT ?"
-                ),
-                None,
-            )
+            ""
         };
-
-        let rows = if let Some(type_str) = type_filter {
-            sqlx::query(&query_str)
-                .bind(type_str)
-                .bind(limit)
-                .fetch_all(&self.pool)
-                .await
+        let private_clause = if visible_in.is_some() {
+            "AND (private = 0 OR channel_id = ?)"
         } else {
-            sqlx::query(&query_str)
-                .bind(limit)
-                .fetch_all(&self.pool)
-                .await
+            ""
+        };
+
+        let query_str = format!(
+            "SELECT id, content, memory_type, importance, created_at, updated_at, \
+             last_accessed_at, access_count, source, channel_id, forgotten, private \
+             FROM memories WHERE forgotten = 0 {type_clause} {private_clause} \
+             {order_clause} LIMIT ?"
+        );
+
+        let mut query = sqlx::query(&query_str);
+        if let Some(memory_type) = memory_type {
+            query = query.bind(memory_type.to_string());
+        }
+        if let Some(channel_id) = visible_in {
+            query = query.bind(channel_id);
         }
-        .with_context(|| format!("failed to get sorted memories ({sort:?})"))?;
+        let rows = query
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .timed_with("memories.sorted", || format!("sort={sort:?} limit={limit}"))
+            .await
+            .with_context(|| format!("failed to get sorted memories ({sort:?})"))?;
 
         Ok(rows.into_iter().map(|row| row_to_memory(&row)).collect())
     }
@@ -449,6 +519,7 @@ fn row_to_memory(row: &sqlx::sqlite::SqliteRow) -> Memory {
         source: row.try_get("source").ok(),
         channel_id: channel_id.map(|id| Arc::from(id) as crate::ChannelId),
         forgotten: row.try_get::<bool, _>("forgotten").unwrap_or(false),
+        private: row.try_get::<bool, _>("private").unwrap_or(false),
     }
 }
 
@@ -656,4 +727,35 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].id, visible.id);
     }
+
+    #[tokio::test]
+    async fn test_private_memories_only_visible_in_their_channel() {
+        let store = MemoryStore::connect_in_memory().await;
+        let shared = Memory::new("shared", MemoryType::Fact);
+        let dm: crate::ChannelId = Arc::from("discord:dm:42");
+        let private = Memory::new("private", MemoryType::Fact).private_to(dm.clone());
+        store.save(&shared).await.unwrap();
+        store.save(&private).await.unwrap();
+        assert!(store.load(&private.id).await.unwrap().unwrap().private);
+
+        let visible = |channel_id: Option<&'static str>| {
+            let store = store.clone();
+            async move {
+                let mut contents: Vec<String> = store
+                    .get_sorted_visible(SearchSort::Recent, 10, None, channel_id)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|memory| memory.content)
+                    .collect();
+                contents.sort();
+                contents
+            }
+        };
+        assert_eq!(visible(Some("discord:dm:42")).await, ["private", "shared"]);
+        assert_eq!(visible(Some("discord:dm:7")).await, ["shared"]);
+        assert_eq!(visible(None).await, ["shared"]);
+        assert!(!private.visible_in(None));
+        assert!(private.visible_in(Some(&dm)));
+    }
 }
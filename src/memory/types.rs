@@ -19,6 +19,10 @@ pub struct Memory {
     /// Soft-delete flag. Forgotten memories are excluded from search and recall
     /// but remain in the database.
     pub forgotten: bool,
+    /// Only recalled in the channel it was saved in, `channel_id`. Set for
+    /// memories saved in DMs with private memory on.
+    #[serde(default)]
+    pub private: bool,
 }
 
 impl Memory {
@@ -40,6 +44,7 @@ impl Memory {
             source: None,
             channel_id: None,
             forgotten: false,
+            private: false,
         }
     }
 
@@ -61,6 +66,18 @@ impl Memory {
         self
     }
 
+    /// Keep the memory private to a channel.
+    pub fn private_to(mut self, channel_id: crate::ChannelId) -> Self {
+        self.channel_id = Some(channel_id);
+        self.private = true;
+        self
+    }
+
+    /// Whether a search from `channel_id` may return this memory.
+    pub fn visible_in(&self, channel_id: Option<&crate::ChannelId>) -> bool {
+        !self.private || (self.channel_id.is_some() && self.channel_id.as_ref() == channel_id)
+    }
+
     /// Identity memories have maximum importance and don't decay.
     pub const fn identity_importance() -> f32 {
         1.0
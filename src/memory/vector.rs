@@ -0,0 +1,614 @@
+//! Vector store abstraction over embedding backends (SQLite, LanceDB, Qdrant,
+//! pgvector).
+
+use crate::db::Db;
+use crate::error::{DbError, Result};
+use crate::memory::EmbeddingTable;
+use crate::memory::sqlite_vector::SqliteVectorStore;
+
+use serde::Deserialize;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Embedding dimension produced by the default fastembed model (bge-small-en-v1.5).
+pub const EMBEDDING_DIM: usize = 384;
+
+/// Boxed future returned by [`VectorStoreDyn`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Storage and similarity search for memory embeddings.
+///
+/// A table in the agent's SQLite database is the default. Remote backends let
+/// large deployments move the index off the agent host without touching the
+/// code that saves and recalls memories.
+pub trait VectorStore: Send + Sync + 'static {
+    /// Short backend name for logs and diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// Store (or overwrite) the embedding for a memory.
+    fn store(
+        &self,
+        memory_id: &str,
+        content: &str,
+        embedding: &[f32],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Delete the embedding for a memory.
+    fn delete(&self, memory_id: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Nearest neighbours of a query embedding.
+    /// Returns (memory_id, distance) pairs where similarity = 1.0 - distance.
+    fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(String, f32)>>> + Send;
+
+    /// Memories similar to an already-indexed memory, excluding itself.
+    /// Returns (memory_id, similarity) pairs at or above `threshold`, or an
+    /// empty list when the memory has no embedding.
+    fn find_similar(
+        &self,
+        memory_id: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(String, f32)>>> + Send;
+
+    /// Full-text search over stored content. Backends without a keyword
+    /// index return an error, and hybrid search falls back to vector + graph.
+    fn text_search(
+        &self,
+        _query: &str,
+        _limit: usize,
+    ) -> impl Future<Output = Result<Vec<(String, f32)>>> + Send {
+        let name = self.name();
+        async move {
+            Err(DbError::Query(format!("{name} backend does not support full-text search")).into())
+        }
+    }
+
+    /// Make sure any keyword index exists. No-op for backends without one.
+    fn ensure_fts_index(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+}
+
+/// Dynamic companion of [`VectorStore`] so the backend can be chosen from config.
+pub trait VectorStoreDyn: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    fn store<'a>(
+        &'a self,
+        memory_id: &'a str,
+        content: &'a str,
+        embedding: &'a [f32],
+    ) -> BoxFuture<'a, ()>;
+
+    fn delete<'a>(&'a self, memory_id: &'a str) -> BoxFuture<'a, ()>;
+
+    fn vector_search<'a>(
+        &'a self,
+        query_embedding: &'a [f32],
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<(String, f32)>>;
+
+    fn find_similar<'a>(
+        &'a self,
+        memory_id: &'a str,
+        threshold: f32,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<(String, f32)>>;
+
+    fn text_search<'a>(&'a self, query: &'a str, limit: usize)
+    -> BoxFuture<'a, Vec<(String, f32)>>;
+
+    fn ensure_fts_index<'a>(&'a self) -> BoxFuture<'a, ()>;
+}
+
+impl<T: VectorStore> VectorStoreDyn for T {
+    fn name(&self) -> &'static str {
+        VectorStore::name(self)
+    }
+
+    fn store<'a>(
+        &'a self,
+        memory_id: &'a str,
+        content: &'a str,
+        embedding: &'a [f32],
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(VectorStore::store(self, memory_id, content, embedding))
+    }
+
+    fn delete<'a>(&'a self, memory_id: &'a str) -> BoxFuture<'a, ()> {
+        Box::pin(VectorStore::delete(self, memory_id))
+    }
+
+    fn vector_search<'a>(
+        &'a self,
+        query_embedding: &'a [f32],
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<(String, f32)>> {
+        Box::pin(VectorStore::vector_search(self, query_embedding, limit))
+    }
+
+    fn find_similar<'a>(
+        &'a self,
+        memory_id: &'a str,
+        threshold: f32,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<(String, f32)>> {
+        Box::pin(VectorStore::find_similar(self, memory_id, threshold, limit))
+    }
+
+    fn text_search<'a>(
+        &'a self,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Vec<(String, f32)>> {
+        Box::pin(VectorStore::text_search(self, query, limit))
+    }
+
+    fn ensure_fts_index<'a>(&'a self) -> BoxFuture<'a, ()> {
+        Box::pin(VectorStore::ensure_fts_index(self))
+    }
+}
+
+impl VectorStore for EmbeddingTable {
+    fn name(&self) -> &'static str {
+        "lancedb"
+    }
+
+    async fn store(&self, memory_id: &str, content: &str, embedding: &[f32]) -> Result<()> {
+        EmbeddingTable::store(self, memory_id, content, embedding).await
+    }
+
+    async fn delete(&self, memory_id: &str) -> Result<()> {
+        EmbeddingTable::delete(self, memory_id).await
+    }
+
+    async fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        EmbeddingTable::vector_search(self, query_embedding, limit).await
+    }
+
+    async fn find_similar(
+        &self,
+        memory_id: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        EmbeddingTable::find_similar(self, memory_id, threshold, limit).await
+    }
+
+    async fn text_search(&self, query: &str, limit: usize) -> Result<Vec<(String, f32)>> {
+        EmbeddingTable::text_search(self, query, limit).await
+    }
+
+    async fn ensure_fts_index(&self) -> Result<()> {
+        EmbeddingTable::ensure_fts_index(self).await
+    }
+}
+
+/// Which vector backend an agent's memories are indexed in.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum VectorBackendConfig {
+    /// `memory_embeddings` table in the agent's SQLite database.
+    #[default]
+    Sqlite,
+    /// Embedded LanceDB table in the agent's data directory.
+    Lance,
+    /// Remote Qdrant instance over its REST API.
+    Qdrant {
+        url: String,
+        api_key: Option<String>,
+        /// Collection name prefix. The agent ID is appended so agents never
+        /// share a collection.
+        collection_prefix: String,
+    },
+    /// Postgres with the pgvector extension. Requires the `pgvector` cargo feature.
+    PgVector {
+        url: String,
+        /// Table name prefix. The agent ID is appended so agents never share a table.
+        table_prefix: String,
+    },
+}
+
+impl VectorBackendConfig {
+    /// Backend name as written in config, for logs and error context.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sqlite => "sqlite",
+            Self::Lance => "lancedb",
+            Self::Qdrant { .. } => "qdrant",
+            Self::PgVector { .. } => "pgvector",
+        }
+    }
+}
+
+/// Open the configured vector store for an agent.
+///
+/// When the SQLite table is empty, or a remote collection or table is created
+/// for the first time, it is seeded with the embeddings already in the
+/// agent's LanceDB table, so switching backends doesn't lose recall over past
+/// memories.
+pub async fn open_vector_store(
+    backend: &VectorBackendConfig,
+    agent_id: &str,
+    db: &Db,
+    http_client: reqwest::Client,
+) -> Result<Arc<dyn VectorStoreDyn>> {
+    let lance = &db.lance;
+    match backend {
+        VectorBackendConfig::Sqlite => {
+            let store =
+                SqliteVectorStore::new(db.sqlite.clone()).with_readers(db.sqlite_read.clone());
+            if store.is_empty().await? {
+                backfill_from_lance(lance, &store).await?;
+            }
+            Ok(Arc::new(store))
+        }
+        VectorBackendConfig::Lance => {
+            let table = EmbeddingTable::open_or_create(lance).await?;
+            Ok(Arc::new(table))
+        }
+        VectorBackendConfig::Qdrant {
+            url,
+            api_key,
+            collection_prefix,
+        } => {
+            let store = QdrantStore::new(
+                http_client,
+                url.clone(),
+                api_key.clone(),
+                format!("{collection_prefix}_{agent_id}"),
+            );
+            if store.ensure_collection().await? {
+                backfill_from_lance(lance, &store).await?;
+            }
+            Ok(Arc::new(store))
+        }
+        #[cfg(feature = "pgvector")]
+        VectorBackendConfig::PgVector { url, table_prefix } => {
+            let (store, created) =
+                crate::memory::pgvector::PgVectorStore::connect(url, table_prefix, agent_id)
+                    .await?;
+            if created {
+                backfill_from_lance(lance, &store).await?;
+            }
+            Ok(Arc::new(store))
+        }
+        #[cfg(not(feature = "pgvector"))]
+        VectorBackendConfig::PgVector { .. } => Err(DbError::Query(
+            "can't open pgvector store: spacebot was built without the `pgvector` feature".into(),
+        )
+        .into()),
+    }
+}
+
+/// Copy every embedding from the agent's LanceDB table into an empty backend.
+/// Memories that were never embedded in LanceDB are not covered.
+async fn backfill_from_lance(lance: &lancedb::Connection, target: &impl VectorStore) -> Result<()> {
+    let Some(table) = EmbeddingTable::open_existing(lance).await? else {
+        return Ok(());
+    };
+
+    let rows = table.export_all().await?;
+    for (memory_id, content, embedding) in &rows {
+        target.store(memory_id, content, embedding).await?;
+    }
+
+    if !rows.is_empty() {
+        tracing::info!(
+            backend = target.name(),
+            count = rows.len(),
+            "backfilled embeddings from lancedb"
+        );
+    }
+    Ok(())
+}
+
+/// Qdrant-backed vector store using the REST API.
+///
+/// Memory IDs are UUIDs, which Qdrant accepts directly as point IDs. Content
+/// is kept in the point payload for debugging; Qdrant has no keyword index
+/// here, so hybrid search runs on vector + graph results only.
+#[derive(Debug, Clone)]
+pub struct QdrantStore {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    collection: String,
+}
+
+#[derive(Deserialize)]
+struct QdrantResponse<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct QdrantQueryResult {
+    points: Vec<QdrantScoredPoint>,
+}
+
+#[derive(Deserialize)]
+struct QdrantScoredPoint {
+    id: serde_json::Value,
+    score: f32,
+}
+
+#[derive(Deserialize)]
+struct QdrantCollectionInfo {
+    config: QdrantCollectionConfig,
+}
+
+#[derive(Deserialize)]
+struct QdrantCollectionConfig {
+    params: QdrantCollectionParams,
+}
+
+#[derive(Deserialize)]
+struct QdrantCollectionParams {
+    vectors: serde_json::Value,
+}
+
+impl QdrantStore {
+    pub fn new(
+        http_client: reqwest::Client,
+        base_url: impl Into<String>,
+        api_key: Option<String>,
+        collection: impl Into<String>,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+            collection: collection.into(),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/collections/{}{path}", self.base_url, self.collection);
+        let builder = self.http_client.request(method, url);
+        match &self.api_key {
+            Some(api_key) => builder.header("api-key", api_key),
+            None => builder,
+        }
+    }
+
+    async fn execute(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        builder
+            .send()
+            .await
+            .map_err(|error| DbError::Query(format!("qdrant request failed: {error}")).into())
+    }
+
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(DbError::Query(format!("qdrant returned {status}: {body}")).into());
+        }
+        Ok(response)
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        Self::check(self.execute(builder).await?).await
+    }
+
+    /// Create the collection if it doesn't exist yet, or verify that an
+    /// existing one matches the embedding dimension.
+    ///
+    /// Returns `true` when the collection was created by this call.
+    pub async fn ensure_collection(&self) -> Result<bool> {
+        let existing = self.execute(self.request(reqwest::Method::GET, "")).await?;
+        if existing.status() != reqwest::StatusCode::NOT_FOUND {
+            let parsed: QdrantResponse<QdrantCollectionInfo> = Self::check(existing)
+                .await?
+                .json()
+                .await
+                .map_err(|error| DbError::Query(format!("invalid qdrant response: {error}")))?;
+            let size = parsed
+                .result
+                .config
+                .params
+                .vectors
+                .get("size")
+                .and_then(|size| size.as_u64());
+            if size != Some(EMBEDDING_DIM as u64) {
+                return Err(DbError::Query(format!(
+                    "can't use qdrant collection '{}': expected a single {EMBEDDING_DIM}-dimension vector, found {}",
+                    self.collection, parsed.result.config.params.vectors
+                ))
+                .into());
+            }
+            return Ok(false);
+        }
+
+        let body = serde_json::json!({
+            "vectors": { "size": EMBEDDING_DIM, "distance": "Cosine" },
+        });
+        self.send(self.request(reqwest::Method::PUT, "").json(&body))
+            .await?;
+        tracing::info!(collection = %self.collection, "created qdrant collection");
+        Ok(true)
+    }
+
+    async fn collection_exists(&self) -> Result<bool> {
+        let response = self.execute(self.request(reqwest::Method::GET, "")).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        Self::check(response).await?;
+        Ok(true)
+    }
+
+    async fn query_points(&self, response: reqwest::Response) -> Result<Vec<(String, f32)>> {
+        let parsed: QdrantResponse<QdrantQueryResult> =
+            Self::check(response)
+                .await?
+                .json()
+                .await
+                .map_err(|error| DbError::Query(format!("invalid qdrant response: {error}")))?;
+        Ok(parse_scored_points(parsed.result.points))
+    }
+}
+
+/// Convert Qdrant point IDs (UUID strings or unsigned integers) to memory IDs.
+fn parse_scored_points(points: Vec<QdrantScoredPoint>) -> Vec<(String, f32)> {
+    points
+        .into_iter()
+        .filter_map(|point| {
+            let id = match point.id {
+                serde_json::Value::String(id) => id,
+                serde_json::Value::Number(id) => id.to_string(),
+                _ => return None,
+            };
+            Some((id, point.score))
+        })
+        .collect()
+}
+
+/// Qdrant reports cosine similarity; callers of `vector_search` expect a distance.
+fn score_to_distance(score: f32) -> f32 {
+    1.0 - score
+}
+
+impl VectorStore for QdrantStore {
+    fn name(&self) -> &'static str {
+        "qdrant"
+    }
+
+    async fn store(&self, memory_id: &str, content: &str, embedding: &[f32]) -> Result<()> {
+        if embedding.len() != EMBEDDING_DIM {
+            return Err(DbError::Query(format!(
+                "embedding dimension mismatch: expected {EMBEDDING_DIM}, got {}",
+                embedding.len()
+            ))
+            .into());
+        }
+        let body = serde_json::json!({
+            "points": [{
+                "id": memory_id,
+                "vector": embedding,
+                "payload": { "content": content },
+            }],
+        });
+        self.send(
+            self.request(reqwest::Method::PUT, "/points?wait=true")
+                .json(&body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, memory_id: &str) -> Result<()> {
+        let body = serde_json::json!({ "points": [memory_id] });
+        self.send(
+            self.request(reqwest::Method::POST, "/points/delete?wait=true")
+                .json(&body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn vector_search(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        let body = serde_json::json!({
+            "query": query_embedding,
+            "limit": limit,
+            "with_payload": false,
+        });
+        let response = self
+            .execute(
+                self.request(reqwest::Method::POST, "/points/query")
+                    .json(&body),
+            )
+            .await?;
+        let results = self.query_points(response).await?;
+        Ok(results
+            .into_iter()
+            .map(|(id, score)| (id, score_to_distance(score)))
+            .collect())
+    }
+
+    async fn find_similar(
+        &self,
+        memory_id: &str,
+        threshold: f32,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>> {
+        // Recommend queries exclude the example point from the results.
+        let body = serde_json::json!({
+            "query": { "recommend": { "positive": [memory_id] } },
+            "limit": limit,
+            "score_threshold": threshold,
+            "with_payload": false,
+        });
+        let response = self
+            .execute(
+                self.request(reqwest::Method::POST, "/points/query")
+                    .json(&body),
+            )
+            .await?;
+
+        // Qdrant answers 404 both for a point that isn't stored and for a
+        // missing collection. A memory without an embedding is not an error,
+        // matching LanceDB, but a missing collection is.
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            if !self.collection_exists().await? {
+                return Err(DbError::Query(format!(
+                    "qdrant collection '{}' doesn't exist",
+                    self.collection
+                ))
+                .into());
+            }
+            return Ok(Vec::new());
+        }
+        self.query_points(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_to_distance() {
+        assert_eq!(score_to_distance(1.0), 0.0);
+        assert_eq!(score_to_distance(0.25), 0.75);
+        assert_eq!(score_to_distance(0.0), 1.0);
+    }
+
+    #[test]
+    fn test_parse_scored_points_ids() {
+        let body = serde_json::json!({
+            "result": {
+                "points": [
+                    { "id": "5f0c6c1e-2d1b-4c55-9a57-3bd4c1b7a001", "score": 0.9 },
+                    { "id": 42, "score": 0.5 },
+                    { "id": null, "score": 0.1 },
+                ]
+            }
+        });
+        let parsed: QdrantResponse<QdrantQueryResult> =
+            serde_json::from_value(body).expect("valid qdrant response");
+        let points = parse_scored_points(parsed.result.points);
+
+        assert_eq!(
+            points,
+            vec![
+                ("5f0c6c1e-2d1b-4c55-9a57-3bd4c1b7a001".to_string(), 0.9),
+                ("42".to_string(), 0.5),
+            ]
+        );
+    }
+}
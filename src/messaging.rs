@@ -1,13 +1,28 @@
-//! Messaging adapters (Discord, Slack, Telegram, Twitch, Webhook, WebChat).
+//! Messaging adapters (Discord, Slack, Telegram, Matrix, IRC, XMPP, Email,
+//! WhatsApp, SMS, Twitch, Webhook, Stdio, WebChat).
 
+pub mod chunking;
 pub mod discord;
+pub mod email;
+pub mod guardrails;
+pub mod irc;
 pub mod manager;
+pub mod matrix;
+pub mod presence;
+pub mod rate_limit;
+pub mod render;
 pub mod slack;
+pub mod sms;
+pub mod stdio;
 pub mod telegram;
+pub mod tls;
 pub mod traits;
+pub mod twilio;
 pub mod twitch;
 pub mod webchat;
 pub mod webhook;
+pub mod whatsapp;
+pub mod xmpp;
 
 pub use manager::MessagingManager;
 pub use traits::Messaging;
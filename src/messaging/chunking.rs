@@ -0,0 +1,145 @@
+//! Splitting long replies into messages that fit a platform's length limit.
+//!
+//! Splits land on paragraph, line, or word boundaries. A code block cut in
+//! two is closed at the end of one message and reopened, with its language,
+//! at the start of the next, so both halves still render as code. Short code
+//! blocks are moved whole to the next message instead of being cut. When a
+//! reply takes more than one message, each is numbered.
+
+/// Room kept for the `(i/n)` continuation marker.
+const MARKER_RESERVE: usize = 12;
+
+/// Length of the fence added to close a code block that continues.
+const FENCE_CLOSE: &str = "\n```";
+
+/// Split a reply into messages of at most `max_chars` characters.
+///
+/// Replies that fit are returned as-is. Longer ones are split and each part
+/// gets a `-# (i/n)` line, which Discord renders as small text.
+pub fn chunk_message(text: &str, max_chars: usize) -> Vec<String> {
+    if text.chars().count() <= max_chars {
+        return vec![text.to_string()];
+    }
+
+    let budget = max_chars.saturating_sub(MARKER_RESERVE).max(1);
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+    let mut open_fence: Option<String> = None;
+
+    while !remaining.is_empty() {
+        let prefix = open_fence
+            .as_ref()
+            .map(|fence| format!("{fence}\n"))
+            .unwrap_or_default();
+        let room = budget
+            .saturating_sub(prefix.chars().count() + FENCE_CLOSE.len())
+            .max(1);
+
+        if remaining.chars().count() <= room {
+            chunks.push(format!("{prefix}{remaining}"));
+            break;
+        }
+
+        let window = &remaining[..byte_index(remaining, room)];
+        let split_at = break_point(window, open_fence.is_some());
+        let (head, tail) = remaining.split_at(split_at);
+
+        let mut chunk = format!("{prefix}{}", head.trim_end());
+        open_fence = unclosed_fence(&chunk).map(|(_, fence)| fence.to_string());
+        if open_fence.is_some() {
+            chunk.push_str(FENCE_CLOSE);
+        }
+        chunks.push(chunk);
+
+        // Keep indentation inside code blocks; drop it in prose.
+        remaining = if open_fence.is_some() {
+            tail.trim_start_matches('\n')
+        } else {
+            tail.trim_start()
+        };
+    }
+
+    let total = chunks.len();
+    if total > 1 {
+        for (index, chunk) in chunks.iter_mut().enumerate() {
+            chunk.push_str(&format!("\n-# ({}/{total})", index + 1));
+        }
+    }
+    chunks
+}
+
+/// Where to split a window that ends mid-reply, as a byte offset.
+fn break_point(window: &str, starts_in_fence: bool) -> usize {
+    // Move a code block that starts in this window but doesn't end in it to
+    // the next message whole, unless that would leave this one nearly empty.
+    if !starts_in_fence
+        && let Some((start, _)) = unclosed_fence(window)
+        && start > window.len() / 4
+    {
+        return start;
+    }
+
+    let half = window.len() / 2;
+    window
+        .rfind("\n\n")
+        .filter(|&index| index >= half)
+        .or_else(|| window.rfind('\n').filter(|&index| index > 0))
+        .or_else(|| window.rfind(' ').filter(|&index| index > 0))
+        .unwrap_or(window.len())
+}
+
+/// The code block left open at the end of the text, if any: the byte offset
+/// of its opening fence line, and that line (e.g. "```rust").
+fn unclosed_fence(text: &str) -> Option<(usize, &str)> {
+    let mut open = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        // A line like "```code```" opens and closes on its own.
+        if trimmed.starts_with("```") && !trimmed[3..].contains("```") {
+            open = match open {
+                Some(_) => None,
+                None => Some((offset, trimmed)),
+            };
+        }
+        offset += line.len();
+    }
+    open
+}
+
+/// Byte offset of the character at `chars`, or the end of the text.
+fn byte_index(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_blocks_reopen_across_numbered_chunks() {
+        assert_eq!(chunk_message("short reply", 2000), ["short reply"]);
+
+        let code = (0..40)
+            .map(|line| format!("    let value_{line} = {line};"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let intro = "The loop reads the values one at a time, so here's a version that declares them up front instead. ".repeat(2);
+        let text = format!("{intro}\n\n```rust\n{code}\n```\n\nThat should do it.");
+        let chunks = chunk_message(&text, 500);
+
+        assert_eq!(chunks.len(), 3);
+        for (index, chunk) in chunks.iter().enumerate() {
+            assert!(chunk.chars().count() <= 500, "chunk {index} too long");
+            assert!(chunk.ends_with(&format!("-# ({}/3)", index + 1)));
+            assert_eq!(chunk.matches("```").count() % 2, 0, "chunk {index} fence");
+        }
+        // The block moves whole to the second message, then reopens in the third.
+        assert!(!chunks[0].contains("```"));
+        assert!(chunks[1].starts_with("```rust\n    let value_0"));
+        assert!(chunks[2].starts_with("```rust\n    let value_"));
+        assert!(chunks[2].ends_with("```\n\nThat should do it.\n-# (3/3)"));
+    }
+}
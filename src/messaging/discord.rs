@@ -1,35 +1,136 @@
 //! Discord messaging adapter using serenity.
 
-use crate::config::DiscordPermissions;
+use crate::agent::catch_up::{self, CATCH_UP_KEY};
+use crate::agent::channel::{
+    CANCEL_TURN_KEY, CONFIG_REQUEST_KEY, MESSAGE_EDIT_KEY, MODERATOR_KEY, SETTINGS_REQUEST_KEY,
+    STATUS_REQUEST_KEY,
+};
+use crate::agent::controls::{CONTROL_TARGET_KEY, REACTION_CONTROL_KEY, ReactionControl};
+use crate::agent::forums::{FORUM_TAGS_KEY, FORUM_TRIAGE_KEY, ForumTriage};
+use crate::agent::listening;
+use crate::agent::sentiment::{self, REACTION_SENTIMENT_KEY, SentimentReaction};
+use crate::config::{
+    AGENT_AVATAR_URL_KEY, AGENT_DISPLAY_NAME_KEY, DiscordPermissions, EXPENSIVE_TOOLS_KEY,
+    ForumConfig, PRIVATE_MEMORY_KEY, RolePermission,
+};
+use crate::conversation::history::ConversationMessage;
+use crate::messaging::chunking::chunk_message;
+use crate::messaging::presence::Presence;
+use crate::messaging::rate_limit::{self, RateLimitDecision, UserRateLimiter};
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging};
-use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+use crate::{InboundMessage, MessageContent, ModerationAction, OutboundResponse, StatusUpdate};
 
 use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use serenity::all::{
-    ButtonStyle, ChannelId, ChannelType, ComponentInteraction, Context, CreateActionRow,
-    CreateAttachment, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    ActionRow, ActionRowComponent, ActivityData, ButtonKind, ButtonStyle, ChannelId, ChannelType,
+    Command, CommandInteraction, CommandOptionType, ComponentInteraction, Context, CreateActionRow,
+    CreateAttachment, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseFollowup,
     CreateInteractionResponseMessage, CreateMessage, CreatePoll, CreatePollAnswer,
-    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, EditMessage,
-    EventHandler, GatewayIntents, GetMessages, Http, Interaction, Message, MessageId, ReactionType,
-    Ready, ShardManager, User, UserId,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, CreateWebhook,
+    EditInteractionResponse, EditMember, EditMessage, EditThread, Emoji, EmojiId, EventHandler,
+    ExecuteWebhook, GatewayIntents, GetMessages, Guild, GuildChannel, GuildId, Http, Interaction,
+    Message, MessageId, MessageUpdateEvent, OnlineStatus, PartialChannel, Reaction, ReactionType,
+    Ready, ResolvedValue, ResumedEvent, RoleId, ShardManager, Timestamp, User, UserId, Webhook,
 };
-use std::collections::HashMap;
+use serenity::builder::Builder as _;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, mpsc};
 
+mod emoji;
+#[cfg(feature = "voice")]
+mod voice;
+
+/// Discord's message length limit, in characters.
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Reacting with this emoji stops the agent's in-flight turn in that channel.
+const CANCEL_REACTION: &str = "🛑";
+
+/// Interaction tokens stop working after 15 minutes, so replies to older
+/// slash commands go to the channel instead.
+const INTERACTION_TOKEN_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Wait before the first attempt to rebuild a gateway connection that
+/// stopped on an error. Doubles on each failed attempt, up to the max.
+const RECONNECT_MIN_BACKOFF: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// A connection that stayed up this long resets the backoff.
+const RECONNECT_STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// After a reconnect that couldn't resume the session or a restart, the most
+/// recently active channels to fetch missed messages for, and how many per
+/// channel.
+pub const BACKFILL_MAX_CHANNELS: usize = 50;
+const BACKFILL_MESSAGE_LIMIT: u8 = 100;
+
+/// Slash commands limited to members with the `admin_commands` role. Their
+/// responses, and `/settings`', are visible only to whoever ran them.
+const ADMIN_COMMANDS: &[&str] = &["status", "forget", "config"];
+
+/// Name of the webhook the bot creates in each channel for agent replies.
+const AGENT_WEBHOOK_NAME: &str = "Spacebot";
+
+/// Discord's limit on the name a webhook message is sent under.
+const WEBHOOK_USERNAME_LIMIT: usize = 80;
+
+/// How a message reached the adapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arrival {
+    /// Sent while connected.
+    Live,
+    /// An edit to an earlier message.
+    Edited,
+    /// Sent while disconnected or down, fetched after reconnecting or on
+    /// startup.
+    Backfilled,
+    /// The last of this many messages sent while down, for the agent to
+    /// summarize.
+    CaughtUp(usize),
+}
+
+/// A reply being streamed into a Discord message.
+struct ActiveStream {
+    message_id: MessageId,
+    /// The reply so far, sent in full when the stream ends.
+    text: String,
+}
+
 /// Discord adapter state.
 pub struct DiscordAdapter {
     token: String,
     permissions: Arc<ArcSwap<DiscordPermissions>>,
     http: Arc<RwLock<Option<Arc<Http>>>>,
     bot_user_id: Arc<RwLock<Option<UserId>>>,
-    /// Maps InboundMessage.id to the Discord MessageId being edited during streaming.
-    active_messages: Arc<RwLock<HashMap<String, serenity::all::MessageId>>>,
+    /// Maps InboundMessage.id to the reply being streamed into a Discord message.
+    active_messages: Arc<RwLock<HashMap<String, ActiveStream>>>,
     /// Typing handles per message. Typing stops when the handle is dropped.
     typing_tasks: Arc<RwLock<HashMap<String, serenity::http::Typing>>>,
+    /// Slash command interactions whose deferred response has been filled,
+    /// keyed by interaction token. Further replies are sent as followups.
+    answered_interactions: Arc<RwLock<HashMap<String, Instant>>>,
+    /// Threads the bot has posted in. Messages there count as addressed to
+    /// the agent, so it keeps following the thread without being mentioned.
+    followed_threads: Arc<RwLock<HashSet<u64>>>,
     shard_manager: Arc<RwLock<Option<Arc<ShardManager>>>>,
+    /// The task running the gateway and rebuilding it after errors.
+    gateway_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+    /// Webhooks agent replies are sent through, by channel. Messages from
+    /// them count as the bot's own.
+    webhooks: Arc<RwLock<HashMap<ChannelId, Webhook>>>,
+    /// Custom emojis per guild, for writing them by name in replies.
+    guild_emojis: Arc<RwLock<HashMap<GuildId, Vec<emoji::CustomEmoji>>>>,
+    /// The last message recorded per channel before a restart, to catch up
+    /// from on connecting.
+    catch_up_from: HashMap<ChannelId, SeenMessage>,
+    /// Voice connections, for transcribing voice channels and speaking replies.
+    #[cfg(feature = "voice")]
+    voice: Arc<songbird::Songbird>,
 }
 
 impl DiscordAdapter {
@@ -41,8 +142,46 @@ impl DiscordAdapter {
             bot_user_id: Arc::new(RwLock::new(None)),
             active_messages: Arc::new(RwLock::new(HashMap::new())),
             typing_tasks: Arc::new(RwLock::new(HashMap::new())),
+            answered_interactions: Arc::new(RwLock::new(HashMap::new())),
+            followed_threads: Arc::new(RwLock::new(HashSet::new())),
             shard_manager: Arc::new(RwLock::new(None)),
+            gateway_task: Arc::new(RwLock::new(None)),
+            webhooks: Arc::new(RwLock::new(HashMap::new())),
+            guild_emojis: Arc::new(RwLock::new(HashMap::new())),
+            catch_up_from: HashMap::new(),
+            #[cfg(feature = "voice")]
+            voice: voice::manager(),
+        }
+    }
+
+    /// Fetch the messages sent since these, the last ones recorded before a
+    /// restart, when first connecting. Messages recorded without a Discord
+    /// position are skipped.
+    pub fn with_catch_up(mut self, recorded: &[ConversationMessage]) -> Self {
+        for (channel_id, seen) in recorded.iter().filter_map(recorded_position) {
+            let latest = self.catch_up_from.entry(channel_id).or_insert(seen);
+            if seen.1 > latest.1 {
+                *latest = seen;
+            }
         }
+        self
+    }
+
+    /// The custom emojis of the guild a message is in. None in DMs.
+    async fn custom_emojis(&self, message: &InboundMessage) -> Vec<emoji::CustomEmoji> {
+        let Some(guild_id) = message
+            .metadata
+            .get("discord_guild_id")
+            .and_then(|v| v.as_u64())
+        else {
+            return Vec::new();
+        };
+        self.guild_emojis
+            .read()
+            .await
+            .get(&GuildId::new(guild_id))
+            .cloned()
+            .unwrap_or_default()
     }
 
     async fn get_http(&self) -> anyhow::Result<Arc<Http>> {
@@ -53,6 +192,36 @@ impl DiscordAdapter {
             .context("discord not connected")
     }
 
+    /// Find a text channel by name in the guilds the bot serves, for
+    /// broadcast targets like "#standup".
+    async fn find_channel_by_name(&self, http: &Http, name: &str) -> anyhow::Result<ChannelId> {
+        let guild_filter = self.permissions.load().guild_filter.clone();
+        let guilds = http
+            .get_guilds(None, None)
+            .await
+            .context("failed to list discord guilds")?;
+        for guild in guilds {
+            if guild_filter
+                .as_ref()
+                .is_some_and(|filter| !filter.contains(&guild.id.get()))
+            {
+                continue;
+            }
+            let channels = guild
+                .id
+                .channels(http)
+                .await
+                .context("failed to list discord channels")?;
+            if let Some(channel) = channels.values().find(|channel| {
+                matches!(channel.kind, ChannelType::Text | ChannelType::News)
+                    && channel.name.eq_ignore_ascii_case(name)
+            }) {
+                return Ok(channel.id);
+            }
+        }
+        anyhow::bail!("no discord channel named #{name}")
+    }
+
     fn extract_channel_id(&self, message: &InboundMessage) -> anyhow::Result<ChannelId> {
         let id = message
             .metadata
@@ -78,6 +247,160 @@ impl DiscordAdapter {
             .await
             .remove(&Self::channel_key(message));
     }
+
+    /// The channel's webhook for agent replies, found or created on first
+    /// use.
+    async fn agent_webhook(&self, http: &Http, channel_id: ChannelId) -> anyhow::Result<Webhook> {
+        if let Some(webhook) = self.webhooks.read().await.get(&channel_id) {
+            return Ok(webhook.clone());
+        }
+        let bot_user_id = *self.bot_user_id.read().await;
+        let existing = channel_id
+            .webhooks(http)
+            .await
+            .context("failed to list discord webhooks")?
+            .into_iter()
+            .find(|webhook| {
+                webhook.token.is_some()
+                    && webhook.name.as_deref() == Some(AGENT_WEBHOOK_NAME)
+                    && webhook.user.as_ref().map(|user| user.id) == bot_user_id
+            });
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => channel_id
+                .create_webhook(http, CreateWebhook::new(AGENT_WEBHOOK_NAME))
+                .await
+                .context("failed to create discord webhook")?,
+        };
+        self.webhooks
+            .write()
+            .await
+            .insert(channel_id, webhook.clone());
+        Ok(webhook)
+    }
+
+    /// Send a reply through the channel's webhook, under the agent's name
+    /// and avatar, one message per part. Threads use their parent's webhook.
+    /// `sent` counts the parts that went out, so a caller falling back to
+    /// sending as the bot after an error can send only the rest.
+    async fn send_as_agent(
+        &self,
+        http: &Http,
+        message: &InboundMessage,
+        identity: &AgentIdentity,
+        parts: Vec<ExecuteWebhook>,
+        sent: &mut usize,
+    ) -> anyhow::Result<()> {
+        let channel_id = self.extract_channel_id(message)?;
+        let parent_id = message
+            .metadata
+            .get("discord_parent_channel_id")
+            .and_then(|v| v.as_u64())
+            .map(ChannelId::new);
+        let webhook = self
+            .agent_webhook(http, parent_id.unwrap_or(channel_id))
+            .await?;
+        for part in parts {
+            let mut part = part.username(&identity.name);
+            if let Some(avatar_url) = &identity.avatar_url {
+                part = part.avatar_url(avatar_url);
+            }
+            if parent_id.is_some() {
+                part = part.in_thread(channel_id);
+            }
+            webhook
+                .execute(http, false, part)
+                .await
+                .context("failed to send discord webhook message")?;
+            *sent += 1;
+        }
+        Ok(())
+    }
+
+    /// Follow the thread the message came from, if it's in one.
+    async fn follow_thread(&self, message: &InboundMessage) {
+        let is_thread = message
+            .metadata
+            .get("discord_is_thread")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let channel_id = message
+            .metadata
+            .get("discord_channel_id")
+            .and_then(|v| v.as_u64());
+        if is_thread && let Some(channel_id) = channel_id {
+            self.followed_threads.write().await.insert(channel_id);
+        }
+    }
+
+    /// Answer a slash command. The first reply fills the deferred response;
+    /// later ones are followups. Returns false once the token has expired.
+    async fn reply_to_interaction(
+        &self,
+        http: &Http,
+        message: &InboundMessage,
+        text: &str,
+    ) -> anyhow::Result<bool> {
+        let Some(token) = message
+            .metadata
+            .get("discord_interaction_token")
+            .and_then(|v| v.as_str())
+        else {
+            return Ok(false);
+        };
+        if message.timestamp + INTERACTION_TOKEN_TTL < chrono::Utc::now() {
+            return Ok(false);
+        }
+
+        // The first response inherits this from the deferral.
+        let ephemeral = message
+            .metadata
+            .get("discord_interaction_ephemeral")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let mut answered = self.answered_interactions.write().await;
+        answered.retain(|_, answered_at| answered_at.elapsed() < INTERACTION_TOKEN_TTL);
+        let mut first = !answered.contains_key(token);
+
+        for chunk in chunk_message(text, MESSAGE_LIMIT) {
+            if first {
+                EditInteractionResponse::new()
+                    .content(chunk)
+                    .execute(http, token)
+                    .await
+                    .context("failed to send discord slash command response")?;
+                answered.insert(token.to_string(), Instant::now());
+                first = false;
+            } else {
+                CreateInteractionResponseFollowup::new()
+                    .content(chunk)
+                    .ephemeral(ephemeral)
+                    .execute(http, (None, token))
+                    .await
+                    .context("failed to send discord slash command followup")?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Speak a reply to a voice utterance in the call, if TTS is on.
+    #[cfg(feature = "voice")]
+    fn speak_reply(&self, message: &InboundMessage, text: &str) {
+        let config = self.permissions.load().voice.clone();
+        if !config.tts || text.trim().is_empty() || !voice::is_voice(message) {
+            return;
+        }
+        let manager = self.voice.clone();
+        let message = message.clone();
+        let text = text.to_string();
+        tokio::spawn(async move {
+            if let Err(error) = voice::speak(&manager, &config, &message, &text).await {
+                tracing::warn!(%error, "failed to speak reply in voice channel");
+            }
+        });
+    }
 }
 
 impl Messaging for DiscordAdapter {
@@ -93,26 +416,30 @@ impl Messaging for DiscordAdapter {
             permissions: self.permissions.clone(),
             http_slot: self.http.clone(),
             bot_user_id_slot: self.bot_user_id.clone(),
+            followed_threads: self.followed_threads.clone(),
+            rate_limiter: Arc::new(UserRateLimiter::new()),
+            last_seen: Arc::new(RwLock::new(self.catch_up_from.clone())),
+            webhooks: self.webhooks.clone(),
+            guild_emojis: self.guild_emojis.clone(),
+            #[cfg(feature = "voice")]
+            voice: self.voice.clone(),
         };
 
-        let intents = GatewayIntents::GUILD_MESSAGES
-            | GatewayIntents::DIRECT_MESSAGES
-            | GatewayIntents::MESSAGE_CONTENT
-            | GatewayIntents::GUILDS;
-
-        let mut client = serenity::Client::builder(&self.token, intents)
-            .event_handler(handler)
+        let client = build_client(&self.token, handler.clone())
             .await
             .context("failed to build discord client")?;
 
         *self.http.write().await = Some(client.http.clone());
         *self.shard_manager.write().await = Some(client.shard_manager.clone());
 
-        tokio::spawn(async move {
-            if let Err(error) = client.start().await {
-                tracing::error!(%error, "discord gateway error");
-            }
-        });
+        let task = tokio::spawn(run_gateway(
+            client,
+            handler,
+            self.token.clone(),
+            self.http.clone(),
+            self.shard_manager.clone(),
+        ));
+        *self.gateway_task.write().await = Some(task);
 
         let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
         Ok(Box::pin(stream))
@@ -125,14 +452,68 @@ impl Messaging for DiscordAdapter {
     ) -> crate::Result<()> {
         let http = self.get_http().await?;
         let channel_id = self.extract_channel_id(message)?;
+        let emojis = self.custom_emojis(message).await;
+        let response = emoji::resolve_response(response, &emojis);
+
+        if matches!(
+            response,
+            OutboundResponse::Text(_)
+                | OutboundResponse::RichMessage { .. }
+                | OutboundResponse::File { .. }
+                | OutboundResponse::StreamStart
+        ) {
+            self.follow_thread(message).await;
+        }
+
+        #[cfg(feature = "voice")]
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = &response
+        {
+            self.speak_reply(message, text);
+        }
 
         match response {
             OutboundResponse::Text(text) => {
                 self.stop_typing(message).await;
 
-                for chunk in split_message(&text, 2000) {
+                if self.reply_to_interaction(&http, message, &text).await? {
+                    return Ok(());
+                }
+                // A reply of nothing but a few emoji is added as reactions.
+                if self.permissions.load().reaction_replies
+                    && let Some(reactions) = emoji::reply_reactions(&text)
+                    && let Some(message_id) = message
+                        .metadata
+                        .get("discord_message_id")
+                        .and_then(|v| v.as_u64())
+                {
+                    for reaction in reactions {
+                        channel_id
+                            .create_reaction(&*http, MessageId::new(message_id), reaction)
+                            .await
+                            .context("failed to add reply reaction")?;
+                    }
+                    return Ok(());
+                }
+                let chunks = chunk_message(&text, MESSAGE_LIMIT);
+                let mut sent = 0;
+                if let Some(identity) = agent_identity(&self.permissions.load(), message) {
+                    let parts = chunks
+                        .iter()
+                        .map(|chunk| ExecuteWebhook::new().content(chunk))
+                        .collect();
+                    match self
+                        .send_as_agent(&http, message, &identity, parts, &mut sent)
+                        .await
+                    {
+                        Ok(()) => return Ok(()),
+                        Err(error) => {
+                            tracing::warn!(%error, sent, "failed to reply through webhook, replying as the bot");
+                        }
+                    }
+                }
+                for chunk in chunks.iter().skip(sent) {
                     channel_id
-                        .say(&*http, &chunk)
+                        .say(&*http, chunk)
                         .await
                         .context("failed to send discord message")?;
                 }
@@ -146,8 +527,42 @@ impl Messaging for DiscordAdapter {
             } => {
                 self.stop_typing(message).await;
 
-                let chunks = split_message(&text, 2000);
-                for (i, chunk) in chunks.iter().enumerate() {
+                let chunks = chunk_message(&text, MESSAGE_LIMIT);
+                // Each chunk is one part, so after a webhook failure the bot
+                // sends only the chunks the webhook didn't.
+                let mut sent = 0;
+                // Webhooks can't send polls.
+                if poll.is_none()
+                    && let Some(identity) = agent_identity(&self.permissions.load(), message)
+                {
+                    let mut parts: Vec<_> = chunks
+                        .iter()
+                        .filter(|chunk| !chunk.is_empty())
+                        .map(|chunk| ExecuteWebhook::new().content(chunk))
+                        .collect();
+                    if parts.is_empty() {
+                        parts.push(ExecuteWebhook::new());
+                    }
+                    if let Some(last) = parts.pop() {
+                        let embeds = cards.iter().take(10).map(build_embed).collect();
+                        let components = interactive_elements
+                            .iter()
+                            .take(5)
+                            .map(build_action_row)
+                            .collect();
+                        parts.push(last.embeds(embeds).components(components));
+                    }
+                    match self
+                        .send_as_agent(&http, message, &identity, parts, &mut sent)
+                        .await
+                    {
+                        Ok(()) => return Ok(()),
+                        Err(error) => {
+                            tracing::warn!(%error, sent, "failed to reply through webhook, replying as the bot");
+                        }
+                    }
+                }
+                for (i, chunk) in chunks.iter().enumerate().skip(sent) {
                     let is_last = i == chunks.len() - 1;
                     let mut msg = CreateMessage::new();
                     if !chunk.is_empty() {
@@ -209,7 +624,8 @@ impl Messaging for DiscordAdapter {
 
                 match thread_result {
                     Ok(thread) => {
-                        for chunk in split_message(&text, 2000) {
+                        self.followed_threads.write().await.insert(thread.id.get());
+                        for chunk in chunk_message(&text, MESSAGE_LIMIT) {
                             thread
                                 .id
                                 .say(&*http, &chunk)
@@ -225,7 +641,7 @@ impl Messaging for DiscordAdapter {
                             thread_name = %thread_name,
                             "failed to create thread, falling back to regular message"
                         );
-                        for chunk in split_message(&text, 2000) {
+                        for chunk in chunk_message(&text, MESSAGE_LIMIT) {
                             channel_id
                                 .say(&*http, &chunk)
                                 .await
@@ -264,7 +680,7 @@ impl Messaging for DiscordAdapter {
                     .create_reaction(
                         &*http,
                         MessageId::new(message_id),
-                        ReactionType::Unicode(emoji),
+                        emoji::reaction(&emoji, &emojis),
                     )
                     .await
                     .context("failed to add reaction")?;
@@ -277,28 +693,63 @@ impl Messaging for DiscordAdapter {
                     .await
                     .context("failed to send stream placeholder")?;
 
-                self.active_messages
-                    .write()
-                    .await
-                    .insert(message.id.clone(), placeholder.id);
+                self.active_messages.write().await.insert(
+                    message.id.clone(),
+                    ActiveStream {
+                        message_id: placeholder.id,
+                        text: String::new(),
+                    },
+                );
             }
             OutboundResponse::StreamChunk(text) => {
-                let active = self.active_messages.read().await;
-                if let Some(&message_id) = active.get(&message.id) {
-                    let display_text = if text.len() > 2000 {
-                        let end = text.floor_char_boundary(1997);
-                        format!("{}...", &text[..end])
-                    } else {
-                        text
-                    };
+                let mut active = self.active_messages.write().await;
+                if let Some(stream) = active.get_mut(&message.id) {
+                    // Only the first message is streamed into; the rest of a
+                    // long reply is sent when the stream ends.
+                    let display_text = chunk_message(&text, MESSAGE_LIMIT).swap_remove(0);
+                    stream.text = text;
                     let builder = EditMessage::new().content(display_text);
-                    if let Err(error) = channel_id.edit_message(&*http, message_id, builder).await {
+                    if let Err(error) = channel_id
+                        .edit_message(&*http, stream.message_id, builder)
+                        .await
+                    {
                         tracing::warn!(%error, "failed to edit streaming message");
                     }
                 }
             }
             OutboundResponse::StreamEnd => {
-                self.active_messages.write().await.remove(&message.id);
+                let stream = self.active_messages.write().await.remove(&message.id);
+                let chunks = stream
+                    .as_ref()
+                    .map(|stream| chunk_message(&stream.text, MESSAGE_LIMIT))
+                    .unwrap_or_default();
+                if let Some(stream) = stream
+                    && let [first, rest @ ..] = chunks.as_slice()
+                    && !rest.is_empty()
+                {
+                    // The count in the streamed message's marker may be out of date.
+                    let builder = EditMessage::new().content(first);
+                    if let Err(error) = channel_id
+                        .edit_message(&*http, stream.message_id, builder)
+                        .await
+                    {
+                        tracing::warn!(%error, "failed to edit streaming message");
+                    }
+                    for chunk in rest {
+                        channel_id
+                            .say(&*http, chunk)
+                            .await
+                            .context("failed to send discord message")?;
+                    }
+                }
+            }
+            OutboundResponse::StreamAbort => {
+                let stream = self.active_messages.write().await.remove(&message.id);
+                if let Some(stream) = stream
+                    && let Err(error) = channel_id.delete_message(&*http, stream.message_id).await
+                {
+                    tracing::warn!(%error, "failed to delete aborted streaming message");
+                }
             }
             OutboundResponse::Status(status) => {
                 self.send_status(message, status).await?;
@@ -309,20 +760,24 @@ impl Messaging for DiscordAdapter {
                 // Discord has no ephemeral equivalent here; send as regular text
                 if let Ok(channel_id) = self.extract_channel_id(message) {
                     let http = self.get_http().await?;
-                    channel_id
-                        .say(&*http, &text)
-                        .await
-                        .context("failed to send ephemeral fallback on discord")?;
+                    for chunk in chunk_message(&text, MESSAGE_LIMIT) {
+                        channel_id
+                            .say(&*http, &chunk)
+                            .await
+                            .context("failed to send ephemeral fallback on discord")?;
+                    }
                 }
             }
             OutboundResponse::ScheduledMessage { text, .. } => {
                 // Discord has no native scheduled messages — send immediately
                 if let Ok(channel_id) = self.extract_channel_id(message) {
                     let http = self.get_http().await?;
-                    channel_id
-                        .say(&*http, &text)
-                        .await
-                        .context("failed to send scheduled message fallback on discord")?;
+                    for chunk in chunk_message(&text, MESSAGE_LIMIT) {
+                        channel_id
+                            .say(&*http, &chunk)
+                            .await
+                            .context("failed to send scheduled message fallback on discord")?;
+                    }
                 }
             }
         }
@@ -337,18 +792,23 @@ impl Messaging for DiscordAdapter {
     ) -> crate::Result<()> {
         match status {
             StatusUpdate::Thinking => {
+                // Sent before every LLM call of a turn. A running indicator
+                // keeps refreshing itself until it's dropped.
+                let key = Self::channel_key(message);
+                let mut typing_tasks = self.typing_tasks.write().await;
+                if typing_tasks.contains_key(&key) {
+                    return Ok(());
+                }
+
                 let http = self.get_http().await?;
                 let channel_id = self.extract_channel_id(message)?;
-
-                let typing = channel_id.start_typing(&http);
-                self.typing_tasks
-                    .write()
-                    .await
-                    .insert(Self::channel_key(message), typing);
+                typing_tasks.insert(key, channel_id.start_typing(&http));
             }
-            _ => {
+            StatusUpdate::StopTyping => {
                 self.stop_typing(message).await;
             }
+            // Tool and worker progress doesn't change whether the agent is typing.
+            _ => {}
         }
 
         Ok(())
@@ -369,6 +829,8 @@ impl Messaging for DiscordAdapter {
                 .await
                 .context("failed to open DM channel")?
                 .id
+        } else if let Some(name) = target.strip_prefix('#') {
+            self.find_channel_by_name(&http, name).await?
         } else {
             ChannelId::new(
                 target
@@ -378,7 +840,7 @@ impl Messaging for DiscordAdapter {
         };
 
         if let OutboundResponse::Text(text) = response {
-            for chunk in split_message(&text, 2000) {
+            for chunk in chunk_message(&text, MESSAGE_LIMIT) {
                 channel_id
                     .say(&*http, &chunk)
                     .await
@@ -392,7 +854,7 @@ impl Messaging for DiscordAdapter {
             ..
         } = response
         {
-            let chunks = split_message(&text, 2000);
+            let chunks = chunk_message(&text, MESSAGE_LIMIT);
             for (i, chunk) in chunks.iter().enumerate() {
                 let is_last = i == chunks.len() - 1;
                 let mut msg = CreateMessage::new();
@@ -456,16 +918,15 @@ impl Messaging for DiscordAdapter {
             .await
             .context("failed to fetch discord message history")?;
 
-        let bot_user_id = self.bot_user_id.read().await;
+        let bot_user_id = *self.bot_user_id.read().await;
+        let webhooks = self.webhooks.read().await;
 
         // Messages come back newest-first from Discord, reverse to chronological
         let history: Vec<HistoryMessage> = messages
             .iter()
             .rev()
             .map(|message| {
-                let is_bot = bot_user_id
-                    .map(|bot_id| message.author.id == bot_id)
-                    .unwrap_or(false);
+                let is_bot = sent_by_bot(message, bot_user_id, &webhooks);
 
                 let resolved_content = resolve_mentions(&message.content, &message.mentions);
 
@@ -507,6 +968,112 @@ impl Messaging for DiscordAdapter {
         Ok(history)
     }
 
+    async fn moderate(
+        &self,
+        conversation_id: &str,
+        action: ModerationAction,
+        reason: &str,
+    ) -> crate::Result<()> {
+        let http = self.get_http().await?;
+        let (guild_id, channel_id) =
+            guild_channel(conversation_id).context("moderation only works in server channels")?;
+
+        match action {
+            ModerationAction::DeleteMessage { message_id } => {
+                let message_id = MessageId::new(parse_id(&message_id, "message")?);
+                http.delete_message(channel_id, message_id, Some(reason))
+                    .await
+                    .context("failed to delete discord message")?;
+            }
+            ModerationAction::TimeoutUser {
+                user_id,
+                duration_secs,
+            } => {
+                let user_id = UserId::new(parse_id(&user_id, "user")?);
+                let until = Timestamp::from_unix_timestamp(
+                    chrono::Utc::now().timestamp() + duration_secs as i64,
+                )
+                .context("invalid timeout duration")?;
+                guild_id
+                    .edit_member(
+                        &*http,
+                        user_id,
+                        EditMember::new()
+                            .disable_communication_until_datetime(until)
+                            .audit_log_reason(reason),
+                    )
+                    .await
+                    .context("failed to time out discord member")?;
+            }
+            ModerationAction::ApplyRole { user_id, role_id } => {
+                let user_id = UserId::new(parse_id(&user_id, "user")?);
+                let role_id = RoleId::new(parse_id(&role_id, "role")?);
+                http.add_member_role(guild_id, user_id, role_id, Some(reason))
+                    .await
+                    .context("failed to apply discord role")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn tag_post(&self, conversation_id: &str, tags: Vec<String>) -> crate::Result<()> {
+        let http = self.get_http().await?;
+        let (_, post_id) =
+            guild_channel(conversation_id).context("tags only work in forum posts")?;
+        let post = post_id
+            .to_channel(&*http)
+            .await
+            .context("failed to fetch discord forum post")?
+            .guild()
+            .context("tags only work in forum posts")?;
+        let forum = match post.parent_id {
+            Some(parent_id) => parent_id
+                .to_channel(&*http)
+                .await
+                .context("failed to fetch discord forum")?
+                .guild(),
+            None => None,
+        }
+        .filter(|channel| channel.kind == ChannelType::Forum)
+        .context("tags only work in forum posts")?;
+
+        let tag_ids = tags
+            .iter()
+            .map(|name| {
+                forum
+                    .available_tags
+                    .iter()
+                    .find(|tag| tag.name == *name)
+                    .map(|tag| tag.id)
+                    .with_context(|| format!("the forum has no tag '{name}'"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        post_id
+            .edit_thread(&*http, EditThread::new().applied_tags(tag_ids))
+            .await
+            .context("failed to tag discord forum post")?;
+        Ok(())
+    }
+
+    async fn set_presence(&self, presence: Presence) -> crate::Result<()> {
+        let Some(shard_manager) = self.shard_manager.read().await.clone() else {
+            return Ok(());
+        };
+        let status = match presence {
+            Presence::Idle => OnlineStatus::Idle,
+            Presence::Busy(_) => OnlineStatus::Online,
+            Presence::RateLimited => OnlineStatus::DoNotDisturb,
+        };
+        let activity = ActivityData::custom(presence.label());
+        for runner in shard_manager.runners.lock().await.values() {
+            runner
+                .runner_tx
+                .set_presence(Some(activity.clone()), status);
+        }
+        Ok(())
+    }
+
     async fn health_check(&self) -> crate::Result<()> {
         let http = self.get_http().await?;
         http.get_current_user()
@@ -521,19 +1088,194 @@ impl Messaging for DiscordAdapter {
         if let Some(shard_manager) = self.shard_manager.read().await.as_ref() {
             shard_manager.shutdown_all().await;
         }
+        if let Some(task) = self.gateway_task.write().await.take() {
+            task.abort();
+        }
 
         tracing::info!("discord adapter shut down");
         Ok(())
     }
 }
 
+/// Build a gateway client around the handler.
+async fn build_client(token: &str, handler: Handler) -> serenity::Result<serenity::Client> {
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::DIRECT_MESSAGE_REACTIONS
+        | GatewayIntents::GUILD_EMOJIS_AND_STICKERS
+        | GatewayIntents::GUILDS;
+    #[cfg(feature = "voice")]
+    let intents = intents | GatewayIntents::GUILD_VOICE_STATES;
+
+    #[cfg(feature = "voice")]
+    let voice = handler.voice.clone();
+    let builder = serenity::Client::builder(token, intents).event_handler(handler);
+    #[cfg(feature = "voice")]
+    let builder = {
+        use songbird::SerenityInit as _;
+        builder.register_songbird_with(voice)
+    };
+    builder.await
+}
+
+/// Run the gateway until shutdown. Serenity reconnects and resumes dropped
+/// sessions itself; when the client stops on an error anyway, for example
+/// because the gateway couldn't be reached, it's rebuilt with exponential
+/// backoff. Errors that would recur, like a bad token, stop it for good.
+async fn run_gateway(
+    mut client: serenity::Client,
+    handler: Handler,
+    token: String,
+    http_slot: Arc<RwLock<Option<Arc<Http>>>>,
+    shard_manager_slot: Arc<RwLock<Option<Arc<ShardManager>>>>,
+) {
+    let mut backoff = RECONNECT_MIN_BACKOFF;
+    loop {
+        let started = Instant::now();
+        let error = match client.start().await {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+        if is_fatal(&error) {
+            tracing::error!(%error, "discord gateway error, not reconnecting");
+            return;
+        }
+        if started.elapsed() >= RECONNECT_STABLE_AFTER {
+            backoff = RECONNECT_MIN_BACKOFF;
+        }
+
+        client = loop {
+            tracing::warn!(
+                %error,
+                retry_in_secs = backoff.as_secs(),
+                "discord gateway stopped, reconnecting"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+            match build_client(&token, handler.clone()).await {
+                Ok(client) => break client,
+                Err(error) => {
+                    tracing::warn!(%error, "failed to rebuild discord client");
+                }
+            }
+        };
+        *http_slot.write().await = Some(client.http.clone());
+        *shard_manager_slot.write().await = Some(client.shard_manager.clone());
+    }
+}
+
+/// Whether a gateway error would recur on every attempt.
+fn is_fatal(error: &serenity::Error) -> bool {
+    use serenity::gateway::GatewayError;
+
+    matches!(
+        error,
+        serenity::Error::Gateway(
+            GatewayError::InvalidAuthentication
+                | GatewayError::NoAuthentication
+                | GatewayError::InvalidGatewayIntents
+                | GatewayError::DisallowedGatewayIntents
+                | GatewayError::InvalidShardData
+                | GatewayError::OverloadedShard
+        )
+    )
+}
+
+/// The name and avatar an agent's replies are sent under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AgentIdentity {
+    name: String,
+    avatar_url: Option<String>,
+}
+
+/// The identity to reply to the message under, if agent webhooks are on.
+/// Webhooks only exist in guild channels, so DMs get None.
+fn agent_identity(
+    permissions: &DiscordPermissions,
+    message: &InboundMessage,
+) -> Option<AgentIdentity> {
+    if !permissions.agent_webhooks || !message.metadata.contains_key("discord_guild_id") {
+        return None;
+    }
+    let name = message
+        .metadata
+        .get(AGENT_DISPLAY_NAME_KEY)
+        .and_then(|value| value.as_str())?;
+    let name = name.chars().take(WEBHOOK_USERNAME_LIMIT).collect();
+    let avatar_url = message
+        .metadata
+        .get(AGENT_AVATAR_URL_KEY)
+        .and_then(|value| value.as_str())
+        .map(String::from);
+    Some(AgentIdentity { name, avatar_url })
+}
+
+/// Whether the bot sent the message, itself or through one of its agent
+/// webhooks.
+fn sent_by_bot(
+    message: &Message,
+    bot_user_id: Option<UserId>,
+    webhooks: &HashMap<ChannelId, Webhook>,
+) -> bool {
+    bot_user_id.is_some_and(|id| message.author.id == id)
+        || message
+            .webhook_id
+            .is_some_and(|id| webhooks.values().any(|webhook| webhook.id == id))
+}
+
+/// The latest message forwarded from a channel, and the channel's guild.
+type SeenMessage = (Option<GuildId>, MessageId);
+
+/// The Discord channel and latest message a recorded message points at.
+fn recorded_position(message: &ConversationMessage) -> Option<(ChannelId, SeenMessage)> {
+    let metadata: HashMap<String, serde_json::Value> =
+        serde_json::from_str(message.metadata.as_deref()?).ok()?;
+    let id = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(|value| value.as_u64())
+            .filter(|id| *id != 0)
+    };
+    Some((
+        ChannelId::new(id("discord_channel_id")?),
+        (
+            id("discord_guild_id").map(GuildId::new),
+            MessageId::new(id("discord_message_id")?),
+        ),
+    ))
+}
+
+/// The channels to fetch missed messages for, most recently active first.
+fn backfill_targets(last_seen: &HashMap<ChannelId, SeenMessage>) -> Vec<(ChannelId, SeenMessage)> {
+    let mut targets: Vec<_> = last_seen
+        .iter()
+        .map(|(channel_id, seen)| (*channel_id, *seen))
+        .collect();
+    // Snowflakes sort by time.
+    targets.sort_by(|a, b| b.1.1.cmp(&a.1.1));
+    targets.truncate(BACKFILL_MAX_CHANNELS);
+    targets
+}
+
 // -- Serenity EventHandler --
 
+#[derive(Clone)]
 struct Handler {
     inbound_tx: mpsc::Sender<InboundMessage>,
     permissions: Arc<ArcSwap<DiscordPermissions>>,
     http_slot: Arc<RwLock<Option<Arc<Http>>>>,
     bot_user_id_slot: Arc<RwLock<Option<UserId>>>,
+    followed_threads: Arc<RwLock<HashSet<u64>>>,
+    rate_limiter: Arc<UserRateLimiter>,
+    /// The latest message forwarded per channel, for fetching what was
+    /// missed while disconnected. Starts from the positions to catch up from.
+    last_seen: Arc<RwLock<HashMap<ChannelId, SeenMessage>>>,
+    webhooks: Arc<RwLock<HashMap<ChannelId, Webhook>>>,
+    guild_emojis: Arc<RwLock<HashMap<GuildId, Vec<emoji::CustomEmoji>>>>,
+    #[cfg(feature = "voice")]
+    voice: Arc<songbird::Songbird>,
 }
 
 #[async_trait]
@@ -542,115 +1284,198 @@ impl EventHandler for Handler {
         tracing::info!(bot_name = %ready.user.name, "discord connected");
 
         *self.http_slot.write().await = Some(ctx.http.clone());
-        *self.bot_user_id_slot.write().await = Some(ready.user.id);
+        // A ready after the first means the session couldn't be resumed, so
+        // the events sent while disconnected are lost. Fetch the messages.
+        let reconnected = self
+            .bot_user_id_slot
+            .write()
+            .await
+            .replace(ready.user.id)
+            .is_some();
+        let catch_up = self.permissions.load().catch_up;
+        if reconnected {
+            self.backfill(&ctx, false).await;
+        } else if catch_up.enabled {
+            // On the first ready, `last_seen` holds the last messages
+            // recorded before the restart.
+            self.backfill(&ctx, catch_up.summarize).await;
+        }
         tracing::info!(guild_count = ready.guilds.len(), "discord guilds available");
+
+        if self.permissions.load().slash_commands {
+            match Command::set_global_commands(&ctx.http, slash_commands()).await {
+                Ok(commands) => {
+                    tracing::info!(count = commands.len(), "discord slash commands registered");
+                }
+                Err(error) => {
+                    tracing::warn!(%error, "failed to register discord slash commands");
+                }
+            }
+        }
+
+        #[cfg(feature = "voice")]
+        voice::join_configured(&ctx, &self.voice, &self.inbound_tx, &self.permissions).await;
+        #[cfg(not(feature = "voice"))]
+        if self.permissions.load().voice.enabled {
+            tracing::warn!(
+                "discord voice is enabled but spacebot was built without the voice feature"
+            );
+        }
+    }
+
+    async fn resume(&self, _ctx: Context, _event: ResumedEvent) {
+        tracing::info!("discord session resumed");
+    }
+
+    async fn guild_create(&self, _ctx: Context, guild: Guild, _is_new: Option<bool>) {
+        let emojis = emoji::CustomEmoji::usable(guild.emojis.values());
+        self.guild_emojis.write().await.insert(guild.id, emojis);
+    }
+
+    async fn guild_emojis_update(
+        &self,
+        _ctx: Context,
+        guild_id: GuildId,
+        current_state: HashMap<EmojiId, Emoji>,
+    ) {
+        let emojis = emoji::CustomEmoji::usable(current_state.values());
+        self.guild_emojis.write().await.insert(guild_id, emojis);
     }
 
     async fn message(&self, ctx: Context, message: Message) {
-        // Always ignore our own messages to prevent self-response loops
-        let bot_user_id = self.bot_user_id_slot.read().await;
-        if bot_user_id.is_some_and(|id| message.author.id == id) {
+        self.forward_message(&ctx, message, Arrival::Live).await;
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // Link previews arrive as updates too; only content edits matter.
+        if event.content.is_none() || event.edited_timestamp.is_none() {
             return;
         }
-        drop(bot_user_id);
+        let mut message = match new {
+            Some(message) => message,
+            None => match event.channel_id.message(&ctx.http, event.id).await {
+                Ok(message) => message,
+                Err(error) => {
+                    tracing::warn!(%error, "failed to fetch edited discord message");
+                    return;
+                }
+            },
+        };
+        // Messages fetched over HTTP don't say which guild they're in.
+        message.guild_id = message.guild_id.or(event.guild_id);
+        self.forward_message(&ctx, message, Arrival::Edited).await;
+    }
 
-        // Load a snapshot of the current permissions (hot-reloadable)
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        let ReactionType::Unicode(emoji) = &reaction.emoji else {
+            return;
+        };
         let permissions = self.permissions.load();
-
-        // Filter other bots unless explicitly allowed
-        if message.author.bot && !permissions.allow_bot_messages {
+        let control = ReactionControl::from_emoji(&permissions.reaction_controls, emoji);
+        if emoji != CANCEL_REACTION && control.is_none() {
+            if let Some(score) = sentiment::score(emoji) {
+                self.handle_sentiment(&ctx, &reaction, emoji, score, false)
+                    .await;
+            }
+            return;
+        }
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        if self
+            .bot_user_id_slot
+            .read()
+            .await
+            .is_some_and(|id| id == user_id)
+        {
+            return;
+        }
+        if !member_allows(
+            &permissions,
+            RolePermission::ChannelControls,
+            reaction.guild_id,
+            reaction
+                .member
+                .as_ref()
+                .map(|member| member.roles.as_slice()),
+        ) {
             return;
         }
+        let Some((conversation_id, parent_channel_id)) = self
+            .reaction_conversation(&ctx, &permissions, &reaction, user_id)
+            .await
+        else {
+            return;
+        };
 
-        // DM filter: if no guild_id, it's a DM — only allow listed users
-        if message.guild_id.is_none() {
-            if permissions.dm_allowed_users.is_empty()
-                || !permissions
-                    .dm_allowed_users
-                    .contains(&message.author.id.get())
-            {
-                return;
-            }
+        if let Some(control) = control {
+            self.handle_control(&ctx, &reaction, control, conversation_id, parent_channel_id)
+                .await;
+            return;
         }
 
-        if let Some(filter) = &permissions.guild_filter {
-            if let Some(guild_id) = message.guild_id {
-                if !filter.contains(&guild_id.get()) {
-                    return;
-                }
-            }
-        }
-
-        let conversation_id = build_conversation_id(&message);
-        let content = extract_content(&message);
-        let (metadata, formatted_author) = build_metadata(&ctx, &message).await;
-
-        // Channel filter: allow if the channel ID or its parent (for threads) is in the allowlist
-        if let Some(guild_id) = message.guild_id {
-            if let Some(allowed_channels) = permissions.channel_filter.get(&guild_id.get()) {
-                if !allowed_channels.is_empty() {
-                    let parent_channel_id = metadata
-                        .get("discord_parent_channel_id")
-                        .and_then(|v| v.as_u64());
-
-                    let direct_match = allowed_channels.contains(&message.channel_id.get());
-                    let parent_match =
-                        parent_channel_id.is_some_and(|pid| allowed_channels.contains(&pid));
-
-                    if !direct_match && !parent_match {
-                        return;
-                    }
-                }
-            }
+        let mut metadata = HashMap::new();
+        metadata.insert(CANCEL_TURN_KEY.into(), true.into());
+        metadata.insert(
+            "discord_channel_id".into(),
+            reaction.channel_id.get().into(),
+        );
+        metadata.insert("discord_user_id".into(), user_id.get().into());
+        if is_moderator(&ctx, &reaction, parent_channel_id) {
+            metadata.insert(MODERATOR_KEY.into(), true.into());
         }
 
         let inbound = InboundMessage {
-            id: message.id.to_string(),
+            id: format!("{}:cancel:{}", reaction.message_id, user_id),
             source: "discord".into(),
             conversation_id,
-            sender_id: message.author.id.to_string(),
+            sender_id: user_id.to_string(),
             agent_id: None,
-            content,
-            timestamp: *message.timestamp,
+            content: MessageContent::Text(String::new()),
+            timestamp: chrono::Utc::now(),
             metadata,
-            formatted_author: Some(formatted_author),
+            formatted_author: None,
         };
 
         if let Err(error) = self.inbound_tx.send(inbound).await {
             tracing::warn!(
                 %error,
-                "failed to send inbound message from Discord (receiver dropped)"
+                "failed to send cancel request from Discord (receiver dropped)"
             );
         }
     }
 
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        let ReactionType::Unicode(emoji) = &reaction.emoji else {
+            return;
+        };
+        if let Some(score) = sentiment::score(emoji) {
+            self.handle_sentiment(&ctx, &reaction, emoji, score, true)
+                .await;
+        }
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         let component = match interaction {
             Interaction::Component(c) => c,
-            _ => return, // Only handle component interactions
+            Interaction::Command(command) => {
+                self.handle_command(&ctx, command).await;
+                return;
+            }
+            _ => return, // Only handle component and command interactions
         };
 
-        // Acknowledge the interaction immediately to prevent "This interaction failed" in the UI.
-        // We use Defer to indicate we've received it and might edit the message soon.
-        if let Err(error) = component
-            .create_response(
-                &ctx.http,
-                CreateInteractionResponse::Defer(CreateInteractionResponseMessage::new()),
-            )
-            .await
-        {
-            tracing::warn!(%error, "failed to acknowledge interaction");
-        }
-
         let user = &component.user;
         let permissions = self.permissions.load();
 
-        if component.guild_id.is_none() {
-            if permissions.dm_allowed_users.is_empty()
-                || !permissions.dm_allowed_users.contains(&user.id.get())
-            {
-                return;
-            }
+        if component.guild_id.is_none() && !permissions.allows_dm(user.id.get()) {
+            return;
         }
 
         if let Some(filter) = &permissions.guild_filter {
@@ -661,21 +1486,40 @@ impl EventHandler for Handler {
             }
         }
 
-        let conversation_id = match component.guild_id {
-            Some(guild_id) => format!("discord:{}:{}", guild_id, component.channel_id),
-            None => format!("discord:dm:{}", user.id),
-        };
+        let parent_channel_id = component.channel.as_ref().and_then(thread_parent);
+        let conversation_id = build_conversation_id(
+            component.guild_id,
+            component.channel_id,
+            parent_channel_id,
+            user.id,
+        );
 
         let values = match &component.data.kind {
             serenity::all::ComponentInteractionDataKind::StringSelect { values } => values.clone(),
             _ => Vec::new(),
         };
+        let custom_id = &component.data.custom_id;
+
+        // Acknowledging by updating the message closes the row that was
+        // used, so each prompt is answered once.
+        let rows = close_row(&component.message.components, custom_id, &values);
+        if let Err(error) = component
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().components(rows),
+                ),
+            )
+            .await
+        {
+            tracing::warn!(%error, "failed to acknowledge interaction");
+        }
 
         let content = MessageContent::Interaction {
-            action_id: component.data.custom_id.clone(),
+            action_id: custom_id.clone(),
             block_id: None,
+            label: component_label(&component.message.components, custom_id, &values),
             values,
-            label: None,
             message_ts: Some(component.message.id.get().to_string()),
         };
 
@@ -705,6 +1549,16 @@ impl EventHandler for Handler {
             serde_json::Value::String(formatted_author.clone()),
         );
 
+        mark_private_memory(&permissions, component.guild_id, &mut metadata);
+        mark_expensive_tools(
+            &permissions,
+            component.guild_id,
+            component
+                .member
+                .as_ref()
+                .map(|member| member.roles.as_slice()),
+            &mut metadata,
+        );
         let inbound = InboundMessage {
             id: component.id.to_string(), // Use interaction ID to ensure uniqueness
             source: "discord".into(),
@@ -726,15 +1580,865 @@ impl EventHandler for Handler {
     }
 }
 
+impl Handler {
+    /// Filter a new or edited message and send it to the agents. Edits carry
+    /// `MESSAGE_EDIT_KEY` and don't count toward the rate limit: they update
+    /// a message the agent already has instead of starting a turn.
+    async fn forward_message(&self, ctx: &Context, message: Message, arrival: Arrival) {
+        // Always ignore our own messages to prevent self-response loops
+        let bot_user_id = *self.bot_user_id_slot.read().await;
+        if sent_by_bot(&message, bot_user_id, &*self.webhooks.read().await) {
+            return;
+        }
+        let edited = arrival == Arrival::Edited;
+        let mentions_bot = bot_user_id.is_some_and(|id| message.mentions_user_id(id));
+
+        // Load a snapshot of the current permissions (hot-reloadable)
+        let permissions = self.permissions.load();
+
+        // Filter other bots unless explicitly allowed
+        if message.author.bot && !permissions.allow_bot_messages {
+            return;
+        }
+
+        // DM filter: if no guild_id, it's a DM — only allow listed users
+        if message.guild_id.is_none() && !permissions.allows_dm(message.author.id.get()) {
+            return;
+        }
+
+        if let Some(filter) = &permissions.guild_filter {
+            if let Some(guild_id) = message.guild_id {
+                if !filter.contains(&guild_id.get()) {
+                    return;
+                }
+            }
+        }
+
+        let mut content = extract_content(&message);
+        let (mut metadata, formatted_author) = build_metadata(ctx, &message, bot_user_id).await;
+        if mentions_bot {
+            metadata.insert("discord_mentions_bot".into(), true.into());
+        }
+        if let Some(guild_id) = message.guild_id
+            && let Some(emojis) = self.guild_emojis.read().await.get(&guild_id)
+            && !emojis.is_empty()
+        {
+            let names: Vec<&str> = emojis
+                .iter()
+                .take(emoji::MAX_LISTED)
+                .map(|emoji| emoji.name.as_str())
+                .collect();
+            metadata.insert("discord_custom_emojis".into(), names.into());
+        }
+        if !message.mention_roles.is_empty() {
+            let roles: Vec<u64> = message
+                .mention_roles
+                .iter()
+                .map(|role| role.get())
+                .collect();
+            metadata.insert("discord_mentioned_roles".into(), roles.into());
+        }
+        let parent_channel_id = metadata
+            .get("discord_parent_channel_id")
+            .and_then(|v| v.as_u64())
+            .map(ChannelId::new);
+        if parent_channel_id.is_some()
+            && self
+                .followed_threads
+                .read()
+                .await
+                .contains(&message.channel_id.get())
+        {
+            metadata.insert("discord_followed_thread".into(), true.into());
+        }
+        let conversation_id = build_conversation_id(
+            message.guild_id,
+            message.channel_id,
+            parent_channel_id,
+            message.author.id,
+        );
+        if let Some(parent_id) = parent_channel_id
+            && let Some(forum) = forum_channel(ctx, parent_id).await
+            && let Some(triage) = mark_forum_post(
+                &permissions.forums,
+                &forum,
+                &message,
+                arrival != Arrival::Live,
+                &mut metadata,
+            )
+        {
+            let tags = metadata
+                .get(FORUM_TAGS_KEY)
+                .and_then(|value| serde_json::from_value::<Vec<String>>(value.clone()).ok())
+                .unwrap_or_default();
+            content = prepend_text(content, &triage.instruction(&tags));
+        }
+        if let Arrival::CaughtUp(missed) = arrival {
+            content = prepend_text(content, &catch_up::instruction(missed));
+            metadata.insert(CATCH_UP_KEY.into(), true.into());
+        }
+
+        // Channel filter: allow if the channel ID or its parent (for threads) is in the allowlist
+        if let Some(guild_id) = message.guild_id {
+            if let Some(allowed_channels) = permissions.channel_filter.get(&guild_id.get()) {
+                if !allowed_channels.is_empty() {
+                    let parent_channel_id = metadata
+                        .get("discord_parent_channel_id")
+                        .and_then(|v| v.as_u64());
+
+                    let direct_match = allowed_channels.contains(&message.channel_id.get());
+                    let parent_match =
+                        parent_channel_id.is_some_and(|pid| allowed_channels.contains(&pid));
+
+                    if !direct_match && !parent_match {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let guild_id = message.guild_id.map(|id| id.get());
+        if let Some(rate_limit) = permissions
+            .rate_limit_for(guild_id)
+            .filter(|_| arrival == Arrival::Live)
+        {
+            let user_id = message.author.id.to_string();
+            if let RateLimitDecision::Limited {
+                retry_after,
+                notify,
+            } = self.rate_limiter.check(
+                &rate_limit_key(&permissions, guild_id, &user_id),
+                rate_limit,
+            ) {
+                tracing::debug!(
+                    user_id = %user_id,
+                    retry_after_secs = retry_after.as_secs(),
+                    "discord user rate limited, dropping message"
+                );
+                if notify {
+                    let text = rate_limit::cooldown_message(rate_limit, retry_after);
+                    if let Err(error) = message.reply(&ctx.http, text).await {
+                        tracing::warn!(%error, "failed to send rate limit notice");
+                    }
+                }
+                return;
+            }
+        }
+
+        if edited {
+            metadata.insert(MESSAGE_EDIT_KEY.into(), true.into());
+        }
+        mark_private_memory(&permissions, message.guild_id, &mut metadata);
+        mark_expensive_tools(
+            &permissions,
+            message.guild_id,
+            message
+                .member
+                .as_ref()
+                .map(|member| member.roles.as_slice()),
+            &mut metadata,
+        );
+        let mut inbound = InboundMessage {
+            id: message.id.to_string(),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: message.author.id.to_string(),
+            agent_id: None,
+            content,
+            timestamp: *message.timestamp,
+            metadata,
+            formatted_author: Some(formatted_author),
+        };
+        // Missed messages are context for the agent's next turn, not new
+        // requests to answer late.
+        if arrival == Arrival::Backfilled {
+            listening::mark_passive(&mut inbound);
+        }
+        if !edited {
+            self.last_seen
+                .write()
+                .await
+                .insert(message.channel_id, (message.guild_id, message.id));
+        }
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send inbound message from Discord (receiver dropped)"
+            );
+        }
+    }
+
+    /// Forward the messages sent in recently active channels since the last
+    /// one seen, oldest first, as context only. With `summarize`, the last
+    /// in each channel asks the agent to summarize them instead.
+    async fn backfill(&self, ctx: &Context, summarize: bool) {
+        let targets = backfill_targets(&*self.last_seen.read().await);
+        let mut count = 0;
+        for (channel_id, (guild_id, after)) in targets {
+            let mut messages = match channel_id
+                .messages(
+                    &ctx.http,
+                    GetMessages::new()
+                        .after(after)
+                        .limit(BACKFILL_MESSAGE_LIMIT),
+                )
+                .await
+            {
+                Ok(messages) => messages,
+                Err(error) => {
+                    tracing::warn!(%error, %channel_id, "failed to fetch missed discord messages");
+                    continue;
+                }
+            };
+            messages.sort_by_key(|message| message.id);
+            let missed = messages.len();
+            for (index, mut message) in messages.into_iter().enumerate() {
+                // Messages fetched over HTTP don't say which guild they're in.
+                message.guild_id = message.guild_id.or(guild_id);
+                let arrival = if summarize && index + 1 == missed {
+                    Arrival::CaughtUp(missed)
+                } else {
+                    Arrival::Backfilled
+                };
+                self.forward_message(ctx, message, arrival).await;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            tracing::info!(count, "backfilled discord messages missed while offline");
+        }
+    }
+
+    /// The conversation a reaction belongs to, and the parent channel if it's
+    /// in a thread. None if the guild or DM gates reject it.
+    ///
+    /// The channel filter isn't applied: a cancel only reaches channels that
+    /// already have a running agent, and controls and sentiment only act on
+    /// the bot's own messages.
+    async fn reaction_conversation(
+        &self,
+        ctx: &Context,
+        permissions: &DiscordPermissions,
+        reaction: &Reaction,
+        user_id: UserId,
+    ) -> Option<(String, Option<ChannelId>)> {
+        let parent_channel_id = match reaction.guild_id {
+            Some(guild_id) => {
+                if let Some(filter) = &permissions.guild_filter
+                    && !filter.contains(&guild_id.get())
+                {
+                    return None;
+                }
+                // Threads are their own conversations, keyed under the parent.
+                reaction
+                    .channel_id
+                    .to_channel(&ctx.http)
+                    .await
+                    .ok()
+                    .and_then(|channel| channel.guild())
+                    .filter(|channel| channel.thread_metadata.is_some())
+                    .and_then(|channel| channel.parent_id)
+            }
+            None => {
+                if !permissions.allows_dm(user_id.get()) {
+                    return None;
+                }
+                None
+            }
+        };
+        let conversation_id = build_conversation_id(
+            reaction.guild_id,
+            reaction.channel_id,
+            parent_channel_id,
+            user_id,
+        );
+        Some((conversation_id, parent_channel_id))
+    }
+
+    /// Pass a reaction with a sentiment on one of the bot's messages to the
+    /// agents, which record it. Unlike controls, anyone may react.
+    async fn handle_sentiment(
+        &self,
+        ctx: &Context,
+        reaction: &Reaction,
+        emoji: &str,
+        score: i64,
+        removed: bool,
+    ) {
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        let Some(bot_user_id) = *self.bot_user_id_slot.read().await else {
+            return;
+        };
+        if user_id == bot_user_id {
+            return;
+        }
+        let permissions = self.permissions.load();
+        let Some((conversation_id, _)) = self
+            .reaction_conversation(ctx, &permissions, reaction, user_id)
+            .await
+        else {
+            return;
+        };
+        let target = match reaction.message(&ctx.http).await {
+            Ok(target) => target,
+            Err(error) => {
+                tracing::warn!(%error, "failed to fetch message for reaction sentiment");
+                return;
+            }
+        };
+        if !sent_by_bot(&target, Some(bot_user_id), &*self.webhooks.read().await) {
+            return;
+        }
+
+        let sentiment = SentimentReaction {
+            target_message_id: target.id.to_string(),
+            emoji: emoji.to_string(),
+            score,
+            removed,
+        };
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            REACTION_SENTIMENT_KEY.into(),
+            serde_json::to_value(&sentiment).unwrap_or_default(),
+        );
+        metadata.insert(
+            "discord_channel_id".into(),
+            reaction.channel_id.get().into(),
+        );
+        metadata.insert("discord_user_id".into(), user_id.get().into());
+
+        let inbound = InboundMessage {
+            id: format!("{}:sentiment:{}", target.id, user_id),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: user_id.to_string(),
+            agent_id: None,
+            content: MessageContent::Text(String::new()),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: None,
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send reaction sentiment from Discord (receiver dropped)"
+            );
+        }
+    }
+
+    /// Act on a control reaction to one of the bot's messages. Deletes are
+    /// carried out here; regenerate and expand go to the channel as an
+    /// instruction. All of them reach the channel so they're recorded.
+    async fn handle_control(
+        &self,
+        ctx: &Context,
+        reaction: &Reaction,
+        control: ReactionControl,
+        conversation_id: String,
+        parent_channel_id: Option<ChannelId>,
+    ) {
+        let Some(user_id) = reaction.user_id else {
+            return;
+        };
+        let Some(bot_user_id) = *self.bot_user_id_slot.read().await else {
+            return;
+        };
+        let target = match reaction.message(&ctx.http).await {
+            Ok(target) => target,
+            Err(error) => {
+                tracing::warn!(%error, "failed to fetch message for reaction control");
+                return;
+            }
+        };
+        if !sent_by_bot(&target, Some(bot_user_id), &*self.webhooks.read().await) {
+            return;
+        }
+
+        let permissions = self.permissions.load();
+        let guild_id = reaction.guild_id.map(|id| id.get());
+        if control != ReactionControl::Delete
+            && let Some(rate_limit) = permissions.rate_limit_for(guild_id)
+            && let RateLimitDecision::Limited { .. } = self.rate_limiter.check(
+                &rate_limit_key(&permissions, guild_id, &user_id.to_string()),
+                rate_limit,
+            )
+        {
+            tracing::debug!(user_id = %user_id, "discord user rate limited, dropping reaction control");
+            return;
+        }
+
+        if control == ReactionControl::Delete
+            && let Err(error) = target.delete(&ctx.http).await
+        {
+            tracing::warn!(%error, "failed to delete message for reaction control");
+            return;
+        }
+
+        let display_name = reaction
+            .member
+            .as_ref()
+            .map(|member| member.display_name().to_string())
+            .unwrap_or_else(|| format!("<@{user_id}>"));
+        let formatted_author = format!("{} (<@{}>)", display_name, user_id);
+
+        let mut metadata = HashMap::new();
+        metadata.insert(REACTION_CONTROL_KEY.into(), control.as_str().into());
+        metadata.insert(CONTROL_TARGET_KEY.into(), target.id.to_string().into());
+        metadata.insert(
+            "discord_channel_id".into(),
+            reaction.channel_id.get().into(),
+        );
+        metadata.insert("discord_message_id".into(), target.id.get().into());
+        if let Some(guild_id) = reaction.guild_id {
+            metadata.insert("discord_guild_id".into(), guild_id.get().into());
+        }
+        if let Some(parent_id) = parent_channel_id {
+            metadata.insert("discord_is_thread".into(), true.into());
+            metadata.insert("discord_parent_channel_id".into(), parent_id.get().into());
+        }
+        metadata.insert("discord_user_id".into(), user_id.get().into());
+        metadata.insert("sender_display_name".into(), display_name.into());
+
+        mark_private_memory(&permissions, reaction.guild_id, &mut metadata);
+        mark_expensive_tools(
+            &permissions,
+            reaction.guild_id,
+            reaction
+                .member
+                .as_ref()
+                .map(|member| member.roles.as_slice()),
+            &mut metadata,
+        );
+        let inbound = InboundMessage {
+            id: format!("{}:{}:{}", target.id, control.as_str(), user_id),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: user_id.to_string(),
+            agent_id: None,
+            content: MessageContent::Text(control.instruction(&target.content)),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(formatted_author),
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send reaction control from Discord (receiver dropped)"
+            );
+        }
+    }
+
+    /// Turn a slash command into an inbound message. The interaction is
+    /// deferred first so Discord shows "thinking…" while the agent works.
+    async fn handle_command(&self, ctx: &Context, command: CommandInteraction) {
+        let user = &command.user;
+        let permissions = self.permissions.load();
+
+        let allowed = match command.guild_id {
+            None => permissions.allows_dm(user.id.get()),
+            Some(guild_id) => {
+                let guild_allowed = permissions
+                    .guild_filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.contains(&guild_id.get()));
+                let parent_id = command.channel.as_ref().and_then(thread_parent);
+                let channel_allowed =
+                    permissions
+                        .channel_filter
+                        .get(&guild_id.get())
+                        .is_none_or(|allowed| {
+                            allowed.is_empty()
+                                || allowed.contains(&command.channel_id.get())
+                                || parent_id.is_some_and(|pid| allowed.contains(&pid.get()))
+                        });
+                guild_allowed && channel_allowed
+            }
+        };
+        if !allowed {
+            respond_ephemeral(ctx, &command, "I'm not available in this channel.").await;
+            return;
+        }
+        let required = command_permission(&command.data.name);
+        let private = required.is_some();
+        if let Some(required) = required
+            && !member_allows(
+                &permissions,
+                required,
+                command.guild_id,
+                command
+                    .member
+                    .as_ref()
+                    .map(|member| member.roles.as_slice()),
+            )
+        {
+            respond_ephemeral(
+                ctx,
+                &command,
+                "You don't have permission to use this command.",
+            )
+            .await;
+            return;
+        }
+
+        let guild_id = command.guild_id.map(|id| id.get());
+        if let Some(rate_limit) = permissions.rate_limit_for(guild_id)
+            && let RateLimitDecision::Limited { retry_after, .. } = self.rate_limiter.check(
+                &rate_limit_key(&permissions, guild_id, &user.id.to_string()),
+                rate_limit,
+            )
+        {
+            let text = rate_limit::cooldown_message(rate_limit, retry_after);
+            respond_ephemeral(ctx, &command, &text).await;
+            return;
+        }
+
+        let deferred = if private {
+            command.defer_ephemeral(&ctx.http).await
+        } else {
+            command.defer(&ctx.http).await
+        };
+        if let Err(error) = deferred {
+            tracing::warn!(%error, command = %command.data.name, "failed to defer slash command");
+            return;
+        }
+
+        let options: HashMap<&str, &str> = command
+            .data
+            .options()
+            .into_iter()
+            .filter_map(|option| match option.value {
+                ResolvedValue::String(value) => Some((option.name, value)),
+                _ => None,
+            })
+            .collect();
+        let text = command_text(&command.data.name, &options);
+
+        let parent_channel_id = command.channel.as_ref().and_then(thread_parent);
+        let conversation_id = build_conversation_id(
+            command.guild_id,
+            command.channel_id,
+            parent_channel_id,
+            user.id,
+        );
+
+        let display_name = command
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.clone())
+            .or_else(|| user.global_name.clone())
+            .unwrap_or_else(|| user.name.clone());
+        let formatted_author = format!("{} (<@{}>)", display_name, user.id);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("discord_channel_id".into(), command.channel_id.get().into());
+        if let Some(guild_id) = command.guild_id {
+            metadata.insert("discord_guild_id".into(), guild_id.get().into());
+        }
+        if let Some(name) = command
+            .channel
+            .as_ref()
+            .and_then(|channel| channel.name.clone())
+        {
+            metadata.insert("discord_channel_name".into(), name.into());
+        }
+        if let Some(parent_id) = parent_channel_id {
+            metadata.insert("discord_is_thread".into(), true.into());
+            metadata.insert("discord_parent_channel_id".into(), parent_id.get().into());
+            if self
+                .followed_threads
+                .read()
+                .await
+                .contains(&command.channel_id.get())
+            {
+                metadata.insert("discord_followed_thread".into(), true.into());
+            }
+        }
+        metadata.insert("discord_user_id".into(), user.id.get().into());
+        metadata.insert("sender_display_name".into(), display_name.into());
+        metadata.insert(
+            "discord_user_mention".into(),
+            serde_json::Value::String(format!("<@{}>", user.id)),
+        );
+        metadata.insert(
+            "discord_interaction_token".into(),
+            command.token.clone().into(),
+        );
+        metadata.insert("discord_command".into(), command.data.name.clone().into());
+        if private {
+            metadata.insert("discord_interaction_ephemeral".into(), true.into());
+        }
+        match command.data.name.as_str() {
+            "status" => {
+                metadata.insert(STATUS_REQUEST_KEY.into(), true.into());
+            }
+            "config" => {
+                metadata.insert(CONFIG_REQUEST_KEY.into(), true.into());
+            }
+            "settings" => {
+                metadata.insert(SETTINGS_REQUEST_KEY.into(), serde_json::json!(options));
+            }
+            _ => {}
+        }
+
+        mark_private_memory(&permissions, command.guild_id, &mut metadata);
+        mark_expensive_tools(
+            &permissions,
+            command.guild_id,
+            command
+                .member
+                .as_ref()
+                .map(|member| member.roles.as_slice()),
+            &mut metadata,
+        );
+        let inbound = InboundMessage {
+            id: command.id.to_string(),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: user.id.to_string(),
+            agent_id: None,
+            content: MessageContent::Text(text),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(formatted_author),
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send slash command from Discord (receiver dropped)"
+            );
+        }
+    }
+}
+
 // -- Helper functions --
 
-fn build_conversation_id(message: &Message) -> String {
-    match message.guild_id {
-        Some(guild_id) => format!("discord:{}:{}", guild_id, message.channel_id),
-        None => format!("discord:dm:{}", message.author.id),
+/// The slash commands registered when `slash_commands` is enabled.
+fn slash_commands() -> Vec<CreateCommand> {
+    vec![
+        CreateCommand::new("ask")
+            .description("Ask the agent something")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "question", "Your question")
+                    .required(true),
+            ),
+        CreateCommand::new("summarize")
+            .description("Summarize the recent conversation in this channel")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "focus",
+                "What the summary should focus on",
+            )),
+        CreateCommand::new("status").description("Show what the agent is working on"),
+        CreateCommand::new("config").description("Show the agent settings for this channel"),
+        CreateCommand::new("settings")
+            .description("Change the agent settings for this channel")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "listening",
+                    "Which messages the agent answers",
+                )
+                .add_string_choice("Every message", "all")
+                .add_string_choice("Only mentions and replies", "mention_only")
+                .add_string_choice("Mentions and keywords", "keyword")
+                .add_string_choice("Mentions and some others at random", "probabilistic")
+                .add_string_choice("As configured", "default"),
+            )
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "verbosity",
+                    "How long the agent's replies are",
+                )
+                .add_string_choice("Brief", "brief")
+                .add_string_choice("Normal", "normal")
+                .add_string_choice("Detailed", "detailed")
+                .add_string_choice("As configured", "default"),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "agent",
+                "ID of the agent that handles this channel, or \"default\"",
+            )),
+        CreateCommand::new("forget")
+            .description("Ask the agent to forget something")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "what", "What to forget")
+                    .required(true),
+            ),
+    ]
+}
+
+/// The message text the agent sees for a slash command. `/status`,
+/// `/config`, and `/settings` are answered without a turn, so they have none.
+fn command_text(name: &str, options: &HashMap<&str, &str>) -> String {
+    match name {
+        "ask" => options
+            .get("question")
+            .copied()
+            .unwrap_or_default()
+            .to_string(),
+        "summarize" => match options
+            .get("focus")
+            .filter(|focus| !focus.trim().is_empty())
+        {
+            Some(focus) => {
+                format!("Summarize the recent conversation in this channel, focusing on {focus}.")
+            }
+            None => "Summarize the recent conversation in this channel.".to_string(),
+        },
+        "forget" => format!(
+            "Forget this: {}",
+            options.get("what").copied().unwrap_or_default()
+        ),
+        _ => String::new(),
     }
 }
 
+async fn respond_ephemeral(ctx: &Context, command: &CommandInteraction, text: &str) {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(text)
+            .ephemeral(true),
+    );
+    if let Err(error) = command.create_response(&ctx.http, response).await {
+        tracing::warn!(%error, "failed to respond to slash command");
+    }
+}
+
+/// Mark a DM whose memories stay private to it, when the config says so.
+fn mark_private_memory(
+    permissions: &DiscordPermissions,
+    guild_id: Option<GuildId>,
+    metadata: &mut HashMap<String, serde_json::Value>,
+) {
+    if guild_id.is_none() && permissions.dms.private_memory {
+        metadata.insert(PRIVATE_MEMORY_KEY.into(), true.into());
+    }
+}
+
+/// The role permission a slash command needs, if any. `/settings` changes
+/// how the channel behaves, so it's a channel control, not an admin command.
+fn command_permission(name: &str) -> Option<RolePermission> {
+    match name {
+        "settings" => Some(RolePermission::ChannelControls),
+        name if ADMIN_COMMANDS.contains(&name) => Some(RolePermission::AdminCommands),
+        _ => None,
+    }
+}
+
+/// Whether the sender has a role permission. DMs aren't gated, and a guild
+/// member Discord leaves out of the event has no roles.
+fn member_allows(
+    permissions: &DiscordPermissions,
+    permission: RolePermission,
+    guild_id: Option<GuildId>,
+    roles: Option<&[RoleId]>,
+) -> bool {
+    if guild_id.is_none() {
+        return true;
+    }
+    let roles: Vec<u64> = roles
+        .unwrap_or_default()
+        .iter()
+        .map(|role| role.get())
+        .collect();
+    permissions.roles.allows(permission, &roles)
+}
+
+/// Whether whoever reacted can manage other members' messages in the
+/// channel, going by the cache. Threads take their parent's permissions.
+fn is_moderator(ctx: &Context, reaction: &Reaction, parent_channel_id: Option<ChannelId>) -> bool {
+    let (Some(guild_id), Some(member)) = (reaction.guild_id, &reaction.member) else {
+        return false;
+    };
+    let Some(guild) = ctx.cache.guild(guild_id) else {
+        return false;
+    };
+    let channel_id = parent_channel_id.unwrap_or(reaction.channel_id);
+    guild
+        .channels
+        .get(&channel_id)
+        .is_some_and(|channel| guild.user_permissions_in(channel, member).manage_messages())
+}
+
+/// Mark a message whose sender may not start workers.
+fn mark_expensive_tools(
+    permissions: &DiscordPermissions,
+    guild_id: Option<GuildId>,
+    roles: Option<&[RoleId]>,
+    metadata: &mut HashMap<String, serde_json::Value>,
+) {
+    if !member_allows(permissions, RolePermission::ExpensiveTools, guild_id, roles) {
+        metadata.insert(EXPENSIVE_TOOLS_KEY.into(), false.into());
+    }
+}
+
+/// Rate limiter key for a user. Guilds with their own limit count a user's
+/// messages there separately from everywhere else.
+fn rate_limit_key(
+    permissions: &DiscordPermissions,
+    guild_id: Option<u64>,
+    user_id: &str,
+) -> String {
+    match guild_id.filter(|id| permissions.guild_rate_limits.contains_key(id)) {
+        Some(guild_id) => format!("{guild_id}:{user_id}"),
+        None => user_id.to_string(),
+    }
+}
+
+/// Conversation ID for a Discord channel. Threads get their own
+/// conversation, with the thread ID composed under the parent channel's:
+/// `discord:{guild_id}:{parent_id}:{thread_id}`.
+fn build_conversation_id(
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    parent_channel_id: Option<ChannelId>,
+    user_id: UserId,
+) -> String {
+    match (guild_id, parent_channel_id) {
+        (Some(guild_id), Some(parent_id)) => {
+            format!("discord:{guild_id}:{parent_id}:{channel_id}")
+        }
+        (Some(guild_id), None) => format!("discord:{guild_id}:{channel_id}"),
+        (None, _) => format!("discord:dm:{user_id}"),
+    }
+}
+
+/// The guild and channel of a server conversation ID. For threads, the
+/// channel is the thread itself. None for DMs.
+fn guild_channel(conversation_id: &str) -> Option<(GuildId, ChannelId)> {
+    let mut parts = conversation_id.strip_prefix("discord:")?.split(':');
+    let guild_id = parts.next()?.parse::<u64>().ok()?;
+    let channel_id = parts.last()?.parse::<u64>().ok()?;
+    Some((GuildId::new(guild_id), ChannelId::new(channel_id)))
+}
+
+/// Parse a Discord snowflake ID given as a string.
+fn parse_id(id: &str, kind: &str) -> anyhow::Result<u64> {
+    id.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&id| id != 0)
+        .with_context(|| format!("invalid discord {kind} id '{id}'"))
+}
+
+/// The parent channel of a thread. None for other channels, whose
+/// `parent_id` is their category.
+fn thread_parent(channel: &PartialChannel) -> Option<ChannelId> {
+    channel
+        .thread_metadata
+        .is_some()
+        .then_some(channel.parent_id)
+        .flatten()
+}
+
 fn extract_content(message: &Message) -> MessageContent {
     let resolved_content = resolve_mentions(&message.content, &message.mentions);
 
@@ -763,6 +2467,75 @@ fn extract_content(message: &Message) -> MessageContent {
     }
 }
 
+/// The forum a post's parent channel is, or None if the parent isn't a forum.
+/// Looked up in the cache first, since every message in a post needs it.
+async fn forum_channel(ctx: &Context, parent_id: ChannelId) -> Option<GuildChannel> {
+    let cached = ctx
+        .cache
+        .channel(parent_id)
+        .map(|channel| GuildChannel::clone(&channel));
+    let channel = match cached {
+        Some(channel) => channel,
+        None => parent_id.to_channel(&ctx.http).await.ok()?.guild()?,
+    };
+    (channel.kind == ChannelType::Forum).then_some(channel)
+}
+
+/// Mark a message in a forum post with its forum and, if the forum config
+/// covers it, the tags the agent may apply. Returns the triage for a new
+/// post's opening message, which has the same ID as the post.
+fn mark_forum_post(
+    config: &ForumConfig,
+    forum: &GuildChannel,
+    message: &Message,
+    edited: bool,
+    metadata: &mut HashMap<String, serde_json::Value>,
+) -> Option<ForumTriage> {
+    metadata.insert("discord_forum_id".into(), forum.id.get().into());
+    metadata.insert("discord_forum_name".into(), forum.name.clone().into());
+    if !config.covers(forum.id.get()) {
+        return None;
+    }
+
+    let tags: Vec<String> = forum
+        .available_tags
+        .iter()
+        .map(|tag| tag.name.clone())
+        .collect();
+    if config.auto_tag && !tags.is_empty() {
+        metadata.insert(FORUM_TAGS_KEY.into(), tags.into());
+    }
+
+    if !config.triage || edited || message.id.get() != message.channel_id.get() {
+        return None;
+    }
+    metadata.insert(FORUM_TRIAGE_KEY.into(), true.into());
+    Some(ForumTriage {
+        forum: forum.name.clone(),
+        title: metadata
+            .get("discord_channel_name")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        routing: config.routing.clone(),
+    })
+}
+
+/// Put `prefix` ahead of a message's text, on its own paragraph.
+fn prepend_text(content: MessageContent, prefix: &str) -> MessageContent {
+    match content {
+        MessageContent::Text(text) => MessageContent::Text(format!("{prefix}\n\n{text}")),
+        MessageContent::Media { text, attachments } => MessageContent::Media {
+            text: Some(match text {
+                Some(text) => format!("{prefix}\n\n{text}"),
+                None => prefix.to_string(),
+            }),
+            attachments,
+        },
+        other => other,
+    }
+}
+
 /// Replace raw Discord mention syntax (`<@ID>` and `<@!ID>`) with readable display names.
 /// Serenity provides resolved `User` objects in `message.mentions` for every mention in the text.
 fn resolve_mentions(content: &str, mentions: &[User]) -> String {
@@ -783,6 +2556,7 @@ fn resolve_mentions(content: &str, mentions: &[User]) -> String {
 async fn build_metadata(
     ctx: &Context,
     message: &Message,
+    bot_user_id: Option<UserId>,
 ) -> (HashMap<String, serde_json::Value>, String) {
     let mut metadata = HashMap::new();
     metadata.insert("discord_channel_id".into(), message.channel_id.get().into());
@@ -845,6 +2619,10 @@ async fn build_metadata(
                 if let Some(parent_id) = guild_channel.parent_id {
                     metadata.insert("discord_parent_channel_id".into(), parent_id.get().into());
                 }
+                // The bot follows threads it started, even across restarts.
+                if bot_user_id.is_some_and(|id| guild_channel.owner_id == Some(id)) {
+                    metadata.insert("discord_followed_thread".into(), true.into());
+                }
             }
         }
     }
@@ -872,42 +2650,6 @@ async fn build_metadata(
     (metadata, formatted_author)
 }
 
-/// Split a message into chunks that fit within Discord's 2000 char limit.
-/// Tries to split at newlines, then spaces, then hard-cuts.
-fn split_message(text: &str, max_len: usize) -> Vec<String> {
-    if text.len() <= max_len {
-        return vec![text.to_string()];
-    }
-
-    let mut chunks = Vec::new();
-    let mut remaining = text;
-
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining.to_string());
-            break;
-        }
-
-        let safe_max = {
-            let mut i = max_len.min(remaining.len());
-            while !remaining.is_char_boundary(i) {
-                i -= 1;
-            }
-            i
-        };
-
-        let split_at = remaining[..safe_max]
-            .rfind('\n')
-            .or_else(|| remaining[..safe_max].rfind(' '))
-            .unwrap_or(safe_max);
-
-        chunks.push(remaining[..split_at].to_string());
-        remaining = remaining[split_at..].trim_start();
-    }
-
-    chunks
-}
-
 // --- Rich Message Builders ---
 
 fn build_embed(card: &crate::Card) -> CreateEmbed {
@@ -1001,6 +2743,96 @@ fn build_action_row(elements: &crate::InteractiveElements) -> CreateActionRow {
     }
 }
 
+/// The label of the button clicked, or the labels of the options selected,
+/// from the message's components.
+fn component_label(rows: &[ActionRow], custom_id: &str, values: &[String]) -> Option<String> {
+    rows.iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::Button(button) => match &button.data {
+                ButtonKind::NonLink { custom_id: id, .. } if id == custom_id => {
+                    button.label.clone()
+                }
+                _ => None,
+            },
+            ActionRowComponent::SelectMenu(menu)
+                if menu.custom_id.as_deref() == Some(custom_id) =>
+            {
+                let labels: Vec<&str> = menu
+                    .options
+                    .iter()
+                    .filter(|option| values.contains(&option.value))
+                    .map(|option| option.label.as_str())
+                    .collect();
+                (!labels.is_empty()).then(|| labels.join(", "))
+            }
+            _ => None,
+        })
+}
+
+/// The message's components with the row holding `custom_id` disabled. A
+/// select menu in it keeps the options chosen as its selection. Link buttons
+/// stay usable.
+fn close_row(rows: &[ActionRow], custom_id: &str, values: &[String]) -> Vec<CreateActionRow> {
+    rows.iter()
+        .filter_map(|row| {
+            let used = row.components.iter().any(|component| match component {
+                ActionRowComponent::Button(button) => matches!(
+                    &button.data,
+                    ButtonKind::NonLink { custom_id: id, .. } if id == custom_id
+                ),
+                ActionRowComponent::SelectMenu(menu) => {
+                    menu.custom_id.as_deref() == Some(custom_id)
+                }
+                _ => false,
+            });
+
+            let mut buttons = Vec::new();
+            for component in &row.components {
+                match component {
+                    ActionRowComponent::Button(button) => {
+                        let link = matches!(button.data, ButtonKind::Link { .. });
+                        buttons.push(
+                            CreateButton::from(button.clone())
+                                .disabled(button.disabled || (used && !link)),
+                        );
+                    }
+                    ActionRowComponent::SelectMenu(menu) => {
+                        let options = menu
+                            .options
+                            .iter()
+                            .map(|option| {
+                                let mut created =
+                                    CreateSelectMenuOption::new(&option.label, &option.value)
+                                        .default_selection(if used {
+                                            values.contains(&option.value)
+                                        } else {
+                                            option.default
+                                        });
+                                if let Some(description) = &option.description {
+                                    created = created.description(description);
+                                }
+                                created
+                            })
+                            .collect();
+                        let mut created = CreateSelectMenu::new(
+                            menu.custom_id.clone().unwrap_or_default(),
+                            CreateSelectMenuKind::String { options },
+                        )
+                        .disabled(menu.disabled || used);
+                        if let Some(placeholder) = &menu.placeholder {
+                            created = created.placeholder(placeholder);
+                        }
+                        return Some(CreateActionRow::SelectMenu(created));
+                    }
+                    ActionRowComponent::InputText(_) => {}
+                }
+            }
+            (!buttons.is_empty()).then_some(CreateActionRow::Buttons(buttons))
+        })
+        .collect()
+}
+
 fn build_poll(
     poll: &crate::Poll,
 ) -> serenity::builder::CreatePoll<serenity::builder::create_poll::Ready> {
@@ -1075,6 +2907,220 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_used_component_row_is_labelled_and_closed() {
+        let rows: Vec<ActionRow> = serde_json::from_value(serde_json::json!([
+            {"type": 1, "components": [
+                {"type": 2, "style": 1, "label": "Yes", "custom_id": "yes"},
+                {"type": 2, "style": 5, "label": "Docs", "url": "https://example.com"}
+            ]},
+            {"type": 1, "components": [
+                {"type": 3, "custom_id": "plan", "options": [
+                    {"label": "Pro", "value": "pro"},
+                    {"label": "Team", "value": "team"}
+                ]}
+            ]}
+        ]))
+        .expect("valid components");
+
+        assert_eq!(component_label(&rows, "yes", &[]).as_deref(), Some("Yes"));
+        assert_eq!(
+            component_label(&rows, "plan", &["team".into()]).as_deref(),
+            Some("Team")
+        );
+
+        let closed = serde_json::to_value(close_row(&rows, "yes", &[])).unwrap();
+        assert_eq!(closed[0]["components"][0]["disabled"], true);
+        assert_eq!(closed[0]["components"][1]["disabled"], false);
+        assert_eq!(closed[1]["components"][0]["disabled"], false);
+
+        let closed = serde_json::to_value(close_row(&rows, "plan", &["team".into()])).unwrap();
+        let menu = &closed[1]["components"][0];
+        assert_eq!(menu["disabled"], true);
+        assert_eq!(menu["options"][1]["default"], true);
+        assert_eq!(closed[0]["components"][0]["disabled"], false);
+    }
+
+    #[test]
+    fn test_backfill_targets_most_recent_channels_and_fatal_errors() {
+        let mut last_seen: HashMap<ChannelId, SeenMessage> = (1..=60)
+            .map(|id| (ChannelId::new(id), (None, MessageId::new(id * 10))))
+            .collect();
+        last_seen.insert(ChannelId::new(3), (None, MessageId::new(10_000)));
+
+        let targets = backfill_targets(&last_seen);
+        assert_eq!(targets.len(), BACKFILL_MAX_CHANNELS);
+        assert_eq!(targets[0].0, ChannelId::new(3));
+        assert_eq!(targets[1].0, ChannelId::new(60));
+        assert!(!targets.iter().any(|(channel_id, _)| channel_id.get() == 4));
+
+        use serenity::gateway::GatewayError;
+        assert!(is_fatal(&serenity::Error::Gateway(
+            GatewayError::InvalidAuthentication
+        )));
+        assert!(!is_fatal(&serenity::Error::Gateway(
+            GatewayError::ReconnectFailure
+        )));
+    }
+
+    #[test]
+    fn test_catch_up_starts_from_latest_recorded_message_per_channel() {
+        let recorded = |metadata: &str| ConversationMessage {
+            id: "row".into(),
+            channel_id: "discord:1:2".into(),
+            role: "user".into(),
+            sender_name: None,
+            sender_id: None,
+            content: String::new(),
+            metadata: Some(metadata.into()),
+            turn_id: None,
+            created_at: chrono::Utc::now(),
+        };
+        let adapter = DiscordAdapter::new(
+            "token",
+            Arc::new(ArcSwap::from_pointee(DiscordPermissions::default())),
+        )
+        .with_catch_up(&[
+            recorded(r#"{"discord_guild_id":1,"discord_channel_id":2,"discord_message_id":30}"#),
+            recorded(r#"{"discord_guild_id":1,"discord_channel_id":2,"discord_message_id":50}"#),
+            recorded(r#"{"discord_channel_id":7,"discord_message_id":40}"#),
+            recorded(r#"{"slack_channel_id":"C1"}"#),
+        ]);
+
+        assert_eq!(adapter.catch_up_from.len(), 2);
+        assert_eq!(
+            adapter.catch_up_from[&ChannelId::new(2)],
+            (Some(GuildId::new(1)), MessageId::new(50))
+        );
+        assert_eq!(
+            adapter.catch_up_from[&ChannelId::new(7)],
+            (None, MessageId::new(40))
+        );
+    }
+
+    #[test]
+    fn test_agent_identity_and_webhook_messages_count_as_own() {
+        let mut message = crate::InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1:2".into(),
+            sender_id: "9".into(),
+            agent_id: Some("support".into()),
+            content: MessageContent::Text("hi".into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::from([
+                ("discord_guild_id".to_string(), serde_json::json!(1)),
+                (AGENT_DISPLAY_NAME_KEY.to_string(), "x".repeat(100).into()),
+            ]),
+            formatted_author: None,
+        };
+        let on = DiscordPermissions {
+            agent_webhooks: true,
+            ..Default::default()
+        };
+
+        assert!(agent_identity(&DiscordPermissions::default(), &message).is_none());
+        let identity = agent_identity(&on, &message).expect("identity");
+        assert_eq!(identity.name.len(), WEBHOOK_USERNAME_LIMIT);
+        assert_eq!(identity.avatar_url, None);
+        message.metadata.remove("discord_guild_id");
+        assert!(agent_identity(&on, &message).is_none());
+
+        let webhook: Webhook = serde_json::from_value(serde_json::json!({
+            "id": "50", "type": 1, "channel_id": "2", "name": AGENT_WEBHOOK_NAME
+        }))
+        .expect("valid webhook");
+        let webhooks = HashMap::from([(ChannelId::new(2), webhook)]);
+        let mut sent = Message::default();
+        sent.author.id = UserId::new(7);
+        sent.webhook_id = Some(serenity::all::WebhookId::new(50));
+        assert!(sent_by_bot(&sent, Some(UserId::new(3)), &webhooks));
+        sent.webhook_id = None;
+        assert!(!sent_by_bot(&sent, Some(UserId::new(3)), &webhooks));
+        sent.author.id = UserId::new(3);
+        assert!(sent_by_bot(&sent, Some(UserId::new(3)), &webhooks));
+    }
+
+    #[test]
+    fn test_command_text_maps_slash_commands() {
+        let options = HashMap::from([("question", "when is the deploy?")]);
+        assert_eq!(command_text("ask", &options), "when is the deploy?");
+
+        let options = HashMap::from([("focus", "action items")]);
+        assert_eq!(
+            command_text("summarize", &options),
+            "Summarize the recent conversation in this channel, focusing on action items."
+        );
+        assert_eq!(
+            command_text("summarize", &HashMap::new()),
+            "Summarize the recent conversation in this channel."
+        );
+
+        let options = HashMap::from([("what", "my home address")]);
+        assert_eq!(
+            command_text("forget", &options),
+            "Forget this: my home address"
+        );
+        assert_eq!(command_text("status", &HashMap::new()), "");
+        assert_eq!(command_text("config", &HashMap::new()), "");
+        assert_eq!(slash_commands().len(), 6);
+    }
+
+    #[test]
+    fn test_settings_command_is_gated_on_channel_controls() {
+        let permissions = DiscordPermissions {
+            roles: crate::config::DiscordRolePermissions {
+                admin_commands: vec![10],
+                channel_controls: vec![20],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let guild = Some(GuildId::new(1));
+        let allows = |command: &str, role: u64| {
+            let roles = [RoleId::new(role)];
+            command_permission(command)
+                .is_none_or(|required| member_allows(&permissions, required, guild, Some(&roles)))
+        };
+
+        assert!(allows("settings", 20));
+        assert!(!allows("settings", 10));
+        for command in ["status", "config", "forget"] {
+            assert!(allows(command, 10));
+            assert!(!allows(command, 20));
+        }
+        assert!(allows("ask", 30));
+        assert!(member_allows(
+            &permissions,
+            RolePermission::ChannelControls,
+            None,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_thread_conversation_ids_nest_under_parent() {
+        let guild = Some(GuildId::new(1));
+        let user = UserId::new(9);
+        assert_eq!(
+            build_conversation_id(guild, ChannelId::new(2), None, user),
+            "discord:1:2"
+        );
+        assert_eq!(
+            build_conversation_id(guild, ChannelId::new(3), Some(ChannelId::new(2)), user),
+            "discord:1:2:3"
+        );
+        assert_eq!(
+            build_conversation_id(None, ChannelId::new(4), None, user),
+            "discord:dm:9"
+        );
+        assert_eq!(
+            guild_channel("discord:1:2:3"),
+            Some((GuildId::new(1), ChannelId::new(3)))
+        );
+        assert_eq!(guild_channel("discord:dm:9"), None);
+    }
+
     #[test]
     fn test_build_poll_limits() {
         let mut poll = Poll {
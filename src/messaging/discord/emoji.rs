@@ -0,0 +1,244 @@
+//! Guild custom emojis in replies, and replies sent as reactions.
+//!
+//! The agent writes a guild's custom emojis by name, like `:partyparrot:`,
+//! and the names are turned into Discord's markup before sending. With
+//! `reaction_replies` on, a reply that's nothing but a few emoji is added as
+//! reactions to the message it answers instead of posted.
+
+use crate::OutboundResponse;
+
+use serenity::all::{Emoji, EmojiId, ReactionType};
+
+/// Most custom emoji names shown to the agent per guild.
+pub(super) const MAX_LISTED: usize = 50;
+
+/// Most reactions a reply made only of emoji is sent as.
+const MAX_REACTIONS: usize = 3;
+
+/// A guild's custom emoji.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct CustomEmoji {
+    pub name: String,
+    pub id: EmojiId,
+    pub animated: bool,
+}
+
+impl CustomEmoji {
+    /// The guild's emojis the bot can use.
+    pub(super) fn usable<'a>(emojis: impl IntoIterator<Item = &'a Emoji>) -> Vec<Self> {
+        emojis
+            .into_iter()
+            .filter(|emoji| emoji.available)
+            .map(|emoji| Self {
+                name: emoji.name.clone(),
+                id: emoji.id,
+                animated: emoji.animated,
+            })
+            .collect()
+    }
+
+    fn markup(&self) -> String {
+        let prefix = if self.animated { "a" } else { "" };
+        format!("<{prefix}:{}:{}>", self.name, self.id)
+    }
+
+    fn reaction(&self) -> ReactionType {
+        ReactionType::Custom {
+            animated: self.animated,
+            id: self.id,
+            name: Some(self.name.clone()),
+        }
+    }
+}
+
+/// `text` with each `:name:` of a custom emoji written as Discord's markup.
+/// Emojis already in markup are left as they are.
+pub(super) fn resolve(text: &str, emojis: &[CustomEmoji]) -> String {
+    if emojis.is_empty() {
+        return text.to_string();
+    }
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        let (before, after) = rest.split_at(start);
+        let after = &after[1..];
+        resolved.push_str(before);
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        let in_markup = before.ends_with('<') || before.ends_with("<a");
+        let emoji = after[name_len..]
+            .starts_with(':')
+            .then(|| emojis.iter().find(|emoji| emoji.name == after[..name_len]))
+            .flatten()
+            .filter(|_| !in_markup);
+        match emoji {
+            Some(emoji) => {
+                resolved.push_str(&emoji.markup());
+                rest = &after[name_len + 1..];
+            }
+            None => {
+                resolved.push(':');
+                rest = after;
+            }
+        }
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+/// The response with custom emoji names in its text resolved.
+pub(super) fn resolve_response(
+    mut response: OutboundResponse,
+    emojis: &[CustomEmoji],
+) -> OutboundResponse {
+    match &mut response {
+        OutboundResponse::Text(text)
+        | OutboundResponse::StreamChunk(text)
+        | OutboundResponse::ThreadReply { text, .. }
+        | OutboundResponse::RichMessage { text, .. } => *text = resolve(text, emojis),
+        _ => {}
+    }
+    response
+}
+
+/// The reaction for an emoji the agent gave: a custom emoji by name, with or
+/// without colons, or in markup, else a unicode emoji.
+pub(super) fn reaction(emoji: &str, emojis: &[CustomEmoji]) -> ReactionType {
+    let emoji = emoji.trim();
+    let name = emoji.trim_matches(':');
+    if let Some(custom) = emojis.iter().find(|custom| custom.name == name) {
+        return custom.reaction();
+    }
+    ReactionType::try_from(emoji).unwrap_or_else(|_| ReactionType::Unicode(emoji.to_string()))
+}
+
+/// The reactions a reply stands for, if it's nothing but one to three emoji.
+/// Custom emojis count in markup, so resolve names first.
+pub(super) fn reply_reactions(text: &str) -> Option<Vec<ReactionType>> {
+    let mut reactions = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let len = if rest.starts_with('<') {
+            rest.find('>')? + 1
+        } else {
+            emoji_len(rest)?
+        };
+        reactions.push(ReactionType::try_from(&rest[..len]).ok()?);
+        if reactions.len() > MAX_REACTIONS {
+            return None;
+        }
+        rest = rest[len..].trim_start();
+    }
+    (!reactions.is_empty()).then_some(reactions)
+}
+
+/// Length in bytes of the unicode emoji `text` starts with, with its skin
+/// tone, variation selector, and joined emoji. None if it doesn't start with
+/// one.
+fn emoji_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next()?;
+    if first.is_alphanumeric() || !is_pictographic(first) {
+        return None;
+    }
+    let mut regional = is_regional_indicator(first);
+    while let Some(&(index, c)) = chars.peek() {
+        if c == '\u{200D}' {
+            chars.next();
+            // The joined emoji is part of this one.
+            chars.next();
+        } else if is_modifier(c) || (regional && is_regional_indicator(c)) {
+            regional = false;
+            chars.next();
+        } else {
+            return Some(index);
+        }
+    }
+    Some(text.len())
+}
+
+fn is_pictographic(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00A9}'
+            | '\u{00AE}'
+            | '\u{203C}'
+            | '\u{2049}'
+            | '\u{2122}'
+            | '\u{2139}'
+            | '\u{2194}'..='\u{21AA}'
+            | '\u{231A}'..='\u{23FF}'
+            | '\u{24C2}'
+            | '\u{25AA}'..='\u{27BF}'
+            | '\u{2934}'..='\u{2935}'
+            | '\u{2B05}'..='\u{2B55}'
+            | '\u{3030}'
+            | '\u{303D}'
+            | '\u{3297}'
+            | '\u{3299}'
+            | '\u{1F000}'..='\u{1FAFF}'
+    )
+}
+
+/// Variation selectors, keycaps, skin tones, and tags.
+fn is_modifier(c: char) -> bool {
+    matches!(
+        c,
+        '\u{FE0E}'
+            | '\u{FE0F}'
+            | '\u{20E3}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_emoji_names_resolve_and_emoji_replies_become_reactions() {
+        let emojis = vec![
+            CustomEmoji {
+                name: "party_parrot".into(),
+                id: EmojiId::new(42),
+                animated: true,
+            },
+            CustomEmoji {
+                name: "ship".into(),
+                id: EmojiId::new(7),
+                animated: false,
+            },
+        ];
+
+        assert_eq!(
+            resolve("Shipped :ship: at 10:30 :party_parrot: :unknown:", &emojis),
+            "Shipped <:ship:7> at 10:30 <a:party_parrot:42> :unknown:"
+        );
+        assert_eq!(resolve("<:ship:7>", &emojis), "<:ship:7>");
+        assert_eq!(reaction(":ship:", &emojis), emojis[1].reaction());
+        assert_eq!(reaction("👍", &emojis), ReactionType::Unicode("👍".into()));
+
+        let reactions = reply_reactions(&resolve("👍🏽 :ship: 🇸🇪", &emojis)).unwrap();
+        assert_eq!(
+            reactions,
+            vec![
+                ReactionType::Unicode("👍🏽".into()),
+                emojis[1].reaction(),
+                ReactionType::Unicode("🇸🇪".into()),
+            ]
+        );
+        assert_eq!(
+            reply_reactions("👨‍👩‍👧"),
+            Some(vec![ReactionType::Unicode("👨‍👩‍👧".into())])
+        );
+        assert!(reply_reactions("Nice 👍").is_none());
+        assert!(reply_reactions("👍👍👍👍").is_none());
+        assert!(reply_reactions("").is_none());
+    }
+}
@@ -0,0 +1,431 @@
+//! Voice channels: transcribe what people say, and speak replies.
+//!
+//! The bot joins the configured voice channels on connect and receives
+//! decoded audio per speaker. Speech is cut into utterances at pauses, each
+//! utterance is transcribed, and the text reaches the agent as a message from
+//! its speaker in the voice channel's text chat. Replies are posted there, and
+//! with `tts` enabled they're also spoken in the call.
+
+use super::build_conversation_id;
+use crate::config::{DiscordPermissions, VoiceConfig};
+use crate::{InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serenity::all::{ChannelId, Context, GuildId, UserId};
+use songbird::driver::DecodeMode;
+use songbird::{CoreEvent, Event, EventContext, Songbird};
+use tokio::sync::mpsc;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Songbird decodes to 48kHz interleaved stereo.
+const SAMPLE_RATE: usize = 48_000;
+const CHANNELS: usize = 2;
+
+/// Audio is sent for transcription as 16kHz mono, which is what whisper
+/// models work at.
+const TRANSCRIBE_RATE: usize = 16_000;
+
+/// Voice ticks arrive every 20ms. This many silent ones in a row end an
+/// utterance.
+const SILENCE_TICKS: u32 = 40;
+
+/// Utterances shorter than this are coughs and clicks, and aren't sent.
+const MIN_UTTERANCE_SAMPLES: usize = SAMPLE_RATE * CHANNELS / 2;
+
+/// Longer utterances are cut here and transcribed in pieces.
+const MAX_UTTERANCE_SAMPLES: usize = SAMPLE_RATE * CHANNELS * 30;
+
+/// The voice manager the Discord client is built with.
+pub(super) fn manager() -> Arc<Songbird> {
+    Songbird::serenity_from_config(songbird::Config::default().decode_mode(DecodeMode::Decode))
+}
+
+/// Join the configured voice channels and start transcribing them.
+pub(super) async fn join_configured(
+    ctx: &Context,
+    voice: &Songbird,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+    permissions: &Arc<ArcSwap<DiscordPermissions>>,
+) {
+    let config = permissions.load().voice.clone();
+    if !config.enabled {
+        return;
+    }
+
+    let http = reqwest::Client::new();
+    for channel_id in config.channels.into_iter().map(ChannelId::new) {
+        let guild_id = match channel_id.to_channel(&ctx.http).await {
+            Ok(channel) => match channel.guild() {
+                Some(channel) => channel.guild_id,
+                None => {
+                    tracing::warn!(%channel_id, "configured voice channel is not in a guild");
+                    continue;
+                }
+            },
+            Err(error) => {
+                tracing::warn!(%error, %channel_id, "failed to look up voice channel");
+                continue;
+            }
+        };
+        // Ready fires again on reconnect. The bot can only be in one voice
+        // channel per guild, so an existing call is the one joined before.
+        if voice.get(guild_id).is_some() {
+            continue;
+        }
+
+        let call = match voice.join(guild_id, channel_id).await {
+            Ok(call) => call,
+            Err(error) => {
+                tracing::warn!(%error, %channel_id, "failed to join voice channel");
+                continue;
+            }
+        };
+
+        let receiver = Receiver {
+            ctx: ctx.clone(),
+            guild_id,
+            channel_id,
+            inbound_tx: inbound_tx.clone(),
+            permissions: permissions.clone(),
+            http: http.clone(),
+            speakers: Arc::new(Mutex::new(HashMap::new())),
+            segmenter: Arc::new(Mutex::new(Segmenter::default())),
+        };
+        let mut call = call.lock().await;
+        call.add_global_event(CoreEvent::SpeakingStateUpdate.into(), receiver.clone());
+        call.add_global_event(CoreEvent::VoiceTick.into(), receiver);
+        tracing::info!(%guild_id, %channel_id, "joined discord voice channel");
+    }
+}
+
+/// Speak a reply in the call of the voice channel the message came from.
+pub(super) async fn speak(
+    voice: &Songbird,
+    config: &VoiceConfig,
+    message: &InboundMessage,
+    text: &str,
+) -> anyhow::Result<()> {
+    let guild_id = message
+        .metadata
+        .get("discord_guild_id")
+        .and_then(|v| v.as_u64())
+        .map(GuildId::new)
+        .context("missing discord_guild_id in metadata")?;
+    let call = voice
+        .get(guild_id)
+        .context("not in a voice channel in this guild")?;
+
+    let audio = synthesize(&reqwest::Client::new(), config, text).await?;
+    call.lock().await.play_input(audio.into());
+    Ok(())
+}
+
+/// Whether the message is a transcribed utterance.
+pub(super) fn is_voice(message: &InboundMessage) -> bool {
+    message
+        .metadata
+        .get("discord_voice")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Receives audio events for one call.
+#[derive(Clone)]
+struct Receiver {
+    ctx: Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    permissions: Arc<ArcSwap<DiscordPermissions>>,
+    http: reqwest::Client,
+    /// Who is behind each audio stream.
+    speakers: Arc<Mutex<HashMap<u32, UserId>>>,
+    segmenter: Arc<Mutex<Segmenter>>,
+}
+
+#[async_trait]
+impl songbird::EventHandler for Receiver {
+    async fn act(&self, event: &EventContext<'_>) -> Option<Event> {
+        match event {
+            EventContext::SpeakingStateUpdate(speaking) => {
+                if let Some(user_id) = speaking.user_id {
+                    self.speakers
+                        .lock()
+                        .expect("speakers lock poisoned")
+                        .insert(speaking.ssrc, UserId::new(user_id.0));
+                }
+            }
+            EventContext::VoiceTick(tick) => {
+                let mut ended = Vec::new();
+                {
+                    let mut segmenter = self.segmenter.lock().expect("segmenter lock poisoned");
+                    for (ssrc, data) in &tick.speaking {
+                        if let Some(samples) = &data.decoded_voice {
+                            ended.extend(segmenter.speak(*ssrc, samples).map(|u| (*ssrc, u)));
+                        }
+                    }
+                    for ssrc in &tick.silent {
+                        ended.extend(segmenter.silence(*ssrc).map(|u| (*ssrc, u)));
+                    }
+                }
+                for (ssrc, samples) in ended {
+                    let receiver = self.clone();
+                    tokio::spawn(async move { receiver.deliver(ssrc, samples).await });
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Receiver {
+    /// Transcribe an utterance and send it to the agent as its speaker's
+    /// message.
+    async fn deliver(&self, ssrc: u32, samples: Vec<i16>) {
+        let Some(user_id) = self
+            .speakers
+            .lock()
+            .expect("speakers lock poisoned")
+            .get(&ssrc)
+            .copied()
+        else {
+            tracing::debug!(ssrc, "dropping utterance from unknown speaker");
+            return;
+        };
+
+        let config = self.permissions.load().voice.clone();
+        let text = match transcribe(&self.http, &config, encode_wav(&samples)).await {
+            Ok(text) if !text.is_empty() => text,
+            Ok(_) => return,
+            Err(error) => {
+                tracing::warn!(%error, %user_id, "failed to transcribe voice utterance");
+                return;
+            }
+        };
+
+        let member = self.guild_id.member(&self.ctx, user_id).await.ok();
+        if member.as_ref().is_some_and(|member| member.user.bot) {
+            return;
+        }
+        let display_name = member
+            .as_ref()
+            .map(|member| member.display_name().to_string())
+            .unwrap_or_else(|| user_id.to_string());
+
+        let mut metadata = HashMap::new();
+        metadata.insert("discord_channel_id".into(), self.channel_id.get().into());
+        metadata.insert("discord_guild_id".into(), self.guild_id.get().into());
+        metadata.insert("discord_voice".into(), true.into());
+        metadata.insert("sender_display_name".into(), display_name.clone().into());
+        metadata.insert("sender_id".into(), user_id.get().into());
+        metadata.insert(
+            "discord_user_mention".into(),
+            serde_json::Value::String(format!("<@{user_id}>")),
+        );
+
+        let inbound = InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "discord".into(),
+            conversation_id: build_conversation_id(
+                Some(self.guild_id),
+                self.channel_id,
+                None,
+                user_id,
+            ),
+            sender_id: user_id.to_string(),
+            agent_id: None,
+            content: MessageContent::Text(text),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(format!("{display_name} (<@{user_id}>) [voice]")),
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send voice utterance from Discord (receiver dropped)"
+            );
+        }
+    }
+}
+
+/// Cuts each speaker's audio into utterances at pauses.
+#[derive(Debug, Default)]
+struct Segmenter {
+    utterances: HashMap<u32, Utterance>,
+}
+
+#[derive(Debug, Default)]
+struct Utterance {
+    samples: Vec<i16>,
+    silent_ticks: u32,
+}
+
+impl Segmenter {
+    /// Add a tick of speech. Returns the utterance if it hit the length cap.
+    fn speak(&mut self, ssrc: u32, samples: &[i16]) -> Option<Vec<i16>> {
+        let utterance = self.utterances.entry(ssrc).or_default();
+        utterance.samples.extend_from_slice(samples);
+        utterance.silent_ticks = 0;
+        if utterance.samples.len() < MAX_UTTERANCE_SAMPLES {
+            return None;
+        }
+        self.utterances
+            .remove(&ssrc)
+            .map(|utterance| utterance.samples)
+    }
+
+    /// Record a silent tick. Returns the utterance once the pause is long
+    /// enough, unless it was too short to be speech.
+    fn silence(&mut self, ssrc: u32) -> Option<Vec<i16>> {
+        let utterance = self.utterances.get_mut(&ssrc)?;
+        utterance.silent_ticks += 1;
+        if utterance.silent_ticks < SILENCE_TICKS {
+            return None;
+        }
+        let utterance = self.utterances.remove(&ssrc)?;
+        (utterance.samples.len() >= MIN_UTTERANCE_SAMPLES).then_some(utterance.samples)
+    }
+}
+
+/// Encode 48kHz stereo samples as a 16kHz mono WAV file.
+fn encode_wav(samples: &[i16]) -> Vec<u8> {
+    let step = SAMPLE_RATE / TRANSCRIBE_RATE;
+    // Average each run of frames down to one mono sample.
+    let mono: Vec<i16> = samples
+        .chunks(CHANNELS * step)
+        .map(|frames| {
+            let sum: i32 = frames.iter().map(|&sample| i32::from(sample)).sum();
+            (sum / frames.len() as i32) as i16
+        })
+        .collect();
+
+    let data_len = (mono.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&(TRANSCRIBE_RATE as u32).to_le_bytes());
+    wav.extend_from_slice(&(TRANSCRIBE_RATE as u32 * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in mono {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+/// Transcribe a WAV file through the `/audio/transcriptions` endpoint.
+async fn transcribe(
+    http: &reqwest::Client,
+    config: &VoiceConfig,
+    wav: Vec<u8>,
+) -> anyhow::Result<String> {
+    let file = reqwest::multipart::Part::bytes(wav)
+        .file_name("utterance.wav")
+        .mime_str("audio/wav")?;
+    let form = reqwest::multipart::Form::new()
+        .text("model", config.transcription_model.clone())
+        .part("file", file);
+
+    let mut request = http
+        .post(format!(
+            "{}/audio/transcriptions",
+            config.api_base.trim_end_matches('/')
+        ))
+        .multipart(form);
+    if let Some(api_key) = &config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response: TranscriptionResponse = request
+        .send()
+        .await
+        .context("transcription request failed")?
+        .error_for_status()
+        .context("transcription API returned an error")?
+        .json()
+        .await
+        .context("failed to parse transcription response")?;
+    Ok(response.text.trim().to_string())
+}
+
+/// Synthesize speech through the `/audio/speech` endpoint, as WAV.
+async fn synthesize(
+    http: &reqwest::Client,
+    config: &VoiceConfig,
+    text: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let mut request = http
+        .post(format!(
+            "{}/audio/speech",
+            config.api_base.trim_end_matches('/')
+        ))
+        .json(&serde_json::json!({
+            "model": config.tts_model,
+            "voice": config.tts_voice,
+            "input": text,
+            "response_format": "wav",
+        }));
+    if let Some(api_key) = &config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let audio = request
+        .send()
+        .await
+        .context("speech request failed")?
+        .error_for_status()
+        .context("speech API returned an error")?
+        .bytes()
+        .await
+        .context("failed to read synthesized speech")?;
+    Ok(audio.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_ends_utterance_and_short_noise_is_dropped() {
+        let mut segmenter = Segmenter::default();
+        let tick = vec![100i16; SAMPLE_RATE * CHANNELS / 50];
+
+        // One second of speech, then a pause.
+        for _ in 0..50 {
+            assert!(segmenter.speak(7, &tick).is_none());
+        }
+        for _ in 1..SILENCE_TICKS {
+            assert!(segmenter.silence(7).is_none());
+        }
+        let utterance = segmenter.silence(7).expect("pause ends the utterance");
+        assert_eq!(utterance.len(), SAMPLE_RATE * CHANNELS);
+
+        // A single tick is too short to be speech.
+        segmenter.speak(8, &tick);
+        for _ in 0..SILENCE_TICKS {
+            assert!(segmenter.silence(8).is_none());
+        }
+
+        let wav = encode_wav(&utterance);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav.len(), 44 + TRANSCRIBE_RATE * 2);
+    }
+}
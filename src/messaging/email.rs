@@ -0,0 +1,532 @@
+//! Email messaging adapter over IMAP and SMTP.
+//!
+//! Each thread is a conversation, `email:<message-id>`, keyed by the first
+//! Message-ID in its References, so replies on either side land in the same
+//! channel. New mail is read by polling the configured folder for unread
+//! messages; replies go out over SMTP with the threading headers set.
+//!
+//! With an `approver` configured, replies aren't sent directly. Each is held
+//! as a draft and put to the approver agent in the thread's approval
+//! conversation, `email:approval:<message-id>`. Its answer decides: APPROVE
+//! sends the draft, REVISE followed by new text sends that instead, and
+//! anything else drops it and tells the drafting agent why.
+
+mod imap;
+mod mime;
+mod smtp;
+
+use crate::agent::listening;
+use crate::config::{EmailConfig, EmailPermissions};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+
+/// Most messages read per poll, so a full inbox is worked through gradually.
+const MAX_PER_POLL: usize = 20;
+
+/// Metadata key on approval requests holding the conversation ID of the
+/// thread the draft answers.
+const APPROVAL_KEY: &str = "email_approval_thread";
+
+/// Email adapter state.
+pub struct EmailAdapter {
+    config: EmailConfig,
+    permissions: Arc<ArcSwap<EmailPermissions>>,
+    inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
+    /// Replies waiting on the approver, by the conversation ID of their thread.
+    drafts: Arc<RwLock<HashMap<String, Draft>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    /// Whether the last poll reached the mailbox.
+    healthy: Arc<AtomicBool>,
+}
+
+/// A reply held for approval.
+struct Draft {
+    mail: smtp::Outgoing,
+    /// Agent that wrote it, to tell it when the draft is rejected.
+    agent_id: Option<crate::AgentId>,
+}
+
+/// The approver's answer to a draft.
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    Approve,
+    Revise(String),
+    Reject(String),
+}
+
+impl EmailAdapter {
+    pub fn new(config: &EmailConfig, permissions: Arc<ArcSwap<EmailPermissions>>) -> Self {
+        Self {
+            config: config.clone(),
+            permissions,
+            inbound_tx: Arc::new(RwLock::new(None)),
+            drafts: Arc::new(RwLock::new(HashMap::new())),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+            healthy: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    async fn inject(&self, message: InboundMessage) -> anyhow::Result<()> {
+        let inbound_tx = self.inbound_tx.read().await;
+        inbound_tx
+            .as_ref()
+            .context("email adapter not started")?
+            .send(message)
+            .await
+            .context("email inbound stream closed")
+    }
+
+    /// Hold `mail` as the thread's draft and ask the approver about it.
+    async fn request_approval(
+        &self,
+        message: &InboundMessage,
+        mail: smtp::Outgoing,
+        approver: &str,
+    ) -> anyhow::Result<()> {
+        let thread = message.conversation_id.clone();
+        let text = format!(
+            "A reply to {} in the email thread \"{}\" is waiting for your approval:\n\n{}\n\n\
+             Answer APPROVE to send it as is, REVISE followed by the corrected text to send \
+             that instead, or anything else to reject it, with your reason.",
+            mail.to, mail.subject, mail.body
+        );
+        self.drafts.write().await.insert(
+            thread.clone(),
+            Draft {
+                mail,
+                agent_id: message.agent_id.clone(),
+            },
+        );
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            APPROVAL_KEY.into(),
+            serde_json::Value::String(thread.clone()),
+        );
+        metadata.insert(
+            "display_name".into(),
+            serde_json::Value::String("email drafts".into()),
+        );
+        let thread_id = thread.strip_prefix("email:").unwrap_or(&thread);
+        tracing::info!(conversation_id = %thread, %approver, "email reply held for approval");
+        self.inject(InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "email".into(),
+            conversation_id: format!("email:approval:{thread_id}"),
+            sender_id: "email-drafts".into(),
+            agent_id: Some(approver.into()),
+            content: MessageContent::Text(text),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some("email drafts".into()),
+        })
+        .await
+    }
+
+    /// Act on the approver's answer for the thread's draft.
+    async fn decide(&self, thread: &str, answer: &str) -> anyhow::Result<()> {
+        // The first answer decides; later messages in the same turn find no draft.
+        let Some(mut draft) = self.drafts.write().await.remove(thread) else {
+            return Ok(());
+        };
+
+        match decision(answer) {
+            Decision::Approve => {}
+            Decision::Revise(text) => draft.mail.body = text,
+            Decision::Reject(reason) => {
+                tracing::info!(conversation_id = %thread, "email draft rejected");
+                let mut note = InboundMessage {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    source: "email".into(),
+                    conversation_id: thread.to_string(),
+                    sender_id: "email-drafts".into(),
+                    agent_id: draft.agent_id,
+                    content: MessageContent::Text(format!(
+                        "Your reply wasn't approved and wasn't sent. The approver said: {reason}"
+                    )),
+                    timestamp: chrono::Utc::now(),
+                    metadata: HashMap::new(),
+                    formatted_author: Some("email drafts".into()),
+                };
+                // Context for the next turn; a turn now would draft again.
+                listening::mark_passive(&mut note);
+                return self.inject(note).await;
+            }
+        }
+
+        smtp::send(&self.config, &draft.mail).await?;
+        tracing::info!(conversation_id = %thread, "approved email reply sent");
+        Ok(())
+    }
+}
+
+impl Messaging for EmailAdapter {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.inbound_tx.write().await = Some(inbound_tx.clone());
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let config = self.config.clone();
+        let permissions = self.permissions.clone();
+        let healthy = self.healthy.clone();
+
+        tracing::info!(
+            imap_host = %config.imap_host,
+            address = %config.address,
+            folder = %config.folder,
+            "email adapter polling"
+        );
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(config.poll_interval_secs));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = shutdown_rx.recv() => {
+                        tracing::info!("email poll loop shutting down");
+                        break;
+                    }
+                    _ = interval.tick() => {}
+                }
+
+                match poll(&config, &permissions, &inbound_tx).await {
+                    Ok(()) => healthy.store(true, Ordering::Relaxed),
+                    Err(error) => {
+                        healthy.store(false, Ordering::Relaxed);
+                        tracing::warn!(%error, "email poll failed");
+                    }
+                }
+                if inbound_tx.is_closed() {
+                    break;
+                }
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let text = match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => text,
+            OutboundResponse::File {
+                filename, caption, ..
+            } => match caption {
+                Some(caption) => format!("[File: {filename}] {caption}"),
+                None => format!("[File: {filename}]"),
+            },
+            // Mail can't be edited, so streaming is buffered: the final text
+            // arrives as a Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort
+            | OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => return Ok(()),
+        };
+
+        if let Some(thread) = message.metadata.get(APPROVAL_KEY).and_then(|v| v.as_str()) {
+            self.decide(thread, &text).await?;
+            return Ok(());
+        }
+
+        let mail = reply(message, text)?;
+        match &self.config.approver {
+            Some(approver) => self.request_approval(message, mail, approver).await?,
+            None => {
+                smtp::send(&self.config, &mail).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            // A new thread, titled with the first line.
+            let subject: String = text
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(78)
+                .collect();
+            let mail = smtp::Outgoing {
+                to: target.to_string(),
+                subject,
+                in_reply_to: None,
+                references: Vec::new(),
+                body: text,
+            };
+            smtp::send(&self.config, &mail).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if !self.healthy.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("email mailbox not reachable").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.inbound_tx.write().await = None;
+
+        tracing::info!("email adapter shut down");
+        Ok(())
+    }
+}
+
+/// Read the unread mail in the folder, marking each message read, and
+/// forward the messages that pass the filters.
+async fn poll(
+    config: &EmailConfig,
+    permissions: &ArcSwap<EmailPermissions>,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+) -> anyhow::Result<()> {
+    let mut mailbox = imap::ImapClient::connect(&config.imap_host, config.imap_port).await?;
+    mailbox.login(&config.username, &config.password).await?;
+    mailbox.select(&config.folder).await?;
+
+    for uid in mailbox.unseen().await?.into_iter().take(MAX_PER_POLL) {
+        let Some(raw) = mailbox.fetch(uid).await? else {
+            continue;
+        };
+        // Read whether or not it's let through, so each message is looked at once.
+        mailbox.mark_seen(uid).await?;
+
+        let Some(mail) = mime::parse(&raw) else {
+            tracing::warn!(uid, "skipping email without a readable sender");
+            continue;
+        };
+        let Some(message) = inbound_message(config, &permissions.load(), mail) else {
+            continue;
+        };
+        if inbound_tx.send(message).await.is_err() {
+            break;
+        }
+    }
+
+    mailbox.logout().await;
+    Ok(())
+}
+
+/// The inbound message for a mail, or None if it's filtered out: sent by the
+/// bot itself, sent by a program, or from a sender that isn't allowed.
+fn inbound_message(
+    config: &EmailConfig,
+    permissions: &EmailPermissions,
+    mail: mime::Mail,
+) -> Option<InboundMessage> {
+    let sender = &mail.from.address;
+    if sender.eq_ignore_ascii_case(&config.address) {
+        return None;
+    }
+    if mail.automated {
+        tracing::debug!(%sender, subject = %mail.subject, "ignoring automated email");
+        return None;
+    }
+    if !permissions.allows_sender(sender) {
+        tracing::debug!(%sender, "ignoring email from sender not in allowed list");
+        return None;
+    }
+
+    let message_id = mail
+        .message_id
+        .clone()
+        .unwrap_or_else(|| format!("{}@spacebot", uuid::Uuid::new_v4()));
+    let thread_id = mail
+        .references
+        .first()
+        .or(mail.in_reply_to.as_ref())
+        .unwrap_or(&message_id)
+        .clone();
+    let reply_to = mail.reply_to.as_ref().unwrap_or(&mail.from).address.clone();
+    let display_name = mail.from.name.clone().unwrap_or_else(|| sender.clone());
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "email_message_id".into(),
+        serde_json::Value::String(message_id),
+    );
+    metadata.insert(
+        "email_references".into(),
+        serde_json::Value::from(mail.references.clone()),
+    );
+    metadata.insert(
+        "email_subject".into(),
+        serde_json::Value::String(mail.subject.clone()),
+    );
+    metadata.insert("email_reply_to".into(), serde_json::Value::String(reply_to));
+    metadata.insert(
+        "email_from".into(),
+        serde_json::Value::String(sender.clone()),
+    );
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(display_name.clone()),
+    );
+
+    let text = if mail.subject.is_empty() {
+        mail.body
+    } else {
+        format!("Subject: {}\n\n{}", mail.subject, mail.body)
+    };
+
+    Some(InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "email".into(),
+        conversation_id: format!("email:{thread_id}"),
+        sender_id: sender.to_lowercase(),
+        agent_id: None,
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(format!("{display_name} <{sender}>")),
+    })
+}
+
+/// The reply to the mail behind `message`, threaded under it.
+fn reply(message: &InboundMessage, body: String) -> anyhow::Result<smtp::Outgoing> {
+    let field = |key: &str| message.metadata.get(key).and_then(|v| v.as_str());
+    let to = field("email_reply_to").context("missing email_reply_to in metadata")?;
+    let subject = field("email_subject").unwrap_or_default();
+    let subject = if subject.to_lowercase().starts_with("re:") {
+        subject.to_string()
+    } else {
+        format!("Re: {subject}")
+    };
+    let in_reply_to = field("email_message_id").map(String::from);
+    let mut references: Vec<String> = message
+        .metadata
+        .get("email_references")
+        .and_then(|v| v.as_array())
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    references.extend(in_reply_to.clone());
+
+    Ok(smtp::Outgoing {
+        to: to.to_string(),
+        subject,
+        in_reply_to,
+        references,
+        body,
+    })
+}
+
+/// Read the approver's answer. Markdown emphasis around the verdict is
+/// ignored.
+fn decision(answer: &str) -> Decision {
+    let answer = answer.trim();
+    let verdict = answer.trim_start_matches(['*', '_', '`']);
+    let word_len = verdict
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(verdict.len());
+    let rest = verdict[word_len..].trim_start_matches(['*', '_', '`', ':', '.', '!']);
+    match verdict[..word_len].to_ascii_uppercase().as_str() {
+        "APPROVE" | "APPROVED" => Decision::Approve,
+        "REVISE" | "REVISED" if !rest.trim().is_empty() => {
+            Decision::Revise(rest.trim().to_string())
+        }
+        _ => Decision::Reject(answer.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threads_replies_and_approval_decisions() {
+        let config = EmailConfig {
+            enabled: true,
+            imap_host: "imap.example.com".into(),
+            imap_port: 993,
+            smtp_host: "smtp.example.com".into(),
+            smtp_port: 465,
+            username: "bot@example.com".into(),
+            password: "secret".into(),
+            address: "bot@example.com".into(),
+            display_name: None,
+            folder: "INBOX".into(),
+            poll_interval_secs: 60,
+            approver: Some("lead".into()),
+        };
+        let permissions = EmailPermissions {
+            allowed_senders: vec!["@example.com".into()],
+        };
+        let mail = mime::parse(
+            b"From: Ana <ana@example.com>\r\n\
+              Subject: Re: Invoice\r\n\
+              Message-ID: <c@example.com>\r\n\
+              In-Reply-To: <b@example.com>\r\n\
+              References: <a@example.com> <b@example.com>\r\n\
+              \r\n\
+              Looks good.\r\n",
+        )
+        .unwrap();
+
+        let message = inbound_message(&config, &permissions, mail.clone()).unwrap();
+        assert_eq!(message.conversation_id, "email:a@example.com");
+        assert_eq!(
+            message.content.to_string(),
+            "Subject: Re: Invoice\n\nLooks good."
+        );
+
+        let outgoing = reply(&message, "Thanks!".into()).unwrap();
+        assert_eq!(outgoing.to, "ana@example.com");
+        assert_eq!(outgoing.subject, "Re: Invoice");
+        assert_eq!(outgoing.in_reply_to.as_deref(), Some("c@example.com"));
+        assert_eq!(
+            outgoing.references,
+            vec!["a@example.com", "b@example.com", "c@example.com"]
+        );
+
+        let mut stranger = mail;
+        stranger.from.address = "eve@elsewhere.org".into();
+        assert!(inbound_message(&config, &permissions, stranger).is_none());
+
+        assert_eq!(decision("**APPROVE**"), Decision::Approve);
+        assert_eq!(
+            decision("REVISE: Thanks, Ana!"),
+            Decision::Revise("Thanks, Ana!".into())
+        );
+        assert_eq!(
+            decision("No, don't promise a date."),
+            Decision::Reject("No, don't promise a date.".into())
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! A minimal IMAP client: log in, find unread mail, fetch it, and mark it
+//! read. Connections use implicit TLS.
+
+use anyhow::Context as _;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+/// A response line the server sent before completing a command, with the
+/// literals it carried.
+struct Untagged {
+    line: String,
+    literals: Vec<Vec<u8>>,
+}
+
+pub(super) struct ImapClient {
+    stream: BufReader<TlsStream<TcpStream>>,
+    next_tag: u32,
+}
+
+impl ImapClient {
+    pub(super) async fn connect(host: &str, port: u16) -> anyhow::Result<Self> {
+        let tcp = TcpStream::connect((host, port))
+            .await
+            .with_context(|| format!("failed to connect to {host}:{port}"))?;
+        let tls = crate::messaging::tls::connect(host, tcp).await?;
+        let mut client = Self {
+            stream: BufReader::new(tls),
+            next_tag: 1,
+        };
+        let greeting = client.read_response().await?;
+        if !greeting.line.starts_with("* OK") && !greeting.line.starts_with("* PREAUTH") {
+            anyhow::bail!("imap server refused the connection: {}", greeting.line);
+        }
+        Ok(client)
+    }
+
+    pub(super) async fn login(&mut self, username: &str, password: &str) -> anyhow::Result<()> {
+        self.command(&format!("LOGIN {} {}", quote(username), quote(password)))
+            .await
+            .context("imap login failed")?;
+        Ok(())
+    }
+
+    pub(super) async fn select(&mut self, folder: &str) -> anyhow::Result<()> {
+        self.command(&format!("SELECT {}", quote(folder)))
+            .await
+            .with_context(|| format!("failed to open imap folder '{folder}'"))?;
+        Ok(())
+    }
+
+    /// UIDs of the unread messages in the selected folder.
+    pub(super) async fn unseen(&mut self) -> anyhow::Result<Vec<u32>> {
+        let responses = self.command("UID SEARCH UNSEEN").await?;
+        Ok(responses
+            .iter()
+            .filter_map(|response| response.line.strip_prefix("* SEARCH"))
+            .flat_map(|uids| uids.split_whitespace())
+            .filter_map(|uid| uid.parse().ok())
+            .collect())
+    }
+
+    /// The raw message, without marking it read. None if it's gone.
+    pub(super) async fn fetch(&mut self, uid: u32) -> anyhow::Result<Option<Vec<u8>>> {
+        let responses = self
+            .command(&format!("UID FETCH {uid} BODY.PEEK[]"))
+            .await?;
+        Ok(responses
+            .into_iter()
+            .find(|response| response.line.contains("FETCH"))
+            .and_then(|response| response.literals.into_iter().next()))
+    }
+
+    pub(super) async fn mark_seen(&mut self, uid: u32) -> anyhow::Result<()> {
+        self.command(&format!("UID STORE {uid} +FLAGS.SILENT (\\Seen)"))
+            .await?;
+        Ok(())
+    }
+
+    pub(super) async fn logout(mut self) {
+        let _ = self.command("LOGOUT").await;
+    }
+
+    /// Send a command and read the responses up to its completion. Errors if
+    /// it doesn't complete with OK.
+    async fn command(&mut self, command: &str) -> anyhow::Result<Vec<Untagged>> {
+        let tag = format!("a{}", self.next_tag);
+        self.next_tag += 1;
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{tag} {command}\r\n").as_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response.line.strip_prefix(&format!("{tag} ")) {
+                if !status.starts_with("OK") {
+                    anyhow::bail!("imap server answered: {status}");
+                }
+                return Ok(responses);
+            }
+            responses.push(response);
+        }
+    }
+
+    /// Read one response line, with the literals (`{size}` followed by that
+    /// many bytes) embedded in it.
+    async fn read_response(&mut self) -> anyhow::Result<Untagged> {
+        let mut line = String::new();
+        let mut literals = Vec::new();
+        loop {
+            let mut bytes = Vec::new();
+            if self.stream.read_until(b'\n', &mut bytes).await? == 0 {
+                anyhow::bail!("imap connection closed");
+            }
+            let part = String::from_utf8_lossy(&bytes);
+            let part = part.trim_end_matches(['\r', '\n']);
+            line.push_str(part);
+
+            let Some(size) = part
+                .strip_suffix('}')
+                .and_then(|part| part.rsplit_once('{'))
+                .and_then(|(_, size)| size.parse::<usize>().ok())
+            else {
+                return Ok(Untagged { line, literals });
+            };
+            let mut literal = vec![0; size];
+            self.stream.read_exact(&mut literal).await?;
+            literals.push(literal);
+        }
+    }
+}
+
+/// An IMAP quoted string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
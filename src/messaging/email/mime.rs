@@ -0,0 +1,420 @@
+//! Just enough MIME to read mail: headers, encoded words, and the plain text
+//! of the body, with the quoted message it replies to cut off.
+
+use base64::Engine as _;
+
+/// An address with its display name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Mailbox {
+    pub name: Option<String>,
+    pub address: String,
+}
+
+impl Mailbox {
+    /// Parse `Name <address>`, `"Name" <address>`, or a bare address.
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        let value = decode_words(value.trim());
+        let (name, address) = match (value.rfind('<'), value.rfind('>')) {
+            (Some(open), Some(close)) if open < close => {
+                let name = value[..open].trim().trim_matches('"').trim();
+                (
+                    (!name.is_empty()).then(|| name.to_string()),
+                    &value[open + 1..close],
+                )
+            }
+            _ => (None, value.as_str()),
+        };
+        let address = address.trim();
+        address.contains('@').then(|| Self {
+            name,
+            address: address.to_string(),
+        })
+    }
+}
+
+/// The parts of a mail the adapter reads.
+#[derive(Debug, Clone)]
+pub(super) struct Mail {
+    /// Message-ID, without the angle brackets.
+    pub message_id: Option<String>,
+    pub in_reply_to: Option<String>,
+    /// Message-IDs of the thread, oldest first.
+    pub references: Vec<String>,
+    pub subject: String,
+    pub from: Mailbox,
+    pub reply_to: Option<Mailbox>,
+    /// Whether a program sent it: an autoresponder, a bounce, or a list.
+    /// Answering those loops.
+    pub automated: bool,
+    /// The new text of the mail, without the quoted message it replies to.
+    pub body: String,
+}
+
+/// Parse a raw RFC 5322 message. None if it has no usable From.
+pub(super) fn parse(raw: &[u8]) -> Option<Mail> {
+    let (headers, body) = split_message(raw);
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let automated = header("Auto-Submitted")
+        .is_some_and(|value| !value.trim().eq_ignore_ascii_case("no"))
+        || header("Precedence").is_some_and(|value| {
+            matches!(
+                value.trim().to_lowercase().as_str(),
+                "bulk" | "list" | "junk"
+            )
+        })
+        || header("List-Id").is_some();
+
+    Some(Mail {
+        message_id: header("Message-ID").and_then(|value| message_ids(value).into_iter().next()),
+        in_reply_to: header("In-Reply-To").and_then(|value| message_ids(value).into_iter().next()),
+        references: header("References").map(message_ids).unwrap_or_default(),
+        subject: header("Subject").map(decode_words).unwrap_or_default(),
+        from: Mailbox::parse(header("From")?)?,
+        reply_to: header("Reply-To").and_then(Mailbox::parse),
+        automated,
+        body: strip_quoted(&text_body(&headers, body).unwrap_or_default()),
+    })
+}
+
+/// Headers, unfolded, and the body of a message or part.
+fn split_message(raw: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+    let (head, body) = match find(raw, b"\r\n\r\n") {
+        Some(end) => (&raw[..end], &raw[end + 4..]),
+        None => match find(raw, b"\n\n") {
+            Some(end) => (&raw[..end], &raw[end + 2..]),
+            None => (raw, &raw[raw.len()..]),
+        },
+    };
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The Message-IDs in a header, without their angle brackets.
+fn message_ids(value: &str) -> Vec<String> {
+    value
+        .split('<')
+        .skip(1)
+        .filter_map(|id| id.split_once('>'))
+        .map(|(id, _)| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// A header's media type, lowercased, and its parameters.
+fn content_type(value: &str) -> (String, Vec<(String, String)>) {
+    let mut parts = value.split(';');
+    let media_type = parts.next().unwrap_or("").trim().to_lowercase();
+    let parameters = parts
+        .filter_map(|parameter| parameter.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    (media_type, parameters)
+}
+
+/// The text of a message or part: its first plain text part, else its
+/// first HTML part with the markup stripped.
+fn text_body(headers: &[(String, String)], body: &[u8]) -> Option<String> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+    if header("Content-Disposition")
+        .is_some_and(|value| value.trim().to_lowercase().starts_with("attachment"))
+    {
+        return None;
+    }
+    let (media_type, parameters) = content_type(header("Content-Type").unwrap_or("text/plain"));
+    let parameter = |name: &str| {
+        parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if media_type.starts_with("multipart/") {
+        let boundary = parameter("boundary")?;
+        let mut html = None;
+        for part in multipart_parts(body, boundary) {
+            let (part_headers, part_body) = split_message(part);
+            let is_html = part_headers.iter().any(|(key, value)| {
+                key.eq_ignore_ascii_case("Content-Type")
+                    && value.to_lowercase().starts_with("text/html")
+            });
+            match text_body(&part_headers, part_body) {
+                Some(text) if !is_html => return Some(text),
+                Some(text) => {
+                    html.get_or_insert(text);
+                }
+                None => {}
+            }
+        }
+        return html;
+    }
+
+    let is_html = media_type == "text/html";
+    if media_type != "text/plain" && !is_html {
+        return None;
+    }
+    let decoded = match header("Content-Transfer-Encoding").map(|value| value.trim().to_lowercase())
+    {
+        Some(encoding) if encoding == "base64" => {
+            let compact: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(compact)
+                .ok()?
+        }
+        Some(encoding) if encoding == "quoted-printable" => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    };
+    let text = decode_charset(&decoded, parameter("charset").unwrap_or("utf-8"));
+    Some(if is_html { strip_html(&text) } else { text })
+}
+
+/// The bodies of a multipart body's parts.
+fn multipart_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut line_start = 0;
+    while line_start < body.len() {
+        let line_end = body[line_start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(body.len(), |end| line_start + end + 1);
+        let line = &body[line_start..line_end];
+        if line.starts_with(delimiter) {
+            if let Some(start) = start {
+                parts.push(&body[start..line_start]);
+            }
+            if line[delimiter.len()..].starts_with(b"--") {
+                return parts;
+            }
+            start = Some(line_end);
+        }
+        line_start = line_end;
+    }
+    parts
+}
+
+/// Decode quoted-printable. In encoded words, `_` stands for a space.
+fn decode_quoted_printable(input: &[u8], underscore_is_space: bool) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'=' if input[index + 1..].starts_with(b"\r\n") => index += 3,
+            b'=' if input[index + 1..].starts_with(b"\n") => index += 2,
+            b'=' if index + 2 < input.len() => {
+                let hex = std::str::from_utf8(&input[index + 1..index + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        output.push(byte);
+                        index += 3;
+                    }
+                    None => {
+                        output.push(b'=');
+                        index += 1;
+                    }
+                }
+            }
+            b'_' if underscore_is_space => {
+                output.push(b' ');
+                index += 1;
+            }
+            byte => {
+                output.push(byte);
+                index += 1;
+            }
+        }
+    }
+    output
+}
+
+/// Text in `charset`. Latin-1 and Windows-1252 map byte for byte; anything
+/// else is read as UTF-8.
+fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    match charset.trim().to_lowercase().as_str() {
+        "iso-8859-1" | "latin1" | "windows-1252" | "cp1252" => {
+            bytes.iter().map(|&b| b as char).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Decode the RFC 2047 encoded words in a header value, like
+/// `=?utf-8?Q?Caf=C3=A9?=`. Whitespace between two encoded words is dropped.
+fn decode_words(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, word) = rest.split_at(start);
+        let Some((text, consumed)) = decode_word(word) else {
+            decoded.push_str(before);
+            decoded.push_str("=?");
+            rest = &word[2..];
+            after_word = false;
+            continue;
+        };
+        if !(after_word && before.trim().is_empty()) {
+            decoded.push_str(before);
+        }
+        decoded.push_str(&text);
+        rest = &word[consumed..];
+        after_word = true;
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// One encoded word at the start of `word`, and its length.
+fn decode_word(word: &str) -> Option<(String, usize)> {
+    let inner = word.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let text = &inner[..end];
+    let bytes = match encoding.to_ascii_uppercase().as_str() {
+        "B" => base64::engine::general_purpose::STANDARD
+            .decode(text)
+            .ok()?,
+        "Q" => decode_quoted_printable(text.as_bytes(), true),
+        _ => return None,
+    };
+    let consumed = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    Some((decode_charset(&bytes, charset), consumed))
+}
+
+/// HTML as plain text: tags dropped, line breaks kept, common entities
+/// decoded.
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_matches('/')
+            .to_lowercase();
+        if ["br", "p", "div", "li", "tr"]
+            .iter()
+            .any(|name| tag == *name || tag.starts_with(&format!("{name} ")))
+        {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The new text of a reply: everything before the quoted message and the
+/// signature, without quoted lines.
+fn strip_quoted(body: &str) -> String {
+    let mut lines = Vec::new();
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed == "--"
+            || line == "-- "
+            || trimmed.starts_with("-----Original Message-----")
+            || (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+        {
+            break;
+        }
+        if !trimmed.starts_with('>') {
+            lines.push(line.trim_end());
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multipart_reply() {
+        let raw = concat!(
+            "From: =?utf-8?Q?Ren=C3=A9e?= <renee@example.com>\r\n",
+            "Subject: Re: =?utf-8?B?SW52b2ljZQ==?= =?utf-8?Q?_=E2=84=96?= 42\r\n",
+            "Message-ID: <c@example.com>\r\n",
+            "In-Reply-To: <b@spacebot>\r\n",
+            "References: <a@example.com>\r\n <b@spacebot>\r\n",
+            "Content-Type: multipart/alternative; boundary=\"xyz\"\r\n",
+            "\r\n",
+            "--xyz\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "Thanks, that works =\r\nfor me. Caf=C3=A9?\r\n",
+            "\r\n",
+            "On Tue, Oct 14, 2026 at 9:00 AM Spacebot wrote:\r\n",
+            "> Here is the invoice.\r\n",
+            "--xyz\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>Thanks</p>\r\n",
+            "--xyz--\r\n",
+        );
+        let mail = parse(raw.as_bytes()).unwrap();
+
+        assert_eq!(mail.from.name.as_deref(), Some("Renée"));
+        assert_eq!(mail.from.address, "renee@example.com");
+        assert_eq!(mail.subject, "Re: Invoice № 42");
+        assert_eq!(mail.message_id.as_deref(), Some("c@example.com"));
+        assert_eq!(mail.in_reply_to.as_deref(), Some("b@spacebot"));
+        assert_eq!(mail.references, vec!["a@example.com", "b@spacebot"]);
+        assert_eq!(mail.body, "Thanks, that works for me. Café?");
+        assert!(!mail.automated);
+
+        let bounce = parse(
+            b"From: MAILER-DAEMON@example.com\nAuto-Submitted: auto-replied\n\nUndeliverable",
+        )
+        .unwrap();
+        assert!(bounce.automated);
+        assert_eq!(strip_html("<p>Hi &amp; bye</p><br/>x"), "\nHi & bye\n\nx");
+    }
+}
@@ -0,0 +1,163 @@
+//! A minimal SMTP client for sending one plain text mail per connection.
+//! Port 465 uses implicit TLS; other ports upgrade with STARTTLS.
+
+use crate::config::EmailConfig;
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// A mail to send.
+#[derive(Debug, Clone)]
+pub(super) struct Outgoing {
+    pub to: String,
+    pub subject: String,
+    /// Message-ID this answers, without angle brackets.
+    pub in_reply_to: Option<String>,
+    /// Message-IDs of the thread, oldest first.
+    pub references: Vec<String>,
+    pub body: String,
+}
+
+/// Send `mail` from the configured address. Returns its Message-ID.
+pub(super) async fn send(config: &EmailConfig, mail: &Outgoing) -> anyhow::Result<String> {
+    let host = config.smtp_host.as_str();
+    let port = config.smtp_port;
+    let tcp = TcpStream::connect((host, port))
+        .await
+        .with_context(|| format!("failed to connect to {host}:{port}"))?;
+    let domain = domain(&config.address);
+    let (message_id, message) = compose(config, mail);
+
+    if port == 465 {
+        let tls = crate::messaging::tls::connect(host, tcp).await?;
+        let mut stream = BufReader::new(tls);
+        expect(&mut stream, 220).await?;
+        command(&mut stream, &format!("EHLO {domain}"), 250).await?;
+        deliver(&mut stream, config, &mail.to, &message).await?;
+    } else {
+        let mut plain = BufReader::new(tcp);
+        expect(&mut plain, 220).await?;
+        command(&mut plain, &format!("EHLO {domain}"), 250).await?;
+        command(&mut plain, "STARTTLS", 220).await?;
+        let tls = crate::messaging::tls::connect(host, plain.into_inner()).await?;
+        let mut stream = BufReader::new(tls);
+        command(&mut stream, &format!("EHLO {domain}"), 250).await?;
+        deliver(&mut stream, config, &mail.to, &message).await?;
+    }
+    Ok(message_id)
+}
+
+/// Log in and hand over the message on an established session.
+async fn deliver<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    config: &EmailConfig,
+    to: &str,
+    message: &str,
+) -> anyhow::Result<()> {
+    let credentials = format!("\0{}\0{}", config.username, config.password);
+    let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+    command(stream, &format!("AUTH PLAIN {credentials}"), 235)
+        .await
+        .context("smtp login failed")?;
+    command(stream, &format!("MAIL FROM:<{}>", config.address), 250).await?;
+    command(stream, &format!("RCPT TO:<{to}>"), 250).await?;
+    command(stream, "DATA", 354).await?;
+    // The body is base64, so no line starts with a dot that needs escaping.
+    command(stream, &format!("{message}\r\n."), 250).await?;
+    let _ = command(stream, "QUIT", 221).await;
+    Ok(())
+}
+
+/// Send a line and expect a reply with `code`.
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    line: &str,
+    code: u16,
+) -> anyhow::Result<String> {
+    let inner = stream.get_mut();
+    inner.write_all(format!("{line}\r\n").as_bytes()).await?;
+    inner.flush().await?;
+    expect(stream, code).await
+}
+
+/// Read a reply, which may span lines (`250-...` up to `250 ...`), and
+/// check its code.
+async fn expect<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    code: u16,
+) -> anyhow::Result<String> {
+    let mut reply = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            anyhow::bail!("smtp connection closed");
+        }
+        reply.push_str(&line);
+        if line.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+    }
+    let received: u16 = reply.get(..3).and_then(|c| c.parse().ok()).unwrap_or(0);
+    if received != code && !(code == 250 && received == 251) {
+        anyhow::bail!("smtp server answered: {}", reply.trim());
+    }
+    Ok(reply)
+}
+
+/// The Message-ID and text of `mail`, headers and base64 body.
+fn compose(config: &EmailConfig, mail: &Outgoing) -> (String, String) {
+    let message_id = format!("{}@{}", uuid::Uuid::new_v4(), domain(&config.address));
+    let from = match &config.display_name {
+        Some(name) => format!("{} <{}>", encode_header(name), config.address),
+        None => config.address.clone(),
+    };
+
+    let mut headers = vec![
+        format!("From: {from}"),
+        format!("To: {}", mail.to),
+        format!("Subject: {}", encode_header(&mail.subject)),
+        format!("Date: {}", chrono::Utc::now().to_rfc2822()),
+        format!("Message-ID: <{message_id}>"),
+    ];
+    if let Some(in_reply_to) = &mail.in_reply_to {
+        headers.push(format!("In-Reply-To: <{in_reply_to}>"));
+    }
+    if !mail.references.is_empty() {
+        let references: Vec<String> = mail.references.iter().map(|id| format!("<{id}>")).collect();
+        headers.push(format!("References: {}", references.join(" ")));
+    }
+    headers.push("Auto-Submitted: auto-replied".into());
+    headers.push("MIME-Version: 1.0".into());
+    headers.push("Content-Type: text/plain; charset=utf-8".into());
+    headers.push("Content-Transfer-Encoding: base64".into());
+
+    let body = mail.body.replace("\r\n", "\n").replace('\n', "\r\n");
+    let encoded = base64::engine::general_purpose::STANDARD.encode(body);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+
+    let message = format!("{}\r\n\r\n{}", headers.join("\r\n"), lines.join("\r\n"));
+    (message_id, message)
+}
+
+/// A header value, as an encoded word if it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        return value.to_string();
+    }
+    format!(
+        "=?utf-8?B?{}?=",
+        base64::engine::general_purpose::STANDARD.encode(value)
+    )
+}
+
+fn domain(address: &str) -> &str {
+    address
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain)
+}
@@ -0,0 +1,372 @@
+//! Outbound content guardrails: filter responses before they reach a platform.
+
+use crate::OutboundResponse;
+use crate::config::{GuardrailAction, GuardrailsConfig};
+use crate::llm::{LlmManager, SpacebotModel};
+
+use regex::{Regex, RegexBuilder};
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel as _, Prompt as _};
+use serde::Deserialize;
+
+use std::sync::Arc;
+
+/// Replacement for matched content when the action is `redact`.
+const REDACTED: &str = "[redacted]";
+
+/// Outcome of checking one piece of outbound text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// Matches were redacted; `rules` names what matched.
+    Rewritten {
+        text: String,
+        rules: Vec<String>,
+    },
+    Blocked {
+        reason: String,
+    },
+}
+
+#[derive(Debug)]
+struct Rule {
+    label: String,
+    regex: Regex,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModerationVerdict {
+    allowed: bool,
+    #[serde(default)]
+    reason: String,
+}
+
+/// Pattern rules plus an optional moderation model, built from
+/// `[messaging.guardrails]`.
+pub struct OutputFilter {
+    config: GuardrailsConfig,
+    rules: Vec<Rule>,
+    llm_manager: Arc<LlmManager>,
+}
+
+impl std::fmt::Debug for OutputFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputFilter")
+            .field("config", &self.config)
+            .field("rules", &self.rules.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl OutputFilter {
+    /// Build a filter, or None when guardrails are disabled.
+    pub fn from_config(
+        config: &GuardrailsConfig,
+        llm_manager: Arc<LlmManager>,
+    ) -> Result<Option<Self>, regex::Error> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let mut rules = Vec::new();
+        if config.block_secrets {
+            rules.extend(
+                crate::secrets::scan::secret_patterns()
+                    .iter()
+                    .map(|regex| Rule {
+                        label: "secret".into(),
+                        regex: regex.clone(),
+                    }),
+            );
+        }
+        for word in &config.banned_words {
+            rules.push(Rule {
+                label: format!("banned word \"{word}\""),
+                regex: RegexBuilder::new(&format!(r"\b{}\b", regex::escape(word)))
+                    .case_insensitive(true)
+                    .build()?,
+            });
+        }
+        for pattern in &config.banned_patterns {
+            rules.push(Rule {
+                label: format!("pattern `{pattern}`"),
+                regex: Regex::new(pattern)?,
+            });
+        }
+
+        Ok(Some(Self {
+            config: config.clone(),
+            rules,
+            llm_manager,
+        }))
+    }
+
+    /// Whether replies can be streamed as drafts. A draft can't be taken
+    /// back, so streaming is off when a whole response may still be blocked,
+    /// by the `block` action or the moderation model.
+    pub fn allows_streaming(&self) -> bool {
+        self.config.action != GuardrailAction::Block && self.config.moderation_model.is_none()
+    }
+
+    /// Check text against the pattern rules, then the moderation model when
+    /// `moderate` is set and one is configured.
+    pub async fn check(&self, text: &str, moderate: bool) -> Verdict {
+        let verdict = self.check_rules(text);
+        if matches!(verdict, Verdict::Blocked { .. }) || !moderate {
+            return verdict;
+        }
+        let Some(model_name) = &self.config.moderation_model else {
+            return verdict;
+        };
+
+        let checked = match &verdict {
+            Verdict::Rewritten { text, .. } => text.as_str(),
+            _ => text,
+        };
+        match self.moderate(model_name, checked).await {
+            Some(reason) => Verdict::Blocked { reason },
+            None => verdict,
+        }
+    }
+
+    fn check_rules(&self, text: &str) -> Verdict {
+        let matched: Vec<&Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(text))
+            .collect();
+        if matched.is_empty() {
+            return Verdict::Pass;
+        }
+
+        let mut labels: Vec<String> = matched.iter().map(|rule| rule.label.clone()).collect();
+        labels.dedup();
+        match self.config.action {
+            GuardrailAction::Block => Verdict::Blocked {
+                reason: labels.join(", "),
+            },
+            GuardrailAction::Redact => Verdict::Rewritten {
+                text: redact(text, &matched),
+                rules: labels,
+            },
+        }
+    }
+
+    /// Ask the moderation model about `text`. Returns the rejection reason,
+    /// or None if it's allowed. Fails open: an error or unreadable answer lets
+    /// the text through, since the pattern rules have already run.
+    async fn moderate(&self, model_name: &str, text: &str) -> Option<String> {
+        let model = SpacebotModel::make(&self.llm_manager, model_name);
+        let agent = AgentBuilder::new(model)
+            .preamble(crate::prompts::text::get("moderation"))
+            .build();
+
+        let response = match agent.prompt(text).await {
+            Ok(response) => response,
+            Err(error) => {
+                tracing::warn!(%error, model = %model_name, "moderation call failed, allowing response");
+                return None;
+            }
+        };
+
+        let verdict = crate::llm::structured::parse_json_response(&response)
+            .and_then(serde_json::from_value::<ModerationVerdict>);
+        match verdict {
+            Ok(verdict) if !verdict.allowed => Some(format!("moderation: {}", verdict.reason)),
+            Ok(_) => None,
+            Err(error) => {
+                tracing::warn!(%error, model = %model_name, "unreadable moderation verdict, allowing response");
+                None
+            }
+        }
+    }
+
+    /// Filter text, returning what to send or None if it's blocked.
+    async fn filter_text(&self, text: String, moderate: bool) -> Option<String> {
+        match self.check(&text, moderate).await {
+            Verdict::Pass => Some(text),
+            Verdict::Rewritten { text, rules } => {
+                tracing::info!(rules = %rules.join(", "), "guardrails redacted outbound content");
+                Some(text)
+            }
+            Verdict::Blocked { reason } => {
+                tracing::warn!(%reason, "guardrails blocked outbound response");
+                None
+            }
+        }
+    }
+
+    /// Apply the guardrails to an outbound response.
+    ///
+    /// Blocked responses are replaced by `blocked_message`. Stream chunks are
+    /// only redacted: earlier chunks are already out, and a match split across
+    /// two chunks isn't seen.
+    pub async fn filter(&self, response: OutboundResponse) -> OutboundResponse {
+        let blocked = || self.config.blocked_message.clone();
+
+        match response {
+            OutboundResponse::Text(text) => {
+                OutboundResponse::Text(self.filter_text(text, true).await.unwrap_or_else(blocked))
+            }
+            OutboundResponse::ThreadReply { thread_name, text } => OutboundResponse::ThreadReply {
+                thread_name,
+                text: self.filter_text(text, true).await.unwrap_or_else(blocked),
+            },
+            OutboundResponse::Ephemeral { text, user_id } => OutboundResponse::Ephemeral {
+                text: self.filter_text(text, true).await.unwrap_or_else(blocked),
+                user_id,
+            },
+            OutboundResponse::ScheduledMessage { text, post_at } => {
+                OutboundResponse::ScheduledMessage {
+                    text: self.filter_text(text, true).await.unwrap_or_else(blocked),
+                    post_at,
+                }
+            }
+            OutboundResponse::RichMessage {
+                text,
+                blocks,
+                cards,
+                interactive_elements,
+                poll,
+            } => {
+                let Some(text) = self.filter_text(text, true).await else {
+                    return OutboundResponse::Text(blocked());
+                };
+                // Rich parts can't be rewritten in place, so any match in them
+                // falls back to the filtered plain text.
+                let rich = serde_json::json!([blocks, cards, interactive_elements, poll]);
+                if self.check_rules(&rich.to_string()) != Verdict::Pass {
+                    tracing::warn!("guardrails matched rich message content, sending text only");
+                    return OutboundResponse::Text(text);
+                }
+                OutboundResponse::RichMessage {
+                    text,
+                    blocks,
+                    cards,
+                    interactive_elements,
+                    poll,
+                }
+            }
+            OutboundResponse::StreamChunk(text) => {
+                let matched: Vec<&Rule> = self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.regex.is_match(&text))
+                    .collect();
+                OutboundResponse::StreamChunk(redact(&text, &matched))
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => {
+                let caption = match caption {
+                    Some(caption) => self.filter_text(caption, true).await,
+                    None => None,
+                };
+                OutboundResponse::File {
+                    filename,
+                    data,
+                    mime_type,
+                    caption,
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+fn redact(text: &str, rules: &[&Rule]) -> String {
+    rules.iter().fold(text.to_string(), |text, rule| {
+        rule.regex.replace_all(&text, REDACTED).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn filter(config: GuardrailsConfig) -> OutputFilter {
+        let llm_manager = Arc::new(
+            LlmManager::new(crate::config::LlmConfig::default())
+                .await
+                .expect("failed to build LLM manager"),
+        );
+        OutputFilter::from_config(&config, llm_manager)
+            .expect("valid patterns")
+            .expect("guardrails enabled")
+    }
+
+    #[tokio::test]
+    async fn test_streaming_is_off_when_responses_can_be_blocked() {
+        assert!(
+            filter(GuardrailsConfig {
+                enabled: true,
+                ..Default::default()
+            })
+            .await
+            .allows_streaming()
+        );
+        assert!(
+            !filter(GuardrailsConfig {
+                enabled: true,
+                action: GuardrailAction::Block,
+                ..Default::default()
+            })
+            .await
+            .allows_streaming()
+        );
+        assert!(
+            !filter(GuardrailsConfig {
+                enabled: true,
+                moderation_model: Some("anthropic/claude-haiku-4.5".into()),
+                ..Default::default()
+            })
+            .await
+            .allows_streaming()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redacts_secrets_and_banned_words() {
+        let filter = filter(GuardrailsConfig {
+            enabled: true,
+            banned_words: vec!["darn".into()],
+            ..Default::default()
+        })
+        .await;
+
+        let verdict = filter
+            .check("Darn, the key is sk-abcdefghijklmnopqrstuvwx", false)
+            .await;
+        assert_eq!(
+            verdict,
+            Verdict::Rewritten {
+                text: "[redacted], the key is [redacted]".into(),
+                rules: vec!["secret".into(), "banned word \"darn\"".into()],
+            }
+        );
+        // Word boundaries: "darned" isn't "darn".
+        assert_eq!(filter.check("darned socks", false).await, Verdict::Pass);
+    }
+
+    #[tokio::test]
+    async fn test_block_action_replaces_response() {
+        let filter = filter(GuardrailsConfig {
+            enabled: true,
+            banned_patterns: vec![r"\binternal-\d+\b".into()],
+            action: GuardrailAction::Block,
+            ..Default::default()
+        })
+        .await;
+
+        let response = filter
+            .filter(OutboundResponse::Text("see internal-42".into()))
+            .await;
+        assert!(matches!(
+            response,
+            OutboundResponse::Text(text) if text == "I can't send that response."
+        ));
+    }
+}
@@ -0,0 +1,566 @@
+//! IRC messaging adapter.
+//!
+//! Each channel is a conversation, `irc:<server>:<#channel>`, and each private
+//! message sender one, `irc:<server>:dm:<nick>`. Outbound lines are paced the
+//! way ircd flood control counts them, so long replies go out in bursts no
+//! faster than the server allows instead of getting the bot kicked.
+
+use crate::config::{IrcConfig, IrcPermissions};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, mpsc};
+
+/// Most bytes of message text per PRIVMSG. Lines are capped at 512 bytes
+/// including the prefix the server adds when relaying, so leave room for it.
+const MAX_TEXT_BYTES: usize = 400;
+
+/// Longest wait between reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(120);
+
+trait IrcStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IrcStream for T {}
+
+/// IRC adapter state.
+pub struct IrcAdapter {
+    config: IrcConfig,
+    permissions: Arc<ArcSwap<IrcPermissions>>,
+    outbound_tx: Arc<RwLock<Option<mpsc::Sender<String>>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl IrcAdapter {
+    pub fn new(config: &IrcConfig, permissions: Arc<ArcSwap<IrcPermissions>>) -> Self {
+        Self {
+            config: config.clone(),
+            permissions,
+            outbound_tx: Arc::new(RwLock::new(None)),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+            connected: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Queue `text` to `target`, a channel or nick, one PRIVMSG per line.
+    async fn send_text(&self, target: &str, text: &str) -> anyhow::Result<()> {
+        let outbound = self.outbound_tx.read().await;
+        let outbound = outbound.as_ref().context("irc client not connected")?;
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            for chunk in split_line(&sanitize(line), MAX_TEXT_BYTES) {
+                outbound
+                    .send(format!("PRIVMSG {target} :{chunk}"))
+                    .await
+                    .context("irc connection task stopped")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Messaging for IrcAdapter {
+    fn name(&self) -> &str {
+        "irc"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (outbound_tx, mut outbound_rx) = mpsc::channel::<String>(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.outbound_tx.write().await = Some(outbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let session = Session {
+            config: self.config.clone(),
+            permissions: self.permissions.clone(),
+            inbound_tx,
+            connected: self.connected.clone(),
+        };
+        let mut pacer = Pacer::new(
+            self.config.flood_burst,
+            Duration::from_millis(self.config.flood_interval_ms),
+        );
+
+        tokio::spawn(async move {
+            let mut delay = Duration::from_secs(5);
+            loop {
+                let started = Instant::now();
+                match session
+                    .run(&mut outbound_rx, &mut shutdown_rx, &mut pacer)
+                    .await
+                {
+                    Ok(Exit::Shutdown) => {
+                        tracing::info!("irc connection shutting down");
+                        break;
+                    }
+                    Ok(Exit::InboundClosed) => return,
+                    Ok(Exit::Disconnected) => {
+                        tracing::warn!(server = %session.config.server, "irc connection closed");
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            server = %session.config.server,
+                            %error,
+                            "irc connection failed"
+                        );
+                    }
+                }
+                session.connected.store(false, Ordering::Relaxed);
+
+                // A connection that lasted a while resets the backoff.
+                if started.elapsed() > MAX_RECONNECT_DELAY {
+                    delay = Duration::from_secs(5);
+                }
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let target = message
+            .metadata
+            .get("irc_target")
+            .and_then(|v| v.as_str())
+            .context("missing irc_target in metadata")?;
+
+        match response {
+            // IRC has no threads, ephemeral, or scheduled messages.
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.send_text(target, &text).await?;
+            }
+            OutboundResponse::File {
+                filename, caption, ..
+            } => {
+                // IRC is text-only — send a note about the file
+                let text = match caption {
+                    Some(caption) => format!("[File: {filename}] {caption}"),
+                    None => format!("[File: {filename}]"),
+                };
+                self.send_text(target, &text).await?;
+            }
+            // IRC can't edit messages, so streaming is buffered: the final
+            // text arrives as a Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort => {}
+            OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            self.send_text(target, &text).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("irc client not connected").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.outbound_tx.write().await = None;
+
+        tracing::info!("irc adapter shut down");
+        Ok(())
+    }
+}
+
+/// Why a connection ended.
+enum Exit {
+    Shutdown,
+    Disconnected,
+    /// The inbound receiver was dropped, so there's no one to deliver to.
+    InboundClosed,
+}
+
+/// Everything a connection needs, kept across reconnects.
+struct Session {
+    config: IrcConfig,
+    permissions: Arc<ArcSwap<IrcPermissions>>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    connected: Arc<AtomicBool>,
+}
+
+impl Session {
+    async fn connect(&self) -> anyhow::Result<Box<dyn IrcStream>> {
+        let IrcConfig { server, port, .. } = &self.config;
+        let tcp = TcpStream::connect((server.as_str(), *port))
+            .await
+            .with_context(|| format!("failed to connect to {server}:{port}"))?;
+        if !self.config.tls {
+            return Ok(Box::new(tcp));
+        }
+
+        let stream = crate::messaging::tls::connect(server, tcp).await?;
+        Ok(Box::new(stream))
+    }
+
+    /// Run one connection until it drops or the adapter shuts down.
+    async fn run(
+        &self,
+        outbound_rx: &mut mpsc::Receiver<String>,
+        shutdown_rx: &mut mpsc::Receiver<()>,
+        pacer: &mut Pacer,
+    ) -> anyhow::Result<Exit> {
+        let stream = self.connect().await?;
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+
+        if let Some(password) = &self.config.password {
+            write_line(&mut writer, &format!("PASS {password}")).await?;
+        }
+        let mut nick = self.config.nick.clone();
+        write_line(&mut writer, &format!("NICK {nick}")).await?;
+        write_line(&mut writer, &format!("USER {nick} 0 * :Spacebot")).await?;
+
+        let mut buffer = Vec::new();
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    write_line(&mut writer, "QUIT :Shutting down").await.ok();
+                    return Ok(Exit::Shutdown);
+                }
+                read = reader.read_until(b'\n', &mut buffer) => {
+                    if read? == 0 {
+                        return Ok(Exit::Disconnected);
+                    }
+                    let line = String::from_utf8_lossy(&buffer).into_owned();
+                    buffer.clear();
+                    let Some(message) = parse_line(&line) else {
+                        continue;
+                    };
+
+                    match message.command {
+                        "PING" => {
+                            let token = message.params.first().copied().unwrap_or_default();
+                            write_line(&mut writer, &format!("PONG :{token}")).await?;
+                        }
+                        // Welcome: registered with the server.
+                        "001" => {
+                            if let Some(current) = message.params.first() {
+                                nick = current.to_string();
+                            }
+                            if let Some(password) = &self.config.nickserv_password {
+                                write_line(
+                                    &mut writer,
+                                    &format!("PRIVMSG NickServ :IDENTIFY {password}"),
+                                )
+                                .await?;
+                            }
+                            for channel in &self.config.channels {
+                                write_line(&mut writer, &format!("JOIN {channel}")).await?;
+                            }
+                            self.connected.store(true, Ordering::Relaxed);
+                            tracing::info!(
+                                server = %self.config.server,
+                                nick = %nick,
+                                channels = ?self.config.channels,
+                                "irc connected"
+                            );
+                        }
+                        // Nick in use: try another.
+                        "433" => {
+                            nick.push('_');
+                            write_line(&mut writer, &format!("NICK {nick}")).await?;
+                        }
+                        // Our own nick changed.
+                        "NICK"
+                            if message
+                                .nick()
+                                .is_some_and(|from| from.eq_ignore_ascii_case(&nick)) =>
+                        {
+                            if let Some(new_nick) = message.params.first() {
+                                nick = new_nick.to_string();
+                            }
+                        }
+                        "PRIVMSG" => {
+                            let Some(inbound) =
+                                inbound_from_privmsg(&self.config.server, &nick, &message)
+                            else {
+                                continue;
+                            };
+                            if !self.permissions.load().allows(&inbound) {
+                                continue;
+                            }
+                            if self.inbound_tx.send(inbound).await.is_err() {
+                                tracing::warn!(
+                                    "failed to send inbound message from IRC (receiver dropped)"
+                                );
+                                return Ok(Exit::InboundClosed);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Some(line) = outbound_rx.recv() => {
+                    let wait = pacer.delay(Instant::now());
+                    if !wait.is_zero() {
+                        tokio::time::sleep(wait).await;
+                    }
+                    write_line(&mut writer, &line).await?;
+                }
+            }
+        }
+    }
+}
+
+async fn write_line(writer: &mut (impl AsyncWrite + Unpin), line: &str) -> anyhow::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Paces outbound lines the way ircd flood control counts them: each line
+/// moves a clock `interval` ahead of now, and a client whose clock runs more
+/// than `burst` lines ahead is flooding. Lines that would put it there wait.
+struct Pacer {
+    interval: Duration,
+    window: Duration,
+    clock: Instant,
+}
+
+impl Pacer {
+    fn new(burst: u32, interval: Duration) -> Self {
+        Self {
+            interval,
+            window: interval * burst.max(1),
+            clock: Instant::now(),
+        }
+    }
+
+    /// How long to wait before sending a line at `now`, counting the line.
+    fn delay(&mut self, now: Instant) -> Duration {
+        let clock = self.clock.max(now) + self.interval;
+        self.clock = clock;
+        clock.saturating_duration_since(now + self.window)
+    }
+}
+
+/// A parsed IRC line.
+struct IrcMessage<'a> {
+    prefix: Option<&'a str>,
+    command: &'a str,
+    params: Vec<&'a str>,
+}
+
+impl IrcMessage<'_> {
+    /// The nick of the prefix, `nick!user@host`.
+    fn nick(&self) -> Option<&str> {
+        self.prefix
+            .map(|prefix| prefix.split('!').next().unwrap_or(prefix))
+    }
+}
+
+/// Parse an IRC line, skipping IRCv3 message tags.
+fn parse_line(line: &str) -> Option<IrcMessage<'_>> {
+    let mut rest = line.trim_end_matches(['\r', '\n']);
+    if rest.starts_with('@') {
+        rest = rest.split_once(' ')?.1;
+    }
+    let prefix = match rest.strip_prefix(':') {
+        Some(prefixed) => {
+            let (prefix, after) = prefixed.split_once(' ')?;
+            rest = after;
+            Some(prefix)
+        }
+        None => None,
+    };
+
+    let (head, trailing) = match rest.split_once(" :") {
+        Some((head, trailing)) => (head, Some(trailing)),
+        None => (rest, None),
+    };
+    let mut parts = head.split(' ').filter(|part| !part.is_empty());
+    let command = parts.next()?;
+    let mut params: Vec<&str> = parts.collect();
+    params.extend(trailing);
+
+    Some(IrcMessage {
+        prefix,
+        command,
+        params,
+    })
+}
+
+/// Build an inbound message from a PRIVMSG. None for CTCP other than ACTION.
+fn inbound_from_privmsg(
+    server: &str,
+    bot_nick: &str,
+    message: &IrcMessage<'_>,
+) -> Option<InboundMessage> {
+    let sender = message.nick()?;
+    let (target, text) = match message.params.as_slice() {
+        [target, text, ..] => (*target, *text),
+        _ => return None,
+    };
+
+    let text = match text.strip_prefix('\u{1}') {
+        Some(ctcp) => format!(
+            "* {}",
+            ctcp.strip_prefix("ACTION ")?.trim_end_matches('\u{1}')
+        ),
+        None => text.to_string(),
+    };
+
+    let is_private = target.eq_ignore_ascii_case(bot_nick);
+    let (conversation_id, reply_target) = if is_private {
+        (format!("irc:{server}:dm:{sender}"), sender)
+    } else {
+        (format!("irc:{server}:{target}"), target)
+    };
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "irc_target".into(),
+        serde_json::Value::String(reply_target.into()),
+    );
+    if !is_private {
+        metadata.insert(
+            "irc_channel".into(),
+            serde_json::Value::String(target.into()),
+        );
+    }
+    metadata.insert("irc_nick".into(), serde_json::Value::String(sender.into()));
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(sender.into()),
+    );
+    if mentions(&text, bot_nick) {
+        metadata.insert("irc_mentions_bot".into(), serde_json::Value::Bool(true));
+    }
+
+    Some(InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "irc".into(),
+        conversation_id,
+        sender_id: sender.to_string(),
+        agent_id: None,
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(sender.to_string()),
+    })
+}
+
+/// Whether `text` names `nick` as a word, like `spacebot: hi` or `thanks spacebot!`.
+pub(super) fn mentions(text: &str, nick: &str) -> bool {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| matches!(c, ',' | ':' | ';' | '.' | '!' | '?' | '@'))
+        })
+        .any(|word| word.eq_ignore_ascii_case(nick))
+}
+
+/// Strip characters that would end or corrupt an IRC line.
+fn sanitize(line: &str) -> String {
+    line.chars()
+        .filter(|c| !matches!(c, '\r' | '\n' | '\0'))
+        .collect()
+}
+
+/// Split a line into chunks of at most `max_bytes`, at spaces where possible.
+fn split_line(line: &str, max_bytes: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut remaining = line;
+
+    while remaining.len() > max_bytes {
+        let mut limit = max_bytes;
+        while !remaining.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        let split_at = remaining[..limit]
+            .rfind(' ')
+            .filter(|&index| index > 0)
+            .unwrap_or(limit);
+        chunks.push(remaining[..split_at].to_string());
+        remaining = remaining[split_at..].trim_start();
+    }
+    if !remaining.is_empty() {
+        chunks.push(remaining.to_string());
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_privmsgs_map_to_conversations_and_output_is_paced() {
+        let line =
+            "@time=2026-01-01T00:00:00Z :alice!a@host PRIVMSG #rust :spacebot: any ideas?\r\n";
+        let message = parse_line(line).expect("line");
+        assert_eq!(message.command, "PRIVMSG");
+        assert_eq!(message.params, vec!["#rust", "spacebot: any ideas?"]);
+
+        let inbound =
+            inbound_from_privmsg("irc.libera.chat", "spacebot", &message).expect("inbound");
+        assert_eq!(inbound.conversation_id, "irc:irc.libera.chat:#rust");
+        assert_eq!(inbound.metadata["irc_target"], "#rust");
+        assert_eq!(inbound.metadata["irc_mentions_bot"], true);
+
+        let private = parse_line(":alice!a@host PRIVMSG spacebot :\u{1}ACTION waves\u{1}").unwrap();
+        let inbound = inbound_from_privmsg("irc.libera.chat", "spacebot", &private).unwrap();
+        assert_eq!(inbound.conversation_id, "irc:irc.libera.chat:dm:alice");
+        assert_eq!(inbound.metadata["irc_target"], "alice");
+        assert!(matches!(&inbound.content, MessageContent::Text(text) if text == "* waves"));
+        assert!(!mentions("spacebots are neat", "spacebot"));
+
+        let mut pacer = Pacer::new(3, Duration::from_secs(2));
+        let now = Instant::now();
+        let delays: Vec<Duration> = (0..5).map(|_| pacer.delay(now)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::from_secs(2),
+                Duration::from_secs(4),
+            ]
+        );
+
+        let chunks = split_line(&"word ".repeat(200), MAX_TEXT_BYTES);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= MAX_TEXT_BYTES));
+        assert_eq!(chunks.join(" ").split_whitespace().count(), 200);
+    }
+}
@@ -1,9 +1,14 @@
 //! MessagingManager: Fan-in and routing for all adapters.
 
+use crate::events::{self, BusEvent};
+use crate::messaging::guardrails::OutputFilter;
+use crate::messaging::presence::{Presence, PresenceBoard};
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging, MessagingDyn};
-use crate::{InboundMessage, OutboundResponse, StatusUpdate};
+use crate::messaging::webchat::WebChatAdapter;
+use crate::{InboundMessage, ModerationAction, OutboundResponse, StatusUpdate};
 
 use anyhow::Context as _;
+use arc_swap::ArcSwapOption;
 use futures::StreamExt as _;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -19,8 +24,20 @@ pub struct MessagingManager {
     fan_in_tx: mpsc::Sender<InboundMessage>,
     /// Receiver side, taken once by `start()`.
     fan_in_rx: RwLock<Option<mpsc::Receiver<InboundMessage>>>,
+    /// Outbound guardrails, swapped on config reload. None when disabled.
+    output_filter: ArcSwapOption<OutputFilter>,
+    /// Handle to the registered webchat adapter, for callers that open their
+    /// own sessions (the API and agent-to-agent questions).
+    webchat: ArcSwapOption<WebChatAdapter>,
+    /// What the agents are doing, shown in the bot's presence.
+    presence: Arc<PresenceBoard>,
 }
 
+/// Minimum time between presence updates sent to the adapters. Changes in
+/// between are folded into the next update; Discord rate-limits presence
+/// changes.
+const PRESENCE_UPDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 impl MessagingManager {
     pub fn new() -> Self {
         let (fan_in_tx, fan_in_rx) = mpsc::channel(512);
@@ -28,9 +45,69 @@ impl MessagingManager {
             adapters: RwLock::new(HashMap::new()),
             fan_in_tx,
             fan_in_rx: RwLock::new(Some(fan_in_rx)),
+            output_filter: ArcSwapOption::empty(),
+            webchat: ArcSwapOption::empty(),
+            presence: Arc::default(),
+        }
+    }
+
+    /// The board channels report their activity to.
+    pub fn presence(&self) -> Arc<PresenceBoard> {
+        self.presence.clone()
+    }
+
+    /// Push the presence to every adapter whenever it changes, until the
+    /// manager is dropped.
+    pub fn spawn_presence_updates(self: &Arc<Self>) {
+        let mut presence_rx = self.presence.subscribe();
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while presence_rx.changed().await.is_ok() {
+                let presence = presence_rx.borrow_and_update().clone();
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                manager.set_presence(presence).await;
+                drop(manager);
+                tokio::time::sleep(PRESENCE_UPDATE_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Show a presence on every adapter that supports one.
+    pub async fn set_presence(&self, presence: Presence) {
+        let adapters = self.adapters.read().await;
+        for (name, adapter) in adapters.iter() {
+            if let Err(error) = adapter.set_presence(presence.clone()).await {
+                tracing::debug!(adapter = %name, %error, "failed to update presence");
+            }
         }
     }
 
+    /// Install or clear the outbound guardrails.
+    pub fn set_output_filter(&self, filter: Option<OutputFilter>) {
+        self.output_filter.store(filter.map(Arc::new));
+    }
+
+    /// Run a response through the outbound guardrails, if enabled.
+    ///
+    /// `broadcast` does this itself. Callers of `respond` filter first so
+    /// anything they mirror elsewhere (e.g. SSE) matches what was sent.
+    pub async fn filter_outbound(&self, response: OutboundResponse) -> OutboundResponse {
+        match self.output_filter.load_full() {
+            Some(filter) => filter.filter(response).await,
+            None => response,
+        }
+    }
+
+    /// Whether the outbound guardrails let replies be streamed as drafts.
+    pub fn allows_token_streaming(&self) -> bool {
+        self.output_filter
+            .load()
+            .as_ref()
+            .is_none_or(|filter| filter.allows_streaming())
+    }
+
     /// Register an adapter (before start). Use `register_and_start` for runtime addition.
     pub async fn register(&self, adapter: impl Messaging) {
         let name = adapter.name().to_string();
@@ -45,6 +122,16 @@ impl MessagingManager {
         self.adapters.write().await.insert(name, adapter);
     }
 
+    /// Register the webchat adapter and keep a handle to it.
+    pub async fn register_webchat(&self, adapter: Arc<WebChatAdapter>) {
+        self.register_shared(adapter.clone()).await;
+        self.webchat.store(Some(adapter));
+    }
+
+    pub fn webchat(&self) -> Option<Arc<WebChatAdapter>> {
+        self.webchat.load_full()
+    }
+
     /// Start all registered adapters and return the merged inbound stream.
     ///
     /// Each adapter's stream is forwarded into a shared channel, so adapters
@@ -53,7 +140,13 @@ impl MessagingManager {
         let adapters = self.adapters.read().await;
         for (name, adapter) in adapters.iter() {
             match adapter.start().await {
-                Ok(stream) => Self::spawn_forwarder(name.clone(), stream, self.fan_in_tx.clone()),
+                Ok(stream) => {
+                    Self::spawn_forwarder(name.clone(), stream, self.fan_in_tx.clone());
+                    events::publish(BusEvent::LinkChanged {
+                        adapter: name.clone(),
+                        linked: true,
+                    });
+                }
                 Err(error) => {
                     tracing::error!(adapter = %name, %error, "adapter failed to start, skipping")
                 }
@@ -103,6 +196,10 @@ impl MessagingManager {
         self.adapters.write().await.insert(name.clone(), adapter);
 
         tracing::info!(adapter = %name, "adapter registered and started at runtime");
+        events::publish(BusEvent::LinkChanged {
+            adapter: name,
+            linked: true,
+        });
         Ok(())
     }
 
@@ -169,6 +266,7 @@ impl MessagingManager {
         target: &str,
         response: OutboundResponse,
     ) -> crate::Result<()> {
+        let response = self.filter_outbound(response).await;
         let adapters = self.adapters.read().await;
         let adapter = adapters
             .get(adapter_name)
@@ -176,6 +274,32 @@ impl MessagingManager {
         adapter.broadcast(target, response).await
     }
 
+    /// Take a moderation action through the adapter that owns the conversation.
+    pub async fn moderate(
+        &self,
+        conversation_id: &str,
+        action: ModerationAction,
+        reason: &str,
+    ) -> crate::Result<()> {
+        let adapter_name = conversation_id.split(':').next().unwrap_or(conversation_id);
+        let adapters = self.adapters.read().await;
+        let adapter = adapters
+            .get(adapter_name)
+            .with_context(|| format!("no messaging adapter named '{adapter_name}'"))?;
+        adapter.moderate(conversation_id, action, reason).await
+    }
+
+    /// Set the tags of a forum post through the adapter that owns the
+    /// conversation.
+    pub async fn tag_post(&self, conversation_id: &str, tags: Vec<String>) -> crate::Result<()> {
+        let adapter_name = conversation_id.split(':').next().unwrap_or(conversation_id);
+        let adapters = self.adapters.read().await;
+        let adapter = adapters
+            .get(adapter_name)
+            .with_context(|| format!("no messaging adapter named '{adapter_name}'"))?;
+        adapter.tag_post(conversation_id, tags).await
+    }
+
     /// Fetch recent message history from the platform for context backfill.
     pub async fn fetch_history(
         &self,
@@ -193,6 +317,10 @@ impl MessagingManager {
     pub async fn remove_adapter(&self, name: &str) -> crate::Result<()> {
         let adapter = self.adapters.write().await.remove(name);
         if let Some(adapter) = adapter {
+            events::publish(BusEvent::LinkChanged {
+                adapter: name.to_string(),
+                linked: false,
+            });
             adapter.shutdown().await?;
             tracing::info!(adapter = %name, "adapter removed and shut down");
         }
@@ -0,0 +1,755 @@
+//! Matrix messaging adapter using the client-server API.
+//!
+//! Each joined room is a conversation, `matrix:<room_id>`. The adapter long
+//! polls `/sync` with the bot account's access token, so it works against any
+//! homeserver without an appservice registration. Encrypted rooms aren't
+//! supported: their events are skipped with a warning, so run the bot in
+//! unencrypted rooms.
+
+use crate::config::MatrixPermissions;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{Attachment, InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use reqwest::{Method, StatusCode, Url};
+use serde_json::{Value, json};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{RwLock, mpsc};
+
+/// How long the homeserver holds a `/sync` request open waiting for events.
+const SYNC_TIMEOUT_MS: u64 = 30_000;
+
+/// Matrix has no hard message limit, but long events render poorly in clients.
+const MAX_MESSAGE_LENGTH: usize = 16_000;
+
+/// How long the typing indicator lasts unless renewed or cancelled.
+const TYPING_TIMEOUT_MS: u64 = 30_000;
+
+/// Matrix client adapter state.
+pub struct MatrixAdapter {
+    homeserver_url: String,
+    rooms: Vec<String>,
+    auto_join: bool,
+    permissions: Arc<ArcSwap<MatrixPermissions>>,
+    client: Arc<MatrixClient>,
+    user_id: Arc<RwLock<Option<String>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+impl MatrixAdapter {
+    pub fn new(
+        homeserver_url: impl Into<String>,
+        access_token: impl Into<String>,
+        rooms: Vec<String>,
+        auto_join: bool,
+        permissions: Arc<ArcSwap<MatrixPermissions>>,
+    ) -> Self {
+        let homeserver_url = homeserver_url.into();
+        Self {
+            client: Arc::new(MatrixClient::new(&homeserver_url, &access_token.into())),
+            homeserver_url,
+            rooms,
+            auto_join,
+            permissions,
+            user_id: Arc::new(RwLock::new(None)),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+}
+
+impl Messaging for MatrixAdapter {
+    fn name(&self) -> &str {
+        "matrix"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let whoami = self
+            .client
+            .request(Method::GET, &["account", "whoami"], None)
+            .await
+            .context("failed to authenticate with the matrix homeserver")?;
+        let user_id = whoami["user_id"]
+            .as_str()
+            .context("matrix whoami response missing user_id")?
+            .to_string();
+
+        // Join configured rooms. Aliases resolve to the room ID on join.
+        for room in &self.rooms {
+            if let Err(error) = self.client.join(room).await {
+                tracing::error!(room = %room, %error, "failed to join matrix room");
+            }
+        }
+
+        // Start from now: an initial sync with an empty timeline skips the
+        // backlog of every joined room.
+        let initial = self
+            .client
+            .sync(None, 0)
+            .await
+            .context("failed initial matrix sync")?;
+        let mut since = initial["next_batch"].as_str().map(String::from);
+
+        tracing::info!(
+            homeserver = %self.homeserver_url,
+            user_id = %user_id,
+            rooms = ?self.rooms,
+            "matrix connected"
+        );
+
+        *self.user_id.write().await = Some(user_id.clone());
+
+        let client = self.client.clone();
+        let permissions = self.permissions.clone();
+        let homeserver_url = self.homeserver_url.clone();
+        let auto_join = self.auto_join;
+
+        tokio::spawn(async move {
+            let mut warned_encrypted = HashSet::new();
+            loop {
+                let response = tokio::select! {
+                    _ = shutdown_rx.recv() => {
+                        tracing::info!("matrix sync loop shutting down");
+                        break;
+                    }
+                    response = client.sync(since.as_deref(), SYNC_TIMEOUT_MS) => response,
+                };
+
+                let response = match response {
+                    Ok(response) => response,
+                    Err(error) => {
+                        tracing::warn!(%error, "matrix sync failed, retrying");
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        continue;
+                    }
+                };
+                if let Some(next_batch) = response["next_batch"].as_str() {
+                    since = Some(next_batch.to_string());
+                }
+
+                let permissions = permissions.load();
+
+                if let Some(invites) = response["rooms"]["invite"].as_object() {
+                    for room_id in invites.keys() {
+                        if !auto_join || !permissions.allows_room(room_id) {
+                            continue;
+                        }
+                        if let Err(error) = client.join(room_id).await {
+                            tracing::warn!(
+                                room_id = %room_id,
+                                %error,
+                                "failed to join matrix room on invite"
+                            );
+                        }
+                    }
+                }
+
+                let Some(joined) = response["rooms"]["join"].as_object() else {
+                    continue;
+                };
+                for (room_id, room) in joined {
+                    if !permissions.allows_room(room_id) {
+                        continue;
+                    }
+                    let Some(events) = room["timeline"]["events"].as_array() else {
+                        continue;
+                    };
+                    for event in events {
+                        if event["type"] == "m.room.encrypted" {
+                            if warned_encrypted.insert(room_id.clone()) {
+                                tracing::warn!(
+                                    room_id = %room_id,
+                                    "skipping encrypted matrix room, encryption isn't supported"
+                                );
+                            }
+                            continue;
+                        }
+                        let Some(inbound) =
+                            inbound_from_event(room_id, event, &user_id, &homeserver_url)
+                        else {
+                            continue;
+                        };
+                        if !permissions.allowed_users.is_empty()
+                            && !permissions.allowed_users.contains(&inbound.sender_id)
+                        {
+                            continue;
+                        }
+
+                        if let Err(error) = inbound_tx.send(inbound).await {
+                            tracing::warn!(
+                                %error,
+                                "failed to send inbound message from Matrix (receiver dropped)"
+                            );
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let typing = match status {
+            StatusUpdate::Thinking => true,
+            StatusUpdate::StopTyping => false,
+            _ => return Ok(()),
+        };
+        let Some(user_id) = self.user_id.read().await.clone() else {
+            return Ok(());
+        };
+        let room_id = extract_room_id(message)?;
+
+        let body = if typing {
+            json!({ "typing": true, "timeout": TYPING_TIMEOUT_MS })
+        } else {
+            json!({ "typing": false })
+        };
+        self.client
+            .request(
+                Method::PUT,
+                &["rooms", room_id, "typing", user_id.as_str()],
+                Some(body),
+            )
+            .await
+            .context("failed to set matrix typing indicator")?;
+        Ok(())
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let room_id = extract_room_id(message)?;
+        let event_id = message
+            .metadata
+            .get("matrix_event_id")
+            .and_then(|v| v.as_str());
+
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                    self.client
+                        .send_message(room_id, text_content(chunk))
+                        .await
+                        .context("failed to send matrix message")?;
+                }
+            }
+            OutboundResponse::ThreadReply { text, .. } => {
+                // Reply in the thread rooted at the triggering event, or the
+                // thread it's already in.
+                let root = message
+                    .metadata
+                    .get("matrix_thread_root")
+                    .and_then(|v| v.as_str())
+                    .or(event_id);
+                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                    let mut content = text_content(chunk);
+                    if let Some(root) = root {
+                        content["m.relates_to"] = json!({
+                            "rel_type": "m.thread",
+                            "event_id": root,
+                            "is_falling_back": true,
+                            "m.in_reply_to": { "event_id": event_id.unwrap_or(root) },
+                        });
+                    }
+                    self.client
+                        .send_message(room_id, content)
+                        .await
+                        .context("failed to send matrix thread reply")?;
+                }
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => {
+                let size = data.len();
+                let content_uri = self
+                    .client
+                    .upload(&filename, &mime_type, data)
+                    .await
+                    .context("failed to upload matrix file")?;
+                let msgtype = match mime_type.split('/').next() {
+                    Some("image") => "m.image",
+                    Some("audio") => "m.audio",
+                    Some("video") => "m.video",
+                    _ => "m.file",
+                };
+                let content = json!({
+                    "msgtype": msgtype,
+                    "body": caption.as_deref().unwrap_or(&filename),
+                    "filename": filename,
+                    "url": content_uri,
+                    "info": { "mimetype": mime_type, "size": size },
+                });
+                self.client
+                    .send_message(room_id, content)
+                    .await
+                    .context("failed to send matrix file")?;
+            }
+            OutboundResponse::Reaction(emoji) => {
+                let event_id = event_id.context("missing matrix_event_id for reaction")?;
+                let content = json!({
+                    "m.relates_to": {
+                        "rel_type": "m.annotation",
+                        "event_id": event_id,
+                        "key": emoji,
+                    }
+                });
+                self.client
+                    .send_event(room_id, "m.reaction", content)
+                    .await
+                    .context("failed to add matrix reaction")?;
+            }
+            // Removing a reaction means redacting its event, which the
+            // adapter doesn't track.
+            OutboundResponse::RemoveReaction(_) => {}
+            // Streaming is buffered: the final text arrives as a Text response
+            // after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort => {}
+            OutboundResponse::Status(status) => {
+                self.send_status(message, status).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let room_id = target.strip_prefix("matrix:").unwrap_or(target);
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+                self.client
+                    .send_message(room_id, text_content(chunk))
+                    .await
+                    .context("failed to broadcast matrix message")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        self.client
+            .request(Method::GET, &["account", "whoami"], None)
+            .await
+            .context("matrix homeserver unreachable")?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.user_id.write().await = None;
+
+        tracing::info!("matrix adapter shut down");
+        Ok(())
+    }
+}
+
+/// Thin client for the Matrix client-server API.
+struct MatrixClient {
+    http: reqwest::Client,
+    base: String,
+    access_token: String,
+    /// Counter for transaction IDs, which make sends idempotent on retry.
+    next_txn: AtomicU64,
+}
+
+impl MatrixClient {
+    fn new(homeserver_url: &str, access_token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base: homeserver_url.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+            next_txn: AtomicU64::new(0),
+        }
+    }
+
+    /// URL of a client-server API endpoint, with each segment percent-encoded.
+    fn url(&self, prefix: &[&str], segments: &[&str]) -> anyhow::Result<Url> {
+        let mut url = Url::parse(&self.base).context("invalid matrix homeserver_url")?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("invalid matrix homeserver_url"))?
+            .pop_if_empty()
+            .extend(prefix)
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        segments: &[&str],
+        body: Option<Value>,
+    ) -> anyhow::Result<Value> {
+        let url = self.url(&["_matrix", "client", "v3"], segments)?;
+        self.send(method, url, body).await
+    }
+
+    /// Send a request, waiting out the homeserver's rate limit once.
+    async fn send(&self, method: Method, url: Url, body: Option<Value>) -> anyhow::Result<Value> {
+        for attempt in 0..2 {
+            let mut request = self
+                .http
+                .request(method.clone(), url.clone())
+                .bearer_auth(&self.access_token);
+            if let Some(body) = &body {
+                request = request.json(body);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            let value: Value = response.json().await.unwrap_or(Value::Null);
+
+            if status == StatusCode::TOO_MANY_REQUESTS && attempt == 0 {
+                let retry_after = value["retry_after_ms"].as_u64().unwrap_or(1_000);
+                tokio::time::sleep(Duration::from_millis(retry_after)).await;
+                continue;
+            }
+            if !status.is_success() {
+                anyhow::bail!(
+                    "matrix request failed with {status}: {}",
+                    value["error"].as_str().unwrap_or("no error message")
+                );
+            }
+            return Ok(value);
+        }
+        anyhow::bail!("matrix request rate limited")
+    }
+
+    async fn sync(&self, since: Option<&str>, timeout_ms: u64) -> anyhow::Result<Value> {
+        let mut url = self.url(&["_matrix", "client", "v3"], &["sync"])?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("timeout", &timeout_ms.to_string());
+            match since {
+                Some(since) => {
+                    query.append_pair("since", since);
+                }
+                None => {
+                    query.append_pair("filter", r#"{"room":{"timeline":{"limit":0}}}"#);
+                }
+            }
+        }
+        let request = self
+            .http
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .timeout(Duration::from_millis(timeout_ms + 30_000));
+        let response = request.send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    async fn join(&self, room: &str) -> anyhow::Result<()> {
+        self.request(Method::POST, &["join", room], Some(json!({})))
+            .await?;
+        Ok(())
+    }
+
+    async fn send_message(&self, room_id: &str, content: Value) -> anyhow::Result<()> {
+        self.send_event(room_id, "m.room.message", content).await
+    }
+
+    async fn send_event(
+        &self,
+        room_id: &str,
+        event_type: &str,
+        content: Value,
+    ) -> anyhow::Result<()> {
+        let txn_id = format!(
+            "spacebot.{}.{}",
+            chrono::Utc::now().timestamp_millis(),
+            self.next_txn.fetch_add(1, Ordering::Relaxed)
+        );
+        self.request(
+            Method::PUT,
+            &["rooms", room_id, "send", event_type, txn_id.as_str()],
+            Some(content),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Upload a file to the media repository, returning its `mxc://` URI.
+    async fn upload(
+        &self,
+        filename: &str,
+        mime_type: &str,
+        data: Vec<u8>,
+    ) -> anyhow::Result<String> {
+        let mut url = self.url(&["_matrix", "media", "v3"], &["upload"])?;
+        url.query_pairs_mut().append_pair("filename", filename);
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(&self.access_token)
+            .header(reqwest::header::CONTENT_TYPE, mime_type)
+            .body(data)
+            .send()
+            .await?
+            .error_for_status()?;
+        let value: Value = response.json().await?;
+        value["content_uri"]
+            .as_str()
+            .map(String::from)
+            .context("matrix upload response missing content_uri")
+    }
+}
+
+/// Build an inbound message from a room timeline event. None for events that
+/// aren't messages, edits, or the bot's own.
+fn inbound_from_event(
+    room_id: &str,
+    event: &Value,
+    bot_user_id: &str,
+    homeserver_url: &str,
+) -> Option<InboundMessage> {
+    if event["type"] != "m.room.message" {
+        return None;
+    }
+    let sender = event["sender"].as_str()?;
+    if sender == bot_user_id {
+        return None;
+    }
+    let event_id = event["event_id"].as_str()?;
+    let content = &event["content"];
+    let relates_to = &content["m.relates_to"];
+    // Edits arrive as new events replacing an earlier one.
+    if relates_to["rel_type"] == "m.replace" {
+        return None;
+    }
+
+    let body = content["body"].as_str().unwrap_or_default();
+    let message_content = match content["msgtype"].as_str()? {
+        "m.text" | "m.notice" => MessageContent::Text(body.to_string()),
+        "m.emote" => MessageContent::Text(format!("* {body}")),
+        "m.image" | "m.file" | "m.audio" | "m.video" => {
+            let filename = content["filename"].as_str().unwrap_or(body).to_string();
+            let attachment = Attachment {
+                filename: filename.clone(),
+                mime_type: content["info"]["mimetype"]
+                    .as_str()
+                    .unwrap_or("application/octet-stream")
+                    .to_string(),
+                url: media_download_url(homeserver_url, content["url"].as_str()?)?,
+                size_bytes: content["info"]["size"].as_u64(),
+            };
+            // The body is the caption when a filename is given separately.
+            let text = (body != filename).then(|| body.to_string());
+            MessageContent::Media {
+                text,
+                attachments: vec![attachment],
+            }
+        }
+        _ => return None,
+    };
+
+    let mentions_bot = content["m.mentions"]["user_ids"]
+        .as_array()
+        .is_some_and(|ids| ids.iter().any(|id| id == bot_user_id))
+        || body.contains(bot_user_id);
+
+    let display_name = localpart(sender);
+    let mut metadata = HashMap::new();
+    metadata.insert("matrix_room_id".into(), Value::String(room_id.into()));
+    metadata.insert("matrix_event_id".into(), Value::String(event_id.into()));
+    metadata.insert("matrix_user_id".into(), Value::String(sender.into()));
+    metadata.insert(
+        "sender_display_name".into(),
+        Value::String(display_name.into()),
+    );
+    if mentions_bot {
+        metadata.insert("matrix_mentions_bot".into(), Value::Bool(true));
+    }
+    if relates_to["rel_type"] == "m.thread"
+        && let Some(root) = relates_to["event_id"].as_str()
+    {
+        metadata.insert("matrix_thread_root".into(), Value::String(root.into()));
+    }
+    if let Some(reply_to) = relates_to["m.in_reply_to"]["event_id"].as_str() {
+        metadata.insert("reply_to_message_id".into(), Value::String(reply_to.into()));
+    }
+
+    let timestamp = event["origin_server_ts"]
+        .as_i64()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .unwrap_or_else(chrono::Utc::now);
+
+    Some(InboundMessage {
+        id: event_id.to_string(),
+        source: "matrix".into(),
+        conversation_id: format!("matrix:{room_id}"),
+        sender_id: sender.to_string(),
+        agent_id: None,
+        content: message_content,
+        timestamp,
+        metadata,
+        formatted_author: Some(format!("{display_name} ({sender})")),
+    })
+}
+
+/// The user part of a Matrix ID: `alice` for `@alice:example.org`.
+fn localpart(user_id: &str) -> &str {
+    let user_id = user_id.strip_prefix('@').unwrap_or(user_id);
+    user_id.split(':').next().unwrap_or(user_id)
+}
+
+/// A download URL for an `mxc://server/media_id` content URI.
+fn media_download_url(homeserver_url: &str, content_uri: &str) -> Option<String> {
+    let (server, media_id) = content_uri.strip_prefix("mxc://")?.split_once('/')?;
+    Some(format!(
+        "{}/_matrix/media/v3/download/{server}/{media_id}",
+        homeserver_url.trim_end_matches('/')
+    ))
+}
+
+fn text_content(text: String) -> Value {
+    json!({ "msgtype": "m.text", "body": text })
+}
+
+fn extract_room_id(message: &InboundMessage) -> anyhow::Result<&str> {
+    message
+        .metadata
+        .get("matrix_room_id")
+        .and_then(|v| v.as_str())
+        .context("missing matrix_room_id in metadata")
+}
+
+/// Split a message into chunks that fit within the length limit.
+/// Tries to split at newlines, then spaces, then hard-cuts.
+fn split_message(text: &str, max_len: usize) -> Vec<String> {
+    if text.len() <= max_len {
+        return vec![text.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut remaining = text;
+
+    while !remaining.is_empty() {
+        if remaining.len() <= max_len {
+            chunks.push(remaining.to_string());
+            break;
+        }
+
+        let mut limit = max_len;
+        while !remaining.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        let split_at = remaining[..limit]
+            .rfind('\n')
+            .or_else(|| remaining[..limit].rfind(' '))
+            .filter(|&index| index > 0)
+            .unwrap_or(limit);
+
+        chunks.push(remaining[..split_at].to_string());
+        remaining = remaining[split_at..].trim_start();
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_messages_become_inbound_messages() {
+        let event = json!({
+            "type": "m.room.message",
+            "event_id": "$abc",
+            "sender": "@alice:example.org",
+            "origin_server_ts": 1_700_000_000_000_i64,
+            "content": {
+                "msgtype": "m.text",
+                "body": "hey @bot:example.org",
+                "m.mentions": { "user_ids": ["@bot:example.org"] },
+                "m.relates_to": { "rel_type": "m.thread", "event_id": "$root" },
+            },
+        });
+
+        let message = inbound_from_event(
+            "!room:example.org",
+            &event,
+            "@bot:example.org",
+            "https://example.org",
+        )
+        .expect("message");
+        assert_eq!(message.conversation_id, "matrix:!room:example.org");
+        assert_eq!(message.sender_id, "@alice:example.org");
+        assert_eq!(
+            message.formatted_author.as_deref(),
+            Some("alice (@alice:example.org)")
+        );
+        assert_eq!(message.metadata["matrix_mentions_bot"], true);
+        assert_eq!(message.metadata["matrix_thread_root"], "$root");
+
+        let own = json!({
+            "type": "m.room.message",
+            "event_id": "$own",
+            "sender": "@bot:example.org",
+            "content": { "msgtype": "m.text", "body": "hi" },
+        });
+        assert!(
+            inbound_from_event(
+                "!room:example.org",
+                &own,
+                "@bot:example.org",
+                "https://example.org"
+            )
+            .is_none()
+        );
+
+        let image = json!({
+            "type": "m.room.message",
+            "event_id": "$img",
+            "sender": "@alice:example.org",
+            "content": {
+                "msgtype": "m.image",
+                "body": "cat.png",
+                "url": "mxc://example.org/xyz",
+                "info": { "mimetype": "image/png", "size": 12 },
+            },
+        });
+        let message = inbound_from_event(
+            "!room:example.org",
+            &image,
+            "@bot:example.org",
+            "https://example.org/",
+        )
+        .expect("image");
+        let MessageContent::Media { text, attachments } = message.content else {
+            panic!("expected media");
+        };
+        assert_eq!(text, None);
+        assert_eq!(
+            attachments[0].url,
+            "https://example.org/_matrix/media/v3/download/example.org/xyz"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! The bot's presence: one line, on platforms that show one (Discord's
+//! activity status), saying what the agents are doing.
+//!
+//! Channels report their turns to the `PresenceBoard` as they start and end,
+//! and rate limits as they hit them. The board combines those into a single
+//! `Presence` and publishes it whenever it changes; the messaging manager
+//! pushes it to the adapters.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Longest presence line shown. Discord cuts custom statuses at 128.
+const MAX_LABEL_CHARS: usize = 120;
+
+/// What the agents are doing, as shown in the bot's presence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Presence {
+    Idle,
+    /// Working on something, e.g. "replying in #general".
+    Busy(String),
+    /// A provider is rate-limiting the agents, so replies are delayed.
+    RateLimited,
+}
+
+impl Presence {
+    /// The line shown to server members.
+    pub fn label(&self) -> &str {
+        match self {
+            Presence::Idle => "idle",
+            Presence::Busy(label) => label,
+            Presence::RateLimited => "rate-limited, replies may be slow",
+        }
+    }
+}
+
+/// What every channel is doing, combined into one presence.
+#[derive(Debug)]
+pub struct PresenceBoard {
+    /// In-progress activities with their IDs, oldest first.
+    activities: Mutex<Vec<(u64, String)>>,
+    next_id: AtomicU64,
+    rate_limited_until: Mutex<Option<Instant>>,
+    tx: watch::Sender<Presence>,
+}
+
+impl Default for PresenceBoard {
+    fn default() -> Self {
+        Self {
+            activities: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            rate_limited_until: Mutex::new(None),
+            tx: watch::Sender::new(Presence::Idle),
+        }
+    }
+}
+
+impl PresenceBoard {
+    /// Receive the combined presence each time it changes.
+    pub fn subscribe(&self) -> watch::Receiver<Presence> {
+        self.tx.subscribe()
+    }
+
+    /// Report an activity, such as "replying in #general". It lasts until
+    /// the returned guard is dropped.
+    pub fn begin(self: &Arc<Self>, label: impl Into<String>) -> PresenceGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let label: String = label.into().chars().take(MAX_LABEL_CHARS).collect();
+        self.activities
+            .lock()
+            .expect("presence lock poisoned")
+            .push((id, label));
+        self.publish();
+        PresenceGuard {
+            board: self.clone(),
+            id,
+        }
+    }
+
+    /// Show the agents as rate-limited for `duration`.
+    pub fn rate_limited(self: &Arc<Self>, duration: Duration) {
+        *self
+            .rate_limited_until
+            .lock()
+            .expect("presence lock poisoned") = Some(Instant::now() + duration);
+        self.publish();
+
+        let board = Arc::downgrade(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            if let Some(board) = board.upgrade() {
+                board.publish();
+            }
+        });
+    }
+
+    /// The presence for the current activities. A rate limit outranks
+    /// everything; otherwise the newest activity is shown.
+    pub fn current(&self) -> Presence {
+        let rate_limited = self
+            .rate_limited_until
+            .lock()
+            .expect("presence lock poisoned")
+            .is_some_and(|until| until > Instant::now());
+        if rate_limited {
+            return Presence::RateLimited;
+        }
+
+        let activities = self.activities.lock().expect("presence lock poisoned");
+        match activities.last() {
+            None => Presence::Idle,
+            Some((_, label)) if activities.len() == 1 => Presence::Busy(label.clone()),
+            Some((_, label)) => Presence::Busy(format!("{label} (+{} more)", activities.len() - 1)),
+        }
+    }
+
+    fn publish(&self) {
+        let presence = self.current();
+        self.tx.send_if_modified(|current| {
+            if *current == presence {
+                return false;
+            }
+            *current = presence;
+            true
+        });
+    }
+}
+
+/// An activity on the `PresenceBoard`, removed when dropped.
+#[derive(Debug)]
+pub struct PresenceGuard {
+    board: Arc<PresenceBoard>,
+    id: u64,
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        self.board
+            .activities
+            .lock()
+            .expect("presence lock poisoned")
+            .retain(|(id, _)| *id != self.id);
+        self.board.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_presence_combines_activities_and_rate_limits() {
+        let board = Arc::new(PresenceBoard::default());
+        let presence = board.subscribe();
+        assert_eq!(board.current(), Presence::Idle);
+
+        let general = board.begin("replying in #general");
+        let standup = board.begin("replying in #standup");
+        assert_eq!(
+            *presence.borrow(),
+            Presence::Busy("replying in #standup (+1 more)".into())
+        );
+
+        drop(standup);
+        assert_eq!(
+            *presence.borrow(),
+            Presence::Busy("replying in #general".into())
+        );
+
+        board.rate_limited(Duration::from_secs(60));
+        assert_eq!(*presence.borrow(), Presence::RateLimited);
+
+        drop(general);
+        assert_eq!(board.current(), Presence::RateLimited);
+    }
+}
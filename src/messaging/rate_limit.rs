@@ -0,0 +1,178 @@
+//! Per-user inbound rate limiting for messaging adapters.
+
+use crate::config::UserRateLimitConfig;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Users tracked before idle windows are swept out of the map.
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// Outcome of checking one inbound message against the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allow,
+    /// Drop the message. `notify` is true only for the first dropped message
+    /// of a cooldown, so the user gets one cooldown notice rather than one per
+    /// message.
+    Limited {
+        retry_after: Duration,
+        notify: bool,
+    },
+}
+
+#[derive(Debug, Default)]
+struct UserWindow {
+    accepted: VecDeque<Instant>,
+    notified: bool,
+}
+
+/// Sliding-window counter of accepted messages per user.
+///
+/// Only accepted messages count toward the window, so a user who keeps
+/// sending while limited is let back in as soon as their oldest accepted
+/// message ages out.
+#[derive(Debug, Default)]
+pub struct UserRateLimiter {
+    users: Mutex<HashMap<String, UserWindow>>,
+}
+
+impl UserRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(&self, user_id: &str, config: &UserRateLimitConfig) -> RateLimitDecision {
+        self.check_at(user_id, config, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        user_id: &str,
+        config: &UserRateLimitConfig,
+        now: Instant,
+    ) -> RateLimitDecision {
+        if config.max_messages == 0 {
+            return RateLimitDecision::Allow;
+        }
+        let window = Duration::from_secs(config.window_secs);
+        let mut users = self.users.lock().expect("rate limiter lock poisoned");
+
+        if users.len() >= SWEEP_THRESHOLD {
+            users.retain(|_, user| {
+                user.accepted
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) < window)
+            });
+        }
+
+        let user = users.entry(user_id.to_string()).or_default();
+        while user
+            .accepted
+            .front()
+            .is_some_and(|first| now.duration_since(*first) >= window)
+        {
+            user.accepted.pop_front();
+        }
+
+        if user.accepted.len() < config.max_messages as usize {
+            user.accepted.push_back(now);
+            user.notified = false;
+            return RateLimitDecision::Allow;
+        }
+
+        let oldest = *user
+            .accepted
+            .front()
+            .expect("a full window has at least one entry");
+        let retry_after = window.saturating_sub(now.duration_since(oldest));
+        let notify = !std::mem::replace(&mut user.notified, true);
+        RateLimitDecision::Limited {
+            retry_after,
+            notify,
+        }
+    }
+}
+
+/// Render the configured cooldown message for a limited user.
+pub fn cooldown_message(config: &UserRateLimitConfig, retry_after: Duration) -> String {
+    config
+        .cooldown_message
+        .replace("{wait}", &format_wait(retry_after))
+}
+
+fn format_wait(duration: Duration) -> String {
+    let seconds = duration.as_secs().max(1);
+    if seconds < 60 {
+        return format!("{seconds} second{}", if seconds == 1 { "" } else { "s" });
+    }
+    let minutes = seconds.div_ceil(60);
+    format!("{minutes} minute{}", if minutes == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_messages: u32, window_secs: u64) -> UserRateLimitConfig {
+        UserRateLimitConfig {
+            max_messages,
+            window_secs,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_limits_per_user_and_notifies_once() {
+        let limiter = UserRateLimiter::new();
+        let config = config(2, 60);
+        let start = Instant::now();
+
+        assert_eq!(
+            limiter.check_at("alice", &config, start),
+            RateLimitDecision::Allow
+        );
+        assert_eq!(
+            limiter.check_at("alice", &config, start + Duration::from_secs(10)),
+            RateLimitDecision::Allow
+        );
+        assert_eq!(
+            limiter.check_at("alice", &config, start + Duration::from_secs(20)),
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs(40),
+                notify: true,
+            }
+        );
+        assert!(matches!(
+            limiter.check_at("alice", &config, start + Duration::from_secs(30)),
+            RateLimitDecision::Limited { notify: false, .. }
+        ));
+        assert_eq!(
+            limiter.check_at("bob", &config, start + Duration::from_secs(30)),
+            RateLimitDecision::Allow
+        );
+
+        // The first accepted message ages out of the window.
+        assert_eq!(
+            limiter.check_at("alice", &config, start + Duration::from_secs(60)),
+            RateLimitDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_cooldown_message_formats_wait() {
+        let config = UserRateLimitConfig {
+            cooldown_message: "Back in {wait}.".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            cooldown_message(&config, Duration::from_secs(1)),
+            "Back in 1 second."
+        );
+        assert_eq!(
+            cooldown_message(&config, Duration::from_secs(61)),
+            "Back in 2 minutes."
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Rendering structured replies as cards.
+//!
+//! The agent picks a structured reply kind in the reply tool (a summary, a
+//! status report, or search results) and fills in its parts. This layer turns
+//! it into a `Card`, which Discord sends as an embed, styled with the agent's
+//! color and name. The same reply renders as plain text for the conversation
+//! history and for platforms without embeds.
+
+use crate::{Card, CardField};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Discord embed limits, in bytes to stay safely under the character limits.
+const TITLE_MAX: usize = 256;
+const DESCRIPTION_MAX: usize = 4096;
+const FIELD_NAME_MAX: usize = 256;
+const FIELD_VALUE_MAX: usize = 1024;
+const MAX_FIELDS: usize = 25;
+
+/// Colors agents without an `embed_color` are assigned from, by agent id.
+const PALETTE: [u32; 8] = [
+    0x5865F2, 0x57F287, 0xFEE75C, 0xEB459E, 0xED4245, 0x1ABC9C, 0xE67E22, 0x9B59B6,
+];
+
+/// A reply with a known shape, rendered as a card.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StructuredReply {
+    /// The key points of a conversation, document, or discussion.
+    Summary {
+        title: String,
+        points: Vec<String>,
+        #[serde(default)]
+        action_items: Vec<String>,
+    },
+    /// The state of a system, project, or task.
+    StatusReport {
+        title: String,
+        /// One line on the overall state.
+        overall: String,
+        #[serde(default)]
+        items: Vec<StatusItem>,
+    },
+    /// Search results, best first.
+    SearchResults {
+        query: String,
+        results: Vec<SearchResult>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct StatusItem {
+    pub name: String,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SearchResult {
+    pub title: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub snippet: Option<String>,
+}
+
+impl StructuredReply {
+    /// Render as a card. Color and footer come from the agent's `EmbedStyle`.
+    pub fn to_card(&self) -> Card {
+        let mut card = Card::default();
+        match self {
+            Self::Summary {
+                title,
+                points,
+                action_items,
+            } => {
+                card.title = Some(truncate(title, TITLE_MAX));
+                card.description = Some(truncate(&bullets(points), DESCRIPTION_MAX));
+                if !action_items.is_empty() {
+                    card.fields
+                        .push(field("Action items", &bullets(action_items), false));
+                }
+            }
+            Self::StatusReport {
+                title,
+                overall,
+                items,
+            } => {
+                card.title = Some(truncate(title, TITLE_MAX));
+                card.description = Some(truncate(overall, DESCRIPTION_MAX));
+                card.fields = items
+                    .iter()
+                    .take(MAX_FIELDS)
+                    .map(|item| field(&item.name, &item.status, true))
+                    .collect();
+            }
+            Self::SearchResults { query, results } => {
+                card.title = Some(truncate(&format!("Results for \"{query}\""), TITLE_MAX));
+                if results.is_empty() {
+                    card.description = Some("No results.".into());
+                }
+                card.fields = results
+                    .iter()
+                    .take(MAX_FIELDS)
+                    .enumerate()
+                    .map(|(index, result)| {
+                        let value = [result.snippet.as_deref(), result.url.as_deref()]
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        field(&format!("{}. {}", index + 1, result.title), &value, false)
+                    })
+                    .collect();
+            }
+        }
+        card
+    }
+
+    /// Render as plain markdown.
+    pub fn to_text(&self) -> String {
+        match self {
+            Self::Summary {
+                title,
+                points,
+                action_items,
+            } => {
+                let mut text = format!("**{title}**\n{}", bullets(points));
+                if !action_items.is_empty() {
+                    text.push_str(&format!("\n\nAction items:\n{}", bullets(action_items)));
+                }
+                text
+            }
+            Self::StatusReport {
+                title,
+                overall,
+                items,
+            } => {
+                let mut text = format!("**{title}**\n{overall}");
+                for item in items {
+                    text.push_str(&format!("\n- {}: {}", item.name, item.status));
+                }
+                text
+            }
+            Self::SearchResults { query, results } => {
+                let mut text = format!("**Results for \"{query}\"**");
+                if results.is_empty() {
+                    text.push_str("\nNo results.");
+                }
+                for (index, result) in results.iter().enumerate() {
+                    text.push_str(&format!("\n{}. {}", index + 1, result.title));
+                    if let Some(snippet) = &result.snippet {
+                        text.push_str(&format!(": {snippet}"));
+                    }
+                    if let Some(url) = &result.url {
+                        text.push_str(&format!(" ({url})"));
+                    }
+                }
+                text
+            }
+        }
+    }
+}
+
+/// How an agent's cards look: its accent color, and its name in the footer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedStyle {
+    pub color: u32,
+    pub footer: String,
+}
+
+impl EmbedStyle {
+    /// The agent's style. Without a configured color, one is picked from the
+    /// palette by agent id, so each agent keeps the same color.
+    pub fn for_agent(agent_id: &str, color: Option<u32>) -> Self {
+        let color = color.unwrap_or_else(|| {
+            let hash = agent_id.bytes().fold(0u32, |hash, byte| {
+                hash.wrapping_mul(31).wrapping_add(u32::from(byte))
+            });
+            PALETTE[hash as usize % PALETTE.len()]
+        });
+        Self {
+            color,
+            footer: agent_id.to_string(),
+        }
+    }
+
+    /// Fill in the color and footer of a card that doesn't set its own.
+    pub fn apply(&self, card: &mut Card) {
+        card.color.get_or_insert(self.color);
+        if card.footer.is_none() {
+            card.footer = Some(self.footer.clone());
+        }
+    }
+}
+
+fn bullets(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("• {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn field(name: &str, value: &str, inline: bool) -> CardField {
+    // Discord rejects empty field values.
+    let value = if value.trim().is_empty() {
+        "—"
+    } else {
+        value
+    };
+    CardField {
+        name: truncate(name, FIELD_NAME_MAX),
+        value: truncate(value, FIELD_VALUE_MAX),
+        inline,
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let end = text.floor_char_boundary(max - '…'.len_utf8());
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_renders_as_styled_card_and_text() {
+        let reply: StructuredReply = serde_json::from_value(serde_json::json!({
+            "kind": "summary",
+            "title": "Standup",
+            "points": ["Deploy is green", "Search is slow"],
+            "action_items": ["Profile search"]
+        }))
+        .unwrap();
+
+        let style = EmbedStyle::for_agent("main", None);
+        assert_eq!(style, EmbedStyle::for_agent("main", None));
+        let mut card = reply.to_card();
+        style.apply(&mut card);
+        assert_eq!(card.title.as_deref(), Some("Standup"));
+        assert_eq!(
+            card.description.as_deref(),
+            Some("• Deploy is green\n• Search is slow")
+        );
+        assert_eq!(card.fields[0].value, "• Profile search");
+        assert_eq!(card.color, Some(style.color));
+        assert_eq!(card.footer.as_deref(), Some("main"));
+
+        assert_eq!(
+            reply.to_text(),
+            "**Standup**\n• Deploy is green\n• Search is slow\n\nAction items:\n• Profile search"
+        );
+
+        let mut custom = Card {
+            color: Some(0xFF0000),
+            ..Default::default()
+        };
+        EmbedStyle::for_agent("main", Some(0x00FF00)).apply(&mut custom);
+        assert_eq!(custom.color, Some(0xFF0000));
+        assert_eq!(truncate("ééé", 5), "é…");
+    }
+}
@@ -279,7 +279,7 @@ async fn handle_app_mention_event(
     let content = MessageContent::Text(text);
 
     let slack_uid = SlackUserId(user_id.clone());
-    let (metadata, formatted_author) = build_metadata_and_author(
+    let (mut metadata, formatted_author) = build_metadata_and_author(
         &team_id_str,
         &channel_id,
         &ts,
@@ -292,6 +292,7 @@ async fn handle_app_mention_event(
         &adapter_state.channel_name_cache,
     )
     .await;
+    metadata.insert("slack_mentions_bot".into(), serde_json::Value::Bool(true));
 
     send_inbound(
         &adapter_state.inbound_tx,
@@ -992,6 +993,16 @@ impl Messaging for SlackAdapter {
                 self.active_messages.write().await.remove(&message.id);
             }
 
+            OutboundResponse::StreamAbort => {
+                let placeholder = self.active_messages.write().await.remove(&message.id);
+                if let Some(ts) = placeholder {
+                    let req = SlackApiChatDeleteRequest::new(channel_id.clone(), SlackTs(ts));
+                    if let Err(error) = session.chat_delete(&req).await {
+                        tracing::warn!(%error, "failed to delete aborted streaming message");
+                    }
+                }
+            }
+
             OutboundResponse::Status(_) => {
                 // Status updates are handled via send_status(); ignored here.
             }
@@ -1451,6 +1462,7 @@ fn variant_name(response: &OutboundResponse) -> &'static str {
         OutboundResponse::StreamStart => "StreamStart",
         OutboundResponse::StreamChunk(_) => "StreamChunk",
         OutboundResponse::StreamEnd => "StreamEnd",
+        OutboundResponse::StreamAbort => "StreamAbort",
         OutboundResponse::Status(_) => "Status",
     }
 }
@@ -0,0 +1,384 @@
+//! SMS messaging adapter over Twilio.
+//!
+//! Twilio posts incoming texts to a small HTTP server run by the adapter;
+//! replies go out through Twilio's Messages API. Each phone number is its own
+//! conversation, `sms:<number>`, with its own history.
+//!
+//! Long replies are split into numbered texts of a few segments each, since
+//! every segment is billed and carriers drop very long concatenated texts.
+//! Opt-out keywords are honored: a number that texts STOP gets nothing more
+//! until it texts START, and the agent is told either way.
+
+mod segment;
+
+use crate::agent::listening;
+use crate::config::{SmsConfig, SmsPermissions};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::messaging::twilio;
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use axum::Router;
+use axum::extract::{Form, OriginalUri, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::post;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{RwLock, mpsc};
+
+/// Keywords that opt a number out of texts. Carriers and Twilio honor the
+/// same list.
+const OPT_OUT_KEYWORDS: &[&str] = &[
+    "STOP",
+    "STOPALL",
+    "UNSUBSCRIBE",
+    "CANCEL",
+    "END",
+    "QUIT",
+    "OPTOUT",
+    "REVOKE",
+];
+
+/// Keywords that opt a number back in.
+const OPT_IN_KEYWORDS: &[&str] = &["START", "UNSTOP", "YES"];
+
+/// SMS adapter state.
+pub struct SmsAdapter {
+    config: Arc<SmsConfig>,
+    permissions: Arc<ArcSwap<SmsPermissions>>,
+    client: twilio::Client,
+    inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
+    /// Numbers that texted an opt-out keyword.
+    opted_out: Arc<RwLock<HashSet<String>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// Shared state for axum handlers.
+#[derive(Clone)]
+struct AppState {
+    config: Arc<SmsConfig>,
+    permissions: Arc<ArcSwap<SmsPermissions>>,
+    inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
+    opted_out: Arc<RwLock<HashSet<String>>>,
+}
+
+/// A keyword that changes whether a number gets texts.
+#[derive(Debug, PartialEq, Eq)]
+enum Keyword {
+    OptOut,
+    OptIn,
+}
+
+impl Keyword {
+    /// The keyword a text is, if it's nothing but one.
+    fn of(body: &str) -> Option<Self> {
+        let word = body
+            .trim()
+            .trim_end_matches(['.', '!'])
+            .to_ascii_uppercase();
+        if OPT_OUT_KEYWORDS.contains(&word.as_str()) {
+            Some(Self::OptOut)
+        } else if OPT_IN_KEYWORDS.contains(&word.as_str()) {
+            Some(Self::OptIn)
+        } else {
+            None
+        }
+    }
+}
+
+impl SmsAdapter {
+    pub fn new(config: &SmsConfig, permissions: Arc<ArcSwap<SmsPermissions>>) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            permissions,
+            client: twilio::Client::new(&config.account_sid, &config.auth_token),
+            inbound_tx: Arc::new(RwLock::new(None)),
+            opted_out: Arc::new(RwLock::new(HashSet::new())),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Text `text` to `number`, split into as many texts as it takes. Nothing
+    /// is sent to a number that opted out.
+    async fn send_text(&self, number: &str, text: &str) -> anyhow::Result<()> {
+        if self.opted_out.read().await.contains(number) {
+            tracing::info!(%number, "not texting a number that opted out");
+            return Ok(());
+        }
+
+        let parts = segment::split(
+            text,
+            self.config.segments_per_message,
+            self.config.max_messages,
+        );
+        for part in parts {
+            tracing::debug!(%number, segments = segment::segments(&part), "sending sms");
+            let params = [
+                ("From", self.config.from_number.clone()),
+                ("To", number.to_string()),
+                ("Body", part),
+            ];
+            match self.client.send(&params).await {
+                Ok(_) => {}
+                // Opted out before we saw the keyword, e.g. while the bot was down.
+                Err(twilio::SendError::Rejected {
+                    code: twilio::OPTED_OUT,
+                    ..
+                }) => {
+                    tracing::info!(%number, "number has opted out of texts");
+                    self.opted_out.write().await.insert(number.to_string());
+                    return Ok(());
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Messaging for SmsAdapter {
+    fn name(&self) -> &str {
+        "sms"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.inbound_tx.write().await = Some(inbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        if self.config.public_url.is_none() {
+            tracing::warn!("sms public_url not set; Twilio signatures aren't checked");
+        }
+
+        let state = AppState {
+            config: self.config.clone(),
+            permissions: self.permissions.clone(),
+            inbound_tx: self.inbound_tx.clone(),
+            opted_out: self.opted_out.clone(),
+        };
+
+        let app = Router::new()
+            .route("/twilio/sms", post(handle_message))
+            .with_state(state);
+
+        let bind = if self.config.bind.contains(':') {
+            format!("[{}]:{}", self.config.bind, self.config.port)
+        } else {
+            format!("{}:{}", self.config.bind, self.config.port)
+        };
+        let listener = tokio::net::TcpListener::bind(&bind)
+            .await
+            .with_context(|| format!("failed to bind sms server to {bind}"))?;
+        tracing::info!(%bind, "sms server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            {
+                tracing::error!(%error, "sms server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let text = match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => text,
+            OutboundResponse::File {
+                filename, caption, ..
+            } => match caption {
+                Some(caption) => format!("[File: {filename}] {caption}"),
+                None => format!("[File: {filename}]"),
+            },
+            // Texts can't be edited, so streaming is buffered: the final text
+            // arrives as a Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort
+            | OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => return Ok(()),
+        };
+        let number = message
+            .metadata
+            .get("sms_number")
+            .and_then(|v| v.as_str())
+            .context("missing sms_number in metadata")?;
+        self.send_text(number, &text).await?;
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            self.send_text(target, &text).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if self.inbound_tx.read().await.is_none() {
+            return Err(anyhow::anyhow!("sms adapter not started").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.inbound_tx.write().await = None;
+
+        tracing::info!("sms adapter shut down");
+        Ok(())
+    }
+}
+
+/// Incoming text from Twilio.
+async fn handle_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    uri: OriginalUri,
+    Form(params): Form<BTreeMap<String, String>>,
+) -> axum::response::Response {
+    let config = &state.config;
+    if !twilio::verified(
+        &config.auth_token,
+        config.public_url.as_deref(),
+        &headers,
+        &uri,
+        &params,
+    ) {
+        tracing::warn!("sms request with invalid twilio signature rejected");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let Some(mut message) = inbound_message(&params) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let number = message.sender_id.clone();
+    let permissions = state.permissions.load();
+    if !permissions.allowed_numbers.is_empty() && !permissions.allowed_numbers.contains(&number) {
+        tracing::debug!(%number, "sms from unlisted number");
+        return twilio::empty_response();
+    }
+
+    // Twilio answers the keywords itself; the agent only needs to know. A
+    // "yes" from a number that never opted out is just an answer.
+    let note = {
+        let mut opted_out = state.opted_out.write().await;
+        match Keyword::of(&message.content.to_string()) {
+            Some(Keyword::OptOut) => {
+                opted_out.insert(number.clone());
+                Some(
+                    "This number opted out of texts. Nothing more will be sent to it until \
+                     it texts START.",
+                )
+            }
+            Some(Keyword::OptIn) if opted_out.remove(&number) => {
+                Some("This number opted back in to texts.")
+            }
+            _ => None,
+        }
+    };
+    if let Some(note) = note {
+        tracing::info!(%number, note, "sms opt-out keyword");
+        message.content = MessageContent::Text(note.into());
+        message.sender_id = "sms-opt-out".into();
+        message.formatted_author = Some("SMS".into());
+        listening::mark_passive(&mut message);
+    }
+
+    let inbound_tx = state.inbound_tx.read().await;
+    let Some(tx) = inbound_tx.as_ref() else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    if tx.send(message).await.is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+    twilio::empty_response()
+}
+
+/// The inbound message for a Twilio webhook's form parameters.
+fn inbound_message(params: &BTreeMap<String, String>) -> Option<InboundMessage> {
+    let number = params.get("From").filter(|from| !from.is_empty())?.clone();
+    let sid = params.get("MessageSid").cloned().unwrap_or_default();
+    let body = params.get("Body").cloned().unwrap_or_default();
+
+    // Picture messages (MMS) carry media like WhatsApp's.
+    let (attachments, media) = twilio::media(params);
+    let content = if attachments.is_empty() {
+        MessageContent::Text(body)
+    } else {
+        MessageContent::Media {
+            text: (!body.is_empty()).then_some(body),
+            attachments,
+        }
+    };
+
+    let mut metadata = HashMap::from([
+        ("sms_number".into(), serde_json::json!(number)),
+        ("sms_message_sid".into(), serde_json::json!(sid)),
+        ("sender_display_name".into(), serde_json::json!(number)),
+    ]);
+    if !media.is_empty() {
+        metadata.insert("sms_media".into(), serde_json::Value::Array(media));
+    }
+
+    Some(InboundMessage {
+        id: sid,
+        source: "sms".into(),
+        conversation_id: format!("sms:{number}"),
+        sender_id: number.clone(),
+        agent_id: None,
+        content,
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(number),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texts_map_to_number_conversations_and_keywords_are_recognized() {
+        let params = BTreeMap::from([
+            ("From".to_string(), "+15551234567".to_string()),
+            ("Body".to_string(), "Can I move my appointment?".to_string()),
+            ("MessageSid".to_string(), "SM123".to_string()),
+            ("NumMedia".to_string(), "0".to_string()),
+        ]);
+        let message = inbound_message(&params).unwrap();
+        assert_eq!(message.conversation_id, "sms:+15551234567");
+        assert_eq!(message.sender_id, "+15551234567");
+        assert_eq!(message.metadata["sms_message_sid"], "SM123");
+        assert!(matches!(message.content, MessageContent::Text(ref text) if text.contains("move")));
+        assert!(inbound_message(&BTreeMap::new()).is_none());
+
+        assert_eq!(Keyword::of(" stop "), Some(Keyword::OptOut));
+        assert_eq!(Keyword::of("Unsubscribe."), Some(Keyword::OptOut));
+        assert_eq!(Keyword::of("START"), Some(Keyword::OptIn));
+        assert_eq!(Keyword::of("Please stop texting me"), None);
+    }
+}
@@ -0,0 +1,165 @@
+//! SMS segmentation.
+//!
+//! A text travels as one or more 140-byte segments: 160 characters each in
+//! the GSM-7 alphabet, or 70 UTF-16 units once a single character falls
+//! outside it. Texts longer than one segment lose some of each to the header
+//! that joins them back together, leaving 153 and 67.
+
+/// Characters of the GSM-7 default alphabet.
+const GSM7_BASIC: &str = concat!(
+    "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ",
+    " !\"#¤%&'()*+,-./0123456789:;<=>?",
+    "¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§",
+    "¿abcdefghijklmnopqrstuvwxyzäöñüà",
+);
+
+/// Characters of the GSM-7 extension table, which take two septets each.
+const GSM7_EXTENDED: &str = "^{}\\[~]|€";
+
+/// Room kept for the " (1/3)" numbering of a split reply.
+const NUMBERING_LEN: usize = 8;
+
+/// Marks a reply cut short.
+const ELLIPSIS: &str = "...";
+
+/// How a text is encoded, and so how much of it fits a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gsm7,
+    Ucs2,
+}
+
+impl Encoding {
+    fn of(text: &str) -> Self {
+        if text
+            .chars()
+            .all(|c| GSM7_BASIC.contains(c) || GSM7_EXTENDED.contains(c))
+        {
+            Self::Gsm7
+        } else {
+            Self::Ucs2
+        }
+    }
+
+    /// Units `c` takes: septets for GSM-7, UTF-16 units for UCS-2.
+    fn cost(self, c: char) -> usize {
+        match self {
+            Self::Gsm7 if GSM7_EXTENDED.contains(c) => 2,
+            Self::Gsm7 => 1,
+            Self::Ucs2 => c.len_utf16(),
+        }
+    }
+
+    fn len(self, text: &str) -> usize {
+        text.chars().map(|c| self.cost(c)).sum()
+    }
+
+    /// Units that fit in `segments` segments.
+    fn capacity(self, segments: usize) -> usize {
+        match (self, segments) {
+            (Self::Gsm7, 0 | 1) => 160,
+            (Self::Ucs2, 0 | 1) => 70,
+            (Self::Gsm7, segments) => 153 * segments,
+            (Self::Ucs2, segments) => 67 * segments,
+        }
+    }
+}
+
+/// Segments `text` is sent as.
+pub(super) fn segments(text: &str) -> usize {
+    let encoding = Encoding::of(text);
+    let len = encoding.len(text);
+    if len <= encoding.capacity(1) {
+        1
+    } else {
+        len.div_ceil(encoding.capacity(2) / 2)
+    }
+}
+
+/// Split a reply into texts of at most `max_segments` segments each, at
+/// spaces where possible, numbered like "(1/3)" when there's more than one.
+/// A reply needing more than `max_messages` texts is cut short.
+pub(super) fn split(text: &str, max_segments: usize, max_messages: usize) -> Vec<String> {
+    let text = text.trim();
+    let encoding = Encoding::of(text);
+    let capacity = encoding.capacity(max_segments);
+    if encoding.len(text) <= capacity {
+        return vec![text.to_string()];
+    }
+
+    let budget = capacity - NUMBERING_LEN;
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut used = 0;
+        let mut end = rest.len();
+        let mut last_space = None;
+        for (index, c) in rest.char_indices() {
+            used += encoding.cost(c);
+            if used > budget {
+                end = last_space.filter(|&space| space > 0).unwrap_or(index);
+                break;
+            }
+            if c.is_whitespace() {
+                last_space = Some(index);
+            }
+        }
+        parts.push(rest[..end].trim_end().to_string());
+        rest = rest[end..].trim_start();
+    }
+
+    if parts.len() > max_messages {
+        parts.truncate(max_messages);
+        if let Some(last) = parts.last_mut() {
+            while encoding.len(last) + ELLIPSIS.len() > budget {
+                last.pop();
+            }
+            last.push_str(ELLIPSIS);
+        }
+    }
+    let total = parts.len();
+    if total == 1 {
+        return parts;
+    }
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, part)| format!("{part} ({}/{total})", index + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replies_split_into_numbered_texts_within_the_segment_limit() {
+        assert_eq!(segments("Your table is booked for 7pm."), 1);
+        assert_eq!(segments(&"a".repeat(160)), 1);
+        assert_eq!(segments(&"a".repeat(161)), 2);
+        assert_eq!(segments(&"€".repeat(80)), 1);
+        assert_eq!(segments(&"€".repeat(81)), 2);
+        assert_eq!(segments(&"ł".repeat(70)), 1);
+        assert_eq!(segments(&"ł".repeat(71)), 2);
+        assert_eq!(segments("Thanks 🙂"), 1);
+
+        let reply = "word ".repeat(200);
+        let parts = split(&reply, 2, 10);
+        assert_eq!(parts.len(), 4);
+        assert!(parts.iter().all(|part| segments(part) <= 2));
+        assert!(parts[0].ends_with("word (1/4)"));
+        assert!(parts[3].ends_with(" (4/4)"));
+        let words: usize = parts
+            .iter()
+            .map(|part| part.split_whitespace().filter(|w| *w == "word").count())
+            .sum();
+        assert_eq!(words, 200);
+
+        let cut = split(&reply, 1, 2);
+        assert_eq!(cut.len(), 2);
+        assert!(cut[1].ends_with("... (2/2)"));
+        assert!(cut.iter().all(|part| segments(part) == 1));
+
+        assert_eq!(split("  Short reply. ", 1, 1), vec!["Short reply."]);
+    }
+}
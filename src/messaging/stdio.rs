@@ -0,0 +1,461 @@
+//! Stdio messaging adapter for embedding Spacebot as a subprocess.
+//!
+//! Started with `spacebot start --stdio`, the process reads JSON-RPC 2.0
+//! requests from stdin and writes responses and event notifications to
+//! stdout, one JSON object per line. Logs go to stderr. This suits editors,
+//! game servers, and test harnesses that run Spacebot as a child process
+//! rather than talking to it over HTTP.
+//!
+//! Methods:
+//! - `send` — `{conversation_id, content, sender_id?, sender_name?, agent_id?}`
+//! - `cancel` — `{conversation_id}`, stops the conversation's in-flight turn
+//!
+//! Notifications:
+//! - `ready` — sent once, when Spacebot is ready for messages
+//! - `event` — `{conversation_id, type, ...}` for each reply, stream chunk,
+//!   and status update
+
+use crate::agent::channel::{CANCEL_TURN_KEY, MODERATOR_KEY};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{Mutex, mpsc, watch};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Stdio adapter state.
+pub struct StdioAdapter {
+    stdout: Arc<Mutex<tokio::io::Stdout>>,
+    /// Set when stdin closes, so the process stops with its parent.
+    closed: watch::Sender<bool>,
+}
+
+/// A JSON-RPC request or notification from the embedding program.
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Absent on notifications, which get no response.
+    id: Option<serde_json::Value>,
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct SendParams {
+    /// Reuse the same ID to continue a conversation.
+    conversation_id: String,
+    content: String,
+    #[serde(default = "default_sender")]
+    sender_id: String,
+    sender_name: Option<String>,
+    /// Agent to route to, overriding bindings.
+    agent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelParams {
+    conversation_id: String,
+}
+
+fn default_sender() -> String {
+    "stdio".into()
+}
+
+/// Something the agent did in a conversation.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    Text {
+        text: String,
+    },
+    File {
+        filename: String,
+        mime_type: String,
+        /// File contents, base64-encoded.
+        data: String,
+        caption: Option<String>,
+    },
+    StreamStart,
+    StreamChunk {
+        text: String,
+    },
+    StreamEnd,
+    StreamAbort,
+    Reaction {
+        emoji: String,
+    },
+    Thinking,
+    StopTyping,
+    ToolStarted {
+        tool_name: String,
+    },
+    ToolCompleted {
+        tool_name: String,
+    },
+}
+
+impl Event {
+    fn from_response(response: OutboundResponse) -> Option<Self> {
+        Some(match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => Self::Text { text },
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => Self::File {
+                filename,
+                mime_type,
+                data: base64::engine::general_purpose::STANDARD.encode(data),
+                caption,
+            },
+            OutboundResponse::StreamStart => Self::StreamStart,
+            OutboundResponse::StreamChunk(text) => Self::StreamChunk { text },
+            OutboundResponse::StreamEnd => Self::StreamEnd,
+            OutboundResponse::StreamAbort => Self::StreamAbort,
+            OutboundResponse::Reaction(emoji) => Self::Reaction { emoji },
+            OutboundResponse::RemoveReaction(_) | OutboundResponse::Status(_) => return None,
+        })
+    }
+
+    fn from_status(status: StatusUpdate) -> Option<Self> {
+        Some(match status {
+            StatusUpdate::Thinking => Self::Thinking,
+            StatusUpdate::StopTyping => Self::StopTyping,
+            StatusUpdate::ToolStarted { tool_name } => Self::ToolStarted { tool_name },
+            StatusUpdate::ToolCompleted { tool_name } => Self::ToolCompleted { tool_name },
+            _ => return None,
+        })
+    }
+}
+
+/// A request that couldn't be handled, as a JSON-RPC error.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl StdioAdapter {
+    pub fn new(closed: watch::Sender<bool>) -> Self {
+        Self {
+            stdout: Arc::new(Mutex::new(tokio::io::stdout())),
+            closed,
+        }
+    }
+
+    async fn send_event(&self, conversation_id: &str, event: Event) -> crate::Result<()> {
+        let mut params = serde_json::to_value(event).context("failed to serialize stdio event")?;
+        params["conversation_id"] = conversation_id.into();
+        write_line(
+            &self.stdout,
+            &serde_json::json!({ "jsonrpc": "2.0", "method": "event", "params": params }),
+        )
+        .await
+    }
+}
+
+impl Messaging for StdioAdapter {
+    fn name(&self) -> &str {
+        "stdio"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let stdout = self.stdout.clone();
+        let closed = self.closed.clone();
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(tokio::io::stdin()).lines();
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(error) => {
+                        tracing::error!(%error, "failed to read stdin");
+                        break;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(reply) = handle_line(&line, &inbound_tx).await
+                    && let Err(error) = write_line(&stdout, &reply).await
+                {
+                    tracing::error!(%error, "failed to write to stdout");
+                    break;
+                }
+            }
+            tracing::info!("stdin closed");
+            closed.send_replace(true);
+        });
+
+        write_line(
+            &self.stdout,
+            &serde_json::json!({ "jsonrpc": "2.0", "method": "ready" }),
+        )
+        .await?;
+        tracing::info!("stdio adapter listening");
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let Some(event) = Event::from_response(response) else {
+            return Ok(());
+        };
+        self.send_event(client_conversation_id(message), event)
+            .await
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let Some(event) = Event::from_status(status) else {
+            return Ok(());
+        };
+        self.send_event(client_conversation_id(message), event)
+            .await
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let Some(event) = Event::from_response(response) else {
+            return Ok(());
+        };
+        self.send_event(target, event).await
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if *self.closed.borrow() {
+            return Err(anyhow::anyhow!("stdin closed").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        tracing::info!("stdio adapter shut down");
+        Ok(())
+    }
+}
+
+/// The conversation ID the embedding program knows a message by.
+fn client_conversation_id(message: &InboundMessage) -> &str {
+    message
+        .metadata
+        .get("stdio_conversation_id")
+        .and_then(|value| value.as_str())
+        .unwrap_or(&message.conversation_id)
+}
+
+/// Handle one line of input, returning the response to write, if any.
+async fn handle_line(
+    line: &str,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+) -> Option<serde_json::Value> {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(error) => {
+            let code = if serde_json::from_str::<serde_json::Value>(line).is_ok() {
+                INVALID_REQUEST
+            } else {
+                PARSE_ERROR
+            };
+            return Some(error_response(
+                serde_json::Value::Null,
+                RpcError::new(code, error.to_string()),
+            ));
+        }
+    };
+
+    let result = if request.jsonrpc != "2.0" {
+        Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""))
+    } else {
+        match inbound_message(&request.method, request.params) {
+            Ok(message) => {
+                let conversation_id = client_conversation_id(&message).to_string();
+                inbound_tx
+                    .send(message)
+                    .await
+                    .map(|()| serde_json::json!({ "conversation_id": conversation_id }))
+                    .map_err(|_| RpcError::new(INTERNAL_ERROR, "spacebot is shutting down"))
+            }
+            Err(error) => Err(error),
+        }
+    };
+
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+/// The inbound message a request stands for.
+fn inbound_message(method: &str, params: serde_json::Value) -> Result<InboundMessage, RpcError> {
+    let invalid_params =
+        |error: serde_json::Error| RpcError::new(INVALID_PARAMS, error.to_string());
+    match method {
+        "send" => {
+            let params: SendParams = serde_json::from_value(params).map_err(invalid_params)?;
+            let author = params
+                .sender_name
+                .clone()
+                .unwrap_or_else(|| params.sender_id.clone());
+            let mut message = message(&params.conversation_id, &params.sender_id);
+            message.agent_id = params.agent_id.map(Into::into);
+            message.content = MessageContent::Text(params.content);
+            message
+                .metadata
+                .insert("display_name".into(), author.clone().into());
+            message.formatted_author = Some(author);
+            Ok(message)
+        }
+        "cancel" => {
+            let params: CancelParams = serde_json::from_value(params).map_err(invalid_params)?;
+            let mut message = message(&params.conversation_id, "stdio");
+            message
+                .metadata
+                .insert(CANCEL_TURN_KEY.into(), serde_json::Value::Bool(true));
+            // The process that owns stdio may stop any turn.
+            message
+                .metadata
+                .insert(MODERATOR_KEY.into(), serde_json::Value::Bool(true));
+            Ok(message)
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method: {method}"),
+        )),
+    }
+}
+
+fn message(conversation_id: &str, sender_id: &str) -> InboundMessage {
+    InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "stdio".into(),
+        conversation_id: format!("stdio:{conversation_id}"),
+        sender_id: sender_id.to_string(),
+        agent_id: None,
+        content: MessageContent::Text(String::new()),
+        timestamp: chrono::Utc::now(),
+        metadata: HashMap::from([(
+            "stdio_conversation_id".into(),
+            serde_json::Value::String(conversation_id.to_string()),
+        )]),
+        formatted_author: None,
+    }
+}
+
+fn error_response(id: serde_json::Value, error: RpcError) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Write one JSON object as a line, so lines from concurrent writers never
+/// interleave.
+async fn write_line(
+    stdout: &Mutex<tokio::io::Stdout>,
+    value: &serde_json::Value,
+) -> crate::Result<()> {
+    let mut line = serde_json::to_vec(value).context("failed to serialize stdio message")?;
+    line.push(b'\n');
+    let mut stdout = stdout.lock().await;
+    stdout.write_all(&line).await?;
+    stdout.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_become_messages_and_errors_follow_json_rpc() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let send = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "send",
+            "params": {
+                "conversation_id": "level-3",
+                "content": "Open the gate",
+                "sender_name": "Guard",
+            },
+        });
+
+        let reply = handle_line(&send.to_string(), &tx).await.unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["conversation_id"], "level-3");
+        let message = rx.recv().await.unwrap();
+        assert_eq!(message.conversation_id, "stdio:level-3");
+        assert_eq!(message.sender_id, "stdio");
+        assert_eq!(message.formatted_author.as_deref(), Some("Guard"));
+        assert_eq!(client_conversation_id(&message), "level-3");
+
+        let cancel =
+            r#"{"jsonrpc":"2.0","method":"cancel","params":{"conversation_id":"level-3"}}"#;
+        assert!(handle_line(cancel, &tx).await.is_none());
+        let message = rx.recv().await.unwrap();
+        assert!(message.metadata.contains_key(CANCEL_TURN_KEY));
+
+        for (line, code) in [
+            ("{not json", PARSE_ERROR),
+            (r#"{"id":2}"#, INVALID_REQUEST),
+            (
+                r#"{"jsonrpc":"2.0","id":3,"method":"dance"}"#,
+                METHOD_NOT_FOUND,
+            ),
+            (
+                r#"{"jsonrpc":"2.0","id":4,"method":"send","params":{}}"#,
+                INVALID_PARAMS,
+            ),
+        ] {
+            let reply = handle_line(line, &tx).await.unwrap();
+            assert_eq!(reply["error"]["code"], code, "{line}");
+        }
+        assert!(rx.try_recv().is_err());
+
+        let event = Event::ToolStarted {
+            tool_name: "reply".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            serde_json::json!({ "type": "tool_started", "tool_name": "reply" })
+        );
+    }
+}
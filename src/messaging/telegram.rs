@@ -367,6 +367,22 @@ impl Messaging for TelegramAdapter {
                     .await
                     .remove(&message.conversation_id);
             }
+            OutboundResponse::StreamAbort => {
+                let stream = self
+                    .active_messages
+                    .write()
+                    .await
+                    .remove(&message.conversation_id);
+                if let Some(stream) = stream
+                    && let Err(error) = self
+                        .bot
+                        .delete_message(stream.chat_id, stream.message_id)
+                        .send()
+                        .await
+                {
+                    tracing::debug!(%error, "failed to delete aborted streaming message");
+                }
+            }
             OutboundResponse::Status(status) => {
                 self.send_status(message, status).await?;
             }
@@ -420,10 +436,16 @@ impl Messaging for TelegramAdapter {
                     }
                 });
 
-                self.typing_tasks
+                // Thinking is sent before every LLM call of a turn, so stop
+                // the previous loop instead of leaving it running.
+                if let Some(previous) = self
+                    .typing_tasks
                     .write()
                     .await
-                    .insert(conversation_id, handle);
+                    .insert(conversation_id, handle)
+                {
+                    previous.abort();
+                }
             }
             _ => {
                 self.stop_typing(&message.conversation_id).await;
@@ -0,0 +1,27 @@
+//! TLS client connections for adapters that speak their protocol over a raw
+//! socket (IRC, XMPP, IMAP, SMTP), verified against the webpki roots.
+
+use anyhow::Context as _;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::client::TlsStream;
+
+use std::sync::Arc;
+
+/// Run the TLS handshake for `host` over an established connection.
+pub async fn connect<S>(host: &str, stream: S) -> anyhow::Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .with_context(|| format!("invalid server name '{host}'"))?;
+    tokio_rustls::TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+        .with_context(|| format!("TLS handshake with {host} failed"))
+}
@@ -1,7 +1,8 @@
 //! Messaging trait and dynamic dispatch companion.
 
 use crate::error::Result;
-use crate::{InboundMessage, OutboundResponse, StatusUpdate};
+use crate::messaging::presence::Presence;
+use crate::{InboundMessage, ModerationAction, OutboundResponse, StatusUpdate};
 use futures::Stream;
 use std::pin::Pin;
 
@@ -62,6 +63,40 @@ pub trait Messaging: Send + Sync + 'static {
         async { Ok(Vec::new()) }
     }
 
+    /// Take a moderation action in a conversation, with `reason` recorded
+    /// on the platform's own audit log where it has one.
+    fn moderate(
+        &self,
+        conversation_id: &str,
+        action: ModerationAction,
+        reason: &str,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = (conversation_id, action, reason);
+        let name = self.name().to_string();
+        async move { Err(anyhow::anyhow!("{name} doesn't support moderation").into()) }
+    }
+
+    /// Replace the tags of the forum post a conversation is, by tag name.
+    fn tag_post(
+        &self,
+        conversation_id: &str,
+        tags: Vec<String>,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = (conversation_id, tags);
+        let name = self.name().to_string();
+        async move { Err(anyhow::anyhow!("{name} doesn't support forum tags").into()) }
+    }
+
+    /// Show what the agents are doing in the bot's presence, on platforms
+    /// that have one.
+    fn set_presence(
+        &self,
+        presence: Presence,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = presence;
+        async { Ok(()) }
+    }
+
     /// Health check.
     fn health_check(&self) -> impl std::future::Future<Output = Result<()>> + Send;
 
@@ -104,6 +139,24 @@ pub trait MessagingDyn: Send + Sync + 'static {
         limit: usize,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<Vec<HistoryMessage>>> + Send + 'a>>;
 
+    fn moderate<'a>(
+        &'a self,
+        conversation_id: &'a str,
+        action: ModerationAction,
+        reason: &'a str,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
+    fn tag_post<'a>(
+        &'a self,
+        conversation_id: &'a str,
+        tags: Vec<String>,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
+    fn set_presence<'a>(
+        &'a self,
+        presence: Presence,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
@@ -156,6 +209,30 @@ impl<T: Messaging> MessagingDyn for T {
         Box::pin(Messaging::fetch_history(self, message, limit))
     }
 
+    fn moderate<'a>(
+        &'a self,
+        conversation_id: &'a str,
+        action: ModerationAction,
+        reason: &'a str,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::moderate(self, conversation_id, action, reason))
+    }
+
+    fn tag_post<'a>(
+        &'a self,
+        conversation_id: &'a str,
+        tags: Vec<String>,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::tag_post(self, conversation_id, tags))
+    }
+
+    fn set_presence<'a>(
+        &'a self,
+        presence: Presence,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::set_presence(self, presence))
+    }
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
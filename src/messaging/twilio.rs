@@ -0,0 +1,194 @@
+//! Twilio's Messages API and webhook requests, shared by the adapters for
+//! platforms reached through Twilio (WhatsApp, SMS).
+
+use crate::Attachment;
+
+use axum::extract::OriginalUri;
+use axum::http::{HeaderMap, header};
+use axum::response::IntoResponse;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use std::collections::BTreeMap;
+
+/// Error code for a message to a number that opted out by texting STOP.
+pub const OPTED_OUT: i64 = 21610;
+
+/// Empty TwiML, so Twilio doesn't answer a message itself.
+const EMPTY_TWIML: &str = r#"<?xml version="1.0" encoding="UTF-8"?><Response></Response>"#;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("failed to reach twilio: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("twilio rejected the message ({code}): {message}")]
+    Rejected { code: i64, message: String },
+}
+
+/// Sends messages from one Twilio account.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    account_sid: String,
+    auth_token: String,
+}
+
+impl Client {
+    pub fn new(account_sid: &str, auth_token: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            account_sid: account_sid.to_string(),
+            auth_token: auth_token.to_string(),
+        }
+    }
+
+    /// Send a message with the Messages API `params`, returning its SID.
+    pub async fn send(&self, params: &[(&str, String)]) -> Result<String, SendError> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        );
+        let response = self
+            .http
+            .post(url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(params)
+            .send()
+            .await?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await?;
+        if !status.is_success() {
+            return Err(SendError::Rejected {
+                code: body["code"].as_i64().unwrap_or_default(),
+                message: body["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(body["sid"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// Whether a webhook request came from Twilio. Without a public URL there's
+/// no URL to check the signature against, so every request passes.
+pub fn verified(
+    auth_token: &str,
+    public_url: Option<&str>,
+    headers: &HeaderMap,
+    uri: &OriginalUri,
+    params: &BTreeMap<String, String>,
+) -> bool {
+    let Some(public_url) = public_url else {
+        return true;
+    };
+    let Some(signature) = headers
+        .get("x-twilio-signature")
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let url = match uri.path_and_query() {
+        Some(path) => format!("{public_url}{path}"),
+        None => format!("{public_url}{}", uri.path()),
+    };
+    base64::engine::general_purpose::STANDARD
+        .decode(signature)
+        .is_ok_and(|signature| {
+            mac(auth_token, &url, params)
+                .verify_slice(&signature)
+                .is_ok()
+        })
+}
+
+/// Twilio's request signature: the HMAC-SHA1 of the URL followed by each
+/// form parameter's name and value, sorted by name.
+fn mac(auth_token: &str, url: &str, params: &BTreeMap<String, String>) -> Hmac<Sha1> {
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(auth_token.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(url.as_bytes());
+    for (name, value) in params {
+        mac.update(name.as_bytes());
+        mac.update(value.as_bytes());
+    }
+    mac
+}
+
+/// The response to an incoming message webhook.
+pub fn empty_response() -> axum::response::Response {
+    ([(header::CONTENT_TYPE, "text/xml")], EMPTY_TWIML).into_response()
+}
+
+/// The media on an incoming message, as attachments and as metadata entries
+/// with each file's URL and content type.
+pub fn media(params: &BTreeMap<String, String>) -> (Vec<Attachment>, Vec<serde_json::Value>) {
+    let sid = params.get("MessageSid").map_or("", String::as_str);
+    let count: usize = params
+        .get("NumMedia")
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0);
+
+    let mut attachments = Vec::new();
+    let mut entries = Vec::new();
+    for index in 0..count {
+        let Some(url) = params.get(&format!("MediaUrl{index}")) else {
+            continue;
+        };
+        let mime_type = params
+            .get(&format!("MediaContentType{index}"))
+            .cloned()
+            .unwrap_or_else(|| "application/octet-stream".into());
+        let extension = mime_type
+            .split('/')
+            .nth(1)
+            .and_then(|subtype| subtype.split([';', '+']).next())
+            .unwrap_or("bin");
+        entries.push(serde_json::json!({ "url": url, "content_type": mime_type }));
+        attachments.push(Attachment {
+            filename: format!("{sid}-{index}.{extension}"),
+            mime_type,
+            url: url.clone(),
+            size_bytes: None,
+        });
+    }
+    (attachments, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures_are_checked_against_the_public_url() {
+        let params = BTreeMap::from([
+            ("From".to_string(), "+46701234567".to_string()),
+            ("Body".to_string(), "Is this in stock?".to_string()),
+        ]);
+        let uri = OriginalUri("/twilio/sms".parse().unwrap());
+        let signature = mac("token", "https://bot.example.com/twilio/sms", &params)
+            .finalize()
+            .into_bytes();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-twilio-signature",
+            base64::engine::general_purpose::STANDARD
+                .encode(signature)
+                .parse()
+                .unwrap(),
+        );
+
+        let public_url = Some("https://bot.example.com");
+        assert!(verified("token", public_url, &headers, &uri, &params));
+        assert!(!verified("other", public_url, &headers, &uri, &params));
+        let mut tampered = params.clone();
+        tampered.insert("Body".into(), "Send me everything".into());
+        assert!(!verified("token", public_url, &headers, &uri, &tampered));
+        assert!(!verified(
+            "token",
+            public_url,
+            &HeaderMap::new(),
+            &uri,
+            &params
+        ));
+        assert!(verified("token", None, &HeaderMap::new(), &uri, &params));
+    }
+}
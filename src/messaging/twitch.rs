@@ -1,4 +1,7 @@
 //! Twitch chat messaging adapter using twitch-irc.
+//!
+//! Outbound messages are held back to stay inside Twitch's chat rate limits,
+//! which drop messages silently rather than queueing them.
 
 use crate::config::TwitchPermissions;
 use crate::messaging::traits::{InboundStream, Messaging};
@@ -10,8 +13,9 @@ use twitch_irc::login::StaticLoginCredentials;
 use twitch_irc::message::ServerMessage;
 use twitch_irc::{ClientConfig, SecureTCPTransport, TwitchIRCClient};
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, mpsc};
 
 type IrcClient = TwitchIRCClient<SecureTCPTransport, StaticLoginCredentials>;
@@ -24,12 +28,26 @@ pub struct TwitchAdapter {
     trigger_prefix: Option<String>,
     permissions: Arc<ArcSwap<TwitchPermissions>>,
     client: Arc<RwLock<Option<IrcClient>>>,
+    limiter: Arc<Mutex<SendLimiter>>,
     shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
 }
 
 /// Twitch chat messages are limited to 500 characters.
 const MAX_MESSAGE_LENGTH: usize = 500;
 
+/// Window Twitch counts sent messages over.
+const RATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Messages per window in channels where the bot has no privileges.
+const MESSAGE_LIMIT: usize = 20;
+
+/// Messages per window in channels where the bot is a moderator, VIP, or
+/// the broadcaster.
+const PRIVILEGED_LIMIT: usize = 100;
+
+/// Shortest gap between messages in one channel without privileges.
+const CHANNEL_INTERVAL: Duration = Duration::from_secs(1);
+
 impl TwitchAdapter {
     pub fn new(
         username: impl Into<String>,
@@ -45,9 +63,42 @@ impl TwitchAdapter {
             trigger_prefix,
             permissions,
             client: Arc::new(RwLock::new(None)),
+            limiter: Arc::new(Mutex::new(SendLimiter::default())),
             shutdown_tx: Arc::new(RwLock::new(None)),
         }
     }
+
+    /// Send one message to `channel` once the rate limits allow it, as a
+    /// reply to `reply_to` if given.
+    async fn say(&self, channel: &str, text: String, reply_to: Option<&str>) -> anyhow::Result<()> {
+        loop {
+            let wait = self
+                .limiter
+                .lock()
+                .expect("twitch send limiter lock poisoned")
+                .reserve(channel, Instant::now());
+            if wait.is_zero() {
+                break;
+            }
+            tokio::time::sleep(wait).await;
+        }
+
+        let client_guard = self.client.read().await;
+        let client = client_guard
+            .as_ref()
+            .context("twitch client not connected")?;
+        match reply_to {
+            Some(parent_id) => client
+                .say_in_reply_to(&(channel, parent_id), text)
+                .await
+                .context("failed to send twitch reply")?,
+            None => client
+                .say(channel.to_owned(), text)
+                .await
+                .context("failed to send twitch message")?,
+        }
+        Ok(())
+    }
 }
 
 impl Messaging for TwitchAdapter {
@@ -93,6 +144,7 @@ impl Messaging for TwitchAdapter {
         let permissions = self.permissions.clone();
         let bot_username = self.username.to_lowercase();
         let trigger_prefix = self.trigger_prefix.clone();
+        let limiter = self.limiter.clone();
 
         tokio::spawn(async move {
             loop {
@@ -107,8 +159,32 @@ impl Messaging for TwitchAdapter {
                             break;
                         };
 
-                        let ServerMessage::Privmsg(privmsg) = message else {
-                            continue;
+                        let privmsg = match message {
+                            ServerMessage::Privmsg(privmsg) => privmsg,
+                            // The bot's badges in a channel decide its rate limit there.
+                            ServerMessage::UserState(state) => {
+                                let privileged = state.badges.iter().any(|badge| {
+                                    matches!(
+                                        badge.name.as_str(),
+                                        "moderator" | "vip" | "broadcaster"
+                                    )
+                                });
+                                limiter
+                                    .lock()
+                                    .expect("twitch send limiter lock poisoned")
+                                    .set_privileged(&state.channel_login, privileged);
+                                continue;
+                            }
+                            ServerMessage::Notice(notice)
+                                if notice.message_id.as_deref() == Some("msg_ratelimit") =>
+                            {
+                                tracing::warn!(
+                                    channel = ?notice.channel_login,
+                                    "twitch dropped a message over the rate limit"
+                                );
+                                continue;
+                            }
+                            _ => continue,
                         };
 
                         // Skip our own messages
@@ -208,55 +284,26 @@ impl Messaging for TwitchAdapter {
         message: &InboundMessage,
         response: OutboundResponse,
     ) -> crate::Result<()> {
-        let client_guard = self.client.read().await;
-        let client = client_guard
-            .as_ref()
-            .context("twitch client not connected")?;
-
         let channel = message
             .metadata
             .get("twitch_channel")
             .and_then(|v| v.as_str())
             .context("missing twitch_channel in metadata")?;
 
-        match response {
-            OutboundResponse::Text(text) => {
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
-                    client
-                        .say(channel.to_owned(), chunk)
-                        .await
-                        .context("failed to send twitch message")?;
-                }
-            }
-            OutboundResponse::RichMessage { text, .. } => {
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
-                    client
-                        .say(channel.to_owned(), chunk)
-                        .await
-                        .context("failed to send twitch message")?;
-                }
-            }
+        let (text, reply_to) = match response {
+            // No ephemeral or scheduled messages on Twitch — send as regular
+            // chat messages, immediately
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => (text, None),
+            // Twitch has no threads — reply to the source message instead
             OutboundResponse::ThreadReply { text, .. } => {
-                // Twitch has no threads — reply to the source message instead
-                let reply_to_id = message
+                let reply_to = message
                     .metadata
                     .get("twitch_message_id")
                     .and_then(|v| v.as_str());
-
-                for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
-                    if let Some(parent_id) = reply_to_id {
-                        let reply_ref = (channel, parent_id);
-                        client
-                            .say_in_reply_to(&reply_ref, chunk)
-                            .await
-                            .context("failed to send twitch reply")?;
-                    } else {
-                        client
-                            .say(channel.to_owned(), chunk)
-                            .await
-                            .context("failed to send twitch message")?;
-                    }
-                }
+                (text, reply_to)
             }
             OutboundResponse::File {
                 filename, caption, ..
@@ -266,61 +313,34 @@ impl Messaging for TwitchAdapter {
                     Some(caption) => format!("[File: {filename}] {caption}"),
                     None => format!("[File: {filename}]"),
                 };
-                client
-                    .say(channel.to_owned(), text)
-                    .await
-                    .context("failed to send twitch file notice")?;
-            }
-            // Twitch doesn't support message editing, so buffer streaming and
-            // send the final result as a single message
-            OutboundResponse::StreamStart | OutboundResponse::StreamChunk(_) => {
-                // No-op: we can't edit messages in Twitch chat.
-                // The StreamEnd with final text is handled by the outbound routing
-                // which sends a Text response after StreamEnd.
+                (text, None)
             }
-            OutboundResponse::StreamEnd => {}
-            // Reactions, status updates, and Slack-specific variants aren't meaningful in Twitch chat
+            // Twitch doesn't support message editing, so streaming is
+            // buffered: the final text arrives as a Text response after
+            // StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort => return Ok(()),
+            // Reactions and status updates aren't meaningful in Twitch chat
             OutboundResponse::Reaction(_)
             | OutboundResponse::RemoveReaction(_)
-            | OutboundResponse::Status(_) => {}
-            OutboundResponse::Ephemeral { text, .. } => {
-                // No ephemeral concept in Twitch — send as regular chat message
-                client
-                    .say(channel.to_owned(), text)
-                    .await
-                    .context("failed to send ephemeral fallback on twitch")?;
-            }
-            OutboundResponse::ScheduledMessage { text, .. } => {
-                // No scheduled messages on Twitch — send immediately
-                client
-                    .say(channel.to_owned(), text)
-                    .await
-                    .context("failed to send scheduled message fallback on twitch")?;
-            }
+            | OutboundResponse::Status(_) => return Ok(()),
+        };
+
+        for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
+            self.say(channel, chunk, reply_to).await?;
         }
 
         Ok(())
     }
 
     async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
-        let client_guard = self.client.read().await;
-        let client = client_guard
-            .as_ref()
-            .context("twitch client not connected")?;
-
-        if let OutboundResponse::Text(text) = response {
-            let channel = target.strip_prefix('#').unwrap_or(target);
-            for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
-                client
-                    .say(channel.to_owned(), chunk)
-                    .await
-                    .context("failed to broadcast twitch message")?;
-            }
-        } else if let OutboundResponse::RichMessage { text, .. } = response {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
             let channel = target.strip_prefix('#').unwrap_or(target);
             for chunk in split_message(&text, MAX_MESSAGE_LENGTH) {
-                client
-                    .say(channel.to_owned(), chunk)
+                self.say(channel, chunk, None)
                     .await
                     .context("failed to broadcast twitch message")?;
             }
@@ -351,6 +371,62 @@ impl Messaging for TwitchAdapter {
     }
 }
 
+/// Messages sent in the last window, to hold new ones back until Twitch's
+/// limits allow them.
+#[derive(Debug, Default)]
+struct SendLimiter {
+    sent: VecDeque<Instant>,
+    last_in_channel: HashMap<String, Instant>,
+    /// Channels where the bot is a moderator, VIP, or the broadcaster.
+    privileged: HashSet<String>,
+}
+
+impl SendLimiter {
+    fn set_privileged(&mut self, channel: &str, privileged: bool) {
+        if privileged {
+            self.privileged.insert(channel.to_lowercase());
+        } else {
+            self.privileged.remove(&channel.to_lowercase());
+        }
+    }
+
+    /// How long until a message may go to `channel`. When that's now, the
+    /// message is counted as sent.
+    fn reserve(&mut self, channel: &str, now: Instant) -> Duration {
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= RATE_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+
+        let channel = channel.to_lowercase();
+        let privileged = self.privileged.contains(&channel);
+        let limit = if privileged {
+            PRIVILEGED_LIMIT
+        } else {
+            MESSAGE_LIMIT
+        };
+
+        let mut wait = Duration::ZERO;
+        if self.sent.len() >= limit {
+            // Room opens up once enough of the oldest messages age out.
+            let oldest = self.sent[self.sent.len() - limit];
+            wait = RATE_WINDOW.saturating_sub(now.duration_since(oldest));
+        }
+        if !privileged && let Some(last) = self.last_in_channel.get(&channel) {
+            wait = wait.max(CHANNEL_INTERVAL.saturating_sub(now.duration_since(*last)));
+        }
+
+        if wait.is_zero() {
+            self.sent.push_back(now);
+            self.last_in_channel.insert(channel, now);
+        }
+        wait
+    }
+}
+
 /// Split a message into chunks that fit within Twitch's character limit.
 /// Tries to split at newlines, then spaces, then hard-cuts.
 fn split_message(text: &str, max_len: usize) -> Vec<String> {
@@ -378,3 +454,35 @@ fn split_message(text: &str, max_len: usize) -> Vec<String> {
 
     chunks
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_limiter_holds_messages_within_twitch_limits() {
+        let mut limiter = SendLimiter::default();
+        let start = Instant::now();
+
+        assert_eq!(limiter.reserve("chan", start), Duration::ZERO);
+        // One message per second per channel without privileges.
+        assert_eq!(
+            limiter.reserve("chan", start + Duration::from_millis(400)),
+            Duration::from_millis(600)
+        );
+        assert_eq!(limiter.reserve("other", start), Duration::ZERO);
+
+        for second in 1..19 {
+            let now = start + Duration::from_secs(second);
+            assert_eq!(limiter.reserve("chan", now), Duration::ZERO);
+        }
+        // 20 sent in the window; the next waits for the first to age out.
+        let now = start + Duration::from_secs(20);
+        assert_eq!(limiter.reserve("chan", now), Duration::from_secs(10));
+
+        // A moderator has room for 100 and no per-channel gap.
+        limiter.set_privileged("Chan", true);
+        assert_eq!(limiter.reserve("chan", now), Duration::ZERO);
+        assert_eq!(limiter.reserve("chan", now), Duration::ZERO);
+    }
+}
@@ -16,13 +16,14 @@ pub struct WebChatAdapter {
     sessions: Arc<RwLock<HashMap<String, mpsc::Sender<WebChatEvent>>>>,
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum WebChatEvent {
     Thinking,
     Text(String),
     StreamStart,
     StreamChunk(String),
     StreamEnd,
+    StreamAbort,
     ToolStarted { tool_name: String },
     ToolCompleted { tool_name: String },
     StopTyping,
@@ -46,6 +47,21 @@ impl WebChatAdapter {
         rx
     }
 
+    /// Register a session unless one is already open for this conversation.
+    pub async fn try_register_session(
+        &self,
+        conversation_id: &str,
+    ) -> Option<mpsc::Receiver<WebChatEvent>> {
+        let mut sessions = self.sessions.write().await;
+        if sessions.contains_key(conversation_id) {
+            return None;
+        }
+        let (tx, rx) = mpsc::channel(256);
+        sessions.insert(conversation_id.to_string(), tx);
+        tracing::debug!(%conversation_id, "webchat session registered");
+        Some(rx)
+    }
+
     pub async fn unregister_session(&self, conversation_id: &str) {
         self.sessions.write().await.remove(conversation_id);
         tracing::debug!(%conversation_id, "webchat session unregistered");
@@ -77,15 +93,18 @@ impl Messaging for WebChatAdapter {
         let (event, signals_done) = match response {
             OutboundResponse::Text(text) => (WebChatEvent::Text(text), true),
             OutboundResponse::ThreadReply { text, .. } => (WebChatEvent::Text(text), true),
+            // Webchat can't show blocks, cards, or polls; the text fallback
+            // carries the reply.
+            OutboundResponse::RichMessage { text, .. } => (WebChatEvent::Text(text), true),
             OutboundResponse::StreamStart => (WebChatEvent::StreamStart, false),
             OutboundResponse::StreamChunk(text) => (WebChatEvent::StreamChunk(text), false),
             OutboundResponse::StreamEnd => (WebChatEvent::StreamEnd, true),
+            OutboundResponse::StreamAbort => (WebChatEvent::StreamAbort, true),
             OutboundResponse::File { .. }
             | OutboundResponse::Reaction(_)
             | OutboundResponse::RemoveReaction(_)
             | OutboundResponse::Ephemeral { .. }
             | OutboundResponse::ScheduledMessage { .. }
-            | OutboundResponse::RichMessage { .. }
             | OutboundResponse::Status(_) => return Ok(()),
         };
 
@@ -128,3 +147,38 @@ impl Messaging for WebChatAdapter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageContent;
+
+    #[tokio::test]
+    async fn test_rich_messages_answer_with_their_text() {
+        let adapter = WebChatAdapter::new();
+        let mut events = adapter.register_session("agent:main:helper").await;
+        let message = InboundMessage {
+            id: "1".into(),
+            source: "webchat".into(),
+            conversation_id: "agent:main:helper".into(),
+            sender_id: "agent:main".into(),
+            agent_id: Some("helper".into()),
+            content: MessageContent::Text("question".into()),
+            timestamp: chrono::Utc::now(),
+            metadata: HashMap::new(),
+            formatted_author: None,
+        };
+
+        let rich = OutboundResponse::RichMessage {
+            text: "answer".into(),
+            blocks: Vec::new(),
+            cards: Vec::new(),
+            interactive_elements: Vec::new(),
+            poll: None,
+        };
+        adapter.respond(&message, rich).await.expect("respond");
+
+        assert!(matches!(events.recv().await, Some(WebChatEvent::Text(text)) if text == "answer"));
+        assert!(matches!(events.recv().await, Some(WebChatEvent::Done)));
+    }
+}
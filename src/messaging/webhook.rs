@@ -4,13 +4,22 @@
 //! delivers responses via a per-conversation polling endpoint. This is
 //! the integration point for scripts, CI pipelines, and other programs
 //! that need to interact with Spacebot programmatically.
-
+//!
+//! Configured hooks also accept arbitrary JSON at `/hooks/<token>`, from
+//! services like CI or alerting that can't speak the `/send` format. The
+//! payload is rendered into a message through the hook's template, and the
+//! agent's reply can be posted to a linked channel on another platform.
+
+use crate::agent::listening;
+use crate::config::{WebhookConfig, WebhookHook};
+use crate::cron::scheduler::DeliveryTarget;
 use crate::messaging::traits::{InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse};
 
 use anyhow::Context as _;
 use axum::Router;
-use axum::extract::{Json, State};
+use axum::body::Bytes;
+use axum::extract::{Json, Path, State};
 use axum::http::StatusCode;
 use axum::routing::{get, post};
 use serde::{Deserialize, Serialize};
@@ -19,10 +28,15 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc};
 
+/// Metadata key holding where the replies to a hook's message are posted,
+/// in "adapter:target" format.
+const DELIVER_TO_KEY: &str = "webhook_deliver_to";
+
 /// Webhook adapter state.
 pub struct WebhookAdapter {
     port: u16,
     bind: String,
+    hooks: Arc<Vec<WebhookHook>>,
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
     /// Buffered responses per conversation_id, waiting to be polled.
     response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
@@ -32,6 +46,7 @@ pub struct WebhookAdapter {
 /// Shared state for axum handlers.
 #[derive(Clone)]
 struct AppState {
+    hooks: Arc<Vec<WebhookHook>>,
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
     response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
 }
@@ -72,10 +87,11 @@ struct PollResponse {
 }
 
 impl WebhookAdapter {
-    pub fn new(port: u16, bind: impl Into<String>) -> Self {
+    pub fn new(config: &WebhookConfig) -> Self {
         Self {
-            port,
-            bind: bind.into(),
+            port: config.port,
+            bind: config.bind.clone(),
+            hooks: Arc::new(config.hooks.clone()),
             inbound_tx: Arc::new(RwLock::new(None)),
             response_buffers: Arc::new(RwLock::new(HashMap::new())),
             shutdown_tx: Arc::new(RwLock::new(None)),
@@ -96,6 +112,7 @@ impl Messaging for WebhookAdapter {
         *self.shutdown_tx.write().await = Some(shutdown_tx);
 
         let state = AppState {
+            hooks: self.hooks.clone(),
             inbound_tx: self.inbound_tx.clone(),
             response_buffers: self.response_buffers.clone(),
         };
@@ -103,6 +120,7 @@ impl Messaging for WebhookAdapter {
         let app = Router::new()
             .route("/send", post(handle_send))
             .route("/poll/{conversation_id}", get(handle_poll))
+            .route("/hooks/{token}", post(handle_hook))
             .route("/health", get(handle_health))
             .with_state(state);
 
@@ -181,6 +199,12 @@ impl Messaging for WebhookAdapter {
                 filename: None,
                 caption: None,
             },
+            OutboundResponse::StreamAbort => WebhookResponse {
+                response_type: "stream_abort".into(),
+                content: None,
+                filename: None,
+                caption: None,
+            },
             // Reactions, status updates, and remove-reaction aren't meaningful over webhook
             OutboundResponse::Reaction(_)
             | OutboundResponse::RemoveReaction(_)
@@ -268,9 +292,112 @@ async fn handle_send(
     Ok(StatusCode::ACCEPTED)
 }
 
+async fn handle_hook(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let Some(hook) = state.hooks.iter().find(|hook| hook.token == token) else {
+        return Err((StatusCode::NOT_FOUND, "unknown hook".into()));
+    };
+    let payload: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|error| (StatusCode::BAD_REQUEST, format!("invalid JSON: {error}")))?;
+    let text = render_hook_message(hook, &payload).map_err(|error| {
+        tracing::warn!(hook = %hook.name, %error, "failed to render webhook hook template");
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("failed to render template: {error}"),
+        )
+    })?;
+
+    let tx = state.inbound_tx.read().await;
+    let Some(tx) = tx.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "webhook not initialized".into(),
+        ));
+    };
+
+    let mut inbound = hook_message(hook, text);
+    if !hook.respond {
+        listening::mark_passive(&mut inbound);
+    }
+    tx.send(inbound)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "channel closed".into()))?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// The message text for a hook's payload: its template rendered with the
+/// payload as `payload`, or the payload as JSON without a template.
+fn render_hook_message(hook: &WebhookHook, payload: &serde_json::Value) -> anyhow::Result<String> {
+    let Some(template) = &hook.template else {
+        let json = serde_json::to_string_pretty(payload)?;
+        return Ok(format!("Webhook `{}`:\n```json\n{json}\n```", hook.name));
+    };
+    let environment = minijinja::Environment::new();
+    let text = environment.render_str(
+        template,
+        minijinja::context! { payload => payload, hook => hook.name },
+    )?;
+    Ok(text.trim().to_string())
+}
+
+/// The inbound message for a hook. Each hook is its own conversation.
+fn hook_message(hook: &WebhookHook, text: String) -> InboundMessage {
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "webhook_hook".into(),
+        serde_json::Value::String(hook.name.clone()),
+    );
+    metadata.insert(
+        "display_name".into(),
+        serde_json::Value::String(hook.name.clone()),
+    );
+    if let Some(deliver_to) = &hook.deliver_to {
+        metadata.insert(
+            DELIVER_TO_KEY.into(),
+            serde_json::Value::String(deliver_to.clone()),
+        );
+    }
+
+    InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "webhook".into(),
+        conversation_id: format!("webhook:hook:{}", hook.name),
+        sender_id: hook.name.clone(),
+        agent_id: hook.agent.clone().map(Into::into),
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(hook.name.clone()),
+    }
+}
+
+/// Where a reply to `message` is also posted, and what's posted, if the
+/// message came through a hook linked to a channel. Only finished replies
+/// are posted; streamed chunks, statuses, and reactions stay here.
+pub fn hook_delivery(
+    message: &InboundMessage,
+    response: &OutboundResponse,
+) -> Option<(DeliveryTarget, OutboundResponse)> {
+    let target = message
+        .metadata
+        .get(DELIVER_TO_KEY)
+        .and_then(|value| value.as_str())
+        .and_then(DeliveryTarget::parse)?;
+    let response = match response {
+        OutboundResponse::Text(_) | OutboundResponse::RichMessage { .. } => response.clone(),
+        OutboundResponse::ThreadReply { text, .. } => OutboundResponse::Text(text.clone()),
+        _ => return None,
+    };
+    Some((target, response))
+}
+
 async fn handle_poll(
     State(state): State<AppState>,
-    axum::extract::Path(conversation_id): axum::extract::Path<String>,
+    Path(conversation_id): Path<String>,
 ) -> Json<PollResponse> {
     let key = format!("webhook:{conversation_id}");
     let messages = state
@@ -286,3 +413,41 @@ async fn handle_poll(
 async fn handle_health() -> StatusCode {
     StatusCode::OK
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(template: Option<&str>) -> WebhookHook {
+        WebhookHook {
+            name: "ci".into(),
+            token: "secret".into(),
+            template: template.map(Into::into),
+            agent: None,
+            respond: true,
+            deliver_to: Some("discord:123".into()),
+        }
+    }
+
+    #[test]
+    fn test_hook_payloads_render_and_replies_deliver_to_linked_channel() {
+        let payload = serde_json::json!({"pipeline": "deploy", "status": "failed"});
+        let templated = hook(Some(
+            "{{ payload.pipeline }} {{ payload.status }} ({{ hook }})",
+        ));
+        assert_eq!(
+            render_hook_message(&templated, &payload).unwrap(),
+            "deploy failed (ci)"
+        );
+        let raw = render_hook_message(&hook(None), &payload).unwrap();
+        assert!(raw.starts_with("Webhook `ci`:") && raw.contains("\"status\": \"failed\""));
+
+        let message = hook_message(&templated, "deploy failed".into());
+        assert_eq!(message.conversation_id, "webhook:hook:ci");
+        let (target, response) =
+            hook_delivery(&message, &OutboundResponse::Text("On it.".into())).unwrap();
+        assert_eq!(target.to_string(), "discord:123");
+        assert!(matches!(response, OutboundResponse::Text(text) if text == "On it."));
+        assert!(hook_delivery(&message, &OutboundResponse::StreamChunk("On".into())).is_none());
+    }
+}
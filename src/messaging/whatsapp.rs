@@ -0,0 +1,487 @@
+//! WhatsApp messaging adapter over Twilio.
+//!
+//! Twilio posts incoming messages and delivery receipts to a small HTTP
+//! server run by the adapter; replies go out through Twilio's Messages API.
+//! Each phone number is its own conversation, `whatsapp:<number>`.
+//!
+//! With `public_url` set, requests are checked against Twilio's signature,
+//! sent messages ask for delivery receipts, and files are served from the
+//! server for Twilio to fetch. A message WhatsApp couldn't deliver is noted
+//! in its conversation, so the agent knows the customer never saw it.
+
+use crate::agent::listening;
+use crate::config::{WhatsAppConfig, WhatsAppPermissions};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::messaging::twilio;
+use crate::{InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use axum::Router;
+use axum::extract::{Form, OriginalUri, Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, mpsc};
+
+/// Longest message body Twilio accepts.
+const MAX_MESSAGE_LEN: usize = 1600;
+
+/// How long a sent message is tracked for its delivery receipt.
+const RECEIPT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a file is served for Twilio to fetch.
+const MEDIA_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// WhatsApp adapter state.
+pub struct WhatsAppAdapter {
+    config: Arc<WhatsAppConfig>,
+    permissions: Arc<ArcSwap<WhatsAppPermissions>>,
+    client: twilio::Client,
+    inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
+    /// Number each sent message went to, by message SID, until its receipt.
+    sent: Arc<RwLock<HashMap<String, (String, Instant)>>>,
+    /// Files waiting for Twilio to fetch them, by ID.
+    media: Arc<RwLock<HashMap<String, HostedMedia>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// A file served for Twilio to attach to an outgoing message.
+struct HostedMedia {
+    data: Vec<u8>,
+    mime_type: String,
+    stored_at: Instant,
+}
+
+/// Shared state for axum handlers.
+#[derive(Clone)]
+struct AppState {
+    config: Arc<WhatsAppConfig>,
+    permissions: Arc<ArcSwap<WhatsAppPermissions>>,
+    inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
+    sent: Arc<RwLock<HashMap<String, (String, Instant)>>>,
+    media: Arc<RwLock<HashMap<String, HostedMedia>>>,
+}
+
+impl WhatsAppAdapter {
+    pub fn new(config: &WhatsAppConfig, permissions: Arc<ArcSwap<WhatsAppPermissions>>) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+            permissions,
+            client: twilio::Client::new(&config.account_sid, &config.auth_token),
+            inbound_tx: Arc::new(RwLock::new(None)),
+            sent: Arc::new(RwLock::new(HashMap::new())),
+            media: Arc::new(RwLock::new(HashMap::new())),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Send `body` to `number`, with the file at `media_url` attached.
+    async fn send(&self, number: &str, body: &str, media_url: Option<&str>) -> anyhow::Result<()> {
+        let config = &self.config;
+        let mut params = vec![
+            ("From", format!("whatsapp:{}", config.from_number)),
+            ("To", format!("whatsapp:{number}")),
+            ("Body", body.to_string()),
+        ];
+        if let Some(media_url) = media_url {
+            params.push(("MediaUrl", media_url.to_string()));
+        }
+        if let Some(public_url) = &config.public_url {
+            params.push((
+                "StatusCallback",
+                format!("{public_url}/twilio/whatsapp/status"),
+            ));
+        }
+
+        let sid = self.client.send(&params).await?;
+        if config.public_url.is_some() {
+            let mut sent = self.sent.write().await;
+            sent.retain(|_, (_, sent_at)| sent_at.elapsed() < RECEIPT_TTL);
+            sent.insert(sid, (number.to_string(), Instant::now()));
+        }
+        Ok(())
+    }
+
+    async fn send_text(&self, number: &str, text: &str) -> anyhow::Result<()> {
+        for chunk in crate::messaging::chunking::chunk_message(text, MAX_MESSAGE_LEN) {
+            self.send(number, &chunk, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Serve `data` for Twilio to fetch, returning its URL. None without a
+    /// public URL to serve it at.
+    async fn host_media(&self, data: Vec<u8>, mime_type: String) -> Option<String> {
+        let public_url = self.config.public_url.as_ref()?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut media = self.media.write().await;
+        media.retain(|_, hosted| hosted.stored_at.elapsed() < MEDIA_TTL);
+        media.insert(
+            id.clone(),
+            HostedMedia {
+                data,
+                mime_type,
+                stored_at: Instant::now(),
+            },
+        );
+        Some(format!("{public_url}/twilio/whatsapp/media/{id}"))
+    }
+}
+
+impl Messaging for WhatsAppAdapter {
+    fn name(&self) -> &str {
+        "whatsapp"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.inbound_tx.write().await = Some(inbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        if self.config.public_url.is_none() {
+            tracing::warn!(
+                "whatsapp public_url not set; Twilio signatures aren't checked and \
+                 delivery receipts and files are off"
+            );
+        }
+
+        let state = AppState {
+            config: self.config.clone(),
+            permissions: self.permissions.clone(),
+            inbound_tx: self.inbound_tx.clone(),
+            sent: self.sent.clone(),
+            media: self.media.clone(),
+        };
+
+        let app = Router::new()
+            .route("/twilio/whatsapp", post(handle_message))
+            .route("/twilio/whatsapp/status", post(handle_status))
+            .route("/twilio/whatsapp/media/{id}", get(handle_media))
+            .with_state(state);
+
+        let bind = if self.config.bind.contains(':') {
+            format!("[{}]:{}", self.config.bind, self.config.port)
+        } else {
+            format!("{}:{}", self.config.bind, self.config.port)
+        };
+        let listener = tokio::net::TcpListener::bind(&bind)
+            .await
+            .with_context(|| format!("failed to bind whatsapp server to {bind}"))?;
+        tracing::info!(%bind, "whatsapp server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            {
+                tracing::error!(%error, "whatsapp server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let number = &message.sender_id;
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.send_text(number, &text).await?;
+            }
+            OutboundResponse::File {
+                filename,
+                data,
+                mime_type,
+                caption,
+            } => match self.host_media(data, mime_type).await {
+                Some(url) => {
+                    self.send(number, caption.as_deref().unwrap_or_default(), Some(&url))
+                        .await?;
+                }
+                None => {
+                    let text = match caption {
+                        Some(caption) => format!("[File: {filename}] {caption}"),
+                        None => format!("[File: {filename}]"),
+                    };
+                    self.send_text(number, &text).await?;
+                }
+            },
+            // WhatsApp messages can't be edited, so streaming is buffered:
+            // the final text arrives as a Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort
+            | OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            let number = target.strip_prefix("whatsapp:").unwrap_or(target);
+            self.send_text(number, &text).await?;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if self.inbound_tx.read().await.is_none() {
+            return Err(anyhow::anyhow!("whatsapp adapter not started").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.inbound_tx.write().await = None;
+
+        tracing::info!("whatsapp adapter shut down");
+        Ok(())
+    }
+}
+
+/// Whether the request came from Twilio.
+fn verified(
+    config: &WhatsAppConfig,
+    headers: &HeaderMap,
+    uri: &OriginalUri,
+    params: &BTreeMap<String, String>,
+) -> bool {
+    twilio::verified(
+        &config.auth_token,
+        config.public_url.as_deref(),
+        headers,
+        uri,
+        params,
+    )
+}
+
+async fn forward(state: &AppState, message: InboundMessage) -> StatusCode {
+    let inbound_tx = state.inbound_tx.read().await;
+    let Some(tx) = inbound_tx.as_ref() else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+    match tx.send(message).await {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// Incoming message from Twilio.
+async fn handle_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    uri: OriginalUri,
+    Form(params): Form<BTreeMap<String, String>>,
+) -> axum::response::Response {
+    if !verified(&state.config, &headers, &uri, &params) {
+        tracing::warn!("whatsapp request with invalid twilio signature rejected");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let Some(message) = inbound_message(&params) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let permissions = state.permissions.load();
+    if !permissions.allowed_numbers.is_empty()
+        && !permissions.allowed_numbers.contains(&message.sender_id)
+    {
+        tracing::debug!(number = %message.sender_id, "whatsapp message from unlisted number");
+        return twilio::empty_response();
+    }
+
+    match forward(&state, message).await {
+        StatusCode::OK => twilio::empty_response(),
+        status => status.into_response(),
+    }
+}
+
+/// The inbound message for a Twilio webhook's form parameters.
+fn inbound_message(params: &BTreeMap<String, String>) -> Option<InboundMessage> {
+    let field = |name: &str| params.get(name).map(String::as_str).unwrap_or_default();
+    let number = field("From").strip_prefix("whatsapp:")?.to_string();
+    let sid = field("MessageSid");
+    let profile_name = params.get("ProfileName").unwrap_or(&number).clone();
+
+    let (attachments, media) = twilio::media(params);
+
+    let body = field("Body").to_string();
+    let content = if attachments.is_empty() {
+        MessageContent::Text(body)
+    } else {
+        MessageContent::Media {
+            text: (!body.is_empty()).then_some(body),
+            attachments,
+        }
+    };
+
+    let mut metadata = HashMap::from([
+        ("whatsapp_number".into(), serde_json::json!(number)),
+        ("whatsapp_message_sid".into(), serde_json::json!(sid)),
+        (
+            "sender_display_name".into(),
+            serde_json::json!(profile_name),
+        ),
+    ]);
+    if !media.is_empty() {
+        metadata.insert("whatsapp_media".into(), serde_json::Value::Array(media));
+    }
+    if let Some(replied_to) = params.get("OriginalRepliedMessageSid") {
+        metadata.insert(
+            "whatsapp_replied_to_sid".into(),
+            serde_json::json!(replied_to),
+        );
+    }
+
+    Some(InboundMessage {
+        id: sid.to_string(),
+        source: "whatsapp".into(),
+        conversation_id: format!("whatsapp:{number}"),
+        sender_id: number.clone(),
+        agent_id: None,
+        content,
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(format!("{profile_name} ({number})")),
+    })
+}
+
+/// Delivery receipt for a sent message.
+async fn handle_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    uri: OriginalUri,
+    Form(params): Form<BTreeMap<String, String>>,
+) -> StatusCode {
+    if !verified(&state.config, &headers, &uri, &params) {
+        tracing::warn!("whatsapp receipt with invalid twilio signature rejected");
+        return StatusCode::FORBIDDEN;
+    }
+
+    let field = |name: &str| params.get(name).map(String::as_str).unwrap_or_default();
+    let sid = field("MessageSid");
+    let status = field("MessageStatus");
+    tracing::debug!(sid, status, "whatsapp delivery receipt");
+    if !matches!(status, "delivered" | "read" | "failed" | "undelivered") {
+        return StatusCode::OK;
+    }
+    let Some((number, _)) = state.sent.write().await.remove(sid) else {
+        return StatusCode::OK;
+    };
+    if status != "failed" && status != "undelivered" {
+        return StatusCode::OK;
+    }
+
+    let error_code = field("ErrorCode");
+    tracing::warn!(sid, %number, error_code, "whatsapp message not delivered");
+    let mut note = InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "whatsapp".into(),
+        conversation_id: format!("whatsapp:{number}"),
+        sender_id: "whatsapp-receipts".into(),
+        agent_id: None,
+        content: MessageContent::Text(undelivered_note(error_code)),
+        timestamp: chrono::Utc::now(),
+        metadata: HashMap::new(),
+        formatted_author: Some("WhatsApp".into()),
+    };
+    // Context for the next turn; retrying now would likely fail the same way.
+    listening::mark_passive(&mut note);
+    forward(&state, note).await
+}
+
+fn undelivered_note(error_code: &str) -> String {
+    match error_code {
+        // Free-form messages are only allowed within 24 hours of the
+        // customer's last message.
+        "63016" => "Your last message wasn't delivered: it's been over 24 hours since the \
+                    customer last wrote, so WhatsApp only allows approved templates."
+            .into(),
+        "" => "Your last message wasn't delivered.".into(),
+        code => format!("Your last message wasn't delivered (Twilio error {code})."),
+    }
+}
+
+/// A file being fetched by Twilio for an outgoing message.
+async fn handle_media(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    let media = state.media.read().await;
+    match media
+        .get(&id)
+        .filter(|hosted| hosted.stored_at.elapsed() < MEDIA_TTL)
+    {
+        Some(hosted) => (
+            [(header::CONTENT_TYPE, hosted.mime_type.clone())],
+            hosted.data.clone(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inbound_media_and_undelivered_notes() {
+        let params = BTreeMap::from([
+            ("From".to_string(), "whatsapp:+46701234567".to_string()),
+            ("Body".to_string(), "Is this in stock?".to_string()),
+            ("ProfileName".to_string(), "Anna".to_string()),
+            ("MessageSid".to_string(), "MM123".to_string()),
+            ("NumMedia".to_string(), "1".to_string()),
+            (
+                "MediaUrl0".to_string(),
+                "https://api.twilio.com/media/ME1".to_string(),
+            ),
+            ("MediaContentType0".to_string(), "image/jpeg".to_string()),
+        ]);
+
+        let message = inbound_message(&params).unwrap();
+        assert_eq!(message.conversation_id, "whatsapp:+46701234567");
+        assert_eq!(message.sender_id, "+46701234567");
+        assert_eq!(
+            message.formatted_author.as_deref(),
+            Some("Anna (+46701234567)")
+        );
+        let MessageContent::Media { text, attachments } = message.content else {
+            panic!("expected media content");
+        };
+        assert_eq!(text.as_deref(), Some("Is this in stock?"));
+        assert_eq!(attachments[0].filename, "MM123-0.jpeg");
+        assert_eq!(attachments[0].mime_type, "image/jpeg");
+        assert_eq!(
+            message.metadata["whatsapp_media"][0]["content_type"],
+            "image/jpeg"
+        );
+
+        assert!(inbound_message(&BTreeMap::new()).is_none());
+        assert!(undelivered_note("63016").contains("24 hours"));
+    }
+}
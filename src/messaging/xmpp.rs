@@ -0,0 +1,680 @@
+//! XMPP messaging adapter.
+//!
+//! Each multi-user chat room is a conversation, `xmpp:muc:<room>`, and each
+//! contact chatting with the bot directly one, `xmpp:dm:<jid>`. The client
+//! speaks just enough of RFC 6120 to get online: STARTTLS or TLS from the
+//! start, SASL PLAIN, and resource binding. Then it joins its rooms, answers
+//! server pings, and keeps the connection alive with whitespace.
+
+mod xml;
+
+use crate::config::{XmppConfig, XmppPermissions};
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use base64::Engine as _;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, mpsc};
+
+/// Longest wait between reconnect attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(120);
+
+/// How often a whitespace keepalive is sent, so idle connections aren't
+/// dropped and dead ones are noticed.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+trait XmppStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> XmppStream for T {}
+
+/// XMPP adapter state.
+pub struct XmppAdapter {
+    config: XmppConfig,
+    permissions: Arc<ArcSwap<XmppPermissions>>,
+    outbound_tx: Arc<RwLock<Option<mpsc::Sender<String>>>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl XmppAdapter {
+    pub fn new(config: &XmppConfig, permissions: Arc<ArcSwap<XmppPermissions>>) -> Self {
+        Self {
+            config: config.clone(),
+            permissions,
+            outbound_tx: Arc::new(RwLock::new(None)),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+            connected: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Queue a stanza for the connection to send.
+    async fn send_stanza(&self, stanza: String) -> anyhow::Result<()> {
+        let outbound = self.outbound_tx.read().await;
+        outbound
+            .as_ref()
+            .context("xmpp client not connected")?
+            .send(stanza)
+            .await
+            .context("xmpp connection task stopped")
+    }
+
+    /// Send `text` to `to`, a room with `kind` "groupchat" or a contact with
+    /// "chat".
+    async fn send_text(&self, to: &str, kind: &str, text: &str) -> anyhow::Result<()> {
+        self.send_stanza(format!(
+            "<message to='{}' type='{kind}' id='{}'><body>{}</body></message>",
+            xml::escape(to),
+            uuid::Uuid::new_v4(),
+            xml::escape(text)
+        ))
+        .await
+    }
+}
+
+impl Messaging for XmppAdapter {
+    fn name(&self) -> &str {
+        "xmpp"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (outbound_tx, mut outbound_rx) = mpsc::channel::<String>(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.outbound_tx.write().await = Some(outbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let session = Session {
+            config: self.config.clone(),
+            permissions: self.permissions.clone(),
+            inbound_tx,
+            connected: self.connected.clone(),
+        };
+
+        tokio::spawn(async move {
+            let mut delay = Duration::from_secs(5);
+            loop {
+                let started = Instant::now();
+                match session.run(&mut outbound_rx, &mut shutdown_rx).await {
+                    Ok(Exit::Shutdown) => {
+                        tracing::info!("xmpp connection shutting down");
+                        break;
+                    }
+                    Ok(Exit::InboundClosed) => return,
+                    Ok(Exit::Disconnected) => {
+                        tracing::warn!(server = %session.config.server, "xmpp connection closed");
+                    }
+                    Err(error) => {
+                        tracing::warn!(
+                            server = %session.config.server,
+                            %error,
+                            "xmpp connection failed"
+                        );
+                    }
+                }
+                session.connected.store(false, Ordering::Relaxed);
+
+                // A connection that lasted a while resets the backoff.
+                if started.elapsed() > MAX_RECONNECT_DELAY {
+                    delay = Duration::from_secs(5);
+                }
+                tokio::select! {
+                    _ = shutdown_rx.recv() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let field = |key: &str| {
+            message
+                .metadata
+                .get(key)
+                .and_then(|v| v.as_str())
+                .with_context(|| format!("missing {key} in metadata"))
+        };
+        let target = field("xmpp_target")?;
+        let kind = field("xmpp_type")?;
+
+        match response {
+            OutboundResponse::Text(text)
+            | OutboundResponse::RichMessage { text, .. }
+            | OutboundResponse::ThreadReply { text, .. }
+            | OutboundResponse::Ephemeral { text, .. }
+            | OutboundResponse::ScheduledMessage { text, .. } => {
+                self.send_text(target, kind, &text).await?;
+            }
+            OutboundResponse::File {
+                filename, caption, ..
+            } => {
+                let text = match caption {
+                    Some(caption) => format!("[File: {filename}] {caption}"),
+                    None => format!("[File: {filename}]"),
+                };
+                self.send_text(target, kind, &text).await?;
+            }
+            // Typing notifications, for direct chats only; in rooms they'd
+            // be noise for everyone.
+            OutboundResponse::Status(status) if kind == "chat" => {
+                let state = match status {
+                    StatusUpdate::Thinking => "composing",
+                    StatusUpdate::StopTyping => "active",
+                    _ => return Ok(()),
+                };
+                self.send_stanza(format!(
+                    "<message to='{}' type='chat'><{state} \
+                     xmlns='http://jabber.org/protocol/chatstates'/></message>",
+                    xml::escape(target)
+                ))
+                .await?;
+            }
+            // Messages can't be edited, so streaming is buffered: the final
+            // text arrives as a Text response after StreamEnd.
+            OutboundResponse::StreamStart
+            | OutboundResponse::StreamChunk(_)
+            | OutboundResponse::StreamEnd
+            | OutboundResponse::StreamAbort
+            | OutboundResponse::Reaction(_)
+            | OutboundResponse::RemoveReaction(_)
+            | OutboundResponse::Status(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Targets are `muc:<room>` for a room, or a contact's bare JID.
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if let OutboundResponse::Text(text) | OutboundResponse::RichMessage { text, .. } = response
+        {
+            match target.strip_prefix("muc:") {
+                Some(room) => self.send_text(room, "groupchat", &text).await?,
+                None => {
+                    let jid = target.strip_prefix("dm:").unwrap_or(target);
+                    self.send_text(jid, "chat", &text).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("xmpp client not connected").into());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        *self.outbound_tx.write().await = None;
+
+        tracing::info!("xmpp adapter shut down");
+        Ok(())
+    }
+}
+
+/// Why a connection ended.
+enum Exit {
+    Shutdown,
+    Disconnected,
+    /// The inbound receiver was dropped, so there's no one to deliver to.
+    InboundClosed,
+}
+
+/// Everything a connection needs, kept across reconnects.
+struct Session {
+    config: XmppConfig,
+    permissions: Arc<ArcSwap<XmppPermissions>>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    connected: Arc<AtomicBool>,
+}
+
+impl Session {
+    fn domain(&self) -> &str {
+        self.config
+            .jid
+            .split_once('@')
+            .map_or(self.config.jid.as_str(), |(_, domain)| domain)
+    }
+
+    /// Connect, secure the stream, log in, and bind a resource. Returns the
+    /// stream and its reader, ready for stanzas.
+    async fn login(&self) -> anyhow::Result<(Box<dyn XmppStream>, xml::Reader)> {
+        let XmppConfig { server, port, .. } = &self.config;
+        let domain = self.domain();
+        let tcp = TcpStream::connect((server.as_str(), *port))
+            .await
+            .with_context(|| format!("failed to connect to {server}:{port}"))?;
+        let mut stream: Box<dyn XmppStream> = if self.config.direct_tls {
+            Box::new(crate::messaging::tls::connect(domain, tcp).await?)
+        } else {
+            Box::new(tcp)
+        };
+
+        let mut reader = xml::Reader::default();
+        let mut features = open_stream(&mut stream, &mut reader, domain).await?;
+        if !self.config.direct_tls {
+            if features.child("starttls").is_none() {
+                anyhow::bail!("server doesn't offer STARTTLS");
+            }
+            write(
+                &mut stream,
+                "<starttls xmlns='urn:ietf:params:xml:ns:xmpp-tls'/>",
+            )
+            .await?;
+            if read_stanza(&mut stream, &mut reader).await?.name != "proceed" {
+                anyhow::bail!("server refused STARTTLS");
+            }
+            stream = Box::new(crate::messaging::tls::connect(domain, stream).await?);
+            reader = xml::Reader::default();
+            features = open_stream(&mut stream, &mut reader, domain).await?;
+        }
+
+        let offers_plain = features.child("mechanisms").is_some_and(|mechanisms| {
+            mechanisms
+                .children
+                .iter()
+                .any(|mechanism| mechanism.text == "PLAIN")
+        });
+        if !offers_plain {
+            anyhow::bail!("server doesn't offer SASL PLAIN");
+        }
+        let username = self.config.jid.split('@').next().unwrap_or_default();
+        let credentials = base64::engine::general_purpose::STANDARD
+            .encode(format!("\0{username}\0{}", self.config.password));
+        write(
+            &mut stream,
+            &format!(
+                "<auth xmlns='urn:ietf:params:xml:ns:xmpp-sasl' mechanism='PLAIN'>\
+                 {credentials}</auth>"
+            ),
+        )
+        .await?;
+        let answer = read_stanza(&mut stream, &mut reader).await?;
+        if answer.name != "success" {
+            let reason = answer
+                .children
+                .first()
+                .map_or("unknown", |c| c.name.as_str());
+            anyhow::bail!("xmpp login failed: {reason}");
+        }
+
+        reader = xml::Reader::default();
+        let features = open_stream(&mut stream, &mut reader, domain).await?;
+        write(
+            &mut stream,
+            &format!(
+                "<iq type='set' id='bind'><bind xmlns='urn:ietf:params:xml:ns:xmpp-bind'>\
+                 <resource>{}</resource></bind></iq>",
+                xml::escape(&self.config.resource)
+            ),
+        )
+        .await?;
+        let bound = read_result(&mut stream, &mut reader, "bind").await?;
+        let jid = bound
+            .child("bind")
+            .and_then(|bind| bind.child("jid"))
+            .map(|jid| jid.text.clone())
+            .unwrap_or_default();
+
+        // Older servers still want a session established first.
+        if features
+            .child("session")
+            .is_some_and(|session| session.child("optional").is_none())
+        {
+            write(
+                &mut stream,
+                "<iq type='set' id='session'>\
+                 <session xmlns='urn:ietf:params:xml:ns:xmpp-session'/></iq>",
+            )
+            .await?;
+            read_result(&mut stream, &mut reader, "session").await?;
+        }
+
+        tracing::debug!(%jid, "xmpp resource bound");
+        Ok((stream, reader))
+    }
+
+    /// Run one connection until it drops or the adapter shuts down.
+    async fn run(
+        &self,
+        outbound_rx: &mut mpsc::Receiver<String>,
+        shutdown_rx: &mut mpsc::Receiver<()>,
+    ) -> anyhow::Result<Exit> {
+        let (stream, mut reader) = self.login().await?;
+        let (mut read_half, mut writer) = tokio::io::split(stream);
+
+        write(&mut writer, "<presence/>").await?;
+        for room in &self.config.rooms {
+            write(
+                &mut writer,
+                &format!(
+                    "<presence to='{}/{}'><x xmlns='http://jabber.org/protocol/muc'>\
+                     <history maxstanzas='0'/></x></presence>",
+                    xml::escape(room),
+                    xml::escape(&self.config.nick)
+                ),
+            )
+            .await?;
+        }
+        self.connected.store(true, Ordering::Relaxed);
+        tracing::info!(
+            jid = %self.config.jid,
+            rooms = ?self.config.rooms,
+            "xmpp connected"
+        );
+
+        let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+        keepalive.tick().await;
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.recv() => {
+                    write(&mut writer, "<presence type='unavailable'/></stream:stream>")
+                        .await
+                        .ok();
+                    return Ok(Exit::Shutdown);
+                }
+                event = read_event(&mut read_half, &mut reader) => {
+                    let stanza = match event? {
+                        xml::Event::Stanza(stanza) => stanza,
+                        xml::Event::Close => return Ok(Exit::Disconnected),
+                        xml::Event::Open(_) => continue,
+                    };
+                    match stanza.name.as_str() {
+                        "message" => {
+                            let Some(inbound) = inbound_from_message(&self.config, &stanza) else {
+                                continue;
+                            };
+                            if !self.permissions.load().allows(&inbound) {
+                                continue;
+                            }
+                            if self.inbound_tx.send(inbound).await.is_err() {
+                                tracing::warn!(
+                                    "failed to send inbound message from XMPP (receiver dropped)"
+                                );
+                                return Ok(Exit::InboundClosed);
+                            }
+                        }
+                        "iq" => {
+                            if let Some(reply) = iq_reply(&stanza) {
+                                write(&mut writer, &reply).await?;
+                            }
+                        }
+                        "presence" if stanza.attr("type") == Some("error") => {
+                            let from = stanza.attr("from").unwrap_or_default();
+                            let error = stanza
+                                .child("error")
+                                .and_then(|error| error.children.first())
+                                .map_or("unknown", |condition| condition.name.as_str());
+                            tracing::warn!(%from, %error, "xmpp presence error");
+                        }
+                        "stream:error" => {
+                            let reason =
+                                stanza.children.first().map_or("unknown", |c| c.name.as_str());
+                            anyhow::bail!("xmpp stream error: {reason}");
+                        }
+                        _ => {}
+                    }
+                }
+                Some(stanza) = outbound_rx.recv() => {
+                    write(&mut writer, &stanza).await?;
+                }
+                _ = keepalive.tick() => {
+                    write(&mut writer, " ").await?;
+                }
+            }
+        }
+    }
+}
+
+/// Open a stream to `domain` and read the server's features.
+async fn open_stream(
+    stream: &mut Box<dyn XmppStream>,
+    reader: &mut xml::Reader,
+    domain: &str,
+) -> anyhow::Result<xml::Element> {
+    write(
+        stream,
+        &format!(
+            "<?xml version='1.0'?><stream:stream to='{}' version='1.0' xmlns='jabber:client' \
+             xmlns:stream='http://etherx.jabber.org/streams'>",
+            xml::escape(domain)
+        ),
+    )
+    .await?;
+    loop {
+        let stanza = read_stanza(stream, reader).await?;
+        if stanza.name == "stream:features" {
+            return Ok(stanza);
+        }
+    }
+}
+
+/// Read the result of the iq with `id`.
+async fn read_result(
+    stream: &mut Box<dyn XmppStream>,
+    reader: &mut xml::Reader,
+    id: &str,
+) -> anyhow::Result<xml::Element> {
+    loop {
+        let stanza = read_stanza(stream, reader).await?;
+        if stanza.name != "iq" || stanza.attr("id") != Some(id) {
+            continue;
+        }
+        if stanza.attr("type") != Some("result") {
+            anyhow::bail!("xmpp {id} request failed");
+        }
+        return Ok(stanza);
+    }
+}
+
+/// Read the next stanza, skipping the stream header.
+async fn read_stanza(
+    stream: &mut Box<dyn XmppStream>,
+    reader: &mut xml::Reader,
+) -> anyhow::Result<xml::Element> {
+    loop {
+        match read_event(stream, reader).await? {
+            xml::Event::Stanza(stanza) if stanza.name == "stream:error" => {
+                let reason = stanza
+                    .children
+                    .first()
+                    .map_or("unknown", |c| c.name.as_str());
+                anyhow::bail!("xmpp stream error: {reason}");
+            }
+            xml::Event::Stanza(stanza) => return Ok(stanza),
+            xml::Event::Open(_) => {}
+            xml::Event::Close => anyhow::bail!("xmpp stream closed by the server"),
+        }
+    }
+}
+
+async fn read_event(
+    stream: &mut (impl AsyncRead + Unpin),
+    reader: &mut xml::Reader,
+) -> anyhow::Result<xml::Event> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        if let Some(event) = reader.next()? {
+            return Ok(event);
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(xml::Event::Close);
+        }
+        reader.feed(&buffer[..read]);
+    }
+}
+
+async fn write(writer: &mut (impl AsyncWrite + Unpin), data: &str) -> anyhow::Result<()> {
+    writer.write_all(data.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// The reply to an iq request: a pong for pings, and "not supported" for
+/// anything else, since every request must be answered.
+fn iq_reply(stanza: &xml::Element) -> Option<String> {
+    if !matches!(stanza.attr("type"), Some("get" | "set")) {
+        return None;
+    }
+    let id = xml::escape(stanza.attr("id").unwrap_or_default());
+    let to = stanza
+        .attr("from")
+        .map(|from| format!(" to='{}'", xml::escape(from)))
+        .unwrap_or_default();
+    if stanza.child("ping").is_some() {
+        return Some(format!("<iq type='result' id='{id}'{to}/>"));
+    }
+    Some(format!(
+        "<iq type='error' id='{id}'{to}><error type='cancel'>\
+         <service-unavailable xmlns='urn:ietf:params:xml:ns:xmpp-stanzas'/></error></iq>"
+    ))
+}
+
+/// Build an inbound message from a message stanza. None for messages
+/// without a body, the bot's own room messages, room history, and private
+/// messages through a room.
+fn inbound_from_message(config: &XmppConfig, stanza: &xml::Element) -> Option<InboundMessage> {
+    let text = stanza.child("body")?.text.clone();
+    if text.trim().is_empty() {
+        return None;
+    }
+    let from = stanza.attr("from")?;
+    let (bare, resource) = match from.split_once('/') {
+        Some((bare, resource)) => (bare, Some(resource)),
+        None => (from, None),
+    };
+    let in_room = config
+        .rooms
+        .iter()
+        .any(|room| room.eq_ignore_ascii_case(bare));
+
+    let mut metadata = HashMap::new();
+    let (conversation_id, sender_id, target, display_name) = match stanza.attr("type") {
+        Some("groupchat") => {
+            let nick = resource?;
+            if nick == config.nick || stanza.child("delay").is_some() {
+                return None;
+            }
+            metadata.insert("xmpp_room".into(), serde_json::json!(bare));
+            metadata.insert("xmpp_type".into(), serde_json::json!("groupchat"));
+            if super::irc::mentions(&text, &config.nick) {
+                metadata.insert("xmpp_mentions_bot".into(), serde_json::Value::Bool(true));
+            }
+            (
+                format!("xmpp:muc:{bare}"),
+                from.to_string(),
+                bare.to_string(),
+                nick.to_string(),
+            )
+        }
+        Some("chat" | "normal") | None if !in_room => {
+            let bare = bare.to_lowercase();
+            metadata.insert("xmpp_type".into(), serde_json::json!("chat"));
+            let name = bare.split('@').next().unwrap_or_default().to_string();
+            (format!("xmpp:dm:{bare}"), bare.clone(), bare, name)
+        }
+        _ => return None,
+    };
+    metadata.insert("xmpp_target".into(), serde_json::json!(target));
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::json!(display_name),
+    );
+
+    Some(InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "xmpp".into(),
+        conversation_id,
+        sender_id,
+        agent_id: None,
+        content: MessageContent::Text(text),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(display_name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stanza(text: &str) -> xml::Element {
+        let mut reader = xml::Reader::default();
+        reader.feed(text.as_bytes());
+        match reader.next().unwrap() {
+            Some(xml::Event::Stanza(stanza)) => stanza,
+            other => panic!("expected a stanza, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_rooms_and_direct_chats_map_to_conversations() {
+        let config = XmppConfig {
+            enabled: true,
+            jid: "spacebot@example.org".into(),
+            password: "secret".into(),
+            server: "example.org".into(),
+            port: 5222,
+            direct_tls: false,
+            resource: "spacebot".into(),
+            nick: "spacebot".into(),
+            rooms: vec!["ops@muc.example.org".into()],
+        };
+
+        let room = stanza(
+            "<message from='ops@muc.example.org/alice' type='groupchat'>\
+             <body>spacebot: is the deploy done?</body></message>",
+        );
+        let inbound = inbound_from_message(&config, &room).unwrap();
+        assert_eq!(inbound.conversation_id, "xmpp:muc:ops@muc.example.org");
+        assert_eq!(inbound.metadata["xmpp_target"], "ops@muc.example.org");
+        assert_eq!(inbound.metadata["xmpp_mentions_bot"], true);
+        assert_eq!(inbound.formatted_author.as_deref(), Some("alice"));
+
+        let direct = stanza(
+            "<message from='Bob@Example.org/phone' type='chat'><body>hi</body>\
+             <active xmlns='http://jabber.org/protocol/chatstates'/></message>",
+        );
+        let inbound = inbound_from_message(&config, &direct).unwrap();
+        assert_eq!(inbound.conversation_id, "xmpp:dm:bob@example.org");
+        assert_eq!(inbound.metadata["xmpp_type"], "chat");
+
+        let own = stanza(
+            "<message from='ops@muc.example.org/spacebot' type='groupchat'>\
+             <body>done</body></message>",
+        );
+        assert!(inbound_from_message(&config, &own).is_none());
+        let typing = stanza(
+            "<message from='bob@example.org/phone' type='chat'>\
+             <composing xmlns='http://jabber.org/protocol/chatstates'/></message>",
+        );
+        assert!(inbound_from_message(&config, &typing).is_none());
+
+        let ping =
+            stanza("<iq from='example.org' id='p1' type='get'><ping xmlns='urn:xmpp:ping'/></iq>");
+        assert_eq!(
+            iq_reply(&ping).as_deref(),
+            Some("<iq type='result' id='p1' to='example.org'/>")
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Just enough XML for an XMPP stream: its header and the stanzas inside it.
+//!
+//! XMPP forbids DTDs, comments, and processing instructions inside a stream,
+//! so this reads elements, attributes, text, CDATA, and the predefined and
+//! numeric entities, and nothing more.
+
+use anyhow::Context as _;
+
+/// An element with its attributes, child elements, and text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// Text directly inside the element.
+    pub text: String,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// What was read from the stream.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Event {
+    /// The stream header, with its attributes.
+    Open(Element),
+    Stanza(Element),
+    /// The stream was closed.
+    Close,
+}
+
+/// Collects bytes read from the stream and takes events from them once
+/// they're complete.
+#[derive(Default)]
+pub(super) struct Reader {
+    buffer: Vec<u8>,
+}
+
+impl Reader {
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete event, or None until more bytes arrive.
+    pub fn next(&mut self) -> anyhow::Result<Option<Event>> {
+        loop {
+            let text = match std::str::from_utf8(&self.buffer) {
+                Ok(text) => text,
+                // A character split across reads.
+                Err(error) if error.error_len().is_none() => {
+                    std::str::from_utf8(&self.buffer[..error.valid_up_to()])?
+                }
+                Err(_) => anyhow::bail!("invalid UTF-8 in XMPP stream"),
+            };
+            let Some((event, consumed)) = take(text)? else {
+                return Ok(None);
+            };
+            self.buffer.drain(..consumed);
+            if let Some(event) = event {
+                return Ok(Some(event));
+            }
+        }
+    }
+}
+
+/// The event at the start of `text` and the bytes it spans. The event is
+/// None for whitespace and the XML declaration, which are skipped.
+fn take(text: &str) -> anyhow::Result<Option<(Option<Event>, usize)>> {
+    let start = text.len() - text.trim_start().len();
+    let rest = &text[start..];
+    if rest.is_empty() {
+        return Ok((start > 0).then_some((None, start)));
+    }
+    if !rest.starts_with('<') {
+        anyhow::bail!("text outside of a stanza");
+    }
+
+    if rest.starts_with("<?") {
+        return Ok(rest.find("?>").map(|end| (None, start + end + 2)));
+    }
+    if rest.starts_with("</") {
+        return Ok(rest
+            .find('>')
+            .map(|end| (Some(Event::Close), start + end + 1)));
+    }
+    if rest.starts_with("<stream:stream") {
+        let Some(len) = tag_len(rest) else {
+            return Ok(None);
+        };
+        let (header, _) = Parser::new(&rest[..len]).start_tag()?;
+        return Ok(Some((Some(Event::Open(header)), start + len)));
+    }
+    let Some(len) = element_len(rest) else {
+        return Ok(None);
+    };
+    let stanza = Parser::new(&rest[..len]).element()?;
+    Ok(Some((Some(Event::Stanza(stanza)), start + len)))
+}
+
+/// Length of the tag at the start of `text`, through its `>`. None if it
+/// isn't complete yet.
+fn tag_len(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Length of the element at the start of `text`. None if it isn't complete
+/// yet.
+fn element_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut pos = 0;
+    loop {
+        pos += text[pos..].find('<')?;
+        let rest = &text[pos..];
+        if rest.starts_with("<![CDATA[") {
+            pos += rest.find("]]>")? + 3;
+            continue;
+        }
+        let len = tag_len(rest)?;
+        if rest.starts_with("</") {
+            depth = depth.saturating_sub(1);
+        } else if !rest[..len].ends_with("/>") {
+            depth += 1;
+        }
+        pos += len;
+        if depth == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+/// Parses one complete element.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    /// The element of a start tag, and whether the tag closes itself.
+    fn start_tag(&mut self) -> anyhow::Result<(Element, bool)> {
+        let rest = self.rest();
+        let len = tag_len(rest).context("unterminated tag")?;
+        self.pos += len;
+        let tag = &rest[1..len - 1];
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let mut element = Element {
+            name: tag[..name_end].to_string(),
+            ..Element::default()
+        };
+        let mut attrs = tag[name_end..].trim_start();
+        while !attrs.is_empty() {
+            let (name, value) = attrs.split_once('=').context("attribute without a value")?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+                .context("unquoted attribute value")?;
+            let end = value[1..]
+                .find(quote)
+                .context("unterminated attribute value")?;
+            element
+                .attrs
+                .push((name.trim().to_string(), unescape(&value[1..end + 1])?));
+            attrs = value[end + 2..].trim_start();
+        }
+        Ok((element, empty))
+    }
+
+    fn element(&mut self) -> anyhow::Result<Element> {
+        let (mut element, empty) = self.start_tag()?;
+        if empty {
+            return Ok(element);
+        }
+        loop {
+            let rest = self.rest();
+            if let Some(closing) = rest.strip_prefix("</") {
+                let end = closing.find('>').context("unterminated closing tag")?;
+                if closing[..end].trim() != element.name {
+                    anyhow::bail!("mismatched closing tag for <{}>", element.name);
+                }
+                self.pos += end + 3;
+                return Ok(element);
+            }
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").context("unterminated CDATA")?;
+                element.text.push_str(&cdata[..end]);
+                self.pos += "<![CDATA[".len() + end + 3;
+            } else if rest.starts_with('<') {
+                let child = self.element()?;
+                element.children.push(child);
+            } else {
+                let end = rest.find('<').context("unterminated element")?;
+                element.text.push_str(&unescape(&rest[..end])?);
+                self.pos += end;
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> anyhow::Result<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let len = rest[start..].find(';').context("unterminated entity")?;
+        let entity = &rest[start + 1..start + len];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                };
+                code.and_then(char::from_u32)
+                    .with_context(|| format!("unknown entity &{entity};"))?
+            }
+        };
+        unescaped.push(c);
+        rest = &rest[start + len + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// `text` escaped for an attribute value or element text, without the
+/// control characters XML can't carry.
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stanzas_are_read_across_partial_reads() {
+        let stream = "<?xml version='1.0'?><stream:stream from='example.org' \
+            xmlns='jabber:client' version='1.0'> <message from='room@muc.example.org/alice' \
+            type='groupchat'><body>a &lt;b&gt; &amp; caf&#xe9; <![CDATA[<raw>]]></body>\
+            <delay xmlns='urn:xmpp:delay' stamp='2026-01-01T00:00:00Z'/></message></stream:stream>";
+
+        let mut reader = Reader::default();
+        let mut events = Vec::new();
+        // Feed a few bytes at a time, splitting tags and the multibyte é.
+        for chunk in stream.as_bytes().chunks(7) {
+            reader.feed(chunk);
+            while let Some(event) = reader.next().unwrap() {
+                events.push(event);
+            }
+        }
+
+        assert_eq!(events.len(), 3);
+        let Event::Open(header) = &events[0] else {
+            panic!("expected the stream header");
+        };
+        assert_eq!(header.attr("from"), Some("example.org"));
+        let Event::Stanza(message) = &events[1] else {
+            panic!("expected a stanza");
+        };
+        assert_eq!(message.attr("type"), Some("groupchat"));
+        assert_eq!(message.child("body").unwrap().text, "a <b> & café <raw>");
+        assert!(message.child("delay").is_some());
+        assert_eq!(events[2], Event::Close);
+
+        assert_eq!(
+            escape("<a href=\"x\">&\u{1}"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;"
+        );
+        assert!(Parser::new("<a><b></a>").element().is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! OpenTelemetry metrics, exported over OTLP next to the traces.
+//!
+//! Spans need nothing here: `tracing` spans reach the collector through the
+//! OTel layer the daemon installs. Metrics are recorded on instruments from
+//! the global meter provider, which the daemon also installs when
+//! `[telemetry] otlp_endpoint` is set. Without one, recording is a no-op.
+
+use crate::events::BusEvent;
+
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::{Counter, Gauge, Histogram};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::Instrument as _;
+
+use std::future::Future;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Created on first use, which comes after the daemon has installed the
+/// meter provider: instruments created before that would stay no-ops.
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Process-wide write counts, for the `db_write_failure_rate` alert.
+static DB_WRITES: AtomicU64 = AtomicU64::new(0);
+static DB_WRITE_FAILURES: AtomicU64 = AtomicU64::new(0);
+/// Writes started but not finished, for channel backpressure.
+static DB_WRITES_PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// Bucket boundaries, in seconds, for everything from DB writes to LLM calls
+/// with retries.
+const DURATION_BOUNDARIES: [f64; 14] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+/// All OTel metric instruments. Access via `Metrics::global()`.
+pub struct Metrics {
+    /// Inbound messages. Attributes: adapter.
+    pub messages_received: Counter<u64>,
+    /// Rendering the channel prompt and fitting history into the context
+    /// window. Attributes: agent_id.
+    pub context_assembly_duration: Histogram<f64>,
+    /// LLM completion calls, including retries and fallbacks. Attributes:
+    /// model, outcome (`ok` or `error`).
+    pub llm_requests: Counter<u64>,
+    /// Attributes: model, outcome.
+    pub llm_request_duration: Histogram<f64>,
+    /// Attributes: model, direction (`input` or `output`).
+    pub llm_tokens: Counter<u64>,
+    /// Attributes: agent_id, tool_name.
+    pub tool_calls: Counter<u64>,
+    /// Attributes: agent_id, tool_name.
+    pub tool_call_duration: Histogram<f64>,
+    /// Attributes: table, operation, outcome (`ok` or `error`).
+    pub db_write_duration: Histogram<f64>,
+    /// Queries over the slow query threshold. Attributes: query.
+    pub db_slow_queries: Counter<u64>,
+    /// Database writes started but not finished.
+    pub db_writes_pending: Gauge<u64>,
+    /// Messages waiting for a channel. Attributes: agent_id, channel_id.
+    pub channel_queue_depth: Gauge<u64>,
+    /// Messages dropped or folded into a batch because their channel was
+    /// behind. Attributes: agent_id, action (`dropped` or `coalesced`).
+    pub messages_shed: Counter<u64>,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let meter = opentelemetry::global::meter("spacebot");
+        let duration = |name: &'static str, description: &'static str| {
+            meter
+                .f64_histogram(name)
+                .with_description(description)
+                .with_unit("s")
+                .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                .build()
+        };
+
+        Self {
+            messages_received: meter
+                .u64_counter("spacebot.messages.received")
+                .with_description("Inbound messages")
+                .build(),
+            context_assembly_duration: duration(
+                "spacebot.context.assembly.duration",
+                "Channel context assembly duration",
+            ),
+            llm_requests: meter
+                .u64_counter("spacebot.llm.requests")
+                .with_description("LLM completion calls")
+                .build(),
+            llm_request_duration: duration(
+                "spacebot.llm.request.duration",
+                "LLM completion call duration",
+            ),
+            llm_tokens: meter
+                .u64_counter("spacebot.llm.tokens")
+                .with_description("LLM tokens used")
+                .build(),
+            tool_calls: meter
+                .u64_counter("spacebot.tool.calls")
+                .with_description("Tool calls executed")
+                .build(),
+            tool_call_duration: duration("spacebot.tool.call.duration", "Tool call duration"),
+            db_write_duration: duration("spacebot.db.write.duration", "Database write duration"),
+            db_slow_queries: meter
+                .u64_counter("spacebot.db.slow_queries")
+                .with_description("Database queries over the slow query threshold")
+                .build(),
+            db_writes_pending: meter
+                .u64_gauge("spacebot.db.writes.pending")
+                .with_description("Database writes in flight")
+                .build(),
+            channel_queue_depth: meter
+                .u64_gauge("spacebot.channel.queue.depth")
+                .with_description("Messages waiting for a channel")
+                .build(),
+            messages_shed: meter
+                .u64_counter("spacebot.messages.shed")
+                .with_description("Messages dropped or batched by channel backpressure")
+                .build(),
+        }
+    }
+
+    pub fn global() -> &'static Self {
+        &METRICS
+    }
+}
+
+/// The result of a database write, telling [`db_write`] whether it failed.
+pub trait WriteOutcome {
+    fn failed(&self) -> bool;
+}
+
+impl<T, E> WriteOutcome for Result<T, E> {
+    fn failed(&self) -> bool {
+        self.is_err()
+    }
+}
+
+/// Database writes since startup, and how many of them failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DbWriteCounts {
+    pub writes: u64,
+    pub failures: u64,
+}
+
+pub fn db_write_counts() -> DbWriteCounts {
+    DbWriteCounts {
+        writes: DB_WRITES.load(Ordering::Relaxed),
+        failures: DB_WRITE_FAILURES.load(Ordering::Relaxed),
+    }
+}
+
+/// Database writes started but not yet finished, across the process.
+pub fn db_writes_pending() -> u64 {
+    DB_WRITES_PENDING.load(Ordering::Relaxed)
+}
+
+fn record_db_writes_pending(pending: u64) {
+    Metrics::global().db_writes_pending.record(pending, &[]);
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .db_writes_pending
+        .set(pending as i64);
+}
+
+/// Run a database write in a `db.write` span, recording how long it took
+/// and whether it failed. The span is created when this is called, under the
+/// caller's span.
+pub fn db_write<F>(
+    table: &'static str,
+    operation: &'static str,
+    write: F,
+) -> impl Future<Output = F::Output>
+where
+    F: Future,
+    F::Output: WriteOutcome,
+{
+    db_write_with(table, operation, String::new, write)
+}
+
+/// Like [`db_write`], with a summary of the bound values to log if the write
+/// is slow, like `id=3f2a`.
+pub fn db_write_with<F, B>(
+    table: &'static str,
+    operation: &'static str,
+    binds: B,
+    write: F,
+) -> impl Future<Output = F::Output>
+where
+    F: Future,
+    F::Output: WriteOutcome,
+    B: FnOnce() -> String,
+{
+    let span = tracing::info_span!(
+        "db.write",
+        db.system = "sqlite",
+        db.table = table,
+        db.operation = operation
+    );
+    async move {
+        record_db_writes_pending(DB_WRITES_PENDING.fetch_add(1, Ordering::Relaxed) + 1);
+        let start = Instant::now();
+        let output = write.await;
+        let elapsed = start.elapsed();
+        record_db_writes_pending(DB_WRITES_PENDING.fetch_sub(1, Ordering::Relaxed) - 1);
+        crate::db::record_if_slow(
+            format_args!("{table}.{operation}"),
+            binds,
+            elapsed,
+            crate::db::slow_query_threshold(),
+        );
+        let failed = output.failed();
+        DB_WRITES.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            DB_WRITE_FAILURES.fetch_add(1, Ordering::Relaxed);
+        }
+        Metrics::global().db_write_duration.record(
+            elapsed.as_secs_f64(),
+            &[
+                KeyValue::new("table", table),
+                KeyValue::new("operation", operation),
+                KeyValue::new("outcome", if failed { "error" } else { "ok" }),
+            ],
+        );
+        output
+    }
+    .instrument(span)
+}
+
+/// Record tool call metrics from the event bus until the process exits. Start
+/// it before any agent runs, so no calls are missed.
+pub fn spawn_event_metrics() {
+    let mut events = crate::events::subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(BusEvent::ToolExecuted {
+                    agent_id,
+                    tool_name,
+                    duration_ms,
+                    ..
+                }) => record_tool_call(&agent_id, &tool_name, duration_ms),
+                Ok(_) => {}
+                Err(RecvError::Lagged(count)) => {
+                    tracing::warn!(count, "metrics fell behind the event bus, skipped events");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn record_tool_call(agent_id: &str, tool_name: &str, duration_ms: Option<u64>) {
+    let elapsed = duration_ms.map(|duration_ms| duration_ms as f64 / 1000.0);
+    let attributes = [
+        KeyValue::new("agent_id", agent_id.to_string()),
+        KeyValue::new("tool_name", tool_name.to_string()),
+    ];
+    let otel = Metrics::global();
+    otel.tool_calls.add(1, &attributes);
+    if let Some(elapsed) = elapsed {
+        otel.tool_call_duration.record(elapsed, &attributes);
+    }
+
+    #[cfg(feature = "metrics")]
+    {
+        let metrics = crate::telemetry::Metrics::global();
+        metrics
+            .tool_calls_total
+            .with_label_values(&[agent_id, tool_name])
+            .inc();
+        if let Some(elapsed) = elapsed {
+            metrics.tool_call_duration_seconds.observe(elapsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_db_writes_return_the_write_output_and_count_failures() {
+        let before = db_write_counts();
+        let result = db_write("memories", "insert", async { Ok::<_, String>(1) }).await;
+        assert_eq!(result, Ok(1));
+        let result = db_write("memories", "insert", async { Err::<i32, _>("locked") }).await;
+        assert_eq!(result, Err("locked"));
+
+        // Other tests write concurrently, so only lower bounds hold.
+        let after = db_write_counts();
+        assert!(after.writes >= before.writes + 2);
+        assert!(after.failures > before.failures);
+    }
+}
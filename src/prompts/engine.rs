@@ -1,3 +1,5 @@
+use crate::agent::capabilities::AgentCapabilities;
+use crate::conversation::FewShotExample;
 use crate::error::Result;
 use anyhow::Context;
 use minijinja::{Environment, Value, context};
@@ -54,6 +56,18 @@ impl PromptEngine {
             "cortex_profile",
             crate::prompts::text::get("cortex_profile"),
         )?;
+        env.add_template("reflection", crate::prompts::text::get("reflection"))?;
+        env.add_template("vision", crate::prompts::text::get("vision"))?;
+        env.add_template(
+            "ensemble_judge",
+            crate::prompts::text::get("ensemble_judge"),
+        )?;
+        env.add_template("moderation", crate::prompts::text::get("moderation"))?;
+        env.add_template("digest", crate::prompts::text::get("digest"))?;
+        env.add_template(
+            "injection_classifier",
+            crate::prompts::text::get("injection_classifier"),
+        )?;
 
         // Fragment templates
         env.add_template(
@@ -76,6 +90,14 @@ impl PromptEngine {
             "fragments/available_channels",
             crate::prompts::text::get("fragments/available_channels"),
         )?;
+        env.add_template(
+            "fragments/peer_agents",
+            crate::prompts::text::get("fragments/peer_agents"),
+        )?;
+        env.add_template(
+            "fragments/few_shot_examples",
+            crate::prompts::text::get("fragments/few_shot_examples"),
+        )?;
 
         // System message fragments
         env.add_template(
@@ -118,6 +140,30 @@ impl PromptEngine {
             "fragments/system/tool_syntax_correction",
             crate::prompts::text::get("fragments/system/tool_syntax_correction"),
         )?;
+        env.add_template(
+            "fragments/system/reflection_draft",
+            crate::prompts::text::get("fragments/system/reflection_draft"),
+        )?;
+        env.add_template(
+            "fragments/system/digest_conversation",
+            crate::prompts::text::get("fragments/system/digest_conversation"),
+        )?;
+        env.add_template(
+            "fragments/system/ensemble_candidates",
+            crate::prompts::text::get("fragments/system/ensemble_candidates"),
+        )?;
+        env.add_template(
+            "fragments/system/structured_output",
+            crate::prompts::text::get("fragments/system/structured_output"),
+        )?;
+        env.add_template(
+            "fragments/system/structured_output_correction",
+            crate::prompts::text::get("fragments/system/structured_output_correction"),
+        )?;
+        env.add_template(
+            "fragments/system/untrusted_message",
+            crate::prompts::text::get("fragments/system/untrusted_message"),
+        )?;
         env.add_template(
             "fragments/coalesce_hint",
             crate::prompts::text::get("fragments/coalesce_hint"),
@@ -189,6 +235,7 @@ impl PromptEngine {
         platform: &str,
         server_name: Option<&str>,
         channel_name: Option<&str>,
+        custom_emojis: &[String],
     ) -> Result<String> {
         self.render(
             "fragments/conversation_context",
@@ -196,6 +243,7 @@ impl PromptEngine {
                 platform => platform,
                 server_name => server_name,
                 channel_name => channel_name,
+                custom_emojis => custom_emojis,
             },
         )
     }
@@ -242,6 +290,26 @@ impl PromptEngine {
         )
     }
 
+    /// Render the peer agents fragment so the channel knows who else handles what.
+    pub fn render_peer_agents(&self, peers: &[AgentCapabilities]) -> Result<String> {
+        self.render(
+            "fragments/peer_agents",
+            context! {
+                peers => peers,
+            },
+        )
+    }
+
+    /// Render the curated few-shot examples for the channel prompt.
+    pub fn render_few_shot_examples(&self, examples: &[FewShotExample]) -> Result<String> {
+        self.render(
+            "fragments/few_shot_examples",
+            context! {
+                examples => examples,
+            },
+        )
+    }
+
     /// Convenience method for rendering skills worker fragment.
     pub fn render_skills_worker(&self, skill_name: &str, skill_content: &str) -> Result<String> {
         self.render(
@@ -309,6 +377,91 @@ impl PromptEngine {
         )
     }
 
+    /// Convenience method for rendering the reply reflection review prompt.
+    pub fn render_system_reflection_draft(
+        &self,
+        identity_context: Option<&str>,
+        conversation_context: Option<&str>,
+        draft: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/reflection_draft",
+            context! {
+                identity_context => identity_context,
+                conversation_context => conversation_context,
+                draft => draft,
+            },
+        )
+    }
+
+    /// Render one conversation's day for the daily digest.
+    pub fn render_system_digest_conversation(
+        &self,
+        name: &str,
+        summaries: &[String],
+        transcript: &str,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/digest_conversation",
+            context! {
+                name => name,
+                summaries => summaries,
+                transcript => transcript,
+            },
+        )
+    }
+
+    /// Render the candidates an ensemble judge chooses between.
+    pub fn render_system_ensemble_candidates(
+        &self,
+        question: Option<&str>,
+        candidates: &[&str],
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/ensemble_candidates",
+            context! {
+                question => question,
+                candidates => candidates,
+            },
+        )
+    }
+
+    /// Wrap a prompt with instructions to answer as JSON matching `schema`.
+    pub fn render_system_structured_output(&self, prompt: &str, schema: &str) -> Result<String> {
+        self.render(
+            "fragments/system/structured_output",
+            context! {
+                prompt => prompt,
+                schema => schema,
+            },
+        )
+    }
+
+    /// Correction message listing why the previous structured response was rejected.
+    pub fn render_system_structured_output_correction(&self, errors: &[String]) -> Result<String> {
+        self.render(
+            "fragments/system/structured_output_correction",
+            context! {
+                errors => errors,
+            },
+        )
+    }
+
+    /// Wrap an inbound message flagged as a possible prompt injection.
+    pub fn render_system_untrusted_message(
+        &self,
+        message: &str,
+        reasons: &[String],
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/untrusted_message",
+            context! {
+                message => message,
+                reasons => reasons,
+            },
+        )
+    }
+
     /// Convenience method for rendering cortex synthesis prompt.
     pub fn render_system_cortex_synthesis(
         &self,
@@ -381,6 +534,8 @@ impl PromptEngine {
         status_text: Option<String>,
         coalesce_hint: Option<String>,
         available_channels: Option<String>,
+        peer_agents: Option<String>,
+        few_shot_examples: Option<String>,
     ) -> Result<String> {
         self.render(
             "channel",
@@ -393,6 +548,8 @@ impl PromptEngine {
                 status_text => status_text,
                 coalesce_hint => coalesce_hint,
                 available_channels => available_channels,
+                peer_agents => peer_agents,
+                few_shot_examples => few_shot_examples,
             },
         )
     }
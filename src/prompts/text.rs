@@ -64,6 +64,14 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "memory_persistence") => include_str!("../../prompts/en/memory_persistence.md.j2"),
         ("en", "ingestion") => include_str!("../../prompts/en/ingestion.md.j2"),
         ("en", "cortex_chat") => include_str!("../../prompts/en/cortex_chat.md.j2"),
+        ("en", "reflection") => include_str!("../../prompts/en/reflection.md.j2"),
+        ("en", "vision") => include_str!("../../prompts/en/vision.md.j2"),
+        ("en", "ensemble_judge") => include_str!("../../prompts/en/ensemble_judge.md.j2"),
+        ("en", "moderation") => include_str!("../../prompts/en/moderation.md.j2"),
+        ("en", "digest") => include_str!("../../prompts/en/digest.md.j2"),
+        ("en", "injection_classifier") => {
+            include_str!("../../prompts/en/injection_classifier.md.j2")
+        }
 
         // Fragment Templates
         ("en", "fragments/worker_capabilities") => {
@@ -81,6 +89,12 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/available_channels") => {
             include_str!("../../prompts/en/fragments/available_channels.md.j2")
         }
+        ("en", "fragments/peer_agents") => {
+            include_str!("../../prompts/en/fragments/peer_agents.md.j2")
+        }
+        ("en", "fragments/few_shot_examples") => {
+            include_str!("../../prompts/en/fragments/few_shot_examples.md.j2")
+        }
 
         // System Message Fragments
         ("en", "fragments/system/retrigger") => {
@@ -113,6 +127,24 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/system/tool_syntax_correction") => {
             include_str!("../../prompts/en/fragments/system/tool_syntax_correction.md.j2")
         }
+        ("en", "fragments/system/reflection_draft") => {
+            include_str!("../../prompts/en/fragments/system/reflection_draft.md.j2")
+        }
+        ("en", "fragments/system/digest_conversation") => {
+            include_str!("../../prompts/en/fragments/system/digest_conversation.md.j2")
+        }
+        ("en", "fragments/system/ensemble_candidates") => {
+            include_str!("../../prompts/en/fragments/system/ensemble_candidates.md.j2")
+        }
+        ("en", "fragments/system/structured_output") => {
+            include_str!("../../prompts/en/fragments/system/structured_output.md.j2")
+        }
+        ("en", "fragments/system/structured_output_correction") => {
+            include_str!("../../prompts/en/fragments/system/structured_output_correction.md.j2")
+        }
+        ("en", "fragments/system/untrusted_message") => {
+            include_str!("../../prompts/en/fragments/system/untrusted_message.md.j2")
+        }
 
         // Coalesce Hint
         ("en", "fragments/coalesce_hint") => {
@@ -129,6 +161,12 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "tools/cancel") => include_str!("../../prompts/en/tools/cancel_description.md.j2"),
         ("en", "tools/skip") => include_str!("../../prompts/en/tools/skip_description.md.j2"),
         ("en", "tools/react") => include_str!("../../prompts/en/tools/react_description.md.j2"),
+        ("en", "tools/moderate") => {
+            include_str!("../../prompts/en/tools/moderate_description.md.j2")
+        }
+        ("en", "tools/tag_post") => {
+            include_str!("../../prompts/en/tools/tag_post_description.md.j2")
+        }
         ("en", "tools/set_status") => {
             include_str!("../../prompts/en/tools/set_status_description.md.j2")
         }
@@ -158,6 +196,9 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "tools/send_message_to_another_channel") => {
             include_str!("../../prompts/en/tools/send_message_description.md.j2")
         }
+        ("en", "tools/ask_agent") => {
+            include_str!("../../prompts/en/tools/ask_agent_description.md.j2")
+        }
 
         // Fallback: unknown language or key -> try English
         (lang, key) if lang != "en" => {
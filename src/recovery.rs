@@ -0,0 +1,322 @@
+//! Crash recovery: what the previous run left unfinished.
+//!
+//! While it runs, the process keeps a `spacebot.running` marker in the
+//! instance directory and removes it on a graceful shutdown, so a marker
+//! found on boot means the previous run crashed or was killed. Either way,
+//! every agent's database is checked for work that never finished: worker
+//! and branch runs still open, files still being ingested, and user messages
+//! that never got a reply. Open runs are closed as interrupted, so they stop
+//! showing as running. The report is logged, kept for the API, and sent to
+//! `[alerts] recovery_notify` if the shutdown was unclean.
+
+use crate::db::TimedQuery as _;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row as _, SqlitePool};
+
+use std::path::Path;
+
+const MARKER_FILE: &str = "spacebot.running";
+
+/// How far back unanswered messages are looked for.
+const UNANSWERED_WINDOW: &str = "-1 day";
+
+/// The marker a running process leaves in the instance directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMarker {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Leave this process's marker, returning the previous run's if it was
+/// never removed.
+pub fn mark_running(instance_dir: &Path) -> Option<RunMarker> {
+    let path = instance_dir.join(MARKER_FILE);
+    let previous = std::fs::read_to_string(&path).ok().map(|content| {
+        // A marker that doesn't parse still means the run didn't stop cleanly.
+        serde_json::from_str(&content).unwrap_or(RunMarker {
+            pid: 0,
+            started_at: DateTime::UNIX_EPOCH,
+        })
+    });
+
+    let marker = RunMarker {
+        pid: std::process::id(),
+        started_at: Utc::now(),
+    };
+    if let Err(error) = std::fs::write(&path, serde_json::to_string(&marker).unwrap_or_default()) {
+        tracing::warn!(%error, path = %path.display(), "failed to write run marker");
+    }
+    previous
+}
+
+/// Remove this process's marker. Call last on a graceful shutdown.
+pub fn mark_stopped(instance_dir: &Path) {
+    let _ = std::fs::remove_file(instance_dir.join(MARKER_FILE));
+}
+
+/// What the previous run left unfinished.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    pub checked_at: DateTime<Utc>,
+    /// False if the previous run never removed its marker.
+    pub clean_shutdown: bool,
+    /// The run that didn't shut down cleanly.
+    pub previous_run: Option<RunMarker>,
+    pub agents: Vec<AgentRecovery>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AgentRecovery {
+    pub agent_id: String,
+    /// Worker runs that never completed. Now closed as interrupted.
+    pub interrupted_workers: Vec<InterruptedRun>,
+    /// Branch runs that never concluded. Now closed as interrupted.
+    pub interrupted_branches: Vec<InterruptedRun>,
+    /// Files whose ingestion didn't finish. The ingestion loop picks them up
+    /// again where it left off.
+    pub unfinished_ingestions: Vec<String>,
+    /// Channels whose latest messages, from the last day, are from users.
+    /// Includes messages the agent chose not to answer, like in a listening
+    /// mode.
+    pub unanswered_channels: Vec<UnansweredChannel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InterruptedRun {
+    pub id: String,
+    pub channel_id: Option<String>,
+    /// The worker's task or the branch's description.
+    pub description: String,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnansweredChannel {
+    pub channel_id: String,
+    pub messages: i64,
+    /// When the oldest unanswered message arrived.
+    pub since: DateTime<Utc>,
+}
+
+impl AgentRecovery {
+    pub fn is_empty(&self) -> bool {
+        self.interrupted_workers.is_empty()
+            && self.interrupted_branches.is_empty()
+            && self.unfinished_ingestions.is_empty()
+            && self.unanswered_channels.is_empty()
+    }
+}
+
+impl RecoveryReport {
+    pub fn new(previous_run: Option<RunMarker>, agents: Vec<AgentRecovery>) -> Self {
+        Self {
+            checked_at: Utc::now(),
+            clean_shutdown: previous_run.is_none(),
+            previous_run,
+            agents: agents
+                .into_iter()
+                .filter(|agent| !agent.is_empty())
+                .collect(),
+        }
+    }
+
+    /// One line per finding, for logs and notifications.
+    pub fn summary(&self) -> String {
+        let mut lines = vec![if self.clean_shutdown {
+            "The previous run shut down cleanly.".to_string()
+        } else {
+            match &self.previous_run {
+                Some(run) if run.pid != 0 => format!(
+                    "The previous run (pid {}, started {}) didn't shut down cleanly.",
+                    run.pid,
+                    run.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+                ),
+                _ => "The previous run didn't shut down cleanly.".to_string(),
+            }
+        }];
+        for agent in &self.agents {
+            let mut findings = Vec::new();
+            let mut note = |count: usize, what: &str| {
+                if count > 0 {
+                    findings.push(format!("{count} {what}"));
+                }
+            };
+            note(agent.interrupted_workers.len(), "interrupted workers");
+            note(agent.interrupted_branches.len(), "interrupted branches");
+            note(agent.unfinished_ingestions.len(), "unfinished ingestions");
+            note(
+                agent.unanswered_channels.len(),
+                "channels with unanswered messages",
+            );
+            lines.push(format!("{}: {}", agent.agent_id, findings.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Check one agent's database for work the previous run left unfinished,
+/// and close its open runs. Runs started at or after `booted_at` belong to
+/// this process and are left alone.
+pub async fn inspect_agent(
+    agent_id: &str,
+    pool: &SqlitePool,
+    booted_at: DateTime<Utc>,
+) -> crate::error::Result<AgentRecovery> {
+    let booted_at = booted_at.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let interrupted_workers = sqlx::query(
+        "SELECT id, channel_id, task AS description, started_at FROM worker_runs \
+         WHERE completed_at IS NULL AND started_at < ? \
+         ORDER BY started_at",
+    )
+    .bind(&booted_at)
+    .fetch_all(pool)
+    .timed("recovery.workers")
+    .await?
+    .iter()
+    .map(interrupted_run)
+    .collect();
+    sqlx::query(
+        "UPDATE worker_runs SET status = 'interrupted', completed_at = CURRENT_TIMESTAMP \
+         WHERE completed_at IS NULL AND started_at < ?",
+    )
+    .bind(&booted_at)
+    .execute(pool)
+    .timed("recovery.close_workers")
+    .await?;
+
+    let interrupted_branches = sqlx::query(
+        "SELECT id, channel_id, description, started_at FROM branch_runs \
+         WHERE completed_at IS NULL AND started_at < ? \
+         ORDER BY started_at",
+    )
+    .bind(&booted_at)
+    .fetch_all(pool)
+    .timed("recovery.branches")
+    .await?
+    .iter()
+    .map(interrupted_run)
+    .collect();
+    sqlx::query(
+        "UPDATE branch_runs \
+         SET conclusion = COALESCE(conclusion, 'Interrupted by shutdown'), \
+         completed_at = CURRENT_TIMESTAMP \
+         WHERE completed_at IS NULL AND started_at < ?",
+    )
+    .bind(&booted_at)
+    .execute(pool)
+    .timed("recovery.close_branches")
+    .await?;
+
+    let unfinished_ingestions = sqlx::query_scalar(
+        "SELECT filename FROM ingestion_files WHERE status = 'processing' ORDER BY started_at",
+    )
+    .fetch_all(pool)
+    .timed("recovery.ingestions")
+    .await?;
+
+    let unanswered_channels = sqlx::query(
+        "SELECT m.channel_id, COUNT(*) AS messages, MIN(m.created_at) AS since \
+         FROM conversation_messages m \
+         WHERE m.role = 'user' \
+         AND m.created_at >= datetime('now', ?) AND m.created_at < ? \
+         AND m.created_at > COALESCE(( \
+             SELECT MAX(a.created_at) FROM conversation_messages a \
+             WHERE a.channel_id = m.channel_id AND a.role = 'assistant' \
+         ), '') \
+         GROUP BY m.channel_id \
+         ORDER BY since",
+    )
+    .bind(UNANSWERED_WINDOW)
+    .bind(&booted_at)
+    .fetch_all(pool)
+    .timed("recovery.unanswered")
+    .await?
+    .iter()
+    .map(|row| UnansweredChannel {
+        channel_id: row.get("channel_id"),
+        messages: row.get("messages"),
+        since: row.try_get("since").unwrap_or_default(),
+    })
+    .collect();
+
+    Ok(AgentRecovery {
+        agent_id: agent_id.to_string(),
+        interrupted_workers,
+        interrupted_branches,
+        unfinished_ingestions,
+        unanswered_channels,
+    })
+}
+
+fn interrupted_run(row: &sqlx::sqlite::SqliteRow) -> InterruptedRun {
+    InterruptedRun {
+        id: row.get("id"),
+        channel_id: row.get("channel_id"),
+        description: row.get("description"),
+        started_at: row.try_get("started_at").unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_open_runs_and_unanswered_messages_are_reported_and_runs_closed() {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = sqlx::pool::PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .expect("in-memory SQLite");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("migrations");
+
+        for statement in [
+            "INSERT INTO channels (id, platform) VALUES ('discord:1:2', 'discord')",
+            "INSERT INTO worker_runs (id, channel_id, task, started_at) \
+             VALUES ('w1', 'discord:1:2', 'summarize the thread', datetime('now', '-10 minutes'))",
+            "INSERT INTO worker_runs (id, channel_id, task, status, completed_at) \
+             VALUES ('w2', 'discord:1:2', 'done already', 'done', CURRENT_TIMESTAMP)",
+            "INSERT INTO branch_runs (id, channel_id, description, started_at) \
+             VALUES ('b1', 'discord:1:2', 'look it up', datetime('now', '-10 minutes'))",
+            "INSERT INTO conversation_messages (id, channel_id, role, content, created_at) \
+             VALUES ('m1', 'discord:1:2', 'assistant', 'hi', datetime('now', '-30 minutes'))",
+            "INSERT INTO conversation_messages (id, channel_id, role, content, created_at) \
+             VALUES ('m2', 'discord:1:2', 'user', 'are you there?', datetime('now', '-5 minutes'))",
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+
+        let booted_at = Utc::now() + chrono::Duration::seconds(1);
+        let recovery = inspect_agent("main", &pool, booted_at).await.unwrap();
+        assert_eq!(recovery.interrupted_workers.len(), 1);
+        assert_eq!(recovery.interrupted_workers[0].id, "w1");
+        assert_eq!(recovery.interrupted_branches.len(), 1);
+        assert_eq!(recovery.unanswered_channels.len(), 1);
+        assert_eq!(recovery.unanswered_channels[0].messages, 1);
+
+        // Closed runs aren't reported again.
+        let again = inspect_agent("main", &pool, booted_at).await.unwrap();
+        assert!(again.interrupted_workers.is_empty());
+        assert!(again.interrupted_branches.is_empty());
+
+        let report = RecoveryReport::new(
+            Some(RunMarker {
+                pid: 42,
+                started_at: Utc::now(),
+            }),
+            vec![recovery, AgentRecovery::default()],
+        );
+        assert!(!report.clean_shutdown);
+        assert_eq!(report.agents.len(), 1);
+        assert!(report.summary().contains("main: 1 interrupted workers"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Terminal chat with an agent of the running daemon (`spacebot chat`).
+//!
+//! Messages go through the daemon's webchat API, so they take the same path
+//! as any other conversation: routing, the channel, tools, and history. Each
+//! session is its own conversation, `cli:<session>`, and picking a session
+//! again picks up where it left off.
+
+use crate::config::Config;
+use crate::messaging::webchat::WebChatEvent;
+
+use anyhow::Context as _;
+use futures::StreamExt as _;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// The conversation ID of a CLI chat session.
+pub fn conversation_id(session: &str) -> String {
+    format!("cli:{session}")
+}
+
+/// Chat with `agent_id` in `session` until the user quits or stdin closes.
+pub async fn run(config: &Config, agent_id: &str, session: &str) -> anyhow::Result<()> {
+    // A wildcard bind isn't an address to connect to.
+    let host = match config.api.bind.as_str() {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        bind => bind,
+    };
+    let api_url = format!("http://{host}:{}/api", config.api.port);
+    let conversation_id = conversation_id(session);
+    let sender_name = std::env::var("USER").unwrap_or_else(|_| "user".into());
+    let http = reqwest::Client::new();
+
+    eprintln!("Chatting with {agent_id} in {conversation_id}. /quit to leave.");
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    loop {
+        stdout.write_all(b"\n> ").await?;
+        stdout.flush().await?;
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        let message = line.trim();
+        if message.is_empty() {
+            continue;
+        }
+        if matches!(message, "/quit" | "/exit") {
+            break;
+        }
+
+        let response = http
+            .post(format!("{api_url}/webchat/send"))
+            .json(&serde_json::json!({
+                "agent_id": agent_id,
+                "session_id": conversation_id,
+                "sender_name": sender_name,
+                "message": message,
+                "stream": true,
+            }))
+            .send()
+            .await
+            .with_context(|| format!("failed to reach the spacebot API at {api_url}"))?
+            .error_for_status()
+            .context("the spacebot API rejected the message")?;
+
+        print_reply(response, &mut stdout).await?;
+    }
+
+    Ok(())
+}
+
+/// Print the reply's events as they arrive, until the turn is done.
+async fn print_reply(
+    response: reqwest::Response,
+    stdout: &mut tokio::io::Stdout,
+) -> anyhow::Result<()> {
+    let mut body = response.bytes_stream();
+    let mut buffer = String::new();
+    let mut replied = false;
+
+    'events: while let Some(chunk) = body.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk?));
+        while let Some(end) = buffer.find("\n\n") {
+            let block: String = buffer.drain(..end + 2).collect();
+            let Some(event) = parse_event(&block) else {
+                continue;
+            };
+            match event {
+                WebChatEvent::StreamChunk(text) => {
+                    stdout.write_all(text.as_bytes()).await?;
+                    stdout.flush().await?;
+                    replied = true;
+                }
+                // The full text follows a stream too; only print it if
+                // nothing was streamed.
+                WebChatEvent::Text(text) if !replied => {
+                    stdout.write_all(text.as_bytes()).await?;
+                    replied = true;
+                }
+                WebChatEvent::ToolStarted { tool_name } => eprintln!("[{tool_name}]"),
+                WebChatEvent::StreamAbort => eprintln!("\n[reply cancelled]"),
+                // The agent chose not to reply.
+                WebChatEvent::StopTyping if !replied => {
+                    eprintln!("[no reply]");
+                    return Ok(());
+                }
+                WebChatEvent::Done => break 'events,
+                _ => {}
+            }
+        }
+    }
+    stdout.write_all(b"\n").await?;
+    stdout.flush().await?;
+    Ok(())
+}
+
+/// The event of a server-sent event block, from its JSON `data` lines.
+fn parse_event(block: &str) -> Option<WebChatEvent> {
+    let data: Vec<&str> = block
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    serde_json::from_str(&data.join("\n")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_webchat_events() {
+        let chunk = parse_event("event: stream_chunk\ndata: {\"StreamChunk\":\"Hel\"}\n\n");
+        assert!(matches!(chunk, Some(WebChatEvent::StreamChunk(text)) if text == "Hel"));
+        assert!(matches!(
+            parse_event("event: done\ndata: \"Done\"\n\n"),
+            Some(WebChatEvent::Done)
+        ));
+        assert!(parse_event(": keep-alive\n\n").is_none());
+        assert_eq!(conversation_id("persona-dev"), "cli:persona-dev");
+    }
+}
@@ -1,3 +1,6 @@
-//! Encrypted secrets storage.
+//! Encrypted secrets storage, and secret references in config values.
 
+pub mod encrypted;
+pub mod provider;
+pub mod scan;
 pub mod store;
@@ -0,0 +1,93 @@
+//! Config values encrypted under a key provided at runtime.
+//!
+//! `spacebot secret encrypt` turns a token into an `enc:` value, AES-256-GCM
+//! under a key that only the running instance is given, so a config holding
+//! tokens can be committed. The key is read as base64 from
+//! `SPACEBOT_CONFIG_KEY`, or from the file `SPACEBOT_CONFIG_KEY_FILE` names.
+
+use crate::error::SecretsError;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+
+const NONCE_LEN: usize = 12;
+
+/// A new random key, base64-encoded.
+pub fn generate_key() -> String {
+    STANDARD.encode(rand::random::<[u8; 32]>())
+}
+
+/// The cipher for the key in the environment.
+pub fn load_key() -> Result<Aes256Gcm, SecretsError> {
+    let encoded = if let Ok(key) = std::env::var("SPACEBOT_CONFIG_KEY") {
+        key
+    } else if let Ok(path) = std::env::var("SPACEBOT_CONFIG_KEY_FILE") {
+        std::fs::read_to_string(&path).map_err(|error| {
+            SecretsError::DecryptionFailed(format!("can't read key file {path}: {error}"))
+        })?
+    } else {
+        return Err(SecretsError::DecryptionFailed(
+            "no key, set SPACEBOT_CONFIG_KEY or SPACEBOT_CONFIG_KEY_FILE".into(),
+        ));
+    };
+    cipher(&encoded)
+}
+
+/// The cipher for a base64-encoded key.
+pub fn cipher(encoded_key: &str) -> Result<Aes256Gcm, SecretsError> {
+    let key = STANDARD
+        .decode(encoded_key.trim())
+        .map_err(|_| SecretsError::InvalidKey)?;
+    Aes256Gcm::new_from_slice(&key).map_err(|_| SecretsError::InvalidKey)
+}
+
+/// Encrypt `plaintext` into an `enc:` value: the nonce and ciphertext,
+/// base64-encoded.
+pub fn encrypt(cipher: &Aes256Gcm, plaintext: &str) -> Result<String, SecretsError> {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|error| SecretsError::EncryptionFailed(error.to_string()))?;
+    let mut payload = nonce.to_vec();
+    payload.extend(ciphertext);
+    Ok(format!("enc:{}", STANDARD.encode(payload)))
+}
+
+/// Decrypt the part of an `enc:` value after the prefix.
+pub fn decrypt(cipher: &Aes256Gcm, encoded: &str) -> Result<String, SecretsError> {
+    let failed = |details: &str| SecretsError::DecryptionFailed(details.into());
+    let payload = STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| failed("value isn't valid base64"))?;
+    if payload.len() < NONCE_LEN {
+        return Err(failed("value is too short"));
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| failed("wrong key, or the value was altered"))?;
+    String::from_utf8(plaintext).map_err(|_| failed("value isn't valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypted_values_only_decrypt_with_their_key() {
+        let key = cipher(&generate_key()).unwrap();
+        let value = encrypt(&key, "discord-token").unwrap();
+        let encoded = value.strip_prefix("enc:").unwrap();
+        assert_eq!(decrypt(&key, encoded).unwrap(), "discord-token");
+        assert_ne!(encrypt(&key, "discord-token").unwrap(), value);
+
+        let other_key = cipher(&generate_key()).unwrap();
+        assert!(matches!(
+            decrypt(&other_key, encoded),
+            Err(SecretsError::DecryptionFailed(_))
+        ));
+        assert!(matches!(cipher("c2hvcnQ="), Err(SecretsError::InvalidKey)));
+    }
+}
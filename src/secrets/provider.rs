@@ -0,0 +1,245 @@
+//! Secret references in config values.
+//!
+//! A value like `file:/run/secrets/discord_token` or
+//! `vault:secret/spacebot#discord_token` names where a secret lives instead of
+//! holding it. Each prefix is served by a [`SecretsProvider`]. References are
+//! resolved whenever the config loads, so a rotated secret is picked up by the
+//! next reload.
+
+use crate::error::SecretsError;
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a secrets CLI gets before it's killed, so a hung `vault` or a
+/// keyring waiting on an unlock prompt fails the reference instead of
+/// stalling the config load.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A source of secrets, addressed by a provider-specific key.
+pub trait SecretsProvider: Send + Sync {
+    /// Prefix of the references this provider serves, without the colon.
+    fn scheme(&self) -> &'static str;
+
+    /// Fetch the secret `key` points at.
+    fn fetch(&self, key: &str) -> Result<String, SecretsError>;
+}
+
+/// `env:VAR_NAME`, read from the process environment.
+pub struct EnvProvider;
+
+impl SecretsProvider for EnvProvider {
+    fn scheme(&self) -> &'static str {
+        "env"
+    }
+
+    fn fetch(&self, key: &str) -> Result<String, SecretsError> {
+        std::env::var(key).map_err(|_| SecretsError::NotFound {
+            key: format!("env:{key}"),
+        })
+    }
+}
+
+/// `file:/path/to/secret`, as mounted by Docker and Kubernetes secrets.
+/// Surrounding whitespace, like the trailing newline most files end with, is
+/// trimmed.
+pub struct FileProvider;
+
+impl SecretsProvider for FileProvider {
+    fn scheme(&self) -> &'static str {
+        "file"
+    }
+
+    fn fetch(&self, key: &str) -> Result<String, SecretsError> {
+        std::fs::read_to_string(key)
+            .map(|contents| contents.trim().to_string())
+            .map_err(|error| SecretsError::FetchFailed {
+                reference: format!("file:{key}"),
+                details: error.to_string(),
+            })
+    }
+}
+
+/// `vault:path#field`, read from a KV secret through the `vault` CLI, so
+/// `VAULT_ADDR`, `VAULT_TOKEN`, and token helpers work as they do in a shell.
+pub struct VaultProvider;
+
+impl SecretsProvider for VaultProvider {
+    fn scheme(&self) -> &'static str {
+        "vault"
+    }
+
+    fn fetch(&self, key: &str) -> Result<String, SecretsError> {
+        let reference = format!("vault:{key}");
+        let Some((path, field)) = key.split_once('#') else {
+            return Err(SecretsError::FetchFailed {
+                reference,
+                details: "expected vault:path#field".into(),
+            });
+        };
+        run(
+            &reference,
+            Command::new("vault").args(["kv", "get", &format!("-field={field}"), path]),
+            FETCH_TIMEOUT,
+        )
+    }
+}
+
+/// `keyring:service/account`, read from the OS keyring: the Keychain on
+/// macOS, or the Secret Service (GNOME Keyring, KWallet) through
+/// `secret-tool` elsewhere.
+pub struct KeyringProvider;
+
+impl SecretsProvider for KeyringProvider {
+    fn scheme(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn fetch(&self, key: &str) -> Result<String, SecretsError> {
+        let reference = format!("keyring:{key}");
+        let Some((service, account)) = key.split_once('/') else {
+            return Err(SecretsError::FetchFailed {
+                reference,
+                details: "expected keyring:service/account".into(),
+            });
+        };
+        let mut command = if cfg!(target_os = "macos") {
+            let mut command = Command::new("security");
+            command.args(["find-generic-password", "-s", service, "-a", account, "-w"]);
+            command
+        } else {
+            let mut command = Command::new("secret-tool");
+            command.args(["lookup", "service", service, "account", account]);
+            command
+        };
+        run(&reference, &mut command, FETCH_TIMEOUT)
+    }
+}
+
+/// `enc:...`, a value encrypted with `spacebot secret encrypt`, decrypted
+/// with the key given at runtime. See [`super::encrypted`].
+pub struct EncryptedProvider;
+
+impl SecretsProvider for EncryptedProvider {
+    fn scheme(&self) -> &'static str {
+        "enc"
+    }
+
+    fn fetch(&self, key: &str) -> Result<String, SecretsError> {
+        super::encrypted::decrypt(&super::encrypted::load_key()?, key)
+    }
+}
+
+/// Run a CLI that prints the secret on stdout, killing it after `timeout`.
+/// This blocks, so async callers load config through
+/// [`Config::load_from_path_async`](crate::config::Config::load_from_path_async).
+fn run(reference: &str, command: &mut Command, timeout: Duration) -> Result<String, SecretsError> {
+    let failed = |details: String| SecretsError::FetchFailed {
+        reference: reference.to_string(),
+        details,
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| failed(error.to_string()))?;
+    let started = Instant::now();
+    // A secret fits in the pipe buffers, so the child can't block on output
+    // before it exits.
+    while child
+        .try_wait()
+        .map_err(|error| failed(error.to_string()))?
+        .is_none()
+    {
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(failed(format!("timed out after {timeout:?}")));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|error| failed(error.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let secret =
+        String::from_utf8(output.stdout).map_err(|_| failed("secret isn't valid UTF-8".into()))?;
+    Ok(secret.trim().to_string())
+}
+
+static PROVIDERS: [&dyn SecretsProvider; 5] = [
+    &EnvProvider,
+    &FileProvider,
+    &VaultProvider,
+    &KeyringProvider,
+    &EncryptedProvider,
+];
+
+/// Resolve a config value that may be a secret reference. A value without a
+/// known prefix is returned as it is.
+pub fn resolve(value: &str) -> Result<String, SecretsError> {
+    let provider = value.split_once(':').and_then(|(scheme, key)| {
+        PROVIDERS
+            .iter()
+            .find(|provider| provider.scheme() == scheme)
+            .map(|provider| (provider, key))
+    });
+    match provider {
+        Some((provider, key)) => provider.fetch(key),
+        None => Ok(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_resolve_through_their_provider() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("discord_token");
+        std::fs::write(&path, "file-token\n").unwrap();
+
+        assert_eq!(
+            resolve(&format!("file:{}", path.display())).unwrap(),
+            "file-token"
+        );
+        assert_eq!(resolve("plain-token").unwrap(), "plain-token");
+        assert_eq!(
+            resolve("https://qdrant.internal:6333").unwrap(),
+            "https://qdrant.internal:6333"
+        );
+        assert!(matches!(
+            resolve("env:SPACEBOT_TEST_SECRET_THAT_IS_NEVER_SET"),
+            Err(SecretsError::NotFound { .. })
+        ));
+        assert!(matches!(
+            resolve(&format!("file:{}", dir.path().join("missing").display())),
+            Err(SecretsError::FetchFailed { .. })
+        ));
+        assert!(matches!(
+            resolve("vault:secret/spacebot"),
+            Err(SecretsError::FetchFailed { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_commands_are_killed_after_the_timeout() {
+        let timeout = Duration::from_millis(200);
+        assert_eq!(
+            run("test", Command::new("echo").arg(" cli-token "), timeout).unwrap(),
+            "cli-token"
+        );
+
+        let started = Instant::now();
+        let error = run("test", Command::new("sleep").arg("5"), timeout).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(error.to_string().contains("timed out"), "{error}");
+    }
+}
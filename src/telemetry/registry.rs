@@ -1,7 +1,8 @@
 //! Global metrics registry and metric handle definitions.
 
 use prometheus::{
-    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry,
 };
 
 use std::sync::LazyLock;
@@ -31,6 +32,15 @@ pub struct Metrics {
     /// Total memory save (write) operations.
     pub memory_writes_total: IntCounter,
 
+    /// Database queries slower than `[telemetry] slow_query_ms`.
+    /// Label: query.
+    pub db_slow_queries_total: IntCounterVec,
+
+    /// Messages dropped or folded into a batch because their channel was
+    /// behind.
+    /// Labels: agent_id, action ("dropped" or "coalesced").
+    pub messages_shed_total: IntCounterVec,
+
     // -- Histograms --
     /// LLM request duration in seconds.
     pub llm_request_duration_seconds: HistogramVec,
@@ -38,6 +48,11 @@ pub struct Metrics {
     /// Tool call duration in seconds.
     pub tool_call_duration_seconds: Histogram,
 
+    /// Duration of successful provider calls in seconds, without retries
+    /// or queueing.
+    /// Labels: provider, model.
+    pub llm_generation_duration_seconds: HistogramVec,
+
     // -- Gauges --
     /// Currently active workers per agent.
     /// Label: agent_id.
@@ -48,6 +63,13 @@ pub struct Metrics {
     // TODO: Not wired to any call site. Needs periodic store queries or
     // inc/dec in MemoryStore::save()/delete() to reflect actual counts.
     pub memory_entry_count: IntGaugeVec,
+
+    /// Messages waiting for each channel.
+    /// Labels: agent_id, channel_id.
+    pub channel_queue_depth: IntGaugeVec,
+
+    /// Database writes started but not finished.
+    pub db_writes_pending: IntGauge,
 }
 
 impl Metrics {
@@ -81,6 +103,24 @@ impl Metrics {
         )
         .expect("hardcoded metric descriptor");
 
+        let db_slow_queries_total = IntCounterVec::new(
+            Opts::new(
+                "spacebot_db_slow_queries_total",
+                "Database queries over the slow query threshold",
+            ),
+            &["query"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let messages_shed_total = IntCounterVec::new(
+            Opts::new(
+                "spacebot_messages_shed_total",
+                "Messages dropped or batched by channel backpressure",
+            ),
+            &["agent_id", "action"],
+        )
+        .expect("hardcoded metric descriptor");
+
         let llm_request_duration_seconds = HistogramVec::new(
             HistogramOpts::new(
                 "spacebot_llm_request_duration_seconds",
@@ -103,6 +143,18 @@ impl Metrics {
         )
         .expect("hardcoded metric descriptor");
 
+        let llm_generation_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "spacebot_llm_generation_duration_seconds",
+                "Successful LLM provider call duration in seconds",
+            )
+            .buckets(vec![
+                0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0,
+            ]),
+            &["provider", "model"],
+        )
+        .expect("hardcoded metric descriptor");
+
         let active_workers = IntGaugeVec::new(
             Opts::new("spacebot_active_workers", "Currently active workers"),
             &["agent_id"],
@@ -118,6 +170,19 @@ impl Metrics {
         )
         .expect("hardcoded metric descriptor");
 
+        let channel_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "spacebot_channel_queue_depth",
+                "Messages waiting for a channel",
+            ),
+            &["agent_id", "channel_id"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let db_writes_pending =
+            IntGauge::new("spacebot_db_writes_pending", "Database writes in flight")
+                .expect("hardcoded metric descriptor");
+
         registry
             .register(Box::new(llm_requests_total.clone()))
             .expect("hardcoded metric");
@@ -130,18 +195,33 @@ impl Metrics {
         registry
             .register(Box::new(memory_writes_total.clone()))
             .expect("hardcoded metric");
+        registry
+            .register(Box::new(db_slow_queries_total.clone()))
+            .expect("hardcoded metric");
         registry
             .register(Box::new(llm_request_duration_seconds.clone()))
             .expect("hardcoded metric");
         registry
             .register(Box::new(tool_call_duration_seconds.clone()))
             .expect("hardcoded metric");
+        registry
+            .register(Box::new(llm_generation_duration_seconds.clone()))
+            .expect("hardcoded metric");
         registry
             .register(Box::new(active_workers.clone()))
             .expect("hardcoded metric");
         registry
             .register(Box::new(memory_entry_count.clone()))
             .expect("hardcoded metric");
+        registry
+            .register(Box::new(messages_shed_total.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(channel_queue_depth.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(db_writes_pending.clone()))
+            .expect("hardcoded metric");
 
         Self {
             registry,
@@ -149,10 +229,15 @@ impl Metrics {
             tool_calls_total,
             memory_reads_total,
             memory_writes_total,
+            db_slow_queries_total,
+            messages_shed_total,
             llm_request_duration_seconds,
             tool_call_duration_seconds,
+            llm_generation_duration_seconds,
             active_workers,
             memory_entry_count,
+            channel_queue_depth,
+            db_writes_pending,
         }
     }
 
@@ -9,6 +9,13 @@
 //! - `reply`, `branch`, `spawn_worker`, `route`, `cancel`, `skip`, `react` — added
 //!   dynamically per conversation turn via `add_channel_tools()` /
 //!   `remove_channel_tools()` because they hold per-channel state.
+//!   `spawn_worker` is left out when the sender's roles don't allow workers.
+//! - `ask_agent` — added alongside them when messaging is running and the
+//!   agent has peers.
+//! - `moderate` — added alongside them when messaging is running and the
+//!   agent's moderation policy is enabled.
+//! - `tag_post` — added alongside them when messaging is running and the
+//!   conversation is a forum post with tagging on.
 //! - No memory tools — the channel delegates memory work to branches.
 //!
 //! **Branch ToolServer** (one per branch, isolated):
@@ -21,6 +28,7 @@
 //! **Cortex ToolServer** (one per agent):
 //! - `memory_save` — registered at startup
 
+pub mod ask_agent;
 pub mod branch_tool;
 pub mod browser;
 pub mod cancel;
@@ -31,6 +39,7 @@ pub mod file;
 pub mod memory_delete;
 pub mod memory_recall;
 pub mod memory_save;
+pub mod moderate;
 pub mod react;
 pub mod reply;
 pub mod route;
@@ -40,8 +49,10 @@ pub mod set_status;
 pub mod shell;
 pub mod skip;
 pub mod spawn_worker;
+pub mod tag_post;
 pub mod web_search;
 
+pub use ask_agent::{AskAgentArgs, AskAgentError, AskAgentOutput, AskAgentTool};
 pub use branch_tool::{BranchArgs, BranchError, BranchOutput, BranchTool};
 pub use browser::{
     ActKind, BrowserAction, BrowserArgs, BrowserError, BrowserOutput, BrowserTool, ElementSummary,
@@ -63,8 +74,11 @@ pub use memory_recall::{
 pub use memory_save::{
     AssociationInput, MemorySaveArgs, MemorySaveError, MemorySaveOutput, MemorySaveTool,
 };
+pub use moderate::{ModerateArgs, ModerateError, ModerateOutput, ModerateTool, ModerationTarget};
 pub use react::{ReactArgs, ReactError, ReactOutput, ReactTool};
-pub use reply::{ReplyArgs, ReplyError, ReplyOutput, ReplyTool};
+pub use reply::{
+    ReplyArgs, ReplyError, ReplyMessageId, ReplyOutput, ReplyTool, new_reply_message_id,
+};
 pub use route::{RouteArgs, RouteError, RouteOutput, RouteTool};
 pub use send_file::{SendFileArgs, SendFileError, SendFileOutput, SendFileTool};
 pub use send_message_to_another_channel::{
@@ -74,11 +88,14 @@ pub use set_status::{SetStatusArgs, SetStatusError, SetStatusOutput, SetStatusTo
 pub use shell::{ShellArgs, ShellError, ShellOutput, ShellResult, ShellTool};
 pub use skip::{SkipArgs, SkipError, SkipFlag, SkipOutput, SkipTool, new_skip_flag};
 pub use spawn_worker::{SpawnWorkerArgs, SpawnWorkerError, SpawnWorkerOutput, SpawnWorkerTool};
+pub use tag_post::{TagPostArgs, TagPostError, TagPostOutput, TagPostTool};
 pub use web_search::{SearchResult, WebSearchArgs, WebSearchError, WebSearchOutput, WebSearchTool};
 
 use crate::agent::channel::ChannelState;
+use crate::agent::reflection::Reflector;
 use crate::config::BrowserConfig;
 use crate::memory::MemorySearch;
+use crate::messaging::render::EmbedStyle;
 use crate::{AgentId, ChannelId, OutboundResponse, ProcessEvent, WorkerId};
 use rig::tool::Tool as _;
 use rig::tool::server::{ToolServer, ToolServerHandle};
@@ -121,27 +138,46 @@ pub fn truncate_output(value: &str, max_bytes: usize) -> String {
 /// Add per-turn tools to a channel's ToolServer.
 ///
 /// Called when a conversation turn begins. These tools hold per-turn state
-/// (response sender, skip flag) that changes between turns. Cleaned up via
-/// `remove_channel_tools()` when the turn ends.
+/// (response sender, skip flag, reply message id, turn id) that changes
+/// between turns.
+/// Cleaned up via `remove_channel_tools()` when the turn ends.
 pub async fn add_channel_tools(
     handle: &ToolServerHandle,
     state: ChannelState,
     response_tx: mpsc::Sender<OutboundResponse>,
     conversation_id: impl Into<String>,
     skip_flag: SkipFlag,
+    reply_message_id: ReplyMessageId,
+    turn_id: Option<String>,
     cron_tool: Option<CronTool>,
+    reflector: Option<Reflector>,
+    moderation_target: Option<ModerationTarget>,
+    allow_workers: bool,
+    forum_tags: Option<Vec<String>>,
 ) -> Result<(), rig::tool::server::ToolServerError> {
+    let conversation_id: String = conversation_id.into();
     handle
-        .add_tool(ReplyTool::new(
-            response_tx.clone(),
-            conversation_id,
-            state.conversation_logger.clone(),
-            state.channel_id.clone(),
-            skip_flag.clone(),
-        ))
+        .add_tool(
+            ReplyTool::new(
+                response_tx.clone(),
+                conversation_id.clone(),
+                state.conversation_logger.clone(),
+                state.channel_id.clone(),
+                skip_flag.clone(),
+            )
+            .with_reflector(reflector)
+            .with_message_id(reply_message_id)
+            .with_turn_id(turn_id)
+            .with_embed_style(EmbedStyle::for_agent(
+                &state.deps.agent_id,
+                **state.deps.runtime_config.embed_color.load(),
+            )),
+        )
         .await?;
     handle.add_tool(BranchTool::new(state.clone())).await?;
-    handle.add_tool(SpawnWorkerTool::new(state.clone())).await?;
+    if allow_workers {
+        handle.add_tool(SpawnWorkerTool::new(state.clone())).await?;
+    }
     handle.add_tool(RouteTool::new(state.clone())).await?;
     if let Some(messaging_manager) = &state.deps.messaging_manager {
         handle
@@ -150,6 +186,39 @@ pub async fn add_channel_tools(
                 state.channel_store.clone(),
             ))
             .await?;
+        if !state.deps.runtime_config.peers.load().is_empty() {
+            handle
+                .add_tool(AskAgentTool::new(
+                    state.deps.agent_id.clone(),
+                    state.deps.runtime_config.clone(),
+                    messaging_manager.clone(),
+                ))
+                .await?;
+        }
+        if state.deps.runtime_config.moderation.load().enabled {
+            handle
+                .add_tool(
+                    ModerateTool::new(
+                        state.deps.agent_id.clone(),
+                        state.channel_id.clone(),
+                        conversation_id.clone(),
+                        state.deps.runtime_config.clone(),
+                        messaging_manager.clone(),
+                        state.deps.sqlite_pool.clone(),
+                    )
+                    .with_target(moderation_target),
+                )
+                .await?;
+        }
+        if let Some(tags) = forum_tags.filter(|tags| !tags.is_empty()) {
+            handle
+                .add_tool(TagPostTool::new(
+                    conversation_id,
+                    messaging_manager.clone(),
+                    tags,
+                ))
+                .await?;
+        }
     }
     handle.add_tool(CancelTool::new(state)).await?;
     handle
@@ -174,15 +243,18 @@ pub async fn remove_channel_tools(
 ) -> Result<(), rig::tool::server::ToolServerError> {
     handle.remove_tool(ReplyTool::NAME).await?;
     handle.remove_tool(BranchTool::NAME).await?;
-    handle.remove_tool(SpawnWorkerTool::NAME).await?;
     handle.remove_tool(RouteTool::NAME).await?;
     handle.remove_tool(CancelTool::NAME).await?;
     handle.remove_tool(SkipTool::NAME).await?;
     handle.remove_tool(SendFileTool::NAME).await?;
     handle.remove_tool(ReactTool::NAME).await?;
-    // Cron and send_message removal is best-effort since not all channels have them
+    // Removal of the tools below is best-effort since not every channel or turn has them
+    let _ = handle.remove_tool(SpawnWorkerTool::NAME).await;
     let _ = handle.remove_tool(CronTool::NAME).await;
     let _ = handle.remove_tool(SendMessageTool::NAME).await;
+    let _ = handle.remove_tool(AskAgentTool::NAME).await;
+    let _ = handle.remove_tool(ModerateTool::NAME).await;
+    let _ = handle.remove_tool(TagPostTool::NAME).await;
     Ok(())
 }
 
@@ -190,15 +262,20 @@ pub async fn remove_channel_tools(
 ///
 /// Each branch gets its own isolated ToolServer so `memory_recall` is never
 /// visible to the channel. Both `memory_save` and `memory_recall` are
-/// registered at creation.
+/// registered at creation. With `private_channel` set, memories are saved
+/// private to that channel and its private memories can be recalled.
 pub fn create_branch_tool_server(
     memory_search: Arc<MemorySearch>,
     conversation_logger: crate::conversation::history::ConversationLogger,
     channel_store: crate::conversation::ChannelStore,
+    private_channel: Option<ChannelId>,
 ) -> ToolServerHandle {
     ToolServer::new()
-        .tool(MemorySaveTool::new(memory_search.clone()))
-        .tool(MemoryRecallTool::new(memory_search.clone()))
+        .tool(
+            MemorySaveTool::new(memory_search.clone())
+                .with_private_channel(private_channel.clone()),
+        )
+        .tool(MemoryRecallTool::new(memory_search.clone()).with_private_channel(private_channel))
         .tool(MemoryDeleteTool::new(memory_search))
         .tool(ChannelRecallTool::new(conversation_logger, channel_store))
         .run()
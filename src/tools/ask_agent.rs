@@ -0,0 +1,216 @@
+//! Ask agent tool for putting a question to a peer agent and awaiting its reply.
+
+use crate::config::RuntimeConfig;
+use crate::messaging::MessagingManager;
+use crate::messaging::webchat::WebChatEvent;
+use crate::{AgentId, InboundMessage, MessageContent};
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long to wait for the peer's reply.
+const ASK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Tool for asking another agent in the instance a question.
+///
+/// The question is injected as an inbound webchat message addressed to the
+/// peer, in a conversation reserved for this pair of agents, and the peer's
+/// first reply is returned as the tool result. The exchange is persisted in
+/// the peer's conversation history like any other channel.
+#[derive(Clone)]
+pub struct AskAgentTool {
+    agent_id: AgentId,
+    runtime_config: Arc<RuntimeConfig>,
+    messaging_manager: Arc<MessagingManager>,
+}
+
+impl std::fmt::Debug for AskAgentTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AskAgentTool")
+            .field("agent_id", &self.agent_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AskAgentTool {
+    pub fn new(
+        agent_id: AgentId,
+        runtime_config: Arc<RuntimeConfig>,
+        messaging_manager: Arc<MessagingManager>,
+    ) -> Self {
+        Self {
+            agent_id,
+            runtime_config,
+            messaging_manager,
+        }
+    }
+}
+
+/// Error type for ask_agent tool.
+#[derive(Debug, thiserror::Error)]
+#[error("AskAgent failed: {0}")]
+pub struct AskAgentError(String);
+
+/// Arguments for ask_agent tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AskAgentArgs {
+    /// ID of the peer agent to ask.
+    pub agent_id: String,
+    /// The question, with whatever context the peer needs to answer it.
+    pub question: String,
+}
+
+/// Output from ask_agent tool.
+#[derive(Debug, Serialize)]
+pub struct AskAgentOutput {
+    pub agent_id: String,
+    pub answer: String,
+}
+
+impl Tool for AskAgentTool {
+    const NAME: &'static str = "ask_agent";
+
+    type Error = AskAgentError;
+    type Args = AskAgentArgs;
+    type Output = AskAgentOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/ask_agent").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "agent_id": {
+                        "type": "string",
+                        "description": "ID of the agent to ask, from the Other Agents list."
+                    },
+                    "question": {
+                        "type": "string",
+                        "description": "The question. Include the context the other agent needs; it can't see this conversation."
+                    }
+                },
+                "required": ["agent_id", "question"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.agent_id == self.agent_id.as_ref() {
+            return Err(AskAgentError("you can't ask yourself".into()));
+        }
+        let peers = self.runtime_config.peers.load();
+        if !peers.iter().any(|peer| peer.agent_id == args.agent_id) {
+            let known: Vec<&str> = peers.iter().map(|peer| peer.agent_id.as_str()).collect();
+            return Err(AskAgentError(format!(
+                "no agent named '{}'. Known agents: {}",
+                args.agent_id,
+                known.join(", ")
+            )));
+        }
+
+        let webchat = self
+            .messaging_manager
+            .webchat()
+            .ok_or_else(|| AskAgentError("agent messaging is not available".into()))?;
+
+        // One conversation per pair, so the peer keeps context across questions.
+        let conversation_id = format!("agent:{}:{}", self.agent_id, args.agent_id);
+        let mut event_rx = webchat
+            .try_register_session(&conversation_id)
+            .await
+            .ok_or_else(|| {
+                AskAgentError(format!(
+                    "already waiting on an answer from '{}'; ask again once it arrives",
+                    args.agent_id
+                ))
+            })?;
+
+        tracing::info!(
+            from = %self.agent_id,
+            to = %args.agent_id,
+            question_len = args.question.len(),
+            "ask_agent tool called"
+        );
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "display_name".into(),
+            serde_json::Value::String(format!("agent {}", self.agent_id)),
+        );
+        let inbound = InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "webchat".into(),
+            conversation_id: conversation_id.clone(),
+            sender_id: format!("agent:{}", self.agent_id),
+            agent_id: Some(args.agent_id.as_str().into()),
+            content: MessageContent::Text(args.question),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(format!("agent {}", self.agent_id)),
+        };
+
+        let result = match self.messaging_manager.inject_message(inbound).await {
+            Ok(()) => tokio::time::timeout(ASK_TIMEOUT, collect_reply(&mut event_rx)).await,
+            Err(error) => {
+                webchat.unregister_session(&conversation_id).await;
+                return Err(AskAgentError(format!(
+                    "failed to deliver question: {error}"
+                )));
+            }
+        };
+        webchat.unregister_session(&conversation_id).await;
+
+        let answer = match result {
+            Ok(Some(answer)) => answer,
+            Ok(None) => {
+                return Err(AskAgentError(format!(
+                    "'{}' ended the conversation without answering",
+                    args.agent_id
+                )));
+            }
+            Err(_) => {
+                tracing::warn!(from = %self.agent_id, to = %args.agent_id, "ask_agent timed out");
+                return Err(AskAgentError(format!(
+                    "'{}' didn't answer within {}s",
+                    args.agent_id,
+                    ASK_TIMEOUT.as_secs()
+                )));
+            }
+        };
+
+        tracing::info!(
+            from = %self.agent_id,
+            to = %args.agent_id,
+            answer_len = answer.len(),
+            "ask_agent answered"
+        );
+
+        Ok(AskAgentOutput {
+            agent_id: args.agent_id,
+            answer,
+        })
+    }
+}
+
+/// Read session events until the peer's reply is complete. Returns None if
+/// the session closes first.
+async fn collect_reply(event_rx: &mut tokio::sync::mpsc::Receiver<WebChatEvent>) -> Option<String> {
+    let mut answer = String::new();
+    while let Some(event) = event_rx.recv().await {
+        match event {
+            WebChatEvent::Text(text) => answer.push_str(&text),
+            WebChatEvent::StreamChunk(chunk) => answer.push_str(&chunk),
+            WebChatEvent::StreamAbort => answer.clear(),
+            WebChatEvent::Done => return Some(answer),
+            _ => {}
+        }
+    }
+    None
+}
@@ -1,6 +1,6 @@
 //! Cron job management tool for creating, listing, and deleting scheduled tasks.
 
-use crate::cron::scheduler::{CronConfig, Scheduler};
+use crate::cron::scheduler::{CronConfig, Scheduler, parse_start_at};
 use crate::cron::store::CronStore;
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
@@ -32,9 +32,18 @@ pub struct CronArgs {
     /// Required for "create": a short unique ID for the cron job (e.g. "check-email", "daily-summary").
     #[serde(default)]
     pub id: Option<String>,
-    /// Required for "create": the prompt/instruction to execute on each run.
+    /// For "create": the prompt/instruction to execute on each run. Required unless `message` is set.
     #[serde(default)]
     pub prompt: Option<String>,
+    /// For "create": an announcement to post as-is on each run instead of running a prompt.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Optional for "create": when the first run happens, as an RFC 3339 timestamp or local "YYYY-MM-DD HH:MM".
+    #[serde(default)]
+    pub start_at: Option<String>,
+    /// Optional for "create": run once at `start_at`, then stop.
+    #[serde(default)]
+    pub run_once: bool,
     /// Required for "create": interval in seconds between runs.
     #[serde(default)]
     pub interval_secs: Option<u64>,
@@ -65,6 +74,11 @@ pub struct CronOutput {
 pub struct CronEntry {
     pub id: String,
     pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<String>,
+    pub run_once: bool,
     pub interval_secs: u64,
     pub delivery_target: String,
     pub active_hours: Option<String>,
@@ -95,7 +109,19 @@ impl Tool for CronTool {
                     },
                     "prompt": {
                         "type": "string",
-                        "description": "For 'create': the instruction to execute on each run."
+                        "description": "For 'create': the instruction to execute on each run. Not needed when 'message' is set."
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "For 'create': an announcement to post exactly as written on each run, e.g. a reminder or standup call, instead of running a prompt."
+                    },
+                    "start_at": {
+                        "type": "string",
+                        "description": "For 'create': when the first run happens, as an RFC 3339 timestamp or local 'YYYY-MM-DD HH:MM'. Later runs keep the same time of day."
+                    },
+                    "run_once": {
+                        "type": "boolean",
+                        "description": "For 'create': run once at start_at, then stop. Requires start_at."
                     },
                     "interval_secs": {
                         "type": "integer",
@@ -103,7 +129,7 @@ impl Tool for CronTool {
                     },
                     "delivery_target": {
                         "type": "string",
-                        "description": "For 'create': where to send results, format 'adapter:target' (e.g. 'discord:123456789')."
+                        "description": "For 'create': where to send results, format 'adapter:target' (e.g. 'discord:123456789', or 'discord:#standup' for a channel by name)."
                     },
                     "active_start_hour": {
                         "type": "integer",
@@ -142,11 +168,26 @@ impl CronTool {
         let id = args
             .id
             .ok_or_else(|| CronError("'id' is required for create".into()))?;
-        let prompt = args
-            .prompt
-            .ok_or_else(|| CronError("'prompt' is required for create".into()))?;
+        let prompt = args.prompt.unwrap_or_default();
+        if prompt.is_empty() && args.message.is_none() {
+            return Err(CronError(
+                "'prompt' or 'message' is required for create".into(),
+            ));
+        }
+        if args.run_once && args.start_at.is_none() {
+            return Err(CronError("'run_once' requires 'start_at'".into()));
+        }
+        if let Some(start_at) = &args.start_at
+            && parse_start_at(start_at).is_none()
+        {
+            return Err(CronError(format!(
+                "invalid start_at '{start_at}', expected an RFC 3339 timestamp or 'YYYY-MM-DD HH:MM'"
+            )));
+        }
+        // A one-off doesn't repeat, so its interval doesn't matter.
         let interval_secs = args
             .interval_secs
+            .or(args.run_once.then_some(86400))
             .ok_or_else(|| CronError("'interval_secs' is required for create".into()))?;
         let delivery_target = args
             .delivery_target
@@ -164,6 +205,9 @@ impl CronTool {
             delivery_target: delivery_target.clone(),
             active_hours,
             enabled: true,
+            message: args.message,
+            start_at: args.start_at.clone(),
+            run_once: args.run_once,
         };
 
         // Persist to database
@@ -178,8 +222,14 @@ impl CronTool {
             .await
             .map_err(|error| CronError(format!("failed to register: {error}")))?;
 
-        let interval_desc = format_interval(interval_secs);
-        let mut message = format!("Cron job '{id}' created. Runs {interval_desc}.");
+        let schedule = match (&args.start_at, args.run_once) {
+            (Some(start_at), true) => format!("once at {start_at}"),
+            (Some(start_at), false) => {
+                format!("{} from {start_at}", format_interval(interval_secs))
+            }
+            (None, _) => format_interval(interval_secs),
+        };
+        let mut message = format!("Cron job '{id}' created. Runs {schedule}.");
         if let Some((start, end)) = active_hours {
             message.push_str(&format!(" Active {start:02}:00-{end:02}:00."));
         }
@@ -205,6 +255,9 @@ impl CronTool {
             .map(|config| CronEntry {
                 id: config.id,
                 prompt: config.prompt,
+                message: config.message,
+                start_at: config.start_at,
+                run_once: config.run_once,
                 interval_secs: config.interval_secs,
                 delivery_target: config.delivery_target,
                 active_hours: config
@@ -227,6 +280,7 @@ impl CronTool {
             .or(args.id)
             .ok_or_else(|| CronError("'delete_id' or 'id' is required for delete".into()))?;
 
+        self.scheduler.unregister(&id).await;
         self.store
             .delete(&id)
             .await
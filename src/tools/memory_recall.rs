@@ -16,12 +16,24 @@ use std::sync::Arc;
 #[derive(Debug, Clone)]
 pub struct MemoryRecallTool {
     memory_search: Arc<MemorySearch>,
+    /// Channel whose private memories may be recalled.
+    private_channel: Option<crate::ChannelId>,
 }
 
 impl MemoryRecallTool {
     /// Create a new memory recall tool.
     pub fn new(memory_search: Arc<MemorySearch>) -> Self {
-        Self { memory_search }
+        Self {
+            memory_search,
+            private_channel: None,
+        }
+    }
+
+    /// Also recall the private memories of a channel, for DMs with private
+    /// memory. Other channels' private memories are never recalled.
+    pub fn with_private_channel(mut self, channel_id: Option<crate::ChannelId>) -> Self {
+        self.private_channel = channel_id;
+        self
     }
 }
 
@@ -217,6 +229,7 @@ impl Tool for MemoryRecallTool {
             sort_by,
             max_results: args.max_results,
             max_results_per_source: args.max_results * 2,
+            channel_id: self.private_channel.clone(),
             ..Default::default()
         };
 
@@ -13,12 +13,23 @@ use std::sync::Arc;
 #[derive(Debug, Clone)]
 pub struct MemorySaveTool {
     memory_search: Arc<MemorySearch>,
+    /// Save every memory as private to this channel.
+    private_channel: Option<crate::ChannelId>,
 }
 
 impl MemorySaveTool {
     /// Create a new memory save tool.
     pub fn new(memory_search: Arc<MemorySearch>) -> Self {
-        Self { memory_search }
+        Self {
+            memory_search,
+            private_channel: None,
+        }
+    }
+
+    /// Keep saved memories private to a channel, for DMs with private memory.
+    pub fn with_private_channel(mut self, channel_id: Option<crate::ChannelId>) -> Self {
+        self.private_channel = channel_id;
+        self
     }
 }
 
@@ -183,7 +194,9 @@ impl Tool for MemorySaveTool {
             memory = memory.with_source(source);
         }
 
-        if let Some(channel_id) = args.channel_id {
+        if let Some(channel_id) = &self.private_channel {
+            memory = memory.private_to(channel_id.clone());
+        } else if let Some(channel_id) = args.channel_id {
             memory = memory.with_channel_id(Arc::from(channel_id.as_str()));
         }
 
@@ -219,30 +232,12 @@ impl Tool for MemorySaveTool {
             }
         }
 
-        // Generate and store embedding (async to avoid blocking the tokio runtime)
-        let embedding = self
-            .memory_search
-            .embedding_model_arc()
-            .embed_one(&args.content)
-            .await
-            .map_err(|e| MemorySaveError(format!("Failed to generate embedding: {e}")))?;
-
+        // Embed and index the memory. With the embedding pipeline enabled this
+        // only queues it, so the save returns without waiting on the model.
         self.memory_search
-            .embedding_table()
-            .store(&memory.id, &args.content, &embedding)
-            .await
-            .map_err(|e| MemorySaveError(format!("Failed to store embedding: {e}")))?;
-
-        // Ensure the FTS index exists so full_text_search queries work.
-        // Safe to call repeatedly — no-ops if the index already exists.
-        if let Err(error) = self
-            .memory_search
-            .embedding_table()
-            .ensure_fts_index()
+            .index_memory(&memory.id, &args.content)
             .await
-        {
-            tracing::warn!(%error, "failed to ensure FTS index after memory save");
-        }
+            .map_err(|e| MemorySaveError(format!("Failed to index memory: {e}")))?;
 
         #[cfg(feature = "metrics")]
         crate::telemetry::Metrics::global()
@@ -0,0 +1,452 @@
+//! Moderate tool for deleting messages, timing out users, and applying roles
+//! (channel only, gated by the agent's moderation policy).
+
+use crate::config::{ModerationConfig, RuntimeConfig};
+use crate::db::TimedQuery as _;
+use crate::messaging::MessagingManager;
+use crate::{AgentId, ChannelId, InboundMessage, ModerationAction};
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+/// The message that started the turn. Actions without an explicit message or
+/// user ID apply to it and its author.
+#[derive(Debug, Clone)]
+pub struct ModerationTarget {
+    pub message_id: Option<String>,
+    pub user_id: String,
+}
+
+impl ModerationTarget {
+    /// The target for an inbound message. None for synthetic messages.
+    pub fn from_message(message: &InboundMessage) -> Option<Self> {
+        if message.source == "system" {
+            return None;
+        }
+        let message_id = message
+            .metadata
+            .get("discord_message_id")
+            .and_then(|v| v.as_u64())
+            .map(|id| id.to_string());
+        Some(Self {
+            message_id,
+            user_id: message.sender_id.clone(),
+        })
+    }
+}
+
+/// Tool for taking moderation actions in the current conversation.
+///
+/// Every attempt is checked against the agent's `[moderation]` policy, which
+/// is re-read on each call so hot-reloaded changes apply mid-turn, and
+/// written to `moderation_actions` whatever the outcome, including calls
+/// whose arguments don't make an action.
+#[derive(Clone)]
+pub struct ModerateTool {
+    agent_id: AgentId,
+    channel_id: ChannelId,
+    conversation_id: String,
+    runtime_config: Arc<RuntimeConfig>,
+    messaging_manager: Arc<MessagingManager>,
+    pool: sqlx::SqlitePool,
+    target: Option<ModerationTarget>,
+}
+
+impl std::fmt::Debug for ModerateTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModerateTool")
+            .field("channel_id", &self.channel_id)
+            .field("target", &self.target)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ModerateTool {
+    pub fn new(
+        agent_id: AgentId,
+        channel_id: ChannelId,
+        conversation_id: impl Into<String>,
+        runtime_config: Arc<RuntimeConfig>,
+        messaging_manager: Arc<MessagingManager>,
+        pool: sqlx::SqlitePool,
+    ) -> Self {
+        Self {
+            agent_id,
+            channel_id,
+            conversation_id: conversation_id.into(),
+            runtime_config,
+            messaging_manager,
+            pool,
+            target: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: Option<ModerationTarget>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Write an attempt to `moderation_actions`. A failed write is logged,
+    /// and doesn't change the tool's result.
+    async fn record(&self, attempt: Attempt<'_>) {
+        if let Err(error) =
+            record_attempt(&self.pool, &self.agent_id, &self.channel_id, attempt).await
+        {
+            tracing::warn!(%error, channel_id = %self.channel_id, "failed to persist moderation action");
+        }
+    }
+}
+
+/// One call of the tool, as written to `moderation_actions`.
+struct Attempt<'a> {
+    action: &'a str,
+    target: String,
+    reason: &'a str,
+    /// `done`, `denied`, `failed`, or `invalid`.
+    outcome: &'static str,
+    error: Option<String>,
+}
+
+impl<'a> Attempt<'a> {
+    fn new(action: &'a ModerationAction, reason: &'a str, outcome: &'static str) -> Self {
+        Self {
+            action: action.kind(),
+            target: action.target(),
+            reason,
+            outcome,
+            error: None,
+        }
+    }
+
+    /// A call whose arguments didn't make an action, with what it asked to
+    /// act on as far as it said.
+    fn invalid(args: &'a ModerateArgs, error: String) -> Self {
+        let target = match (&args.message_id, &args.user_id) {
+            (Some(message_id), _) => format!("message {message_id}"),
+            (None, Some(user_id)) => format!("user {user_id}"),
+            (None, None) => String::new(),
+        };
+        Self {
+            action: &args.action,
+            target,
+            reason: &args.reason,
+            outcome: "invalid",
+            error: Some(error),
+        }
+    }
+
+    fn with_error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+}
+
+async fn record_attempt(
+    pool: &sqlx::SqlitePool,
+    agent_id: &str,
+    channel_id: &str,
+    attempt: Attempt<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO moderation_actions \
+         (id, agent_id, channel_id, action, target, reason, outcome, error) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(agent_id)
+    .bind(channel_id)
+    .bind(attempt.action)
+    .bind(&attempt.target)
+    .bind(attempt.reason)
+    .bind(attempt.outcome)
+    .bind(&attempt.error)
+    .execute(pool)
+    .timed_with("moderation_actions.insert", || {
+        format!("channel_id={channel_id} outcome={}", attempt.outcome)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Error type for moderate tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Moderate failed: {0}")]
+pub struct ModerateError(String);
+
+/// Arguments for moderate tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ModerateArgs {
+    /// "delete_message", "timeout_user", or "apply_role".
+    pub action: String,
+    /// Message to delete. Defaults to the message that started this turn.
+    #[serde(default)]
+    pub message_id: Option<String>,
+    /// User to time out or give a role. Defaults to the author of the
+    /// message that started this turn.
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// How long a timeout lasts, in seconds.
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+    /// Role to apply.
+    #[serde(default)]
+    pub role_id: Option<String>,
+    /// Why the action is being taken. Shown in the platform's audit log.
+    pub reason: String,
+}
+
+/// Output from moderate tool.
+#[derive(Debug, Serialize)]
+pub struct ModerateOutput {
+    pub success: bool,
+    pub action: String,
+    pub target: String,
+}
+
+impl Tool for ModerateTool {
+    const NAME: &'static str = "moderate";
+
+    type Error = ModerateError;
+    type Args = ModerateArgs;
+    type Output = ModerateOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let policy = self.runtime_config.moderation.load();
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/moderate").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": policy.actions,
+                        "description": "The moderation action to take."
+                    },
+                    "message_id": {
+                        "type": "string",
+                        "description": "For delete_message: the message to delete. Defaults to the message that started this turn."
+                    },
+                    "user_id": {
+                        "type": "string",
+                        "description": "For timeout_user and apply_role: the user to act on. Defaults to the author of the message that started this turn."
+                    },
+                    "duration_secs": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": policy.max_timeout_secs,
+                        "description": "For timeout_user: how long the timeout lasts, in seconds."
+                    },
+                    "role_id": {
+                        "type": "string",
+                        "description": "For apply_role: the role to give the user."
+                    },
+                    "reason": {
+                        "type": "string",
+                        "description": "Why you are taking this action. Recorded in the audit log."
+                    }
+                },
+                "required": ["action", "reason"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let action = match build_action(&args, self.target.as_ref()) {
+            Ok(action) => action,
+            Err(error) => {
+                self.record(Attempt::invalid(&args, error.clone())).await;
+                return Err(ModerateError(error));
+            }
+        };
+        let target = action.target();
+        tracing::info!(action = action.kind(), %target, "moderate tool called");
+
+        if let Err(denial) = check_policy(&self.runtime_config.moderation.load(), &action) {
+            self.record(Attempt::new(&action, &args.reason, "denied").with_error(denial.clone()))
+                .await;
+            return Err(ModerateError(denial));
+        }
+
+        match self
+            .messaging_manager
+            .moderate(&self.conversation_id, action.clone(), &args.reason)
+            .await
+        {
+            Ok(()) => {
+                self.record(Attempt::new(&action, &args.reason, "done"))
+                    .await;
+                Ok(ModerateOutput {
+                    success: true,
+                    action: action.kind().to_string(),
+                    target,
+                })
+            }
+            Err(error) => {
+                let error = error.to_string();
+                self.record(
+                    Attempt::new(&action, &args.reason, "failed").with_error(error.clone()),
+                )
+                .await;
+                Err(ModerateError(error))
+            }
+        }
+    }
+}
+
+/// Turn the tool arguments into an action, filling in IDs from the message
+/// that started the turn.
+fn build_action(
+    args: &ModerateArgs,
+    target: Option<&ModerationTarget>,
+) -> Result<ModerationAction, String> {
+    let user_id = || {
+        args.user_id
+            .clone()
+            .or_else(|| target.map(|t| t.user_id.clone()))
+            .ok_or_else(|| "user_id is required".to_string())
+    };
+
+    match args.action.as_str() {
+        "delete_message" => {
+            let message_id = args
+                .message_id
+                .clone()
+                .or_else(|| target.and_then(|t| t.message_id.clone()))
+                .ok_or_else(|| "message_id is required".to_string())?;
+            Ok(ModerationAction::DeleteMessage { message_id })
+        }
+        "timeout_user" => Ok(ModerationAction::TimeoutUser {
+            user_id: user_id()?,
+            duration_secs: args
+                .duration_secs
+                .ok_or_else(|| "duration_secs is required for timeout_user".to_string())?,
+        }),
+        "apply_role" => Ok(ModerationAction::ApplyRole {
+            user_id: user_id()?,
+            role_id: args
+                .role_id
+                .clone()
+                .ok_or_else(|| "role_id is required for apply_role".to_string())?,
+        }),
+        other => Err(format!("unknown moderation action '{other}'")),
+    }
+}
+
+/// Check an action against the moderation policy. The error explains the
+/// denial to the LLM.
+fn check_policy(policy: &ModerationConfig, action: &ModerationAction) -> Result<(), String> {
+    if !policy.enabled {
+        return Err("moderation is disabled for this agent".into());
+    }
+    if !policy
+        .actions
+        .iter()
+        .any(|allowed| allowed == action.kind())
+    {
+        return Err(format!("{} is not allowed for this agent", action.kind()));
+    }
+    match action {
+        ModerationAction::TimeoutUser { duration_secs, .. }
+            if *duration_secs == 0 || *duration_secs > policy.max_timeout_secs =>
+        {
+            Err(format!(
+                "timeouts must be between 1 and {} seconds",
+                policy.max_timeout_secs
+            ))
+        }
+        ModerationAction::ApplyRole { role_id, .. } if !policy.roles.contains(role_id) => {
+            Err(format!("role {role_id} is not in the allowed roles"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_gates_actions_durations_and_roles() {
+        let policy = ModerationConfig {
+            enabled: true,
+            actions: vec!["timeout_user".into(), "apply_role".into()],
+            max_timeout_secs: 600,
+            roles: vec!["42".into()],
+        };
+        let target = ModerationTarget {
+            message_id: Some("7".into()),
+            user_id: "9".into(),
+        };
+        let args = |action: &str, duration_secs, role_id: Option<&str>| ModerateArgs {
+            action: action.into(),
+            message_id: None,
+            user_id: None,
+            duration_secs,
+            role_id: role_id.map(str::to_string),
+            reason: "spam".into(),
+        };
+        let check = |args: ModerateArgs| {
+            let action = build_action(&args, Some(&target))?;
+            check_policy(&policy, &action).map(|()| action)
+        };
+
+        assert_eq!(
+            check(args("timeout_user", Some(300), None)),
+            Ok(ModerationAction::TimeoutUser {
+                user_id: "9".into(),
+                duration_secs: 300
+            })
+        );
+        assert!(check(args("timeout_user", Some(3600), None)).is_err());
+        assert!(check(args("apply_role", None, Some("42"))).is_ok());
+        assert!(check(args("apply_role", None, Some("1"))).is_err());
+        assert!(check(args("delete_message", None, None)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_calls_are_recorded_with_their_error() {
+        use sqlx::Row as _;
+        use sqlx::pool::PoolOptions;
+        use sqlx::sqlite::SqliteConnectOptions;
+
+        let options = SqliteConnectOptions::new()
+            .in_memory(true)
+            .create_if_missing(true);
+        let pool = PoolOptions::<sqlx::Sqlite>::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+        let args = ModerateArgs {
+            action: "timeout_user".into(),
+            message_id: None,
+            user_id: Some("9".into()),
+            duration_secs: None,
+            role_id: None,
+            reason: "spam".into(),
+        };
+        let error = build_action(&args, None).unwrap_err();
+        record_attempt(&pool, "main", "discord:1:2", Attempt::invalid(&args, error))
+            .await
+            .unwrap();
+
+        let row = sqlx::query("SELECT action, target, outcome, error FROM moderation_actions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(row.get::<String, _>("action"), "timeout_user");
+        assert_eq!(row.get::<String, _>("target"), "user 9");
+        assert_eq!(row.get::<String, _>("outcome"), "invalid");
+        assert_eq!(
+            row.get::<Option<String>, _>("error").as_deref(),
+            Some("duration_secs is required for timeout_user")
+        );
+    }
+}
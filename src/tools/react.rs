@@ -54,7 +54,7 @@ impl Tool for ReactTool {
                 "properties": {
                     "emoji": {
                         "type": "string",
-                        "description": "A single unicode emoji character (e.g. \"👍\", \"😂\", \"🔥\", \"👀\")."
+                        "description": "A single unicode emoji character (e.g. \"👍\", \"😂\", \"🔥\", \"👀\"), or a Discord server's custom emoji as :name:."
                     }
                 },
                 "required": ["emoji"]
@@ -1,6 +1,8 @@
 //! Reply tool for sending messages to users (channel only).
 
+use crate::agent::reflection::Reflector;
 use crate::conversation::ConversationLogger;
+use crate::messaging::render::{EmbedStyle, StructuredReply};
 use crate::tools::SkipFlag;
 use crate::{ChannelId, OutboundResponse};
 use rig::completion::ToolDefinition;
@@ -9,8 +11,18 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
+/// Id of the last bot message the reply tool logged in a turn, shared with
+/// the channel so the turn's reasoning trace can be keyed by it.
+pub type ReplyMessageId = Arc<Mutex<Option<String>>>;
+
+/// Create an empty reply message id slot.
+pub fn new_reply_message_id() -> ReplyMessageId {
+    Arc::new(Mutex::new(None))
+}
+
 /// Tool for replying to users.
 ///
 /// Holds a sender channel rather than a specific InboundMessage. The channel
@@ -24,6 +36,10 @@ pub struct ReplyTool {
     conversation_logger: ConversationLogger,
     channel_id: ChannelId,
     skip_flag: SkipFlag,
+    reflector: Option<Reflector>,
+    message_id: Option<ReplyMessageId>,
+    embed_style: Option<EmbedStyle>,
+    turn_id: Option<String>,
 }
 
 impl ReplyTool {
@@ -41,8 +57,36 @@ impl ReplyTool {
             conversation_logger,
             channel_id,
             skip_flag,
+            reflector: None,
+            message_id: None,
+            embed_style: None,
+            turn_id: None,
         }
     }
+
+    /// Review each draft with the given reflector before sending it.
+    pub fn with_reflector(mut self, reflector: Option<Reflector>) -> Self {
+        self.reflector = reflector;
+        self
+    }
+
+    /// Record the id of each logged reply in the given slot.
+    pub fn with_message_id(mut self, message_id: ReplyMessageId) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+
+    /// Log replies under the turn they answer; see [`crate::TURN_ID_KEY`].
+    pub fn with_turn_id(mut self, turn_id: Option<String>) -> Self {
+        self.turn_id = turn_id;
+        self
+    }
+
+    /// Give the reply's cards the agent's color and footer.
+    pub fn with_embed_style(mut self, embed_style: EmbedStyle) -> Self {
+        self.embed_style = Some(embed_style);
+        self
+    }
 }
 
 /// Error type for reply tool.
@@ -72,6 +116,10 @@ pub struct ReplyArgs {
     /// Optional: a poll to attach to the message.
     #[serde(default)]
     pub poll: Option<crate::Poll>,
+    /// Optional: a summary, status report, or search results, rendered as an
+    /// embed on Discord and as text elsewhere.
+    #[serde(default)]
+    pub structured: Option<StructuredReply>,
 }
 
 /// Output from reply tool.
@@ -154,6 +202,14 @@ async fn convert_mentions(
     result
 }
 
+fn join_paragraphs(first: &str, second: &str) -> String {
+    if first.trim().is_empty() {
+        second.to_string()
+    } else {
+        format!("{first}\n\n{second}")
+    }
+}
+
 impl Tool for ReplyTool {
     const NAME: &'static str = "reply";
 
@@ -171,7 +227,7 @@ impl Tool for ReplyTool {
                 },
                 "thread_name": {
                     "type": "string",
-                    "description": "If provided, creates a new public thread with this name and posts the reply inside it. Max 100 characters."
+                    "description": "If provided, creates a new public thread with this name and posts the reply inside it. Use it for long tangents that would crowd the main channel. Max 100 characters."
                 },
                 "cards": {
                     "type": "array",
@@ -201,7 +257,7 @@ impl Tool for ReplyTool {
                 },
                 "interactive_elements": {
                     "type": "array",
-                    "description": "Optional: interactive components to attach. Button clicks will be sent back to you as an inbound InteractionEvent with the corresponding custom_id. Max 5 elements (rows).",
+                    "description": "Optional: buttons or select menus to attach, e.g. to have the user pick one of a few options. A click or selection comes back to you as a message naming the custom_id and the label chosen, and the row is disabled once used. Max 5 elements (rows).",
                     "items": {
                         "type": "object",
                         "properties": {
@@ -243,6 +299,44 @@ impl Tool for ReplyTool {
                         }
                     }
                 },
+                "structured": {
+                    "type": "object",
+                    "description": "Optional: present a summary, status report, or search results as a formatted embed in your color. Keep content to a one-line lead-in when using it.",
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["summary", "status_report", "search_results"] },
+                        "title": { "type": "string", "description": "For summary and status_report" },
+                        "points": { "type": "array", "items": { "type": "string" }, "description": "summary: the key points" },
+                        "action_items": { "type": "array", "items": { "type": "string" }, "description": "summary: follow-ups, if any" },
+                        "overall": { "type": "string", "description": "status_report: one line on the overall state" },
+                        "items": {
+                            "type": "array",
+                            "description": "status_report: the state of each component",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": { "type": "string" },
+                                    "status": { "type": "string" }
+                                },
+                                "required": ["name", "status"]
+                            }
+                        },
+                        "query": { "type": "string", "description": "search_results: what was searched for" },
+                        "results": {
+                            "type": "array",
+                            "description": "search_results: best first",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "title": { "type": "string" },
+                                    "url": { "type": "string" },
+                                    "snippet": { "type": "string" }
+                                },
+                                "required": ["title"]
+                            }
+                        }
+                    },
+                    "required": ["kind"]
+                },
                 "poll": {
                     "type": "object",
                     "description": "Optional: a poll to attach to the message.",
@@ -268,7 +362,7 @@ impl Tool for ReplyTool {
         }
     }
 
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
         tracing::info!(
             conversation_id = %self.conversation_id,
             content_len = args.content.len(),
@@ -276,6 +370,10 @@ impl Tool for ReplyTool {
             "reply tool called"
         );
 
+        if let Some(reflector) = &self.reflector {
+            args.content = reflector.review(&args.content).await;
+        }
+
         // Extract source from conversation_id (format: "platform:id")
         let source = self.conversation_id.split(':').next().unwrap_or("unknown");
 
@@ -288,8 +386,34 @@ impl Tool for ReplyTool {
         )
         .await;
 
-        self.conversation_logger
-            .log_bot_message(&self.channel_id, &converted_content);
+        // Discord shows structured replies as an embed; elsewhere they're
+        // appended to the text. Either way the history gets the text form.
+        let mut cards = args.cards.unwrap_or_default();
+        let mut text = converted_content.clone();
+        let mut logged_content = converted_content.clone();
+        if let Some(structured) = &args.structured {
+            let rendered = structured.to_text();
+            logged_content = join_paragraphs(&converted_content, &rendered);
+            if source == "discord" && args.thread_name.is_none() {
+                cards.insert(0, structured.to_card());
+            } else {
+                text = logged_content.clone();
+            }
+        }
+        if let Some(style) = &self.embed_style {
+            for card in &mut cards {
+                style.apply(card);
+            }
+        }
+
+        let message_id = self.conversation_logger.log_bot_message(
+            &self.channel_id,
+            &logged_content,
+            self.turn_id.as_deref(),
+        );
+        if let Some(slot) = &self.message_id {
+            *slot.lock().expect("reply message id lock poisoned") = Some(message_id);
+        }
 
         let response = if let Some(ref name) = args.thread_name {
             // Cap thread names at 100 characters (Discord limit)
@@ -298,21 +422,17 @@ impl Tool for ReplyTool {
             } else {
                 name.clone()
             };
-            OutboundResponse::ThreadReply {
-                thread_name,
-                text: converted_content.clone(),
-            }
-        } else if args.cards.is_some() || args.interactive_elements.is_some() || args.poll.is_some()
-        {
+            OutboundResponse::ThreadReply { thread_name, text }
+        } else if !cards.is_empty() || args.interactive_elements.is_some() || args.poll.is_some() {
             OutboundResponse::RichMessage {
-                text: converted_content.clone(),
+                text,
                 blocks: vec![], // No block generation for now; Slack adapters will fall back to text
-                cards: args.cards.unwrap_or_default(),
+                cards,
                 interactive_elements: args.interactive_elements.unwrap_or_default(),
                 poll: args.poll,
             }
         } else {
-            OutboundResponse::Text(converted_content.clone())
+            OutboundResponse::Text(text)
         };
 
         self.response_tx
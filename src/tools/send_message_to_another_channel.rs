@@ -171,8 +171,11 @@ fn resolve_broadcast_target(
             }
 
             // Fallback: parse from channel ID format "discord:{guild_id}:{channel_id}"
+            // or "discord:{guild_id}:{parent_id}:{thread_id}"
             match parts.as_slice() {
-                ["discord", _, channel_id] => Some(("discord".to_string(), channel_id.to_string())),
+                ["discord", _, channel_id] | ["discord", _, _, channel_id] => {
+                    Some(("discord".to_string(), channel_id.to_string()))
+                }
                 _ => None,
             }
         }
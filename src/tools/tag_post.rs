@@ -0,0 +1,158 @@
+//! Tag post tool for applying a forum's tags to the current post (channel
+//! only, when tagging is on for the forum).
+
+use crate::messaging::MessagingManager;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+/// Platforms cap the tags on one post. Discord allows five.
+const MAX_TAGS: usize = 5;
+
+/// Tool for setting the tags of the forum post this conversation is.
+#[derive(Clone)]
+pub struct TagPostTool {
+    conversation_id: String,
+    messaging_manager: Arc<MessagingManager>,
+    /// The forum's tags, as offered to the model.
+    available: Vec<String>,
+}
+
+impl std::fmt::Debug for TagPostTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TagPostTool")
+            .field("conversation_id", &self.conversation_id)
+            .field("available", &self.available)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TagPostTool {
+    pub fn new(
+        conversation_id: impl Into<String>,
+        messaging_manager: Arc<MessagingManager>,
+        available: Vec<String>,
+    ) -> Self {
+        Self {
+            conversation_id: conversation_id.into(),
+            messaging_manager,
+            available,
+        }
+    }
+}
+
+/// Error type for tag post tool.
+#[derive(Debug, thiserror::Error)]
+#[error("Tag post failed: {0}")]
+pub struct TagPostError(String);
+
+/// Arguments for tag post tool.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TagPostArgs {
+    /// Tags to apply. They replace the post's current tags.
+    pub tags: Vec<String>,
+}
+
+/// Output from tag post tool.
+#[derive(Debug, Serialize)]
+pub struct TagPostOutput {
+    pub success: bool,
+    pub tags: Vec<String>,
+}
+
+impl Tool for TagPostTool {
+    const NAME: &'static str = "tag_post";
+
+    type Error = TagPostError;
+    type Args = TagPostArgs;
+    type Output = TagPostOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/tag_post").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "tags": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": self.available
+                        },
+                        "maxItems": MAX_TAGS,
+                        "description": "Tags to apply. They replace the post's current tags."
+                    }
+                },
+                "required": ["tags"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let tags = match_tags(&self.available, &args.tags).map_err(TagPostError)?;
+        tracing::info!(?tags, "tag_post tool called");
+
+        self.messaging_manager
+            .tag_post(&self.conversation_id, tags.clone())
+            .await
+            .map_err(|error| TagPostError(error.to_string()))?;
+
+        Ok(TagPostOutput {
+            success: true,
+            tags,
+        })
+    }
+}
+
+/// The forum's spelling of each requested tag, matched case-insensitively.
+/// The error names the tags that don't exist.
+fn match_tags(available: &[String], requested: &[String]) -> Result<Vec<String>, String> {
+    if requested.len() > MAX_TAGS {
+        return Err(format!("a post can have at most {MAX_TAGS} tags"));
+    }
+    let mut tags = Vec::new();
+    let mut unknown = Vec::new();
+    for tag in requested {
+        match available
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(tag.trim()))
+        {
+            Some(name) if !tags.contains(name) => tags.push(name.clone()),
+            Some(_) => {}
+            None => unknown.push(tag.as_str()),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(format!(
+            "unknown tags: {}. Available: {}",
+            unknown.join(", "),
+            available.join(", ")
+        ));
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_match_case_insensitively_and_reject_unknown() {
+        let available = vec!["Bug".to_string(), "Feature Request".to_string()];
+
+        assert_eq!(
+            match_tags(
+                &available,
+                &["bug".into(), "feature request".into(), "BUG".into()]
+            ),
+            Ok(vec!["Bug".to_string(), "Feature Request".to_string()])
+        );
+        let error = match_tags(&available, &["billing".into()]).unwrap_err();
+        assert!(error.contains("unknown tags: billing"));
+    }
+}
@@ -46,7 +46,7 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
 
     let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
         memory_store,
-        embedding_table,
+        Arc::new(embedding_table),
         embedding_model,
     ));
 
@@ -77,6 +77,7 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
         runtime_config,
         event_tx,
         sqlite_pool: db.sqlite.clone(),
+        sqlite_read: db.sqlite_read.clone(),
         messaging_manager: None,
     })
 }
@@ -46,7 +46,7 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
 
     let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
         memory_store,
-        embedding_table,
+        Arc::new(embedding_table),
         embedding_model,
     ));
 
@@ -77,6 +77,7 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
         runtime_config,
         event_tx,
         sqlite_pool: db.sqlite.clone(),
+        sqlite_read: db.sqlite_read.clone(),
         messaging_manager: None,
     };
 
@@ -148,6 +149,8 @@ fn build_channel_system_prompt(rc: &spacebot::config::RuntimeConfig) -> String {
             None,
             None,
             None,
+            None,
+            None,
         )
         .expect("failed to render channel prompt")
 }
@@ -187,6 +190,8 @@ async fn dump_channel_context() {
         channel_store,
         screenshot_dir: std::path::PathBuf::from("/tmp/screenshots"),
         logs_dir: std::path::PathBuf::from("/tmp/logs"),
+        turn_canceller: Default::default(),
+        private_memory: Default::default(),
     };
 
     let tool_server = rig::tool::server::ToolServer::new().run();
@@ -197,7 +202,11 @@ async fn dump_channel_context() {
         response_tx,
         "test-conversation",
         skip_flag,
+        spacebot::tools::new_reply_message_id(),
+        None,
         None,
+        None,
+        true,
     )
     .await
     .expect("failed to add channel tools");
@@ -392,6 +401,8 @@ async fn dump_all_contexts() {
         channel_store: channel_store.clone(),
         screenshot_dir: std::path::PathBuf::from("/tmp/screenshots"),
         logs_dir: std::path::PathBuf::from("/tmp/logs"),
+        turn_canceller: Default::default(),
+        private_memory: Default::default(),
     };
     let channel_tool_server = rig::tool::server::ToolServer::new().run();
     let skip_flag = spacebot::tools::new_skip_flag();
@@ -401,7 +412,11 @@ async fn dump_all_contexts() {
         response_tx,
         "test",
         skip_flag,
+        spacebot::tools::new_reply_message_id(),
+        None,
+        None,
         None,
+        true,
     )
     .await
     .expect("failed to add channel tools");
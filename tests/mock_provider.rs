@@ -0,0 +1,63 @@
+//! Offline tests for the mock LLM provider.
+//!
+//! These run without credentials or network access and exercise the same
+//! SpacebotModel path that channels, branches and the compactor use.
+//!
+//! Run with: cargo test --test mock_provider
+
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel as _, Prompt as _};
+use spacebot::llm::{LlmManager, MockProvider, MockResponse, SpacebotModel};
+use std::sync::Arc;
+
+async fn manager_with(mock: MockProvider) -> (Arc<LlmManager>, Arc<MockProvider>) {
+    let mock = Arc::new(mock);
+    let manager = LlmManager::new(spacebot::config::LlmConfig::default())
+        .await
+        .expect("failed to init LLM manager")
+        .with_mock_provider(mock.clone());
+    (Arc::new(manager), mock)
+}
+
+#[tokio::test]
+async fn keyed_responses_take_priority_over_script() {
+    let (manager, mock) = manager_with(
+        MockProvider::new()
+            .on_prompt("summarize this", MockResponse::text("a summary"))
+            .then(MockResponse::text("scripted")),
+    )
+    .await;
+
+    let agent = AgentBuilder::new(SpacebotModel::make(&manager, "mock/test"))
+        .preamble("You are a test.")
+        .build();
+
+    assert_eq!(agent.prompt("summarize this").await.unwrap(), "a summary");
+    assert_eq!(agent.prompt("anything else").await.unwrap(), "scripted");
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].model, "test");
+    assert_eq!(requests[0].preamble.as_deref(), Some("You are a test."));
+    assert_eq!(
+        requests[0].prompt_hash,
+        MockProvider::prompt_hash("summarize this")
+    );
+}
+
+#[tokio::test]
+async fn exhausted_responses_fall_back_or_error() {
+    let (manager, _mock) = manager_with(MockProvider::new()).await;
+    let agent = AgentBuilder::new(SpacebotModel::make(&manager, "mock/test")).build();
+    let error = agent.prompt("unscripted").await.unwrap_err().to_string();
+    assert!(
+        error.contains(&MockProvider::prompt_hash("unscripted")),
+        "error should name the prompt hash: {error}"
+    );
+
+    let (manager, _mock) =
+        manager_with(MockProvider::new().with_fallback(MockResponse::text("default"))).await;
+    let agent = AgentBuilder::new(SpacebotModel::make(&manager, "mock/test")).build();
+    assert_eq!(agent.prompt("one").await.unwrap(), "default");
+    assert_eq!(agent.prompt("two").await.unwrap(), "default");
+}